@@ -4,7 +4,11 @@ _specsheet()
     prev=${COMP_WORDS[COMP_CWORD-1]}
 
     case "$prev" in
-        -'?'|--help|-v|--version)
+        -'?'|--help|-v|--version|--print-schema)
+            return
+            ;;
+
+        --check|--toml|--keep-last|--keep-days|--name|--skip-name|--shard|--interval|--iterations|--serve|--explain|--prom-push|--notify-url|--notify-slack|--report-url)
             return
             ;;
 
@@ -13,8 +17,25 @@ _specsheet()
             return
             ;;
 
+        -t|--tags|--skip-tags)
+            local word files=()
+            for word in "${COMP_WORDS[@]:1}"; do
+                [[ -f "$word" ]] && files+=( "$word" )
+            done
+
+            if [[ ${#files[@]} -gt 0 ]]; then
+                COMPREPLY=( $( compgen -W "$( "$1" --list-tags "${files[@]}" 2>/dev/null )" -- "$cur" ) )
+            fi
+            return
+            ;;
+
+        --completions)
+            COMPREPLY=( $( compgen -W 'bash fish zsh' -- "$cur" ) )
+            return
+            ;;
+
         -T|--types|--skip-types)
-            COMPREPLY=( $( compgen -W 'apt cmd defaults dns fs group hash homebrew homebrew_cask homebrew_tap http ping specsheet systemd tap tcp udp ufw user' -- "$cur" ) )
+            COMPREPLY=( $( compgen -W 'apt apt_source cmd defaults dns fs gem group hash homebrew homebrew_cask homebrew_tap http npm ping systemd tap tcp udp ufw user' -- "$cur" ) )
             return
             ;;
 
@@ -24,7 +45,22 @@ _specsheet()
             ;;
 
         --summaries)
-            COMPREPLY=( $( compgen -W 'hide show' -- $cur ) )
+            COMPREPLY=( $( compgen -W 'hide show expand' -- $cur ) )
+            return
+            ;;
+
+        --group-by)
+            COMPREPLY=( $( compgen -W 'type tag file' -- $cur ) )
+            return
+            ;;
+
+        --generate)
+            COMPREPLY=( $( compgen -W 'all apt homebrew systemd tcp user group' -- $cur ) )
+            return
+            ;;
+
+        --sort)
+            COMPREPLY=( $( compgen -W 'name duration status' -- $cur ) )
             return
             ;;
 
@@ -34,7 +70,17 @@ _specsheet()
             ;;
 
         -P|--print)
-            COMPREPLY=( $( compgen -W 'ansi docs json-lines tap' -- $cur ) )
+            COMPREPLY=( $( compgen -W 'ansi dots json-lines tap teamcity azure' -- $cur ) )
+            return
+            ;;
+
+        --input-format)
+            COMPREPLY=( $( compgen -W 'toml yaml json' -- $cur ) )
+            return
+            ;;
+
+        --target)
+            COMPREPLY=( $( compgen -W 'local docker://' -- $cur ) )
             return
             ;;
 
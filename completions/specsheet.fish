@@ -1,20 +1,73 @@
+# Tags get completed from the check documents given so far
+function __specsheet_tags
+    set -l files
+    for word in (commandline -opc)
+        test -f "$word"; and set -a files "$word"
+    end
+
+    if test (count $files) -gt 0
+        specsheet --list-tags $files 2>/dev/null
+    end
+end
+
 # Meta options
 complete -c specsheet -s 'v' -l 'version' -d "Show version of specsheet"
 complete -c specsheet -s '?' -l 'help'    -d "Show list of command-line options"
+complete -c specsheet        -l 'completions' -d "Print the shell completions for a shell" -x -a "bash fish zsh"
+complete -c specsheet        -l 'print-schema' -d "Print the JSON Schema of the result documents"
 
 # Running modes
 complete -c specsheet -s 'c' -l 'syntax-check'  -d "Don't run, just check the syntax of the input files"
 complete -c specsheet -s 'C' -l 'list-commands' -d "Don't run, just list the commands that would be executed"
+complete -c specsheet        -l 'dry-run'       -d "Don't run, just print the commands that would be run"
+complete -c specsheet        -l 'explain'       -d "Don't run, just explain the checks with a name or description" -x
+complete -c specsheet        -l 'generate'      -d "Don't run, just write starter checks for this machine" -x -a "
+    all\t'Every type'
+    apt\t'Packages installed with apt'
+    homebrew\t'Formulae installed with Homebrew'
+    systemd\t'Enabled systemd services'
+    tcp\t'Listening TCP ports'
+    user\t'Users'
+    group\t'Groups'
+"
+complete -c specsheet        -l 'diff'          -d "Don't run, just compare two result documents" -r
 complete -c specsheet -s 'l' -l 'list-checks'   -d "Don't run, just list the checks that would be run"
 complete -c specsheet        -l 'list-tags'     -d "Don't run, just list the tags defined in the documents"
 complete -c specsheet        -l 'random-order'  -d "Run the checks in a random order"
 complete -c specsheet        -l 'continual'     -d "Run the checks in continual mode, indefinitely"
+complete -c specsheet        -l 'interval'      -d "Amount of time to wait between batches of checks" -x
+complete -c specsheet        -l 'iterations'    -d "Number of batches of checks to run before stopping" -x
+complete -c specsheet        -l 'until-failure' -d "Stop running batches once a check fails"
+complete -c specsheet        -l 'until-success' -d "Stop running batches once every check passes"
+complete -c specsheet        -l 'detect-flaky'  -d "Report checks whose results flip between batches"
+complete -c specsheet        -l 'serve'         -d "Serve the latest results over HTTP" -x
+complete -c specsheet        -l 'watch'         -d "Run the checks again whenever the input files change"
 complete -c specsheet        -l 'delay'         -d "Amount of time to delay between checks" -x
 complete -c specsheet        -l 'directory'     -d "Directory to run the tests from" -x -a '(__fish_complete_directories)'
 complete -c specsheet -s 'j' -l 'threads'       -d "Number of threads to run in parallel" -x
+complete -c specsheet        -l 'command-timeout' -d "Amount of time commands may run before being killed" -x
+complete -c specsheet        -l 'timeout'       -d "Amount of time any process may run before being killed" -x
+complete -c specsheet        -l 'target'        -d "Where to run commands, such as docker://NAME" -x
+complete -c specsheet        -l 'sudo'          -d "Run commands that need root with sudo"
+complete -c specsheet        -l 'record'        -d "Record the commands that get run to a fixtures file" -r
+complete -c specsheet        -l 'replay'        -d "Replay commands from a fixtures file" -r
+complete -c specsheet        -l 'cache-dir'     -d "Directory to cache command results in between runs" -r
+complete -c specsheet        -l 'cache-ttl'     -d "Amount of time cached command results can be used for" -x
+complete -c specsheet        -l 'async'         -d "Run up to this many commands at once on the async executor" -x
 complete -c specsheet -s 'O' -l 'option'        -d "Set an option or override part of the environment" -x
 complete -c specsheet -s 'R' -l 'rewrite'       -d "Add a rule to rewrites values in input documents" -x
 complete -c specsheet -s 'z' -l 'analysis'      -d "Run analysis after running checks if there are errors"
+complete -c specsheet        -l 'allow-known-issues' -d "Don't fail because of checks with known issues"
+complete -c specsheet        -l 'exit-zero'     -d "Exit with status 0 even if checks fail"
+complete -c specsheet        -l 'fail-on-warn'  -d "Fail if checks with a severity of warn fail"
+complete -c specsheet        -l 'fail-on-skip'  -d "Fail if any checks get skipped"
+complete -c specsheet        -l 'check'         -d "Run a check given as a type and KEY=VAL pairs" -x
+complete -c specsheet        -l 'toml'          -d "Run the checks in a snippet of TOML" -x
+complete -c specsheet        -l 'input-format'  -d "Read the input documents as this format" -x -a "
+    toml\t'TOML check documents'
+    yaml\t'YAML check documents'
+    json\t'JSON check documents'
+"
 
 # Side process options
 complete -c specsheet -s 'x' -l 'exec'          -d "Process to run in the background during execution" -x
@@ -22,18 +75,26 @@ complete -c specsheet        -l 'exec-delay' -x -d "Wait an amount of time befor
 complete -c specsheet        -l 'exec-port'  -x -d "Wait until a port becomes open before running checks"
 complete -c specsheet        -l 'exec-file'  -x -d "Wait until a file exists before running checks"
 complete -c specsheet        -l 'exec-line'  -x -d "Wait until the process outputs a line before running checks"
+complete -c specsheet        -l 'exec-timeout' -x -d "Give up if the process has not started up in time"
+complete -c specsheet        -l 'exec-restart'    -d "Start the process again if it exits during execution"
 complete -c specsheet        -l 'exec-kill-signal' -x -d "Signal to send to the background process after finishing" -a "
     term\t'Send SIGTERM to stop the process'
     kill\t'Send SIGKILL to stop the process'
 "
 
 # Filtering options
-complete -c specsheet -s 't' -l 'tags'          -d "Comma-separated list of tags to run" -x
-complete -c specsheet        -l 'skip-tags'     -d "Comma-separated list of tags to skip" -x
-complete -c specsheet -s 'T' -l 'types'         -d "Comma-separated list of check types to run"  -x -a "apt cmd defaults dns fs group hash homebrew homebrew_cask homebrew_tap http ping specsheet systemd tap tcp udp ufw user"
-complete -c specsheet        -l 'skip-types'    -d "Comma-separated list of check types to skip" -x -a "apt cmd defaults dns fs group hash homebrew homebrew_cask homebrew_tap http ping specsheet systemd tap tcp udp ufw user"
+complete -c specsheet -s 't' -l 'tags'          -d "Comma-separated list of tags to run" -x -a "(__specsheet_tags)"
+complete -c specsheet        -l 'skip-tags'     -d "Comma-separated list of tags to skip" -x -a "(__specsheet_tags)"
+complete -c specsheet -s 'T' -l 'types'         -d "Comma-separated list of check types to run"  -x -a "apt apt_source cmd defaults dns fs gem group hash homebrew homebrew_cask homebrew_tap http npm ping systemd tap tcp udp ufw user"
+complete -c specsheet        -l 'skip-types'    -d "Comma-separated list of check types to skip" -x -a "apt apt_source cmd defaults dns fs gem group hash homebrew homebrew_cask homebrew_tap http npm ping systemd tap tcp udp ufw user"
+complete -c specsheet        -l 'name'          -d "Only run checks whose names match a regex" -x
+complete -c specsheet        -l 'skip-name'     -d "Skip checks whose names match a regex" -x
+complete -c specsheet        -l 'rerun-failed'  -d "Only run the checks that failed in a result document" -r
+complete -c specsheet        -l 'shard'         -d "Only run one part of the checks, such as 2/5" -x
 
 # Console output options
+complete -c specsheet -s 'q' -l 'quiet'         -d "Only show failures and the summaries"
+complete -c specsheet -s 'V' -l 'verbose'       -d "Show everything, including the commands each check ran"
 complete -c specsheet -s 's' -l 'successes'     -d "How to show successful check results" -x -a "
     hide\t'Do not show successful checks'
     show\t'Show successful checks'
@@ -47,13 +108,29 @@ complete -c specsheet -s 'f' -l 'failures'      -d "How to show failed check res
 complete -c specsheet        -l 'summaries'     -d "Whether to show the summary lines" -x -a "
     hide\t'Do not show summary lines'
     show\t'Show summary lines'
+    expand\t'Show summary lines and every failing result in the recap'
+"
+complete -c specsheet        -l 'timings'       -d "Show how long each check took, and the slowest checks"
+complete -c specsheet        -l 'show-commands' -d "Show the commands each check ran underneath it"
+complete -c specsheet        -l 'group-by'      -d "Print the checks in groups once they have all been run" -x -a "
+    type\t'Group checks by their type'
+    tag\t'Group checks by their tags'
+    file\t'Group checks by their input file'
+"
+complete -c specsheet        -l 'sort'          -d "Sort the checks within each group" -x -a "
+    name\t'Sort checks by name'
+    duration\t'Sort checks by how long they took'
+    status\t'Sort checks by result, failures first'
 "
 complete -c specsheet -s 'P' -l 'print'         -d "Specify the output format" -x -a "
     ansi\t'Coloured terminal output'
     dots\t'Print one dot per executed check'
     json-lines\t'Print a JSON object per executed check'
     tap\t'Output in Test Anything Protocol format'
+    teamcity\t'Output TeamCity service messages'
+    azure\t'Output Azure Pipelines logging commands'
 "
+complete -c specsheet        -l 'also-print'    -d "Also print the output to a file in another format" -r
 complete -c specsheet        -l 'color'      -x -d "When to colorise the output" -x -a "
     always\t'Always use colours'
     automatic\t'Use colours when printing to a terminal'
@@ -69,3 +146,14 @@ complete -c specsheet        -l 'colour'     -x -d "When to colourise the output
 complete -c specsheet        -l 'html-doc'      -d "Produce an output HTML document" -r
 complete -c specsheet        -l 'json-doc'      -d "Produce an output JSON document" -r
 complete -c specsheet        -l 'toml-doc'      -d "Produce an output TOML document" -r
+complete -c specsheet        -l 'junit-doc'     -d "Produce an output JUnit XML document" -r
+complete -c specsheet        -l 'markdown-doc'  -d "Produce an output Markdown document" -r
+complete -c specsheet        -l 'prom-doc'      -d "Produce an output Prometheus metrics file" -r
+complete -c specsheet        -l 'prom-push'     -d "Push Prometheus metrics to a Pushgateway" -x
+complete -c specsheet        -l 'csv-doc'       -d "Produce an output CSV document" -r
+complete -c specsheet        -l 'keep-last'     -d "Only keep this many timestamped documents" -x
+complete -c specsheet        -l 'keep-days'     -d "Only keep timestamped documents for this many days" -x
+complete -c specsheet        -l 'history-dir'   -d "Keep every run's results in a directory and report trends" -x -a '(__fish_complete_directories)'
+complete -c specsheet        -l 'notify-url'    -d "Post a JSON summary of the run to a webhook" -x
+complete -c specsheet        -l 'notify-slack'  -d "Post a summary of the run to a Slack webhook" -x
+complete -c specsheet        -l 'report-url'    -d "Link to the HTML report at this URL in notifications" -x
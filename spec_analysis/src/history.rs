@@ -0,0 +1,102 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+
+/// The **run history** records how each check turned out over a series of
+/// runs of the same checks, such as the batches in continual mode. It then
+/// uses this information to figure out which checks are flaky: the ones
+/// whose results flip between passing and failing without anything about
+/// the checks themselves changing.
+pub struct RunHistory<K, O> {
+    checks: BTreeMap<K, Outcomes<O>>,
+}
+
+struct Outcomes<O> {
+    runs: usize,
+    passes: usize,
+    flips: usize,
+    last_passed: bool,
+    outputs: Vec<(bool, O)>,
+}
+
+/// A **flaky check** is one whose result flipped at least once.
+pub struct FlakyCheck<'hist, K, O> {
+
+    /// The key the check’s results were recorded under.
+    pub key: &'hist K,
+
+    /// The number of runs the check’s results were recorded for.
+    pub runs: usize,
+
+    /// The number of those runs where the check passed.
+    pub passes: usize,
+
+    /// The number of times the check went from passing to failing, or from
+    /// failing to passing, between one run and the next.
+    pub flips: usize,
+
+    /// Each different output the check had, in the order they were first
+    /// seen, along with whether the check passed with it.
+    pub outputs: &'hist [(bool, O)],
+}
+
+impl<K: Ord, O: PartialEq> Default for RunHistory<K, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, O: PartialEq> RunHistory<K, O> {
+
+    /// Creates a new empty run history.
+    pub fn new() -> Self {
+        Self {
+            checks: BTreeMap::new(),
+        }
+    }
+
+    /// Records the result of one run of the check with the given key, and
+    /// the output it had. The output only gets kept if the check hasn’t had
+    /// the same output before.
+    pub fn record(&mut self, key: K, passed: bool, output: O) {
+        let outcomes = self.checks.entry(key).or_insert_with(|| Outcomes {
+            runs: 0,
+            passes: 0,
+            flips: 0,
+            last_passed: passed,
+            outputs: Vec::new(),
+        });
+
+        if outcomes.last_passed != passed {
+            outcomes.flips += 1;
+        }
+
+        outcomes.runs += 1;
+        outcomes.last_passed = passed;
+        if passed {
+            outcomes.passes += 1;
+        }
+
+        if ! outcomes.outputs.iter().any(|(p, o)| *p == passed && *o == output) {
+            outcomes.outputs.push((passed, output));
+        }
+    }
+
+    /// Goes through each of the recorded checks, and returns the ones whose
+    /// results flipped, the flakiest first.
+    pub fn flaky_checks(&self) -> Vec<FlakyCheck<'_, K, O>> {
+        let mut flaky_checks = self.checks.iter()
+            .filter(|(_, outcomes)| outcomes.flips > 0)
+            .map(|(key, outcomes)| FlakyCheck {
+                key,
+                runs: outcomes.runs,
+                passes: outcomes.passes,
+                flips: outcomes.flips,
+                outputs: &outcomes.outputs,
+            })
+            .collect::<Vec<_>>();
+
+        flaky_checks.sort_by_key(|flaky| Reverse(flaky.flips));
+        flaky_checks
+    }
+}
@@ -1,5 +1,6 @@
 //! Analysing the properties of completed checks to look for any similarities
-//! or correlations.
+//! or correlations, and their results over several runs to look for any
+//! that are flaky or getting worse.
 
 #![warn(deprecated_in_future)]
 #![warn(future_incompatible)]
@@ -19,8 +20,14 @@
 
 #![deny(unsafe_code)]
 
+mod history;
+pub use self::history::{RunHistory, FlakyCheck};
+
 mod property;
 pub use self::property::DataPoint;
 
 mod table;
-pub use self::table::AnalysisTable;
+pub use self::table::{AnalysisTable, Correlation};
+
+mod trend;
+pub use self::trend::{TrendTable, Trend};
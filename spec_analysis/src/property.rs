@@ -3,6 +3,7 @@ use std::path::Path;
 
 
 /// A property of a check, used during analysis.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
 pub enum DataPoint<'a> {
 
     /// The check has something to do with the given path.
@@ -13,14 +14,70 @@ pub enum DataPoint<'a> {
 
     /// The check has something to do with the group with the given name.
     InvolvesGroup(&'a str),
+
+    /// The check connects to the machine with the given hostname or IP
+    /// address.
+    InvolvesHost(&'a str),
+
+    /// The check looks up the given domain.
+    InvolvesDomain(&'a str),
+
+    /// The check has something to do with the given port number.
+    InvolvesPort(u16),
+
+    /// The check has something to do with the package with the given name,
+    /// from any package manager.
+    InvolvesPackage(&'a str),
+
+    /// The check has something to do with the service with the given name.
+    InvolvesService(&'a str),
+
+    /// The check runs the program with the given name.
+    InvolvesCommand(&'a str),
+}
+
+impl DataPoint<'_> {
+
+    /// The name of the kind of thing this property is about, such as
+    /// ‘path’ or ‘host’.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InvolvesPath(_)     => "path",
+            Self::InvolvesUser(_)     => "user",
+            Self::InvolvesGroup(_)    => "group",
+            Self::InvolvesHost(_)     => "host",
+            Self::InvolvesDomain(_)   => "domain",
+            Self::InvolvesPort(_)     => "port",
+            Self::InvolvesPackage(_)  => "package",
+            Self::InvolvesService(_)  => "service",
+            Self::InvolvesCommand(_)  => "command",
+        }
+    }
+
+    /// The thing this property is about, such as the path or the hostname,
+    /// as a string.
+    pub fn value(&self) -> String {
+        match self {
+            Self::InvolvesPath(path)  => path.display().to_string(),
+            Self::InvolvesPort(port)  => port.to_string(),
+            Self::InvolvesUser(value) | Self::InvolvesGroup(value) | Self::InvolvesHost(value) | Self::InvolvesDomain(value)
+            | Self::InvolvesPackage(value) | Self::InvolvesService(value) | Self::InvolvesCommand(value) => (*value).to_string(),
+        }
+    }
 }
 
 impl<'a> fmt::Display for DataPoint<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::InvolvesPath(path)    => write!(f, "involving path ‘{}’", path.display()),
-            Self::InvolvesUser(user)    => write!(f, "involving user ‘{}’", user),
-            Self::InvolvesGroup(group)  => write!(f, "involving group ‘{}’", group),
+            Self::InvolvesPath(path)        => write!(f, "involving path ‘{}’", path.display()),
+            Self::InvolvesUser(user)        => write!(f, "involving user ‘{}’", user),
+            Self::InvolvesGroup(group)      => write!(f, "involving group ‘{}’", group),
+            Self::InvolvesHost(host)        => write!(f, "involving host ‘{}’", host),
+            Self::InvolvesDomain(domain)    => write!(f, "involving domain ‘{}’", domain),
+            Self::InvolvesPort(port)        => write!(f, "involving port {}", port),
+            Self::InvolvesPackage(package)  => write!(f, "involving package ‘{}’", package),
+            Self::InvolvesService(service)  => write!(f, "involving service ‘{}’", service),
+            Self::InvolvesCommand(command)  => write!(f, "involving command ‘{}’", command),
         }
     }
 }
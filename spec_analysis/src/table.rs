@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
 
 use crate::property::DataPoint;
 
@@ -9,9 +8,7 @@ use crate::property::DataPoint;
 /// particular user, as well as whether the check succeeded or failed. It then
 /// uses this information to figure out correlations between the results.
 pub struct AnalysisTable<'set, C> {
-    paths: HashMap<PathBuf, MatchingChecks<'set, C>>,
-    users: HashMap<String, MatchingChecks<'set, C>>,
-    groups: HashMap<String, MatchingChecks<'set, C>>,
+    properties: BTreeMap<DataPoint<'set>, MatchingChecks<'set, C>>,
 }
 
 struct MatchingChecks<'set, C> {
@@ -43,9 +40,7 @@ impl<'set, C> AnalysisTable<'set, C> {
     /// Creates a new empty analysis table.
     pub fn new() -> Self {
         Self {
-            paths:  HashMap::new(),
-            users:  HashMap::new(),
-            groups: HashMap::new(),
+            properties: BTreeMap::new(),
         }
     }
 
@@ -54,37 +49,9 @@ impl<'set, C> AnalysisTable<'set, C> {
     /// passed or failed.
     pub fn add(&mut self, check: &'set C, properties: impl Iterator<Item=DataPoint<'set>>, passed: bool) {
         for prop in properties {
-            match prop {
-                DataPoint::InvolvesPath(path) => {
-                    if ! self.paths.contains_key(path) {
-                        self.paths.insert(path.to_owned(), MatchingChecks::new());
-                    }
-
-                    let entry = self.paths.get_mut(path).unwrap();
-                    if passed { entry.passes.push(check); }
-                         else { entry.fails.push(check); }
-                }
-
-                DataPoint::InvolvesUser(user) => {
-                    if ! self.users.contains_key(user) {
-                        self.users.insert(user.to_owned(), MatchingChecks::new());
-                    }
-
-                    let entry = self.users.get_mut(user).unwrap();
-                    if passed { entry.passes.push(check); }
-                         else { entry.fails.push(check); }
-                }
-
-                DataPoint::InvolvesGroup(group) => {
-                    if ! self.groups.contains_key(group) {
-                        self.groups.insert(group.to_owned(), MatchingChecks::new());
-                    }
-
-                    let entry = self.groups.get_mut(group).unwrap();
-                    if passed { entry.passes.push(check); }
-                         else { entry.fails.push(check); }
-                }
-            }
+            let entry = self.properties.entry(prop).or_insert_with(MatchingChecks::new);
+            if passed { entry.passes.push(check); }
+                 else { entry.fails.push(check); }
         }
     }
 
@@ -93,32 +60,13 @@ impl<'set, C> AnalysisTable<'set, C> {
     pub fn resolve_correlations<'tab>(&'tab self) -> Vec<Correlation<'tab>> {
         let mut correlations = Vec::new();
 
-        // Check for a path that has been involved entirely with failed checks.
-        for (path, path_checks) in &self.paths {
-            if path_checks.passes.is_empty() && ! path_checks.fails.is_empty() {
-                correlations.push(Correlation {
-                    property: DataPoint::InvolvesPath(path),
-                    count: path_checks.fails.len(),
-                });
-            }
-        }
-
-        // Check for a user that has been involved entirely with failed checks.
-        for (user, user_checks) in &self.users {
-            if user_checks.passes.is_empty() && ! user_checks.fails.is_empty() {
-                correlations.push(Correlation {
-                    property: DataPoint::InvolvesUser(user),
-                    count: user_checks.fails.len(),
-                });
-            }
-        }
-
-        // Check for a user that has been involved entirely with failed checks.
-        for (group, group_checks) in &self.groups {
-            if group_checks.passes.is_empty() && ! group_checks.fails.is_empty() {
+        // Check for a property that has been involved entirely with failed
+        // checks, such as a path, a user, or a host.
+        for (property, checks) in &self.properties {
+            if checks.passes.is_empty() && ! checks.fails.is_empty() {
                 correlations.push(Correlation {
-                    property: DataPoint::InvolvesGroup(group),
-                    count: group_checks.fails.len(),
+                    property: *property,
+                    count: checks.fails.len(),
                 });
             }
         }
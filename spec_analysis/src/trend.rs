@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+
+/// The **trend table** collects the results of each check over a series of
+/// runs, in the order they were run, such as every run kept in a history
+/// directory. It then uses this information to figure out how a check has
+/// been changing over time: when it started failing, how often it passes,
+/// and whether it has got any slower.
+pub struct TrendTable<K, T> {
+    checks: BTreeMap<K, Vec<Sample<T>>>,
+}
+
+struct Sample<T> {
+    time: T,
+    passed: bool,
+    duration: Duration,
+}
+
+/// A **trend** describes how one check has been turning out recently.
+pub struct Trend<'tab, T> {
+
+    /// If the check failed in its latest run, but passed at some point
+    /// before that, the time of the first run where it failed since then.
+    pub failing_since: Option<&'tab T>,
+
+    /// The number of recent runs the check was in, including the latest.
+    pub recent_runs: usize,
+
+    /// The number of those runs where the check passed.
+    pub recent_passes: usize,
+
+    /// How long the check took to run on average in the recent runs before
+    /// the latest, if there were any.
+    pub average_duration: Option<Duration>,
+
+    /// How long the check took to run in the latest run.
+    pub latest_duration: Duration,
+}
+
+impl<K: Ord, T> Default for TrendTable<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, T> TrendTable<K, T> {
+
+    /// Creates a new empty trend table.
+    pub fn new() -> Self {
+        Self {
+            checks: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the result of one run of the check with the given key. Each
+    /// check’s runs have to be added in the order they happened.
+    pub fn add(&mut self, check: K, time: T, passed: bool, duration: Duration) {
+        self.checks.entry(check).or_default().push(Sample { time, passed, duration });
+    }
+
+    /// Works out the trend of the check with the given key, looking at up to
+    /// the given number of its most recent runs for its pass rate and its
+    /// average duration. Returns nothing if the check was never run.
+    pub fn trend(&self, check: &K, window: usize) -> Option<Trend<'_, T>> {
+        let samples = self.checks.get(check)?;
+        let (latest, earlier) = samples.split_last()?;
+
+        // Look back through the runs before the latest for the last one
+        // where the check passed; the run after it is where it started
+        // failing.
+        let failing_since = if latest.passed {
+            None
+        }
+        else {
+            earlier.iter().rposition(|sample| sample.passed)
+                .map(|index| &samples[index + 1].time)
+        };
+
+        let recent = &samples[samples.len().saturating_sub(window) ..];
+        let recent_passes = recent.iter().filter(|sample| sample.passed).count();
+
+        let previous = &recent[.. recent.len() - 1];
+        let average_duration = if previous.is_empty() {
+            None
+        }
+        else {
+            let total = previous.iter().map(|sample| sample.duration).sum::<Duration>();
+            Some(total / u32::try_from(previous.len()).unwrap_or(u32::MAX))
+        };
+
+        Some(Trend {
+            failing_since,
+            recent_runs: recent.len(),
+            recent_passes,
+            average_duration,
+            latest_duration: latest.duration,
+        })
+    }
+}
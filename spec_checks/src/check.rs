@@ -1,5 +1,5 @@
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use spec_exec::{Executor, ExecError};
 
@@ -24,7 +24,7 @@ pub enum CheckResult<PASS, FAIL> {
     Failed(FAIL),
 
     /// A command didn’t execute as expected. `:S`
-    CommandError(Rc<ExecError>),
+    CommandError(Arc<ExecError>),
 }
 
 impl<PASS, FAIL> CheckResult<PASS, FAIL> {
@@ -7,6 +7,10 @@
 //! shell = "consul version"
 //! status = 0
 //! stdout = { string = "Consul v1.5" }
+//! output = { max_lines = 1 }
+//! timeout = "10s"
+//! directory = "/opt/consul"
+//! pty = true
 //! ```
 //!
 //! # Commands
@@ -15,13 +19,18 @@
 
 
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use log::*;
 
-use spec_exec::{Executor, ExitReason};
+use spec_analysis::DataPoint;
+use spec_exec::{Executor, ExitReason, Input};
 
 use crate::{Check, RunCheck, CheckResult, PassResult, FailResult};
+use crate::common::parse_duration;
 use crate::contents::{self, ContentsMatcher};
+use crate::numbers::NumberMatcher;
 use crate::read::{TomlValue, ValueExtras, ReadError};
 
 use super::{Invocation, ShellCommand, Environment, RunShell};
@@ -35,6 +44,7 @@ pub struct CommandCheck {
     status: ExpectedStatus,
     stdout: Option<ContentsMatcher>,
     stderr: Option<ContentsMatcher>,
+    output: Option<ContentsMatcher>,
 }
 
 /// The return code we expect from the process.
@@ -47,6 +57,17 @@ enum ExpectedStatus {
 
     /// The process must exit with the given code.
     Specific(u8),
+
+    /// The process must exit with one of the given codes.
+    OneOf(Vec<u8>),
+
+    /// The process must not exit with any of the given codes. Processes
+    /// that were killed by a signal count as not matching any of them.
+    NoneOf(Vec<u8>),
+
+    /// The process must exit with a code that satisfies the given
+    /// comparisons. Processes that were killed by a signal never do.
+    Matching(NumberMatcher),
 }
 
 
@@ -58,80 +79,99 @@ impl Check for CommandCheck {
 
 impl fmt::Display for CommandCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { invocation, status, stdout, stderr } = &self;
+        let Self { invocation, status, stdout, stderr, output } = &self;
 
         write!(f, "Command ‘{}’ ", invocation)?;
 
-        match (stdout, stderr, status) {
-            (None, None, ExpectedStatus::Any) => {
-                write!(f, "executes")
+        if let Some(directory) = &invocation.directory {
+            write!(f, "in ‘{}’ ", directory.display())?;
+        }
+
+        if invocation.pty == Some(true) {
+            write!(f, "in a terminal ")?;
+        }
+
+        match (stdout, stderr, output) {
+            (None, None, None) => {
+                write!(f, "{}", status)
             }
-            (None, None, ExpectedStatus::Specific(ec)) => {
-                write!(f, "returns ‘{}’", ec)
+            (Some(ContentsMatcher::ShouldBeEmpty), Some(ContentsMatcher::ShouldBeEmpty), None) => {
+                write!(f, "{} with empty stdout and stderr", status)
             }
-            (Some(ContentsMatcher::ShouldBeEmpty), Some(ContentsMatcher::ShouldBeEmpty), _) => {
-                if let ExpectedStatus::Specific(ec) = status {
-                    write!(f, "returns ‘{}’ with", ec)?;
-                }
-                else {
-                    write!(f, "executes with")?;
-                }
-
-                write!(f, " empty stdout and stderr")?;
-                Ok(())
+            (Some(ContentsMatcher::ShouldBeNonEmpty), Some(ContentsMatcher::ShouldBeNonEmpty), None) => {
+                write!(f, "{} with non-empty stdout and stderr", status)
             }
-            (Some(ContentsMatcher::ShouldBeNonEmpty), Some(ContentsMatcher::ShouldBeNonEmpty), _) => {
-                if let ExpectedStatus::Specific(ec) = status {
-                    write!(f, "returns ‘{}’ with", ec)?;
-                }
-                else {
-                    write!(f, "executes with")?;
+            _ => {
+                write!(f, "{} with", status)?;
+
+                let mut first = true;
+                for (noun, matcher) in &[ ("stdout", stdout), ("stderr", stderr), ("output", output) ] {
+                    if let Some(contents_matcher) = matcher {
+                        if ! first {
+                            write!(f, " and")?;
+                        }
+
+                        contents_matcher.describe(f, noun)?;
+                        first = false;
+                    }
                 }
 
-                write!(f, " non-empty stdout and stderr")?;
                 Ok(())
             }
-            _ => {
-                if let ExpectedStatus::Specific(ec) = status {
-                    write!(f, "returns ‘{}’ with", ec)?;
-                }
-                else {
-                    write!(f, "executes with")?;
-                }
-
-                if let Some(contents_matcher) = stdout {
-                    contents_matcher.describe(f, "stdout")?;
-                }
-
-                if stdout.is_some() && stderr.is_some() {
-                    write!(f, " and")?;
-                }
-
-                if let Some(contents_matcher) = stderr {
-                    contents_matcher.describe(f, "stderr")?;
-                }
+        }
+    }
+}
 
-                Ok(())
+impl fmt::Display for ExpectedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => {
+                write!(f, "executes")
+            }
+            Self::Specific(ec) => {
+                write!(f, "returns ‘{}’", ec)
+            }
+            Self::OneOf(ecs) => {
+                write!(f, "returns {}", quoted_list(ecs))
+            }
+            Self::NoneOf(ecs) => {
+                write!(f, "does not return {}", quoted_list(ecs))
+            }
+            Self::Matching(matcher) => {
+                write!(f, "returns {}", matcher)
             }
         }
     }
 }
 
+/// Joins a list of exit statuses with ‘or’, quoting each one.
+fn quoted_list(statuses: &[u8]) -> String {
+    statuses.iter()
+            .map(|ec| format!("‘{}’", ec))
+            .collect::<Vec<_>>()
+            .join(" or ")
+}
+
 
 // ---- reading from TOML ----
 
 impl CommandCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["shell", "environment", "status", "stdout", "stderr"])?;
+        table.ensure_only_keys(&["shell", "environment", "directory", "stdin", "status", "stdout", "stderr", "output", "timeout", "pty"])?;
 
         let shell = ShellCommand::read(table)?;
         let environment = Environment::read(table)?;
-        let invocation = Invocation { shell, environment };
+        let directory = read_directory(table)?;
+        let timeout = table.get("timeout").map(read_timeout).transpose()?;
+        let stdin = table.get("stdin").map(read_stdin).transpose()?;
+        let pty = table.get("pty").map(|e| e.boolean_or_error("pty")).transpose()?;
+        let invocation = Invocation { shell, environment, directory, timeout, stdin, pty };
 
         let status = ExpectedStatus::read(table)?;
         let stdout = table.get("stdout").map(|e| ContentsMatcher::read("stdout", e)).transpose()?;
         let stderr = table.get("stderr").map(|e| ContentsMatcher::read("stderr", e)).transpose()?;
-        Ok(Self { invocation, status, stdout, stderr })
+        let output = table.get("output").map(|e| ContentsMatcher::read("output", e)).transpose()?;
+        Ok(Self { invocation, status, stdout, stderr, output })
     }
 }
 
@@ -161,26 +201,156 @@ impl Environment {
     }
 }
 
+/// Reads the directory to run the command from, if it has been given.
+pub(crate) fn read_directory(table: &TomlValue) -> Result<Option<PathBuf>, ReadError> {
+    if let Some(directory_value) = table.get("directory") {
+        let directory = directory_value.string_or_error("directory")?;
+        if directory.is_empty() {
+            return Err(ReadError::invalid("directory", directory_value.clone(), "it must not be empty"));
+        }
+
+        Ok(Some(PathBuf::from(directory)))
+    }
+    else {
+        Ok(None)
+    }
+}
+
+/// Reads the amount of time a command is allowed to run for, which is either
+/// a duration string such as `"500ms"`, or a whole number of seconds.
+fn read_timeout(value: &TomlValue) -> Result<Duration, ReadError> {
+    use std::convert::TryFrom;
+
+    let timeout = if value.is_integer() {
+        let number = value.number_or_error("timeout")?;
+        u64::try_from(number).ok().map(Duration::from_secs)
+    }
+    else {
+        parse_duration(&value.string_or_error("timeout")?)
+    };
+
+    match timeout {
+        Some(d) if d > Duration::from_secs(0) => {
+            Ok(d)
+        }
+        Some(_) => {
+            Err(ReadError::invalid("timeout", value.clone(), "it must not be zero"))
+        }
+        None => {
+            Err(ReadError::invalid("timeout", value.clone(), "it must be a duration such as ‘10s’"))
+        }
+    }
+}
+
+/// Reads the input to feed to the command, which is either a string to write
+/// to it, or a table containing the path to a file to read from.
+fn read_stdin(value: &TomlValue) -> Result<Input, ReadError> {
+    if value.is_str() {
+        let string = value.string_or_error("stdin")?;
+        return Ok(Input::Bytes(string.into_bytes()));
+    }
+
+    value.ensure_table("stdin")?;
+    value.ensure_only_keys(&["file"])?;
+
+    let file_value = value.get_or_read_error("file")?;
+    let path = file_value.string_or_error("file")?;
+    if path.is_empty() {
+        Err(ReadError::invalid("file", file_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(Input::File(PathBuf::from(path)))
+    }
+}
+
 impl ExpectedStatus {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        use std::convert::TryFrom;
-
         if let Some(status_value) = table.get("status") {
-            let number = status_value.number_or_error("status")?;
-            match u8::try_from(number) {
-                Ok(status) => {
-                    Ok(Self::Specific(status))
+            if status_value.is_array() {
+                let statuses = read_status_list("status", status_value)?;
+                Ok(Self::OneOf(statuses))
+            }
+            else if NumberMatcher::is_comparison_table(status_value) {
+                Ok(Self::Matching(NumberMatcher::read("status", status_value)?))
+            }
+            else if status_value.is_table() {
+                status_value.ensure_only_keys(&["not"])?;
+
+                let not_value = status_value.get_or_read_error("not")?;
+                if not_value.is_array() {
+                    Ok(Self::NoneOf(read_status_list("not", not_value)?))
                 }
-                Err(e) => {
-                    warn!("Number out of range: {}", e);
-                    Err(ReadError::invalid("status", status_value.clone(), "it must be between 0 and 255"))
+                else {
+                    Ok(Self::NoneOf(vec![ read_status("not", not_value)? ]))
                 }
             }
+            else {
+                Ok(Self::Specific(read_status("status", status_value)?))
+            }
         }
         else {
             Ok(Self::Any)
         }
     }
+
+    /// Whether the process’s exit reason is one that we expect, or `None`
+    /// if we don’t care about it.
+    fn matches(&self, exit_reason: ExitReason) -> Option<bool> {
+        match self {
+            Self::Any           => None,
+            Self::Specific(ec)  => Some(exit_reason.is(*ec)),
+            Self::OneOf(ecs)    => Some(ecs.iter().any(|ec| exit_reason.is(*ec))),
+            Self::NoneOf(ecs)   => Some(! ecs.iter().any(|ec| exit_reason.is(*ec))),
+            Self::Matching(m)   => Some(match exit_reason {
+                ExitReason::Status(ec)   => m.matches(ec.into()),
+                ExitReason::Overridden   => true,
+                _                        => false,
+            }),
+        }
+    }
+}
+
+/// Reads a single exit status, which must fit in a byte.
+fn read_status(parameter_name: &'static str, value: &TomlValue) -> Result<u8, ReadError> {
+    use std::convert::TryFrom;
+
+    let number = value.number_or_error(parameter_name)?;
+    match u8::try_from(number) {
+        Ok(status) => {
+            Ok(status)
+        }
+        Err(e) => {
+            warn!("Number out of range: {}", e);
+            Err(ReadError::invalid(parameter_name, value.clone(), "it must be between 0 and 255"))
+        }
+    }
+}
+
+/// Reads a non-empty list of exit statuses.
+fn read_status_list(parameter_name: &'static str, value: &TomlValue) -> Result<Vec<u8>, ReadError> {
+    let array = value.as_array().unwrap();
+    if array.is_empty() {
+        return Err(ReadError::invalid(parameter_name, value.clone(), "it must not be empty"));
+    }
+
+    array.iter()
+         .map(|e| read_status(parameter_name, e))
+         .collect()
+}
+
+
+// ---- analysis properties ----
+
+impl CommandCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        if let Some(program) = self.invocation.shell.program() {
+            points.push(DataPoint::InvolvesCommand(program));
+        }
+
+        points
+    }
 }
 
 
@@ -199,54 +369,60 @@ impl<S: RunShell> RunCheck<S> for CommandCheck {
             Ok(c)  => c,
             Err(e) => {
                 warn!("Error running command: {}", e);
-                return vec![ CheckResult::Failed(Fail::No) ];
+                return vec![ CheckResult::CommandError(e) ];
             }
         };
 
+        // A command that had to be killed won’t have finished writing its
+        // output, so there’s no point checking anything else.
+        if ran_command.exit_reason == ExitReason::TimedOut {
+            return vec![ CheckResult::Failed(Fail::TimedOut) ];
+        }
+
         let mut results = vec![ CheckResult::Passed(Pass::CommandWasExecuted) ];
 
         // Status check
-        if let ExpectedStatus::Specific(num) = self.status {
-            if ran_command.exit_reason.is(num) {
+        match self.status.matches(ran_command.exit_reason) {
+            Some(true) => {
                 results.push(CheckResult::Passed(Pass::StatusCodeMatches));
             }
-            else {
-                results.push(CheckResult::Failed(Fail::ExitReasonMismatch(ran_command.exit_reason)));
+            Some(false) => {
+                results.push(CheckResult::Failed(Fail::ExitReasonMismatch(ran_command.exit_reason, ran_command.output_text())));
             }
+            None => {/* no status to check */}
         }
 
         if let Some(stdout_matcher) = &self.stdout {
-            match stdout_matcher.check(&ran_command.stdout_bytes()) {
-                CheckResult::Passed(pass) => {
-                    results.push(CheckResult::Passed(Pass::ContentsPass("stdout", pass)));
-                }
-                CheckResult::Failed(fail) => {
-                    results.push(CheckResult::Failed(Fail::ContentsFail("stdout", fail)));
-                }
-                CheckResult::CommandError(_) => {
-                    unreachable!();
-                }
-            }
+            results.push(contents_result("stdout", stdout_matcher, &ran_command.stdout_bytes(), executor.working_directory()));
         }
 
         if let Some(stderr_matcher) = &self.stderr {
-            match stderr_matcher.check(&ran_command.stderr_bytes()) {
-                CheckResult::Passed(pass) => {
-                     results.push(CheckResult::Passed(Pass::ContentsPass("stderr", pass)));
-                }
-                CheckResult::Failed(fail) => {
-                    results.push(CheckResult::Failed(Fail::ContentsFail("stderr", fail)));
-                }
-                CheckResult::CommandError(_) => {
-                    unreachable!();
-                }
-            }
+            results.push(contents_result("stderr", stderr_matcher, &ran_command.stderr_bytes(), executor.working_directory()));
+        }
+
+        if let Some(output_matcher) = &self.output {
+            results.push(contents_result("output", output_matcher, &ran_command.output_bytes(), executor.working_directory()));
         }
 
         results
     }
 }
 
+/// Runs a contents matcher against one of the process’s output streams.
+fn contents_result(stream: &'static str, matcher: &ContentsMatcher, contents: &[u8], directory: Option<&Path>) -> CheckResult<Pass, Fail> {
+    match matcher.check(contents, directory) {
+        CheckResult::Passed(pass) => {
+            CheckResult::Passed(Pass::ContentsPass(stream, pass))
+        }
+        CheckResult::Failed(fail) => {
+            CheckResult::Failed(Fail::ContentsFail(stream, fail))
+        }
+        CheckResult::CommandError(_) => {
+            unreachable!();
+        }
+    }
+}
+
 /// The successful result of a command check.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Pass {
@@ -264,12 +440,16 @@ pub enum Pass {
 #[derive(Debug)]
 pub enum Fail {
 
-    No,
-
     /// The process’s exit reason was different from the one we expected.
-    ExitReasonMismatch(ExitReason),
+    /// Its output, with both streams interleaved, is kept to help explain
+    /// why.
+    ExitReasonMismatch(ExitReason, String),
+
+    /// The process was still running once its timeout had elapsed, so it
+    /// was killed.
+    TimedOut,
 
-    /// One of the two contents matchers did not match.
+    /// One of the contents matchers did not match.
     ContentsFail(&'static str, contents::Fail),
 }
 
@@ -279,8 +459,9 @@ impl PassResult for Pass {
 impl FailResult for Fail {
     fn command_output(&self) -> Option<(String, &String)> {
         match self {
-            Self::ContentsFail(_, fail)  => fail.command_output("Command output:"),
-            _                            => None,
+            Self::ContentsFail(_, fail)                              => fail.command_output("Command output:"),
+            Self::ExitReasonMismatch(_, output) if ! output.is_empty()  => Some(("Command output:".into(), output)),
+            _                                                        => None,
         }
     }
 
@@ -314,13 +495,13 @@ impl fmt::Display for Pass {
 impl fmt::Display for Fail {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::No => {
-                write!(f, "No")
-            }
-            Self::ExitReasonMismatch(ExitReason::Status(num)) => {
+            Self::ExitReasonMismatch(ExitReason::Status(num), _) => {
                 write!(f, "command exited with status code ‘{}’", num)
             }
-            Self::ExitReasonMismatch(e) => {
+            Self::TimedOut => {
+                write!(f, "command timed out and was killed")
+            }
+            Self::ExitReasonMismatch(e, _) => {
                 write!(f, "command exited with reason ‘{:?}’", e)  // todo: englishify these variants
             }
             Self::ContentsFail(stream, contents_fail) => {
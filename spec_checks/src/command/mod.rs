@@ -3,15 +3,24 @@ pub mod tap;
 
 use std::collections::BTreeMap;
 use std::fmt;
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use spec_exec::{Executor, RanCommand, ExecError};
+use spec_exec::{Executor, RanCommand, ExecError, Input};
 
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
 pub struct Invocation {
     pub shell: ShellCommand,
     pub environment: Environment,
+    pub directory: Option<PathBuf>,
+    pub timeout: Option<Duration>,
+    pub stdin: Option<Input>,
+
+    /// Whether to run the process under a pseudo-terminal, or `None` to
+    /// leave it up to the global option.
+    pub pty: Option<bool>,
 }
 
 impl fmt::Display for Invocation {
@@ -28,6 +37,15 @@ impl fmt::Display for Invocation {
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
 pub struct ShellCommand(pub String);
 
+impl ShellCommand {
+
+    /// The name of the program the command runs first, skipping over any
+    /// environment variables it sets beforehand.
+    pub fn program(&self) -> Option<&str> {
+        self.0.split_whitespace().find(|word| ! word.contains('='))
+    }
+}
+
 /// Any environment variables to set when running the process.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default, Clone)]
 pub struct Environment(pub BTreeMap<String, String>);
@@ -40,5 +58,5 @@ pub trait RunShell {
 
     /// Runs a short shell command with the given environment variables,
     /// and returns its output.
-    fn run_command(&self, executor: &mut Executor, invocation: &Invocation) -> Result<Rc<RanCommand>, Rc<ExecError>>;
+    fn run_command(&self, executor: &mut Executor, invocation: &Invocation) -> Result<Arc<RanCommand>, Arc<ExecError>>;
 }
@@ -7,12 +7,18 @@
 //! [[tap]]
 //! shell = './my_tests'
 //! ```
+//!
+//! Both plain TAP and TAP version 13 are understood: YAML diagnostic blocks
+//! get shown alongside the test that failed, and indented subtests have
+//! their results reported individually.
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
+
+use spec_analysis::DataPoint;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
@@ -22,6 +28,7 @@ use crate::{Check, RunCheck, CheckResult, PassResult, FailResult};
 use crate::read::{TomlValue, ValueExtras, ReadError};
 
 use super::{Invocation, ShellCommand, Environment, RunShell};
+use super::cmd::read_directory;
 
 
 /// The **tap check** runs a shell command and interprets its output as TAP.
@@ -37,7 +44,13 @@ impl fmt::Display for TapCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Self { invocation } = &self;
 
-        write!(f, "TAP tests for command ‘{}’", invocation)
+        write!(f, "TAP tests for command ‘{}’", invocation)?;
+
+        if let Some(directory) = &invocation.directory {
+            write!(f, " in ‘{}’", directory.display())?;
+        }
+
+        Ok(())
     }
 }
 
@@ -50,16 +63,32 @@ impl Check for TapCheck {
 
 impl TapCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["shell", "environment"])?;
+        table.ensure_only_keys(&["shell", "environment", "directory"])?;
 
         let shell = ShellCommand::read(table)?;
         let environment = Environment::read(table)?;
-        let invocation = Invocation { shell, environment };
+        let directory = read_directory(table)?;
+        let invocation = Invocation { shell, environment, directory, timeout: None, stdin: None, pty: None };
         Ok(Self { invocation })
     }
 }
 
 
+// ---- analysis properties ----
+
+impl TapCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        if let Some(program) = self.invocation.shell.program() {
+            points.push(DataPoint::InvolvesCommand(program));
+        }
+
+        points
+    }
+}
+
+
 // ---- running the check ----
 
 impl<S: RunShell> RunCheck<S> for TapCheck {
@@ -81,85 +110,194 @@ impl<S: RunShell> RunCheck<S> for TapCheck {
             }
         };
 
-        let mut results = Vec::new();
-        let mut expected_count: Option<TestNumber> = None;
-        let mut test_count: TestNumber = 0;
+        let lines = ran_command.stdout_lines();
+        let lines = lines.iter().map(|line| &**line).collect::<Vec<_>>();
 
-        for line in ran_command.stdout_lines() {
-            if let Some(caps) = COUNT_LINE.captures(&line) {
-                if results.is_empty() {
-                    expected_count = Some(caps[2].parse().unwrap());
-                }
-                else {
-                    panic!("The count line came late! What gives?");
-                }
-            }
-            else if let Some(caps) = RESULT_LINE.captures(&line) {
-                test_count += 1;
+        let mut results = Vec::new();
+        parse_tap(&lines, &TestPath::default(), &mut results);
+        results
+    }
+}
 
-                let number = caps[2].parse().unwrap();
-                let description = caps.get(3).map(|e| String::from(e.as_str()));
+/// Parses the lines of a block of TAP output, pushing a result for each test
+/// onto the results vector. This is called once for the top-level tests, and
+/// again for each block of subtests, with the lines having had their
+/// indentation removed and the path of the test they are a part of.
+fn parse_tap(lines: &[&str], parent: &TestPath, results: &mut Vec<CheckResult<Pass, Fail>>) {
+    let mut expected_count: Option<TestNumber> = None;
+    let mut test_count: TestNumber = 0;
+    let mut subtest_lines = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+
+        // Subtests are indented by four spaces, and come before the line
+        // for the test that they are a part of.
+        if let Some(subtest_line) = line.strip_prefix("    ") {
+            subtest_lines.push(subtest_line);
+            continue;
+        }
 
-                if caps.get(1).is_some() {
-                    results.push(CheckResult::Failed(Fail::TestFailed(number, description)));
-                }
-                else {
-                    results.push(CheckResult::Passed(Pass::TestPassed(number, description)));
-                }
+        // YAML diagnostic blocks are indented by two spaces, and come after
+        // the line for the test they describe. We don’t parse the YAML, but
+        // show it to the user if the test failed.
+        if line.trim_end() == "  ---" {
+            let mut diagnostics = String::new();
+            while index < lines.len() && lines[index].trim_end() != "  ..." {
+                diagnostics.push_str(lines[index].strip_prefix("  ").unwrap_or(lines[index]));
+                diagnostics.push('\n');
+                index += 1;
             }
-            else {
-                results.push(CheckResult::Failed(Fail::UnparseableLine(line)));
+            index += 1;
+
+            if let Some(CheckResult::Failed(Fail::TestFailed(_, _, diags @ None))) = results.last_mut() {
+                *diags = Some(diagnostics);
             }
+
+            continue;
         }
 
-        if let Some(expected) = expected_count {
-            if test_count == expected {
-                results.push(CheckResult::Passed(Pass::CorrectNumber(expected)));
+        if let Some(caps) = RESULT_LINE.captures(line) {
+            test_count += 1;
+
+            let number = caps.get(2).map_or(test_count, |n| n.as_str().parse().unwrap());
+            let description = caps.get(3).map(|e| e.as_str().trim()).filter(|e| ! e.is_empty()).map(String::from);
+            let path = parent.child(number);
+
+            if ! subtest_lines.is_empty() {
+                parse_tap(&subtest_lines, &path, results);
+                subtest_lines.clear();
             }
-            else {
-                results.push(CheckResult::Failed(Fail::IncorrectNumber { expected, got: test_count }));
+
+            let directive = caps.get(4).map(|e| e.as_str().to_ascii_uppercase());
+            let reason = caps.get(5).map(|e| e.as_str().trim()).filter(|e| ! e.is_empty()).map(String::from);
+
+            match (caps.get(1).is_some(), directive.as_deref()) {
+                (_, Some("SKIP")) => {
+                    results.push(CheckResult::Passed(Pass::TestSkipped(path, description.or(reason))));
+                }
+                (true, Some("TODO")) => {
+                    results.push(CheckResult::Passed(Pass::TodoTestFailed(path, description)));
+                }
+                (true, _) => {
+                    results.push(CheckResult::Failed(Fail::TestFailed(path, description, None)));
+                }
+                (false, _) => {
+                    results.push(CheckResult::Passed(Pass::TestPassed(path, description)));
+                }
             }
         }
+        else if let Some(caps) = COUNT_LINE.captures(line) {
+            expected_count = Some(caps[2].parse().unwrap());
+        }
+        else if let Some(reason) = line.strip_prefix("Bail out!") {
+            let reason = Some(reason.trim()).filter(|e| ! e.is_empty()).map(String::from);
+            results.push(CheckResult::Failed(Fail::BailedOut(reason)));
+            return;
+        }
+        else if ! is_ignorable_line(line) {
+            results.push(CheckResult::Failed(Fail::UnparseableLine(line.into())));
+        }
+    }
 
-        results
+    // Subtests without a line for their parent test at the end still count.
+    if ! subtest_lines.is_empty() {
+        parse_tap(&subtest_lines, &parent.child(test_count + 1), results);
+    }
+
+    if let Some(expected) = expected_count {
+        if test_count == expected {
+            results.push(CheckResult::Passed(Pass::CorrectNumber(parent.clone(), expected)));
+        }
+        else {
+            results.push(CheckResult::Failed(Fail::IncorrectNumber { subtest: parent.clone(), expected, got: test_count }));
+        }
     }
 }
 
+/// Whether this line is one that doesn’t affect the results: a blank line,
+/// a comment, the version line, or a pragma.
+fn is_ignorable_line(line: &str) -> bool {
+    line.trim().is_empty() || line.starts_with('#') || line.starts_with("TAP version ") || line.starts_with("pragma ")
+}
+
 /// Regular expression for the count line of a TAP file.
 static COUNT_LINE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r##"(?x) ^
         (\d+) \.\. (\d+)
+        (?: \s* \# .* )?
     $ "##).unwrap()
 });
 
-/// Regular expression for the count line of a TAP file.
+/// Regular expression for a result line of a TAP file, with an optional
+/// test number, description, and comment, which may be a SKIP or TODO
+/// directive.
 static RESULT_LINE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r##"(?x) ^
         (?: (not) \s+)?
-        ok \s+
-        (\d+)
+        ok \b
+        (?: \s+ (\d+) )?
+        \s* -? \s*
+        ( (?: [^\\\#] | \\. )*? )
+        \s*
         (?:
-          \s*
-          -
-          \s*
-          (.+)
+          \# \s*
+          (?: ( (?i: skip | todo ) ) \S* )?
+          (.*)
         )?
     $ "##).unwrap()
 });
 
-
 /// A TAP test’s number.
 pub type TestNumber = u32;
 
+/// The numbers of a test and all the tests it is a subtest of, starting with
+/// the outermost one. The path of the top level of tests is empty.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct TestPath(Vec<TestNumber>);
+
+impl TestPath {
+
+    /// Returns the path of the test with the given number inside this one.
+    fn child(&self, number: TestNumber) -> Self {
+        let mut numbers = self.0.clone();
+        numbers.push(number);
+        Self(numbers)
+    }
+}
+
+impl fmt::Display for TestPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, number) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ".")?;
+            }
+
+            write!(f, "{}", number)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// The successful result of a TAP check.
 #[derive(PartialEq, Debug)]
 pub enum Pass {
 
-    /// A TAP test passed, with its number and description string.
-    TestPassed(TestNumber, Option<String>),
+    /// A TAP test passed, with its path and description string.
+    TestPassed(TestPath, Option<String>),
+
+    /// A TAP test was skipped, with its path and description or reason.
+    TestSkipped(TestPath, Option<String>),
 
-    /// The correct number of tests were run.
-    CorrectNumber(TestNumber),
+    /// A TAP test failed, but it was marked as TODO, so this was expected.
+    TodoTestFailed(TestPath, Option<String>),
+
+    /// The correct number of tests were run, either at the top level or
+    /// within a subtest.
+    CorrectNumber(TestPath, TestNumber),
 }
 
 /// The failure result of running a TAP check.
@@ -170,19 +308,31 @@ pub enum Fail {
     /// code. In this case, we don’t check the output for TAP at all.
     CommandFailed,
 
-    /// A TAP test failed, with its number and description string.
-    TestFailed(TestNumber, Option<String>),
+    /// A TAP test failed, with its path, description string, and any YAML
+    /// diagnostics that were printed after it.
+    TestFailed(TestPath, Option<String>, Option<String>),
+
+    /// The incorrect number of tests were run, either at the top level or
+    /// within a subtest.
+    IncorrectNumber { subtest: TestPath, expected: TestNumber, got: TestNumber },
 
-    /// The incorrect number of tests were run at the end.
-    IncorrectNumber { expected: TestNumber, got: TestNumber },
+    /// The test run stopped early, with an optional reason.
+    BailedOut(Option<String>),
 
     /// One of the output lines didn’t make any gosh darn sense.
-    UnparseableLine(Rc<str>),
+    UnparseableLine(Arc<str>),
 }
 
 impl PassResult for Pass {}
 
-impl FailResult for Fail {}
+impl FailResult for Fail {
+    fn command_output(&self) -> Option<(String, &String)> {
+        match self {
+            Self::TestFailed(_, _, Some(diagnostics))  => Some(("Diagnostics:".into(), diagnostics)),
+            _                                           => None,
+        }
+    }
+}
 
 
 // ---- check result descriptions ----
@@ -190,14 +340,27 @@ impl FailResult for Fail {}
 impl fmt::Display for Pass {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::TestPassed(num, None) => {
-                write!(f, "TAP test #{} passed", num)
+            Self::TestPassed(path, None) => {
+                write!(f, "TAP test #{} passed", path)
+            }
+            Self::TestPassed(path, Some(desc)) => {
+                write!(f, "TAP test #{} passed ({})", path, desc)
             }
-            Self::TestPassed(num, Some(desc)) => {
-                write!(f, "TAP test #{} passed ({})", num, desc)
+            Self::TestSkipped(path, None) => {
+                write!(f, "TAP test #{} skipped", path)
             }
-            Self::CorrectNumber(expected) => {
-                write!(f, "Correct number ({}) of tests run", expected)
+            Self::TestSkipped(path, Some(desc)) => {
+                write!(f, "TAP test #{} skipped ({})", path, desc)
+            }
+            Self::TodoTestFailed(path, None) => {
+                write!(f, "TAP test #{} failed, but is marked TODO", path)
+            }
+            Self::TodoTestFailed(path, Some(desc)) => {
+                write!(f, "TAP test #{} failed, but is marked TODO ({})", path, desc)
+            }
+            Self::CorrectNumber(path, expected) => {
+                write!(f, "Correct number ({}) of tests run", expected)?;
+                write_subtest(f, path)
             }
         }
     }
@@ -209,14 +372,21 @@ impl fmt::Display for Fail {
             Self::CommandFailed => {
                 write!(f, "The command failed to be run")
             }
-            Self::TestFailed(num, None) => {
-                write!(f, "TAP test #{} failed", num)
+            Self::TestFailed(path, None, _) => {
+                write!(f, "TAP test #{} failed", path)
+            }
+            Self::TestFailed(path, Some(desc), _) => {
+                write!(f, "TAP test #{} failed ({})", path, desc)
+            }
+            Self::IncorrectNumber { subtest, expected, got } => {
+                write!(f, "Incorrect number of tests run (expected {}, got {})", expected, got)?;
+                write_subtest(f, subtest)
             }
-            Self::TestFailed(num, Some(desc)) => {
-                write!(f, "TAP test #{} failed ({})", num, desc)
+            Self::BailedOut(None) => {
+                write!(f, "The tests bailed out")
             }
-            Self::IncorrectNumber { expected, got } => {
-                write!(f, "Incorrect number of tests run (expected {}, got {})", expected, got)
+            Self::BailedOut(Some(reason)) => {
+                write!(f, "The tests bailed out ({})", reason)
             }
             Self::UnparseableLine(line) => {
                 write!(f, "Unparseable TAP line {:?}", line)
@@ -224,3 +394,14 @@ impl fmt::Display for Fail {
         }
     }
 }
+
+/// Writes which subtest a count of tests was for, unless it was for the
+/// top-level tests.
+fn write_subtest(f: &mut fmt::Formatter<'_>, path: &TestPath) -> fmt::Result {
+    if path.0.is_empty() {
+        Ok(())
+    }
+    else {
+        write!(f, " in subtest #{}", path)
+    }
+}
@@ -1,5 +1,6 @@
 use std::convert::TryInto;
 use std::fmt;
+use std::time::Duration;
 
 use log::*;
 
@@ -40,3 +41,50 @@ impl fmt::Display for PortNumberOutOfRange {
         write!(f, "it must be between 1 and 65535")
     }
 }
+
+
+/// Parses a human-readable duration, made up of one or more numbers that
+/// are each followed by a unit, such as `500ms` or `2m30s`. The units are
+/// `ms` for milliseconds, `s` for seconds, `m` for minutes, `h` for hours,
+/// and `d` for days, which can also be written out, as in `2 minutes`. A
+/// number on its own is counted as seconds.
+///
+/// This is the one parser for every duration, whether it’s given as a
+/// command-line option or as a field in a check document.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let is_number = |c: char| c.is_ascii_digit() || c == '.';
+
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    else if input.chars().all(is_number) {
+        return Duration::try_from_secs_f64(input.parse().ok()?).ok();
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = input;
+    while ! rest.is_empty() {
+        let split = rest.find(|c: char| ! is_number(c)).unwrap_or(rest.len());
+        let (number, after) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+
+        let after = after.trim_start();
+        let split = after.find(|c: char| ! c.is_ascii_alphabetic()).unwrap_or(after.len());
+        let (unit, after) = after.split_at(split);
+        let multiplier = match unit {
+            "ms" | "msec" | "msecs" | "millisecond" | "milliseconds"  => 0.001,
+            "s"  | "sec"  | "secs"  | "second"      | "seconds"       => 1.0,
+            "m"  | "min"  | "mins"  | "minute"      | "minutes"       => 60.0,
+            "h"  | "hr"   | "hrs"   | "hour"        | "hours"         => 3600.0,
+            "d"  | "day"  | "days"                                    => 86400.0,
+            _                                                         => return None,
+        };
+
+        total = total.checked_add(Duration::try_from_secs_f64(number * multiplier).ok()?)?;
+        rest = after.trim_start();
+    }
+
+    Some(total)
+}
+
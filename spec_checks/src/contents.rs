@@ -1,12 +1,14 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::fs::read;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 //use log::*;
 use regex::{Error as RegexError, bytes::Regex};
 
 use crate::CheckResult;
+use crate::numbers::NumberMatcher;
 use crate::read::{TomlValue, ValueExtras, ReadError};
 
 
@@ -30,6 +32,10 @@ pub enum ContentsMatcher {
 
     /// The output should be non-empty.
     ShouldBeNonEmpty,
+
+    /// The output should have a number of lines that satisfies the
+    /// given number matcher.
+    LineCount(NumberMatcher),
 }
 
 impl ContentsMatcher {
@@ -45,6 +51,7 @@ impl ContentsMatcher {
             Self::FileMatch(path)             => write!(f, " {} matching file ‘{}’", noun, path.display()),
             Self::ShouldBeEmpty               => write!(f, " empty {}", noun),
             Self::ShouldBeNonEmpty            => write!(f, " non-empty {}", noun),
+            Self::LineCount(lines)            => write!(f, " {} of {} lines", noun, lines),
         }
     }
 }
@@ -55,7 +62,7 @@ impl ContentsMatcher {
 impl ContentsMatcher {
     pub fn read(parameter_name: &'static str, table: &TomlValue) -> Result<Self, ReadError> {
         table.ensure_table(parameter_name)?;
-        table.ensure_only_keys(&["regex", "string", "file", "empty", "matches"])?;
+        table.ensure_only_keys(&["regex", "string", "file", "empty", "matches", "lines", "min_lines", "max_lines"])?;
 
         let matches = table.get("matches")
                            .map(|m| m.boolean_or_error("matches")).transpose()?
@@ -105,8 +112,46 @@ impl ContentsMatcher {
             }
         }
 
-        Err(ReadError::invalid(parameter_name, table.clone(), ContentsReadError::NoConditions))
+        if let Some(lines_value) = table.get("lines") {
+            if table.get("min_lines").is_some() {
+                return Err(ReadError::conflict("min_lines", "lines"));
+            }
+
+            if table.get("max_lines").is_some() {
+                return Err(ReadError::conflict("max_lines", "lines"));
+            }
+
+            if lines_value.is_table() {
+                return Ok(Self::LineCount(NumberMatcher::read("lines", lines_value)?));
+            }
+
+            let lines = read_line_count("lines", lines_value)?;
+            return Ok(Self::LineCount(NumberMatcher::exactly(lines)));
+        }
+
+        let min_lines = table.get("min_lines").map(|e| read_line_count("min_lines", e)).transpose()?;
+        let max_lines = table.get("max_lines").map(|e| read_line_count("max_lines", e)).transpose()?;
+        if let (Some(min), Some(max)) = (min_lines, max_lines) {
+            if min > max {
+                return Err(ReadError::invalid("min_lines", table["min_lines"].clone(), ContentsReadError::MinAboveMax));
+            }
+        }
+
+        match NumberMatcher::range(min_lines, max_lines) {
+            Some(lines)  => Ok(Self::LineCount(lines)),
+            None         => Err(ReadError::invalid(parameter_name, table.clone(), ContentsReadError::NoConditions)),
+        }
+    }
+}
+
+/// Reads a number of lines, which must not be negative.
+fn read_line_count(parameter_name: &'static str, value: &TomlValue) -> Result<i64, ReadError> {
+    let number = value.number_or_error(parameter_name)?;
+    if number < 0 {
+        return Err(ReadError::invalid(parameter_name, value.clone(), ContentsReadError::NegativeLines));
     }
+
+    Ok(number)
 }
 
 /// Something that can go wrong while reading a `ContentsMatcher`.
@@ -121,6 +166,12 @@ pub enum ContentsReadError {
 
     /// The input string to match on was empty.
     EmptyString,
+
+    /// A number of lines was negative.
+    NegativeLines,
+
+    /// The minimum number of lines was greater than the maximum.
+    MinAboveMax,
 }
 
 impl fmt::Display for ContentsReadError {
@@ -135,6 +186,12 @@ impl fmt::Display for ContentsReadError {
             Self::EmptyString => {
                 write!(f, "Empty string")
             }
+            Self::NegativeLines => {
+                write!(f, "it must not be negative")
+            }
+            Self::MinAboveMax => {
+                write!(f, "it must not be greater than ‘max_lines’")
+            }
         }
     }
 }
@@ -143,7 +200,11 @@ impl fmt::Display for ContentsReadError {
 // ---- running the check ----
 
 impl ContentsMatcher {
-    pub fn check(&self, contents: &[u8]) -> CheckResult<Pass, Fail> {
+
+    /// Checks the given contents against this matcher. Relative paths to
+    /// files of expected contents are looked up from the given directory,
+    /// or from the current directory of the process if there isn’t one.
+    pub fn check(&self, contents: &[u8], directory: Option<&Path>) -> CheckResult<Pass, Fail> {
 
         // regex check
         if let Self::LineRegex(regex_str, matches) = &self {
@@ -202,7 +263,12 @@ impl ContentsMatcher {
 
         // file check
         if let Self::FileMatch(contents_file) = &self {
-            match read(contents_file) {
+            let resolved = match directory {
+                Some(directory)  => directory.join(contents_file),
+                None             => contents_file.clone(),
+            };
+
+            match read(resolved) {
                 Ok(read_contents) => {
                     if read_contents == contents {
                         return CheckResult::Passed(Pass::OutputMatchesFile);
@@ -238,10 +304,35 @@ impl ContentsMatcher {
             }
         }
 
+        // line count check
+        if let Self::LineCount(lines) = &self {
+            let count = line_count(contents);
+
+            if ! lines.matches(i64::try_from(count).unwrap_or(i64::MAX)) {
+                let output_string = String::from_utf8_lossy(contents).into();
+                return CheckResult::Failed(Fail::OutputLineCountMismatch(count, output_string));
+            }
+
+            return CheckResult::Passed(Pass::OutputLineCount);
+        }
+
         unreachable!()
     }
 }
 
+/// Counts the lines in the given contents, including a final line that
+/// isn’t followed by a newline.
+fn line_count(contents: &[u8]) -> usize {
+    let mut count = contents.split(|b| *b == b'\n').count();
+
+    // A trailing newline ends the last line, rather than starting a new one.
+    if contents.is_empty() || contents.ends_with(b"\n") {
+        count -= 1;
+    }
+
+    count
+}
+
 fn bytes_contains(haystack: &[u8], needle: &[u8]) -> bool {
     haystack.windows(needle.len())
             .any(|e| e == needle)
@@ -271,6 +362,9 @@ pub enum Pass {
 
     /// The contents was non-empty.
     OutputNonEmpty,
+
+    /// The contents had an acceptable number of lines.
+    OutputLineCount,
 }
 
 #[derive(Debug)]
@@ -306,6 +400,9 @@ pub enum Fail {
 
     /// The contents should have been non-empty, but was empty.
     OutputEmpty,
+
+    /// The contents had this many lines, which was not an acceptable number.
+    OutputLineCountMismatch(usize, String),
 }
 
 
@@ -335,6 +432,9 @@ impl fmt::Display for Pass {
             Self::OutputNonEmpty => {
                 write!(f, "is non-empty")
             }
+            Self::OutputLineCount => {
+                write!(f, "has the right number of lines")
+            }
         }
     }
 }
@@ -369,6 +469,12 @@ impl fmt::Display for Fail {
             Self::OutputEmpty => {
                 write!(f, "was empty")
             }
+            Self::OutputLineCountMismatch(1, _) => {
+                write!(f, "had ‘1’ line")
+            }
+            Self::OutputLineCountMismatch(count, _) => {
+                write!(f, "had ‘{}’ lines", count)
+            }
         }
     }
 }
@@ -377,10 +483,11 @@ impl fmt::Display for Fail {
 impl Fail {
     pub fn command_output(&self, title: &'static str) -> Option<(String, &String)> {
         match self {
-            Self::OutputRegexMismatch(got)  |
-            Self::OutputStringMismatch(got) |
-            Self::OutputNotEmpty(got)       => Some((title.into(), got)),
-            _                                  => None,
+            Self::OutputRegexMismatch(got)         |
+            Self::OutputStringMismatch(got)        |
+            Self::OutputNotEmpty(got)              |
+            Self::OutputLineCountMismatch(_, got)  => Some((title.into(), got)),
+            _                                      => None,
         }
     }
 
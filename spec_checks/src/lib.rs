@@ -56,4 +56,6 @@ pub use self::network::*;
 pub mod common;
 pub mod contents;
 pub mod load;
+pub mod numbers;
 pub mod read;
+pub mod semver;
@@ -1,17 +1,108 @@
 use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+use log::*;
 use serde::Deserialize;
+use toml_edit::TableLike;
 
 pub use toml::{Value as TomlValue, de::Error as TomlError};
+pub use serde_yaml::Error as YamlError;
+pub use serde_json::Error as JsonError;
 
 
-/// The schema of a check document.
-pub type CheckDocument = BTreeMap<String, Vec<CheckEntry>>;
+/// The schema of a check document: its checks, keyed by check type, along
+/// with the commands to run before and after all of them, and the values of
+/// the variables they use.
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckDocument {
+
+    /// Paths, or glob patterns, of other check documents whose checks get
+    /// run along with this one’s, relative to the directory this one is in.
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// The values of the variables used in the document, from its `[vars]`
+    /// table.
+    #[serde(default)]
+    pub vars: Variables,
+
+    /// Commands to run before any of the checks in the document.
+    pub setup: Option<HookTable>,
+
+    /// Commands to run after all of the checks in the document, even if the
+    /// setup commands failed.
+    pub teardown: Option<HookTable>,
+
+    /// Every other table in the document is an array of checks.
+    #[serde(flatten)]
+    pub checks: BTreeMap<String, Vec<CheckEntry>>,
+}
+
+impl CheckDocument {
+
+    /// Merges a later document into this one, which is how included
+    /// documents get combined with the document that includes them. The
+    /// later document’s checks come after this one’s, its setup commands
+    /// get run after this one’s and its teardown commands before, and its
+    /// variables override this one’s.
+    pub fn append(&mut self, later: Self) {
+        for (check_type, mut entries) in later.checks {
+            self.checks.entry(check_type).or_default().append(&mut entries);
+        }
+
+        self.include.extend(later.include);
+        self.vars.extend(later.vars);
+        self.setup = HookTable::join(self.setup.take(), later.setup);
+        self.teardown = HookTable::join(later.teardown, self.teardown.take());
+    }
+
+    /// Expands the entries with a matrix into their checks.
+    fn expand_matrices(&mut self) {
+        for entries in self.checks.values_mut() {
+            *entries = entries.drain(..).flat_map(CheckEntry::expand_matrix).collect();
+        }
+    }
+
+    /// Records the file that this document’s checks were read from.
+    pub fn set_file(&mut self, path: &Path) {
+        for entry in self.checks.values_mut().flatten() {
+            entry.location.file = Some(path.to_path_buf());
+        }
+    }
+}
+
+/// The `[setup]` or `[teardown]` table of a check document.
+#[derive(Debug, Deserialize)]
+pub struct HookTable {
+
+    /// The shell commands to run, in order.
+    pub shell: Hook,
+}
+
+impl HookTable {
+
+    /// Joins the commands of two tables together, in order.
+    fn join(first: Option<Self>, second: Option<Self>) -> Option<Self> {
+        match (first, second) {
+            (Some(first), Some(second)) => {
+                let mut commands = first.shell.into_commands();
+                commands.extend(second.shell.into_commands());
+                Some(Self { shell: Hook::Many(commands) })
+            }
+            (first, second) => {
+                first.or(second)
+            }
+        }
+    }
+}
 
 /// The type that is parsed from TOML is not just an arbitrary table, it’s an
 /// arbitrary table that could have `name` and `tags` fields! This type holds
 /// the fields that are common to all checks. (Also: more fields.)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CheckEntry {
 
     /// The rest of the check-specific fields, which have not been deciphered
@@ -25,18 +116,459 @@ pub struct CheckEntry {
 
     /// A list of tags, which lets the user control which checks get run.
     pub tags: Option<Tags>,
+
+    /// How long the check is expected to take to run, as a duration string.
+    /// Going over budget does not cause the check to fail.
+    pub budget: Option<String>,
+
+    /// A reference to a known issue, such as a ticket number, that explains
+    /// why this check is expected to fail for now.
+    pub known_issue: Option<String>,
+
+    /// How many more times the check should be run if it fails, before
+    /// its failure gets recorded.
+    pub retries: Option<u32>,
+
+    /// How long to wait between each attempt, as a duration string.
+    pub retry_delay: Option<String>,
+
+    /// How long to wait after running the check before running it again in
+    /// continual mode, as a duration string.
+    pub interval: Option<String>,
+
+    /// Commands to run before this check.
+    pub setup: Option<Hook>,
+
+    /// Commands to run after this check, even if it or its setup commands
+    /// failed.
+    pub teardown: Option<Hook>,
+
+    /// The names of the checks that have to pass for this check to be run.
+    pub needs: Option<Vec<String>>,
+
+    /// The conditions the machine has to meet for this check to be run.
+    pub only_if: Option<OnlyIf>,
+
+    /// Whether the check is expected to `pass` or to `fail`.
+    pub expected: Option<String>,
+
+    /// How much it matters when the check fails, either `error` or `warn`.
+    pub severity: Option<String>,
+
+    /// Lists of values for variables, which turn this entry into one check
+    /// for every combination of them. This gets expanded while parsing, so
+    /// it’s always `None` afterwards.
+    pub matrix: Option<BTreeMap<String, Vec<TomlValue>>>,
+
+    /// Where the entry was written, which isn’t part of the document
+    /// itself, and gets filled in after it has been parsed.
+    #[serde(skip)]
+    pub location: Location,
+}
+
+/// Where in a check document a check entry was written.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Location {
+
+    /// The file the document was read from, if it was read from one.
+    pub file: Option<PathBuf>,
+
+    /// Where the entry starts, which is its `[[type]]` header if it was
+    /// written as a table of its own.
+    pub start: Option<Position>,
+
+    /// Where each of the entry’s parameters was written, keyed by their
+    /// names, with the names of parameters in sub-tables joined with dots.
+    pub parameters: BTreeMap<String, Position>,
+}
+
+impl Location {
+
+    /// Returns where the parameter with the given name was written. Errors
+    /// only have the name of a parameter without the tables it is in, so a
+    /// parameter in a sub-table gets found by its own name if there isn’t
+    /// one at the top level.
+    pub fn parameter(&self, name: &str) -> Option<Position> {
+        let suffix = format!(".{}", name);
+
+        self.parameters.get(name).copied()
+            .or_else(|| self.parameters.iter().find(|(path, _)| path.ends_with(&suffix)).map(|(_, position)| *position))
+    }
+}
+
+/// A line and column in a check document, both starting at 1.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+
+    /// Works out the line and column of the given byte offset.
+    fn from_offset(document: &str, offset: usize) -> Self {
+        let before = &document[.. offset.min(document.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start ..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The `only_if` table of a check, which lets one check document be used
+/// on different kinds of machine. Every condition given has to be met.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnlyIf {
+
+    /// The operating system, such as `linux` or `macos`.
+    pub os: Option<String>,
+
+    /// The processor architecture, such as `x86_64` or `aarch64`.
+    pub arch: Option<String>,
+
+    /// A program that has to be installed.
+    pub command_exists: Option<String>,
+
+    /// Environment variables that have to be set to the given values.
+    pub env: Option<BTreeMap<String, String>>,
 }
 
 /// Each check can have one or more tags.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum Tags {
     One(String),
     Many(Vec<String>),
 }
 
+impl Tags {
+
+    /// Returns the tags, in order.
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(tag)    => vec![ tag ],
+            Self::Many(tags)  => tags,
+        }
+    }
+}
+
+/// Setup and teardown hooks can have one or more shell commands.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Hook {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Hook {
+
+    /// Returns the hook’s shell commands, in order.
+    pub fn into_commands(self) -> Vec<String> {
+        match self {
+            Self::One(command)    => vec![ command ],
+            Self::Many(commands)  => commands,
+        }
+    }
+
+    /// Returns a copy of this hook with the variable placeholders in its
+    /// commands replaced, or the first variable that has no value.
+    pub fn interpolated(&self, variables: &Variables) -> Result<Self, String> {
+        match self {
+            Self::One(command)    => interpolate_text(command, variables).map(Self::One),
+            Self::Many(commands)  => commands.iter().map(|c| interpolate_text(c, variables)).collect::<Result<_, _>>().map(Self::Many),
+        }
+    }
+}
+
+/// The values of the variables in a check document, keyed by name.
+pub type Variables = BTreeMap<String, TomlValue>;
+
 /// Parse the given string (that has been read from standard input or a file)
 /// from the TOML representing a check document, or return a parse error.
+/// Entries with a matrix get expanded into their checks.
 pub fn parse_toml(check_document: &str) -> Result<CheckDocument, TomlError> {
-    toml::from_str(check_document)
+    let mut document: CheckDocument = toml::from_str(check_document)?;
+
+    // The positions have to be found before the matrices get expanded,
+    // while there’s still one entry for each table.
+    locate_entries(&mut document, check_document);
+
+    document.expand_matrices();
+    Ok(document)
+}
+
+/// Parse the given string from the YAML representing a check document,
+/// which has the same structure as the TOML one, or return a parse error.
+/// Where each entry was written doesn’t get found for YAML documents, so
+/// errors in their checks only say which file they are in.
+pub fn parse_yaml(check_document: &str) -> Result<CheckDocument, YamlError> {
+
+    // A document with nothing but comments in it has no checks, like an
+    // empty TOML document, rather than being invalid.
+    let mut document: CheckDocument = serde_yaml::from_str::<Option<_>>(check_document)?.unwrap_or_default();
+
+    document.expand_matrices();
+    Ok(document)
+}
+
+
+/// Parse the given string from the JSON representing a check document,
+/// which has the same structure as the TOML one, or return a parse error.
+/// Like YAML documents, where each entry was written doesn’t get found.
+pub fn parse_json(check_document: &str) -> Result<CheckDocument, JsonError> {
+    let mut document: CheckDocument = serde_json::from_str(check_document)?;
+
+    document.expand_matrices();
+    Ok(document)
+}
+
+
+/// Fills in where each entry in the document, and each of its parameters,
+/// was written. The TOML parser that the document gets deserialised with
+/// doesn’t keep track of where anything was, so the document gets parsed a
+/// second time with one that does.
+fn locate_entries(document: &mut CheckDocument, source: &str) {
+    let spans = match toml_edit::ImDocument::parse(source) {
+        Ok(spans) => spans,
+        Err(e) => {
+            debug!("Error finding positions in check document: {}", e);
+            return;
+        }
+    };
+
+    let position = |span: Option<Range<usize>>| span.map(|s| Position::from_offset(source, s.start));
+
+    for (check_type, entries) in &mut document.checks {
+        let Some(item) = spans.as_table().get(check_type) else { continue };
+
+        let tables: Vec<(Option<Range<usize>>, &dyn TableLike)> =
+            if let Some(array) = item.as_array_of_tables() {
+                array.iter().map(|t| {
+                    let table: &dyn TableLike = t;
+                    (t.span(), table)
+                }).collect()
+            }
+            else if let Some(array) = item.as_array() {
+                array.iter().filter_map(|v| {
+                    let table: &dyn TableLike = v.as_inline_table()?;
+                    Some((v.span(), table))
+                }).collect()
+            }
+            else {
+                continue;
+            };
+
+        for (entry, (span, table)) in entries.iter_mut().zip(tables) {
+            entry.location.start = position(span);
+            locate_parameters(table, "", &mut entry.location.parameters, &position);
+        }
+    }
+}
+
+/// Records where each key in the table was written, going into sub-tables.
+fn locate_parameters(table: &dyn TableLike, prefix: &str, parameters: &mut BTreeMap<String, Position>, position: &dyn Fn(Option<Range<usize>>) -> Option<Position>) {
+    for (name, item) in table.iter() {
+        let path = format!("{}{}", prefix, name);
+
+        if let Some(key_position) = position(table.key(name).and_then(toml_edit::Key::span)) {
+            parameters.insert(path.clone(), key_position);
+        }
+
+        if let Some(sub_table) = item.as_table_like() {
+            locate_parameters(sub_table, &format!("{}.", path), parameters, position);
+        }
+    }
+}
+
+
+/// The values of each matrix variable for one of the checks it expands into.
+type MatrixValues<'a> = BTreeMap<&'a str, &'a TomlValue>;
+
+impl CheckEntry {
+
+    /// Expands an entry with a matrix into one entry for each combination
+    /// of the values of its variables, in order, with the placeholders for
+    /// them replaced. An entry without a matrix is left as it is.
+    fn expand_matrix(mut self) -> Vec<Self> {
+        let Some(matrix) = self.matrix.take() else { return vec![ self ] };
+
+        let mut combinations = vec![ MatrixValues::new() ];
+        for (variable, values) in &matrix {
+            combinations = combinations.into_iter().flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(variable.as_str(), value);
+                    combination
+                })
+            }).collect();
+        }
+
+        combinations.iter().map(|values| self.substituted(values)).collect()
+    }
+
+    /// Returns a copy of this entry with the placeholders for the matrix
+    /// variables in its parameters, name, and hooks replaced.
+    fn substituted(&self, values: &MatrixValues<'_>) -> Self {
+        let text = |string: &String| substitute_text(string, values);
+
+        let hook = |hook: &Hook| match hook {
+            Hook::One(command)    => Hook::One(text(command)),
+            Hook::Many(commands)  => Hook::Many(commands.iter().map(text).collect()),
+        };
+
+        Self {
+            inner: substitute(&self.inner, values),
+            name: self.name.as_ref().map(text),
+            setup: self.setup.as_ref().map(hook),
+            teardown: self.teardown.as_ref().map(hook),
+            .. self.clone()
+        }
+    }
+}
+
+/// Replaces the placeholders for matrix variables, such as `${vhost}`, in
+/// every string in the given value. A string that is nothing but a
+/// placeholder gets replaced with the variable’s value as it is, so numbers
+/// stay as numbers.
+fn substitute(value: &TomlValue, values: &MatrixValues<'_>) -> TomlValue {
+    match value {
+        TomlValue::String(string) => {
+            let whole = values.iter().find(|(variable, _)| *string == placeholder(variable));
+
+            match whole {
+                Some((_, value))  => (*value).clone(),
+                None              => TomlValue::String(substitute_text(string, values)),
+            }
+        }
+        TomlValue::Array(array) => {
+            TomlValue::Array(array.iter().map(|e| substitute(e, values)).collect())
+        }
+        TomlValue::Table(table) => {
+            TomlValue::Table(table.iter().map(|(k, v)| (k.clone(), substitute(v, values))).collect())
+        }
+        other => {
+            other.clone()
+        }
+    }
+}
+
+/// Replaces the placeholders for matrix variables in a string with the
+/// text of their values.
+fn substitute_text(string: &str, values: &MatrixValues<'_>) -> String {
+    let mut string = string.to_owned();
+
+    for (variable, value) in values {
+        string = string.replace(&placeholder(variable), &value_text(value));
+    }
+
+    string
+}
+
+/// Returns the text to replace a placeholder in the middle of a string
+/// with, which for strings is the string without any quotes.
+fn value_text(value: &TomlValue) -> String {
+    match value {
+        TomlValue::String(s)  => s.clone(),
+        other                 => other.to_string(),
+    }
+}
+
+/// The placeholder for a matrix variable.
+fn placeholder(variable: &str) -> String {
+    format!("${{{}}}", variable)
+}
+
+
+impl CheckEntry {
+
+    /// Returns a copy of this entry with the placeholders for variables,
+    /// such as `${host}` or `${env:HOME}`, in its parameters, name, and
+    /// hooks replaced, or the first variable that has no value.
+    pub fn interpolated(&self, variables: &Variables) -> Result<Self, String> {
+        let hook = |hook: &Hook| hook.interpolated(variables);
+
+        Ok(Self {
+            inner: interpolate(&self.inner, variables)?,
+            name: self.name.as_ref().map(|n| interpolate_text(n, variables)).transpose()?,
+            setup: self.setup.as_ref().map(hook).transpose()?,
+            teardown: self.teardown.as_ref().map(hook).transpose()?,
+            .. self.clone()
+        })
+    }
+}
+
+/// Replaces the placeholders for variables in every string in the given
+/// value. Like with matrix variables, a string that is nothing but a
+/// placeholder gets replaced with the variable’s value as it is.
+fn interpolate(value: &TomlValue, variables: &Variables) -> Result<TomlValue, String> {
+    match value {
+        TomlValue::String(string) => {
+            let whole = string.strip_prefix("${")
+                              .and_then(|s| s.strip_suffix('}'))
+                              .filter(|variable| ! variable.contains('}'));
+
+            match whole {
+                Some(variable)  => resolve(variable, variables),
+                None            => interpolate_text(string, variables).map(TomlValue::String),
+            }
+        }
+        TomlValue::Array(array) => {
+            array.iter().map(|e| interpolate(e, variables)).collect::<Result<_, _>>().map(TomlValue::Array)
+        }
+        TomlValue::Table(table) => {
+            table.iter().map(|(k, v)| Ok((k.clone(), interpolate(v, variables)?))).collect::<Result<_, _>>().map(TomlValue::Table)
+        }
+        other => {
+            Ok(other.clone())
+        }
+    }
+}
+
+/// Replaces the placeholders for variables in a string with the text of
+/// their values. A placeholder can be written as `$${var}` to have it be
+/// left in the string as `${var}`, such as for shell variables.
+fn interpolate_text(string: &str, variables: &Variables) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = string;
+
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[.. index]);
+        let from_dollar = &rest[index ..];
+
+        if let Some(after) = from_dollar.strip_prefix("$${") {
+            result.push_str("${");
+            rest = after;
+        }
+        else if let Some((variable, after)) = from_dollar.strip_prefix("${").and_then(|s| s.split_once('}')) {
+            result.push_str(&value_text(&resolve(variable, variables)?));
+            rest = after;
+        }
+        else {
+            result.push('$');
+            rest = &from_dollar[1 ..];
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Looks up the value of a variable, which is an environment variable if
+/// its name starts with `env:`.
+fn resolve(variable: &str, variables: &Variables) -> Result<TomlValue, String> {
+    let value = match variable.strip_prefix("env:") {
+        Some(name)  => env::var(name).ok().map(TomlValue::String),
+        None        => variables.get(variable).cloned(),
+    };
+
+    value.ok_or_else(|| variable.to_owned())
 }
@@ -5,14 +5,27 @@
 //! [[apt]]
 //! package = 'httpd'
 //! state = 'installed'
+//!
+//! [[apt]]
+//! package = 'nginx'
+//! version = '>= 1.18'
+//! held = true
 //! ```
+//!
+//! # Version comparisons
+//!
+//! Versions with an operator get compared using the same rules as `dpkg`:
+//! the epoch first, then the upstream version, then the Debian revision,
+//! with `~` sorting before anything, even the end of the string.
 
 
+use std::cmp::Ordering;
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::{Check, RunCheck, CheckResult, PassResult, FailResult};
@@ -28,6 +41,10 @@ pub struct AptCheck {
 
     /// The condition to test it with.
     condition: Condition,
+
+    /// Whether the package should be held back from upgrades, if that is
+    /// being checked.
+    held: Option<bool>,
 }
 
 #[derive(PartialEq, Debug)]
@@ -46,9 +63,38 @@ enum Condition {
 #[derive(PartialEq, Debug)]
 enum PackageVersion {
 
+    /// Any version of the package will do.
     Any,
 
+    /// The version must be exactly this string.
     Specific(String),
+
+    /// The version must compare to this one in a certain way.
+    Compared(Operator, String),
+}
+
+/// An operator that can precede a version.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Operator {
+
+    /// `=`, the versions must compare as equal.
+    Equal,
+
+    /// `>=`, the version must be this one or later.
+    AtLeast,
+
+    /// `>`, the version must be later than this one.
+    Above,
+
+    /// `<=`, the version must be this one or earlier.
+    AtMost,
+
+    /// `<`, the version must be earlier than this one.
+    Below,
+
+    /// `~>`, the version must be this one or later, but only increase in
+    /// its last component, so `~> 2.4` allows `2.9` but not `3.0`.
+    Pessimistic,
 }
 
 
@@ -56,19 +102,28 @@ enum PackageVersion {
 
 impl fmt::Display for AptCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { package_name, condition } = &self;
+        let Self { package_name, condition, held } = &self;
 
         match condition {
             Condition::Installed(PackageVersion::Specific(version)) => {
-                write!(f, "Package ‘{}’ version ‘{}’ is installed", package_name.0, version)
+                write!(f, "Package ‘{}’ version ‘{}’ is installed", package_name.0, version)?;
+            }
+            Condition::Installed(PackageVersion::Compared(operator, version)) => {
+                write!(f, "Package ‘{}’ version ‘{} {}’ is installed", package_name.0, operator.as_str(), version)?;
             }
             Condition::Installed(PackageVersion::Any) => {
-                write!(f, "Package ‘{}’ is installed", package_name.0)
+                write!(f, "Package ‘{}’ is installed", package_name.0)?;
             }
             Condition::Missing => {
-                write!(f, "Package ‘{}’ is not installed", package_name.0)
+                write!(f, "Package ‘{}’ is not installed", package_name.0)?;
             }
         }
+
+        match held {
+            Some(true)   => write!(f, " and held"),
+            Some(false)  => write!(f, " and not held"),
+            None         => Ok(()),
+        }
     }
 }
 
@@ -81,11 +136,17 @@ impl Check for AptCheck {
 
 impl AptCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["package", "state", "version"])?;
+        table.ensure_only_keys(&["package", "state", "version", "held"])?;
 
         let package_name = PackageName::read(table)?;
         let condition = Condition::read(table)?;
-        Ok(Self { package_name, condition })
+        let held = table.get("held").map(|e| e.boolean_or_error("held")).transpose()?;
+
+        if held.is_some() && condition == Condition::Missing {
+            return Err(ReadError::conflict2("held", "state", table["state"].clone()));
+        }
+
+        Ok(Self { package_name, condition, held })
     }
 }
 
@@ -143,12 +204,204 @@ impl PackageVersion {
                 return Err(ReadError::invalid("version", version_value.clone(), "it must not be empty"));
             }
 
-            Ok(Self::Specific(version_string))
+            if let Some((operator, rest)) = Operator::strip(&version_string) {
+                let version = rest.trim();
+
+                if version.is_empty() {
+                    return Err(ReadError::invalid("version", version_value.clone(), "it must have a version after the operator"));
+                }
+
+                Ok(Self::Compared(operator, version.into()))
+            }
+            else {
+                Ok(Self::Specific(version_string))
+            }
         }
         else {
             Ok(Self::Any)
         }
     }
+
+    /// Whether the given installed version satisfies this version.
+    fn matches(&self, got_version: &str) -> bool {
+        match self {
+            Self::Any                       => true,
+            Self::Specific(version)         => version == got_version,
+            Self::Compared(operator, version) => operator.matches(got_version, version),
+        }
+    }
+}
+
+impl Operator {
+
+    /// Splits an operator off the front of a version string, if it
+    /// has one.
+    fn strip(version: &str) -> Option<(Self, &str)> {
+        const OPERATORS: &[(&str, Operator)] = &[
+            ("~>", Operator::Pessimistic),
+            (">=", Operator::AtLeast),
+            ("<=", Operator::AtMost),
+            (">",  Operator::Above),
+            ("<",  Operator::Below),
+            ("=",  Operator::Equal),
+        ];
+
+        OPERATORS.iter().find_map(|(symbol, operator)| {
+            version.strip_prefix(symbol).map(|rest| (*operator, rest))
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Equal        => "=",
+            Self::AtLeast      => ">=",
+            Self::Above        => ">",
+            Self::AtMost       => "<=",
+            Self::Below        => "<",
+            Self::Pessimistic  => "~>",
+        }
+    }
+
+    /// Whether the installed version compares to the expected version
+    /// in the way this operator wants it to.
+    fn matches(self, got_version: &str, expected_version: &str) -> bool {
+        let ordering = compare_versions(got_version, expected_version);
+
+        match self {
+            Self::Equal        => ordering == Ordering::Equal,
+            Self::AtLeast      => ordering != Ordering::Less,
+            Self::Above        => ordering == Ordering::Greater,
+            Self::AtMost       => ordering != Ordering::Greater,
+            Self::Below        => ordering == Ordering::Less,
+            Self::Pessimistic  => {
+                ordering != Ordering::Less
+                    && compare_versions(got_version, &pessimistic_limit(expected_version)) == Ordering::Less
+            }
+        }
+    }
+}
+
+/// Returns the version that a pessimistic constraint has to stay below,
+/// by dropping the last component of the upstream version and
+/// incrementing the one before it, so `2.4.1` becomes `2.5`.
+fn pessimistic_limit(version: &str) -> String {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest))  => (Some(epoch), rest),
+        None                 => (None, version),
+    };
+
+    let upstream = rest.rsplit_once('-').map_or(rest, |(upstream, _)| upstream);
+    let mut components = upstream.split('.').collect::<Vec<_>>();
+    if components.len() > 1 {
+        components.pop();
+    }
+
+    let last = components.pop().unwrap_or_default();
+    let digits = last.chars().take_while(char::is_ascii_digit).collect::<String>();
+    let bumped = digits.parse::<u64>().map_or(1, |n| n + 1);
+    components.push("");
+
+    let mut limit = String::new();
+    if let Some(epoch) = epoch {
+        limit.push_str(epoch);
+        limit.push(':');
+    }
+
+    limit.push_str(&components.join("."));
+    limit.push_str(&bumped.to_string());
+    limit
+}
+
+/// Compares two Debian version strings, made up of an optional epoch, an
+/// upstream version, and an optional revision.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a_epoch, a_upstream, a_revision) = split_version(a);
+    let (b_epoch, b_upstream, b_revision) = split_version(b);
+
+    a_epoch.cmp(&b_epoch)
+        .then_with(|| compare_version_parts(a_upstream, b_upstream))
+        .then_with(|| compare_version_parts(a_revision, b_revision))
+}
+
+/// Splits a version string into its epoch, upstream version, and revision.
+fn split_version(version: &str) -> (u64, &str, &str) {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest))  => (epoch.parse().unwrap_or(0), rest),
+        None                 => (0, version),
+    };
+
+    match rest.rsplit_once('-') {
+        Some((upstream, revision))  => (epoch, upstream, revision),
+        None                        => (epoch, rest, ""),
+    }
+}
+
+/// Compares two upstream versions or revisions, by alternately comparing
+/// runs of non-digits character by character, and runs of digits
+/// numerically.
+fn compare_version_parts(a: &str, b: &str) -> Ordering {
+    let mut a = a.as_bytes();
+    let mut b = b.as_bytes();
+
+    while ! a.is_empty() || ! b.is_empty() {
+        loop {
+            let a_char = a.first().filter(|c| ! c.is_ascii_digit());
+            let b_char = b.first().filter(|c| ! c.is_ascii_digit());
+
+            if a_char.is_none() && b_char.is_none() {
+                break;
+            }
+
+            let ordering = character_weight(a_char).cmp(&character_weight(b_char));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+
+            if a_char.is_some() { a = &a[1 ..]; }
+            if b_char.is_some() { b = &b[1 ..]; }
+        }
+
+        let a_digits = a.iter().take_while(|c| c.is_ascii_digit()).count();
+        let b_digits = b.iter().take_while(|c| c.is_ascii_digit()).count();
+        let ordering = compare_digits(&a[.. a_digits], &b[.. b_digits]);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        a = &a[a_digits ..];
+        b = &b[b_digits ..];
+    }
+
+    Ordering::Equal
+}
+
+/// The sort weight of a non-digit character in a version: a tilde sorts
+/// before everything, then the end of the string, then letters, then
+/// everything else.
+fn character_weight(c: Option<&u8>) -> i32 {
+    match c {
+        Some(b'~')                          => -1,
+        None                                => 0,
+        Some(c) if c.is_ascii_alphabetic()  => i32::from(*c),
+        Some(c)                             => i32::from(*c) + 256,
+    }
+}
+
+/// Compares two runs of digits numerically, without limiting how long
+/// they can be.
+fn compare_digits(a: &[u8], b: &[u8]) -> Ordering {
+    let a = &a[a.iter().take_while(|c| **c == b'0').count() ..];
+    let b = &b[b.iter().take_while(|c| **c == b'0').count() ..];
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+
+// ---- analysis properties ----
+
+impl AptCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesPackage(&self.package_name.0) ]
+    }
 }
 
 
@@ -160,10 +413,17 @@ pub trait RunApt {
     /// Prime the command for running.
     fn prime(&mut self) { }
 
+    /// Prime the command for running, to get the list of held packages.
+    fn prime_holds(&mut self) { }
+
     /// Running the command if it hasn’t been run already, consult the
     /// database and find the installed version of the package with the
     /// given name, if any.
-    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<Option<String>, Rc<ExecError>>;
+    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<Option<String>, Arc<ExecError>>;
+
+    /// Running the command if it hasn’t been run already, return whether
+    /// the package with the given name is being held back from upgrades.
+    fn is_held(&self, executor: &mut Executor, package_name: &str) -> Result<bool, Arc<ExecError>>;
 }
 
 impl<A: RunApt> RunCheck<A> for AptCheck {
@@ -172,6 +432,10 @@ impl<A: RunApt> RunCheck<A> for AptCheck {
 
     fn load(&self, apt: &mut A) {
         apt.prime();
+
+        if self.held.is_some() {
+            apt.prime_holds();
+        }
     }
 
     fn check(&self, executor: &mut Executor, apt: &A) -> Vec<CheckResult<Pass, Fail>> {
@@ -183,9 +447,12 @@ impl<A: RunApt> RunCheck<A> for AptCheck {
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
-        match (&self.condition, package.as_ref()) {
-            (Installed(PackageVersion::Specific(expected_version)), Some(got_version)) => {
-                if expected_version == got_version {
+        let mut results = match (&self.condition, package.as_ref()) {
+            (Installed(PackageVersion::Any), Some(_got_version)) => {
+                vec![ CheckResult::Passed(Pass::IsInstalled) ]
+            }
+            (Installed(version), Some(got_version)) => {
+                if version.matches(got_version) {
                     vec![ CheckResult::Passed(Pass::IsInstalled),
                           CheckResult::Passed(Pass::HasCorrectVersion { got_version: got_version.clone() }) ]
                 }
@@ -194,14 +461,8 @@ impl<A: RunApt> RunCheck<A> for AptCheck {
                           CheckResult::Failed(Fail::WrongVersion { got_version: got_version.clone() }) ]
                 }
             }
-            (Installed(PackageVersion::Specific(_expected_version)), None) => {
-                vec![ CheckResult::Failed(Fail::IsMissing) ]
-            }
-            (Installed(PackageVersion::Any), Some(_got_version)) => {
-                vec![ CheckResult::Passed(Pass::IsInstalled) ]
-            }
-            (Installed(PackageVersion::Any), None) => {
-                vec![ CheckResult::Failed(Fail::IsMissing) ]
+            (Installed(_), None) => {
+                return vec![ CheckResult::Failed(Fail::IsMissing) ];
             }
             (Missing, Some(_got_version)) => {
                 vec![ CheckResult::Failed(Fail::IsPresent) ]
@@ -209,7 +470,19 @@ impl<A: RunApt> RunCheck<A> for AptCheck {
             (Missing, None) => {
                 vec![ CheckResult::Passed(Pass::IsMissing) ]
             }
+        };
+
+        if let Some(expected_held) = self.held {
+            match apt.is_held(executor, &self.package_name.0) {
+                Ok(true) if expected_held   => results.push(CheckResult::Passed(Pass::IsHeld)),
+                Ok(true)                    => results.push(CheckResult::Failed(Fail::IsHeld)),
+                Ok(false) if expected_held  => results.push(CheckResult::Failed(Fail::IsNotHeld)),
+                Ok(false)                   => results.push(CheckResult::Passed(Pass::IsNotHeld)),
+                Err(e)                      => results.push(CheckResult::CommandError(e)),
+            }
         }
+
+        results
     }
 }
 
@@ -227,6 +500,12 @@ pub enum Pass {
     HasCorrectVersion {
         got_version: String,
     },
+
+    /// The package is being held back from upgrades.
+    IsHeld,
+
+    /// The package is not being held back from upgrades.
+    IsNotHeld,
 }
 
 /// The failure result of running an Apt check.
@@ -243,6 +522,12 @@ pub enum Fail {
     WrongVersion {
         got_version: String,
     },
+
+    /// The package was meant to be upgradable, but it is being held.
+    IsHeld,
+
+    /// The package was meant to be held, but it isn’t.
+    IsNotHeld,
 }
 
 impl PassResult for Pass {}
@@ -264,6 +549,12 @@ impl fmt::Display for Pass {
             Self::HasCorrectVersion { got_version } => {
                 write!(f, "version ‘{}’ is installed", got_version)
             }
+            Self::IsHeld => {
+                write!(f, "it is held")
+            }
+            Self::IsNotHeld => {
+                write!(f, "it is not held")
+            }
         }
     }
 }
@@ -280,6 +571,12 @@ impl fmt::Display for Fail {
             Self::WrongVersion { got_version } => {
                 write!(f, "version ‘{}’ is installed", got_version)
             }
+            Self::IsHeld => {
+                write!(f, "it is held")
+            }
+            Self::IsNotHeld => {
+                write!(f, "it is not held")
+            }
         }
     }
 }
@@ -0,0 +1,359 @@
+//! The Apt source check involves reading the list of repositories that Apt
+//! downloads packages from, and the keyrings it uses to verify them.
+//!
+//! # Check example
+//!
+//! ```toml
+//! [[apt_source]]
+//! uri = 'https://download.docker.com/linux/ubuntu'
+//! suite = 'focal'
+//! component = 'stable'
+//! key = '9DC8 5822 9FC7 DD38 854A  E2D8 8D81 803C 0EBF CD88'
+//! ```
+//!
+//! # Commands
+//!
+//! This check works by reading the files in `/etc/apt` that list the
+//! sources, in both the one-line and the deb822 formats. Keys are listed by
+//! running `gpg --show-keys` on the keyrings Apt uses.
+
+
+use std::fmt;
+use std::sync::Arc;
+
+use log::*;
+
+use spec_exec::{Executor, ExecError};
+
+use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
+use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
+
+
+/// A check against the list of Apt sources.
+#[derive(PartialEq, Debug)]
+pub struct AptSourceCheck {
+
+    /// The URI of the repository being checked.
+    uri: String,
+
+    /// The suite that should be used from the repository, if any.
+    suite: Option<String>,
+
+    /// The component that should be used from the suite, if any.
+    component: Option<String>,
+
+    /// The condition to test it with.
+    condition: Condition,
+}
+
+#[derive(PartialEq, Debug)]
+enum Condition {
+
+    /// Check that a matching source is present, optionally with its
+    /// signing key in a keyring.
+    Present(Option<KeyFingerprint>),
+
+    /// Check that no matching source is present.
+    Missing,
+}
+
+/// The fingerprint, or the long or short ID, of a key, as uppercase
+/// hexadecimal without any spaces.
+#[derive(PartialEq, Debug)]
+struct KeyFingerprint(String);
+
+/// One repository that Apt downloads binary packages from.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SourceEntry {
+
+    /// The URI of the repository.
+    pub uri: String,
+
+    /// The suite, which is often the name of a distribution release.
+    pub suite: String,
+
+    /// The components used from the suite.
+    pub components: Vec<String>,
+}
+
+
+// ---- the check description ----
+
+impl fmt::Display for AptSourceCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { uri, suite, component, condition } = &self;
+
+        write!(f, "Apt source ‘{}’", uri)?;
+
+        if let Some(suite) = suite {
+            write!(f, " suite ‘{}’", suite)?;
+        }
+
+        if let Some(component) = component {
+            write!(f, " component ‘{}’", component)?;
+        }
+
+        match condition {
+            Condition::Present(Some(key)) => {
+                write!(f, " is present with key ‘{}’", key.0)
+            }
+            Condition::Present(None) => {
+                write!(f, " is present")
+            }
+            Condition::Missing => {
+                write!(f, " is missing")
+            }
+        }
+    }
+}
+
+
+// ---- reading from TOML ----
+
+impl Check for AptSourceCheck {
+    const TYPE: &'static str = "apt_source";
+}
+
+impl AptSourceCheck {
+    pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        table.ensure_only_keys(&["uri", "suite", "component", "key", "state"])?;
+
+        let uri = read_nonempty_string(table.get_or_read_error("uri")?, "uri")?;
+        let suite = table.get("suite").map(|e| read_nonempty_string(e, "suite")).transpose()?;
+        let component = table.get("component").map(|e| read_nonempty_string(e, "component")).transpose()?;
+
+        if component.is_some() && suite.is_none() {
+            return Err(ReadError::MissingParameter { parameter_name: "suite" });
+        }
+
+        let condition = Condition::read(table)?;
+        Ok(Self { uri, suite, component, condition })
+    }
+}
+
+fn read_nonempty_string(value: &TomlValue, parameter_name: &'static str) -> Result<String, ReadError> {
+    let string = value.string_or_error(parameter_name)?;
+
+    if string.is_empty() {
+        Err(ReadError::invalid(parameter_name, value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(string)
+    }
+}
+
+impl Condition {
+    fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        let key = table.get("key").map(KeyFingerprint::read).transpose()?;
+
+        let state_value = match table.get("state") {
+            Some(s) => s,
+            None    => return Ok(Self::Present(key)),
+        };
+
+        match &state_value.string_or_error2("state", OneOf(&["present", "missing"]))?[..] {
+            "present" => {
+                Ok(Self::Present(key))
+            }
+            "missing" => {
+                if key.is_some() {
+                    Err(ReadError::conflict2("key", "state", state_value.clone()))
+                }
+                else {
+                    Ok(Self::Missing)
+                }
+            }
+            _ => {
+                Err(ReadError::invalid("state", state_value.clone(), OneOf(&["present", "missing"])))
+            }
+        }
+    }
+}
+
+impl KeyFingerprint {
+    fn read(key_value: &TomlValue) -> Result<Self, ReadError> {
+        let key = key_value.string_or_error("key")?;
+        let key = key.strip_prefix("0x").unwrap_or(&key);
+        let key = key.chars().filter(|c| ! c.is_whitespace()).collect::<String>();
+
+        if key.is_empty() {
+            Err(ReadError::invalid("key", key_value.clone(), "it must not be empty"))
+        }
+        else if ! key.chars().all(|c| c.is_ascii_hexdigit()) {
+            Err(ReadError::invalid("key", key_value.clone(), "it must be a hexadecimal fingerprint or key ID"))
+        }
+        else if key.len() < 8 {
+            Err(ReadError::invalid("key", key_value.clone(), "it must be at least eight characters long"))
+        }
+        else {
+            Ok(Self(key.to_ascii_uppercase()))
+        }
+    }
+
+    /// Whether this fingerprint or key ID refers to the key with the given
+    /// full fingerprint.
+    fn matches(&self, fingerprint: &str) -> bool {
+        fingerprint.to_ascii_uppercase().ends_with(&self.0)
+    }
+}
+
+impl AptSourceCheck {
+
+    /// Whether the given entry is for the repository being checked.
+    fn matches(&self, entry: &SourceEntry) -> bool {
+        if entry.uri.trim_end_matches('/') != self.uri.trim_end_matches('/') {
+            return false;
+        }
+
+        if let Some(suite) = &self.suite {
+            if &entry.suite != suite {
+                return false;
+            }
+        }
+
+        if let Some(component) = &self.component {
+            if ! entry.components.contains(component) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+
+// ---- running the check ----
+
+/// The interface to the local Apt configuration used by [`AptSourceCheck`].
+pub trait RunAptSources {
+
+    /// Prime the command for running, to read the list of sources.
+    fn prime(&mut self) { }
+
+    /// Prime the command for running, to list the keys in Apt’s keyrings.
+    fn prime_keys(&mut self) { }
+
+    /// Reading the sources files if they haven’t been read already, return
+    /// every binary package source that Apt has been configured with.
+    fn sources(&self) -> Vec<SourceEntry>;
+
+    /// Running the command if it hasn’t been run already, return the
+    /// fingerprints of every key in Apt’s keyrings.
+    fn key_fingerprints(&self, executor: &mut Executor) -> Result<Vec<String>, Arc<ExecError>>;
+}
+
+impl<A: RunAptSources> RunCheck<A> for AptSourceCheck {
+    type PASS = Pass;
+    type FAIL = Fail;
+
+    fn load(&self, apt: &mut A) {
+        apt.prime();
+
+        if let Condition::Present(Some(_)) = self.condition {
+            apt.prime_keys();
+        }
+    }
+
+    fn check(&self, executor: &mut Executor, apt: &A) -> Vec<CheckResult<Pass, Fail>> {
+        info!("Running check");
+
+        let present = apt.sources().iter().any(|entry| self.matches(entry));
+
+        match (&self.condition, present) {
+            (Condition::Present(_), false) => {
+                vec![ CheckResult::Failed(Fail::IsMissing) ]
+            }
+            (Condition::Present(None), true) => {
+                vec![ CheckResult::Passed(Pass::IsPresent) ]
+            }
+            (Condition::Present(Some(key)), true) => {
+                let fingerprints = match apt.key_fingerprints(executor) {
+                    Ok(fs)  => fs,
+                    Err(e)  => return vec![ CheckResult::Passed(Pass::IsPresent), CheckResult::CommandError(e) ],
+                };
+
+                if fingerprints.iter().any(|fingerprint| key.matches(fingerprint)) {
+                    vec![ CheckResult::Passed(Pass::IsPresent),
+                          CheckResult::Passed(Pass::KeyIsPresent) ]
+                }
+                else {
+                    vec![ CheckResult::Passed(Pass::IsPresent),
+                          CheckResult::Failed(Fail::KeyIsMissing) ]
+                }
+            }
+            (Condition::Missing, true) => {
+                vec![ CheckResult::Failed(Fail::IsPresent) ]
+            }
+            (Condition::Missing, false) => {
+                vec![ CheckResult::Passed(Pass::IsMissing) ]
+            }
+        }
+    }
+}
+
+/// The successful result of an Apt source check.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Pass {
+
+    /// A matching source is present.
+    IsPresent,
+
+    /// No matching source is present.
+    IsMissing,
+
+    /// The signing key is in one of Apt’s keyrings.
+    KeyIsPresent,
+}
+
+/// The failure result of running an Apt source check.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Fail {
+
+    /// The source was meant to be present, but it was missing.
+    IsMissing,
+
+    /// The source was meant to be missing, but it was present.
+    IsPresent,
+
+    /// The signing key is not in any of Apt’s keyrings.
+    KeyIsMissing,
+}
+
+impl PassResult for Pass {}
+
+impl FailResult for Fail {}
+
+
+// ---- check result descriptions ----
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IsPresent => {
+                write!(f, "it is present")
+            }
+            Self::IsMissing => {
+                write!(f, "it is missing")
+            }
+            Self::KeyIsPresent => {
+                write!(f, "the key is in a keyring")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Fail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IsMissing => {
+                write!(f, "it is missing")
+            }
+            Self::IsPresent => {
+                write!(f, "it is present")
+            }
+            Self::KeyIsMissing => {
+                write!(f, "the key is not in any keyring")
+            }
+        }
+    }
+}
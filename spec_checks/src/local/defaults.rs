@@ -8,17 +8,27 @@
 //! domain = "com.apple.Finder"
 //! key = "ShowExternalHardDrivesOnDesktop"
 //! value = "1"
+//!
+//! [[defaults]]
+//! domain = "com.apple.dock"
+//! key = "persistent-others"
+//! value = [ "Downloads", "Applications" ]
+//! current_host = true
 //! ```
 //!
 //! # Commands
 //!
 //! This check works by running `defaults` once per domain+key combination
-//! that needs to be checked.
+//! that needs to be checked. String values are compared against the output
+//! of `defaults read`; other values have their type checked with
+//! `defaults read-type` first, and arrays and dictionaries are read as JSON
+//! using `plutil`.
 
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
@@ -44,6 +54,10 @@ pub struct DefaultsLocation {
 
     /// The key to access the value at.
     pub key: String,
+
+    /// Whether to access the database for the current host, rather than
+    /// the one for every host.
+    pub current_host: bool,
 }
 
 /// Which database is being accessed.
@@ -58,12 +72,36 @@ pub enum DefaultsPlace {
 }
 
 
+/// A value in the defaults database.
+#[derive(PartialEq, Debug, Clone)]
+pub enum DefaultsValue {
+
+    /// A string value, which gets compared against the textual output of
+    /// `defaults read` without regard to its type.
+    String(String),
+
+    /// A boolean value.
+    Boolean(bool),
+
+    /// An integer value.
+    Integer(i64),
+
+    /// A floating-point value.
+    Float(f64),
+
+    /// An array of values.
+    Array(Vec<DefaultsValue>),
+
+    /// A dictionary of values.
+    Dictionary(BTreeMap<String, DefaultsValue>),
+}
+
 /// The condition we are checking about the value.
 #[derive(PartialEq, Debug)]
 enum Condition {
 
     /// It should exist, with the given value.
-    Present(String),
+    Present(DefaultsValue),
 
     /// It should be missing.
     Missing,
@@ -76,12 +114,18 @@ impl fmt::Display for DefaultsCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Self { location, condition } = &self;
 
+        write!(f, "Defaults value ‘{}/{}’", location.place, location.key)?;
+
+        if location.current_host {
+            write!(f, " for the current host")?;
+        }
+
         match condition {
             Condition::Present(value) => {
-                write!(f, "Defaults value ‘{}/{}’ is ‘{}’", location.place, location.key, value)?;
+                write!(f, " is ‘{}’", value)?;
             }
             Condition::Missing => {
-                write!(f, "Defaults value ‘{}/{}’ is absent", location.place, location.key)?;
+                write!(f, " is absent")?;
             }
         }
 
@@ -98,6 +142,47 @@ impl fmt::Display for DefaultsPlace {
     }
 }
 
+impl fmt::Display for DefaultsValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(string)    => write!(f, "{}", string),
+            Self::Boolean(boolean)  => write!(f, "{}", boolean),
+            Self::Integer(integer)  => write!(f, "{}", integer),
+            Self::Float(float)      => write!(f, "{:?}", float),
+            Self::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, " ")?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, " ]")
+            }
+            Self::Dictionary(values) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, " {} = ", key)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+impl DefaultsValue {
+
+    /// Formats a value inside an array or dictionary, where strings get
+    /// quoted so they can be told apart from other types.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(string)  => write!(f, "{:?}", string),
+            _                     => write!(f, "{}", self),
+        }
+    }
+}
+
 
 // ---- reading from TOML ----
 
@@ -107,7 +192,7 @@ impl Check for DefaultsCheck {
 
 impl DefaultsCheck {
     pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["domain", "key", "state", "value", "file"])?;
+        table.ensure_only_keys(&["domain", "key", "state", "value", "file", "current_host"])?;
 
         let location = DefaultsLocation::read(table, rewrites)?;
         let condition = Condition::read(table)?;
@@ -134,14 +219,16 @@ impl DefaultsLocation {
             return Err(ReadError::invalid("key", key_value.clone(), "it must not be empty"));
         }
 
+        let current_host = table.get("current_host").map(|e| e.boolean_or_error("current_host")).transpose()?.unwrap_or_default();
+
         match (domain, file) {
             (Some(domain), None) => {
                 let place = DefaultsPlace::Domain(domain);
-                Ok(Self { place, key })
+                Ok(Self { place, key, current_host })
             }
             (None, Some(file)) => {
                 let place = DefaultsPlace::File(rewrites.path(file));
-                Ok(Self { place, key })
+                Ok(Self { place, key, current_host })
             }
             (None, None) => {
                 // Recommend ‘domain’ because it’s the more common one
@@ -158,15 +245,9 @@ impl Condition {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
 
         let value = table.get("value").map(|v| {
-            if let Some(integer) = v.as_integer() {
-                Ok(integer.to_string())
-            }
-            else if let Some(string) = v.as_str() {
-                Ok(string.to_string())
-            }
-            else {
-                Err(ReadError::invalid("value", table.get("value").unwrap().clone(), "it must be a string or a number"))
-            }
+            DefaultsValue::read(v).ok_or_else(|| {
+                ReadError::invalid("value", v.clone(), "it must be a string, number, boolean, array, or table")
+            })
         }).transpose()?;
 
         if let Some(state_value) = table.get("state") {
@@ -198,6 +279,53 @@ impl Condition {
 }
 
 
+impl DefaultsValue {
+
+    /// Converts a TOML value into the defaults value it represents, or
+    /// nothing if it (or anything inside it) has no equivalent.
+    fn read(value: &TomlValue) -> Option<Self> {
+        match value {
+            TomlValue::String(string)    => Some(Self::String(string.clone())),
+            TomlValue::Boolean(boolean)  => Some(Self::Boolean(*boolean)),
+            TomlValue::Integer(integer)  => Some(Self::Integer(*integer)),
+            TomlValue::Float(float)      => Some(Self::Float(*float)),
+            TomlValue::Array(values) => {
+                values.iter().map(Self::read).collect::<Option<_>>().map(Self::Array)
+            }
+            TomlValue::Table(table) => {
+                table.iter().map(|(k, v)| Self::read(v).map(|v| (k.clone(), v)))
+                     .collect::<Option<_>>().map(Self::Dictionary)
+            }
+            TomlValue::Datetime(_) => None,
+        }
+    }
+
+    /// Whether this value is a string, which gets compared as text rather
+    /// than having its type checked.
+    fn is_string(&self) -> bool {
+        matches!(self, Self::String(_))
+    }
+
+    /// Whether the value read from the defaults database matches this one.
+    /// Integers and floats are compared by their numeric value.
+    pub fn matches(&self, got: &Self) -> bool {
+        #[allow(clippy::float_cmp, clippy::cast_precision_loss)]
+        match (self, got) {
+            (Self::Integer(i), Self::Float(f)) |
+            (Self::Float(f), Self::Integer(i)) => *i as f64 == *f,
+            (Self::Float(a), Self::Float(b))   => a == b,
+            (Self::Array(expected), Self::Array(got)) => {
+                expected.len() == got.len() && expected.iter().zip(got).all(|(e, g)| e.matches(g))
+            }
+            (Self::Dictionary(expected), Self::Dictionary(got)) => {
+                expected.len() == got.len() && expected.iter().all(|(k, e)| matches!(got.get(k), Some(g) if e.matches(g)))
+            }
+            (expected, got) => expected == got,
+        }
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local defaults database used by [`DefaultsCheck`].
@@ -207,9 +335,18 @@ pub trait RunDefaults {
     #[allow(unused)]
     fn prime(&mut self, location: &DefaultsLocation) { }
 
+    /// Prime the command for running, to access the given defaults location
+    /// along with its type.
+    #[allow(unused)]
+    fn prime_typed(&mut self, location: &DefaultsLocation) { }
+
     /// Running the command if it hasn't been run already, examines the
     /// output and returns it as a string.
-    fn get_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<Rc<str>>, Rc<ExecError>>;
+    fn get_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<Arc<str>>, Arc<ExecError>>;
+
+    /// Running the command if it hasn't been run already, examines the
+    /// output and returns it as a value of the type it has in the database.
+    fn get_typed_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<DefaultsValue>, Arc<ExecError>>;
 }
 
 impl<D: RunDefaults> RunCheck<D> for DefaultsCheck {
@@ -217,25 +354,38 @@ impl<D: RunDefaults> RunCheck<D> for DefaultsCheck {
     type FAIL = Fail;
 
     fn load(&self, defaults: &mut D) {
-        defaults.prime(&self.location);
+        match &self.condition {
+            Condition::Present(value) if ! value.is_string() => defaults.prime_typed(&self.location),
+            _                                                 => defaults.prime(&self.location),
+        }
     }
 
     fn check(&self, executor: &mut Executor, defaults: &D) -> Vec<CheckResult<Pass, Fail>> {
         use self::Condition::*;
         info!("Running check");
 
-        let value = match defaults.get_value(executor, &self.location) {
+        let value = match &self.condition {
+            Present(expected_value) if ! expected_value.is_string() => {
+                defaults.get_typed_value(executor, &self.location)
+            }
+            _ => {
+                defaults.get_value(executor, &self.location)
+                        .map(|v| v.map(|s| DefaultsValue::String(s.to_string())))
+            }
+        };
+
+        let value = match value {
             Ok(p)   => p,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
         match (&self.condition, value.as_ref()) {
             (Present(expected_value), Some(got_value)) => {
-                if expected_value == &**got_value {
+                if expected_value.matches(got_value) {
                     vec![ CheckResult::Passed(Pass::ValueMatches) ]
                 }
                 else {
-                    vec![ CheckResult::Failed(Fail::ValueMismatch { got_value: got_value.to_string() }) ]
+                    vec![ CheckResult::Failed(Fail::ValueMismatch { got_value: got_value.clone() }) ]
                 }
             }
             (Present(_expected_value), None) => {
@@ -268,7 +418,7 @@ pub enum Fail {
 
     /// The actual value did not match the expected value.
     ValueMismatch {
-        got_value: String,
+        got_value: DefaultsValue,
     },
 
     /// A value was meant to exist, but it's missing.
@@ -129,6 +129,7 @@ impl fmt::Display for FilesystemCheck {
                         Some(ContentsMatcher::FileMatch(path))             => write!(f, " has the contents of file ‘{}’", path.display())?,
                         Some(ContentsMatcher::ShouldBeEmpty)               => write!(f, " is empty")?,
                         Some(ContentsMatcher::ShouldBeNonEmpty)            => write!(f, " is not empty")?,
+                        Some(ContentsMatcher::LineCount(lines))            => write!(f, " has {} lines", lines)?,
                         None                                               => {/* nothing to match */},
                     }
                 }
@@ -431,6 +432,10 @@ pub trait LookupFile {
     fn read_file_contents(&self, path: &Path) -> Vec<u8>;
 
     fn lookup_link_target(&self, path: &Path) -> Result<PathBuf, IoError>;
+
+    /// Returns the directory that relative paths are looked up from, if
+    /// there is one.
+    fn base_directory(&self) -> Option<&Path> { None }
 }
 
 impl<F: LookupFile> BuiltInCheck<F> for FilesystemCheck {
@@ -556,7 +561,7 @@ impl FilesystemCheck {
 
                     if let Some(contents) = contents {
                         let read_contents = fs.read_file_contents(&self.input_path);
-                        match contents.check(&read_contents) {
+                        match contents.check(&read_contents, fs.base_directory()) {
                             CheckResult::Passed(pass) => {
                                 results.push(CheckResult::Passed(Pass::ContentsPass(pass)));
                             }
@@ -8,26 +8,40 @@
 //! gem = "sinatra"
 //! ```
 //!
+//! A version requirement can be given, as well as the path to a project
+//! to check the gems in its bundle rather than the ones installed globally:
+//!
+//! ```toml
+//! [[gem]]
+//! gem = "rails"
+//! version = "~> 6.0"
+//! path = "./webapp"
+//! ```
+//!
 //! # Commands
 //!
-//! This check works by running `gem`.
+//! This check works by running `gem`, or `bundle` for a project.
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
-use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
+use crate::read::{TomlValue, ValueExtras, ReadError, OneOf, Rewrites};
+use crate::semver::VersionRequirement;
 
 
 /// The Gem check checks the list of installed gems.
 #[derive(PartialEq, Debug)]
 pub struct GemCheck {
     gem_name: GemName,
+    project_path: Option<PathBuf>,
     condition: Condition,
 }
 
@@ -39,8 +53,9 @@ struct GemName(String);
 #[derive(PartialEq, Debug)]
 enum Condition {
 
-    /// The gem should be installed.
-    Installed,
+    /// The gem should be installed, optionally with a version that
+    /// satisfies a requirement.
+    Installed(Option<VersionRequirement>),
 
     /// The gem should be missing.
     Missing,
@@ -51,16 +66,25 @@ enum Condition {
 
 impl fmt::Display for GemCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { gem_name, condition } = &self;
+        let Self { gem_name, project_path, condition } = &self;
 
         match condition {
-            Condition::Installed => {
-                write!(f, "Gem ‘{}’ is installed", gem_name.0)
+            Condition::Installed(Some(version)) => {
+                write!(f, "Gem ‘{}’ version ‘{}’ is installed", gem_name.0, version)?;
+            }
+            Condition::Installed(None) => {
+                write!(f, "Gem ‘{}’ is installed", gem_name.0)?;
             }
             Condition::Missing => {
-                write!(f, "Gem ‘{}’ is not installed", gem_name.0)
+                write!(f, "Gem ‘{}’ is not installed", gem_name.0)?;
             }
         }
+
+        if let Some(path) = project_path {
+            write!(f, " in project ‘{}’", path.display())?;
+        }
+
+        Ok(())
     }
 }
 
@@ -72,12 +96,24 @@ impl Check for GemCheck {
 }
 
 impl GemCheck {
-    pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["gem", "state"])?;
+    pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        table.ensure_only_keys(&["gem", "state", "version", "path"])?;
 
         let gem_name = GemName::read(table)?;
+        let project_path = table.get("path").map(|e| read_project_path(e, rewrites)).transpose()?;
         let condition = Condition::read(table)?;
-        Ok(Self { gem_name, condition })
+        Ok(Self { gem_name, project_path, condition })
+    }
+}
+
+fn read_project_path(path_value: &TomlValue, rewrites: &Rewrites) -> Result<PathBuf, ReadError> {
+    let path = path_value.string_or_error("path")?;
+
+    if path.is_empty() {
+        Err(ReadError::invalid("path", path_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(rewrites.path(path))
     }
 }
 
@@ -103,17 +139,24 @@ impl GemName {
 
 impl Condition {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        let version = VersionRequirement::read(table)?;
+
         let state_value = match table.get("state") {
             Some(s) => s,
-            None    => return Ok(Self::Installed),
+            None    => return Ok(Self::Installed(version)),
         };
 
         match &state_value.string_or_error2("state", OneOf(&["installed", "missing"]))?[..] {
             "installed" => {
-                Ok(Self::Installed)
+                Ok(Self::Installed(version))
             }
             "missing" => {
-                Ok(Self::Missing)
+                if version.is_some() {
+                    Err(ReadError::conflict2("version", "state", state_value.clone()))
+                }
+                else {
+                    Ok(Self::Missing)
+                }
             }
             _ => {
                 Err(ReadError::invalid("state", state_value.clone(), OneOf(&["installed", "missing"])))
@@ -123,18 +166,28 @@ impl Condition {
 }
 
 
+// ---- analysis properties ----
+
+impl GemCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesPackage(&self.gem_name.0) ]
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local Rubygems database used by [`GemCheck`].
 pub trait RunGem {
 
-    /// Prime the command for running.
-    fn prime(&mut self) { }
+    /// Prime the command for running, either globally or in the project
+    /// at the given path.
+    fn prime(&mut self, _project_path: Option<&Path>) { }
 
     /// Running the command if it hasn’t been run already, consult the
-    /// database and return whether it says the given package is
-    /// installed.
-    fn find_gem(&self, executor: &mut Executor, gem_name: &str) -> Result<bool, Rc<ExecError>>;
+    /// database and return every installed version of the given gem,
+    /// which is empty if it is not installed.
+    fn find_gem(&self, executor: &mut Executor, project_path: Option<&Path>, gem_name: &str) -> Result<Vec<String>, Arc<ExecError>>;
 }
 
 impl<G: RunGem> RunCheck<G> for GemCheck {
@@ -142,29 +195,39 @@ impl<G: RunGem> RunCheck<G> for GemCheck {
     type FAIL = Fail;
 
     fn load(&self, gem: &mut G) {
-        gem.prime();
+        gem.prime(self.project_path.as_deref());
     }
 
     fn check(&self, executor: &mut Executor, gem: &G) -> Vec<CheckResult<Pass, Fail>> {
         use self::Condition::*;
         info!("Running check");
 
-        let gem = match gem.find_gem(executor, &self.gem_name.0) {
-            Ok(p)   => p,
+        let versions = match gem.find_gem(executor, self.project_path.as_deref(), &self.gem_name.0) {
+            Ok(vs)  => vs,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
-        match (&self.condition, gem) {
-            (Installed, true) => {
+        match (&self.condition, versions.is_empty()) {
+            (Installed(None), false) => {
                 vec![ CheckResult::Passed(Pass::IsInstalled) ]
             }
-            (Installed, false) => {
+            (Installed(Some(version)), false) => {
+                if let Some(got_version) = versions.iter().find(|v| version.matches(v)) {
+                    vec![ CheckResult::Passed(Pass::IsInstalled),
+                          CheckResult::Passed(Pass::HasCorrectVersion { got_version: got_version.clone() }) ]
+                }
+                else {
+                    vec![ CheckResult::Passed(Pass::IsInstalled),
+                          CheckResult::Failed(Fail::WrongVersions { got_versions: versions }) ]
+                }
+            }
+            (Installed(_), true) => {
                 vec![ CheckResult::Failed(Fail::IsMissing) ]
             }
-            (Missing, true) => {
+            (Missing, false) => {
                 vec![ CheckResult::Failed(Fail::IsInstalled) ]
             }
-            (Missing, false) => {
+            (Missing, true) => {
                 vec![ CheckResult::Passed(Pass::IsMissing) ]
             }
         }
@@ -172,18 +235,23 @@ impl<G: RunGem> RunCheck<G> for GemCheck {
 }
 
 /// The successful result of a Gem check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Pass {
 
     /// The gem is installed.
     IsInstalled,
 
+    /// A version of the gem that satisfies the requirement is installed.
+    HasCorrectVersion {
+        got_version: String,
+    },
+
     /// The gem is not installed.
     IsMissing,
 }
 
 /// The failure result of running a Gem check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Fail {
 
     /// The gem was meant to be installed, but it’s missing.
@@ -191,6 +259,12 @@ pub enum Fail {
 
     /// The gem was meant to be missing, but it’s installed.
     IsInstalled,
+
+    /// The gem is installed, but none of its versions satisfy the
+    /// requirement.
+    WrongVersions {
+        got_versions: Vec<String>,
+    },
 }
 
 impl PassResult for Pass {}
@@ -206,6 +280,9 @@ impl fmt::Display for Pass {
             Self::IsInstalled => {
                 write!(f, "it is installed")
             }
+            Self::HasCorrectVersion { got_version } => {
+                write!(f, "version ‘{}’ is installed", got_version)
+            }
             Self::IsMissing => {
                 write!(f, "it is not installed")
             }
@@ -222,6 +299,12 @@ impl fmt::Display for Fail {
             Self::IsInstalled => {
                 write!(f, "it is installed")
             }
+            Self::WrongVersions { got_versions } if got_versions.len() == 1 => {
+                write!(f, "version ‘{}’ is installed", got_versions[0])
+            }
+            Self::WrongVersions { got_versions } => {
+                write!(f, "versions ‘{}’ are installed", got_versions.join("’, ‘"))
+            }
         }
     }
 }
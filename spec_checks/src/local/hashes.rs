@@ -1,5 +1,5 @@
-//! The Hash check involves searching through the list of installed gems to see
-//! if one is installed.
+//! The Hash check involves hashing a file or a directory, and comparing the
+//! result against a known hash.
 //!
 //! # Check example
 //!
@@ -10,15 +10,38 @@
 //! hash = "d78abb0542736865f9470..."
 //! ```
 //!
+//! Directories can be hashed as well, and the expected hash of a file can
+//! be read from a checksum file in the format output by `sha256sum`:
+//!
+//! ```toml
+//! [[hash]]
+//! directory = "/opt/app/lib"
+//! algorithm = "sha256"
+//! hash = "9f86d081884c7d659a2f..."
+//!
+//! [[hash]]
+//! path = "/opt/app/app.tar.gz"
+//! algorithm = "sha256"
+//! checksum_file = "/opt/app/SHA256SUMS"
+//! ```
+//!
 //! # Commands
 //!
 //! This check works by running one of the checksum commands, such as `md5sum`
 //! or `sha256sum`, depending on the input algorithm.
+//!
+//! Directories are hashed by running the checksum command on every file in
+//! the tree, sorted by path, and then hashing that list of hashes. This is
+//! the same as running the following in the directory:
+//!
+//! ```sh
+//! find . -type f -print0 | LC_ALL=C sort -z | xargs -0 -r sha256sum | sha256sum
+//! ```
 
 
 use std::fmt;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
@@ -32,9 +55,31 @@ use crate::read::{TomlValue, ValueExtras, ReadError, Rewrites};
 /// hash matches an expected hash.
 #[derive(PartialEq, Debug)]
 pub struct HashCheck {
-    input_path: PathBuf,
+    input: HashInput,
     algorithm: Algorithm,
-    expected_hash: String,
+    expected_hash: ExpectedHash,
+}
+
+/// The thing being hashed.
+#[derive(PartialEq, Debug)]
+enum HashInput {
+
+    /// The contents of a single file.
+    File(PathBuf),
+
+    /// The manifest of every file in a directory tree.
+    Directory(PathBuf),
+}
+
+/// Where the hash being compared against comes from.
+#[derive(PartialEq, Debug)]
+enum ExpectedHash {
+
+    /// A hash given in the check.
+    Given(String),
+
+    /// The entry for the file in a checksum file.
+    ChecksumFile(PathBuf),
 }
 
 /// Which hashing algorithm to use.
@@ -46,6 +91,8 @@ pub enum Algorithm {
     SHA256,
     SHA384,
     SHA512,
+    BLAKE2,
+    BLAKE3,
 }
 
 
@@ -53,9 +100,25 @@ pub enum Algorithm {
 
 impl fmt::Display for HashCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { input_path, algorithm, expected_hash } = &self;
+        let Self { input, algorithm, expected_hash } = &self;
+
+        match input {
+            HashInput::File(path) => {
+                write!(f, "File ‘{}’", path.display())?;
+            }
+            HashInput::Directory(path) => {
+                write!(f, "Directory ‘{}’", path.display())?;
+            }
+        }
 
-        write!(f, "File ‘{}’ has {:?} hash ‘{}’", input_path.display(), algorithm, expected_hash)
+        match expected_hash {
+            ExpectedHash::Given(hash) => {
+                write!(f, " has {:?} hash ‘{}’", algorithm, hash)
+            }
+            ExpectedHash::ChecksumFile(checksum_path) => {
+                write!(f, " has the {:?} hash listed in ‘{}’", algorithm, checksum_path.display())
+            }
+        }
     }
 }
 
@@ -68,18 +131,62 @@ impl Check for HashCheck {
 
 impl HashCheck {
     pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["path", "algorithm", "hash"])?;
+        table.ensure_only_keys(&["path", "directory", "algorithm", "hash", "checksum_file"])?;
 
-        let input_value = table.get_or_read_error("path")?;
-        let input_path = input_value.string_or_error("path")?;
+        let input = HashInput::read(table, rewrites)?;
+        let algorithm = Algorithm::read(table)?;
+        let expected_hash = ExpectedHash::read(table, rewrites)?;
 
-        if input_path.is_empty() {
-            return Err(ReadError::invalid("path", input_value.clone(), "it must not be empty"));
+        if let (HashInput::Directory(_), ExpectedHash::ChecksumFile(_)) = (&input, &expected_hash) {
+            return Err(ReadError::conflict("checksum_file", "directory"));
         }
 
-        let algorithm = Algorithm::read(table)?;
-        let expected_hash = table.get_or_read_error("hash")?.string_or_error("hash")?;
-        Ok(Self { input_path: rewrites.path(input_path), algorithm, expected_hash })
+        Ok(Self { input, algorithm, expected_hash })
+    }
+}
+
+fn read_path(value: &TomlValue, parameter_name: &'static str, rewrites: &Rewrites) -> Result<PathBuf, ReadError> {
+    let path = value.string_or_error(parameter_name)?;
+
+    if path.is_empty() {
+        Err(ReadError::invalid(parameter_name, value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(rewrites.path(path))
+    }
+}
+
+impl HashInput {
+    fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        match (table.get("path"), table.get("directory")) {
+            (Some(_), Some(_)) => {
+                Err(ReadError::conflict("directory", "path"))
+            }
+            (None, Some(directory_value)) => {
+                Ok(Self::Directory(read_path(directory_value, "directory", rewrites)?))
+            }
+            (path_value, None) => {
+                let path_value = path_value.ok_or(ReadError::MissingParameter { parameter_name: "path" })?;
+                Ok(Self::File(read_path(path_value, "path", rewrites)?))
+            }
+        }
+    }
+}
+
+impl ExpectedHash {
+    fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        match (table.get("hash"), table.get("checksum_file")) {
+            (Some(_), Some(_)) => {
+                Err(ReadError::conflict("checksum_file", "hash"))
+            }
+            (None, Some(checksum_value)) => {
+                Ok(Self::ChecksumFile(read_path(checksum_value, "checksum_file", rewrites)?))
+            }
+            (hash_value, None) => {
+                let hash_value = hash_value.ok_or(ReadError::MissingParameter { parameter_name: "hash" })?;
+                Ok(Self::Given(hash_value.string_or_error("hash")?))
+            }
+        }
     }
 }
 
@@ -94,6 +201,8 @@ impl Algorithm {
             "sha256" => Ok(Self::SHA256),
             "sha384" => Ok(Self::SHA384),
             "sha512" => Ok(Self::SHA512),
+            "blake2" | "blake2b" => Ok(Self::BLAKE2),
+            "blake3" => Ok(Self::BLAKE3),
             _        => Err(ReadError::invalid("algorithm", algo_value.clone(), "it must be an algorithm such as ‘MD5’, ‘SHA256’...")),
         }
     }
@@ -107,7 +216,18 @@ pub trait RunHash {
     #[allow(unused)]
     fn prime(&mut self, path: &Path, algorithm: Algorithm) { }
 
-    fn hash_file(&self, executor: &mut Executor, path: PathBuf, algorithm: Algorithm) -> Result<String, Rc<ExecError>>;
+    #[allow(unused)]
+    fn prime_directory(&mut self, path: &Path, algorithm: Algorithm) { }
+
+    fn hash_file(&self, executor: &mut Executor, path: PathBuf, algorithm: Algorithm) -> Result<String, Arc<ExecError>>;
+
+    /// Running the command if it hasn’t been run already, return the hash
+    /// of the manifest of every file in the directory.
+    fn hash_directory(&self, executor: &mut Executor, path: &Path, algorithm: Algorithm) -> Result<String, Arc<ExecError>>;
+
+    /// Reading the checksum file, return the hash listed in it for the file
+    /// at the given path, if there is one.
+    fn checksum_entry(&self, checksum_path: &Path, path: &Path) -> Option<String>;
 }
 
 impl<H: RunHash> RunCheck<H> for HashCheck {
@@ -115,18 +235,41 @@ impl<H: RunHash> RunCheck<H> for HashCheck {
     type FAIL = Fail;
 
     fn load(&self, hash: &mut H) {
-        hash.prime(&self.input_path, self.algorithm);
+        match &self.input {
+            HashInput::File(path)       => hash.prime(path, self.algorithm),
+            HashInput::Directory(path)  => hash.prime_directory(path, self.algorithm),
+        }
     }
 
     fn check(&self, executor: &mut Executor, hash: &H) -> Vec<CheckResult<Pass, Fail>> {
         info!("Running check");
 
-        let result_hash = match hash.hash_file(executor, self.input_path.clone(), self.algorithm) {
+        let expected_hash = match (&self.expected_hash, &self.input) {
+            (ExpectedHash::Given(expected_hash), _) => {
+                expected_hash.clone()
+            }
+            (ExpectedHash::ChecksumFile(checksum_path), HashInput::File(path)) => {
+                match hash.checksum_entry(checksum_path, path) {
+                    Some(h) => h,
+                    None    => return vec![ CheckResult::Failed(Fail::NotInChecksumFile) ],
+                }
+            }
+            (ExpectedHash::ChecksumFile(_), HashInput::Directory(_)) => {
+                unreachable!()  // rejected when reading
+            }
+        };
+
+        let result_hash = match &self.input {
+            HashInput::File(path)       => hash.hash_file(executor, path.clone(), self.algorithm),
+            HashInput::Directory(path)  => hash.hash_directory(executor, path, self.algorithm),
+        };
+
+        let result_hash = match result_hash {
             Ok(p)   => p,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
-        if self.expected_hash == result_hash {
+        if expected_hash == result_hash {
             vec![ CheckResult::Passed(Pass::HashesMatch) ]
         }
         else {
@@ -152,6 +295,9 @@ pub enum Fail {
 
     /// The output and input hashes do not match.
     HashMismatch,
+
+    /// The checksum file has no entry for the file being hashed.
+    NotInChecksumFile,
 }
 
 impl PassResult for Pass {}
@@ -180,6 +326,9 @@ impl fmt::Display for Fail {
             Self::HashMismatch => {
                 write!(f, "hash mismatch")
             }
+            Self::NotInChecksumFile => {
+                write!(f, "it is not listed in the checksum file")
+            }
         }
     }
 }
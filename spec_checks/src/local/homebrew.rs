@@ -7,18 +7,25 @@
 //! [[homebrew]]
 //! cask = "exa"
 //! state = "installed"
+//!
+//! [[homebrew]]
+//! formula = "postgresql"
+//! pinned = true
+//! linked = true
 //! ```
 //!
 //! # Commands
 //!
-//! This check works by running `brew`.
+//! This check works by running `brew list`, and `brew info` to find out
+//! whether a formula is pinned, outdated, or linked.
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
@@ -34,6 +41,15 @@ pub struct HomebrewCheck {
 
     /// The condition to test it with.
     condition: Condition,
+
+    /// Whether the formula should be pinned, if that is being checked.
+    pinned: Option<bool>,
+
+    /// Whether the formula should be outdated, if that is being checked.
+    outdated: Option<bool>,
+
+    /// Whether the formula should be linked, if that is being checked.
+    linked: Option<bool>,
 }
 
 /// The name of the formula we are checking.
@@ -56,16 +72,47 @@ enum Condition {
 
 impl fmt::Display for HomebrewCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { formula_name, condition } = &self;
+        let Self { formula_name, condition, pinned, outdated, linked } = &self;
+
+        if *condition == Condition::Missing {
+            return write!(f, "Formula ‘{}’ is not installed", formula_name.0);
+        }
+
+        write!(f, "Formula ‘{}’ is installed", formula_name.0)?;
+
+        let mut extras = Vec::new();
+
+        match pinned {
+            Some(true)   => extras.push("pinned"),
+            Some(false)  => extras.push("not pinned"),
+            None         => {},
+        }
 
-        match condition {
-            Condition::Installed => {
-                write!(f, "Formula ‘{}’ is installed", formula_name.0)
+        match outdated {
+            Some(true)   => extras.push("outdated"),
+            Some(false)  => extras.push("up to date"),
+            None         => {},
+        }
+
+        match linked {
+            Some(true)   => extras.push("linked"),
+            Some(false)  => extras.push("not linked"),
+            None         => {},
+        }
+
+        for (index, extra) in extras.iter().enumerate() {
+            if index + 1 < extras.len() {
+                write!(f, ", {}", extra)?;
+            }
+            else if extras.len() == 1 {
+                write!(f, " and {}", extra)?;
             }
-            Condition::Missing => {
-                write!(f, "Formula ‘{}’ is not installed", formula_name.0)
+            else {
+                write!(f, ", and {}", extra)?;
             }
         }
+
+        Ok(())
     }
 }
 
@@ -78,11 +125,23 @@ impl Check for HomebrewCheck {
 
 impl HomebrewCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["formula", "state"])?;
+        table.ensure_only_keys(&["formula", "state", "pinned", "outdated", "linked"])?;
 
         let formula_name = FormulaName::read(table)?;
         let condition = Condition::read(table)?;
-        Ok(Self { formula_name, condition })
+        let pinned = table.get("pinned").map(|e| e.boolean_or_error("pinned")).transpose()?;
+        let outdated = table.get("outdated").map(|e| e.boolean_or_error("outdated")).transpose()?;
+        let linked = table.get("linked").map(|e| e.boolean_or_error("linked")).transpose()?;
+
+        if condition == Condition::Missing {
+            for (parameter_name, value) in &[ ("pinned", pinned), ("outdated", outdated), ("linked", linked) ] {
+                if value.is_some() {
+                    return Err(ReadError::conflict2(parameter_name, "state", table["state"].clone()));
+                }
+            }
+        }
+
+        Ok(Self { formula_name, condition, pinned, outdated, linked })
     }
 }
 
@@ -128,6 +187,15 @@ impl Condition {
 }
 
 
+// ---- analysis properties ----
+
+impl HomebrewCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesPackage(&self.formula_name.0) ]
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local Homebrew database used by [`HomebrewCheck`].
@@ -136,10 +204,47 @@ pub trait RunBrew {
     /// Primes the command for running.
     fn prime(&mut self) { }
 
+    /// Primes the command for running, to get details about the installed
+    /// formulas.
+    fn prime_details(&mut self) { }
+
     /// Running the database if it hasn’t been run already, consults the
     /// list of packages and returns whether the formula with the given
     /// name is installed.
-    fn find_formula(&self, executor: &mut Executor, formula_name: &str) -> Result<bool, Rc<ExecError>>;
+    fn find_formula(&self, executor: &mut Executor, formula_name: &str) -> Result<bool, Arc<ExecError>>;
+
+    /// Running the database if it hasn’t been run already, returns the
+    /// details of the installed formula with the given name, if it is
+    /// installed.
+    fn formula_details(&self, executor: &mut Executor, formula_name: &str) -> Result<Option<FormulaDetails>, Arc<ExecError>>;
+}
+
+/// Details about an installed formula, as reported by Homebrew.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct FormulaDetails {
+
+    /// The versions of the formula that are installed.
+    pub installed_versions: Vec<String>,
+
+    /// The latest version of the formula that Homebrew knows about.
+    pub latest_version: Option<String>,
+
+    /// The version of the formula that is linked, if any.
+    pub linked_version: Option<String>,
+
+    /// Whether the formula is pinned to its current version.
+    pub pinned: bool,
+
+    /// Whether a newer version of the formula is available.
+    pub outdated: bool,
+}
+
+impl FormulaDetails {
+
+    /// The most recently installed version, for use in messages.
+    fn installed_version(&self) -> String {
+        self.installed_versions.last().cloned().unwrap_or_default()
+    }
 }
 
 impl<BC: RunBrew> RunCheck<BC> for HomebrewCheck {
@@ -149,6 +254,10 @@ impl<BC: RunBrew> RunCheck<BC> for HomebrewCheck {
     fn load(&self, brew: &mut BC) {
         debug!("Priming brew command");
         brew.prime();
+
+        if self.pinned.is_some() || self.outdated.is_some() || self.linked.is_some() {
+            brew.prime_details();
+        }
     }
 
     fn check(&self, executor: &mut Executor, apt: &BC) -> Vec<CheckResult<Pass, Fail>> {
@@ -162,7 +271,17 @@ impl<BC: RunBrew> RunCheck<BC> for HomebrewCheck {
 
         match (&self.condition, formula) {
             (Installed, true) => {
-                vec![ CheckResult::Passed(Pass::IsInstalled) ]
+                let mut results = vec![ CheckResult::Passed(Pass::IsInstalled) ];
+
+                if self.pinned.is_some() || self.outdated.is_some() || self.linked.is_some() {
+                    match apt.formula_details(executor, &self.formula_name.0) {
+                        Ok(Some(details))  => results.extend(self.check_details(&details)),
+                        Ok(None)           => results.push(CheckResult::Failed(Fail::NoDetails)),
+                        Err(e)             => results.push(CheckResult::CommandError(e)),
+                    }
+                }
+
+                results
             }
             (Installed, false) => {
                 vec![ CheckResult::Failed(Fail::IsMissing) ]
@@ -177,9 +296,50 @@ impl<BC: RunBrew> RunCheck<BC> for HomebrewCheck {
     }
 }
 
+impl HomebrewCheck {
+
+    /// Compares the details of an installed formula against the pinned,
+    /// outdated, and linked conditions that are being checked.
+    fn check_details(&self, details: &FormulaDetails) -> Vec<CheckResult<Pass, Fail>> {
+        let mut results = Vec::new();
+
+        if let Some(expected_pinned) = self.pinned {
+            results.push(match (expected_pinned, details.pinned) {
+                (true,  true)   => CheckResult::Passed(Pass::IsPinned(details.installed_version())),
+                (true,  false)  => CheckResult::Failed(Fail::IsNotPinned),
+                (false, true)   => CheckResult::Failed(Fail::IsPinned(details.installed_version())),
+                (false, false)  => CheckResult::Passed(Pass::IsNotPinned),
+            });
+        }
+
+        if let Some(expected_outdated) = self.outdated {
+            let installed = details.installed_version();
+            let latest = details.latest_version.clone().unwrap_or_default();
+
+            results.push(match (expected_outdated, details.outdated) {
+                (true,  true)   => CheckResult::Passed(Pass::IsOutdated { installed, latest }),
+                (true,  false)  => CheckResult::Failed(Fail::IsUpToDate(installed)),
+                (false, true)   => CheckResult::Failed(Fail::IsOutdated { installed, latest }),
+                (false, false)  => CheckResult::Passed(Pass::IsUpToDate(installed)),
+            });
+        }
+
+        if let Some(expected_linked) = self.linked {
+            results.push(match (expected_linked, &details.linked_version) {
+                (true,  Some(version))  => CheckResult::Passed(Pass::IsLinked(version.clone())),
+                (true,  None)           => CheckResult::Failed(Fail::IsNotLinked),
+                (false, Some(version))  => CheckResult::Failed(Fail::IsLinked(version.clone())),
+                (false, None)           => CheckResult::Passed(Pass::IsNotLinked),
+            });
+        }
+
+        results
+    }
+}
+
 
 /// The successful result of an Homebrew check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug)]
 pub enum Pass {
 
     /// The formula is installed.
@@ -187,10 +347,31 @@ pub enum Pass {
 
     /// The formula is not installed.
     IsMissing,
+
+    /// The formula is pinned at this version.
+    IsPinned(String),
+
+    /// The formula is not pinned.
+    IsNotPinned,
+
+    /// The installed version is older than the latest one.
+    IsOutdated {
+        installed: String,
+        latest: String,
+    },
+
+    /// The installed version is the latest one.
+    IsUpToDate(String),
+
+    /// The formula is linked at this version.
+    IsLinked(String),
+
+    /// The formula is not linked.
+    IsNotLinked,
 }
 
 /// The failure result of running an Homebrew check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug)]
 pub enum Fail {
 
     /// The formula was meant to be installed, but it was missing.
@@ -198,6 +379,35 @@ pub enum Fail {
 
     /// The formula was meant to be _not_ installed, but it was installed.
     IsInstalled,
+
+    /// The formula was listed as installed, but Homebrew had no details
+    /// about it.
+    NoDetails,
+
+    /// The formula was meant to be upgradable, but it is pinned at this
+    /// version.
+    IsPinned(String),
+
+    /// The formula was meant to be pinned, but it isn’t.
+    IsNotPinned,
+
+    /// The formula was meant to be up to date, but a newer version is
+    /// available.
+    IsOutdated {
+        installed: String,
+        latest: String,
+    },
+
+    /// The formula was meant to be outdated, but it is at this version,
+    /// which is the latest.
+    IsUpToDate(String),
+
+    /// The formula was meant to be unlinked, but it is linked at this
+    /// version.
+    IsLinked(String),
+
+    /// The formula was meant to be linked, but it isn’t.
+    IsNotLinked,
 }
 
 impl PassResult for Pass {}
@@ -216,6 +426,24 @@ impl fmt::Display for Pass {
             Self::IsMissing => {
                 write!(f, "it is not installed")
             }
+            Self::IsPinned(version) => {
+                write!(f, "it is pinned at version ‘{}’", version)
+            }
+            Self::IsNotPinned => {
+                write!(f, "it is not pinned")
+            }
+            Self::IsOutdated { installed, latest } => {
+                write!(f, "version ‘{}’ is installed, and ‘{}’ is available", installed, latest)
+            }
+            Self::IsUpToDate(version) => {
+                write!(f, "version ‘{}’ is the latest", version)
+            }
+            Self::IsLinked(version) => {
+                write!(f, "version ‘{}’ is linked", version)
+            }
+            Self::IsNotLinked => {
+                write!(f, "it is not linked")
+            }
         }
     }
 }
@@ -229,6 +457,27 @@ impl fmt::Display for Fail {
             Self::IsInstalled => {
                 write!(f, "it is installed")
             }
+            Self::NoDetails => {
+                write!(f, "Homebrew has no details about it")
+            }
+            Self::IsPinned(version) => {
+                write!(f, "it is pinned at version ‘{}’", version)
+            }
+            Self::IsNotPinned => {
+                write!(f, "it is not pinned")
+            }
+            Self::IsOutdated { installed, latest } => {
+                write!(f, "version ‘{}’ is installed, but ‘{}’ is available", installed, latest)
+            }
+            Self::IsUpToDate(version) => {
+                write!(f, "version ‘{}’ is the latest", version)
+            }
+            Self::IsLinked(version) => {
+                write!(f, "version ‘{}’ is linked", version)
+            }
+            Self::IsNotLinked => {
+                write!(f, "it is not linked")
+            }
         }
     }
 }
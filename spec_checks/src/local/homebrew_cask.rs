@@ -15,10 +15,11 @@
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
@@ -122,6 +123,15 @@ impl Condition {
 }
 
 
+// ---- analysis properties ----
+
+impl HomebrewCaskCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesPackage(&self.cask_name.0) ]
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local Homebrew Cask list.
@@ -132,7 +142,7 @@ pub trait RunBrewCask {
 
     /// Running the command if it hasn't been run already, consults the
     /// list and returns whether a cask with the given name is present.
-    fn find_cask(&self, executor: &mut Executor, cask_name: &str) -> Result<bool, Rc<ExecError>>;
+    fn find_cask(&self, executor: &mut Executor, cask_name: &str) -> Result<bool, Arc<ExecError>>;
 }
 
 impl<BC: RunBrewCask> RunCheck<BC> for HomebrewCaskCheck {
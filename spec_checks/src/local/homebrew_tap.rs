@@ -15,7 +15,7 @@
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
@@ -129,7 +129,7 @@ pub trait RunBrewTap {
     /// Running the command if it hasn’t been run already, consults the
     /// database and returns whether a tap with the given name is
     /// present.
-    fn find_tap(&self, executor: &mut Executor, tap_name: &str) -> Result<bool, Rc<ExecError>>;
+    fn find_tap(&self, executor: &mut Executor, tap_name: &str) -> Result<bool, Arc<ExecError>>;
 }
 
 impl<BT: RunBrewTap> RunCheck<BT> for HomebrewTapCheck {
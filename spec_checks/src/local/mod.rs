@@ -1,4 +1,5 @@
 pub mod apt;
+pub mod apt_source;
 pub mod defaults;
 pub mod fs;
 pub mod gem;
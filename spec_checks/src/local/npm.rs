@@ -8,26 +8,40 @@
 //! package = "typescript"
 //! ```
 //!
+//! A version requirement can be given, as well as the path to a project
+//! to check its local `node_modules` rather than the global packages:
+//!
+//! ```toml
+//! [[npm]]
+//! package = "lodash"
+//! version = "^4.17"
+//! path = "./webapp"
+//! ```
+//!
 //! # Commands
 //!
 //! This check works by running `npm`.
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
-use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
+use crate::read::{TomlValue, ValueExtras, ReadError, OneOf, Rewrites};
+use crate::semver::VersionRequirement;
 
 
 /// A check against the installed npm packages list.
 #[derive(PartialEq, Debug)]
 pub struct NpmCheck {
     package_name: PackageName,
+    project_path: Option<PathBuf>,
     condition: Condition,
 }
 
@@ -39,8 +53,9 @@ struct PackageName(String);
 #[derive(PartialEq, Debug)]
 enum Condition {
 
-    /// We expect it to be installed.
-    Installed,
+    /// We expect it to be installed, optionally with a version that
+    /// satisfies a requirement.
+    Installed(Option<VersionRequirement>),
 
     /// We expect it to be missing.
     Missing,
@@ -51,16 +66,25 @@ enum Condition {
 
 impl fmt::Display for NpmCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { package_name, condition } = &self;
+        let Self { package_name, project_path, condition } = &self;
 
         match condition {
-            Condition::Installed => {
-                write!(f, "Package ‘{}’ is installed", package_name.0)
+            Condition::Installed(Some(version)) => {
+                write!(f, "Package ‘{}’ version ‘{}’ is installed", package_name.0, version)?;
+            }
+            Condition::Installed(None) => {
+                write!(f, "Package ‘{}’ is installed", package_name.0)?;
             }
             Condition::Missing => {
-                write!(f, "Package ‘{}’ is not installed", package_name.0)
+                write!(f, "Package ‘{}’ is not installed", package_name.0)?;
             }
         }
+
+        if let Some(path) = project_path {
+            write!(f, " in project ‘{}’", path.display())?;
+        }
+
+        Ok(())
     }
 }
 
@@ -72,12 +96,13 @@ impl Check for NpmCheck {
 }
 
 impl NpmCheck {
-    pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["package", "state", "version"])?;
+    pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        table.ensure_only_keys(&["package", "state", "version", "path"])?;
 
         let package_name = PackageName::read(table)?;
+        let project_path = table.get("path").map(|e| read_project_path(e, rewrites)).transpose()?;
         let condition = Condition::read(table)?;
-        Ok(Self { package_name, condition })
+        Ok(Self { package_name, project_path, condition })
     }
 }
 
@@ -95,19 +120,37 @@ impl PackageName {
     }
 }
 
+fn read_project_path(path_value: &TomlValue, rewrites: &Rewrites) -> Result<PathBuf, ReadError> {
+    let path = path_value.string_or_error("path")?;
+
+    if path.is_empty() {
+        Err(ReadError::invalid("path", path_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(rewrites.path(path))
+    }
+}
+
 impl Condition {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        let version = VersionRequirement::read(table)?;
+
         let state = match table.get("state") {
             Some(s) => s,
-            None    => return Ok(Self::Installed),
+            None    => return Ok(Self::Installed(version)),
         };
 
         match &state.string_or_error2("state", OneOf(&["installed", "missing"]))?[..] {
             "installed" | "present" => {
-                Ok(Self::Installed)
+                Ok(Self::Installed(version))
             }
             "uninstalled" | "missing" => {
-                Ok(Self::Missing)
+                if version.is_some() {
+                    Err(ReadError::conflict2("version", "state", state.clone()))
+                }
+                else {
+                    Ok(Self::Missing)
+                }
             }
             _ => {
                 Err(ReadError::invalid("state", state.clone(), OneOf(&["installed", "missing"])))
@@ -117,18 +160,28 @@ impl Condition {
 }
 
 
+// ---- analysis properties ----
+
+impl NpmCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesPackage(&self.package_name.0) ]
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local npm package database used by [`NpmCheck`].
 pub trait RunNpm {
 
-    /// Prime the command for running.
-    fn prime(&mut self) { }
+    /// Prime the command for running, either globally or in the project
+    /// at the given path.
+    fn prime(&mut self, _project_path: Option<&Path>) { }
 
-    /// Running the command if it hasn’t been run already, consul the
-    /// database and return whether a package with the given name is
-    /// installed.
-    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<bool, Rc<ExecError>>;
+    /// Running the command if it hasn’t been run already, consult the
+    /// database and return the version of the package with the given name
+    /// if it is installed.
+    fn find_package(&self, executor: &mut Executor, project_path: Option<&Path>, package_name: &str) -> Result<Option<String>, Arc<ExecError>>;
 }
 
 impl<N: RunNpm> RunCheck<N> for NpmCheck {
@@ -136,29 +189,39 @@ impl<N: RunNpm> RunCheck<N> for NpmCheck {
     type FAIL = Fail;
 
     fn load(&self, npm: &mut N) {
-        npm.prime();
+        npm.prime(self.project_path.as_deref());
     }
 
     fn check(&self, executor: &mut Executor, npm: &N) -> Vec<CheckResult<Pass, Fail>> {
         use self::Condition::*;
         info!("Running check");
 
-        let package = match npm.find_package(executor, &self.package_name.0) {
+        let package = match npm.find_package(executor, self.project_path.as_deref(), &self.package_name.0) {
             Ok(p)   => p,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
         match (&self.condition, package) {
-            (Installed, true) => {
+            (Installed(None), Some(_got_version)) => {
                 vec![ CheckResult::Passed(Pass::IsInstalled) ]
             }
-            (Installed, false) => {
+            (Installed(Some(version)), Some(got_version)) => {
+                if version.matches(&got_version) {
+                    vec![ CheckResult::Passed(Pass::IsInstalled),
+                          CheckResult::Passed(Pass::HasCorrectVersion { got_version }) ]
+                }
+                else {
+                    vec![ CheckResult::Passed(Pass::IsInstalled),
+                          CheckResult::Failed(Fail::WrongVersion { got_version }) ]
+                }
+            }
+            (Installed(_), None) => {
                 vec![ CheckResult::Failed(Fail::IsMissing) ]
             }
-            (Missing, true) => {
+            (Missing, Some(_got_version)) => {
                 vec![ CheckResult::Failed(Fail::IsInstalled) ]
             }
-            (Missing, false) => {
+            (Missing, None) => {
                 vec![ CheckResult::Passed(Pass::IsMissing) ]
             }
         }
@@ -166,18 +229,24 @@ impl<N: RunNpm> RunCheck<N> for NpmCheck {
 }
 
 /// The successful result of an npm check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Pass {
 
     /// The package is installed.
     IsInstalled,
 
+    /// The package is installed with a version that satisfies the
+    /// requirement.
+    HasCorrectVersion {
+        got_version: String,
+    },
+
     /// The package is missing.
     IsMissing,
 }
 
 /// The failure result of running an npm check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Fail {
 
     /// The package is missing, but was meant to be installed.
@@ -185,6 +254,12 @@ pub enum Fail {
 
     /// The package is installed, but was meant to be missing.
     IsInstalled,
+
+    /// The package is installed, but its version does not satisfy the
+    /// requirement.
+    WrongVersion {
+        got_version: String,
+    },
 }
 
 impl PassResult for Pass {}
@@ -200,6 +275,9 @@ impl fmt::Display for Pass {
             Self::IsInstalled => {
                 write!(f, "it is installed")
             }
+            Self::HasCorrectVersion { got_version } => {
+                write!(f, "version ‘{}’ is installed", got_version)
+            }
             Self::IsMissing => {
                 write!(f, "it is not installed")
             }
@@ -216,6 +294,9 @@ impl fmt::Display for Fail {
             Self::IsInstalled => {
                 write!(f, "it is installed")
             }
+            Self::WrongVersion { got_version } => {
+                write!(f, "version ‘{}’ is installed", got_version)
+            }
         }
     }
 }
@@ -5,21 +5,35 @@
 //! ```toml
 //! [[systemd]]
 //! service = 'consul'
+//! enablement = 'enabled'
+//! properties = { Restart = 'always' }
+//!
+//! [[systemd]]
+//! timer = 'backup'
+//! scope = 'user'
+//! next_elapse_within = '24h'
+//! last_result = 'success'
 //! ```
 //!
 //! # Commands
 //!
-//! This check works by running the `systemctl` command.
+//! This check works by running the `systemctl` command: `systemctl status`
+//! for the state of the service, and `systemctl show` for its properties.
+//! User units are queried with `systemctl --user`.
 
 
+use std::collections::BTreeMap;
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
+use crate::common::parse_duration;
 use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
 
 
@@ -27,15 +41,58 @@ use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
 #[derive(PartialEq, Debug)]
 pub struct SystemdCheck {
 
-    /// The name of the service being checked.
-    service_name: ServiceName,
+    /// The name of the unit being checked.
+    unit_name: UnitName,
+
+    /// Whether the unit is a system unit or a user unit.
+    scope: Scope,
+
+    /// The condition to test it with, if its state is being checked.
+    condition: Option<Condition>,
+
+    /// Whether the service should be enabled, if that is being checked.
+    enablement: Option<Enablement>,
+
+    /// Properties of the unit that should have certain values.
+    properties: BTreeMap<String, String>,
 
-    /// The condition to test it with.
-    condition: Condition,
+    /// How soon a timer should next elapse, if that is being checked.
+    next_elapse: Option<NextElapse>,
+
+    /// The result that the service triggered by a timer should have last
+    /// finished with, if that is being checked.
+    last_result: Option<String>,
 }
 
+/// The name of a unit, and which type of unit it is.
 #[derive(PartialEq, Debug)]
-struct ServiceName(String);
+enum UnitName {
+
+    /// A service, which gets checked using the name as given.
+    Service(String),
+
+    /// A timer, which gets checked with the `.timer` suffix added.
+    Timer(String),
+}
+
+/// Which instance of systemd a unit belongs to.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Scope {
+
+    /// The system-wide instance, queried with plain `systemctl`.
+    System,
+
+    /// The current user’s instance, queried with `systemctl --user`.
+    User,
+}
+
+/// The maximum amount of time until a timer should next elapse, along
+/// with the duration as it was written in the check.
+#[derive(PartialEq, Debug)]
+struct NextElapse {
+    within: Duration,
+    as_written: String,
+}
 
 #[derive(PartialEq, Debug)]
 enum Condition {
@@ -50,24 +107,98 @@ enum Condition {
     Missing,
 }
 
+/// Whether a service’s unit file is enabled, as reported by systemd in its
+/// `UnitFileState` property.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Enablement {
+
+    /// The service gets started automatically.
+    Enabled,
+
+    /// The service does not get started automatically.
+    Disabled,
+
+    /// The service has been masked, so it cannot be started at all.
+    Masked,
+
+    /// The service has no install section, so it cannot be enabled, but
+    /// may be started by other units.
+    Static,
+}
+
 
 // ---- the check description ----
 
 impl fmt::Display for SystemdCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { service_name, condition } = &self;
+        let Self { unit_name, scope, condition, enablement, properties, next_elapse, last_result } = &self;
+
+        match (scope, unit_name) {
+            (Scope::System, UnitName::Service(name))  => write!(f, "Service ‘{}’", name)?,
+            (Scope::System, UnitName::Timer(name))    => write!(f, "Timer ‘{}’", name)?,
+            (Scope::User,   UnitName::Service(name))  => write!(f, "User service ‘{}’", name)?,
+            (Scope::User,   UnitName::Timer(name))    => write!(f, "User timer ‘{}’", name)?,
+        }
 
         match condition {
-            Condition::Running => {
-                write!(f, "Service ‘{}’ is running", service_name.0)
+            Some(Condition::Running)  => write!(f, " is running")?,
+            Some(Condition::Stopped)  => write!(f, " is stopped")?,
+            Some(Condition::Missing)  => write!(f, " is missing")?,
+            None                      => {/* not checking the state */},
+        }
+
+        if let Some(enablement) = enablement {
+            if condition.is_some() {
+                write!(f, " and")?;
+            }
+            else {
+                write!(f, " is")?;
+            }
+
+            write!(f, " {}", enablement.as_str())?;
+        }
+
+        if ! properties.is_empty() {
+            if condition.is_some() || enablement.is_some() {
+                write!(f, " with")?;
+            }
+            else {
+                write!(f, " has")?;
             }
-            Condition::Stopped => {
-                write!(f, "Service ‘{}’ is stopped", service_name.0)
+
+            if properties.len() == 1 {
+                write!(f, " property")?;
+            }
+            else {
+                write!(f, " properties")?;
             }
-            Condition::Missing => {
-                write!(f, "Service ‘{}’ is missing", service_name.0)
+
+            for (index, (name, value)) in properties.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " and")?;
+                }
+
+                write!(f, " ‘{}={}’", name, value)?;
             }
         }
+
+        if let Some(next_elapse) = next_elapse {
+            if condition.is_some() || enablement.is_some() || ! properties.is_empty() {
+                write!(f, " and")?;
+            }
+
+            write!(f, " next elapses within {}", next_elapse.as_written)?;
+        }
+
+        if let Some(last_result) = last_result {
+            if condition.is_some() || enablement.is_some() || ! properties.is_empty() || next_elapse.is_some() {
+                write!(f, " and")?;
+            }
+
+            write!(f, " last ran with result ‘{}’", last_result)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -80,38 +211,126 @@ impl Check for SystemdCheck {
 
 impl SystemdCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["service", "state"])?;
+        table.ensure_only_keys(&["service", "timer", "scope", "state", "enablement", "properties", "next_elapse_within", "last_result"])?;
+
+        let unit_name = UnitName::read(table)?;
+        let scope = table.get("scope").map(Scope::read).transpose()?.unwrap_or(Scope::System);
+        let enablement = table.get("enablement").map(Enablement::read).transpose()?;
+        let properties = match table.get("properties") {
+            Some(properties_value) => read_properties(properties_value)?,
+            None                   => BTreeMap::new(),
+        };
+
+        let next_elapse = table.get("next_elapse_within").map(NextElapse::read).transpose()?;
+        let last_result = table.get("last_result").map(read_last_result).transpose()?;
+
+        if let UnitName::Service(_) = unit_name {
+            if next_elapse.is_some() {
+                return Err(ReadError::conflict("next_elapse_within", "service"));
+            }
+
+            if last_result.is_some() {
+                return Err(ReadError::conflict("last_result", "service"));
+            }
+        }
+
+        // A service’s state only gets checked by default if nothing else
+        // about it is being checked.
+        let condition = match table.get("state") {
+            Some(state_value) => {
+                Some(Condition::read(state_value)?)
+            }
+            None if enablement.is_some() || ! properties.is_empty() || next_elapse.is_some() || last_result.is_some() => {
+                None
+            }
+            None => {
+                Some(Condition::Running)
+            }
+        };
+
+        if condition == Some(Condition::Missing) {
+            if enablement.is_some() {
+                return Err(ReadError::conflict2("enablement", "state", table["state"].clone()));
+            }
+
+            if ! properties.is_empty() {
+                return Err(ReadError::conflict2("properties", "state", table["state"].clone()));
+            }
+
+            if next_elapse.is_some() {
+                return Err(ReadError::conflict2("next_elapse_within", "state", table["state"].clone()));
+            }
+
+            if last_result.is_some() {
+                return Err(ReadError::conflict2("last_result", "state", table["state"].clone()));
+            }
+        }
 
-        let service_name = ServiceName::read(table)?;
-        let condition = Condition::read(table)?;
-        Ok(Self { service_name, condition })
+        Ok(Self { unit_name, scope, condition, enablement, properties, next_elapse, last_result })
     }
 }
 
-impl ServiceName {
-    pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        let name_value = table.get_or_read_error("service")?;
-        let service_name = name_value.string_or_error("service")?;
-
-        if service_name.is_empty() {
-            Err(ReadError::invalid("service", service_name.into(), "it must not be empty"))
+impl UnitName {
+    fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        if table.get("timer").is_some() && table.get("service").is_some() {
+            return Err(ReadError::conflict("timer", "service"));
         }
-        else if service_name.contains('/') {
-            Err(ReadError::invalid("service", service_name.into(), "it must not contain a ‘/’ character"))
+
+        if let Some(timer_value) = table.get("timer") {
+            let timer_name = read_name(timer_value, "timer")?;
+            let timer_name = timer_name.strip_suffix(".timer").map(str::to_owned).unwrap_or(timer_name);
+            Ok(Self::Timer(timer_name))
         }
         else {
-            Ok(Self(service_name))
+            let service_value = table.get_or_read_error("service")?;
+            Ok(Self::Service(read_name(service_value, "service")?))
+        }
+    }
+
+    /// The name of the unit to pass to `systemctl`.
+    fn unit(&self) -> String {
+        match self {
+            Self::Service(name)  => name.clone(),
+            Self::Timer(name)    => format!("{}.timer", name),
+        }
+    }
+
+    /// The name of the service that a timer triggers, which systemd
+    /// defaults to having the same name as the timer.
+    fn triggered_service(&self) -> Option<String> {
+        match self {
+            Self::Service(_)     => None,
+            Self::Timer(name)    => Some(format!("{}.service", name)),
         }
     }
 }
 
-impl Condition {
-    fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        let state_value = match table.get("state") {
-            Some(s) => s,
-            None    => return Ok(Self::Running),
-        };
+fn read_name(name_value: &TomlValue, parameter_name: &'static str) -> Result<String, ReadError> {
+    let name = name_value.string_or_error(parameter_name)?;
 
+    if name.is_empty() {
+        Err(ReadError::invalid(parameter_name, name.into(), "it must not be empty"))
+    }
+    else if name.contains('/') {
+        Err(ReadError::invalid(parameter_name, name.into(), "it must not contain a ‘/’ character"))
+    }
+    else {
+        Ok(name)
+    }
+}
+
+impl Scope {
+    fn read(scope_value: &TomlValue) -> Result<Self, ReadError> {
+        match &scope_value.string_or_error2("scope", OneOf(&["system", "user"]))?[..] {
+            "system"  => Ok(Self::System),
+            "user"    => Ok(Self::User),
+            _         => Err(ReadError::invalid("scope", scope_value.clone(), OneOf(&["system", "user"]))),
+        }
+    }
+}
+
+impl Condition {
+    fn read(state_value: &TomlValue) -> Result<Self, ReadError> {
         match &state_value.string_or_error2("state", OneOf(&["running", "stopped", "missing"]))?[..] {
             "running" => {
                 Ok(Self::Running)
@@ -129,19 +348,118 @@ impl Condition {
     }
 }
 
+impl Enablement {
+    fn read(enablement_value: &TomlValue) -> Result<Self, ReadError> {
+        const STATES: &[&str] = &["enabled", "disabled", "masked", "static"];
+
+        match &enablement_value.string_or_error2("enablement", OneOf(STATES))?[..] {
+            "enabled"   => Ok(Self::Enabled),
+            "disabled"  => Ok(Self::Disabled),
+            "masked"    => Ok(Self::Masked),
+            "static"    => Ok(Self::Static),
+            _           => Err(ReadError::invalid("enablement", enablement_value.clone(), OneOf(STATES))),
+        }
+    }
+
+    /// The value of the `UnitFileState` property for this enablement.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Enabled   => "enabled",
+            Self::Disabled  => "disabled",
+            Self::Masked    => "masked",
+            Self::Static    => "static",
+        }
+    }
+}
+
+impl NextElapse {
+    fn read(within_value: &TomlValue) -> Result<Self, ReadError> {
+        let as_written = within_value.string_or_error2("next_elapse_within", "it must be a duration such as ‘24h’")?;
+
+        match parse_duration(&as_written) {
+            Some(within) if within > Duration::from_secs(0) => {
+                Ok(Self { within, as_written })
+            }
+            Some(_) => {
+                Err(ReadError::invalid("next_elapse_within", within_value.clone(), "it must not be zero"))
+            }
+            None => {
+                Err(ReadError::invalid("next_elapse_within", within_value.clone(), "it must be a duration such as ‘24h’"))
+            }
+        }
+    }
+}
+
+fn read_last_result(result_value: &TomlValue) -> Result<String, ReadError> {
+    let last_result = result_value.string_or_error("last_result")?;
+
+    if last_result.is_empty() {
+        Err(ReadError::invalid("last_result", result_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(last_result)
+    }
+}
+
+/// Reads the table of property names and their expected values. Property
+/// names are in systemd’s own capitalisation, such as `Restart`.
+fn read_properties(properties_value: &TomlValue) -> Result<BTreeMap<String, String>, ReadError> {
+    properties_value.ensure_table("properties")?;
+    let properties = properties_value.string_map_or_read_error("properties")?;
+
+    if properties.is_empty() {
+        Err(ReadError::invalid("properties", properties_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(properties)
+    }
+}
+
+
+// ---- analysis properties ----
+
+impl SystemdCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        match &self.unit_name {
+            UnitName::Service(name) | UnitName::Timer(name) => {
+                points.push(DataPoint::InvolvesService(name));
+            }
+        }
+
+        points
+    }
+}
+
 
 // ---- running the check ----
 
 /// The interface to the local systemd state used by [`SystemdCheck`].
 pub trait RunSystemctl {
 
-    /// Prime the command for running, to get the state of the service with the given name.
+    /// Prime the command for running, to get the state of the unit with the given name.
     #[allow(unused)]
-    fn prime(&mut self, service_name: &str) { }
+    fn prime(&mut self, scope: Scope, unit_name: &str) { }
+
+    /// Prime the command for running, to get the properties of the unit
+    /// with the given name.
+    #[allow(unused)]
+    fn prime_properties(&mut self, scope: Scope, unit_name: &str) { }
+
+    /// Running the command if it hasn’t been run already for the given
+    /// unit, examine the output to return the unit’s state.
+    fn service_state(&self, executor: &mut Executor, scope: Scope, unit_name: &str) -> Result<ServiceState, Arc<ExecError>>;
+
+    /// Running the command if it hasn’t been run already for the given
+    /// unit, return the value of one of its properties, or `None` if it has
+    /// no value for it.
+    fn unit_property(&self, executor: &mut Executor, scope: Scope, unit_name: &str, property_name: &str) -> Result<Option<String>, Arc<ExecError>>;
 
     /// Running the command if it hasn’t been run already for the given
-    /// service, examine the output to return the service’s state.
-    fn service_state(&self, executor: &mut Executor, service_name: &str) -> Result<ServiceState, Rc<ExecError>>;
+    /// timer unit, return the amount of time until it next elapses, or
+    /// `None` if it is not scheduled to elapse at all.
+    fn next_elapse(&self, executor: &mut Executor, scope: Scope, timer_name: &str) -> Result<Option<Duration>, Arc<ExecError>>;
 }
 
 /// One of the states a service could be in, according to systemd.
@@ -164,45 +482,114 @@ impl<S: RunSystemctl> RunCheck<S> for SystemdCheck {
     type FAIL = Fail;
 
     fn load(&self, systemctl: &mut S) {
-        systemctl.prime(&self.service_name.0);
+        let unit = self.unit_name.unit();
+
+        if self.condition.is_some() {
+            systemctl.prime(self.scope, &unit);
+        }
+
+        if self.enablement.is_some() || ! self.properties.is_empty() || self.next_elapse.is_some() {
+            systemctl.prime_properties(self.scope, &unit);
+        }
+
+        if self.last_result.is_some() {
+            if let Some(service) = self.unit_name.triggered_service() {
+                systemctl.prime_properties(self.scope, &service);
+            }
+        }
     }
 
     fn check(&self, executor: &mut Executor, systemctl: &S) -> Vec<CheckResult<Pass, Fail>> {
         info!("Running check");
 
-        let service_state = match systemctl.service_state(executor, &self.service_name.0) {
-            Ok(p)   => p,
-            Err(e)  => return vec![ CheckResult::CommandError(e) ],
-        };
+        let unit = self.unit_name.unit();
+        let mut results = Vec::new();
+
+        if let Some(condition) = &self.condition {
+            let service_state = match systemctl.service_state(executor, self.scope, &unit) {
+                Ok(p)   => p,
+                Err(e)  => return vec![ CheckResult::CommandError(e) ],
+            };
+
+            results.push(match (condition, service_state) {
+                // Successes
+                (Condition::Running, ServiceState::Running)  => CheckResult::Passed(Pass::IsRunning),
+                (Condition::Stopped, ServiceState::Stopped)  => CheckResult::Passed(Pass::IsStopped),
+                (Condition::Missing, ServiceState::Missing)  => CheckResult::Passed(Pass::IsMissing),
+
+                // Fails
+                (_, ServiceState::Running)  => CheckResult::Failed(Fail::IsRunning),
+                (_, ServiceState::Stopped)  => CheckResult::Failed(Fail::IsStopped),
+                (_, ServiceState::Missing)  => CheckResult::Failed(Fail::IsMissing),
+            });
+        }
 
-        match (&self.condition, service_state) {
-            // Successes
-            (Condition::Running, ServiceState::Running) => {
-                vec![ CheckResult::Passed(Pass::IsRunning) ]
-            }
-            (Condition::Stopped, ServiceState::Stopped) => {
-                vec![ CheckResult::Passed(Pass::IsStopped) ]
-            }
-            (Condition::Missing, ServiceState::Missing) => {
-                vec![ CheckResult::Passed(Pass::IsMissing) ]
+        if let Some(enablement) = self.enablement {
+            match systemctl.unit_property(executor, self.scope, &unit, "UnitFileState") {
+                Ok(Some(state)) if state == enablement.as_str() => {
+                    results.push(CheckResult::Passed(Pass::HasEnablement(enablement.as_str())));
+                }
+                Ok(state) => {
+                    results.push(CheckResult::Failed(Fail::HasEnablement(state)));
+                }
+                Err(e) => {
+                    results.push(CheckResult::CommandError(e));
+                    return results;
+                }
             }
+        }
 
-            // Fails
-            (_, ServiceState::Running) => {
-                vec![ CheckResult::Failed(Fail::IsRunning) ]
+        for (name, expected_value) in &self.properties {
+            match systemctl.unit_property(executor, self.scope, &unit, name) {
+                Ok(Some(value)) if &value == expected_value => {
+                    results.push(CheckResult::Passed(Pass::PropertyMatches(name.clone(), value)));
+                }
+                Ok(value) => {
+                    results.push(CheckResult::Failed(Fail::PropertyMismatch(name.clone(), value)));
+                }
+                Err(e) => {
+                    results.push(CheckResult::CommandError(e));
+                    return results;
+                }
             }
-            (_, ServiceState::Stopped) => {
-                vec![ CheckResult::Failed(Fail::IsStopped) ]
+        }
+
+        if let Some(next_elapse) = &self.next_elapse {
+            match systemctl.next_elapse(executor, self.scope, &unit) {
+                Ok(Some(until)) if until <= next_elapse.within => {
+                    results.push(CheckResult::Passed(Pass::ElapsesSoon(next_elapse.as_written.clone())));
+                }
+                Ok(until) => {
+                    results.push(CheckResult::Failed(Fail::ElapsesLater(until.map(|e| e.as_secs()))));
+                }
+                Err(e) => {
+                    results.push(CheckResult::CommandError(e));
+                    return results;
+                }
             }
-            (_, ServiceState::Missing) => {
-                vec![ CheckResult::Failed(Fail::IsMissing) ]
+        }
+
+        if let (Some(expected_result), Some(service)) = (&self.last_result, self.unit_name.triggered_service()) {
+            match systemctl.unit_property(executor, self.scope, &service, "Result") {
+                Ok(Some(result)) if &result == expected_result => {
+                    results.push(CheckResult::Passed(Pass::LastResultMatches(result)));
+                }
+                Ok(result) => {
+                    results.push(CheckResult::Failed(Fail::LastResultMismatch(result)));
+                }
+                Err(e) => {
+                    results.push(CheckResult::CommandError(e));
+                    return results;
+                }
             }
         }
+
+        results
     }
 }
 
 /// The successful result of a systemd check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug)]
 pub enum Pass {
 
     /// The service is running.
@@ -213,10 +600,23 @@ pub enum Pass {
 
     /// The service could not be found.
     IsMissing,
+
+    /// The service’s unit file has the expected enablement.
+    HasEnablement(&'static str),
+
+    /// One of the unit’s properties has the expected value.
+    PropertyMatches(String, String),
+
+    /// The timer next elapses within the given amount of time.
+    ElapsesSoon(String),
+
+    /// The service triggered by the timer last finished with the expected
+    /// result.
+    LastResultMatches(String),
 }
 
 /// The failure result of running a systemd check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug)]
 pub enum Fail {
 
     /// The service was meant to be stopped or missing, but it's running.
@@ -227,6 +627,20 @@ pub enum Fail {
 
     /// The service was meant to exist, but it doesn't.
     IsMissing,
+
+    /// The service’s unit file has a different enablement, or none at all.
+    HasEnablement(Option<String>),
+
+    /// One of the unit’s properties has a different value, or none at all.
+    PropertyMismatch(String, Option<String>),
+
+    /// The timer next elapses after this many seconds, or is not
+    /// scheduled to elapse at all.
+    ElapsesLater(Option<u64>),
+
+    /// The service triggered by the timer last finished with a different
+    /// result, or has none.
+    LastResultMismatch(Option<String>),
 }
 
 impl PassResult for Pass {}
@@ -248,6 +662,18 @@ impl fmt::Display for Pass {
             Self::IsMissing => {
                 write!(f, "it is missing")
             }
+            Self::HasEnablement(enablement) => {
+                write!(f, "it is {}", enablement)
+            }
+            Self::PropertyMatches(name, value) => {
+                write!(f, "property ‘{}’ is ‘{}’", name, value)
+            }
+            Self::ElapsesSoon(within) => {
+                write!(f, "it next elapses within {}", within)
+            }
+            Self::LastResultMatches(result) => {
+                write!(f, "its service last ran with result ‘{}’", result)
+            }
         }
     }
 }
@@ -264,6 +690,30 @@ impl fmt::Display for Fail {
             Self::IsMissing => {
                 write!(f, "it is missing")
             }
+            Self::HasEnablement(Some(enablement)) => {
+                write!(f, "it is {}", enablement)
+            }
+            Self::HasEnablement(None) => {
+                write!(f, "it has no unit file")
+            }
+            Self::PropertyMismatch(name, Some(value)) => {
+                write!(f, "property ‘{}’ is ‘{}’", name, value)
+            }
+            Self::PropertyMismatch(name, None) => {
+                write!(f, "property ‘{}’ is not set", name)
+            }
+            Self::ElapsesLater(Some(secs)) => {
+                write!(f, "it next elapses in {}s", secs)
+            }
+            Self::ElapsesLater(None) => {
+                write!(f, "it is not scheduled to elapse")
+            }
+            Self::LastResultMismatch(Some(result)) => {
+                write!(f, "its service last ran with result ‘{}’", result)
+            }
+            Self::LastResultMismatch(None) => {
+                write!(f, "its service has no result")
+            }
         }
     }
 }
@@ -8,6 +8,22 @@
 //! protocol = 'tcp'
 //! allow = 'Anywhere'
 //! ipv6 = true
+//!
+//! [[ufw]]
+//! port = '6000:6007'
+//! protocol = 'tcp'
+//! interface = 'eth0'
+//! action = 'deny'
+//! allow = 'Anywhere'
+//! ```
+//!
+//! It can also check the default policies for traffic that doesn’t match
+//! any rules:
+//!
+//! ```toml
+//! [[ufw]]
+//! default_incoming = 'deny'
+//! default_outgoing = 'allow'
 //! ```
 //!
 //! # Commands
@@ -16,10 +32,11 @@
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
@@ -27,15 +44,37 @@ use crate::common::PortNumber;
 use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
 
 
-/// A check against the list of ufw firewall rules.
+/// A check against the list of ufw firewall rules, or its default
+/// policies.
+#[derive(PartialEq, Debug)]
+pub enum UfwCheck {
+
+    /// A check against a rule for some ports.
+    Rule(RuleCheck),
+
+    /// A check against the default policies.
+    DefaultPolicy(PolicyCheck),
+}
+
+/// A check against the rules for a port or range of ports.
 #[derive(PartialEq, Debug)]
-pub struct UfwCheck {
+pub struct RuleCheck {
     portspec: Portspec,
     protocol: Protocol,
     ipv6: bool,
+    interface: Option<String>,
     condition: Condition,
 }
 
+/// A check against the policies ufw applies to traffic that no rule
+/// matches. At least one of the directions is present.
+#[derive(PartialEq, Debug)]
+pub struct PolicyCheck {
+    incoming: Option<String>,
+    outgoing: Option<String>,
+    routed: Option<String>,
+}
+
 /// Which ports are being checked.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Portspec {
@@ -50,18 +89,31 @@ pub enum Protocol {
     UDP,
 }
 
+/// What a rule does with the traffic it matches.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Action {
+    Allow,
+    Deny,
+    Reject,
+    Limit,
+}
+
 /// Whether we expect the rule to exist or not.
 #[derive(PartialEq, Debug)]
 enum Condition {
 
     /// We expect a rule with the ports and protocol to exist with the given
-    /// `Allow` field.
+    /// `Allow` field, and the given action if one is specified.
     Exists {
         allow: String,
+        action: Option<Action>,
     },
 
-    /// We expect a rule with the ports and protocol to _not_ exist.
-    Missing,
+    /// We expect a rule with the ports and protocol to _not_ exist, or no
+    /// rule with the given action if one is specified.
+    Missing {
+        action: Option<Action>,
+    },
 }
 
 
@@ -69,7 +121,16 @@ enum Condition {
 
 impl fmt::Display for UfwCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { portspec, protocol, ipv6, condition } = &self;
+        match self {
+            Self::Rule(rule_check)      => rule_check.fmt(f),
+            Self::DefaultPolicy(policy) => policy.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for RuleCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { portspec, protocol, ipv6, interface, condition } = &self;
 
         write!(f, "Rule for {:?}", protocol)?;
 
@@ -78,15 +139,22 @@ impl fmt::Display for UfwCheck {
             Portspec::Range(from, to)  => write!(f, " ports ‘{}–{}’", from, to)?,
         }
 
+        if let Some(interface) = interface {
+            write!(f, " on ‘{}’", interface)?;
+        }
+
         if *ipv6 {
             write!(f, " (IPv6)")?;
         }
 
         match condition {
-            Condition::Exists { allow } => {
-                write!(f, " exists with allow ‘{}’", allow)?;
+            Condition::Exists { allow, action } => {
+                write!(f, " exists with {} ‘{}’", action.unwrap_or(Action::Allow), allow)?;
+            }
+            Condition::Missing { action: Some(action) } => {
+                write!(f, " does not exist with action ‘{}’", action)?;
             }
-            Condition::Missing => {
+            Condition::Missing { action: None } => {
                 write!(f, " does not exist")?;
             }
         }
@@ -95,6 +163,33 @@ impl fmt::Display for UfwCheck {
     }
 }
 
+impl fmt::Display for PolicyCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Default")?;
+
+        for (index, (direction, policy)) in self.directions().enumerate() {
+            if index > 0 {
+                write!(f, " and")?;
+            }
+
+            write!(f, " {} policy is ‘{}’", direction, policy)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow   => write!(f, "allow"),
+            Self::Deny    => write!(f, "deny"),
+            Self::Reject  => write!(f, "reject"),
+            Self::Limit   => write!(f, "limit"),
+        }
+    }
+}
+
 
 // ---- reading from TOML ----
 
@@ -104,25 +199,147 @@ impl Check for UfwCheck {
 
 impl UfwCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["port", "protocol", "ipv6", "state", "allow"])?;
+        table.ensure_only_keys(&["port", "protocol", "ipv6", "interface", "state", "action", "allow",
+                                 "default_incoming", "default_outgoing", "default_routed"])?;
+
+        let policy_keys = ["default_incoming", "default_outgoing", "default_routed"];
+        if let Some(policy_key) = policy_keys.iter().find(|key| table.get(key).is_some()) {
+            for rule_key in &["port", "protocol", "ipv6", "interface", "state", "action", "allow"] {
+                if table.get(rule_key).is_some() {
+                    return Err(ReadError::conflict(rule_key, policy_key));
+                }
+            }
+
+            Ok(Self::DefaultPolicy(PolicyCheck::read(table)?))
+        }
+        else {
+            Ok(Self::Rule(RuleCheck::read(table)?))
+        }
+    }
+}
+
+impl UfwCheck {
 
+    /// Creates a check that a rule exists for the given port and protocol,
+    /// with the given `Allow` field. This gets used by network checks with
+    /// a `ufw` sub-table.
+    pub fn for_port(port: u16, protocol: Protocol, allow: String) -> Self {
+        let condition = Condition::Exists { allow, action: None };
+        Self::Rule(RuleCheck { portspec: Portspec::One(port), protocol, ipv6: false, interface: None, condition })
+    }
+}
+
+impl RuleCheck {
+    fn read(table: &TomlValue) -> Result<Self, ReadError> {
         let portspec = Portspec::read(table)?;
         let protocol = Protocol::read(table)?;
         let ipv6 = table.get("ipv6").map(|e| e.boolean_or_error("ipv6")).transpose()?.unwrap_or_default();
+        let interface = table.get("interface").map(read_interface).transpose()?;
         let condition = Condition::read(table)?;
-        Ok(Self { portspec, protocol, ipv6, condition })
+        Ok(Self { portspec, protocol, ipv6, interface, condition })
+    }
+}
+
+fn read_interface(interface_value: &TomlValue) -> Result<String, ReadError> {
+    let interface = interface_value.string_or_error("interface")?;
+
+    if interface.is_empty() {
+        Err(ReadError::invalid("interface", interface_value.clone(), "it must not be empty"))
+    }
+    else {
+        Ok(interface)
+    }
+}
+
+impl PolicyCheck {
+    fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        let incoming = table.get("default_incoming").map(|e| read_policy(e, "default_incoming", OneOf(&["allow", "deny", "reject"]))).transpose()?;
+        let outgoing = table.get("default_outgoing").map(|e| read_policy(e, "default_outgoing", OneOf(&["allow", "deny", "reject"]))).transpose()?;
+        let routed = table.get("default_routed").map(|e| read_policy(e, "default_routed", OneOf(&["allow", "deny", "reject", "disabled"]))).transpose()?;
+        Ok(Self { incoming, outgoing, routed })
+    }
+
+    /// Returns each direction with an expected policy, along with that
+    /// policy.
+    fn directions(&self) -> impl Iterator<Item=(Direction, &str)> {
+        let incoming = self.incoming.as_deref().map(|p| (Direction::Incoming, p));
+        let outgoing = self.outgoing.as_deref().map(|p| (Direction::Outgoing, p));
+        let routed = self.routed.as_deref().map(|p| (Direction::Routed, p));
+        incoming.into_iter().chain(outgoing).chain(routed)
+    }
+}
+
+fn read_policy(policy_value: &TomlValue, parameter_name: &'static str, policies: OneOf) -> Result<String, ReadError> {
+    let policy = policy_value.string_or_error2(parameter_name, policies)?;
+
+    if policies.0.contains(&&policy[..]) {
+        Ok(policy)
+    }
+    else {
+        Err(ReadError::invalid(parameter_name, policy_value.clone(), policies))
     }
 }
 
 impl Portspec {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        let inner = PortNumber::read(table)?;
-        Ok(Self::One(inner.0))
+        let port_value = table.get_or_read_error("port")?;
+
+        if let Some(range) = port_value.as_str() {
+            let (from, to) = match range.split_once(':') {
+                Some((from, to)) => (from.parse::<u16>(), to.parse::<u16>()),
+                None             => return Err(ReadError::invalid("port", port_value.clone(), PortRangeFormat)),
+            };
+
+            match (from, to) {
+                (Ok(from), Ok(to)) if from > 0 && from < to => {
+                    Ok(Self::Range(from, to))
+                }
+                (Ok(_), Ok(_)) => {
+                    Err(ReadError::invalid("port", port_value.clone(), "it must be a range from a lower port to a higher one"))
+                }
+                _ => {
+                    Err(ReadError::invalid("port", port_value.clone(), PortRangeFormat))
+                }
+            }
+        }
+        else {
+            let inner = PortNumber::read(table)?;
+            Ok(Self::One(inner.0))
+        }
+    }
+}
+
+/// The error message for a port range that cannot be parsed.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct PortRangeFormat;
+
+impl fmt::Display for PortRangeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "it must be a range of ports such as ‘6000:6007’")
+    }
+}
+
+impl Action {
+    fn read(table: &TomlValue) -> Result<Option<Self>, ReadError> {
+        let action_value = match table.get("action") {
+            Some(a) => a,
+            None    => return Ok(None),
+        };
+
+        match &action_value.string_or_error2("action", OneOf(&["allow", "deny", "reject", "limit"]))?[..] {
+            "allow"   => Ok(Some(Self::Allow)),
+            "deny"    => Ok(Some(Self::Deny)),
+            "reject"  => Ok(Some(Self::Reject)),
+            "limit"   => Ok(Some(Self::Limit)),
+            _         => Err(ReadError::invalid("action", action_value.clone(), OneOf(&["allow", "deny", "reject", "limit"]))),
+        }
     }
 }
 
 impl Condition {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
+        let action = Action::read(table)?;
+
         let state_value = table.get("state");
         if state_value.is_none() {
             let allow_value = table.get_or_read_error("allow")?;
@@ -131,20 +348,20 @@ impl Condition {
                 return Err(ReadError::invalid("allow", allow_value.clone(), "it must not be empty"));
             }
 
-            return Ok(Self::Exists { allow });
+            return Ok(Self::Exists { allow, action });
         }
 
         match &state_value.unwrap().string_or_error2("state", OneOf(&["present", "missing"]))?[..] {
             "present" => {
                 let allow = table.get_or_read_error("allow")?.string_or_error("allow")?;
-                Ok(Self::Exists { allow })
+                Ok(Self::Exists { allow, action })
             }
             "missing" => {
                 if table.get("allow").is_some() {
                     return Err(ReadError::conflict2("allow", "state", state_value.unwrap().clone()));
                 }
 
-                Ok(Self::Missing)
+                Ok(Self::Missing { action })
             }
             _ => {
                 Err(ReadError::invalid("state", state_value.unwrap().clone(), OneOf(&["present", "missing"])))
@@ -166,6 +383,21 @@ impl Protocol {
 }
 
 
+// ---- analysis properties ----
+
+impl UfwCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        if let Self::Rule(RuleCheck { portspec: Portspec::One(port), .. }) = self {
+            points.push(DataPoint::InvolvesPort(*port));
+        }
+
+        points
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to the local UFW rules list used by [`UfwCheck`].
@@ -175,9 +407,12 @@ pub trait RunUfw {
     fn prime(&mut self) { }
 
     /// Running the command if it hasn’t been run already, consults the
-    /// rules list and returns whether a rule with the given parameters
-    /// is present.
-    fn find_rule(&self, executor: &mut Executor, portspec: Portspec, protocol: Protocol) -> Result<Option<Rule>, Rc<ExecError>>;
+    /// rules list and returns every rule for the given ports and protocol.
+    fn find_rules(&self, executor: &mut Executor, portspec: Portspec, protocol: Protocol) -> Result<Vec<Rule>, Arc<ExecError>>;
+
+    /// Running the command if it hasn’t been run already, returns the
+    /// default policies, if the firewall is active and lists them.
+    fn default_policies(&self, executor: &mut Executor) -> Result<Option<DefaultPolicies>, Arc<ExecError>>;
 }
 
 #[derive(PartialEq, Debug)]
@@ -186,6 +421,9 @@ pub struct Rule {
     /// The network interface for this rule, if specified.
     pub iface: Option<String>,
 
+    /// What this rule does with the traffic it matches.
+    pub action: Action,
+
     /// The `Allow` field for this rule.
     pub allow: String,
 
@@ -193,6 +431,38 @@ pub struct Rule {
     pub ipv6: bool,
 }
 
+/// The policies for traffic that no rule matches, as listed by ufw.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct DefaultPolicies {
+
+    /// The policy for incoming traffic.
+    pub incoming: String,
+
+    /// The policy for outgoing traffic.
+    pub outgoing: String,
+
+    /// The policy for routed traffic.
+    pub routed: String,
+}
+
+/// The direction of traffic a default policy applies to.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+    Routed,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incoming  => write!(f, "incoming"),
+            Self::Outgoing  => write!(f, "outgoing"),
+            Self::Routed    => write!(f, "routed"),
+        }
+    }
+}
+
 impl<U: RunUfw> RunCheck<U> for UfwCheck {
     type PASS = Pass;
     type FAIL = Fail;
@@ -204,48 +474,120 @@ impl<U: RunUfw> RunCheck<U> for UfwCheck {
     fn check(&self, executor: &mut Executor, ufw: &U) -> Vec<CheckResult<Pass, Fail>> {
         info!("Running check");
 
-        let rule = match ufw.find_rule(executor, self.portspec, self.protocol) {
-            Ok(p)   => p,
+        match self {
+            Self::Rule(rule_check)      => rule_check.check(executor, ufw),
+            Self::DefaultPolicy(policy) => policy.check(executor, ufw),
+        }
+    }
+}
+
+impl RuleCheck {
+    fn check<U: RunUfw>(&self, executor: &mut Executor, ufw: &U) -> Vec<CheckResult<Pass, Fail>> {
+        let rules = match ufw.find_rules(executor, self.portspec, self.protocol) {
+            Ok(rs)  => rs,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
-        match (&self.condition, rule.as_ref()) {
-            (Condition::Exists { allow }, Some(rule)) => {
+        let rules = rules.into_iter().filter(|rule| self.applies_to(rule)).collect::<Vec<_>>();
+
+        match &self.condition {
+            Condition::Exists { allow, action } => {
+                let expected_action = action.unwrap_or(Action::Allow);
+                let rule = match rules.iter().find(|r| r.action == expected_action).or_else(|| rules.first()) {
+                    Some(r) => r,
+                    None    => return vec![ CheckResult::Failed(Fail::RuleMissing) ],
+                };
+
+                let mut results = vec![ CheckResult::Passed(Pass::RuleExists) ];
+
+                if rule.action != expected_action {
+                    results.push(CheckResult::Failed(Fail::ActionMismatch(rule.action)));
+                }
+                else if action.is_some() {
+                    results.push(CheckResult::Passed(Pass::ActionMatches));
+                }
 
                 if *allow == rule.allow {
-                    vec![ CheckResult::Passed(Pass::RuleExists),
-                          CheckResult::Passed(Pass::AllowMatches), ]
+                    results.push(CheckResult::Passed(Pass::AllowMatches));
                 }
                 else {
-                    vec![ CheckResult::Passed(Pass::RuleExists),
-                          CheckResult::Failed(Fail::AllowMismatch(rule.allow.clone())), ]
+                    results.push(CheckResult::Failed(Fail::AllowMismatch(rule.allow.clone())));
                 }
+
+                results
             }
-            (Condition::Exists { .. }, None) => {
-                vec![ CheckResult::Failed(Fail::RuleMissing) ]
+            Condition::Missing { action } => {
+                if rules.iter().any(|r| action.is_none() || *action == Some(r.action)) {
+                    vec![ CheckResult::Failed(Fail::RuleExists) ]
+                }
+                else {
+                    vec![ CheckResult::Passed(Pass::RuleMissing) ]
+                }
             }
-            (Condition::Missing, Some(_)) => {
-                vec![ CheckResult::Failed(Fail::RuleExists) ]
+        }
+    }
+
+    /// Whether the given rule is for the interface and IP version that
+    /// this check is for. Rules on any interface count when no interface
+    /// is given.
+    fn applies_to(&self, rule: &Rule) -> bool {
+        if rule.ipv6 != self.ipv6 {
+            return false;
+        }
+
+        match &self.interface {
+            Some(interface)  => rule.iface.as_ref() == Some(interface),
+            None             => true,
+        }
+    }
+}
+
+impl PolicyCheck {
+    fn check<U: RunUfw>(&self, executor: &mut Executor, ufw: &U) -> Vec<CheckResult<Pass, Fail>> {
+        let policies = match ufw.default_policies(executor) {
+            Ok(Some(ps))  => ps,
+            Ok(None)      => return vec![ CheckResult::Failed(Fail::NoDefaultPolicies) ],
+            Err(e)        => return vec![ CheckResult::CommandError(e) ],
+        };
+
+        let mut results = Vec::new();
+        for (direction, expected_policy) in self.directions() {
+            let got_policy = match direction {
+                Direction::Incoming  => &policies.incoming,
+                Direction::Outgoing  => &policies.outgoing,
+                Direction::Routed    => &policies.routed,
+            };
+
+            if got_policy == expected_policy {
+                results.push(CheckResult::Passed(Pass::PolicyMatches(direction, got_policy.clone())));
             }
-            (Condition::Missing, None) => {
-                vec![ CheckResult::Passed(Pass::RuleMissing) ]
+            else {
+                results.push(CheckResult::Failed(Fail::PolicyMismatch(direction, got_policy.clone())));
             }
         }
+
+        results
     }
 }
 
 /// The successful result of a UFW check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Pass {
 
     /// The rule exists.
     RuleExists,
 
+    /// The rule has the expected action.
+    ActionMatches,
+
     /// The `Allow` field of the rule contains the expected value.
     AllowMatches,
 
     /// No rule matching the criteria exists.
     RuleMissing,
+
+    /// The default policy for a direction is the expected one.
+    PolicyMatches(Direction, String),
 }
 
 /// The failure result of running a UFW check.
@@ -255,12 +597,21 @@ pub enum Fail {
     /// We expected a rule to exist, but no rule matches the criteria.
     RuleMissing,
 
+    /// The existing rule has a different action.
+    ActionMismatch(Action),
+
     /// The `Allow` field of the existing rule contains a different value.
     AllowMismatch(String),
 
     /// We expected no rule to exist, but there is an existing rule that
     /// matches the criteria.
     RuleExists,
+
+    /// The default policy for a direction is a different one.
+    PolicyMismatch(Direction, String),
+
+    /// No default policies are listed, because the firewall is inactive.
+    NoDefaultPolicies,
 }
 
 impl PassResult for Pass {}
@@ -276,12 +627,18 @@ impl fmt::Display for Pass {
             Self::RuleExists => {
                 write!(f, "rule exists")
             }
+            Self::ActionMatches => {
+                write!(f, "action matches")
+            }
             Self::AllowMatches => {
                 write!(f, "Allow matches")
             }
             Self::RuleMissing => {
                 write!(f, "rule missing")
             }
+            Self::PolicyMatches(direction, policy) => {
+                write!(f, "{} policy is ‘{}’", direction, policy)
+            }
         }
     }
 }
@@ -292,12 +649,21 @@ impl fmt::Display for Fail {
             Self::RuleMissing => {
                 write!(f, "rule missing")
             }
+            Self::ActionMismatch(actual_action) => {
+                write!(f, "action is ‘{}’", actual_action)
+            }
             Self::AllowMismatch(actual_allow) => {
                 write!(f, "Allow is ‘{}’", actual_allow)
             }
             Self::RuleExists => {
                 write!(f, "rule exists")
             }
+            Self::PolicyMismatch(direction, policy) => {
+                write!(f, "{} policy is ‘{}’", direction, policy)
+            }
+            Self::NoDefaultPolicies => {
+                write!(f, "the firewall is inactive")
+            }
         }
     }
 }
@@ -5,15 +5,25 @@
 //! ```toml
 //! [[user]]
 //! user = 'consul'
+//!
+//! [[user]]
+//! user = 'consul'
+//! uid = 998
+//! home = '/var/lib/consul'
+//! primary_group = 'consul'
+//! system = true
+//! locked = true
 //! ```
 //!
 //! # Commands
 //!
 //! No commands are run for user checks; Specsheet queries the users database
-//! itself.
+//! itself, and reads the shadow password file for the `locked` and
+//! `no_password` parameters.
 
 
 use std::fmt;
+use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use log::*;
@@ -51,13 +61,42 @@ enum Condition {
 #[derive(PartialEq, Debug)]
 struct UserDataChecks {
 
+    /// If given, what this user’s ID should be.
+    uid: Option<u32>,
+
+    /// If given, what this user’s home directory should be.
+    home: Option<PathBuf>,
+
     /// If given, what this user’s login shell should be.
     login_shell: Option<PathBuf>,
 
+    /// If given, the name or ID of this user’s primary group.
+    primary_group: Option<PrimaryGroup>,
+
     /// If given a list of names of groups that this user should be in.
     groups: Option<Vec<String>>,
+
+    /// If given, whether this user should be a system user.
+    system: Option<bool>,
+
+    /// If given, whether this user’s password should be locked.
+    locked: Option<bool>,
+
+    /// If given, whether this user should have an empty password.
+    no_password: Option<bool>,
 }
 
+/// The primary group a user should have.
+#[derive(PartialEq, Debug)]
+enum PrimaryGroup {
+    ByName(String),
+    ByID(u32),
+}
+
+/// Users with IDs below this are considered system users. This is the
+/// default value of `UID_MIN` in `/etc/login.defs`.
+const SYSTEM_UID_LIMIT: u32 = 1000;
+
 
 // ---- the check description ----
 
@@ -69,8 +108,29 @@ impl fmt::Display for UserCheck {
             Condition::Exists(checks) => {
                 write!(f, "User ‘{}’ exists", user_name.0)?;
 
+                let mut withs = Vec::new();
+
+                if let Some(uid) = &checks.uid {
+                    withs.push(format!("UID ‘{}’", uid));
+                }
+
+                if let Some(home) = &checks.home {
+                    withs.push(format!("home directory ‘{}’", home.display()));
+                }
+
                 if let Some(ls) = &checks.login_shell {
-                    write!(f, " with login shell ‘{}’", ls.display())?;
+                    withs.push(format!("login shell ‘{}’", ls.display()));
+                }
+
+                match &checks.primary_group {
+                    Some(PrimaryGroup::ByName(gn))  => withs.push(format!("primary group ‘{}’", gn)),
+                    Some(PrimaryGroup::ByID(gid))   => withs.push(format!("primary group ID ‘{}’", gid)),
+                    None                            => {},
+                }
+
+                for (i, with) in withs.iter().enumerate() {
+                    let joiner = if i == 0 { "with" } else { "and" };
+                    write!(f, " {} {}", joiner, with)?;
                 }
 
                 if let Some(gs) = &checks.groups {
@@ -82,6 +142,24 @@ impl fmt::Display for UserCheck {
                     }
                 }
 
+                match checks.system {
+                    Some(true)   => write!(f, " and is a system user")?,
+                    Some(false)  => write!(f, " and is not a system user")?,
+                    None         => {},
+                }
+
+                match checks.locked {
+                    Some(true)   => write!(f, " and is locked")?,
+                    Some(false)  => write!(f, " and is not locked")?,
+                    None         => {},
+                }
+
+                match checks.no_password {
+                    Some(true)   => write!(f, " and has no password")?,
+                    Some(false)  => write!(f, " and has a password")?,
+                    None         => {},
+                }
+
                 Ok(())
             }
             Condition::Missing => {
@@ -100,7 +178,8 @@ impl Check for UserCheck {
 
 impl UserCheck {
     pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["user", "state", "login_shell", "groups"])?;
+        table.ensure_only_keys(&["user", "state", "uid", "home", "login_shell", "primary_group", "groups",
+                                 "system", "locked", "no_password"])?;
 
         let user_name = UserName::read(table)?;
         let condition = Condition::read(table, rewrites)?;
@@ -134,6 +213,12 @@ impl Condition {
                 Ok(Self::Exists(UserDataChecks::read(table, rewrites)?))
             }
             "missing" => {
+                for parameter_name in &["uid", "home", "login_shell", "primary_group", "groups", "system", "locked", "no_password"] {
+                    if table.get(parameter_name).is_some() {
+                        return Err(ReadError::conflict2(parameter_name, "state", state.clone()));
+                    }
+                }
+
                 Ok(Self::Missing)
             }
             _ => {
@@ -145,6 +230,25 @@ impl Condition {
 
 impl UserDataChecks {
     fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        let uid = table.get("uid")
+                       .map(|e| {
+                           let number = e.number_or_error("uid")?;
+                           u32::try_from(number).map_err(|_| ReadError::invalid("uid", e.clone(), "it must be a valid user ID"))
+                       })
+                       .transpose()?;
+
+        let home = table.get("home")
+                        .map(|e| {
+                            let s = e.string_or_error("home")?;
+                            if s.is_empty() {
+                                Err(ReadError::invalid("home", e.clone(), "it must not be empty"))
+                            }
+                            else {
+                                Ok(rewrites.path(s))
+                            }
+                         })
+                        .transpose()?;
+
         let login_shell = table.get("login_shell")
                                .map(|e| {
                                    let s = e.string_or_error("login_shell")?;
@@ -166,7 +270,45 @@ impl UserDataChecks {
             return Err(ReadError::invalid("groups", table.get("groups").unwrap().clone(), "group names must not be empty"));
         }
 
-        Ok(Self { login_shell, groups })
+        let primary_group = PrimaryGroup::read(table)?;
+        let system = table.get("system").map(|e| e.boolean_or_error("system")).transpose()?;
+        let locked = table.get("locked").map(|e| e.boolean_or_error("locked")).transpose()?;
+        let no_password = table.get("no_password").map(|e| e.boolean_or_error("no_password")).transpose()?;
+
+        Ok(Self { uid, home, login_shell, primary_group, groups, system, locked, no_password })
+    }
+
+    /// Whether any of the checks need the password state from the shadow
+    /// password file.
+    fn needs_password_state(&self) -> bool {
+        self.locked.is_some() || self.no_password.is_some()
+    }
+}
+
+impl PrimaryGroup {
+    fn read(table: &TomlValue) -> Result<Option<Self>, ReadError> {
+        if let Some(group_value) = table.get("primary_group") {
+            if let Some(group) = group_value.as_str() {
+                if group.is_empty() {
+                    Err(ReadError::invalid("primary_group", group_value.clone(), "it must not be empty"))
+                }
+                else {
+                    Ok(Some(Self::ByName(group.into())))
+                }
+            }
+            else if let Some(int) = group_value.as_integer() {
+                match u32::try_from(int) {
+                    Ok(gid)  => Ok(Some(Self::ByID(gid))),
+                    Err(_)   => Err(ReadError::invalid("primary_group", group_value.clone(), "it must be a valid group ID")),
+                }
+            }
+            else {
+                Err(ReadError::invalid("primary_group", group_value.clone(), "it must be a string or a number"))
+            }
+        }
+        else {
+            Ok(None)
+        }
     }
 }
 
@@ -196,6 +338,24 @@ pub trait LookupUser {
     /// users list and returns a user depending on whether or not one is
     /// present.
     fn lookup_user(&self, username: &str) -> Option<User>;
+
+    /// Consults the shadow password file and returns the state of the
+    /// user’s password, or nothing if it could not be read.
+    fn password_state(&self, username: &str) -> Option<PasswordState>;
+}
+
+/// The state of a user’s password, as listed in the shadow password file.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum PasswordState {
+
+    /// The password is locked, so the user cannot log in with one.
+    Locked,
+
+    /// The password is empty, so the user can log in without one.
+    Empty,
+
+    /// The user has a usable password.
+    Usable,
 }
 
 impl<P: LookupUser> BuiltInCheck<P> for UserCheck {
@@ -217,6 +377,24 @@ impl<P: LookupUser> BuiltInCheck<P> for UserCheck {
             (Condition::Exists(checks), Some(u)) => {
                 let mut results = vec![ CheckResult::Passed(Pass::UserExists) ];
 
+                if let Some(uid) = checks.uid {
+                    if u.uid() == uid {
+                        results.push(CheckResult::Passed(Pass::UserHasUID));
+                    }
+                    else {
+                        results.push(CheckResult::Failed(Fail::UserHasDifferentUID(u.uid())));
+                    }
+                }
+
+                if let Some(home) = &checks.home {
+                    if u.home_dir() == home {
+                        results.push(CheckResult::Passed(Pass::UserHasHomeDirectory));
+                    }
+                    else {
+                        results.push(CheckResult::Failed(Fail::UserHasDifferentHomeDirectory(u.home_dir().to_path_buf())));
+                    }
+                }
+
                 if let Some(login_shell) = &checks.login_shell {
                     if u.shell() == login_shell {
                         results.push(CheckResult::Passed(Pass::UserHasLoginShell));
@@ -242,6 +420,39 @@ impl<P: LookupUser> BuiltInCheck<P> for UserCheck {
                     }
                 }
 
+                if let Some(primary_group) = &checks.primary_group {
+                    let gid = u.primary_group_id();
+                    let matches = match primary_group {
+                        PrimaryGroup::ByName(name) => matches!(users::get_group_by_gid(gid), Some(g) if g.name() == &**name),
+                        PrimaryGroup::ByID(id)     => gid == *id,
+                    };
+
+                    if matches {
+                        results.push(CheckResult::Passed(Pass::UserHasPrimaryGroup));
+                    }
+                    else {
+                        results.push(CheckResult::Failed(Fail::UserHasDifferentPrimaryGroup(gid)));
+                    }
+                }
+
+                if let Some(system) = checks.system {
+                    let is_system = u.uid() < SYSTEM_UID_LIMIT;
+
+                    if is_system == system {
+                        results.push(CheckResult::Passed(Pass::UserIsSystemUser(is_system)));
+                    }
+                    else {
+                        results.push(CheckResult::Failed(Fail::UserIsSystemUser(is_system)));
+                    }
+                }
+
+                if checks.needs_password_state() {
+                    match passwd.password_state(&self.user_name.0) {
+                        Some(state) => checks.check_password_state(state, &mut results),
+                        None        => results.push(CheckResult::Failed(Fail::PasswordStateUnknown)),
+                    }
+                }
+
                 results
             }
             (Condition::Exists(_checks), None) => {
@@ -257,6 +468,32 @@ impl<P: LookupUser> BuiltInCheck<P> for UserCheck {
     }
 }
 
+impl UserDataChecks {
+    fn check_password_state(&self, state: PasswordState, results: &mut Vec<CheckResult<Pass, Fail>>) {
+        if let Some(locked) = self.locked {
+            let is_locked = state == PasswordState::Locked;
+
+            if is_locked == locked {
+                results.push(CheckResult::Passed(Pass::PasswordIsLocked(is_locked)));
+            }
+            else {
+                results.push(CheckResult::Failed(Fail::PasswordIsLocked(is_locked)));
+            }
+        }
+
+        if let Some(no_password) = self.no_password {
+            let is_empty = state == PasswordState::Empty;
+
+            if is_empty == no_password {
+                results.push(CheckResult::Passed(Pass::PasswordIsEmpty(is_empty)));
+            }
+            else {
+                results.push(CheckResult::Failed(Fail::PasswordIsEmpty(is_empty)));
+            }
+        }
+    }
+}
+
 /// The successful result of a user check.
 #[derive(PartialEq, Debug)]
 pub enum Pass {
@@ -272,6 +509,24 @@ pub enum Pass {
 
     /// The user has the correct login shell.
     UserHasLoginShell,
+
+    /// The user has the correct ID.
+    UserHasUID,
+
+    /// The user has the correct home directory.
+    UserHasHomeDirectory,
+
+    /// The user has the correct primary group.
+    UserHasPrimaryGroup,
+
+    /// The user is, or is not, a system user, as expected.
+    UserIsSystemUser(bool),
+
+    /// The user’s password is, or is not, locked, as expected.
+    PasswordIsLocked(bool),
+
+    /// The user’s password is, or is not, empty, as expected.
+    PasswordIsEmpty(bool),
 }
 
 /// The failure result of running a user check.
@@ -291,6 +546,32 @@ pub enum Fail {
     /// The user was meant to have a certain login shell, but they
     /// have a different one.
     UserHasDifferentLoginShell,
+
+    /// The user was meant to have a certain ID, but they have this one.
+    UserHasDifferentUID(u32),
+
+    /// The user was meant to have a certain home directory, but they
+    /// have this one.
+    UserHasDifferentHomeDirectory(PathBuf),
+
+    /// The user was meant to have a certain primary group, but they have
+    /// the group with this ID.
+    UserHasDifferentPrimaryGroup(u32),
+
+    /// The user was meant to be a system user, but isn’t, or the other
+    /// way around.
+    UserIsSystemUser(bool),
+
+    /// The user’s password was meant to be locked, but isn’t, or the
+    /// other way around.
+    PasswordIsLocked(bool),
+
+    /// The user’s password was meant to be empty, but isn’t, or the
+    /// other way around.
+    PasswordIsEmpty(bool),
+
+    /// The shadow password file could not be read.
+    PasswordStateUnknown,
 }
 
 impl PassResult for Pass {}
@@ -315,6 +596,24 @@ impl fmt::Display for Pass {
             Self::UserHasLoginShell => {
                 write!(f, "user has correct login shell")
             }
+            Self::UserHasUID => {
+                write!(f, "user has correct UID")
+            }
+            Self::UserHasHomeDirectory => {
+                write!(f, "user has correct home directory")
+            }
+            Self::UserHasPrimaryGroup => {
+                write!(f, "user has correct primary group")
+            }
+            Self::UserIsSystemUser(is_system) => {
+                describe_system_user(f, *is_system)
+            }
+            Self::PasswordIsLocked(is_locked) => {
+                describe_locked(f, *is_locked)
+            }
+            Self::PasswordIsEmpty(is_empty) => {
+                describe_empty(f, *is_empty)
+            }
         }
     }
 }
@@ -335,6 +634,54 @@ impl fmt::Display for Fail {
             Self::UserHasDifferentLoginShell => {
                 write!(f, "user has different login shell")
             }
+            Self::UserHasDifferentUID(uid) => {
+                write!(f, "user has UID ‘{}’", uid)
+            }
+            Self::UserHasDifferentHomeDirectory(home) => {
+                write!(f, "user has home directory ‘{}’", home.display())
+            }
+            Self::UserHasDifferentPrimaryGroup(gid) => {
+                write!(f, "user has primary group ID ‘{}’", gid)
+            }
+            Self::UserIsSystemUser(is_system) => {
+                describe_system_user(f, *is_system)
+            }
+            Self::PasswordIsLocked(is_locked) => {
+                describe_locked(f, *is_locked)
+            }
+            Self::PasswordIsEmpty(is_empty) => {
+                describe_empty(f, *is_empty)
+            }
+            Self::PasswordStateUnknown => {
+                write!(f, "password state could not be read")
+            }
         }
     }
 }
+
+fn describe_system_user(f: &mut fmt::Formatter<'_>, is_system: bool) -> fmt::Result {
+    if is_system {
+        write!(f, "user is a system user")
+    }
+    else {
+        write!(f, "user is not a system user")
+    }
+}
+
+fn describe_locked(f: &mut fmt::Formatter<'_>, is_locked: bool) -> fmt::Result {
+    if is_locked {
+        write!(f, "password is locked")
+    }
+    else {
+        write!(f, "password is not locked")
+    }
+}
+
+fn describe_empty(f: &mut fmt::Formatter<'_>, is_empty: bool) -> fmt::Result {
+    if is_empty {
+        write!(f, "password is empty")
+    }
+    else {
+        write!(f, "password is not empty")
+    }
+}
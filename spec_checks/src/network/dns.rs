@@ -10,20 +10,45 @@
 //! value = "159.89.251.132"
 //! ```
 //!
+//! Round-robin records can be checked against a set of values:
+//!
+//! ```toml
+//! [[dns]]
+//! domain = "example.com"
+//! type = "A"
+//! values = ["192.0.2.1", "192.0.2.2"]
+//! match = "exact"
+//! ```
+//!
+//! The TTL of the records, and whether the response was validated with
+//! DNSSEC, can also be checked:
+//!
+//! ```toml
+//! [[dns]]
+//! domain = "example.com"
+//! type = "A"
+//! value = "192.0.2.1"
+//! ttl = { min = 60, max = 3600 }
+//! dnssec = true
+//! ```
+//!
 //! # Commands
 //!
 //! This check works by running `dig`.
 
 
+use std::convert::TryFrom;
 use std::fmt;
 use std::net::IpAddr;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
+use crate::numbers::{NumberMatcher, Comparison};
 use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
 
 
@@ -32,6 +57,7 @@ use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
 pub struct DnsCheck {
     request: Request,
     condition: Condition,
+    ttl: Option<NumberMatcher>,
 }
 
 /// The details of a DNS that can be made.
@@ -46,6 +72,30 @@ pub struct Request {
 
     /// The record type to specify during the query.
     pub rtype: RecordType,
+
+    /// Whether the TTLs of the records are needed, rather than just their
+    /// values.
+    pub with_ttl: bool,
+
+    /// Whether DNSSEC records should be requested, so the response can be
+    /// checked for the Authenticated Data flag.
+    pub dnssec: bool,
+}
+
+/// The parts of a DNS response that get checked.
+#[derive(PartialEq, Debug, Default)]
+pub struct Response {
+
+    /// The values of the records in the response.
+    pub values: Vec<Arc<str>>,
+
+    /// The TTL of each record in the response. This is only filled in if
+    /// the request asked for TTLs.
+    pub ttls: Vec<u32>,
+
+    /// Whether the response had the Authenticated Data flag set. This is
+    /// only filled in if the request asked for DNSSEC.
+    pub authenticated: bool,
 }
 
 /// Which nameserver should be used for this request.
@@ -76,23 +126,58 @@ enum Condition {
     /// There should be a value present for this domain and type.
     Present(String),
 
+    /// The values present for this domain and type should match the given
+    /// set of values.
+    Values(Vec<String>, ValuesMatch),
+
     /// There should be no value present for this domain and type.
     Missing,
 }
 
+/// How a set of expected values gets compared with the values in the
+/// response.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum ValuesMatch {
+
+    /// Every expected value must be present, though there may be others.
+    All,
+
+    /// At least one of the expected values must be present.
+    Any,
+
+    /// The values present must be exactly the expected values, with none
+    /// missing and no extras.
+    Exact,
+}
+
 // ---- the check description ----
 
 impl fmt::Display for DnsCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { request, condition } = &self;
+        let Self { request, condition, ttl } = &self;
 
         write!(f, "DNS ‘{:?}’ record for ‘{}’", request.rtype, request.domain)?;
 
         match condition {
             Condition::Present(cond)  => write!(f, " exists with value ‘{}’", cond)?,
+            Condition::Values(values, ValuesMatch::All)    => write!(f, " exists with values {}", quoted_list(values, "and"))?,
+            Condition::Values(values, ValuesMatch::Any)    => write!(f, " exists with value {}", quoted_list(values, "or"))?,
+            Condition::Values(values, ValuesMatch::Exact)  => write!(f, " exists with exactly values {}", quoted_list(values, "and"))?,
             Condition::Missing        => write!(f, " is missing")?,
         }
 
+        if let Some(ttl) = ttl {
+            match ttl.comparisons() {
+                [ Comparison::Equal(exact) ]     => write!(f, " with TTL ‘{}’", exact)?,
+                [ Comparison::Between(_, _) ]    => write!(f, " with TTL {}", ttl)?,
+                _                                => write!(f, " with TTL of {}", ttl)?,
+            }
+        }
+
+        if request.dnssec {
+            write!(f, " and is validated with DNSSEC")?;
+        }
+
         if let Nameserver::ByIP(ip) = &request.nameserver {
             write!(f, " (according to {})", ip)?;
         }
@@ -101,6 +186,28 @@ impl fmt::Display for DnsCheck {
     }
 }
 
+/// Lists the given values in quotes, with the given word before the last.
+fn quoted_list(values: &[impl fmt::Display], last_word: &str) -> String {
+    let mut list = String::new();
+
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 && i == values.len() - 1 {
+            list.push(' ');
+            list.push_str(last_word);
+            list.push(' ');
+        }
+        else if i > 0 {
+            list.push_str(", ");
+        }
+
+        list.push('‘');
+        list.push_str(&value.to_string());
+        list.push('’');
+    }
+
+    list
+}
+
 
 // ---- reading from TOML ----
 
@@ -110,11 +217,20 @@ impl Check for DnsCheck {
 
 impl DnsCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["nameserver", "domain", "type", "state", "value"])?;
+        table.ensure_only_keys(&["nameserver", "domain", "type", "state", "value", "values", "match", "ttl", "dnssec"])?;
 
         let request = Request::read(table)?;
         let condition = Condition::read(table)?;
-        Ok(Self { request, condition })
+        let ttl = table.get("ttl").map(read_ttl_matcher).transpose()?;
+
+        // There are no records to have a TTL if they should be missing.
+        if let (Condition::Missing, Some(state_value)) = (&condition, table.get("state")) {
+            if ttl.is_some() {
+                return Err(ReadError::conflict2("ttl", "state", state_value.clone()));
+            }
+        }
+
+        Ok(Self { request, condition, ttl })
     }
 }
 
@@ -127,7 +243,9 @@ impl Request {
         }
 
         let rtype = RecordType::read(table)?;
-        Ok(Self { nameserver, domain, rtype })
+        let with_ttl = table.get("ttl").is_some();
+        let dnssec = table.get("dnssec").map(|e| e.boolean_or_error("dnssec")).transpose()?.unwrap_or(false);
+        Ok(Self { nameserver, domain, rtype, with_ttl, dnssec })
     }
 }
 
@@ -175,6 +293,25 @@ impl Condition {
             v.string_or_error("value")
         }).transpose()?;
 
+        let values = table.get("values").map(|v| {
+            v.string_array_or_read_error("values")
+        }).transpose()?;
+
+        if let Some(values_value) = table.get("values") {
+            if value.is_some() {
+                return Err(ReadError::conflict("value", "values"));
+            }
+
+            if values.as_ref().is_some_and(Vec::is_empty) {
+                return Err(ReadError::invalid("values", values_value.clone(), "it must not be empty"));
+            }
+        }
+        else if let Some(match_value) = table.get("match") {
+            return Err(ReadError::conflict2("match", "values", match_value.clone()));
+        }
+
+        let values_match = table.get("match").map(ValuesMatch::read).transpose()?.unwrap_or(ValuesMatch::All);
+
         if let Some(state_value) = table.get("state") {
             match &state_value.string_or_error("state")?[..] {
                 "present" => {
@@ -184,6 +321,9 @@ impl Condition {
                     if value.is_some() {
                         return Err(ReadError::conflict2("value", "state", state_value.clone()));
                     }
+                    else if values.is_some() {
+                        return Err(ReadError::conflict2("values", "state", state_value.clone()));
+                    }
                     else {
                         return Ok(Self::Missing);
                     }
@@ -197,12 +337,73 @@ impl Condition {
         if let Some(value) = value {
             Ok(Self::Present(value))
         }
+        else if let Some(values) = values {
+            Ok(Self::Values(values, values_match))
+        }
         else {
             Err(ReadError::MissingParameter { parameter_name: "value" })
         }
     }
 }
 
+/// Reads the range that the TTL of every record must fall within, which
+/// is either one number, a table with ‘min’ and ‘max’, or a table of
+/// comparisons such as `{ gt = 60 }`.
+fn read_ttl_matcher(ttl_value: &TomlValue) -> Result<NumberMatcher, ReadError> {
+    if ttl_value.as_integer().is_some() {
+        let ttl = read_ttl(ttl_value)?;
+        return Ok(NumberMatcher::exactly(ttl.into()));
+    }
+
+    if ttl_value.as_table().is_none() {
+        return Err(ReadError::invalid("ttl", ttl_value.clone(), "it must be a number or a table with ‘min’ and ‘max’"));
+    }
+
+    if NumberMatcher::is_comparison_table(ttl_value) {
+        return NumberMatcher::read("ttl", ttl_value);
+    }
+
+    ttl_value.ensure_only_keys(&["min", "max"])?;
+    let min = ttl_value.get("min").map(read_ttl).transpose()?;
+    let max = ttl_value.get("max").map(read_ttl).transpose()?;
+
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(ReadError::invalid("ttl", TomlValue::Integer(min.into()), "the minimum must not be greater than the maximum"));
+        }
+    }
+
+    match NumberMatcher::range(min.map(i64::from), max.map(i64::from)) {
+        Some(matcher)  => Ok(matcher),
+        None           => Err(ReadError::invalid("ttl", ttl_value.clone(), "it must have ‘min’ or ‘max’")),
+    }
+}
+
+fn read_ttl(value: &TomlValue) -> Result<u32, ReadError> {
+    let number = value.number_or_error("ttl")?;
+    u32::try_from(number).map_err(|_| ReadError::invalid("ttl", value.clone(), "it must be a positive number"))
+}
+
+impl ValuesMatch {
+    fn read(match_value: &TomlValue) -> Result<Self, ReadError> {
+        match &match_value.string_or_error("match")?[..] {
+            "all"    => Ok(Self::All),
+            "any"    => Ok(Self::Any),
+            "exact"  => Ok(Self::Exact),
+            _        => Err(ReadError::invalid("match", match_value.clone(), OneOf(&["all", "any", "exact"]))),
+        }
+    }
+}
+
+
+// ---- analysis properties ----
+
+impl DnsCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesDomain(&self.request.domain) ]
+    }
+}
+
 
 // ---- running the check ----
 
@@ -214,8 +415,9 @@ pub trait RunDns {
     fn prime(&mut self, request: &Request) { }
 
     /// Running the command if it hasn’t been run already, examines the
-    /// output and returns the value in the DNS response.
-    fn get_values(&self, executor: &mut Executor, request: &Request) -> Result<Vec<Rc<str>>, Rc<ExecError>>;
+    /// output and returns the values in the DNS response, along with the
+    /// TTLs and DNSSEC status if the request asked for them.
+    fn get_response(&self, executor: &mut Executor, request: &Request) -> Result<Response, Arc<ExecError>>;
 }
 
 impl<D: RunDns> RunCheck<D> for DnsCheck {
@@ -229,12 +431,32 @@ impl<D: RunDns> RunCheck<D> for DnsCheck {
     fn check(&self, executor: &mut Executor, dig: &D) -> Vec<CheckResult<Pass, Fail>> {
         info!("Running check");
 
-        let results = match dig.get_values(executor, &self.request) {
+        let Response { values: results, ttls, authenticated } = match dig.get_response(executor, &self.request) {
             Ok(p)   => p,
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
-        match (&self.condition, results.is_empty()) {
+        let mut check_results = Vec::new();
+
+        if let Some(ttl_matcher) = &self.ttl {
+            if let Some(&bad_ttl) = ttls.iter().find(|&&t| ! ttl_matcher.matches(t.into())) {
+                check_results.push(CheckResult::Failed(Fail::TtlOutOfRange(bad_ttl)));
+            }
+            else if ! ttls.is_empty() {
+                check_results.push(CheckResult::Passed(Pass::TtlInRange));
+            }
+        }
+
+        if self.request.dnssec {
+            if authenticated {
+                check_results.push(CheckResult::Passed(Pass::Authenticated));
+            }
+            else {
+                check_results.push(CheckResult::Failed(Fail::NotAuthenticated));
+            }
+        }
+
+        let mut value_results = match (&self.condition, results.is_empty()) {
             (Condition::Present(expected_value), false) => {
                 if results.iter().any(|a| **a == *expected_value) {
                     vec![ CheckResult::Passed(Pass::RecordPresent) ]
@@ -243,7 +465,10 @@ impl<D: RunDns> RunCheck<D> for DnsCheck {
                     vec![ CheckResult::Failed(Fail::RecordDifferent { got_values: results }) ]
                 }
             }
-            (Condition::Present(_), true) => {
+            (Condition::Values(expected_values, values_match), false) => {
+                vec![ compare_values(expected_values, *values_match, results) ]
+            }
+            (Condition::Present(_) | Condition::Values(..), true) => {
                 vec![ CheckResult::Failed(Fail::RecordMissing) ]
             }
             (Condition::Missing, false) => {
@@ -252,6 +477,48 @@ impl<D: RunDns> RunCheck<D> for DnsCheck {
             (Condition::Missing, true) => {
                 vec![ CheckResult::Passed(Pass::RecordMissing) ]
             }
+        };
+
+        value_results.extend(check_results);
+        value_results
+    }
+}
+
+/// Compares the values in a response against a set of expected values.
+fn compare_values(expected_values: &[String], values_match: ValuesMatch, results: Vec<Arc<str>>) -> CheckResult<Pass, Fail> {
+    let missing = expected_values.iter()
+                                 .filter(|e| ! results.iter().any(|a| **a == ***e))
+                                 .cloned()
+                                 .collect::<Vec<_>>();
+
+    match values_match {
+        ValuesMatch::Any => {
+            if missing.len() < expected_values.len() {
+                CheckResult::Passed(Pass::RecordPresent)
+            }
+            else {
+                CheckResult::Failed(Fail::RecordDifferent { got_values: results })
+            }
+        }
+        ValuesMatch::All => {
+            if missing.is_empty() {
+                CheckResult::Passed(Pass::AllValuesPresent)
+            }
+            else {
+                CheckResult::Failed(Fail::ValuesDifferent { missing, extra: Vec::new() })
+            }
+        }
+        ValuesMatch::Exact => {
+            let extra = results.into_iter()
+                               .filter(|a| ! expected_values.iter().any(|e| **a == **e))
+                               .collect::<Vec<_>>();
+
+            if missing.is_empty() && extra.is_empty() {
+                CheckResult::Passed(Pass::ExactValuesPresent)
+            }
+            else {
+                CheckResult::Failed(Fail::ValuesDifferent { missing, extra })
+            }
         }
     }
 }
@@ -269,6 +536,18 @@ pub enum Pass {
 
     /// The domain exists, but there is no record for the given type.
     RecordMissing,
+
+    /// Every one of the expected values is present.
+    AllValuesPresent,
+
+    /// The values present are exactly the expected values.
+    ExactValuesPresent,
+
+    /// The TTL of every record is within the range.
+    TtlInRange,
+
+    /// The response had the Authenticated Data flag set.
+    Authenticated,
 }
 
 /// The failure result of running a DNS check.
@@ -290,8 +569,22 @@ pub enum Fail {
     /// There is a record for the given type, but its value was not what we
     /// were expecting.
     RecordDifferent {
-        got_values: Vec<Rc<str>>,
-    }
+        got_values: Vec<Arc<str>>,
+    },
+
+    /// The values present did not match the expected set of values: some
+    /// were missing, or there were extra ones.
+    ValuesDifferent {
+        missing: Vec<String>,
+        extra: Vec<Arc<str>>,
+    },
+
+    /// A record had a TTL outside the range.
+    TtlOutOfRange(u32),
+
+    /// The response did not have the Authenticated Data flag set, so it was
+    /// not validated with DNSSEC.
+    NotAuthenticated,
 }
 
 impl PassResult for Pass {}
@@ -313,6 +606,18 @@ impl fmt::Display for Pass {
             Self::RecordMissing => {
                 write!(f, "there is no record present")
             }
+            Self::AllValuesPresent => {
+                write!(f, "all the values are present")
+            }
+            Self::ExactValuesPresent => {
+                write!(f, "exactly the expected values are present")
+            }
+            Self::TtlInRange => {
+                write!(f, "the TTL is in range")
+            }
+            Self::Authenticated => {
+                write!(f, "the response is validated with DNSSEC")
+            }
         }
     }
 }
@@ -335,6 +640,25 @@ impl fmt::Display for Fail {
             Self::RecordDifferent { got_values } => {
                 write!(f, "the record is different, got ‘{:?}’ instead", got_values)
             }
+            Self::ValuesDifferent { missing, extra } => {
+                write!(f, "the values are different")?;
+
+                if ! missing.is_empty() {
+                    write!(f, ", missing {}", quoted_list(missing, "and"))?;
+                }
+
+                if ! extra.is_empty() {
+                    write!(f, ", with extra {}", quoted_list(extra, "and"))?;
+                }
+
+                Ok(())
+            }
+            Self::TtlOutOfRange(ttl) => {
+                write!(f, "the TTL is out of range, got ‘{}’", ttl)
+            }
+            Self::NotAuthenticated => {
+                write!(f, "the response is not validated with DNSSEC")
+            }
         }
     }
 }
@@ -8,22 +8,53 @@
 //! status = 200
 //! ```
 //!
+//! ```toml
+//! [[http]]
+//! url = "https://specsheet.software/health"
+//! status = [200, "3xx"]
+//! ```
+//!
+//! ```toml
+//! [[http]]
+//! url = "https://specsheet.software/"
+//! also = { X-Powered-By = { absent = true }, Strict-Transport-Security = { regex = "max-age=\\d+" } }
+//! ```
+//!
+//! ```toml
+//! [[http]]
+//! url = "https://self-signed.badssl.com/"
+//! expect_cert_error = true
+//! ```
+//!
+//! ```toml
+//! [[http]]
+//! url = "http://specsheet.software/"
+//! follow_redirects = true
+//! final_url = "https://specsheet.software/"
+//! status = 200
+//! ```
+//!
 //! # Commands
 //!
 //! This check works by running `curl`.
 
 
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::*;
 use mime::Mime;
+use regex::Regex;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
 use crate::contents::{self, ContentsMatcher};
+use crate::numbers::NumberMatcher;
 use crate::read::{TomlValue, ValueExtras, ReadError, Rewrites};
 
 /// The HTTP check makes a HTTP request and checks the response.
@@ -34,12 +65,19 @@ pub struct HttpCheck {
     request: RequestParams,
 
     /// Test: What the response HTTP status should be.
-    status: Option<i32>,
+    status: Option<StatusCheck>,
 
     /// Extra conditions for the HTTP headers.
     headers: HeaderConditions,
 
     body: Option<ContentsMatcher>,
+
+    /// Extra conditions for the chain of redirects that was followed.
+    redirects: RedirectConditions,
+
+    /// Test: Whether the request should fail because of a TLS certificate
+    /// problem, rather than producing a response.
+    expect_cert_error: bool,
 }
 
 /// The parameters that make up a complete HTTP request.
@@ -51,6 +89,16 @@ pub struct RequestParams {
 
     /// Any extra HTTP headers to be sent.
     pub extra_headers: BTreeMap<String, String>,
+
+    /// Whether to skip verifying the server’s certificate.
+    pub insecure: bool,
+
+    /// The path to a client certificate to present to the server.
+    pub client_cert: Option<PathBuf>,
+
+    /// Whether to follow redirects, testing the last response in the chain
+    /// instead of the first.
+    pub follow_redirects: bool,
 }
 
 #[derive(PartialEq, Debug)]
@@ -71,7 +119,54 @@ struct HeaderConditions {
     encoding: Option<String>,
 
     /// Test: A collection of other headers.
-    also: BTreeMap<String, String>,
+    also: BTreeMap<String, HeaderMatcher>,
+}
+
+/// A condition on one of the other headers in the response.
+#[derive(PartialEq, Debug)]
+enum HeaderMatcher {
+
+    /// The header should have this value, optionally ignoring case.
+    Equals(String, bool),
+
+    /// The header should have a value matching this regex.
+    Regex(String),
+
+    /// The header should be present, with any value.
+    Present,
+
+    /// The header should not be present.
+    Absent,
+}
+
+#[derive(PartialEq, Debug)]
+struct RedirectConditions {
+
+    /// Test: The URL that the chain of redirects should end up at.
+    final_url: Option<String>,
+
+    /// Test: The largest number of redirects that should be followed.
+    max_redirects: Option<u32>,
+}
+
+/// The set of HTTP statuses that a response is allowed to have.
+#[derive(PartialEq, Debug)]
+struct StatusCheck {
+    patterns: Vec<StatusPattern>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum StatusPattern {
+
+    /// One specific status, such as `200`.
+    Exact(i32),
+
+    /// Any status in a class, such as `2xx`. This holds the first digit.
+    Class(i32),
+
+    /// Any status that satisfies a table of comparisons, such as
+    /// `{ between = [200, 299] }`.
+    Matching(NumberMatcher),
 }
 
 #[derive(PartialEq, Debug)]
@@ -89,12 +184,36 @@ enum ContentTypeCheck {
 
 impl fmt::Display for HttpCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { request, status, headers, body } = &self;
+        let Self { request, status, headers, body, redirects, expect_cert_error } = &self;
 
         write!(f, "HTTP request to ‘{}’", request.url)?;
 
+        if request.insecure {
+            write!(f, " without certificate verification")?;
+        }
+
+        if let Some(cert) = &request.client_cert {
+            write!(f, " with client certificate ‘{}’", cert.display())?;
+        }
+
+        if *expect_cert_error {
+            return write!(f, " fails with a certificate error");
+        }
+
+        if let Some(max) = redirects.max_redirects {
+            write!(f, " following at most {} redirect{}", max, if max == 1 { "" } else { "s" })?;
+        }
+        else if request.follow_redirects {
+            write!(f, " following redirects")?;
+        }
+
+        if let Some(final_url) = &redirects.final_url {
+            write!(f, " ends at ‘{}’", final_url)?;
+        }
+
         if let Some(status) = status {
-            write!(f, " has status ‘{}’", status)?;
+            if redirects.final_url.is_some() { write!(f, ",")?; }
+            write!(f, " has status {}", status)?;
         }
 
         if let Some(ct) = &headers.content_type {
@@ -122,8 +241,15 @@ impl fmt::Display for HttpCheck {
             contents_matcher.describe(f, "body")?;
         }
 
-        if status.is_none() && headers.content_type.is_none() && headers.redirect_to.is_none()
-        && headers.server.is_none() && headers.encoding.is_none() && body.is_none() {
+        let described_before = status.is_some() || headers.content_type.is_some() || headers.redirect_to.is_some()
+                            || headers.server.is_some() || headers.encoding.is_some() || body.is_some() || redirects.final_url.is_some();
+
+        for (index, (header, matcher)) in headers.also.iter().enumerate() {
+            if described_before || index > 0 { write!(f, ",")?; }
+            matcher.describe(f, header)?;
+        }
+
+        if ! described_before && headers.also.is_empty() {
             write!(f, " succeeds")?;
         }
 
@@ -131,6 +257,43 @@ impl fmt::Display for HttpCheck {
     }
 }
 
+impl HeaderMatcher {
+    fn describe(&self, f: &mut fmt::Formatter<'_>, header: &str) -> fmt::Result {
+        match self {
+            Self::Equals(value, false)  => write!(f, " has header ‘{}’ with value ‘{}’", header, value),
+            Self::Equals(value, true)   => write!(f, " has header ‘{}’ with value ‘{}’ (ignoring case)", header, value),
+            Self::Regex(regex)          => write!(f, " has header ‘{}’ matching regex ‘/{}/’", header, regex),
+            Self::Present               => write!(f, " has header ‘{}’", header),
+            Self::Absent                => write!(f, " has no header ‘{}’", header),
+        }
+    }
+}
+
+impl fmt::Display for StatusCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if i > 0 { write!(f, " or ")?; }
+
+            match pattern {
+                StatusPattern::Matching(matcher)  => write!(f, "{}", matcher)?,
+                _                                 => write!(f, "‘{}’", pattern)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for StatusPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(status)  => write!(f, "{}", status),
+            Self::Class(digit)   => write!(f, "{}xx", digit),
+            Self::Matching(m)    => write!(f, "{}", m),
+        }
+    }
+}
+
 impl fmt::Display for ContentTypeCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -149,13 +312,39 @@ impl Check for HttpCheck {
 
 impl HttpCheck {
     pub fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["url", "headers", "status", "server", "encoding", "content_type", "redirect_to", "body", "also"])?;
+        table.ensure_only_keys(&["url", "headers", "status", "server", "encoding", "content_type", "redirect_to", "body", "also",
+                                 "insecure", "client_cert", "expect_cert_error", "follow_redirects", "final_url", "max_redirects"])?;
 
         let request = RequestParams::read(table, rewrites)?;
-        let status = table.get("status").map(|e| e.as_integer().unwrap() as i32);
+        let status = table.get("status").map(StatusCheck::read).transpose()?;
         let headers = HeaderConditions::read(table, rewrites)?;
         let body = table.get("body").map(|e| ContentsMatcher::read("body", e)).transpose()?;
-        Ok(Self { request, status, headers, body })
+        let redirects = RedirectConditions::read(table, rewrites)?;
+
+        // The last response in a chain of redirects is never a redirect
+        // itself, so there would be no `Location` header to test.
+        if request.follow_redirects && table.get("redirect_to").is_some() {
+            return Err(ReadError::conflict2("redirect_to", "follow_redirects", TomlValue::Boolean(true)));
+        }
+
+        if ! request.follow_redirects {
+            for &param in &["final_url", "max_redirects"] {
+                if table.get(param).is_some() {
+                    return Err(ReadError::conflict2(param, "follow_redirects", TomlValue::Boolean(false)));
+                }
+            }
+        }
+
+        let expect_cert_error = table.get("expect_cert_error").map(|e| e.boolean_or_error("expect_cert_error")).transpose()?.unwrap_or(false);
+        if expect_cert_error {
+            for &param in &["status", "server", "encoding", "content_type", "redirect_to", "body", "also", "final_url", "max_redirects"] {
+                if table.get(param).is_some() {
+                    return Err(ReadError::conflict2(param, "expect_cert_error", TomlValue::Boolean(true)));
+                }
+            }
+        }
+
+        Ok(Self { request, status, headers, body, redirects, expect_cert_error })
     }
 }
 
@@ -168,10 +357,61 @@ impl RequestParams {
         }
 
         let extra_headers = table.get("headers")
-                                 .map(|e| e.string_map_or_read_error("headers").unwrap())
+                                 .map(|e| e.string_map_or_read_error("headers"))
+                                 .transpose()?
                                  .unwrap_or_default();
 
-        Ok(Self { url, extra_headers })
+        let insecure = table.get("insecure").map(|e| e.boolean_or_error("insecure")).transpose()?.unwrap_or(false);
+
+        let client_cert = match table.get("client_cert") {
+            Some(cert_value) => {
+                let cert = cert_value.string_or_error("client_cert")?;
+                if cert.is_empty() {
+                    return Err(ReadError::invalid("client_cert", cert_value.clone(), "it must not be empty"));
+                }
+
+                Some(rewrites.path(cert))
+            }
+            None => {
+                None
+            }
+        };
+
+        let follow_redirects = table.get("follow_redirects").map(|e| e.boolean_or_error("follow_redirects")).transpose()?.unwrap_or(false);
+
+        Ok(Self { url, extra_headers, insecure, client_cert, follow_redirects })
+    }
+}
+
+impl RedirectConditions {
+    fn read(table: &TomlValue, rewrites: &Rewrites) -> Result<Self, ReadError> {
+        let final_url = match table.get("final_url") {
+            Some(final_url_value) => {
+                let final_url = final_url_value.string_or_error("final_url")?;
+                if final_url.is_empty() {
+                    return Err(ReadError::invalid("final_url", final_url_value.clone(), "it must not be empty"));
+                }
+
+                Some(rewrites.url(final_url))
+            }
+            None => {
+                None
+            }
+        };
+
+        let max_redirects = match table.get("max_redirects") {
+            Some(max_value) => {
+                match u32::try_from(max_value.number_or_error("max_redirects")?) {
+                    Ok(max)  => Some(max),
+                    Err(_)   => return Err(ReadError::invalid("max_redirects", max_value.clone(), "it must not be negative")),
+                }
+            }
+            None => {
+                None
+            }
+        };
+
+        Ok(Self { final_url, max_redirects })
     }
 }
 
@@ -182,11 +422,134 @@ impl HeaderConditions {
             redirect_to: table.get("redirect_to").map(|e| e.string_or_error("redirect_to")).transpose()?.map(|e| rewrites.url(e)),
             server: table.get("server").map(|e| e.string_or_error("server")).transpose()?,
             encoding: table.get("encoding").map(|e| e.string_or_error("encoding")).transpose()?,
-            also: table.get("also").map(|e| e.string_map_or_read_error("also")).transpose()?.unwrap_or_default(),
+            also: table.get("also").map(HeaderMatcher::read_map).transpose()?.unwrap_or_default(),
         })
     }
 }
 
+impl HeaderMatcher {
+    fn read_map(also_value: &TomlValue) -> Result<BTreeMap<String, Self>, ReadError> {
+        also_value.ensure_table("also")?;
+
+        let mut map = BTreeMap::new();
+        for (header, value) in also_value.as_table().into_iter().flatten() {
+            map.insert(header.clone(), Self::read(value)?);
+        }
+
+        Ok(map)
+    }
+
+    fn read(value: &TomlValue) -> Result<Self, ReadError> {
+        if let Some(string) = value.as_str() {
+            return Ok(Self::Equals(string.into(), false));
+        }
+
+        value.ensure_table("also")?;
+        value.ensure_only_keys(&["value", "case_insensitive", "regex", "absent"])?;
+
+        let case_insensitive = value.get("case_insensitive").map(|e| e.boolean_or_error("case_insensitive")).transpose()?;
+
+        if let Some(string_value) = value.get("value") {
+            for &param in &["regex", "absent"] {
+                if value.get(param).is_some() {
+                    return Err(ReadError::conflict(param, "value"));
+                }
+            }
+
+            let string = string_value.string_or_error("value")?;
+            return Ok(Self::Equals(string, case_insensitive.unwrap_or(false)));
+        }
+
+        if case_insensitive.is_some() {
+            return Err(ReadError::MissingParameter { parameter_name: "value" });
+        }
+
+        if let Some(regex_value) = value.get("regex") {
+            if value.get("absent").is_some() {
+                return Err(ReadError::conflict("absent", "regex"));
+            }
+
+            let regex = regex_value.string_or_error("regex")?;
+            if regex.is_empty() {
+                return Err(ReadError::invalid("regex", regex_value.clone(), "it must not be empty"));
+            }
+            else if let Err(e) = Regex::new(&regex) {
+                warn!("Invalid header regex: {}", e);
+                return Err(ReadError::invalid("regex", regex_value.clone(), "it must be a valid regex"));
+            }
+
+            return Ok(Self::Regex(regex));
+        }
+
+        if let Some(absent_value) = value.get("absent") {
+            let absent = absent_value.boolean_or_error("absent")?;
+            return Ok(if absent { Self::Absent } else { Self::Present });
+        }
+
+        Err(ReadError::invalid("also", value.clone(), "it must have a ‘value’, ‘regex’, or ‘absent’ parameter"))
+    }
+}
+
+impl StatusCheck {
+    fn read(status_value: &TomlValue) -> Result<Self, ReadError> {
+        if status_value.is_table() {
+            let matcher = NumberMatcher::read("status", status_value)?;
+            return Ok(Self { patterns: vec![ StatusPattern::Matching(matcher) ] });
+        }
+
+        let patterns = if let Some(array) = status_value.as_array() {
+            if array.is_empty() {
+                return Err(ReadError::invalid("status", status_value.clone(), "it must not be empty"));
+            }
+
+            array.iter().map(StatusPattern::read).collect::<Option<Vec<_>>>()
+        }
+        else {
+            StatusPattern::read(status_value).map(|p| vec![ p ])
+        };
+
+        match patterns {
+            Some(patterns) => Ok(Self { patterns }),
+            None           => Err(ReadError::invalid("status", status_value.clone(), "it must be a status, a class such as ‘2xx’, or an array of them")),
+        }
+    }
+
+    /// Whether the given status is one of the allowed ones.
+    fn matches(&self, status: i32) -> bool {
+        self.patterns.iter().any(|p| p.matches(status))
+    }
+}
+
+impl StatusPattern {
+    fn read(value: &TomlValue) -> Option<Self> {
+        match value {
+            TomlValue::Integer(status) if (100 ..= 999).contains(status) => {
+                Some(Self::Exact(*status as i32))
+            }
+            TomlValue::String(class) => {
+                match class.to_ascii_lowercase().as_bytes() {
+                    [ digit @ b'1' ..= b'5', b'x', b'x' ]  => Some(Self::Class(i32::from(digit - b'0'))),
+                    _                                      => None,
+                }
+            }
+            TomlValue::Table(_) if NumberMatcher::is_comparison_table(value) => {
+                NumberMatcher::read("status", value).ok().map(Self::Matching)
+            }
+            _ => {
+                None
+            }
+        }
+    }
+
+    fn matches(&self, status: i32) -> bool {
+        match self {
+            Self::Exact(expected)  => status == *expected,
+            Self::Class(digit)     => status / 100 == *digit,
+            Self::Matching(m)      => m.matches(status.into()),
+        }
+    }
+}
+
 impl ContentTypeCheck {
     fn read(table: &TomlValue) -> Result<Option<Self>, ReadError> {
         let ct1 = match table.get("content_type") {
@@ -216,6 +579,58 @@ impl ContentTypeCheck {
 }
 
 
+// ---- analysis properties ----
+
+impl HttpCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        let (host, port) = url_host_and_port(&self.request.url);
+        if let Some(host) = host {
+            points.push(DataPoint::InvolvesHost(host));
+        }
+
+        if let Some(port) = port {
+            points.push(DataPoint::InvolvesPort(port));
+        }
+
+        points
+    }
+}
+
+/// Picks out the host and port of a URL, using the scheme’s default port
+/// if it doesn’t have one.
+fn url_host_and_port(url: &str) -> (Option<&str>, Option<u16>) {
+    let (scheme, rest) = match url.find("://") {
+        Some(i)  => (&url[.. i], &url[i + 3 ..]),
+        None     => ("http", url),
+    };
+
+    let authority = &rest[.. rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+
+    // IPv6 addresses have colons in them, so they get put in brackets.
+    let port_start = if authority.starts_with('[') { authority.find("]:").map(|i| i + 1) }
+                                                else { authority.rfind(':') };
+
+    let (host, port) = match port_start {
+        Some(i)  => (&authority[.. i], authority[i + 1 ..].parse().ok()),
+        None     => (authority, None),
+    };
+
+    let port = port.or_else(|| {
+        match &*scheme.to_ascii_lowercase() {
+            "http"   => Some(80),
+            "https"  => Some(443),
+            _        => None,
+        }
+    });
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (Some(host).filter(|h| ! h.is_empty()), port)
+}
+
+
 // ---- running the check ----
 
 /// The interface to making HTTP requests used by [`HttpCheck`].
@@ -231,7 +646,7 @@ pub trait RunHttp {
     /// Running the command if it hasn’t been run already for the given
     /// request, examine the result and return its fields as an output
     /// value.
-    fn get_response(&self, executor: &mut Executor, request: HttpRequest) -> Result<Rc<Self::Output>, Rc<ExecError>>;
+    fn get_response(&self, executor: &mut Executor, request: HttpRequest) -> Result<Arc<Self::Output>, Arc<ExecError>>;
 }
 
 /// Accessors for parts of an HTTP response.
@@ -254,6 +669,15 @@ pub trait HttpResponse {
 
     /// The HTTP body, as bytes.
     fn body(&self) -> Vec<u8>;
+
+    /// A description of the TLS certificate problem that prevented the
+    /// request from completing, if there was one.
+    fn certificate_error(&self) -> Option<&str>;
+
+    /// The `Location` headers of each redirect that was followed to reach
+    /// this response, in order. This is empty if redirects were not being
+    /// followed.
+    fn redirect_locations(&self) -> Vec<&str>;
 }
 
 /// The fields that make up an HTTP request. Requests get made by a type
@@ -266,6 +690,15 @@ pub struct HttpRequest {
 
     /// Any extra HTTP headers to send as part of the request.
     pub headers: BTreeMap<String, String>,
+
+    /// Whether to skip verifying the server’s certificate.
+    pub insecure: bool,
+
+    /// The path to a client certificate to present to the server.
+    pub client_cert: Option<PathBuf>,
+
+    /// Whether to follow any redirects, returning the last response.
+    pub follow_redirects: bool,
 }
 
 
@@ -283,6 +716,9 @@ impl HttpCheck {
         HttpRequest {
             url: self.request.url.clone(),
             headers: extra_headers,
+            insecure: self.request.insecure,
+            client_cert: self.request.client_cert.clone(),
+            follow_redirects: self.request.follow_redirects,
         }
     }
 }
@@ -303,6 +739,13 @@ impl<H: RunHttp> RunCheck<H> for HttpCheck {
             Err(e)  => return vec![ CheckResult::CommandError(e) ],
         };
 
+        match (self.expect_cert_error, rs.certificate_error()) {
+            (true,  Some(error))  => return vec![ CheckResult::Passed(Pass::CertificateRejected(error.into())) ],
+            (true,  None)         => return vec![ CheckResult::Failed(Fail::CertificateAccepted) ],
+            (false, Some(error))  => return vec![ CheckResult::Failed(Fail::CertificateError(error.into())) ],
+            (false, None)         => {/* continue */},
+        }
+
         let mut results = vec![ CheckResult::Passed(Pass::HttpSucceeded) ];
 
         let got_status = match rs.status() {
@@ -310,7 +753,18 @@ impl<H: RunHttp> RunCheck<H> for HttpCheck {
             None       => return vec![ CheckResult::Failed(Fail::HttpFailed) ],
         };
 
-        if let Some(status) = self.status {
+        let locations = rs.redirect_locations();
+
+        if let Some(max) = self.redirects.max_redirects {
+            results.push(self.redirect_count_result(max, locations.len()));
+        }
+
+        if let Some(final_url) = &self.redirects.final_url {
+            let got_url = locations.iter().fold(self.request.url.clone(), |url, location| resolve_location(&url, location));
+            results.push(self.final_url_result(final_url, got_url));
+        }
+
+        if let Some(status) = &self.status {
             results.push(self.status_result(status, got_status));
         }
 
@@ -327,21 +781,11 @@ impl<H: RunHttp> RunCheck<H> for HttpCheck {
         }
 
         if let Some(content_matcher) = &self.body {
-            results.push(self.body_result(&rs.body(), content_matcher));
+            results.push(self.body_result(&rs.body(), content_matcher, executor.working_directory()));
         }
 
-        for (header, expected) in &self.headers.also {
-            if let Some(actual) = rs.header(header) {
-                if actual == expected {
-                    results.push(CheckResult::Passed(Pass::HeaderMatch(header.into())));
-                }
-                else {
-                    results.push(CheckResult::Failed(Fail::HeaderMismatch(header.into(), actual.into())));
-                }
-            }
-            else {
-                results.push(CheckResult::Failed(Fail::HeaderMissing(header.into())));
-            }
+        for (header, matcher) in &self.headers.also {
+            results.push(self.header_result(header, matcher, rs.header(header)));
         }
 
         results
@@ -352,8 +796,8 @@ impl HttpCheck {
 
     /// The check result that should be added to the list, given expected and
     /// received HTTP statuses.
-    fn status_result(&self, expected_status: i32, got_status: i32) -> CheckResult<Pass, Fail> {
-        if got_status == expected_status {
+    fn status_result(&self, expected_status: &StatusCheck, got_status: i32) -> CheckResult<Pass, Fail> {
+        if expected_status.matches(got_status) {
             CheckResult::Passed(Pass::StatusMatch)
         }
         else {
@@ -448,6 +892,51 @@ impl HttpCheck {
         }
     }
 
+    /// The check result for the number of redirects that were followed.
+    fn redirect_count_result(&self, max_redirects: u32, got_count: usize) -> CheckResult<Pass, Fail> {
+        if got_count <= max_redirects as usize {
+            CheckResult::Passed(Pass::RedirectCountOk(got_count))
+        }
+        else {
+            CheckResult::Failed(Fail::TooManyRedirects(got_count))
+        }
+    }
+
+    /// The check result for the URL at the end of the chain of redirects.
+    fn final_url_result(&self, expected_url: &str, got_url: String) -> CheckResult<Pass, Fail> {
+        if got_url == expected_url {
+            CheckResult::Passed(Pass::FinalUrlMatch)
+        }
+        else {
+            CheckResult::Failed(Fail::FinalUrlMismatch(got_url))
+        }
+    }
+
+    /// The check result for one of the other headers.
+    fn header_result(&self, header: &str, matcher: &HeaderMatcher, got_value: Option<&str>) -> CheckResult<Pass, Fail> {
+        let actual = match (matcher, got_value) {
+            (HeaderMatcher::Absent, None)     => return CheckResult::Passed(Pass::HeaderAbsent(header.into())),
+            (HeaderMatcher::Absent, Some(_))  => return CheckResult::Failed(Fail::HeaderPresent(header.into())),
+            (_, None)                         => return CheckResult::Failed(Fail::HeaderMissing(header.into())),
+            (_, Some(actual))                 => actual,
+        };
+
+        let is_match = match matcher {
+            HeaderMatcher::Equals(expected, false)  => actual == expected,
+            HeaderMatcher::Equals(expected, true)   => actual.eq_ignore_ascii_case(expected),
+            HeaderMatcher::Regex(regex)             => Regex::new(regex).is_ok_and(|re| re.is_match(actual)),
+            HeaderMatcher::Present                  => true,
+            HeaderMatcher::Absent                   => unreachable!(),
+        };
+
+        if is_match {
+            CheckResult::Passed(Pass::HeaderMatch(header.into()))
+        }
+        else {
+            CheckResult::Failed(Fail::HeaderMismatch(header.into(), actual.into()))
+        }
+    }
+
     /// The check result for the `Content-Encoding` header.
     fn encoding_result(&self, rs: &impl HttpResponse, encoding: &str) -> CheckResult<Pass, Fail> {
         if let Some(actual) = rs.encoding() {
@@ -463,8 +952,8 @@ impl HttpCheck {
         }
     }
 
-    fn body_result(&self, body: &[u8], body_matcher: &ContentsMatcher) -> CheckResult<Pass, Fail> {
-        match body_matcher.check(&body) {
+    fn body_result(&self, body: &[u8], body_matcher: &ContentsMatcher, directory: Option<&Path>) -> CheckResult<Pass, Fail> {
+        match body_matcher.check(body, directory) {
             CheckResult::Passed(pass) => {
                 CheckResult::Passed(Pass::ContentsPass(pass))
             }
@@ -478,6 +967,31 @@ impl HttpCheck {
     }
 }
 
+/// Works out the URL that a `Location` header points to, given the URL of
+/// the response it was in. Locations can be absolute URLs, or relative to
+/// the scheme, host, or path of the original URL.
+fn resolve_location(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.into();
+    }
+
+    let scheme_end = base.find("://").map_or(0, |i| i + 3);
+    if location.starts_with("//") {
+        return format!("{}{}", &base[.. scheme_end.saturating_sub(2)], location);
+    }
+
+    let path_start = base[scheme_end ..].find('/').map_or(base.len(), |i| scheme_end + i);
+    if location.starts_with('/') {
+        return format!("{}{}", &base[.. path_start], location);
+    }
+
+    let path_end = base[path_start ..].find(['?', '#']).map_or(base.len(), |i| path_start + i);
+    match base[path_start .. path_end].rfind('/') {
+        Some(slash)  => format!("{}{}", &base[..= path_start + slash], location),
+        None         => format!("{}/{}", &base[.. path_start], location),
+    }
+}
+
 fn mime_is(mime: &Mime, one: &str, two: &str) -> bool {
     mime.type_() == one && mime.subtype() == two && mime.suffix().is_none()
 }
@@ -493,7 +1007,7 @@ pub enum Pass {
     /// We were able to make a successful HTTP call.
     HttpSucceeded,
 
-    /// The HTTP status was the expected number.
+    /// The HTTP status was one of the expected numbers.
     StatusMatch,
 
     /// The `Content-Type` header matches.
@@ -502,6 +1016,13 @@ pub enum Pass {
     /// The status is a redirect and the `Location` header matches.
     RedirectMatch,
 
+    /// No more than the maximum number of redirects were followed; this
+    /// many were.
+    RedirectCountOk(usize),
+
+    /// The chain of redirects ended at the expected URL.
+    FinalUrlMatch,
+
     /// The `Server` header matches.
     ServerMatch,
 
@@ -511,8 +1032,14 @@ pub enum Pass {
     /// Another header matches.
     HeaderMatch(String),
 
+    /// Another header is absent, as expected.
+    HeaderAbsent(String),
+
     /// The body matches its contents predicate.
     ContentsPass(contents::Pass),
+
+    /// The request failed with a certificate error, as expected.
+    CertificateRejected(String),
 }
 
 /// The failure result of running an HTTP check.
@@ -522,7 +1049,8 @@ pub enum Fail {
     /// We were not able to make an HTTP call.
     HttpFailed,
 
-    /// The HTTP status was not the expected number; instead, it was this.
+    /// The HTTP status was not one of the expected numbers; instead, it was
+    /// this.
     StatusMismatch(i32),
 
     /// The `Content-Type` header was this.
@@ -541,6 +1069,14 @@ pub enum Fail {
     /// The `Location` header was missing.
     RedirectMissing,
 
+    /// More than the maximum number of redirects were followed; this many
+    /// were.
+    TooManyRedirects(usize),
+
+    /// The chain of redirects ended at a different URL; instead, it ended
+    /// at this one.
+    FinalUrlMismatch(String),
+
     /// The `Server` header was this.
     ServerMismatch(String),
 
@@ -556,8 +1092,18 @@ pub enum Fail {
     /// Another header is missing.
     HeaderMissing(String),
 
+    /// Another header is present when it should be absent.
+    HeaderPresent(String),
+
     /// The body did not match its contents predicate.
     ContentsFail(contents::Fail),
+
+    /// The request failed with an unexpected certificate error.
+    CertificateError(String),
+
+    /// The request was expected to fail with a certificate error, but it
+    /// succeeded.
+    CertificateAccepted,
 }
 
 impl PassResult for Pass {}
@@ -596,6 +1142,12 @@ impl fmt::Display for Pass {
             Self::RedirectMatch => {
                 write!(f, "Location header matches")
             }
+            Self::RedirectCountOk(count) => {
+                write!(f, "Followed {} redirect{}", count, if *count == 1 { "" } else { "s" })
+            }
+            Self::FinalUrlMatch => {
+                write!(f, "Final URL matches")
+            }
             Self::ServerMatch => {
                 write!(f, "Server header matches")
             }
@@ -605,9 +1157,15 @@ impl fmt::Display for Pass {
             Self::HeaderMatch(header) => {
                 write!(f, "HTTP header ‘{}’ matches", header)
             }
+            Self::HeaderAbsent(header) => {
+                write!(f, "HTTP header ‘{}’ is absent", header)
+            }
             Self::ContentsPass(contents_pass) => {
                 contents_pass.fmt(f)
             }
+            Self::CertificateRejected(error) => {
+                write!(f, "Certificate was rejected ({})", error)
+            }
         }
     }
 }
@@ -636,6 +1194,12 @@ impl fmt::Display for Fail {
             Self::RedirectMissing => {
                 write!(f, "Location header is missing")
             }
+            Self::TooManyRedirects(count) => {
+                write!(f, "Followed {} redirects", count)
+            }
+            Self::FinalUrlMismatch(url) => {
+                write!(f, "Final URL is ‘{}’", url)
+            }
             Self::ServerMismatch(srv) => {
                 write!(f, "Server header is ‘{}’", srv)
             }
@@ -651,9 +1215,18 @@ impl fmt::Display for Fail {
             Self::HeaderMissing(header) => {
                 write!(f, "HTTP header ‘{}’ was missing", header)
             }
+            Self::HeaderPresent(header) => {
+                write!(f, "HTTP header ‘{}’ was present", header)
+            }
             Self::ContentsFail(contents_fail) => {
                 contents_fail.fmt(f)
             }
+            Self::CertificateError(error) => {
+                write!(f, "Certificate error ({})", error)
+            }
+            Self::CertificateAccepted => {
+                write!(f, "Certificate was accepted")
+            }
         }
     }
 }
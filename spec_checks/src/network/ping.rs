@@ -14,10 +14,11 @@
 
 
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
+use spec_analysis::DataPoint;
 use spec_exec::{Executor, ExecError};
 
 use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
@@ -112,6 +113,15 @@ impl Condition {
 }
 
 
+// ---- analysis properties ----
+
+impl PingCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        vec![ DataPoint::InvolvesHost(&self.target.0) ]
+    }
+}
+
+
 // ---- running the check ----
 
 /// The interface to pinging servers used by [`PingCheck`].
@@ -124,7 +134,7 @@ pub trait RunPing {
     /// Running the command if it hasn’t been run already for this
     /// target, examine the output and return whether a response was
     /// received.
-    fn is_target_up(&self, executor: &mut Executor, target: &str) -> Result<bool, Rc<ExecError>>;
+    fn is_target_up(&self, executor: &mut Executor, target: &str) -> Result<bool, Arc<ExecError>>;
 }
 
 impl<P: RunPing> RunCheck<P> for PingCheck {
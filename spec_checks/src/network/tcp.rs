@@ -8,20 +8,42 @@
 //! state = 'open'
 //! ```
 //!
+//! After connecting, the check can read the banner the port sends, or
+//! perform a TLS handshake:
+//!
+//! ```toml
+//! [[tcp]]
+//! port = 22
+//! banner = { regex = '^SSH-2\.0' }
+//!
+//! [[tcp]]
+//! port = 443
+//! address = '192.0.2.1'
+//! tls = true
+//! sni = 'example.com'
+//! ```
+//!
 //! # Commands
 //!
-//! No commands are run for network checks; Specsheet deals with the network
-//! itself.
+//! No commands are run for plain network checks; Specsheet deals with the
+//! network itself. TLS handshakes are performed by running `openssl`.
 
 
 use std::fmt;
 use std::net::Ipv4Addr;
+use std::path::Path;
+use std::sync::Arc;
 
 use log::*;
 
-use crate::check::{Check, BuiltInCheck, CheckResult, PassResult, FailResult};
+use spec_analysis::DataPoint;
+use spec_exec::{Executor, ExecError};
+
+use crate::check::{Check, RunCheck, CheckResult, PassResult, FailResult};
 use crate::common::PortNumber;
+use crate::contents::{self, ContentsMatcher};
 use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
+use crate::ufw::{UfwCheck, Protocol};
 
 
 /// A check against the network; which other machines the local computer can
@@ -31,6 +53,8 @@ pub struct TcpCheck {
     request: Request,
     condition: Condition,
     ufw: Option<ExtraUfwCheck>,
+    banner: Option<ContentsMatcher>,
+    tls: Option<TlsRequest>,
 }
 
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
@@ -38,6 +62,19 @@ pub struct Request {
     pub port: PortNumber,
     pub target: Option<String>,
     pub source: Source,
+
+    /// Whether to read the banner the port sends after connecting.
+    pub read_banner: bool,
+}
+
+/// The details of a TLS handshake to perform with a port.
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub struct TlsRequest {
+    pub port: PortNumber,
+    pub target: Option<String>,
+
+    /// The server name to send during the handshake, if any.
+    pub sni: Option<String>,
 }
 
 /// What we expect to learn about the port from the response, if any.
@@ -64,7 +101,7 @@ struct ExtraUfwCheck {
 
 impl fmt::Display for TcpCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { request, condition, ufw } = &self;
+        let Self { request, condition, ufw, banner, tls } = &self;
 
         write!(f, "TCP port ‘{}’", request.port.0)?;
 
@@ -92,6 +129,20 @@ impl fmt::Display for TcpCheck {
             }
         }
 
+        if let Some(contents_matcher) = banner {
+            write!(f, " with")?;
+            contents_matcher.describe(f, "banner")?;
+        }
+
+        if let Some(tls) = tls {
+            if banner.is_some() { write!(f, ",")?; }
+
+            write!(f, " and speaks TLS")?;
+            if let Some(sni) = &tls.sni {
+                write!(f, " for ‘{}’", sni)?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -105,13 +156,24 @@ impl Check for TcpCheck {
 
 impl TcpCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["port", "address", "source", "state", "ufw"])?;
+        table.ensure_only_keys(&["port", "address", "source", "state", "ufw", "banner", "tls", "sni"])?;
 
         let request = Request::read(table)?;
         let condition = Condition::read(table)?;
         let ufw = ExtraUfwCheck::read(table)?;
+        let banner = table.get("banner").map(|e| ContentsMatcher::read("banner", e)).transpose()?;
+        let tls = TlsRequest::read(table, &request)?;
+
+        // A closed port can’t send a banner or shake hands.
+        if condition == Condition::Closed {
+            for &param in &["banner", "tls"] {
+                if table.get(param).is_some() {
+                    return Err(ReadError::conflict2(param, "state", TomlValue::String("closed".into())));
+                }
+            }
+        }
 
-        Ok(Self { request, condition, ufw })
+        Ok(Self { request, condition, ufw, banner, tls })
     }
 }
 
@@ -119,15 +181,16 @@ impl Request {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
         let port = PortNumber::read(table)?;
         let source = Source::read(table)?;
+        let read_banner = table.get("banner").is_some();
 
         let address_value = match table.get("address") {
             Some(a) => a,
-            None    => return Ok(Self { target: None, port, source }),
+            None    => return Ok(Self { target: None, port, source, read_banner }),
         };
 
         let address_string = address_value.string_or_error("address")?;
         if let Ok(address) = address_string.parse() {
-            Ok(Self { target: Some(address), port, source })
+            Ok(Self { target: Some(address), port, source, read_banner })
         }
         else {
             Err(ReadError::invalid("address", address_value.clone(), "it must be an IP address"))
@@ -135,6 +198,29 @@ impl Request {
     }
 }
 
+impl TlsRequest {
+    fn read(table: &TomlValue, request: &Request) -> Result<Option<Self>, ReadError> {
+        let tls = table.get("tls").map(|e| e.boolean_or_error("tls")).transpose()?.unwrap_or(false);
+
+        let sni = table.get("sni").map(|e| e.string_or_error("sni")).transpose()?;
+        if let Some(sni) = &sni {
+            if sni.is_empty() {
+                return Err(ReadError::invalid("sni", sni.clone().into(), "it must not be empty"));
+            }
+            else if ! tls {
+                return Err(ReadError::conflict2("sni", "tls", TomlValue::Boolean(false)));
+            }
+        }
+
+        if tls {
+            Ok(Some(Self { port: request.port, target: request.target.clone(), sni }))
+        }
+        else {
+            Ok(None)
+        }
+    }
+}
+
 impl Source {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
         let source_value = match table.get("source") {
@@ -191,6 +277,35 @@ impl ExtraUfwCheck {
 }
 
 
+// ---- analysis properties ----
+
+impl TcpCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        if let Some(target) = &self.request.target {
+            points.push(DataPoint::InvolvesHost(target));
+        }
+
+        points.push(DataPoint::InvolvesPort(self.request.port.0));
+        points
+    }
+}
+
+
+// ---- secondary checks ----
+
+impl TcpCheck {
+
+    /// Returns the ufw check given in the `ufw` sub-table, if there is one,
+    /// which gets run after this check.
+    pub fn ufw_check(&self) -> Option<UfwCheck> {
+        let ufw = self.ufw.as_ref()?;
+        Some(UfwCheck::for_port(self.request.port.0, Protocol::TCP, ufw.allow.clone()))
+    }
+}
+
+
 // ---- running the check ----
 
 /// The network interface used to send TCP packets by [`TcpCheck`].
@@ -200,41 +315,95 @@ pub trait RunTcp {
     #[allow(unused)]
     fn prime(&mut self, request: &Request) { }
 
+    /// Primes the command for performing a TLS handshake.
+    #[allow(unused)]
+    fn prime_tls(&mut self, request: &TlsRequest) { }
+
     /// Running the command if it hasn’t been run already, sends a TCP
-    /// request and reports back if it succeeded.
-    fn send_tcp_request(&self, request: &Request) -> bool;
+    /// request and reports back whether it succeeded, along with the
+    /// banner if the request asked for it.
+    fn send_tcp_request(&self, request: &Request) -> Connection;
+
+    /// Running the command if it hasn’t been run already, performs a TLS
+    /// handshake and reports back whether it succeeded.
+    fn tls_handshake(&self, executor: &mut Executor, request: &TlsRequest) -> Result<bool, Arc<ExecError>>;
+}
+
+/// What happened when trying to connect to a port.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Connection {
+
+    /// The connection was refused, or failed some other way.
+    Refused,
+
+    /// The connection succeeded. If the request asked for it, this contains
+    /// the banner that the port sent, which is empty if it sent nothing
+    /// before timing out.
+    Accepted(Option<Vec<u8>>),
 }
 
-impl<N: RunTcp> BuiltInCheck<N> for TcpCheck {
+impl<N: RunTcp> RunCheck<N> for TcpCheck {
     type PASS = Pass;
     type FAIL = Fail;
 
     fn load(&self, net: &mut N) {
-        net.prime(&self.request)
+        net.prime(&self.request);
+
+        if let Some(tls) = &self.tls {
+            net.prime_tls(tls);
+        }
     }
 
-    fn check(&self, net: &N) -> Vec<CheckResult<Pass, Fail>> {
+    fn check(&self, executor: &mut Executor, net: &N) -> Vec<CheckResult<Pass, Fail>> {
         info!("Running check");
 
         let result = net.send_tcp_request(&self.request);
 
         match (&self.condition, result) {
-            (Condition::Open, true) => {
-                vec![ CheckResult::Passed(Pass::ReceivedResponse) ]
+            (Condition::Open, Connection::Accepted(banner)) => {
+                let mut results = vec![ CheckResult::Passed(Pass::ReceivedResponse) ];
+
+                if let Some(banner_matcher) = &self.banner {
+                    results.push(banner_result(&banner.unwrap_or_default(), banner_matcher, executor.working_directory()));
+                }
+
+                if let Some(tls) = &self.tls {
+                    match net.tls_handshake(executor, tls) {
+                        Ok(true)   => results.push(CheckResult::Passed(Pass::TlsHandshake)),
+                        Ok(false)  => results.push(CheckResult::Failed(Fail::TlsHandshakeFailed)),
+                        Err(e)     => results.push(CheckResult::CommandError(e)),
+                    }
+                }
+
+                results
             }
-            (Condition::Open, false) => {
+            (Condition::Open, Connection::Refused) => {
                 vec![ CheckResult::Failed(Fail::ConnectionRefused) ]
             }
-            (Condition::Closed, true) => {
+            (Condition::Closed, Connection::Accepted(_)) => {
                 vec![ CheckResult::Failed(Fail::ReceivedResponse) ]
             }
-            (Condition::Closed, false) => {
+            (Condition::Closed, Connection::Refused) => {
                 vec![ CheckResult::Passed(Pass::ConnectionRefused) ]
             }
         }
     }
 }
 
+fn banner_result(banner: &[u8], banner_matcher: &ContentsMatcher, directory: Option<&Path>) -> CheckResult<Pass, Fail> {
+    match banner_matcher.check(banner, directory) {
+        CheckResult::Passed(pass) => {
+            CheckResult::Passed(Pass::BannerPass(pass))
+        }
+        CheckResult::Failed(fail) => {
+            CheckResult::Failed(Fail::BannerFail(fail))
+        }
+        CheckResult::CommandError(_) => {
+            unreachable!()
+        }
+    }
+}
+
 impl Request {
 
     /// Returns the address to send packets to.
@@ -251,18 +420,44 @@ impl Request {
 pub enum Pass {
     ReceivedResponse,
     ConnectionRefused,
+
+    /// The banner matches its contents predicate.
+    BannerPass(contents::Pass),
+
+    /// A TLS handshake with the port succeeded.
+    TlsHandshake,
 }
 
 /// The failure result of running a network check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(Debug)]
 pub enum Fail {
     ConnectionRefused,
     ReceivedResponse,
+
+    /// The banner did not match its contents predicate.
+    BannerFail(contents::Fail),
+
+    /// A TLS handshake with the port failed.
+    TlsHandshakeFailed,
 }
 
 impl PassResult for Pass {}
 
-impl FailResult for Fail {}
+impl FailResult for Fail {
+    fn command_output(&self) -> Option<(String, &String)> {
+        match self {
+            Self::BannerFail(fail)  => fail.command_output("Banner:"),
+            _                       => None,
+        }
+    }
+
+    fn diff_output(&self) -> Option<(String, &String, &String)> {
+        match self {
+            Self::BannerFail(fail)  => fail.diff_output(),
+            _                       => None,
+        }
+    }
+}
 
 
 // ---- check result descriptions ----
@@ -275,6 +470,12 @@ impl fmt::Display for Pass {
             Self::ConnectionRefused => {
                 write!(f, "connection refused")
             }
+            Self::BannerPass(contents_pass) => {
+                write!(f, "banner {}", contents_pass)
+            }
+            Self::TlsHandshake => {
+                write!(f, "TLS handshake succeeded")
+            }
         }
     }
 }
@@ -288,6 +489,12 @@ impl fmt::Display for Fail {
             Self::ReceivedResponse => {
                 write!(f, "received a response")
             }
+            Self::BannerFail(contents_fail) => {
+                write!(f, "banner {}", contents_fail)
+            }
+            Self::TlsHandshakeFailed => {
+                write!(f, "TLS handshake failed")
+            }
         }
     }
 }
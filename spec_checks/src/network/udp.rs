@@ -8,20 +8,41 @@
 //! state = 'no-response'
 //! ```
 //!
+//! The packet that gets sent, and the reply that comes back, can be
+//! specified to probe real services:
+//!
+//! ```toml
+//! [[udp]]
+//! port = 8125
+//! send = 'specsheet.probe:1|c'
+//! expect = { empty = true }
+//!
+//! [[udp]]
+//! port = 53
+//! send = { hex = '1337 0100 0001 0000 0000 0000 0000 0100 01' }
+//! expect = { empty = false }
+//! ```
+//!
 //! # Commands
 //!
 //! No commands are run for network checks; Specsheet deals with the network
 //! itself.
 
 
+use std::convert::TryFrom;
 use std::fmt;
 use std::net::Ipv4Addr;
+use std::path::Path;
 
 use log::*;
 
+use spec_analysis::DataPoint;
+
 use crate::check::{Check, BuiltInCheck, CheckResult, PassResult, FailResult};
 use crate::common::PortNumber;
+use crate::contents::{self, ContentsMatcher};
 use crate::read::{TomlValue, ValueExtras, ReadError, OneOf};
+use crate::ufw::{UfwCheck, Protocol};
 
 
 /// A check against the network; which other machines the local computer can
@@ -31,6 +52,7 @@ pub struct UdpCheck {
     request: Request,
     condition: Condition,
     ufw: Option<ExtraUfwCheck>,
+    expect: Option<ContentsMatcher>,
 }
 
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
@@ -38,6 +60,20 @@ pub struct Request {
     pub target: Option<String>,
     pub port: PortNumber,
     pub source: Source,
+
+    /// The packet to send, if one was given.
+    pub payload: Option<Payload>,
+}
+
+/// The contents of the packet to send.
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub enum Payload {
+
+    /// Send a string, as UTF-8.
+    Text(String),
+
+    /// Send these bytes, which were given in hex.
+    Bytes(Vec<u8>),
 }
 
 /// Where the request gets sent from.
@@ -65,7 +101,7 @@ struct ExtraUfwCheck {
 
 impl fmt::Display for UdpCheck {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { request, condition, ufw } = &self;
+        let Self { request, condition, ufw, expect } = &self;
 
         write!(f, "UDP port ‘{}’", request.port.0)?;
 
@@ -93,6 +129,17 @@ impl fmt::Display for UdpCheck {
             }
         }
 
+        match &request.payload {
+            Some(Payload::Text(text))    => write!(f, " to ‘{}’", text)?,
+            Some(Payload::Bytes(bytes))  => write!(f, " after sending {} bytes", bytes.len())?,
+            None                         => {},
+        }
+
+        if let Some(contents_matcher) = expect {
+            write!(f, " with")?;
+            contents_matcher.describe(f, "reply")?;
+        }
+
         Ok(())
     }
 }
@@ -106,13 +153,19 @@ impl Check for UdpCheck {
 
 impl UdpCheck {
     pub fn read(table: &TomlValue) -> Result<Self, ReadError> {
-        table.ensure_only_keys(&["port", "address", "source", "state", "ufw"])?;
+        table.ensure_only_keys(&["port", "address", "source", "state", "ufw", "send", "expect"])?;
 
         let request = Request::read(table)?;
         let condition = Condition::read(table)?;
         let ufw = ExtraUfwCheck::read(table)?;
+        let expect = table.get("expect").map(|e| ContentsMatcher::read("expect", e)).transpose()?;
 
-        Ok(Self { request, condition, ufw })
+        // There’s no reply to examine if there shouldn’t be one.
+        if let (Condition::NoResponse, Some(_)) = (&condition, &expect) {
+            return Err(ReadError::conflict2("expect", "state", TomlValue::String("no-response".into())));
+        }
+
+        Ok(Self { request, condition, ufw, expect })
     }
 }
 
@@ -126,10 +179,68 @@ impl Request {
             None    => None,
         };
 
-        Ok(Self { target, port, source })
+        let payload = table.get("send").map(Payload::read).transpose()?;
+        Ok(Self { target, port, source, payload })
     }
 }
 
+impl Payload {
+    fn read(send_value: &TomlValue) -> Result<Self, ReadError> {
+        if let Some(text) = send_value.as_str() {
+            if text.is_empty() {
+                return Err(ReadError::invalid("send", send_value.clone(), "it must not be empty"));
+            }
+
+            return Ok(Self::Text(text.into()));
+        }
+
+        if send_value.as_table().is_none() {
+            return Err(ReadError::invalid("send", send_value.clone(), "it must be a string or a table with ‘hex’"));
+        }
+
+        send_value.ensure_only_keys(&["hex"])?;
+        let hex_value = send_value.get_or_read_error("hex")?;
+        let hex = hex_value.string_or_error("hex")?;
+
+        match parse_hex(&hex) {
+            Some(bytes) if ! bytes.is_empty() => {
+                Ok(Self::Bytes(bytes))
+            }
+            _ => {
+                Err(ReadError::invalid("hex", hex_value.clone(), "it must be a string of hexadecimal bytes"))
+            }
+        }
+    }
+
+    /// Returns the bytes to put in the packet.
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text)    => text.as_bytes(),
+            Self::Bytes(bytes)  => bytes,
+        }
+    }
+}
+
+/// Parses a string of hexadecimal digits into bytes, ignoring any spaces or
+/// colons between them. Returns nothing if there are any other characters,
+/// or an odd number of digits.
+fn parse_hex(input: &str) -> Option<Vec<u8>> {
+    let digits = input.chars()
+                      .filter(|c| *c != ' ' && *c != ':')
+                      .map(|c| c.to_digit(16))
+                      .collect::<Option<Vec<_>>>()?;
+
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = digits.chunks(2)
+                      .map(|pair| u8::try_from(pair[0] * 16 + pair[1]).unwrap())
+                      .collect();
+
+    Some(bytes)
+}
+
 impl Source {
     fn read(table: &TomlValue) -> Result<Self, ReadError> {
         let source_value = match table.get("source") {
@@ -188,6 +299,35 @@ impl ExtraUfwCheck {
 }
 
 
+// ---- analysis properties ----
+
+impl UdpCheck {
+    pub fn properties(&self) -> Vec<DataPoint<'_>> {
+        let mut points = Vec::new();
+
+        if let Some(target) = &self.request.target {
+            points.push(DataPoint::InvolvesHost(target));
+        }
+
+        points.push(DataPoint::InvolvesPort(self.request.port.0));
+        points
+    }
+}
+
+
+// ---- secondary checks ----
+
+impl UdpCheck {
+
+    /// Returns the ufw check given in the `ufw` sub-table, if there is one,
+    /// which gets run after this check.
+    pub fn ufw_check(&self) -> Option<UfwCheck> {
+        let ufw = self.ufw.as_ref()?;
+        Some(UfwCheck::for_port(self.request.port.0, Protocol::UDP, ufw.allow.clone()))
+    }
+}
+
+
 // ---- running the check ----
 
 /// The network interface used to send UDP packets by [`UdpCheck`].
@@ -198,8 +338,12 @@ pub trait RunUdp {
     fn prime(&mut self, request: &Request) { }
 
     /// Running the command if it hasn’t been run already, sends a UDP
-    /// packet and reports back if we received a response.
-    fn send_udp_request(&self, request: &Request) -> bool;
+    /// packet and returns the reply, if we received one.
+    fn send_udp_request(&self, request: &Request) -> Option<Vec<u8>>;
+
+    /// Returns the directory that relative paths to files of expected
+    /// replies are looked up from, if there is one.
+    fn base_directory(&self) -> Option<&Path> { None }
 }
 
 impl<N: RunUdp> BuiltInCheck<N> for UdpCheck {
@@ -216,22 +360,42 @@ impl<N: RunUdp> BuiltInCheck<N> for UdpCheck {
         let result = net.send_udp_request(&self.request);
 
         match (&self.condition, result) {
-            (Condition::Responds, true) => {
-                vec![ CheckResult::Passed(Pass::ReceivedResponse) ]
+            (Condition::Responds, Some(reply)) => {
+                let mut results = vec![ CheckResult::Passed(Pass::ReceivedResponse) ];
+
+                if let Some(reply_matcher) = &self.expect {
+                    results.push(reply_result(&reply, reply_matcher, net.base_directory()));
+                }
+
+                results
             }
-            (Condition::Responds, false) => {
+            (Condition::Responds, None) => {
                 vec![ CheckResult::Failed(Fail::ConnectionRefused) ]
             }
-            (Condition::NoResponse, true) => {
+            (Condition::NoResponse, Some(_)) => {
                 vec![ CheckResult::Failed(Fail::ReceivedResponse) ]
             }
-            (Condition::NoResponse, false) => {
+            (Condition::NoResponse, None) => {
                 vec![ CheckResult::Passed(Pass::ConnectionRefused) ]
             }
         }
     }
 }
 
+fn reply_result(reply: &[u8], reply_matcher: &ContentsMatcher, directory: Option<&Path>) -> CheckResult<Pass, Fail> {
+    match reply_matcher.check(reply, directory) {
+        CheckResult::Passed(pass) => {
+            CheckResult::Passed(Pass::ReplyPass(pass))
+        }
+        CheckResult::Failed(fail) => {
+            CheckResult::Failed(Fail::ReplyFail(fail))
+        }
+        CheckResult::CommandError(_) => {
+            unreachable!()
+        }
+    }
+}
+
 impl Request {
 
     /// Returns the address to send packets to.
@@ -248,18 +412,38 @@ impl Request {
 pub enum Pass {
     ReceivedResponse,
     ConnectionRefused,
+
+    /// The reply matches its contents predicate.
+    ReplyPass(contents::Pass),
 }
 
 /// The failure result of running a network check.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(Debug)]
 pub enum Fail {
     ConnectionRefused,
     ReceivedResponse,
+
+    /// The reply did not match its contents predicate.
+    ReplyFail(contents::Fail),
 }
 
 impl PassResult for Pass {}
 
-impl FailResult for Fail {}
+impl FailResult for Fail {
+    fn command_output(&self) -> Option<(String, &String)> {
+        match self {
+            Self::ReplyFail(fail)  => fail.command_output("Reply:"),
+            _                      => None,
+        }
+    }
+
+    fn diff_output(&self) -> Option<(String, &String, &String)> {
+        match self {
+            Self::ReplyFail(fail)  => fail.diff_output(),
+            _                      => None,
+        }
+    }
+}
 
 
 // ---- check result descriptions ----
@@ -273,6 +457,9 @@ impl fmt::Display for Pass {
             Self::ConnectionRefused => {
                 write!(f, "connection refused")
             }
+            Self::ReplyPass(contents_pass) => {
+                write!(f, "reply {}", contents_pass)
+            }
         }
     }
 }
@@ -286,6 +473,9 @@ impl fmt::Display for Fail {
             Self::ReceivedResponse => {
                 write!(f, "received a response")
             }
+            Self::ReplyFail(contents_fail) => {
+                write!(f, "reply {}", contents_fail)
+            }
         }
     }
 }
@@ -0,0 +1,229 @@
+use std::fmt;
+
+use crate::read::{TomlValue, ValueExtras, ReadError};
+
+
+/// A **number matcher** asserts that a number, which has been obtained
+/// from somewhere (such as a DNS record’s TTL, an HTTP response’s status,
+/// or the number of lines in a command’s output), satisfies a set of
+/// comparisons.
+///
+/// It gets read from either a plain integer, which the number must be
+/// equal to, or a table of comparisons, such as `{ gt = 5 }` or
+/// `{ between = [1, 10] }`, all of which must hold.
+#[derive(PartialEq, Debug, Clone)]
+pub struct NumberMatcher {
+    comparisons: Vec<Comparison>,
+}
+
+/// One of the comparisons that a number has to satisfy.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Comparison {
+
+    /// The number must be equal to this one (`eq`).
+    Equal(i64),
+
+    /// The number must not be equal to this one (`ne`).
+    NotEqual(i64),
+
+    /// The number must be greater than this one (`gt`).
+    Greater(i64),
+
+    /// The number must be greater than or equal to this one (`ge`).
+    GreaterOrEqual(i64),
+
+    /// The number must be less than this one (`lt`).
+    Less(i64),
+
+    /// The number must be less than or equal to this one (`le`).
+    LessOrEqual(i64),
+
+    /// The number must be between these two, inclusive (`between`).
+    Between(i64, i64),
+}
+
+impl NumberMatcher {
+
+    /// Creates a matcher that the given number must be equal to.
+    pub fn exactly(number: i64) -> Self {
+        Self { comparisons: vec![ Comparison::Equal(number) ] }
+    }
+
+    /// Creates a matcher from an optional minimum and maximum, both of
+    /// which are inclusive, as used by the older `min` and `max` style of
+    /// parameters. Returns `None` if neither is present.
+    pub fn range(min: Option<i64>, max: Option<i64>) -> Option<Self> {
+        let comparison = match (min, max) {
+            (Some(min), Some(max)) if min == max  => Comparison::Equal(min),
+            (Some(min), Some(max))                => Comparison::Between(min, max),
+            (Some(min), None)                     => Comparison::GreaterOrEqual(min),
+            (None, Some(max))                     => Comparison::LessOrEqual(max),
+            (None, None)                          => return None,
+        };
+
+        Some(Self { comparisons: vec![ comparison ] })
+    }
+
+    /// Returns the comparisons that a number has to satisfy.
+    pub fn comparisons(&self) -> &[Comparison] {
+        &self.comparisons
+    }
+
+    /// Whether the given number satisfies every comparison.
+    pub fn matches(&self, number: i64) -> bool {
+        self.comparisons.iter().all(|c| c.matches(number))
+    }
+}
+
+impl Comparison {
+    fn matches(self, number: i64) -> bool {
+        match self {
+            Self::Equal(n)           => number == n,
+            Self::NotEqual(n)        => number != n,
+            Self::Greater(n)         => number > n,
+            Self::GreaterOrEqual(n)  => number >= n,
+            Self::Less(n)            => number < n,
+            Self::LessOrEqual(n)     => number <= n,
+            Self::Between(min, max)  => (min ..= max).contains(&number),
+        }
+    }
+}
+
+
+// ---- the matcher description ----
+
+impl fmt::Display for NumberMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, comparison) in self.comparisons.iter().enumerate() {
+            if i > 0 { write!(f, " and ")?; }
+            write!(f, "{}", comparison)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equal(n)           => write!(f, "exactly ‘{}’", n),
+            Self::NotEqual(n)        => write!(f, "other than ‘{}’", n),
+            Self::Greater(n)         => write!(f, "more than ‘{}’", n),
+            Self::GreaterOrEqual(n)  => write!(f, "at least ‘{}’", n),
+            Self::Less(n)            => write!(f, "less than ‘{}’", n),
+            Self::LessOrEqual(n)     => write!(f, "at most ‘{}’", n),
+            Self::Between(min, max)  => write!(f, "between ‘{}’ and ‘{}’", min, max),
+        }
+    }
+}
+
+
+// ---- reading ----
+
+/// The keys that can appear in a table of comparisons.
+pub const COMPARISON_KEYS: &[&str] = &["gt", "ge", "lt", "le", "between", "eq", "ne"];
+
+impl NumberMatcher {
+
+    /// Reads a number matcher from the value of the given parameter,
+    /// which must be an integer or a table of comparisons.
+    pub fn read(parameter_name: &'static str, value: &TomlValue) -> Result<Self, ReadError> {
+        if let Some(number) = value.as_integer() {
+            return Ok(Self::exactly(number));
+        }
+
+        if value.as_table().is_none() {
+            return Err(ReadError::invalid(parameter_name, value.clone(), NumberReadError::InvalidType));
+        }
+
+        value.ensure_only_keys(COMPARISON_KEYS)?;
+
+        let mut comparisons = Vec::new();
+        for key in COMPARISON_KEYS {
+            if let Some(comparison_value) = value.get(*key) {
+                comparisons.push(Comparison::read(key, comparison_value)?);
+            }
+        }
+
+        if comparisons.is_empty() {
+            return Err(ReadError::invalid(parameter_name, value.clone(), NumberReadError::NoComparisons));
+        }
+
+        Ok(Self { comparisons })
+    }
+
+    /// Whether the given value is a table of comparisons, rather than
+    /// some other kind of table that a check accepts for a parameter.
+    pub fn is_comparison_table(value: &TomlValue) -> bool {
+        match value.as_table() {
+            Some(table)  => ! table.is_empty() && table.keys().all(|k| COMPARISON_KEYS.contains(&&k[..])),
+            None         => false,
+        }
+    }
+}
+
+impl Comparison {
+    fn read(key: &'static str, value: &TomlValue) -> Result<Self, ReadError> {
+        match key {
+            "eq"  => Ok(Self::Equal(value.number_or_error(key)?)),
+            "ne"  => Ok(Self::NotEqual(value.number_or_error(key)?)),
+            "gt"  => Ok(Self::Greater(value.number_or_error(key)?)),
+            "ge"  => Ok(Self::GreaterOrEqual(value.number_or_error(key)?)),
+            "lt"  => Ok(Self::Less(value.number_or_error(key)?)),
+            "le"  => Ok(Self::LessOrEqual(value.number_or_error(key)?)),
+            _     => {
+                let bounds = value.as_array()
+                                  .filter(|a| a.len() == 2)
+                                  .and_then(|a| Some((a[0].as_integer()?, a[1].as_integer()?)));
+
+                match bounds {
+                    Some((min, max)) if min > max => {
+                        Err(ReadError::invalid(key, value.clone(), NumberReadError::BackwardsRange))
+                    }
+                    Some((min, max)) => {
+                        Ok(Self::Between(min, max))
+                    }
+                    None => {
+                        Err(ReadError::invalid(key, value.clone(), NumberReadError::InvalidRange))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Something that can go wrong while reading a `NumberMatcher`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum NumberReadError {
+
+    /// The value was neither an integer nor a table.
+    InvalidType,
+
+    /// The table had no comparisons in it.
+    NoComparisons,
+
+    /// The `between` value was not an array of two integers.
+    InvalidRange,
+
+    /// The first `between` value was greater than the second.
+    BackwardsRange,
+}
+
+impl fmt::Display for NumberReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidType => {
+                write!(f, "it must be a number or a table of comparisons")
+            }
+            Self::NoComparisons => {
+                write!(f, "it must have at least one comparison")
+            }
+            Self::InvalidRange => {
+                write!(f, "it must be an array of two numbers")
+            }
+            Self::BackwardsRange => {
+                write!(f, "the first number must not be greater than the second")
+            }
+        }
+    }
+}
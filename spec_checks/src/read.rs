@@ -139,7 +139,8 @@ impl ValueExtras for TomlValue {
     fn ensure_only_keys(&self, keys: &[&str]) -> Result<(), ReadError> {
         if let Some(t) = self.as_table() {
             if let Some(invalid_param) = t.keys().find(|key| ! keys.iter().any(|k| k == key)) {
-                Err(ReadError::UnknownParameter { parameter_name: invalid_param.into() })
+                let suggestion = closest_name(invalid_param, keys).map(String::from);
+                Err(ReadError::UnknownParameter { parameter_name: invalid_param.into(), suggestion })
             }
             else {
                 Ok(())
@@ -151,6 +152,38 @@ impl ValueExtras for TomlValue {
     }
 }
 
+/// Returns the name that the given unknown name was most likely meant to
+/// be, if any of them are only a typo or two away from it.
+pub fn closest_name<'n>(unknown: &str, names: &[&'n str]) -> Option<&'n str> {
+    let allowed_edits = if unknown.chars().count() <= 4 { 1 } else { 2 };
+
+    names.iter()
+        .map(|name| (edit_distance(unknown, name), *name))
+        .filter(|(distance, name)| *distance <= allowed_edits && *distance < name.chars().count())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Counts how many characters have to be inserted, deleted, or changed to
+/// turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0 ..= b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![ i + 1 ];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 
 /// A general error that can occur while reading a check from a TOML value.
 pub enum ReadError {
@@ -160,9 +193,11 @@ pub enum ReadError {
         parameter_name: &'static str,
     },
 
-    /// A table contained a key we don't know about.
+    /// A table contained a key we don't know about, which could be a typo
+    /// of one we do.
     UnknownParameter {
         parameter_name: String,
+        suggestion: Option<String>,
     },
 
     /// A key was set to a value that was not valid for the key.
@@ -198,6 +233,18 @@ impl ReadError {
     pub fn conflict2(parameter_name: &'static str, other_parameter_name: &'static str, specific: TomlValue) -> Self {
         Self::Conflict { parameter_name, other_parameter_name, specific_value: Some(specific) }
     }
+
+    /// Returns the name of the parameter the error is about, so it can be
+    /// found in the input document.
+    pub fn parameter_name(&self) -> &str {
+        match self {
+            Self::MissingParameter { parameter_name }  |
+            Self::InvalidValue { parameter_name, .. }  |
+            Self::Conflict { parameter_name, .. }      |
+            Self::AliasClash { parameter_name, .. }    => parameter_name,
+            Self::UnknownParameter { parameter_name, .. }  => parameter_name,
+        }
+    }
 }
 
 impl fmt::Debug for ReadError {
@@ -212,9 +259,12 @@ impl fmt::Display for ReadError {
             Self::MissingParameter { parameter_name } => {
                 write!(f, "Parameter ‘{}’ is missing", parameter_name)
             }
-            Self::UnknownParameter { parameter_name } => {
+            Self::UnknownParameter { parameter_name, suggestion: None } => {
                 write!(f, "Parameter ‘{}’ is unknown", parameter_name)
             }
+            Self::UnknownParameter { parameter_name, suggestion: Some(suggestion) } => {
+                write!(f, "Parameter ‘{}’ is unknown (did you mean ‘{}’?)", parameter_name, suggestion)
+            }
             Self::InvalidValue { parameter_name, given_value, ordinance} => {
                 write!(f, "Parameter ‘{}’ value ‘{}’ is invalid ({})", parameter_name, given_value, (ordinance))
             }
@@ -238,15 +288,17 @@ pub struct OneOf(pub &'static [&'static str]);
 
 impl fmt::Display for OneOf {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.0.len() == 2 {
-            write!(f, "it must be ‘{}’ or ‘{}’", self.0[0], self.0[1])
-        }
-        else if self.0.len() == 3 {
-            write!(f, "it must be ‘{}’ or ‘{}’ or ‘{}’", self.0[0], self.0[1], self.0[2])
-        }
-        else {
-            panic!("OneOf")
+        write!(f, "it must be")?;
+
+        for (index, option) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " or")?;
+            }
+
+            write!(f, " ‘{}’", option)?;
         }
+
+        Ok(())
     }
 }
 
@@ -326,4 +378,48 @@ impl Rewrites {
 
         url
     }
+
+    /// Applies these rules to the parameters of a check of the given type,
+    /// the same way reading the check does, so the parameters it gets run
+    /// with can be shown.
+    pub fn parameters(&self, check_type: &str, parameters: &TomlValue) -> TomlValue {
+        let mut parameters = parameters.clone();
+
+        if let TomlValue::Table(table) = &mut parameters {
+            for (key, value) in table.iter_mut() {
+                let TomlValue::String(string) = value else { continue };
+
+                match rewritten_parameter(check_type, key) {
+                    Some(Rewritten::Path)  => *string = self.path(string.clone()).to_string_lossy().into_owned(),
+                    Some(Rewritten::Url)   => *string = self.url(string.clone()),
+                    None                   => {}
+                }
+            }
+        }
+
+        parameters
+    }
+}
+
+/// The ways the parameters of a check can get rewritten.
+enum Rewritten {
+    Path,
+    Url,
+}
+
+/// Which way a check’s parameter gets rewritten when the check is read,
+/// if it does.
+fn rewritten_parameter(check_type: &str, key: &str) -> Option<Rewritten> {
+    match (check_type, key) {
+        ("http", "url" | "final_url" | "redirect_to")  => Some(Rewritten::Url),
+
+        ("http", "client_cert")
+        | ("fs", "path" | "link_target")
+        | ("gem" | "npm", "path")
+        | ("hash", "path" | "directory" | "checksum_file")
+        | ("defaults", "file")
+        | ("user", "home" | "login_shell")             => Some(Rewritten::Path),
+
+        _                                              => None,
+    }
 }
@@ -0,0 +1,356 @@
+//! Version requirements in the style of npm and Rubygems, which are used by
+//! the package checks for language package managers.
+//!
+//! A requirement is made up of one or more sets of comparators separated by
+//! `||`, any of which can match. Each set is made up of comparators
+//! separated by spaces or commas, all of which have to match:
+//!
+//! - `1.2.3` or `=1.2.3` matches exactly that version;
+//! - `>1.2`, `>=1.2`, `<1.2`, and `<=1.2` compare against a version;
+//! - `^1.2` allows changes that do not modify the left-most non-zero
+//!   component, so anything from `1.2.0` up to, but not including, `2.0.0`;
+//! - `~1.2` allows patch-level changes, so anything up to `1.3.0`;
+//! - `~> 1.2` is the Rubygems pessimistic operator, which allows the last
+//!   given component to increase, so anything up to `2.0`;
+//! - `*`, `1.x`, and `1.2.x` are wildcards.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::read::{TomlValue, ValueExtras, ReadError};
+
+
+/// A **version requirement** that an installed version can satisfy.
+#[derive(PartialEq, Debug, Clone)]
+pub struct VersionRequirement {
+
+    /// The requirement as it was written, which gets used in check
+    /// descriptions.
+    as_written: String,
+
+    /// The sets of comparators, any of which can match.
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+struct Comparator {
+    operator: Operator,
+    version: Version,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Operator {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// A version number, made up of numeric components and an optional
+/// pre-release tag.
+#[derive(PartialEq, Debug, Clone)]
+struct Version {
+    components: Vec<u64>,
+    pre_release: Option<String>,
+}
+
+/// The error returned when a version requirement cannot be parsed.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct InvalidRequirement;
+
+impl fmt::Display for InvalidRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "it must be a version or a range such as ‘^1.2’")
+    }
+}
+
+
+impl VersionRequirement {
+
+    /// Reads the requirement from the `version` parameter of a table, if
+    /// it has one.
+    pub fn read(table: &TomlValue) -> Result<Option<Self>, ReadError> {
+        if let Some(version_value) = table.get("version") {
+            let version = version_value.string_or_error("version")?;
+
+            if version.is_empty() {
+                return Err(ReadError::invalid("version", version_value.clone(), "it must not be empty"));
+            }
+
+            match Self::parse(&version) {
+                Ok(requirement) => Ok(Some(requirement)),
+                Err(e)          => Err(ReadError::invalid("version", version_value.clone(), e)),
+            }
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    /// Parses a version requirement.
+    pub fn parse(input: &str) -> Result<Self, InvalidRequirement> {
+        let mut alternatives = Vec::new();
+
+        for alternative in input.split("||") {
+            let mut comparators = Vec::new();
+            let mut words = alternative.split(|c: char| c.is_whitespace() || c == ',')
+                                       .filter(|word| ! word.is_empty());
+
+            while let Some(word) = words.next() {
+
+                // Operators are allowed to be separated from their versions
+                // by a space, such as ‘>= 1.2’ or ‘~> 2.4’.
+                let word = if word.chars().all(|c| "<>=~^".contains(c)) {
+                    let version = words.next().ok_or(InvalidRequirement)?;
+                    format!("{}{}", word, version)
+                }
+                else {
+                    word.to_owned()
+                };
+
+                comparators.extend(parse_comparator(&word)?);
+            }
+
+            if comparators.is_empty() {
+                return Err(InvalidRequirement);
+            }
+
+            alternatives.push(comparators);
+        }
+
+        Ok(Self { as_written: input.trim().into(), alternatives })
+    }
+
+    /// Whether the given version satisfies this requirement. Versions that
+    /// cannot be parsed never satisfy anything.
+    pub fn matches(&self, version: &str) -> bool {
+        if let Some(version) = Version::parse(version) {
+            self.alternatives.iter().any(|comparators| {
+                comparators.iter().all(|comparator| comparator.matches(&version))
+            })
+        }
+        else {
+            false
+        }
+    }
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_written)
+    }
+}
+
+
+/// Parses one comparator, which can expand to two comparators if it
+/// describes a range, such as a caret or tilde requirement.
+fn parse_comparator(word: &str) -> Result<Vec<Comparator>, InvalidRequirement> {
+    use self::Operator::{Equal, Greater, GreaterOrEqual, Less, LessOrEqual};
+
+    let (prefix, rest) = split_operator(word);
+    let (version, given) = parse_partial(rest)?;
+
+    // A wildcard on its own matches everything, whatever the operator.
+    let comparators = match (prefix, given) {
+        (_, 0) | (">=", _) => {
+            vec![ Comparator::new(GreaterOrEqual, version) ]
+        }
+        ("" | "=", n) if n < version.components.len() => {
+            vec![ Comparator::new(GreaterOrEqual, version.clone()),
+                  Comparator::new(Less, version.bump(n - 1)) ]
+        }
+        ("" | "=", _) => {
+            vec![ Comparator::new(Equal, version) ]
+        }
+        (">", n) if n < version.components.len() && n > 0 => {
+            vec![ Comparator::new(GreaterOrEqual, version.bump(n - 1)) ]
+        }
+        (">", _) => {
+            vec![ Comparator::new(Greater, version) ]
+        }
+        ("<", _) => {
+            vec![ Comparator::new(Less, version) ]
+        }
+        ("<=", n) if n < version.components.len() && n > 0 => {
+            vec![ Comparator::new(Less, version.bump(n - 1)) ]
+        }
+        ("<=", _) => {
+            vec![ Comparator::new(LessOrEqual, version) ]
+        }
+        ("^", n) => {
+            let first_non_zero = version.components.iter().take(n).position(|c| *c != 0);
+            let bump_index = match first_non_zero {
+                Some(index)  => index,
+                None         => n.saturating_sub(1),
+            };
+
+            vec![ Comparator::new(GreaterOrEqual, version.clone()),
+                  Comparator::new(Less, version.bump(bump_index)) ]
+        }
+        ("~", n) => {
+            let bump_index = usize::from(n >= 2);
+            vec![ Comparator::new(GreaterOrEqual, version.clone()),
+                  Comparator::new(Less, version.bump(bump_index)) ]
+        }
+        ("~>", n) => {
+            let bump_index = n.saturating_sub(2);
+            vec![ Comparator::new(GreaterOrEqual, version.clone()),
+                  Comparator::new(Less, version.bump(bump_index)) ]
+        }
+        _ => {
+            return Err(InvalidRequirement);
+        }
+    };
+
+    Ok(comparators)
+}
+
+/// Splits the operator characters off the front of a comparator.
+fn split_operator(word: &str) -> (&str, &str) {
+    let index = word.find(|c: char| ! "<>=~^".contains(c)).unwrap_or(word.len());
+    word.split_at(index)
+}
+
+/// Parses a version that may have fewer than three components, or have
+/// wildcards in place of components, returning the version padded with
+/// zeroes and the number of components that were actually given.
+fn parse_partial(input: &str) -> Result<(Version, usize), InvalidRequirement> {
+    let input = input.strip_prefix('v').unwrap_or(input);
+
+    if input == "*" || input.eq_ignore_ascii_case("x") {
+        return Ok((Version { components: vec![0, 0, 0], pre_release: None }, 0));
+    }
+
+    let mut version = Version::parse(input).ok_or(InvalidRequirement)?;
+
+    // Wildcards are only allowed as the last components, so ‘1.x.x’ is
+    // fine but ‘x.1’ is not.
+    let without_build = input.split('+').next().unwrap_or_default();
+    let given = without_build.split('-').next().unwrap_or_default()
+                             .split('.')
+                             .take_while(|c| ! matches!(*c, "x" | "X" | "*"))
+                             .count()
+                             .min(version.components.len());
+
+    while version.components.len() < 3 {
+        version.components.push(0);
+    }
+
+    Ok((version, given))
+}
+
+impl Comparator {
+    fn new(operator: Operator, version: Version) -> Self {
+        Self { operator, version }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let ordering = version.compare(&self.version);
+
+        match self.operator {
+            Operator::Equal           => ordering == Ordering::Equal,
+            Operator::Greater         => ordering == Ordering::Greater,
+            Operator::GreaterOrEqual  => ordering != Ordering::Less,
+            Operator::Less            => ordering == Ordering::Less,
+            Operator::LessOrEqual     => ordering != Ordering::Greater,
+        }
+    }
+}
+
+impl Version {
+
+    /// Parses a full version, such as `1.2.3`, `1.2.3-beta.1`, or the
+    /// Rubygems-style `1.2.3.pre`. Build metadata after a `+` is ignored.
+    /// Wildcard components stop the parsing, and are treated as missing.
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let input = input.split('+').next().unwrap_or_default();
+
+        let (numbers, mut pre_release) = match input.split_once('-') {
+            Some((numbers, pre))  => (numbers, Some(pre.to_owned())),
+            None                  => (input, None),
+        };
+
+        let mut components = Vec::new();
+        for (index, component) in numbers.split('.').enumerate() {
+            if let Ok(number) = component.parse() {
+                components.push(number);
+            }
+            else if matches!(component, "x" | "X" | "*") && index > 0 {
+                break;
+            }
+            else if index > 0 && pre_release.is_none() {
+                let rest = numbers.splitn(index + 1, '.').last().unwrap_or_default();
+                pre_release = Some(rest.to_owned());
+                break;
+            }
+            else {
+                return None;
+            }
+        }
+
+        Some(Self { components, pre_release })
+    }
+
+    /// Returns the version that comes after every version with the same
+    /// components up to the given index, by incrementing that component
+    /// and dropping the rest. Bumping index 0 of `1.2.3` gives `2.0.0`.
+    fn bump(&self, index: usize) -> Self {
+        let mut components = self.components.clone();
+        components[index] += 1;
+
+        for component in &mut components[index + 1 ..] {
+            *component = 0;
+        }
+
+        Self { components, pre_release: None }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        let length = self.components.len().max(other.components.len());
+
+        for index in 0 .. length {
+            let a = self.components.get(index).copied().unwrap_or(0);
+            let b = other.components.get(index).copied().unwrap_or(0);
+
+            if a != b {
+                return a.cmp(&b);
+            }
+        }
+
+        match (&self.pre_release, &other.pre_release) {
+            (None,    None)     => Ordering::Equal,
+            (None,    Some(_))  => Ordering::Greater,
+            (Some(_), None)     => Ordering::Less,
+            (Some(a), Some(b))  => compare_pre_releases(a, b),
+        }
+    }
+}
+
+/// Compares two pre-release tags identifier by identifier, with numeric
+/// identifiers compared numerically and sorting before alphanumeric ones.
+fn compare_pre_releases(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None)     => return Ordering::Equal,
+            (None, Some(_))  => return Ordering::Less,
+            (Some(_), None)  => return Ordering::Greater,
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b))   => a.cmp(&b),
+                    (Ok(_), Err(_))  => Ordering::Less,
+                    (Err(_), Ok(_))  => Ordering::Greater,
+                    (Err(_), Err(_)) => a.cmp(b),
+                };
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
@@ -48,6 +48,28 @@ fn command_with_two_environments_runs() {
                "Command ‘A=b C=d ls’ executes");
 }
 
+#[test]
+fn command_with_directory_runs() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        directory = "/tmp"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ in ‘/tmp’ executes");
+}
+
+#[test]
+fn command_in_pty_runs() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls --color=auto"
+        pty = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls --color=auto’ in a terminal executes");
+}
+
 #[test]
 fn command_runs_with_empty_stderr() {
     let check = CommandCheck::read(&toml! {
@@ -70,6 +92,152 @@ fn command_runs_with_string_in_stdout() {
                "Command ‘ls’ executes with stdout containing ‘ERROR’");
 }
 
+#[test]
+fn command_runs_with_timeout() {
+    let check = CommandCheck::read(&toml! {
+        shell = "sleep 1"
+        timeout = "1500ms"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘sleep 1’ executes");
+}
+
+#[test]
+fn command_runs_with_timeout_seconds() {
+    let check = CommandCheck::read(&toml! {
+        shell = "sleep 1"
+        timeout = 5
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘sleep 1’ executes");
+}
+
+#[test]
+fn command_runs_with_stdin() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = "one\ntwo\n"
+        stdout = { regex = "2" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘wc -l’ executes with stdout matching regex ‘/2/’");
+}
+
+#[test]
+fn command_runs_with_stdin_file() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = { file = "fixture.txt" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘wc -l’ executes");
+}
+
+#[test]
+fn command_runs_with_status_list() {
+    let check = CommandCheck::read(&toml! {
+        shell = "grep -q thing file.txt"
+        status = [0, 1]
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘grep -q thing file.txt’ returns ‘0’ or ‘1’");
+}
+
+#[test]
+fn command_runs_with_negated_status() {
+    let check = CommandCheck::read(&toml! {
+        shell = "false"
+        status = { not = 0 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘false’ does not return ‘0’");
+}
+
+#[test]
+fn command_runs_with_negated_status_list() {
+    let check = CommandCheck::read(&toml! {
+        shell = "false"
+        status = { not = [0, 2] }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘false’ does not return ‘0’ or ‘2’");
+}
+
+#[test]
+fn command_runs_with_output_line_count() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        output = { lines = 3 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ executes with output of exactly ‘3’ lines");
+}
+
+#[test]
+fn command_runs_with_stdout_min_lines() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        stdout = { min_lines = 1 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ executes with stdout of at least ‘1’ lines");
+}
+
+#[test]
+fn command_runs_with_stderr_max_lines() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        stderr = { max_lines = 0 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ executes with stderr of at most ‘0’ lines");
+}
+
+#[test]
+fn command_runs_with_status_comparison() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        status = { lt = 2 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ returns less than ‘2’");
+}
+
+#[test]
+fn command_runs_with_stdout_line_comparison() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        stdout = { lines = { gt = 5 } }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls’ executes with stdout of more than ‘5’ lines");
+}
+
+
+// ---- analysis properties ----
+
+#[test]
+fn properties() {
+    let check = CommandCheck::read(&toml! {
+        shell = "LANG=C psql -c 'select 1'"
+    }).unwrap();
+
+    assert_eq!(check.properties(),
+               vec![ DataPoint::InvolvesCommand("psql") ]);
+}
+
 
 // ---- parameter combinations ----
 
@@ -109,6 +277,41 @@ fn empty_stdout_and_stderr() {
                "Command ‘ls’ executes with empty stdout and stderr");
 }
 
+#[test]
+fn negated_status_and_stdout() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls /nope"
+        status = { not = 0 }
+        stdout = { empty = true }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘ls /nope’ does not return ‘0’ with empty stdout");
+}
+
+#[test]
+fn stdout_and_output() {
+    let check = CommandCheck::read(&toml! {
+        shell = "make"
+        stdout = { string = "Done" }
+        output = { regex = "warning" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Command ‘make’ executes with stdout containing ‘Done’ and output matching regex ‘/warning/’");
+}
+
+#[test]
+fn err_lines_and_min_lines() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        stdout = { lines = 3, min_lines = 1 }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘min_lines’ is inappropriate when parameter ‘lines’ is given");
+}
+
 #[test]
 fn stdout_and_stderr_and_status() {
     let check = CommandCheck::read(&toml! {
@@ -135,6 +338,116 @@ fn err_empty_shell_command() {
                "Parameter ‘shell’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_invalid_timeout() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        timeout = "a while"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘timeout’ value ‘\"a while\"’ is invalid (it must be a duration such as ‘10s’)");
+}
+
+#[test]
+fn err_zero_timeout() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        timeout = 0
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘timeout’ value ‘0’ is invalid (it must not be zero)");
+}
+
+#[test]
+fn err_empty_directory() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        directory = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘directory’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_pty_not_boolean() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        pty = "yes"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘pty’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
+#[test]
+fn err_empty_stdin_file() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = { file = "" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘file’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_status_list() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        status = []
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ value ‘[]’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_negated_status_list() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        status = { not = [] }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘not’ value ‘[]’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_negative_lines() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        output = { lines = -1 }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘lines’ value ‘-1’ is invalid (it must not be negative)");
+}
+
+#[test]
+fn err_min_lines_above_max_lines() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        output = { min_lines = 5, max_lines = 2 }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘min_lines’ value ‘5’ is invalid (it must not be greater than ‘max_lines’)");
+}
+
+#[test]
+fn err_invalid_lines_range() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        output = { lines = { between = [1] } }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘between’ value ‘[1]’ is invalid (it must be an array of two numbers)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -152,11 +465,45 @@ fn err_invalid_shell_type() {
 fn err_invalid_status_type() {
     let check = CommandCheck::read(&toml! {
         shell = "ls"
-        status = []
+        status = "zero"
     }).unwrap_err();
 
     assert_eq!(check.to_string(),
-               "Parameter ‘status’ value ‘[]’ is invalid (it must be an integer)");
+               "Parameter ‘status’ value ‘\"zero\"’ is invalid (it must be an integer)");
+}
+
+
+#[test]
+fn err_invalid_timeout_type() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        timeout = []
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘timeout’ value ‘[]’ is invalid (it must be a string)");
+}
+
+#[test]
+fn err_invalid_stdin_type() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = 42
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘stdin’ value ‘42’ is invalid (it must be a table)");
+}
+
+#[test]
+fn err_unknown_stdin_parameter() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = { path = "fixture.txt" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘path’ is unknown");
 }
 
 
@@ -173,6 +520,28 @@ fn err_status_too_high() {
                "Parameter ‘status’ value ‘9999999’ is invalid (it must be between 0 and 255)");
 }
 
+#[test]
+fn err_status_list_too_high() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        status = [0, 256]
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ value ‘256’ is invalid (it must be between 0 and 255)");
+}
+
+#[test]
+fn err_unknown_status_parameter() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        status = { is = 0 }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘is’ is unknown");
+}
+
 
 // ---- general read errors ----
 
@@ -193,3 +562,36 @@ fn err_unknown_parameter() {
     assert_eq!(check.to_string(),
                "Parameter ‘uehinuheisnthuesnh’ is unknown");
 }
+
+#[test]
+fn err_misspelt_parameter() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        stdotu = "hello"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘stdotu’ is unknown (did you mean ‘stdout’?)");
+}
+
+#[test]
+fn err_misspelt_short_parameter() {
+    let check = CommandCheck::read(&toml! {
+        shell = "ls"
+        ptty = true
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘ptty’ is unknown (did you mean ‘pty’?)");
+}
+
+#[test]
+fn err_misspelt_nested_parameter() {
+    let check = CommandCheck::read(&toml! {
+        shell = "wc -l"
+        stdin = { files = "fixture.txt" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘files’ is unknown (did you mean ‘file’?)");
+}
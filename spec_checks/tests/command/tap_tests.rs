@@ -1,8 +1,44 @@
 use super::*;
 use spec_checks::tap::{TapCheck};
+use spec_checks::{Invocation, RunShell};
+use spec_exec::{RanCommand, OutputLine, OutputStream, ExitReason};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
 use pretty_assertions::assert_eq;
 
 
+struct MockShell(&'static str);
+
+impl RunShell for MockShell {
+    fn run_command(&self, _: &mut Executor, _: &Invocation) -> Result<Arc<RanCommand>, Arc<ExecError>> {
+        let stdout_lines: Vec<_> = self.0.lines().map(|line| OutputLine { timestamp: SystemTime::now(), line: line.into() }).collect();
+        let output_lines = stdout_lines.iter().map(|line| (OutputStream::Stdout, line.clone())).collect();
+
+        Ok(Arc::new(RanCommand {
+            invocation: String::new(),
+            environment: BTreeMap::new(),
+            directory: None,
+            exit_reason: ExitReason::Status(0),
+            stdout_lines,
+            stderr_lines: Vec::new(),
+            stdout_raw: self.0.as_bytes().to_vec(),
+            stderr_raw: Vec::new(),
+            output_lines,
+            runtime: Duration::from_secs(0),
+        }))
+    }
+}
+
+fn run_tap(output: &'static str) -> Vec<String> {
+    let check = TapCheck::read(&toml! {
+        shell = "./some-prog"
+    }).unwrap();
+
+    let results = check.check(&mut Executor::new(), &MockShell(output));
+    results.into_iter().map(phrase).collect()
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -26,6 +62,87 @@ fn tapped_output_with_environment() {
                "TAP tests for command ‘A=b C=d ./some-prog’");
 }
 
+#[test]
+fn tapped_output_with_directory() {
+    let check = TapCheck::read(&toml! {
+        shell = "./some-prog"
+        directory = "tests"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "TAP tests for command ‘./some-prog’ in ‘tests’");
+}
+
+
+// ---- running tests ----
+
+#[test]
+fn plain_tap() {
+    assert_eq!(run_tap("1..2\nok 1 - first\nnot ok 2 - second\n"), vec![
+        "PASS TAP test #1 passed (first)",
+        "FAIL TAP test #2 failed (second)",
+        "PASS Correct number (2) of tests run",
+    ]);
+}
+
+#[test]
+fn plan_at_end() {
+    assert_eq!(run_tap("ok 1\nok 2\n1..3\n"), vec![
+        "PASS TAP test #1 passed",
+        "PASS TAP test #2 passed",
+        "FAIL Incorrect number of tests run (expected 3, got 2)",
+    ]);
+}
+
+#[test]
+fn tap_13_diagnostics() {
+    let output = "TAP version 13\n1..1\nnot ok 1 - sums\n  ---\n  message: wrong total\n  got: 3\n  ...\n";
+    assert_eq!(run_tap(output), vec![
+        "FAIL TAP test #1 failed (sums)",
+        "PASS Correct number (1) of tests run",
+    ]);
+}
+
+#[test]
+fn directives_and_comments() {
+    let output = "1..3\n# setting up\nok 1 - network # SKIP offline\nnot ok 2 - parser # TODO not written yet\nok 3 - numbers # fast\n";
+    assert_eq!(run_tap(output), vec![
+        "PASS TAP test #1 skipped (network)",
+        "PASS TAP test #2 failed, but is marked TODO (parser)",
+        "PASS TAP test #3 passed (numbers)",
+        "PASS Correct number (3) of tests run",
+    ]);
+}
+
+#[test]
+fn subtests() {
+    let output = "TAP version 13\n    # Subtest: maths\n    1..2\n    ok 1 - adding\n    not ok 2 - dividing\nnot ok 1 - maths\nok 2 - strings\n1..2\n";
+    assert_eq!(run_tap(output), vec![
+        "PASS TAP test #1.1 passed (adding)",
+        "FAIL TAP test #1.2 failed (dividing)",
+        "PASS Correct number (2) of tests run in subtest #1",
+        "FAIL TAP test #1 failed (maths)",
+        "PASS TAP test #2 passed (strings)",
+        "PASS Correct number (2) of tests run",
+    ]);
+}
+
+#[test]
+fn bail_out() {
+    assert_eq!(run_tap("1..3\nok 1\nBail out! Database is down\nok 2\n"), vec![
+        "PASS TAP test #1 passed",
+        "FAIL The tests bailed out (Database is down)",
+    ]);
+}
+
+#[test]
+fn unparseable_line() {
+    assert_eq!(run_tap("ok 1\nwhat is this\n"), vec![
+        "PASS TAP test #1 passed",
+        "FAIL Unparseable TAP line \"what is this\"",
+    ]);
+}
+
 
 // ---- empty string errors ----
 
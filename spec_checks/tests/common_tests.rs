@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use spec_checks::common::parse_duration;
+use pretty_assertions::assert_eq;
+
+
+// ---- durations ----
+
+#[test]
+fn durations() {
+    assert_eq!(parse_duration("5"),     Some(Duration::from_secs(5)));
+    assert_eq!(parse_duration("5s"),    Some(Duration::from_secs(5)));
+    assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+    assert_eq!(parse_duration("1.5s"),  Some(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("2m"),    Some(Duration::from_secs(120)));
+    assert_eq!(parse_duration("1h"),    Some(Duration::from_secs(3600)));
+    assert_eq!(parse_duration("1d"),    Some(Duration::from_secs(86400)));
+}
+
+#[test]
+fn compound_durations() {
+    assert_eq!(parse_duration("2m30s"),          Some(Duration::from_secs(150)));
+    assert_eq!(parse_duration("1h 5m"),          Some(Duration::from_secs(3900)));
+    assert_eq!(parse_duration("1s500ms"),        Some(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("2 minutes"),      Some(Duration::from_secs(120)));
+    assert_eq!(parse_duration("1 hour 30 mins"), Some(Duration::from_secs(5400)));
+}
+
+#[test]
+fn invalid_durations() {
+    assert_eq!(parse_duration(""),             None);
+    assert_eq!(parse_duration("s"),            None);
+    assert_eq!(parse_duration("5 fortnights"), None);
+    assert_eq!(parse_duration("-5s"),          None);
+    assert_eq!(parse_duration("2m30"),         None);
+    assert_eq!(parse_duration("1.2.3s"),       None);
+    assert_eq!(parse_duration("inf"),          None);
+}
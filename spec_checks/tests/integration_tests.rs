@@ -1,4 +1,6 @@
 mod command;
+mod common_tests;
+mod load_tests;
 mod local;
 mod network;
 
@@ -6,10 +8,11 @@ mod network;
 // some shared imports, that get used by every test:
 
 pub use std::fmt;
-pub use std::rc::Rc;
+pub use std::sync::Arc;
 
 pub use toml::{toml, map::Map};
 
+pub use spec_analysis::DataPoint;
 pub use spec_checks::{RunCheck, CheckResult};
 pub use spec_exec::{Executor, ExecError};
 
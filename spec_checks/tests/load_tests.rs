@@ -0,0 +1,370 @@
+use spec_checks::load::{parse_toml, parse_yaml, parse_json, Position, TomlValue};
+use pretty_assertions::assert_eq;
+
+
+// ---- matrices ----
+
+#[test]
+fn no_matrix() {
+    let document = parse_toml("[[cmd]]\nshell = 'echo ${thing}'").unwrap();
+    let entries = &document.checks["cmd"];
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].inner.get("shell"), Some(&TomlValue::from("echo ${thing}")));
+}
+
+#[test]
+fn one_variable() {
+    let document = parse_toml(r#"
+        [[http]]
+        url = "https://${vhost}/"
+        name = "${vhost} is up"
+        matrix = { vhost = [ "one.example", "two.example" ] }
+    "#).unwrap();
+
+    let entries = &document.checks["http"];
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].inner.get("url"), Some(&TomlValue::from("https://one.example/")));
+    assert_eq!(entries[0].name.as_deref(), Some("one.example is up"));
+    assert_eq!(entries[1].inner.get("url"), Some(&TomlValue::from("https://two.example/")));
+    assert_eq!(entries[1].name.as_deref(), Some("two.example is up"));
+
+    assert!(entries.iter().all(|e| e.matrix.is_none()));
+    assert!(entries.iter().all(|e| e.inner.get("matrix").is_none()));
+}
+
+#[test]
+fn every_combination() {
+    let document = parse_toml(r#"
+        [[tcp]]
+        address = "${host}:${port}"
+        matrix = { host = [ "a", "b" ], port = [ 80, 443 ] }
+    "#).unwrap();
+
+    let addresses = document.checks["tcp"].iter()
+                            .map(|e| e.inner.get("address").unwrap().as_str().unwrap().to_owned())
+                            .collect::<Vec<_>>();
+
+    assert_eq!(addresses, vec![ "a:80", "a:443", "b:80", "b:443" ]);
+}
+
+#[test]
+fn whole_placeholder_keeps_type() {
+    let document = parse_toml(r#"
+        [[tcp]]
+        port = "${port}"
+        matrix = { port = [ 22 ] }
+    "#).unwrap();
+
+    assert_eq!(document.checks["tcp"][0].inner.get("port"), Some(&TomlValue::Integer(22)));
+}
+
+#[test]
+fn nested_values() {
+    let document = parse_toml(r#"
+        [[fs]]
+        path = "/etc/${service}.conf"
+        contents = { string = "${service}" }
+        matrix = { service = [ "nginx" ] }
+    "#).unwrap();
+
+    let entry = &document.checks["fs"][0];
+    assert_eq!(entry.inner.get("path"), Some(&TomlValue::from("/etc/nginx.conf")));
+    assert_eq!(entry.inner.get("contents").and_then(|c| c.get("string")), Some(&TomlValue::from("nginx")));
+}
+
+#[test]
+fn empty_variable() {
+    let document = parse_toml(r#"
+        [[cmd]]
+        shell = "echo ${thing}"
+        matrix = { thing = [] }
+    "#).unwrap();
+
+    assert_eq!(document.checks["cmd"].len(), 0);
+}
+
+#[test]
+fn invalid_matrix() {
+    assert!(parse_toml("[[cmd]]\nshell = 'x'\nmatrix = { thing = 'not a list' }").is_err());
+}
+
+
+// ---- variables ----
+
+fn interpolated(toml: &str) -> Result<Vec<spec_checks::load::CheckEntry>, String> {
+    let document = parse_toml(toml).unwrap();
+    document.checks.values().flatten().map(|e| e.interpolated(&document.vars)).collect()
+}
+
+#[test]
+fn variable_in_parameter() {
+    let entries = interpolated(r#"
+        [vars]
+        host = "example.com"
+
+        [[http]]
+        url = "https://${host}/"
+    "#).unwrap();
+
+    assert_eq!(entries[0].inner.get("url"), Some(&TomlValue::from("https://example.com/")));
+}
+
+#[test]
+fn variable_keeps_type() {
+    let entries = interpolated(r#"
+        [vars]
+        port = 22
+
+        [[tcp]]
+        port = "${port}"
+    "#).unwrap();
+
+    assert_eq!(entries[0].inner.get("port"), Some(&TomlValue::Integer(22)));
+}
+
+#[test]
+fn variable_in_hooks() {
+    let entries = interpolated(r#"
+        [vars]
+        user = "fixture"
+
+        [[user]]
+        user = "${user}"
+        setup = "useradd ${user}"
+    "#).unwrap();
+
+    let commands = entries[0].setup.clone().unwrap().into_commands();
+    assert_eq!(commands, vec![ "useradd fixture" ]);
+}
+
+#[test]
+fn environment_variable() {
+    let entries = interpolated(r#"
+        [[fs]]
+        path = "${env:CARGO_MANIFEST_DIR}/Cargo.toml"
+    "#).unwrap();
+
+    let path = format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"));
+    assert_eq!(entries[0].inner.get("path"), Some(&TomlValue::from(path)));
+}
+
+#[test]
+fn escaped_placeholder() {
+    let entries = interpolated(r#"
+        [[cmd]]
+        shell = "echo $${HOME} $HOME $"
+    "#).unwrap();
+
+    assert_eq!(entries[0].inner.get("shell"), Some(&TomlValue::from("echo ${HOME} $HOME $")));
+}
+
+#[test]
+fn unresolved_variable() {
+    let result = interpolated(r#"
+        [[cmd]]
+        shell = "echo ${nothing}"
+    "#);
+
+    assert_eq!(result.unwrap_err(), "nothing");
+}
+
+#[test]
+fn unset_environment_variable() {
+    let result = interpolated(r#"
+        [[cmd]]
+        shell = "echo ${env:SPECSHEET_TEST_UNSET_VARIABLE}"
+    "#);
+
+    assert_eq!(result.unwrap_err(), "env:SPECSHEET_TEST_UNSET_VARIABLE");
+}
+
+
+// ---- includes ----
+
+#[test]
+fn append_documents() {
+    let mut document = parse_toml(r#"
+        include = [ "base.toml" ]
+
+        [vars]
+        host = "base.example"
+        port = 80
+
+        [setup]
+        shell = "base-setup"
+
+        [teardown]
+        shell = "base-teardown"
+
+        [[cmd]]
+        shell = "base"
+    "#).unwrap();
+
+    document.append(parse_toml(r#"
+        [vars]
+        host = "web.example"
+
+        [setup]
+        shell = "web-setup"
+
+        [teardown]
+        shell = "web-teardown"
+
+        [[cmd]]
+        shell = "web"
+
+        [[ping]]
+        target = "${host}"
+    "#).unwrap());
+
+    let shells = document.checks["cmd"].iter().map(|e| e.inner.get("shell").unwrap().as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(shells, vec![ "base", "web" ]);
+    assert_eq!(document.checks["ping"].len(), 1);
+
+    assert_eq!(document.vars["host"], TomlValue::from("web.example"));
+    assert_eq!(document.vars["port"], TomlValue::Integer(80));
+
+    assert_eq!(document.setup.unwrap().shell.into_commands(), vec![ "base-setup", "web-setup" ]);
+    assert_eq!(document.teardown.unwrap().shell.into_commands(), vec![ "web-teardown", "base-teardown" ]);
+    assert_eq!(document.include, vec![ "base.toml" ]);
+}
+
+#[test]
+fn append_without_hooks() {
+    let mut document = parse_toml("[setup]\nshell = 'only'").unwrap();
+    document.append(parse_toml("[[cmd]]\nshell = 'x'").unwrap());
+
+    assert_eq!(document.setup.unwrap().shell.into_commands(), vec![ "only" ]);
+    assert!(document.teardown.is_none());
+}
+
+
+// ---- locations ----
+
+fn at(line: usize, column: usize) -> Option<Position> {
+    Some(Position { line, column })
+}
+
+#[test]
+fn header_lines() {
+    let document = parse_toml("[[cmd]]\nshell = 'true'\n\n[[fs]]\npath = '/'\n\n[[ cmd ]]\nshell = 'false'\n").unwrap();
+
+    let starts = |check_type: &str| document.checks[check_type].iter().map(|e| e.location.start).collect::<Vec<_>>();
+    assert_eq!(starts("cmd"), vec![ at(1, 1), at(7, 1) ]);
+    assert_eq!(starts("fs"), vec![ at(4, 1) ]);
+}
+
+#[test]
+fn matrix_lines() {
+    let document = parse_toml("\n[[ping]]\ntarget = '${host}'\nmatrix = { host = [ 'a', 'b' ] }\n").unwrap();
+
+    let entries = &document.checks["ping"];
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.location.start == at(2, 1)));
+    assert!(entries.iter().all(|e| e.location.parameter("target") == at(3, 1)));
+}
+
+#[test]
+fn inline_entries() {
+    let document = parse_toml("cmd = [ { shell = 'true' },\n        { shell = 'false', status = 1 } ]").unwrap();
+
+    let entries = &document.checks["cmd"];
+    assert_eq!(entries[0].location.start, at(1, 9));
+    assert_eq!(entries[1].location.start, at(2, 9));
+    assert_eq!(entries[1].location.parameter("status"), at(2, 28));
+}
+
+#[test]
+fn parameter_positions() {
+    let document = parse_toml("[[http]]\nurl = 'http://localhost/'\n  status = 200\n\n[http.headers]\nEtag = 'x'\nquery = { 'é' = 1, b = 2 }\n").unwrap();
+
+    let location = &document.checks["http"][0].location;
+    assert_eq!(location.parameter("url"), at(2, 1));
+    assert_eq!(location.parameter("status"), at(3, 3));
+    assert_eq!(location.parameter("headers"), at(5, 7));
+    assert_eq!(location.parameter("Etag"), at(6, 1));
+    assert_eq!(location.parameter("headers.Etag"), at(6, 1));
+    assert_eq!(location.parameter("b"), at(7, 20));
+    assert_eq!(location.parameter("missing"), None);
+}
+
+
+// ---- yaml ----
+
+#[test]
+fn yaml_document() {
+    let document = parse_yaml(r#"
+vars:
+  host: example.com
+setup:
+  shell: [ "mkdir -p /tmp/specsheet" ]
+http:
+  - url: "https://${host}/"
+    name: front page
+    tags: [ web ]
+    status: 200
+"#).unwrap();
+
+    assert_eq!(document.vars.get("host"), Some(&TomlValue::from("example.com")));
+    assert!(document.setup.is_some());
+
+    let entry = &document.checks["http"][0];
+    assert_eq!(entry.inner.get("url"), Some(&TomlValue::from("https://${host}/")));
+    assert_eq!(entry.inner.get("status"), Some(&TomlValue::Integer(200)));
+    assert_eq!(entry.name.as_deref(), Some("front page"));
+    assert_eq!(entry.location.start, None);
+}
+
+#[test]
+fn yaml_matrix() {
+    let document = parse_yaml(r#"
+tcp:
+  - address: "${host}:22"
+    matrix:
+      host: [ a, b ]
+"#).unwrap();
+
+    let addresses = document.checks["tcp"].iter()
+                            .map(|e| e.inner.get("address").unwrap().as_str().unwrap().to_owned())
+                            .collect::<Vec<_>>();
+
+    assert_eq!(addresses, vec![ "a:22", "b:22" ]);
+}
+
+#[test]
+fn empty_yaml_document() {
+    let document = parse_yaml("# nothing to see here\n").unwrap();
+    assert!(document.checks.is_empty());
+}
+
+#[test]
+fn invalid_yaml() {
+    let error = parse_yaml("cmd:\n  - shell: [ unclosed\n").unwrap_err();
+    assert!(error.location().is_some());
+}
+
+
+// ---- json ----
+
+#[test]
+fn json_document() {
+    let document = parse_json(r#"{
+        "include": [ "common.toml" ],
+        "cmd": [ { "shell": "echo ${word}", "matrix": { "word": [ "hi", "bye" ] }, "retries": 2 } ]
+    }"#).unwrap();
+
+    assert_eq!(document.include, vec![ "common.toml" ]);
+
+    let entries = &document.checks["cmd"];
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].inner.get("shell"), Some(&TomlValue::from("echo bye")));
+    assert_eq!(entries[1].retries, Some(2));
+}
+
+#[test]
+fn invalid_json() {
+    let error = parse_json("{ \"cmd\": [ { \"shell\": \"true\" }, ] }").unwrap_err();
+    assert_eq!((error.line(), error.column()), (1, 33));
+}
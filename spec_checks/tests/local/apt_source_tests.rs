@@ -0,0 +1,299 @@
+use super::*;
+use spec_checks::apt_source::{AptSourceCheck, RunAptSources, SourceEntry};
+use pretty_assertions::assert_eq;
+
+
+struct MockAptSources(&'static [(&'static str, &'static str, &'static [&'static str])], &'static [&'static str]);
+
+impl RunAptSources for MockAptSources {
+    fn sources(&self) -> Vec<SourceEntry> {
+        self.0.iter().map(|(uri, suite, components)| SourceEntry {
+            uri: String::from(*uri),
+            suite: String::from(*suite),
+            components: components.iter().map(|e| String::from(*e)).collect(),
+        }).collect()
+    }
+
+    fn key_fingerprints(&self, _: &mut Executor) -> Result<Vec<String>, Arc<ExecError>> {
+        Ok(self.1.iter().map(|e| String::from(*e)).collect())
+    }
+}
+
+const DOCKER: MockAptSources = MockAptSources(
+    &[ ("http://archive.ubuntu.com/ubuntu", "focal", &[ "main", "restricted" ]),
+       ("https://download.docker.com/linux/ubuntu/", "focal", &[ "stable" ]) ],
+    &[ "9DC858229FC7DD38854AE2D88D81803C0EBFCD88" ],
+);
+
+const NOTHING: MockAptSources = MockAptSources(&[], &[]);
+
+
+// ---- regular tests ----
+
+#[test]
+fn present() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://download.docker.com/linux/ubuntu"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Apt source ‘https://download.docker.com/linux/ubuntu’ is present");
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is present",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &NOTHING);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is missing",
+    ]);
+}
+
+#[test]
+fn present_with_suite_and_component() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "http://archive.ubuntu.com/ubuntu"
+        suite = "focal"
+        component = "restricted"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Apt source ‘http://archive.ubuntu.com/ubuntu’ suite ‘focal’ component ‘restricted’ is present");
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is present",
+    ]);
+}
+
+#[test]
+fn wrong_component() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "http://archive.ubuntu.com/ubuntu"
+        suite = "focal"
+        component = "universe"
+    }).unwrap();
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is missing",
+    ]);
+}
+
+#[test]
+fn present_with_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://download.docker.com/linux/ubuntu"
+        key = "9DC8 5822 9FC7 DD38 854A  E2D8 8D81 803C 0EBF CD88"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Apt source ‘https://download.docker.com/linux/ubuntu’ is present with key ‘9DC858229FC7DD38854AE2D88D81803C0EBFCD88’");
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is present",
+        "PASS the key is in a keyring",
+    ]);
+}
+
+#[test]
+fn present_with_key_id() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://download.docker.com/linux/ubuntu"
+        key = "0x8d81803c0ebfcd88"
+    }).unwrap();
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is present",
+        "PASS the key is in a keyring",
+    ]);
+}
+
+#[test]
+fn present_without_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://download.docker.com/linux/ubuntu"
+        key = "0EBFCD89"
+    }).unwrap();
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is present",
+        "FAIL the key is not in any keyring",
+    ]);
+}
+
+#[test]
+fn missing() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://download.docker.com/linux/ubuntu"
+        state = "missing"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Apt source ‘https://download.docker.com/linux/ubuntu’ is missing");
+
+    let results = check.check(&mut Executor::new(), &NOTHING);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is missing",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &DOCKER);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is present",
+    ]);
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_missing_with_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        state = "missing"
+        key = "0EBFCD88"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘key’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
+#[test]
+fn err_component_without_suite() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        component = "main"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘suite’ is missing");
+}
+
+
+// ---- invalid string errors ----
+
+#[test]
+fn err_bad_state() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        state = "installed"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘state’ value ‘\"installed\"’ is invalid (it must be ‘present’ or ‘missing’)");
+}
+
+#[test]
+fn err_non_hex_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        key = "docker.gpg"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘key’ value ‘\"docker.gpg\"’ is invalid (it must be a hexadecimal fingerprint or key ID)");
+}
+
+#[test]
+fn err_short_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        key = "CD88"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘key’ value ‘\"CD88\"’ is invalid (it must be at least eight characters long)");
+}
+
+
+// ---- empty string errors ----
+
+#[test]
+fn err_empty_uri() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uri’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_suite() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        suite = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘suite’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_key() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        key = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘key’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+
+// ---- wrong type errors ----
+
+#[test]
+fn err_invalid_uri_type() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = []
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uri’ value ‘[]’ is invalid (it must be a string)");
+}
+
+#[test]
+fn err_invalid_key_type() {
+    let check = AptSourceCheck::read(&toml! {
+        uri = "https://example.com/apt"
+        key = 1234
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘key’ value ‘1234’ is invalid (it must be a string)");
+}
+
+
+// ---- general read errors ----
+
+#[test]
+fn err_empty_document() {
+    let check = AptSourceCheck::read(&Map::new().into()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uri’ is missing");
+}
+
+#[test]
+fn err_unknown_parameter() {
+    let check = AptSourceCheck::read(&toml! {
+        oaehusnaeothunaoehu = "ntsehousitnhoenith"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘oaehusnaeothunaoehu’ is unknown");
+}
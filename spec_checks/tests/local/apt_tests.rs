@@ -3,10 +3,10 @@ use spec_checks::apt::{AptCheck, RunApt};
 use pretty_assertions::assert_eq;
 
 
-struct MockApt(&'static str, &'static str);
+struct MockApt(&'static str, &'static str, bool);
 
 impl RunApt for MockApt {
-    fn find_package(&self, _: &mut Executor, package_name: &str) -> Result<Option<String>, Rc<ExecError>> {
+    fn find_package(&self, _: &mut Executor, package_name: &str) -> Result<Option<String>, Arc<ExecError>> {
         if package_name == self.0 {
             Ok(Some(self.1.into()))
         }
@@ -14,6 +14,15 @@ impl RunApt for MockApt {
             Ok(None)
         }
     }
+
+    fn is_held(&self, _: &mut Executor, package_name: &str) -> Result<bool, Arc<ExecError>> {
+        Ok(package_name == self.0 && self.2)
+    }
+}
+
+fn version_phrases(check: &AptCheck, got_version: &'static str) -> Vec<String> {
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", got_version, false));
+    results.into_iter().map(phrase).collect()
 }
 
 
@@ -28,13 +37,13 @@ fn installed() {
     assert_eq!(check.to_string(),
                "Package ‘wibble-wobble’ is installed");
 
-    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4"));
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockApt("something-else", "v3.1.4"));
+    let results = check.check(&mut Executor::new(), &MockApt("something-else", "v3.1.4", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is not installed",
@@ -52,20 +61,20 @@ fn installed_with_version() {
     assert_eq!(check.to_string(),
                "Package ‘wibble-wobble’ version ‘v3.1.4’ is installed");
 
-    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4"));
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is installed",
         "PASS version ‘v3.1.4’ is installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockApt("something-else", "v3.1.4"));
+    let results = check.check(&mut Executor::new(), &MockApt("something-else", "v3.1.4", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is not installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v2.2.8"));
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v2.2.8", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is installed",
@@ -74,6 +83,182 @@ fn installed_with_version() {
 }
 
 
+#[test]
+fn installed_at_least_version() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = ">= 1.18"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘wibble-wobble’ version ‘>= 1.18’ is installed");
+
+    assert_eq!(version_phrases(&check, "1.18.0-6ubuntu14"), vec![
+        "PASS it is installed",
+        "PASS version ‘1.18.0-6ubuntu14’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "1.20.1"), vec![
+        "PASS it is installed",
+        "PASS version ‘1.20.1’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "1.18~rc1"), vec![
+        "PASS it is installed",
+        "FAIL version ‘1.18~rc1’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "1.9.3"), vec![
+        "PASS it is installed",
+        "FAIL version ‘1.9.3’ is installed",
+    ]);
+}
+
+#[test]
+fn installed_below_version_with_epoch() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = "<2.0"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘wibble-wobble’ version ‘< 2.0’ is installed");
+
+    assert_eq!(version_phrases(&check, "1.99a-3"), vec![
+        "PASS it is installed",
+        "PASS version ‘1.99a-3’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "1:1.0"), vec![
+        "PASS it is installed",
+        "FAIL version ‘1:1.0’ is installed",
+    ]);
+}
+
+#[test]
+fn installed_equal_version() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = "= 2.8-3"
+    }).unwrap();
+
+    assert_eq!(version_phrases(&check, "2.8-3"), vec![
+        "PASS it is installed",
+        "PASS version ‘2.8-3’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "2.8-03"), vec![
+        "PASS it is installed",
+        "PASS version ‘2.8-03’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "2.8-3ubuntu1"), vec![
+        "PASS it is installed",
+        "FAIL version ‘2.8-3ubuntu1’ is installed",
+    ]);
+}
+
+#[test]
+fn installed_pessimistic_version() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = "~> 2.4"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘wibble-wobble’ version ‘~> 2.4’ is installed");
+
+    assert_eq!(version_phrases(&check, "2.4-1"), vec![
+        "PASS it is installed",
+        "PASS version ‘2.4-1’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "2.10.1"), vec![
+        "PASS it is installed",
+        "PASS version ‘2.10.1’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "3.0"), vec![
+        "PASS it is installed",
+        "FAIL version ‘3.0’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "2.3.9"), vec![
+        "PASS it is installed",
+        "FAIL version ‘2.3.9’ is installed",
+    ]);
+}
+
+#[test]
+fn installed_pessimistic_patch_version() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = "~> 2.4.1"
+    }).unwrap();
+
+    assert_eq!(version_phrases(&check, "2.4.7"), vec![
+        "PASS it is installed",
+        "PASS version ‘2.4.7’ is installed",
+    ]);
+
+    assert_eq!(version_phrases(&check, "2.5.0"), vec![
+        "PASS it is installed",
+        "FAIL version ‘2.5.0’ is installed",
+    ]);
+}
+
+#[test]
+fn installed_and_held() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        held = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘wibble-wobble’ is installed and held");
+
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "1.0", true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS it is held",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "1.0", false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL it is not held",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockApt("something-else", "1.0", true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is not installed",
+    ]);
+}
+
+#[test]
+fn installed_and_not_held() {
+    let check = AptCheck::read(&toml! {
+        package = "wibble-wobble"
+        version = ">= 1.0"
+        held = false
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘wibble-wobble’ version ‘>= 1.0’ is installed and not held");
+
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "1.0", true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS version ‘1.0’ is installed",
+        "FAIL it is held",
+    ]);
+}
+
+
 #[test]
 fn missing() {
     let check = AptCheck::read(&toml! {
@@ -84,13 +269,13 @@ fn missing() {
     assert_eq!(check.to_string(),
                "Package ‘wibble-wobble’ is not installed");
 
-    let results = check.check(&mut Executor::new(), &MockApt("another-package", ""));
+    let results = check.check(&mut Executor::new(), &MockApt("another-package", "", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is not installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4"));
+    let results = check.check(&mut Executor::new(), &MockApt("wibble-wobble", "v3.1.4", false));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is installed",
@@ -126,6 +311,18 @@ fn err_missing_with_version() {
                "Parameter ‘version’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
 }
 
+#[test]
+fn err_missing_with_held() {
+    let check = AptCheck::read(&toml! {
+        package = "foo"
+        state = "missing"
+        held = true
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘held’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -151,6 +348,18 @@ fn err_bad_state() {
 }
 
 
+#[test]
+fn err_operator_without_version() {
+    let check = AptCheck::read(&toml! {
+        package = "wib"
+        version = ">= "
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ value ‘\">= \"’ is invalid (it must have a version after the operator)");
+}
+
+
 // ---- empty string errors ----
 
 #[test]
@@ -210,6 +419,18 @@ fn err_invalid_state_type() {
 }
 
 
+#[test]
+fn err_invalid_held_type() {
+    let check = AptCheck::read(&toml! {
+        package = "twenty-three"
+        held = "yes"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘held’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
+
 // ---- general read errors ----
 
 #[test]
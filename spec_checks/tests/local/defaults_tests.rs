@@ -1,5 +1,5 @@
 use super::*;
-use spec_checks::defaults::{DefaultsCheck, DefaultsLocation, RunDefaults};
+use spec_checks::defaults::{DefaultsCheck, DefaultsLocation, DefaultsValue, RunDefaults};
 use spec_checks::read::Rewrites;
 use pretty_assertions::assert_eq;
 
@@ -7,7 +7,7 @@ use pretty_assertions::assert_eq;
 struct MockDefaults(DefaultsLocation, &'static str);
 
 impl RunDefaults for MockDefaults {
-    fn get_value(&self, _: &mut Executor, location: &DefaultsLocation) -> Result<Option<Rc<str>>, Rc<ExecError>> {
+    fn get_value(&self, _: &mut Executor, location: &DefaultsLocation) -> Result<Option<Arc<str>>, Arc<ExecError>> {
         if *location == self.0 {
             Ok(Some(self.1.into()))
         }
@@ -15,6 +15,33 @@ impl RunDefaults for MockDefaults {
             Ok(None)
         }
     }
+
+    fn get_typed_value(&self, _: &mut Executor, location: &DefaultsLocation) -> Result<Option<DefaultsValue>, Arc<ExecError>> {
+        if *location == self.0 {
+            let value = toml::from_str::<toml::Value>(&format!("value = {}", self.1)).unwrap()["value"].clone();
+            Ok(Some(to_defaults_value(value)))
+        }
+        else {
+            Ok(None)
+        }
+    }
+}
+
+fn to_defaults_value(value: toml::Value) -> DefaultsValue {
+    match value {
+        toml::Value::String(s)   => DefaultsValue::String(s),
+        toml::Value::Boolean(b)  => DefaultsValue::Boolean(b),
+        toml::Value::Integer(i)  => DefaultsValue::Integer(i),
+        toml::Value::Float(f)    => DefaultsValue::Float(f),
+        toml::Value::Array(vs)   => DefaultsValue::Array(vs.into_iter().map(to_defaults_value).collect()),
+        toml::Value::Table(t)    => DefaultsValue::Dictionary(t.into_iter().map(|(k, v)| (k, to_defaults_value(v))).collect()),
+        toml::Value::Datetime(_) => unreachable!(),
+    }
+}
+
+fn location(domain: &str, key: &str, current_host: bool) -> DefaultsLocation {
+    use spec_checks::defaults::DefaultsPlace;
+    DefaultsLocation { place: DefaultsPlace::Domain(domain.into()), key: key.into(), current_host }
 }
 
 
@@ -30,6 +57,151 @@ fn key_present() {
 
     assert_eq!(check.to_string(),
                "Defaults value ‘Apple Global Domain/AppleAquaColorVariant’ is ‘6’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("Apple Global Domain", "AppleAquaColorVariant", false), "6"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("Apple Global Domain", "AppleAquaColorVariant", false), "6.0"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("Apple Global Domain", "AppleAquaColorVariant", false), "\"6\""));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL values do not match; got ‘6’",
+    ]);
+}
+
+#[test]
+fn string_value() {
+    let check = DefaultsCheck::read(&toml! {
+        domain   = "com.apple.Finder"
+        key      = "FXPreferredViewStyle"
+        value    = "Nlsv"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.Finder/FXPreferredViewStyle’ is ‘Nlsv’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.Finder", "FXPreferredViewStyle", false), "Nlsv"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+}
+
+#[test]
+fn boolean_value() {
+    let check = DefaultsCheck::read(&toml! {
+        domain   = "com.apple.dock"
+        key      = "autohide"
+        value    = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.dock/autohide’ is ‘true’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.dock", "autohide", false), "false"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL values do not match; got ‘false’",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.dock", "autohide", false), "1"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL values do not match; got ‘1’",
+    ]);
+}
+
+#[test]
+fn float_value() {
+    let check = DefaultsCheck::read(&toml! {
+        domain   = "com.apple.dock"
+        key      = "autohide-delay"
+        value    = 0.5
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.dock/autohide-delay’ is ‘0.5’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.dock", "autohide-delay", false), "0.5"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+}
+
+#[test]
+fn array_value() {
+    let check = DefaultsCheck::read(&toml! {
+        domain   = "com.apple.dock"
+        key      = "persistent-others"
+        value    = [ "Downloads", "Applications" ]
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.dock/persistent-others’ is ‘[ \"Downloads\", \"Applications\" ]’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.dock", "persistent-others", false), r#"[ "Downloads", "Applications" ]"#));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.dock", "persistent-others", false), r#"[ "Downloads" ]"#));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL values do not match; got ‘[ \"Downloads\" ]’",
+    ]);
+}
+
+#[test]
+fn dictionary_value() {
+    let check = DefaultsCheck::read(&toml! {
+        domain   = "com.apple.screencapture"
+        key      = "settings"
+        value    = { type = "png", shadow = false }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.screencapture/settings’ is ‘{ shadow = false, type = \"png\" }’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.screencapture", "settings", false), r#"{ shadow = false, type = "png" }"#));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
+}
+
+#[test]
+fn current_host() {
+    let check = DefaultsCheck::read(&toml! {
+        domain        = "com.apple.screensaver"
+        key           = "idleTime"
+        value         = 0
+        current_host  = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Defaults value ‘com.apple.screensaver/idleTime’ for the current host is ‘0’");
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.screensaver", "idleTime", false), "0"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL value is missing",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDefaults(location("com.apple.screensaver", "idleTime", true), "0"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS the value matches",
+    ]);
 }
 
 #[test]
@@ -236,11 +408,24 @@ fn err_invalid_value_type() {
     let check = DefaultsCheck::read(&toml! {
         domain = "domain"
         key = "key"
-        value = []
+        value = 1979-05-27T07:32:00Z
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘value’ value ‘1979-05-27T07:32:00Z’ is invalid (it must be a string, number, boolean, array, or table)");
+}
+
+#[test]
+fn err_invalid_current_host_type() {
+    let check = DefaultsCheck::read(&toml! {
+        domain = "domain"
+        key = "key"
+        value = "value"
+        current_host = "yes"
     }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
-               "Parameter ‘value’ value ‘[]’ is invalid (it must be a string or a number)");
+               "Parameter ‘current_host’ value ‘\"yes\"’ is invalid (it must be a boolean)");
 }
 
 
@@ -182,6 +182,17 @@ fn file_contents_empty() {
                "File ‘/usr/local/bin/script.sh’ is empty");
 }
 
+#[test]
+fn file_contents_line_count() {
+    let check = FilesystemCheck::read(&toml! {
+        path = "/etc/hosts"
+        contents = { min_lines = 2, max_lines = 10 }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "File ‘/etc/hosts’ has between ‘2’ and ‘10’ lines");
+}
+
 #[test]
 fn file_contents_non_empty() {
     let check = FilesystemCheck::read(&toml! {
@@ -1,13 +1,21 @@
 use super::*;
 use spec_checks::gem::{GemCheck, RunGem};
+use spec_checks::read::Rewrites;
 use pretty_assertions::assert_eq;
 
+use std::path::Path;
 
-struct MockGem(&'static str);
+
+struct MockGem(&'static str, &'static [&'static str]);
 
 impl RunGem for MockGem {
-    fn find_gem(&self, _: &mut Executor, gem_name: &str) -> Result<bool, Rc<ExecError>> {
-        Ok(gem_name == self.0)
+    fn find_gem(&self, _: &mut Executor, _: Option<&Path>, gem_name: &str) -> Result<Vec<String>, Arc<ExecError>> {
+        if gem_name == self.0 {
+            Ok(self.1.iter().map(|e| String::from(*e)).collect())
+        }
+        else {
+            Ok(Vec::new())
+        }
     }
 }
 
@@ -18,18 +26,18 @@ impl RunGem for MockGem {
 fn installed() {
     let check = GemCheck::read(&toml! {
         gem = "pry"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Gem ‘pry’ is installed");
 
-    let results = check.check(&mut Executor::new(), &MockGem("pry"));
+    let results = check.check(&mut Executor::new(), &MockGem("pry", &[ "0.13.1" ]));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockGem("something-else"));
+    let results = check.check(&mut Executor::new(), &MockGem("something-else", &[ "0.13.1" ]));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is not installed",
@@ -42,18 +50,18 @@ fn missing() {
     let check = GemCheck::read(&toml! {
         gem = "pry"
         state = "missing"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Gem ‘pry’ is not installed");
 
-    let results = check.check(&mut Executor::new(), &MockGem("another-gem"));
+    let results = check.check(&mut Executor::new(), &MockGem("another-gem", &[ "0.13.1" ]));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is not installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockGem("pry"));
+    let results = check.check(&mut Executor::new(), &MockGem("pry", &[ "0.13.1" ]));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is installed",
@@ -61,6 +69,53 @@ fn missing() {
 }
 
 
+#[test]
+fn version_requirement() {
+    let check = GemCheck::read(&toml! {
+        gem = "rails"
+        version = "~> 6.0"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Gem ‘rails’ version ‘~> 6.0’ is installed");
+
+    let results = check.check(&mut Executor::new(), &MockGem("rails", &[ "6.1.0", "5.2.4" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS version ‘6.1.0’ is installed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockGem("rails", &[ "5.2.4", "7.0.0" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL versions ‘5.2.4’, ‘7.0.0’ are installed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockGem("sinatra", &[ "6.1.0" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is not installed",
+    ]);
+}
+
+#[test]
+fn prerelease_version() {
+    let check = GemCheck::read(&toml! {
+        gem = "rails"
+        version = ">= 6.1"
+    }, &Rewrites::new()).unwrap();
+
+    let results = check.check(&mut Executor::new(), &MockGem("rails", &[ "6.1.0.rc1" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL version ‘6.1.0.rc1’ is installed",
+    ]);
+}
+
+
 // ---- parameter combinations ----
 
 #[test]
@@ -68,12 +123,51 @@ fn installed_explicitly() {
     let check = GemCheck::read(&toml! {
         gem   = "pry"
         state = "installed"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Gem ‘pry’ is installed");
 }
 
+#[test]
+fn installed_in_project() {
+    let check = GemCheck::read(&toml! {
+        gem     = "rails"
+        version = ">= 6, < 7"
+        path    = "./webapp"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Gem ‘rails’ version ‘>= 6, < 7’ is installed in project ‘./webapp’");
+}
+
+#[test]
+fn missing_in_project() {
+    let check = GemCheck::read(&toml! {
+        gem   = "byebug"
+        state = "missing"
+        path  = "./webapp"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Gem ‘byebug’ is not installed in project ‘./webapp’");
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_missing_with_version() {
+    let check = GemCheck::read(&toml! {
+        gem     = "rails"
+        state   = "missing"
+        version = "6.0.3"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -81,7 +175,7 @@ fn installed_explicitly() {
 fn err_slashy_gem_name() {
     let check = GemCheck::read(&toml! {
         gem = "this/that"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘gem’ value ‘\"this/that\"’ is invalid (it must not contain a ‘/’ character)");
@@ -91,7 +185,7 @@ fn err_slashy_gem_name() {
 fn err_whitespace_gem_name() {
     let check = GemCheck::read(&toml! {
         gem = "this and that"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘gem’ value ‘\"this and that\"’ is invalid (it must not contain whitespace)");
@@ -102,12 +196,23 @@ fn err_bad_state() {
     let check = GemCheck::read(&toml! {
         gem = "wib"
         state = "oobleck"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘state’ value ‘\"oobleck\"’ is invalid (it must be ‘installed’ or ‘missing’)");
 }
 
+#[test]
+fn err_bad_version() {
+    let check = GemCheck::read(&toml! {
+        gem = "rails"
+        version = "~> latest"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ value ‘\"~> latest\"’ is invalid (it must be a version or a range such as ‘^1.2’)");
+}
+
 
 // ---- empty string errors ----
 
@@ -115,12 +220,34 @@ fn err_bad_state() {
 fn err_empty_gem_name() {
     let check = GemCheck::read(&toml! {
         gem = ""
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘gem’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_version() {
+    let check = GemCheck::read(&toml! {
+        gem = "rails"
+        version = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_path() {
+    let check = GemCheck::read(&toml! {
+        gem = "rails"
+        path = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘path’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -128,7 +255,7 @@ fn err_empty_gem_name() {
 fn err_invalid_name_type() {
     let check = GemCheck::read(&toml! {
         gem = []
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘gem’ value ‘[]’ is invalid (it must be a string)");
@@ -139,7 +266,7 @@ fn err_invalid_state_type() {
     let check = GemCheck::read(&toml! {
         gem = "demux-redux"
         state = []
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘state’ value ‘[]’ is invalid (it must be ‘installed’ or ‘missing’)");
@@ -150,7 +277,7 @@ fn err_invalid_state_type() {
 
 #[test]
 fn err_empty_document() {
-    let check = GemCheck::read(&Map::new().into()).unwrap_err();
+    let check = GemCheck::read(&Map::new().into(), &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘gem’ is missing");
@@ -160,7 +287,7 @@ fn err_empty_document() {
 fn err_unknown_parameter() {
     let check = GemCheck::read(&toml! {
         oaehusnaeothunaoehu = "ntsehousitnhoenith"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘oaehusnaeothunaoehu’ is unknown");
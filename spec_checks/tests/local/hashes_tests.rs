@@ -1,8 +1,27 @@
 use super::*;
-use spec_checks::hashes::{HashCheck};
+use spec_checks::hashes::{HashCheck, RunHash, Algorithm};
 use spec_checks::read::Rewrites;
 use pretty_assertions::assert_eq;
 
+use std::path::{Path, PathBuf};
+
+
+struct MockHash(&'static str, Option<&'static str>);
+
+impl RunHash for MockHash {
+    fn hash_file(&self, _: &mut Executor, _: PathBuf, _: Algorithm) -> Result<String, Arc<ExecError>> {
+        Ok(self.0.into())
+    }
+
+    fn hash_directory(&self, _: &mut Executor, _: &Path, _: Algorithm) -> Result<String, Arc<ExecError>> {
+        Ok(self.0.into())
+    }
+
+    fn checksum_entry(&self, _: &Path, _: &Path) -> Option<String> {
+        self.1.map(String::from)
+    }
+}
+
 
 // ---- regular tests ----
 
@@ -16,6 +35,130 @@ fn hash_matching() {
 
     assert_eq!(check.to_string(),
                "File ‘/usr/bin/specsheet’ has MD5 hash ‘3f22baaf4ba820a800dfc51af5ba1892’");
+
+    let results = check.check(&mut Executor::new(), &MockHash("3f22baaf4ba820a800dfc51af5ba1892", None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS hashes match",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockHash("d41d8cd98f00b204e9800998ecf8427e", None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL hash mismatch",
+    ]);
+}
+
+#[test]
+fn blake3_hash() {
+    let check = HashCheck::read(&toml! {
+        path = "/usr/bin/specsheet"
+        algorithm = "blake3"
+        hash = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "File ‘/usr/bin/specsheet’ has BLAKE3 hash ‘af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262’");
+}
+
+#[test]
+fn directory_hash() {
+    let check = HashCheck::read(&toml! {
+        directory = "/opt/app/lib"
+        algorithm = "sha256"
+        hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Directory ‘/opt/app/lib’ has SHA256 hash ‘9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08’");
+
+    let results = check.check(&mut Executor::new(), &MockHash("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08", None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS hashes match",
+    ]);
+}
+
+#[test]
+fn checksum_file() {
+    let check = HashCheck::read(&toml! {
+        path = "/opt/app/app.tar.gz"
+        algorithm = "sha256"
+        checksum_file = "/opt/app/SHA256SUMS"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "File ‘/opt/app/app.tar.gz’ has the SHA256 hash listed in ‘/opt/app/SHA256SUMS’");
+
+    let results = check.check(&mut Executor::new(), &MockHash("60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752", Some("60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752")));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS hashes match",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockHash("60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752", Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL hash mismatch",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockHash("60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752", None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is not listed in the checksum file",
+    ]);
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_path_and_directory() {
+    let check = HashCheck::read(&toml! {
+        path = "/usr/bin/specsheet"
+        directory = "/usr/bin"
+        algorithm = "md5"
+        hash = "3f22baaf4ba820a800dfc51af5ba1892"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘directory’ is inappropriate when parameter ‘path’ is given");
+}
+
+#[test]
+fn err_hash_and_checksum_file() {
+    let check = HashCheck::read(&toml! {
+        path = "/usr/bin/specsheet"
+        algorithm = "md5"
+        hash = "3f22baaf4ba820a800dfc51af5ba1892"
+        checksum_file = "/usr/bin/MD5SUMS"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘checksum_file’ is inappropriate when parameter ‘hash’ is given");
+}
+
+#[test]
+fn err_directory_and_checksum_file() {
+    let check = HashCheck::read(&toml! {
+        directory = "/usr/bin"
+        algorithm = "md5"
+        checksum_file = "/usr/bin/MD5SUMS"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘checksum_file’ is inappropriate when parameter ‘directory’ is given");
+}
+
+#[test]
+fn err_no_hash() {
+    let check = HashCheck::read(&toml! {
+        path = "/usr/bin/specsheet"
+        algorithm = "md5"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘hash’ is missing");
 }
 
 
@@ -48,6 +191,30 @@ fn err_empty_path() {
                "Parameter ‘path’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_directory() {
+    let check = HashCheck::read(&toml! {
+        directory = ""
+        algorithm = "md5"
+        hash = "3f22baaf4ba820a800dfc51af5ba1892"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘directory’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_checksum_file() {
+    let check = HashCheck::read(&toml! {
+        path = "/usr/bin/specsheet"
+        algorithm = "md5"
+        checksum_file = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘checksum_file’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
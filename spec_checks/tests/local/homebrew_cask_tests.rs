@@ -6,7 +6,7 @@ use pretty_assertions::assert_eq;
 struct MockHomebrewCask(&'static str);
 
 impl RunBrewCask for MockHomebrewCask {
-    fn find_cask(&self, _: &mut Executor, cask_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn find_cask(&self, _: &mut Executor, cask_name: &str) -> Result<bool, Arc<ExecError>> {
         Ok(cask_name == self.0)
     }
 }
@@ -6,7 +6,7 @@ use pretty_assertions::assert_eq;
 struct MockHomebrewTap(&'static str);
 
 impl RunBrewTap for MockHomebrewTap {
-    fn find_tap(&self, _: &mut Executor, tap_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn find_tap(&self, _: &mut Executor, tap_name: &str) -> Result<bool, Arc<ExecError>> {
         Ok(tap_name == self.0)
     }
 }
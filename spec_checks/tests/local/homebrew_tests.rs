@@ -1,14 +1,40 @@
 use super::*;
-use spec_checks::homebrew::{HomebrewCheck, RunBrew};
+use spec_checks::homebrew::{HomebrewCheck, RunBrew, FormulaDetails};
 use pretty_assertions::assert_eq;
 
 
 struct MockHomebrew(&'static str);
 
 impl RunBrew for MockHomebrew {
-    fn find_formula(&self, _: &mut Executor, formula_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn find_formula(&self, _: &mut Executor, formula_name: &str) -> Result<bool, Arc<ExecError>> {
         Ok(formula_name == self.0)
     }
+
+    fn formula_details(&self, _: &mut Executor, _: &str) -> Result<Option<FormulaDetails>, Arc<ExecError>> {
+        Ok(None)
+    }
+}
+
+struct MockHomebrewDetails(FormulaDetails);
+
+impl RunBrew for MockHomebrewDetails {
+    fn find_formula(&self, _: &mut Executor, _: &str) -> Result<bool, Arc<ExecError>> {
+        Ok(true)
+    }
+
+    fn formula_details(&self, _: &mut Executor, _: &str) -> Result<Option<FormulaDetails>, Arc<ExecError>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+fn details(pinned: bool, outdated: bool, linked: bool) -> MockHomebrewDetails {
+    MockHomebrewDetails(FormulaDetails {
+        installed_versions: vec![ "1.21.3".into() ],
+        latest_version: Some("1.21.4".into()),
+        linked_version: if linked { Some("1.21.3".into()) } else { None },
+        pinned,
+        outdated,
+    })
 }
 
 
@@ -74,6 +100,130 @@ fn installed_explicitly() {
                "Formula ‘pry’ is installed");
 }
 
+#[test]
+fn pinned() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        pinned = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Formula ‘wget’ is installed and pinned");
+
+    let results = check.check(&mut Executor::new(), &details(true, false, true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS it is pinned at version ‘1.21.3’",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &details(false, false, true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL it is not pinned",
+    ]);
+}
+
+#[test]
+fn up_to_date_and_linked() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        pinned = false
+        outdated = false
+        linked = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Formula ‘wget’ is installed, not pinned, up to date, and linked");
+
+    let results = check.check(&mut Executor::new(), &details(false, false, true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS it is not pinned",
+        "PASS version ‘1.21.3’ is the latest",
+        "PASS version ‘1.21.3’ is linked",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &details(true, true, false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL it is pinned at version ‘1.21.3’",
+        "FAIL version ‘1.21.3’ is installed, but ‘1.21.4’ is available",
+        "FAIL it is not linked",
+    ]);
+}
+
+#[test]
+fn outdated_and_unlinked() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        outdated = true
+        linked = false
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Formula ‘wget’ is installed, outdated, and not linked");
+
+    let results = check.check(&mut Executor::new(), &details(false, true, false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS version ‘1.21.3’ is installed, and ‘1.21.4’ is available",
+        "PASS it is not linked",
+    ]);
+}
+
+#[test]
+fn details_of_missing_formula() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        linked = true
+    }).unwrap();
+
+    let results = check.check(&mut Executor::new(), &MockHomebrew("curl"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is not installed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockHomebrew("wget"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL Homebrew has no details about it",
+    ]);
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_missing_with_pinned() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        state = "missing"
+        pinned = true
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘pinned’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
+#[test]
+fn err_missing_with_linked() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "wget"
+        state = "missing"
+        linked = false
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘linked’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -145,6 +295,17 @@ fn err_invalid_state_type() {
                "Parameter ‘state’ value ‘[]’ is invalid (it must be ‘installed’ or ‘missing’)");
 }
 
+#[test]
+fn err_invalid_outdated_type() {
+    let check = HomebrewCheck::read(&toml! {
+        formula = "demux-redux"
+        outdated = "no"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘outdated’ value ‘\"no\"’ is invalid (it must be a boolean)");
+}
+
 
 // ---- general read errors ----
 
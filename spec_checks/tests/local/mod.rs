@@ -1,4 +1,5 @@
 mod apt_tests;
+mod apt_source_tests;
 mod defaults_tests;
 mod fs_tests;
 mod gem_tests;
@@ -1,16 +1,31 @@
 use super::*;
 use spec_checks::npm::{NpmCheck, RunNpm};
+use spec_checks::read::Rewrites;
 use pretty_assertions::assert_eq;
 
+use std::path::Path;
 
-struct MockNpm(&'static str);
+
+struct MockNpm(&'static str, &'static str);
 
 impl RunNpm for MockNpm {
-    fn find_package(&self, _: &mut Executor, global_package_name: &str) -> Result<bool, Rc<ExecError>> {
-        Ok(global_package_name == self.0)
+    fn find_package(&self, _: &mut Executor, _: Option<&Path>, package_name: &str) -> Result<Option<String>, Arc<ExecError>> {
+        if package_name == self.0 {
+            Ok(Some(self.1.into()))
+        }
+        else {
+            Ok(None)
+        }
     }
 }
 
+fn version_phrases(check: &NpmCheck, versions: &[&'static str]) -> Vec<String> {
+    versions.iter().map(|version| {
+        let results = check.check(&mut Executor::new(), &MockNpm("typescript", version));
+        phrase(results.into_iter().nth(1).unwrap())
+    }).collect()
+}
+
 
 // ---- regular tests ----
 
@@ -18,18 +33,18 @@ impl RunNpm for MockNpm {
 fn installed() {
     let check = NpmCheck::read(&toml! {
         package = "typescript"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Package ‘typescript’ is installed");
 
-    let results = check.check(&mut Executor::new(), &MockNpm("typescript"));
+    let results = check.check(&mut Executor::new(), &MockNpm("typescript", "3.4.3"));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockNpm("border-collie"));
+    let results = check.check(&mut Executor::new(), &MockNpm("border-collie", "3.4.3"));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is not installed",
@@ -42,18 +57,18 @@ fn missing() {
     let check = NpmCheck::read(&toml! {
         package = "typescript"
         state = "missing"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Package ‘typescript’ is not installed");
 
-    let results = check.check(&mut Executor::new(), &MockNpm("border-collie"));
+    let results = check.check(&mut Executor::new(), &MockNpm("border-collie", "3.4.3"));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "PASS it is not installed",
     ]);
 
-    let results = check.check(&mut Executor::new(), &MockNpm("typescript"));
+    let results = check.check(&mut Executor::new(), &MockNpm("typescript", "3.4.3"));
     let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
     assert_eq!(phrases, vec![
         "FAIL it is installed",
@@ -61,6 +76,134 @@ fn missing() {
 }
 
 
+#[test]
+fn caret_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "lodash"
+        version = "^4.17"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘lodash’ version ‘^4.17’ is installed");
+
+    let results = check.check(&mut Executor::new(), &MockNpm("lodash", "4.17.21"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "PASS version ‘4.17.21’ is installed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockNpm("lodash", "4.16.6"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL version ‘4.16.6’ is installed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockNpm("lodash", "5.0.0"));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is installed",
+        "FAIL version ‘5.0.0’ is installed",
+    ]);
+}
+
+#[test]
+fn caret_zero_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = "^0.2.3"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "0.2.3", "0.2.9", "0.3.0", "0.2.2" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘0.2.3’ is installed",
+        "PASS version ‘0.2.9’ is installed",
+        "FAIL version ‘0.3.0’ is installed",
+        "FAIL version ‘0.2.2’ is installed",
+    ]);
+}
+
+#[test]
+fn tilde_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = "~3.4.1"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "3.4.1", "3.4.5", "3.5.0", "3.4.0" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘3.4.1’ is installed",
+        "PASS version ‘3.4.5’ is installed",
+        "FAIL version ‘3.5.0’ is installed",
+        "FAIL version ‘3.4.0’ is installed",
+    ]);
+}
+
+#[test]
+fn exact_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = "3.4.3"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "3.4.3", "3.4.4", "3.4.3-beta" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘3.4.3’ is installed",
+        "FAIL version ‘3.4.4’ is installed",
+        "FAIL version ‘3.4.3-beta’ is installed",
+    ]);
+}
+
+#[test]
+fn wildcard_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = "3.x"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "3.0.0", "3.9.7", "4.0.0" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘3.0.0’ is installed",
+        "PASS version ‘3.9.7’ is installed",
+        "FAIL version ‘4.0.0’ is installed",
+    ]);
+}
+
+#[test]
+fn alternative_versions() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = ">=16.8 <17 || ^18"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "16.8.0", "16.14.0", "17.0.2", "18.2.0", "16.7.0" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘16.8.0’ is installed",
+        "PASS version ‘16.14.0’ is installed",
+        "FAIL version ‘17.0.2’ is installed",
+        "PASS version ‘18.2.0’ is installed",
+        "FAIL version ‘16.7.0’ is installed",
+    ]);
+}
+
+#[test]
+fn prerelease_ordering() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = ">4.0.0-beta.2"
+    }, &Rewrites::new()).unwrap();
+
+    let phrases = version_phrases(&check, &[ "4.0.0-beta.10", "4.0.0-beta.1", "4.0.0-rc", "4.0.0" ]);
+    assert_eq!(phrases, vec![
+        "PASS version ‘4.0.0-beta.10’ is installed",
+        "FAIL version ‘4.0.0-beta.1’ is installed",
+        "PASS version ‘4.0.0-rc’ is installed",
+        "PASS version ‘4.0.0’ is installed",
+    ]);
+}
+
+
 // ---- parameter combinations ----
 
 #[test]
@@ -68,12 +211,51 @@ fn installed_explicitly() {
     let check = NpmCheck::read(&toml! {
         package = "typescript"
         state = "installed"
-    }).unwrap();
+    }, &Rewrites::new()).unwrap();
 
     assert_eq!(check.to_string(),
                "Package ‘typescript’ is installed");
 }
 
+#[test]
+fn installed_in_project() {
+    let check = NpmCheck::read(&toml! {
+        package = "express"
+        version = "^4.17"
+        path = "./webapp"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘express’ version ‘^4.17’ is installed in project ‘./webapp’");
+}
+
+#[test]
+fn missing_in_project() {
+    let check = NpmCheck::read(&toml! {
+        package = "left-pad"
+        state = "missing"
+        path = "./webapp"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Package ‘left-pad’ is not installed in project ‘./webapp’");
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_missing_with_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        state = "missing"
+        version = "^3"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -82,12 +264,34 @@ fn err_bad_state() {
     let check = NpmCheck::read(&toml! {
         package = "wib"
         state = "demi"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘state’ value ‘\"demi\"’ is invalid (it must be ‘installed’ or ‘missing’)");
 }
 
+#[test]
+fn err_bad_version() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = "latest"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ value ‘\"latest\"’ is invalid (it must be a version or a range such as ‘^1.2’)");
+}
+
+#[test]
+fn err_dangling_operator() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        version = ">="
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘version’ value ‘\">=\"’ is invalid (it must be a version or a range such as ‘^1.2’)");
+}
+
 
 // ---- empty string errors ----
 
@@ -95,12 +299,23 @@ fn err_bad_state() {
 fn err_empty_package_name() {
     let check = NpmCheck::read(&toml! {
         package = ""
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘package’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_path() {
+    let check = NpmCheck::read(&toml! {
+        package = "typescript"
+        path = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘path’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -108,7 +323,7 @@ fn err_empty_package_name() {
 fn err_invalid_name_type() {
     let check = NpmCheck::read(&toml! {
         package = []
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘package’ value ‘[]’ is invalid (it must be a string)");
@@ -119,7 +334,7 @@ fn err_invalid_state_type() {
     let check = NpmCheck::read(&toml! {
         package = "demux-redux"
         state = []
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘state’ value ‘[]’ is invalid (it must be ‘installed’ or ‘missing’)");
@@ -130,7 +345,7 @@ fn err_invalid_state_type() {
 
 #[test]
 fn err_empty_document() {
-    let check = NpmCheck::read(&Map::new().into()).unwrap_err();
+    let check = NpmCheck::read(&Map::new().into(), &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘package’ is missing");
@@ -140,7 +355,7 @@ fn err_empty_document() {
 fn err_unknown_parameter() {
     let check = NpmCheck::read(&toml! {
         oaehusnaeothunaoehu = "ntsehousitnhoenith"
-    }).unwrap_err();
+    }, &Rewrites::new()).unwrap_err();
 
     assert_eq!(check.to_string(),
                "Parameter ‘oaehusnaeothunaoehu’ is unknown");
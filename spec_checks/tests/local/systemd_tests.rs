@@ -1,8 +1,26 @@
 use super::*;
-use spec_checks::systemd::{SystemdCheck};
+use spec_checks::systemd::{SystemdCheck, RunSystemctl, ServiceState, Scope};
+use std::time::Duration;
 use pretty_assertions::assert_eq;
 
 
+struct MockSystemctl(ServiceState, &'static [(&'static str, &'static str)], Option<u64>);
+
+impl RunSystemctl for MockSystemctl {
+    fn service_state(&self, _: &mut Executor, _: Scope, _: &str) -> Result<ServiceState, Arc<ExecError>> {
+        Ok(self.0)
+    }
+
+    fn unit_property(&self, _: &mut Executor, _: Scope, _: &str, property_name: &str) -> Result<Option<String>, Arc<ExecError>> {
+        Ok(self.1.iter().find(|e| e.0 == property_name).map(|e| e.1.to_owned()))
+    }
+
+    fn next_elapse(&self, _: &mut Executor, _: Scope, _: &str) -> Result<Option<Duration>, Arc<ExecError>> {
+        Ok(self.2.map(Duration::from_secs))
+    }
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -48,6 +66,233 @@ fn service_is_missing() {
                "Service ‘sshd’ is missing");
 }
 
+#[test]
+fn service_is_enabled() {
+    let check = SystemdCheck::read(&toml! {
+        service = "sshd"
+        enablement = "enabled"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Service ‘sshd’ is enabled");
+}
+
+#[test]
+fn service_has_properties() {
+    let check = SystemdCheck::read(&toml! {
+        service = "postgresql"
+        properties = { Restart = "always", User = "postgres" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Service ‘postgresql’ has properties ‘Restart=always’ and ‘User=postgres’");
+}
+
+#[test]
+fn service_is_running_and_masked_with_property() {
+    let check = SystemdCheck::read(&toml! {
+        service = "postgresql"
+        state = "stopped"
+        enablement = "masked"
+        properties = { Restart = "no" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Service ‘postgresql’ is stopped and masked with property ‘Restart=no’");
+}
+
+#[test]
+fn run_enablement_and_properties() {
+    let check = SystemdCheck::read(&toml! {
+        service = "postgresql"
+        state = "running"
+        enablement = "enabled"
+        properties = { Restart = "always", User = "postgres" }
+    }).unwrap();
+
+    let systemctl = MockSystemctl(ServiceState::Running, &[ ("UnitFileState", "enabled"), ("Restart", "always"), ("User", "root") ], None);
+    let results = check.check(&mut Executor::new(), &systemctl);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it is running",
+        "PASS it is enabled",
+        "PASS property ‘Restart’ is ‘always’",
+        "FAIL property ‘User’ is ‘root’",
+    ]);
+
+    let systemctl = MockSystemctl(ServiceState::Stopped, &[ ("UnitFileState", "disabled") ], None);
+    let results = check.check(&mut Executor::new(), &systemctl);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is stopped",
+        "FAIL it is disabled",
+        "FAIL property ‘Restart’ is not set",
+        "FAIL property ‘User’ is not set",
+    ]);
+}
+
+#[test]
+fn user_service_is_running() {
+    let check = SystemdCheck::read(&toml! {
+        service = "syncthing"
+        scope = "user"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User service ‘syncthing’ is running");
+}
+
+#[test]
+fn timer_is_running() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "logrotate"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Timer ‘logrotate’ is running");
+}
+
+#[test]
+fn timer_with_suffix() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "logrotate.timer"
+        state = "stopped"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Timer ‘logrotate’ is stopped");
+}
+
+#[test]
+fn user_timer_elapses_and_succeeds() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        scope = "user"
+        next_elapse_within = "24h"
+        last_result = "success"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User timer ‘backup’ next elapses within 24h and last ran with result ‘success’");
+}
+
+#[test]
+fn timer_is_enabled_and_elapses() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        enablement = "enabled"
+        next_elapse_within = "6h"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Timer ‘backup’ is enabled and next elapses within 6h");
+}
+
+#[test]
+fn run_timer() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        next_elapse_within = "1h"
+        last_result = "success"
+    }).unwrap();
+
+    let systemctl = MockSystemctl(ServiceState::Running, &[ ("Result", "success") ], Some(600));
+    let results = check.check(&mut Executor::new(), &systemctl);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS it next elapses within 1h",
+        "PASS its service last ran with result ‘success’",
+    ]);
+
+    let systemctl = MockSystemctl(ServiceState::Running, &[ ("Result", "exit-code") ], Some(7200));
+    let results = check.check(&mut Executor::new(), &systemctl);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it next elapses in 7200s",
+        "FAIL its service last ran with result ‘exit-code’",
+    ]);
+
+    let systemctl = MockSystemctl(ServiceState::Stopped, &[], None);
+    let results = check.check(&mut Executor::new(), &systemctl);
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL it is not scheduled to elapse",
+        "FAIL its service has no result",
+    ]);
+}
+
+
+// ---- parameter combinations ----
+
+#[test]
+fn err_missing_and_enablement() {
+    let check = SystemdCheck::read(&toml! {
+        service = "sshd"
+        state = "missing"
+        enablement = "disabled"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘enablement’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
+#[test]
+fn err_missing_and_properties() {
+    let check = SystemdCheck::read(&toml! {
+        service = "sshd"
+        state = "missing"
+        properties = { User = "root" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘properties’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
+#[test]
+fn err_timer_and_service() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        service = "backup"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘timer’ is inappropriate when parameter ‘service’ is given");
+}
+
+#[test]
+fn err_service_and_next_elapse() {
+    let check = SystemdCheck::read(&toml! {
+        service = "sshd"
+        next_elapse_within = "1h"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘next_elapse_within’ is inappropriate when parameter ‘service’ is given");
+}
+
+#[test]
+fn err_service_and_last_result() {
+    let check = SystemdCheck::read(&toml! {
+        service = "sshd"
+        last_result = "success"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘last_result’ is inappropriate when parameter ‘service’ is given");
+}
+
+#[test]
+fn err_missing_and_next_elapse() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        state = "missing"
+        next_elapse_within = "1h"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘next_elapse_within’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -72,6 +317,61 @@ fn err_bad_state() {
                "Parameter ‘state’ value ‘\"oobleck\"’ is invalid (it must be ‘running’ or ‘stopped’ or ‘missing’)");
 }
 
+#[test]
+fn err_bad_enablement() {
+    let check = SystemdCheck::read(&toml! {
+        service = "wibd"
+        enablement = "sometimes"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘enablement’ value ‘\"sometimes\"’ is invalid (it must be ‘enabled’ or ‘disabled’ or ‘masked’ or ‘static’)");
+}
+
+#[test]
+fn err_empty_properties() {
+    let check = SystemdCheck::read(&toml! {
+        service = "wibd"
+        properties = {}
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘properties’ value ‘’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_bad_scope() {
+    let check = SystemdCheck::read(&toml! {
+        service = "wibd"
+        scope = "global"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘scope’ value ‘\"global\"’ is invalid (it must be ‘system’ or ‘user’)");
+}
+
+#[test]
+fn err_bad_next_elapse() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        next_elapse_within = "soon"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘next_elapse_within’ value ‘\"soon\"’ is invalid (it must be a duration such as ‘24h’)");
+}
+
+#[test]
+fn err_zero_next_elapse() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        next_elapse_within = "0s"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘next_elapse_within’ value ‘\"0s\"’ is invalid (it must not be zero)");
+}
+
 
 // ---- empty string errors ----
 
@@ -85,6 +385,27 @@ fn err_empty_service_name() {
                "Parameter ‘service’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_timer_name() {
+    let check = SystemdCheck::read(&toml! {
+        timer = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘timer’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_last_result() {
+    let check = SystemdCheck::read(&toml! {
+        timer = "backup"
+        last_result = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘last_result’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -109,6 +430,17 @@ fn err_invalid_state_type() {
                "Parameter ‘state’ value ‘[]’ is invalid (it must be ‘running’ or ‘stopped’ or ‘missing’)");
 }
 
+#[test]
+fn err_invalid_properties_type() {
+    let check = SystemdCheck::read(&toml! {
+        service = "httpd"
+        properties = "Restart=always"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘properties’ value ‘\"Restart=always\"’ is invalid (it must be a table)");
+}
+
 
 // ---- general read errors ----
 
@@ -1,8 +1,29 @@
 use super::*;
-use spec_checks::ufw::{UfwCheck};
+use spec_checks::ufw::{UfwCheck, RunUfw, Rule, Action, DefaultPolicies, Portspec, Protocol};
 use pretty_assertions::assert_eq;
 
 
+struct MockUfw(Vec<(Option<&'static str>, Action, bool)>, Option<(&'static str, &'static str)>);
+
+impl RunUfw for MockUfw {
+    fn find_rules(&self, _: &mut Executor, _: Portspec, _: Protocol) -> Result<Vec<Rule>, Arc<ExecError>> {
+        let rules = self.0.iter().map(|&(iface, action, ipv6)| {
+            Rule { iface: iface.map(String::from), action, allow: "Anywhere".into(), ipv6 }
+        });
+
+        Ok(rules.collect())
+    }
+
+    fn default_policies(&self, _: &mut Executor) -> Result<Option<DefaultPolicies>, Arc<ExecError>> {
+        let policies = self.1.map(|(incoming, outgoing)| {
+            DefaultPolicies { incoming: incoming.into(), outgoing: outgoing.into(), routed: "disabled".into() }
+        });
+
+        Ok(policies)
+    }
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -39,6 +60,18 @@ fn tcp_rule_missing() {
 
     assert_eq!(check.to_string(),
                "Rule for TCP port ‘8080’ does not exist");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule missing",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Deny, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL rule exists",
+    ]);
 }
 
 #[test]
@@ -52,6 +85,124 @@ fn udp_rule_ipv6() {
 
     assert_eq!(check.to_string(),
                "Rule for UDP port ‘8080’ (IPv6) exists with allow ‘Anywhere’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Allow, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL rule missing",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Allow, false), (None, Action::Allow, true) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule exists",
+        "PASS Allow matches",
+    ]);
+}
+
+#[test]
+fn port_range() {
+    let check = UfwCheck::read(&toml! {
+        port = "6000:6007"
+        protocol = "tcp"
+        allow = "Anywhere"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Rule for TCP ports ‘6000–6007’ exists with allow ‘Anywhere’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Allow, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule exists",
+        "PASS Allow matches",
+    ]);
+}
+
+#[test]
+fn deny_rule() {
+    let check = UfwCheck::read(&toml! {
+        port = 23
+        protocol = "tcp"
+        action = "deny"
+        allow = "Anywhere"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Rule for TCP port ‘23’ exists with deny ‘Anywhere’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Deny, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule exists",
+        "PASS action matches",
+        "PASS Allow matches",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Allow, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule exists",
+        "FAIL action is ‘allow’",
+        "PASS Allow matches",
+    ]);
+}
+
+#[test]
+fn interface_rule() {
+    let check = UfwCheck::read(&toml! {
+        port = 8500
+        protocol = "tcp"
+        interface = "eth0"
+        allow = "Anywhere"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Rule for TCP port ‘8500’ on ‘eth0’ exists with allow ‘Anywhere’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (Some("eth0"), Action::Allow, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule exists",
+        "PASS Allow matches",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (Some("eth1"), Action::Allow, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL rule missing",
+    ]);
+}
+
+#[test]
+fn default_policies() {
+    let check = UfwCheck::read(&toml! {
+        default_incoming = "deny"
+        default_outgoing = "allow"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Default incoming policy is ‘deny’ and outgoing policy is ‘allow’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![], Some(("deny", "allow"))));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS incoming policy is ‘deny’",
+        "PASS outgoing policy is ‘allow’",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![], Some(("allow", "allow"))));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL incoming policy is ‘allow’",
+        "PASS outgoing policy is ‘allow’",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL the firewall is inactive",
+    ]);
 }
 
 
@@ -70,6 +221,35 @@ fn present_explicitly() {
                "Rule for UDP port ‘8080’ exists with allow ‘Anywhere’");
 }
 
+#[test]
+fn missing_with_action() {
+    let check = UfwCheck::read(&toml! {
+        port = 22
+        protocol = "tcp"
+        state = "missing"
+        action = "allow"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Rule for TCP port ‘22’ does not exist with action ‘allow’");
+
+    let results = check.check(&mut Executor::new(), &MockUfw(vec![ (None, Action::Limit, false) ], None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS rule missing",
+    ]);
+}
+
+#[test]
+fn routed_policy() {
+    let check = UfwCheck::read(&toml! {
+        default_routed = "disabled"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "Default routed policy is ‘disabled’");
+}
+
 
 // ---- invalid parameter combination errors ----
 
@@ -86,6 +266,17 @@ fn err_missing_with_allow() {
                "Parameter ‘allow’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
 }
 
+#[test]
+fn err_policy_with_port() {
+    let check = UfwCheck::read(&toml! {
+        port = 8080
+        default_incoming = "deny"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘port’ is inappropriate when parameter ‘default_incoming’ is given");
+}
+
 
 // ---- invalid string/value errors ----
 
@@ -124,6 +315,53 @@ fn err_bad_state() {
                "Parameter ‘state’ value ‘\"filtered\"’ is invalid (it must be ‘present’ or ‘missing’)");
 }
 
+#[test]
+fn err_bad_port_range() {
+    let check = UfwCheck::read(&toml! {
+        port = "6000-6007"
+        protocol = "tcp"
+        allow = "Anywhere"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘port’ value ‘\"6000-6007\"’ is invalid (it must be a range of ports such as ‘6000:6007’)");
+}
+
+#[test]
+fn err_backwards_port_range() {
+    let check = UfwCheck::read(&toml! {
+        port = "6007:6000"
+        protocol = "tcp"
+        allow = "Anywhere"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘port’ value ‘\"6007:6000\"’ is invalid (it must be a range from a lower port to a higher one)");
+}
+
+#[test]
+fn err_bad_action() {
+    let check = UfwCheck::read(&toml! {
+        port = 8080
+        protocol = "tcp"
+        action = "drop"
+        allow = "Anywhere"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘action’ value ‘\"drop\"’ is invalid (it must be ‘allow’ or ‘deny’ or ‘reject’ or ‘limit’)");
+}
+
+#[test]
+fn err_bad_policy() {
+    let check = UfwCheck::read(&toml! {
+        default_incoming = "disabled"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘default_incoming’ value ‘\"disabled\"’ is invalid (it must be ‘allow’ or ‘deny’ or ‘reject’)");
+}
+
 
 // ---- empty string errors ----
 
@@ -139,6 +377,19 @@ fn err_empty_allow() {
                "Parameter ‘allow’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_interface() {
+    let check = UfwCheck::read(&toml! {
+        port = 8080
+        protocol = "tcp"
+        interface = ""
+        allow = "Anywhere"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘interface’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -1,8 +1,25 @@
 use super::*;
-use spec_checks::user::{UserCheck};
+use spec_checks::BuiltInCheck;
+use spec_checks::user::{UserCheck, LookupUser, PasswordState};
 use spec_checks::read::Rewrites;
 use pretty_assertions::assert_eq;
 
+use users::User;
+use users::os::unix::UserExt;
+
+
+struct MockPasswd(u32, Option<PasswordState>);
+
+impl LookupUser for MockPasswd {
+    fn lookup_user(&self, username: &str) -> Option<User> {
+        Some(User::new(self.0, username, self.0).with_home_dir("/home/bethany"))
+    }
+
+    fn password_state(&self, _: &str) -> Option<PasswordState> {
+        self.1
+    }
+}
+
 
 // ---- regular tests ----
 
@@ -49,6 +66,127 @@ fn exists_with_groups() {
                "User ‘bethany’ exists and is a member of groups ‘these’ and ‘those’");
 }
 
+#[test]
+fn exists_with_uid_and_home() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        uid = 1001
+        home = "/home/bethany"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘bethany’ exists with UID ‘1001’ and home directory ‘/home/bethany’");
+
+    let results = check.check(&MockPasswd(1001, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "PASS user has correct UID",
+        "PASS user has correct home directory",
+    ]);
+
+    let results = check.check(&MockPasswd(1002, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "FAIL user has UID ‘1002’",
+        "PASS user has correct home directory",
+    ]);
+}
+
+#[test]
+fn exists_with_primary_group_id() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        primary_group = 1001
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘bethany’ exists with primary group ID ‘1001’");
+
+    let results = check.check(&MockPasswd(1001, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "PASS user has correct primary group",
+    ]);
+}
+
+#[test]
+fn system_user() {
+    let check = UserCheck::read(&toml! {
+        user = "consul"
+        system = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘consul’ exists and is a system user");
+
+    let results = check.check(&MockPasswd(998, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "PASS user is a system user",
+    ]);
+
+    let results = check.check(&MockPasswd(1001, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "FAIL user is not a system user",
+    ]);
+}
+
+#[test]
+fn locked() {
+    let check = UserCheck::read(&toml! {
+        user = "consul"
+        locked = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘consul’ exists and is locked");
+
+    let results = check.check(&MockPasswd(998, Some(PasswordState::Locked)));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "PASS password is locked",
+    ]);
+
+    let results = check.check(&MockPasswd(998, Some(PasswordState::Usable)));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "FAIL password is not locked",
+    ]);
+
+    let results = check.check(&MockPasswd(998, None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "FAIL password state could not be read",
+    ]);
+}
+
+#[test]
+fn has_a_password() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        no_password = false
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘bethany’ exists and has a password");
+
+    let results = check.check(&MockPasswd(1001, Some(PasswordState::Empty)));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS user exists",
+        "FAIL password is empty",
+    ]);
+}
+
 
 // ---- parameter combinations ----
 
@@ -75,6 +213,50 @@ fn everything() {
                "User ‘bethany’ exists with login shell ‘/usr/local/bin/fish’ and is a member of groups ‘these’ and ‘those’");
 }
 
+#[test]
+fn everything_new() {
+    let check = UserCheck::read(&toml! {
+        user = "consul"
+        uid = 998
+        home = "/var/lib/consul"
+        login_shell = "/usr/sbin/nologin"
+        primary_group = "consul"
+        system = true
+        locked = true
+        no_password = false
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "User ‘consul’ exists with UID ‘998’ and home directory ‘/var/lib/consul’ and login shell ‘/usr/sbin/nologin’ and primary group ‘consul’ and is a system user and is locked and has a password");
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_missing_with_uid() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        state = "missing"
+        uid = 1001
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uid’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
+#[test]
+fn err_missing_with_locked() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        state = "missing"
+        locked = true
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘locked’ is inappropriate when parameter ‘state’ is ‘\"missing\"’");
+}
+
 
 // ---- invalid string errors ----
 
@@ -89,6 +271,17 @@ fn err_bad_state() {
                "Parameter ‘state’ value ‘\"ish\"’ is invalid (it must be ‘present’ or ‘missing’)");
 }
 
+#[test]
+fn err_negative_uid() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        uid = -1
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uid’ value ‘-1’ is invalid (it must be a valid user ID)");
+}
+
 
 // ---- empty string errors ----
 
@@ -113,6 +306,28 @@ fn err_empty_login_shell() {
                "Parameter ‘login_shell’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_home() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        home = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘home’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_primary_group() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        primary_group = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘primary_group’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 #[test]
 fn err_empty_group_name() {
     let check = UserCheck::read(&toml! {
@@ -148,6 +363,39 @@ fn err_invalid_login_shell_type() {
                "Parameter ‘login_shell’ value ‘[]’ is invalid (it must be a string)");
 }
 
+#[test]
+fn err_invalid_uid_type() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        uid = "1001"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘uid’ value ‘\"1001\"’ is invalid (it must be an integer)");
+}
+
+#[test]
+fn err_invalid_primary_group_type() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        primary_group = []
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘primary_group’ value ‘[]’ is invalid (it must be a string or a number)");
+}
+
+#[test]
+fn err_invalid_locked_type() {
+    let check = UserCheck::read(&toml! {
+        user = "bethany"
+        locked = "yes"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘locked’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
 #[test]
 fn err_invalid_groups_type() {
     let check = UserCheck::read(&toml! {
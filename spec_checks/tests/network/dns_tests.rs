@@ -1,8 +1,28 @@
 use super::*;
-use spec_checks::dns::{DnsCheck};
+use spec_checks::dns::{DnsCheck, RunDns, Request, Response};
 use pretty_assertions::assert_eq;
 
 
+struct MockDig(&'static [&'static str]);
+
+impl RunDns for MockDig {
+    fn get_response(&self, _: &mut Executor, _: &Request) -> Result<Response, Arc<ExecError>> {
+        let values = self.0.iter().map(|&v| v.into()).collect();
+        Ok(Response { values, .. Response::default() })
+    }
+}
+
+struct MockDigWithTtls(&'static [(&'static str, u32)], bool);
+
+impl RunDns for MockDigWithTtls {
+    fn get_response(&self, _: &mut Executor, _: &Request) -> Result<Response, Arc<ExecError>> {
+        let values = self.0.iter().map(|&(v, _)| v.into()).collect();
+        let ttls = self.0.iter().map(|&(_, t)| t).collect();
+        Ok(Response { values, ttls, authenticated: self.1 })
+    }
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -42,6 +62,206 @@ fn present_using_nameserver() {
                "DNS ‘A’ record for ‘millimeter.io’ exists with value ‘159.65.215.200’ (according to 1.1.1.1)");
 }
 
+#[test]
+fn values_all() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = [ "192.0.2.1", "192.0.2.2" ]
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with values ‘192.0.2.1’ and ‘192.0.2.2’");
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.2", "192.0.2.1", "192.0.2.3" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS all the values are present",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.1" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL the values are different, missing ‘192.0.2.2’",
+    ]);
+}
+
+#[test]
+fn values_any() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = [ "192.0.2.1", "192.0.2.2", "192.0.2.3" ]
+        match = "any"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’, ‘192.0.2.2’ or ‘192.0.2.3’");
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.3" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.4" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL the record is different, got ‘[\"192.0.2.4\"]’ instead",
+    ]);
+}
+
+#[test]
+fn values_exact() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = [ "192.0.2.1", "192.0.2.2" ]
+        match = "exact"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with exactly values ‘192.0.2.1’ and ‘192.0.2.2’");
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.2", "192.0.2.1" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS exactly the expected values are present",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[ "192.0.2.1", "192.0.2.3", "192.0.2.4" ]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL the values are different, missing ‘192.0.2.2’, with extra ‘192.0.2.3’ and ‘192.0.2.4’",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDig(&[]));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL the record is missing",
+    ]);
+}
+
+#[test]
+fn ttl_range() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { min = 60, max = 3600 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ with TTL between ‘60’ and ‘3600’");
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 300) ], false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "PASS the TTL is in range",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 86400) ], false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "FAIL the TTL is out of range, got ‘86400’",
+    ]);
+}
+
+#[test]
+fn ttl_minimum() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { min = 60 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ with TTL of at least ‘60’");
+}
+
+#[test]
+fn ttl_exact() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = 300
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ with TTL ‘300’");
+}
+
+#[test]
+fn ttl_comparison() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { gt = 60, ne = 3600 }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ with TTL of more than ‘60’ and other than ‘3600’");
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 300) ], false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "PASS the TTL is in range",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 3600) ], false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "FAIL the TTL is out of range, got ‘3600’",
+    ]);
+}
+
+#[test]
+fn ttl_between() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { between = [60, 3600] }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ with TTL between ‘60’ and ‘3600’");
+}
+
+#[test]
+fn dnssec() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        dnssec = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "DNS ‘A’ record for ‘example.com’ exists with value ‘192.0.2.1’ and is validated with DNSSEC");
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 300) ], true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "PASS the response is validated with DNSSEC",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockDigWithTtls(&[ ("192.0.2.1", 300) ], false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS there is a record present",
+        "FAIL the response is not validated with DNSSEC",
+    ]);
+}
+
 
 // ---- invalid parameter combination errors ----
 
@@ -70,6 +290,97 @@ fn err_state_with_no_value() {
                "Parameter ‘value’ is missing");
 }
 
+#[test]
+fn err_value_and_values() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        values = [ "192.0.2.2" ]
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘value’ is inappropriate when parameter ‘values’ is given");
+}
+
+#[test]
+fn err_match_without_values() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        match = "exact"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘match’ is inappropriate when parameter ‘values’ is ‘\"exact\"’");
+}
+
+#[test]
+fn err_missing_with_values() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        state = "absent"
+        values = [ "192.0.2.1" ]
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘values’ is inappropriate when parameter ‘state’ is ‘\"absent\"’");
+}
+
+#[test]
+fn err_missing_with_ttl() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        state = "absent"
+        ttl = 300
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘ttl’ is inappropriate when parameter ‘state’ is ‘\"absent\"’");
+}
+
+#[test]
+fn err_ttl_backwards() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { min = 3600, max = 60 }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘ttl’ value ‘3600’ is invalid (the minimum must not be greater than the maximum)");
+}
+
+#[test]
+fn err_ttl_between_backwards() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { between = [3600, 60] }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘between’ value ‘[3600, 60]’ is invalid (the first number must not be greater than the second)");
+}
+
+#[test]
+fn err_negative_ttl() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = -1
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘ttl’ value ‘-1’ is invalid (it must be a positive number)");
+}
+
 // ---- invalid string errors ----
 
 #[test]
@@ -111,6 +422,20 @@ fn err_bad_rameserver() {
 }
 
 
+#[test]
+fn err_bad_match() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = [ "192.0.2.1" ]
+        match = "most"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘match’ value ‘\"most\"’ is invalid (it must be ‘all’ or ‘any’ or ‘exact’)");
+}
+
+
 // ---- empty string errors ----
 
 #[test]
@@ -125,6 +450,18 @@ fn err_empty_domain() {
                "Parameter ‘domain’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_values() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = []
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘values’ value ‘[]’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -190,6 +527,57 @@ fn err_invalid_value_type() {
                "Parameter ‘value’ value ‘[]’ is invalid (it must be a string)");
 }
 
+#[test]
+fn err_invalid_values_type() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        values = "192.0.2.1"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘values’ value ‘\"192.0.2.1\"’ is invalid (it must be an array of strings)");
+}
+
+#[test]
+fn err_invalid_ttl_type() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = "long"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘ttl’ value ‘\"long\"’ is invalid (it must be a number or a table with ‘min’ and ‘max’)");
+}
+
+#[test]
+fn err_invalid_ttl_comparison_type() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        ttl = { gt = "long" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘gt’ value ‘\"long\"’ is invalid (it must be an integer)");
+}
+
+#[test]
+fn err_invalid_dnssec_type() {
+    let check = DnsCheck::read(&toml! {
+        domain = "example.com"
+        type = "A"
+        value = "192.0.2.1"
+        dnssec = "yes"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘dnssec’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
 
 // ---- general read errors ----
 
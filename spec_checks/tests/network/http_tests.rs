@@ -1,6 +1,6 @@
 use super::*;
 use spec_checks::http::{HttpCheck};
-use spec_checks::read::Rewrites;
+use spec_checks::read::{Rewrite, Rewrites};
 use pretty_assertions::assert_eq;
 
 
@@ -27,6 +27,306 @@ fn http_call_succeeds_with_status() {
                "HTTP request to ‘https://example.com/’ has status ‘200’");
 }
 
+#[test]
+fn http_call_succeeds_with_status_list() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+        status = [200, 204]
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/health’ has status ‘200’ or ‘204’");
+}
+
+#[test]
+fn http_call_succeeds_with_status_class() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+        status = "2xx"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/health’ has status ‘2xx’");
+}
+
+#[test]
+fn http_call_succeeds_with_status_comparison() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+        status = { between = [200, 299], ne = 204 }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/health’ has status between ‘200’ and ‘299’ and other than ‘204’");
+}
+
+#[test]
+fn http_call_succeeds_with_status_comparison_list() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+        status = [{ lt = 300 }, 304]
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/health’ has status less than ‘300’ or ‘304’");
+}
+
+#[test]
+fn http_call_succeeds_with_mixed_statuses() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+        status = [200, "3XX"]
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/health’ has status ‘200’ or ‘3xx’");
+}
+
+#[test]
+fn http_call_also_headers() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        status = 200
+
+        [also]
+        X-Frame-Options = "DENY"
+        X-Powered-By = { absent = true }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/’ has status ‘200’, has header ‘X-Frame-Options’ with value ‘DENY’, has no header ‘X-Powered-By’");
+}
+
+#[test]
+fn http_call_also_header_regex() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        Strict-Transport-Security = { regex = "max-age=\\d+" }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/’ has header ‘Strict-Transport-Security’ matching regex ‘/max-age=\\d+/’");
+}
+
+#[test]
+fn http_call_also_header_case_insensitive() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        X-Content-Type-Options = { value = "NoSniff", case_insensitive = true }
+        Server = { absent = false }
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://example.com/’ has header ‘Server’, has header ‘X-Content-Type-Options’ with value ‘NoSniff’ (ignoring case)");
+}
+
+#[test]
+fn http_call_insecure() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://self-signed.example.com/"
+        insecure = true
+        status = 200
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://self-signed.example.com/’ without certificate verification has status ‘200’");
+}
+
+#[test]
+fn http_call_with_client_cert() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://internal.example.com/"
+        client_cert = "client.pem"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://internal.example.com/’ with client certificate ‘client.pem’ succeeds");
+}
+
+#[test]
+fn http_call_cert_error() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://expired.example.com/"
+        expect_cert_error = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘https://expired.example.com/’ fails with a certificate error");
+}
+
+#[test]
+fn http_call_follows_redirects() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘http://example.com/’ following redirects succeeds");
+}
+
+#[test]
+fn http_call_final_url() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+        final_url = "https://www.example.com/"
+        status = 200
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘http://example.com/’ following redirects ends at ‘https://www.example.com/’, has status ‘200’");
+}
+
+#[test]
+fn http_call_max_redirects() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+        max_redirects = 1
+        final_url = "https://example.com/"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘http://example.com/’ following at most 1 redirect ends at ‘https://example.com/’");
+}
+
+#[test]
+fn http_call_rewritten() {
+    let mut rewrites = Rewrites::new();
+    rewrites.add(Rewrite::Url("https://example.com/".into(), "http://localhost:8080/".into()));
+    rewrites.add(Rewrite::Path("/etc/ssl".into(), "/tmp/ssl".into()));
+
+    let parameters = toml! {
+        url = "https://example.com/health"
+        status = 200
+        client_cert = "/etc/ssl/client.pem"
+    };
+
+    let check = HttpCheck::read(&parameters, &rewrites).unwrap();
+    assert_eq!(check.to_string(),
+               "HTTP request to ‘http://localhost:8080/health’ with client certificate ‘/tmp/ssl/client.pem’ has status ‘200’");
+
+    assert_eq!(rewrites.parameters("http", &parameters), toml! {
+        url = "http://localhost:8080/health"
+        status = 200
+        client_cert = "/tmp/ssl/client.pem"
+    });
+}
+
+
+// ---- analysis properties ----
+
+#[test]
+fn properties_default_port() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/health"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.properties(),
+               vec![ DataPoint::InvolvesHost("example.com"), DataPoint::InvolvesPort(443) ]);
+}
+
+#[test]
+fn properties_explicit_port() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://admin@db01:8080?q=1"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.properties(),
+               vec![ DataPoint::InvolvesHost("db01"), DataPoint::InvolvesPort(8080) ]);
+}
+
+#[test]
+fn properties_ipv6() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://[::1]:9000/"
+    }, &Rewrites::new()).unwrap();
+
+    assert_eq!(check.properties(),
+               vec![ DataPoint::InvolvesHost("::1"), DataPoint::InvolvesPort(9000) ]);
+}
+
+
+// ---- invalid parameter combination errors ----
+
+#[test]
+fn err_cert_error_with_status() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://expired.example.com/"
+        expect_cert_error = true
+        status = 200
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ is inappropriate when parameter ‘expect_cert_error’ is ‘true’");
+}
+
+#[test]
+fn err_final_url_without_following() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        final_url = "https://example.com/"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘final_url’ is inappropriate when parameter ‘follow_redirects’ is ‘false’");
+}
+
+#[test]
+fn err_max_redirects_without_following() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = false
+        max_redirects = 3
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘max_redirects’ is inappropriate when parameter ‘follow_redirects’ is ‘false’");
+}
+
+#[test]
+fn err_redirect_to_while_following() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+        redirect_to = "https://example.com/"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘redirect_to’ is inappropriate when parameter ‘follow_redirects’ is ‘true’");
+}
+
+#[test]
+fn err_also_value_and_regex() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        Server = { value = "nginx", regex = "nginx.*" }
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘regex’ is inappropriate when parameter ‘value’ is given");
+}
+
+#[test]
+fn err_also_case_insensitive_without_value() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        Server = { regex = "nginx.*", case_insensitive = true }
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘value’ is missing");
+}
+
 
 // ---- empty string errors ----
 
@@ -40,6 +340,29 @@ fn err_empty_url() {
                "Parameter ‘url’ value ‘\"\"’ is invalid (it must not be empty)");
 }
 
+#[test]
+fn err_empty_client_cert() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://internal.example.com/"
+        client_cert = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘client_cert’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_empty_final_url() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+        final_url = ""
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘final_url’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
 
 // ---- wrong type errors ----
 
@@ -53,6 +376,76 @@ fn err_invalid_url_type() {
                "Parameter ‘url’ value ‘[]’ is invalid (it must be a string)");
 }
 
+#[test]
+fn err_empty_status_list() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        status = []
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ value ‘[]’ is invalid (it must not be empty)");
+}
+
+#[test]
+fn err_invalid_status_class() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        status = "2xy"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ value ‘\"2xy\"’ is invalid (it must be a status, a class such as ‘2xx’, or an array of them)");
+}
+
+#[test]
+fn err_invalid_status_type() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        status = [200, true]
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘status’ value ‘[200, true]’ is invalid (it must be a status, a class such as ‘2xx’, or an array of them)");
+}
+
+#[test]
+fn err_invalid_status_range() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        status = { between = 200 }
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘between’ value ‘200’ is invalid (it must be an array of two numbers)");
+}
+
+#[test]
+fn err_invalid_also_regex() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        Server = { regex = "nginx(" }
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘regex’ value ‘\"nginx(\"’ is invalid (it must be a valid regex)");
+}
+
+#[test]
+fn err_invalid_also_type() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+
+        [also]
+        Server = 123
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘also’ value ‘123’ is invalid (it must be a table)");
+}
+
 #[test]
 fn err_invalid_body_type() {
     let check = HttpCheck::read(&toml! {
@@ -64,6 +457,40 @@ fn err_invalid_body_type() {
                "Parameter ‘body’ value ‘[]’ is invalid (it must be a table)");
 }
 
+#[test]
+fn err_invalid_insecure_type() {
+    let check = HttpCheck::read(&toml! {
+        url = "https://example.com/"
+        insecure = "yes"
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘insecure’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
+#[test]
+fn err_invalid_follow_redirects_type() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = 1
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘follow_redirects’ value ‘1’ is invalid (it must be a boolean)");
+}
+
+#[test]
+fn err_negative_max_redirects() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://example.com/"
+        follow_redirects = true
+        max_redirects = -1
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘max_redirects’ value ‘-1’ is invalid (it must not be negative)");
+}
+
 
 // ---- general read errors ----
 
@@ -84,3 +511,14 @@ fn err_unknown_parameter() {
     assert_eq!(check.to_string(),
                "Parameter ‘oaehusnaeothunaoehu’ is unknown");
 }
+
+#[test]
+fn err_non_string_header() {
+    let check = HttpCheck::read(&toml! {
+        url = "http://localhost/"
+        headers = { X-Count = 1 }
+    }, &Rewrites::new()).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘headers’ value ‘1’ is invalid (it must be a string)");
+}
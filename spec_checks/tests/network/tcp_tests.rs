@@ -1,8 +1,21 @@
 use super::*;
-use spec_checks::tcp::{TcpCheck};
+use spec_checks::tcp::{TcpCheck, RunTcp, Request, TlsRequest, Connection};
 use pretty_assertions::assert_eq;
 
 
+struct MockNet(Connection, bool);
+
+impl RunTcp for MockNet {
+    fn send_tcp_request(&self, _: &Request) -> Connection {
+        self.0.clone()
+    }
+
+    fn tls_handshake(&self, _: &mut Executor, _: &TlsRequest) -> Result<bool, Arc<ExecError>> {
+        Ok(self.1)
+    }
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -59,6 +72,90 @@ fn port_open_from_interface() {
                "TCP port ‘8080’ from interface ‘eth1’ is open");
 }
 
+#[test]
+fn banner() {
+    let check = TcpCheck::read(&toml! {
+        port = 22
+        banner = { regex = "^SSH-2\\.0" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "TCP port ‘22’ is open with banner matching regex ‘/^SSH-2\\.0/’");
+
+    let results = check.check(&mut Executor::new(), &MockNet(Connection::Accepted(Some(b"SSH-2.0-OpenSSH_8.4\r\n".to_vec())), false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "PASS banner matches regex",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockNet(Connection::Accepted(Some(b"220 ftp ready\r\n".to_vec())), false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "FAIL banner did not match the regex",
+    ]);
+}
+
+#[test]
+fn tls() {
+    let check = TcpCheck::read(&toml! {
+        port = 443
+        address = "192.0.2.1"
+        tls = true
+        sni = "example.com"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "TCP port ‘443’ on ‘192.0.2.1’ is open and speaks TLS for ‘example.com’");
+
+    let results = check.check(&mut Executor::new(), &MockNet(Connection::Accepted(None), true));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "PASS TLS handshake succeeded",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockNet(Connection::Accepted(None), false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "FAIL TLS handshake failed",
+    ]);
+
+    let results = check.check(&mut Executor::new(), &MockNet(Connection::Refused, false));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL connection refused",
+    ]);
+}
+
+#[test]
+fn banner_and_tls() {
+    let check = TcpCheck::read(&toml! {
+        port = 8443
+        banner = { empty = true }
+        tls = true
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "TCP port ‘8443’ is open with empty banner, and speaks TLS");
+}
+
+
+// ---- analysis properties ----
+
+#[test]
+fn properties() {
+    let check = TcpCheck::read(&toml! {
+        port = 5432
+        address = "db01"
+    }).unwrap();
+
+    assert_eq!(check.properties(),
+               vec![ DataPoint::InvolvesHost("db01"), DataPoint::InvolvesPort(5432) ]);
+}
+
 
 // ---- parameter combinations ----
 
@@ -86,6 +183,65 @@ fn everything() {
                "TCP port ‘8080’ on ‘192.168.3.3’ from interface ‘eth1’ is closed");
 }
 
+#[test]
+fn with_ufw() {
+    let check = TcpCheck::read(&toml! {
+        port = 22
+        ufw = { allow = "Anywhere" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "TCP port ‘22’ (with UFW check to ‘Anywhere’) is open");
+
+    assert_eq!(check.ufw_check().unwrap().to_string(),
+               "Rule for TCP port ‘22’ exists with allow ‘Anywhere’");
+}
+
+#[test]
+fn without_ufw() {
+    let check = TcpCheck::read(&toml! {
+        port = 22
+    }).unwrap();
+
+    assert_eq!(check.ufw_check(), None);
+}
+
+
+#[test]
+fn err_closed_with_banner() {
+    let check = TcpCheck::read(&toml! {
+        port = 22
+        state = "closed"
+        banner = { string = "SSH" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘banner’ is inappropriate when parameter ‘state’ is ‘\"closed\"’");
+}
+
+#[test]
+fn err_closed_with_tls() {
+    let check = TcpCheck::read(&toml! {
+        port = 443
+        state = "closed"
+        tls = true
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘tls’ is inappropriate when parameter ‘state’ is ‘\"closed\"’");
+}
+
+#[test]
+fn err_sni_without_tls() {
+    let check = TcpCheck::read(&toml! {
+        port = 443
+        sni = "example.com"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘sni’ is inappropriate when parameter ‘tls’ is ‘false’");
+}
+
 
 // ---- invalid value errors ----
 
@@ -167,6 +323,29 @@ fn err_invalid_ufw_type() {
 }
 
 
+#[test]
+fn err_invalid_tls_type() {
+    let check = TcpCheck::read(&toml! {
+        port = 443
+        tls = "yes"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘tls’ value ‘\"yes\"’ is invalid (it must be a boolean)");
+}
+
+#[test]
+fn err_invalid_banner_type() {
+    let check = TcpCheck::read(&toml! {
+        port = 22
+        banner = "SSH"
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘banner’ value ‘\"SSH\"’ is invalid (it must be a table)");
+}
+
+
 // ---- general read errors ----
 
 #[test]
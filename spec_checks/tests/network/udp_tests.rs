@@ -1,8 +1,18 @@
 use super::*;
-use spec_checks::udp::{UdpCheck};
+use spec_checks::BuiltInCheck;
+use spec_checks::udp::{UdpCheck, RunUdp, Request};
 use pretty_assertions::assert_eq;
 
 
+struct MockNet(Option<&'static [u8]>);
+
+impl RunUdp for MockNet {
+    fn send_udp_request(&self, _: &Request) -> Option<Vec<u8>> {
+        self.0.map(<[u8]>::to_vec)
+    }
+}
+
+
 // ---- regular tests ----
 
 #[test]
@@ -59,6 +69,49 @@ fn port_open_from_interface() {
                "UDP port ‘8080’ from interface ‘eth1’ responds");
 }
 
+#[test]
+fn send_string() {
+    let check = UdpCheck::read(&toml! {
+        port = 8125
+        send = "specsheet.probe:1|c"
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "UDP port ‘8125’ responds to ‘specsheet.probe:1|c’");
+}
+
+#[test]
+fn send_hex_and_expect() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        send = { hex = "13:37 01 00" }
+        expect = { regex = "^\\x13\\x37" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "UDP port ‘53’ responds after sending 4 bytes with reply matching regex ‘/^\\x13\\x37/’");
+
+    let results = check.check(&MockNet(Some(b"\x13\x37\x81\x80")));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "PASS reply matches regex",
+    ]);
+
+    let results = check.check(&MockNet(Some(b"\x00\x00")));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "PASS received a response",
+        "FAIL reply did not match the regex",
+    ]);
+
+    let results = check.check(&MockNet(None));
+    let phrases = results.into_iter().map(phrase).collect::<Vec<_>>();
+    assert_eq!(phrases, vec![
+        "FAIL connection refused",
+    ]);
+}
+
 
 // ---- parameter combinations ----
 
@@ -86,6 +139,20 @@ fn everything() {
                "UDP port ‘8080’ on ‘192.168.3.3’ from interface ‘eth1’ does not respond");
 }
 
+#[test]
+fn with_ufw() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        ufw = { allow = "Anywhere" }
+    }).unwrap();
+
+    assert_eq!(check.to_string(),
+               "UDP port ‘53’ (with UFW check to ‘Anywhere’) responds");
+
+    assert_eq!(check.ufw_check().unwrap().to_string(),
+               "Rule for UDP port ‘53’ exists with allow ‘Anywhere’");
+}
+
 
 // ---- invalid value errors ----
 
@@ -135,6 +202,52 @@ fn err_bad_state() {
 }
 
 
+#[test]
+fn err_bad_hex() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        send = { hex = "13 3" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘hex’ value ‘\"13 3\"’ is invalid (it must be a string of hexadecimal bytes)");
+}
+
+#[test]
+fn err_non_hex() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        send = { hex = "zz" }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘hex’ value ‘\"zz\"’ is invalid (it must be a string of hexadecimal bytes)");
+}
+
+#[test]
+fn err_no_response_with_expect() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        state = "no-response"
+        expect = { empty = false }
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘expect’ is inappropriate when parameter ‘state’ is ‘\"no-response\"’");
+}
+
+#[test]
+fn err_empty_send() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        send = ""
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘send’ value ‘\"\"’ is invalid (it must not be empty)");
+}
+
+
 // ---- wrong type errors ----
 
 #[test]
@@ -181,6 +294,18 @@ fn err_invalid_ufw_type() {
 }
 
 
+#[test]
+fn err_invalid_send_type() {
+    let check = UdpCheck::read(&toml! {
+        port = 53
+        send = 1234
+    }).unwrap_err();
+
+    assert_eq!(check.to_string(),
+               "Parameter ‘send’ value ‘1234’ is invalid (it must be a string or a table with ‘hex’)");
+}
+
+
 // ---- general read errors ----
 
 #[test]
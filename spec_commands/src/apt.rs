@@ -12,13 +12,21 @@
 //! apparmor/bionic-updates,bionic-security,now 2.12-4ubuntu5.1 amd64 [installed]
 //! apport/now 2.20.9-0ubuntu7.5 all [installed,upgradable to: 2.20.9-0ubuntu7.6]
 //! ```
+//!
+//! Held packages come from `apt-mark`, which lists one package per line:
+//!
+//! ```text
+//! $ apt-mark showhold
+//! linux-image-generic
+//! nginx
+//! ```
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
 use spec_checks::apt::RunApt;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -27,6 +35,7 @@ use super::GlobalOptions;
 #[derive(Debug)]
 pub struct AptCommand {
     exec: Option<Exec<AptOutput>>,
+    holds: Option<Exec<AptMarkOutput>>,
 }
 
 impl AptCommand {
@@ -34,12 +43,15 @@ impl AptCommand {
     /// Creates a new apt command.
     pub fn create(global_options: &impl GlobalOptions) -> Self {
         let exec = global_options.command("apt.output");
-        Self { exec }
+        let holds = global_options.command("apt.holds");
+        Self { exec, holds }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.exec.into_iter().flat_map(Exec::into_command)
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let exec = self.exec.into_iter().flat_map(Exec::into_command);
+        let holds = self.holds.into_iter().flat_map(Exec::into_command);
+        exec.chain(holds)
     }
 }
 
@@ -51,11 +63,24 @@ impl RunApt for AptCommand {
         }
     }
 
-    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<Option<String>, Rc<ExecError>> {
+    fn prime_holds(&mut self) {
+        if self.holds.is_none() {
+            debug!("Priming apt-mark command");
+            self.holds = Some(Exec::actual(apt_mark_showhold_cmd()));
+        }
+    }
+
+    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<Option<String>, Arc<ExecError>> {
         debug!("Finding apt package -> {:?}", package_name);
         let output = self.exec.as_ref().unwrap().run(executor)?;
         Ok(output.find_package(package_name))
     }
+
+    fn is_held(&self, executor: &mut Executor, package_name: &str) -> Result<bool, Arc<ExecError>> {
+        debug!("Finding apt package hold -> {:?}", package_name);
+        let output = self.holds.as_ref().unwrap().run(executor)?;
+        Ok(output.lines.iter().any(|line| &**line == package_name))
+    }
 }
 
 fn apt_list_installed_cmd() -> Command {
@@ -64,16 +89,22 @@ fn apt_list_installed_cmd() -> Command {
     cmd
 }
 
+fn apt_mark_showhold_cmd() -> Command {
+    let mut cmd = Command::new("apt-mark");
+    cmd.arg("showhold");
+    cmd
+}
+
 
 /// The **apt output** encapsulates the output lines of an
 /// invoked `AptCommand`.
 #[derive(Debug)]
 pub struct AptOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for AptOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
@@ -98,6 +129,21 @@ impl AptOutput {
 }
 
 
+/// The **apt-mark output** encapsulates the output lines of an invoked
+/// `apt-mark showhold` command, which are the names of held packages.
+#[derive(Debug)]
+pub struct AptMarkOutput {
+    lines: Vec<Arc<str>>,
+}
+
+impl CommandOutput for AptMarkOutput {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        exit_reason.should_be(0)?;
+        Ok(Self { lines })
+    }
+}
+
+
 #[cfg(test)]
 mod test {
     use super::*;
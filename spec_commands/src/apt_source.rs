@@ -0,0 +1,356 @@
+//! Apt sources and keyrings
+//!
+//! The sources are read from the files in `/etc/apt` directly, rather than
+//! by running a program. They come in two formats: the one-line format, in
+//! `sources.list` and `sources.list.d/*.list`,
+//!
+//! ```text
+//! deb [arch=amd64 signed-by=/etc/apt/keyrings/docker.gpg] https://download.docker.com/linux/ubuntu focal stable
+//! ```
+//!
+//! and the deb822 format, in `sources.list.d/*.sources`:
+//!
+//! ```text
+//! Types: deb deb-src
+//! URIs: http://archive.ubuntu.com/ubuntu
+//! Suites: focal focal-updates
+//! Components: main restricted
+//! ```
+//!
+//! # Sample output
+//!
+//! The keys come from running `gpg` over every keyring file Apt uses. The
+//! lines that matter are the ones with fingerprints:
+//!
+//! ```text
+//! $ gpg --show-keys --with-colons /etc/apt/keyrings/docker.gpg
+//! pub:-:4096:1:8D81803C0EBFCD88:1487788586:::-:::scESA::::::23::0:
+//! fpr:::::::::9DC858229FC7DD38854AE2D88D81803C0EBFCD88:
+//! uid:-::::1487792064::B5CF8D3B3A0D0C4C2A2A5F5A5F2E2D4F8F8A8B8A::Docker Release (CE deb) <docker@docker.com>::::::::::0:
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::*;
+
+use spec_checks::apt_source::{RunAptSources, SourceEntry};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
+
+use super::GlobalOptions;
+
+
+/// The **apt sources command** reads the list of sources, and runs `gpg` to
+/// list the keys in Apt’s keyrings.
+#[derive(Debug)]
+pub struct AptSourcesCommand {
+    directory: PathBuf,
+    sources: Option<Mutex<Option<Vec<SourceEntry>>>>,
+    keys: Option<Exec<GpgOutput>>,
+}
+
+impl AptSourcesCommand {
+
+    /// Creates a new apt sources command.
+    pub fn create(global_options: &impl GlobalOptions) -> Self {
+        let directory = global_options.key_value("apt_source.directory").unwrap_or_else(|| "/etc/apt".into()).into();
+        let keys = global_options.command("apt_source.keys");
+        Self { directory, sources: None, keys }
+    }
+
+    /// Returns an iterator over the Commands contained within.
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        self.keys.into_iter().flat_map(Exec::into_command)
+    }
+}
+
+impl RunAptSources for AptSourcesCommand {
+    fn prime(&mut self) {
+        if self.sources.is_none() {
+            debug!("Priming apt sources with directory {:?}", self.directory);
+            self.sources = Some(Mutex::new(None));
+        }
+    }
+
+    fn prime_keys(&mut self) {
+        if self.keys.is_none() {
+            debug!("Priming gpg command");
+
+            let keyrings = keyring_files(&self.directory);
+            self.keys = Some(if keyrings.is_empty() {
+                Exec::predetermined("apt_source.keys", GpgOutput { fingerprints: Vec::new() })
+            }
+            else {
+                Exec::actual(gpg_show_keys_cmd(&keyrings))
+            });
+        }
+    }
+
+    fn sources(&self) -> Vec<SourceEntry> {
+        let mut slot = self.sources.as_ref().unwrap().lock().unwrap();
+        let sources = slot.get_or_insert_with(|| read_sources(&self.directory));
+        sources.clone()
+    }
+
+    fn key_fingerprints(&self, executor: &mut Executor) -> Result<Vec<String>, Arc<ExecError>> {
+        debug!("Listing apt keyring fingerprints");
+        let output = self.keys.as_ref().unwrap().run(executor)?;
+        Ok(output.fingerprints.clone())
+    }
+}
+
+fn gpg_show_keys_cmd(keyrings: &[PathBuf]) -> Command {
+    let mut cmd = Command::new("gpg");
+    cmd.arg("--show-keys").arg("--with-colons");
+    cmd.args(keyrings);
+    cmd
+}
+
+
+/// Reads every source from the `sources.list` file and the files in the
+/// `sources.list.d` directory within Apt’s configuration directory.
+fn read_sources(directory: &Path) -> Vec<SourceEntry> {
+    let mut sources = Vec::new();
+
+    let main_list = directory.join("sources.list");
+    if main_list.is_file() {
+        if let Some(contents) = read_file(&main_list) {
+            sources.extend(parse_one_line_sources(&contents));
+        }
+    }
+
+    for path in directory_files(&directory.join("sources.list.d")) {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("list") => {
+                if let Some(contents) = read_file(&path) {
+                    sources.extend(parse_one_line_sources(&contents));
+                }
+            }
+            Some("sources") => {
+                if let Some(contents) = read_file(&path) {
+                    sources.extend(parse_deb822_sources(&contents));
+                }
+            }
+            _ => {
+                debug!("Ignoring file {:?} in sources directory", path);
+            }
+        }
+    }
+
+    sources
+}
+
+/// Returns the paths of the keyring files Apt uses: the old `trusted.gpg`
+/// file, and everything in the directories keyrings are kept in.
+fn keyring_files(directory: &Path) -> Vec<PathBuf> {
+    let mut keyrings = Vec::new();
+
+    let trusted = directory.join("trusted.gpg");
+    if trusted.is_file() {
+        keyrings.push(trusted);
+    }
+
+    keyrings.extend(directory_files(&directory.join("trusted.gpg.d")));
+    keyrings.extend(directory_files(&directory.join("keyrings")));
+    keyrings.extend(directory_files(Path::new("/usr/share/keyrings")));
+    keyrings
+}
+
+/// Returns the paths of the files in a directory, in order, or nothing if
+/// the directory cannot be read.
+fn directory_files(directory: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Could not read directory {:?}: {}", directory, e);
+            return Vec::new();
+        }
+    };
+
+    let mut paths = entries.filter_map(Result::ok)
+                           .map(|entry| entry.path())
+                           .filter(|path| path.is_file())
+                           .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            Some(contents)
+        }
+        Err(e) => {
+            warn!("Could not read sources file {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Parses the binary package sources out of a file in the one-line format,
+/// ignoring source package lines, comments, and the options in brackets.
+fn parse_one_line_sources(contents: &str) -> Vec<SourceEntry> {
+    let mut sources = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace().peekable();
+
+        if fields.next() != Some("deb") {
+            continue;
+        }
+
+        if matches!(fields.peek(), Some(field) if field.starts_with('[')) {
+            for field in &mut fields {
+                if field.ends_with(']') {
+                    break;
+                }
+            }
+        }
+
+        if let (Some(uri), Some(suite)) = (fields.next(), fields.next()) {
+            let components = fields.map(String::from).collect();
+            sources.push(SourceEntry { uri: uri.into(), suite: suite.into(), components });
+        }
+    }
+
+    sources
+}
+
+/// Parses the binary package sources out of a file in the deb822 format,
+/// where each paragraph can list several URIs and suites.
+fn parse_deb822_sources(contents: &str) -> Vec<SourceEntry> {
+    let mut sources = Vec::new();
+
+    for paragraph in contents.split("\n\n") {
+        let mut types = Vec::new();
+        let mut uris = Vec::new();
+        let mut suites = Vec::new();
+        let mut components = Vec::new();
+        let mut enabled = true;
+
+        for line in paragraph.lines() {
+            if line.starts_with('#') || line.starts_with(char::is_whitespace) {
+                continue;
+            }
+
+            if let Some((field, value)) = line.split_once(':') {
+                let values = value.split_whitespace().map(String::from);
+
+                match &field.trim().to_ascii_lowercase()[..] {
+                    "types"       => types.extend(values),
+                    "uris"        => uris.extend(values),
+                    "suites"      => suites.extend(values),
+                    "components"  => components.extend(values),
+                    "enabled"     => enabled = value.trim() != "no",
+                    _             => {/* ignore other fields */},
+                }
+            }
+        }
+
+        if ! enabled || ! types.iter().any(|t| t == "deb") {
+            continue;
+        }
+
+        for uri in &uris {
+            for suite in &suites {
+                sources.push(SourceEntry { uri: uri.clone(), suite: suite.clone(), components: components.clone() });
+            }
+        }
+    }
+
+    sources
+}
+
+
+/// The **gpg output** holds the fingerprints of the keys listed by an
+/// invoked `gpg --show-keys` command.
+#[derive(Debug)]
+pub struct GpgOutput {
+    fingerprints: Vec<String>,
+}
+
+impl CommandOutput for GpgOutput {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        exit_reason.should_be(0)?;
+
+        let fingerprints = lines.iter()
+            .filter_map(|line| line.strip_prefix("fpr:"))
+            .filter_map(|rest| rest.split(':').find(|field| ! field.is_empty()))
+            .map(String::from)
+            .collect();
+
+        Ok(Self { fingerprints })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(uri: &str, suite: &str, components: &[&str]) -> SourceEntry {
+        let components = components.iter().map(|e| String::from(*e)).collect();
+        SourceEntry { uri: uri.into(), suite: suite.into(), components }
+    }
+
+    #[test]
+    fn one_line_sources() {
+        let contents = "\
+# deb cdrom:[Ubuntu 20.04 LTS]/ focal main restricted
+deb http://archive.ubuntu.com/ubuntu focal main restricted
+deb-src http://archive.ubuntu.com/ubuntu focal main restricted
+
+deb [arch=amd64 signed-by=/etc/apt/keyrings/docker.gpg] https://download.docker.com/linux/ubuntu focal stable  # docker
+";
+
+        assert_eq!(parse_one_line_sources(contents), vec![
+            entry("http://archive.ubuntu.com/ubuntu", "focal", &[ "main", "restricted" ]),
+            entry("https://download.docker.com/linux/ubuntu", "focal", &[ "stable" ]),
+        ]);
+    }
+
+    #[test]
+    fn deb822_sources() {
+        let contents = "\
+Types: deb deb-src
+URIs: http://archive.ubuntu.com/ubuntu
+Suites: focal focal-updates
+Components: main
+Signed-By: /usr/share/keyrings/ubuntu-archive-keyring.gpg
+
+Types: deb
+URIs: https://example.com/apt
+Suites: stable
+Components: main
+Enabled: no
+
+Types: deb-src
+URIs: https://example.com/src
+Suites: stable
+Components: main
+";
+
+        assert_eq!(parse_deb822_sources(contents), vec![
+            entry("http://archive.ubuntu.com/ubuntu", "focal", &[ "main" ]),
+            entry("http://archive.ubuntu.com/ubuntu", "focal-updates", &[ "main" ]),
+        ]);
+    }
+
+    #[test]
+    fn gpg_fingerprints() {
+        let lines = vec![
+            Arc::from("pub:-:4096:1:8D81803C0EBFCD88:1487788586:::-:::scESA::::::23::0:"),
+            Arc::from("fpr:::::::::9DC858229FC7DD38854AE2D88D81803C0EBFCD88:"),
+            Arc::from("sub:-:4096:1:7EA0A9C3F273FCD8:1487788586::::::s::::::23:"),
+            Arc::from("fpr:::::::::D3306A018370199E527AE7997EA0A9C3F273FCD8:"),
+        ];
+
+        let output = GpgOutput::interpret_command_output(lines, ExitReason::Status(0)).unwrap();
+        assert_eq!(output.fingerprints, vec![
+            String::from("9DC858229FC7DD38854AE2D88D81803C0EBFCD88"),
+            String::from("D3306A018370199E527AE7997EA0A9C3F273FCD8"),
+        ]);
+    }
+}
@@ -17,13 +17,29 @@
 //! atomicparsley
 //! bash
 //! ```
+//!
+//! Details about the installed formulas come from `brew info`, which
+//! outputs JSON. Only the fields that specsheet uses are shown here:
+//!
+//! ```text
+//! $ brew info --json=v2 --installed
+//! {"formulae": [{
+//!     "name": "wget",
+//!     "versions": {"stable": "1.21.4"},
+//!     "installed": [{"version": "1.21.3"}],
+//!     "linked_keg": "1.21.3",
+//!     "pinned": false,
+//!     "outdated": true
+//! }], "casks": []}
+//! ```
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
+use serde::Deserialize;
 
-use spec_checks::homebrew::RunBrew;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_checks::homebrew::{RunBrew, FormulaDetails};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -32,6 +48,7 @@ use super::GlobalOptions;
 #[derive(Debug)]
 pub struct BrewCommand {
     exec: Option<Exec<BrewOutput>>,
+    details: Option<Exec<BrewInfoOutput>>,
 }
 
 impl BrewCommand {
@@ -39,12 +56,15 @@ impl BrewCommand {
     /// Creates a new command to run `brew`.
     pub fn create(global_options: &impl GlobalOptions) -> Self {
         let exec = global_options.command("brew.output");
-        Self { exec }
+        let details = global_options.command("brew.info");
+        Self { exec, details }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.exec.into_iter().flat_map(Exec::into_command)
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let exec = self.exec.into_iter().flat_map(Exec::into_command);
+        let details = self.details.into_iter().flat_map(Exec::into_command);
+        exec.chain(details)
     }
 }
 
@@ -56,11 +76,24 @@ impl RunBrew for BrewCommand {
         }
     }
 
-    fn find_formula(&self, executor: &mut Executor, formula_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn prime_details(&mut self) {
+        if self.details.is_none() {
+            debug!("Priming brew info command");
+            self.details = Some(Exec::actual(brew_info_installed_cmd()));
+        }
+    }
+
+    fn find_formula(&self, executor: &mut Executor, formula_name: &str) -> Result<bool, Arc<ExecError>> {
         debug!("Finding brew formula -> {:?}", formula_name);
         let output = self.exec.as_ref().unwrap().run(executor)?;
         Ok(output.find_formula(formula_name))
     }
+
+    fn formula_details(&self, executor: &mut Executor, formula_name: &str) -> Result<Option<FormulaDetails>, Arc<ExecError>> {
+        debug!("Finding brew formula details -> {:?}", formula_name);
+        let output = self.details.as_ref().unwrap().run(executor)?;
+        Ok(output.formula_details(formula_name))
+    }
 }
 
 fn brew_list_formulas_cmd() -> Command {
@@ -71,16 +104,25 @@ fn brew_list_formulas_cmd() -> Command {
     cmd
 }
 
+fn brew_info_installed_cmd() -> Command {
+    let mut cmd = Command::new("brew");
+    cmd.env("HOMEBREW_NO_AUTO_UPDATE", "1");
+    cmd.arg("info");
+    cmd.arg("--json=v2");
+    cmd.arg("--installed");
+    cmd
+}
+
 
 /// The **brew output** encapsulates the output lines of an
 /// invoked `BrewCommand`.
 #[derive(Debug)]
 pub struct BrewOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for BrewOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
@@ -93,3 +135,84 @@ impl BrewOutput {
         self.lines.iter().any(|line| **line == *formula_name)
     }
 }
+
+
+/// The **brew info output** holds the parsed JSON output of an invoked
+/// `brew info` command.
+#[derive(Debug, Deserialize)]
+pub struct BrewInfoOutput {
+    formulae: Vec<FormulaInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormulaInfo {
+    name: String,
+    versions: FormulaVersions,
+    installed: Vec<InstalledVersion>,
+    linked_keg: Option<String>,
+    pinned: bool,
+    outdated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormulaVersions {
+    stable: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstalledVersion {
+    version: String,
+}
+
+impl CommandOutput for BrewInfoOutput {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        exit_reason.should_be(0)?;
+
+        let json = lines.join("\n");
+        serde_json::from_str(&json).map_err(|e| ExecError::InvalidOutput(e.to_string()))
+    }
+}
+
+impl BrewInfoOutput {
+
+    /// Searches through the formulas for one with the given name, and
+    /// returns its details.
+    fn formula_details(&self, formula_name: &str) -> Option<FormulaDetails> {
+        let formula = self.formulae.iter().find(|f| f.name == formula_name)?;
+
+        Some(FormulaDetails {
+            installed_versions: formula.installed.iter().map(|i| i.version.clone()).collect(),
+            latest_version: formula.versions.stable.clone(),
+            linked_version: formula.linked_keg.clone(),
+            pinned: formula.pinned,
+            outdated: formula.outdated,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formula_info() {
+        let lines = vec![
+            Arc::from(r#"{"formulae": [{"name": "wget", "versions": {"stable": "1.21.4", "head": null},"#),
+            Arc::from(r#"  "installed": [{"version": "1.21.3", "poured_from_bottle": true}],"#),
+            Arc::from(r#"  "linked_keg": "1.21.3", "pinned": true, "outdated": true}], "casks": []}"#),
+        ];
+
+        let output = BrewInfoOutput::interpret_command_output(lines, ExitReason::Status(0)).unwrap();
+
+        assert_eq!(output.formula_details("wget"), Some(FormulaDetails {
+            installed_versions: vec![ "1.21.3".into() ],
+            latest_version: Some("1.21.4".into()),
+            linked_version: Some("1.21.3".into()),
+            pinned: true,
+            outdated: true,
+        }));
+
+        assert_eq!(output.formula_details("curl"), None);
+    }
+}
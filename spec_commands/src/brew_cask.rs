@@ -17,12 +17,12 @@
 //! audio-hijack
 //! ```
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
 use spec_checks::homebrew_cask::RunBrewCask;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -43,7 +43,7 @@ impl BrewCaskCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.exec.into_iter().flat_map(Exec::into_command)
     }
 }
@@ -56,7 +56,7 @@ impl RunBrewCask for BrewCaskCommand {
         }
     }
 
-    fn find_cask(&self, executor: &mut Executor, cask_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn find_cask(&self, executor: &mut Executor, cask_name: &str) -> Result<bool, Arc<ExecError>> {
         debug!("Finding brew cask -> {:?}", cask_name);
         let output = self.exec.as_ref().unwrap().run(executor)?;
         Ok(output.find_cask(cask_name))
@@ -74,11 +74,11 @@ fn brew_list_casks_cmd() -> Command {
 /// invoked `BrewCaskCommand`.
 #[derive(Debug)]
 pub struct BrewCaskOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for BrewCaskOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
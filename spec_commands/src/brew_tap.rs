@@ -13,12 +13,12 @@
 //! railwaycat/emacsmacport
 //! ```
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
 use spec_checks::homebrew_tap::RunBrewTap;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -39,7 +39,7 @@ impl BrewTapCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.exec.into_iter().flat_map(Exec::into_command)
     }
 }
@@ -52,7 +52,7 @@ impl RunBrewTap for BrewTapCommand {
         }
     }
 
-    fn find_tap(&self, executor: &mut Executor, tap_name: &str) -> Result<bool, Rc<ExecError>> {
+    fn find_tap(&self, executor: &mut Executor, tap_name: &str) -> Result<bool, Arc<ExecError>> {
         debug!("Finding brew tap -> {:?}", tap_name);
         let output = self.exec.as_ref().unwrap().run(executor)?;
         Ok(output.find_tap(tap_name))
@@ -70,11 +70,11 @@ fn brew_list_taps_cmd() -> Command {
 /// invoked `BrewTapCommand`.
 #[derive(Debug)]
 pub struct BrewTapOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for BrewTapOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
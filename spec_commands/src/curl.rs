@@ -22,9 +22,12 @@
 //!
 //! This is where the body would go.
 //! ```
+//!
+//! When following redirects, the status and headers of every response in
+//! the chain get printed one after another, each followed by a blank line.
 
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 
 use log::*;
@@ -32,7 +35,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 
 use spec_checks::http::{RunHttp, HttpRequest, HttpResponse};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -55,7 +58,7 @@ impl CurlCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.results.into_iter().flat_map(|e| e.1.into_command())
     }
 }
@@ -73,7 +76,7 @@ impl RunHttp for CurlCommand {
         }
     }
 
-    fn get_response(&self, executor: &mut Executor, request: HttpRequest) -> Result<Rc<CurlOutput>, Rc<ExecError>> {
+    fn get_response(&self, executor: &mut Executor, request: HttpRequest) -> Result<Arc<CurlOutput>, Arc<ExecError>> {
         debug!("Fetching url -> {:?}", request);
         let output = self.results[&request].run(executor)?;
         Ok(output)
@@ -105,6 +108,18 @@ impl CurlCommand {
             cmd.arg("-H").arg(format!("{}: {}", header, value));
         }
 
+        if request.insecure {
+            cmd.arg("--insecure");
+        }
+
+        if let Some(cert) = &request.client_cert {
+            cmd.arg("--cert").arg(cert);
+        }
+
+        if request.follow_redirects {
+            cmd.arg("--location");
+        }
+
         cmd.arg(&request.url);
         cmd
     }
@@ -115,26 +130,63 @@ impl CurlCommand {
 /// invoked `CurlCommand`.
 #[derive(Debug)]
 pub struct CurlOutput {
-    first_line: Rc<str>,
-    response_header_lines: Vec<Rc<str>>,
-    response_body_lines: Vec<Rc<str>>,
+    first_line: Option<Arc<str>>,
+    response_header_lines: Vec<Arc<str>>,
+    response_body_lines: Vec<Arc<str>>,
+    redirect_locations: Vec<Arc<str>>,
+    certificate_error: Option<&'static str>,
 }
 
 impl CommandOutput for CurlOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
-        exit_reason.should_be(0)?;
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+
+        // Certificate problems are something a check can test for, so they
+        // don’t count as the command failing.
+        if let ExitReason::Status(status) = exit_reason {
+            if let Some(certificate_error) = certificate_error(status) {
+                debug!("curl certificate error -> {:?}", certificate_error);
+                return Ok(Self {
+                    first_line: None,
+                    response_header_lines: Vec::new(),
+                    response_body_lines: Vec::new(),
+                    redirect_locations: Vec::new(),
+                    certificate_error: Some(certificate_error),
+                });
+            }
+        }
 
-        let mut iter = lines.into_iter();
+        exit_reason.should_be(0)?;
 
-        let first_line = iter.next().unwrap();
+        let mut iter = lines.into_iter().peekable();
 
+        let mut first_line = iter.next();
         let mut response_header_lines = Vec::new();
-        while let Some(line) = iter.next() {
-            if line.is_empty() {
-                break;
+        let mut redirect_locations = Vec::new();
+
+        loop {
+            while let Some(line) = iter.next() {
+                if line.is_empty() {
+                    break;
+                }
+                else {
+                    response_header_lines.push(line);
+                }
             }
-            else {
-                response_header_lines.push(line);
+
+            // A redirect followed by another status line means curl
+            // followed it, so the next block is the response that matters.
+            let is_redirect = first_line.as_ref().and_then(|l| status_code(l)).is_some_and(|s| (300 .. 400).contains(&s));
+            let next_is_response = iter.peek().is_some_and(|l| status_code(l).is_some());
+
+            match find_header(&response_header_lines, "Location") {
+                Some(location) if is_redirect && next_is_response => {
+                    redirect_locations.push(Arc::from(location));
+                    first_line = iter.next();
+                    response_header_lines.clear();
+                }
+                _ => {
+                    break;
+                }
             }
         }
 
@@ -143,14 +195,56 @@ impl CommandOutput for CurlOutput {
             response_body_lines.push(line);
         }
 
-        Ok(Self { first_line, response_header_lines, response_body_lines })
+        Ok(Self { first_line, response_header_lines, response_body_lines, redirect_locations, certificate_error: None })
+    }
+}
+
+/// Returns the HTTP status in the given line, if it is a status line.
+fn status_code(line: &str) -> Option<i32> {
+    let caps = HTTP_VERSION.captures(line)?;
+    caps[1].parse().ok()
+}
+
+/// Searches through a list of header lines for the given header, returning
+/// its value.
+fn find_header<'lines>(lines: &'lines [Arc<str>], header_name: &str) -> Option<&'lines str> {
+    // HTTP headers are case-insensitive:
+    // https://www.w3.org/Protocols/rfc2616/rfc2616-sec4.html#sec4.2
+
+    for line in lines {
+        let colon = match line.find(':') {
+            Some(i) => i,
+            None    => continue,
+        };
+
+        if line[.. colon].eq_ignore_ascii_case(header_name) {
+            return Some(line[colon + 1 ..].trim())
+        }
+    }
+
+    None
+}
+
+/// Returns a description of the certificate problem that caused curl to exit
+/// with the given status, if that was the reason. Status 35 isn’t in here,
+/// because curl uses it for any failed handshake, not just ones where the
+/// certificate was the problem.
+fn certificate_error(status: i32) -> Option<&'static str> {
+    match status {
+        51 => Some("server certificate is not OK"),
+        58 => Some("problem with the client certificate"),
+        60 => Some("server certificate cannot be authenticated"),
+        77 => Some("problem reading the CA certificate"),
+        83 => Some("issuer check failed"),
+        90 => Some("server public key does not match pinned key"),
+        91 => Some("invalid certificate status"),
+        _  => None,
     }
 }
 
 impl HttpResponse for CurlOutput {
     fn status(&self) -> Option<i32> {
-        let caps = HTTP_VERSION.captures(&self.first_line)?;
-        Some(caps[1].parse().unwrap())
+        status_code(self.first_line.as_ref()?)
     }
 
     fn content_type(&self) -> Option<&str> {
@@ -166,21 +260,7 @@ impl HttpResponse for CurlOutput {
     }
 
     fn header(&self, header_name: &str) -> Option<&str> {
-        // HTTP headers are case-insensitive:
-        // https://www.w3.org/Protocols/rfc2616/rfc2616-sec4.html#sec4.2
-
-        for line in &self.response_header_lines {
-            let colon = match line.find(':') {
-                Some(i) => i,
-                None    => continue,
-            };
-
-            if line[.. colon].eq_ignore_ascii_case(header_name) {
-                return Some(line[colon + 1 ..].trim())
-            }
-        }
-
-        None
+        find_header(&self.response_header_lines, header_name)
     }
 
     fn body(&self) -> Vec<u8> {
@@ -191,6 +271,14 @@ impl HttpResponse for CurlOutput {
         }
         v
     }
+
+    fn certificate_error(&self) -> Option<&str> {
+        self.certificate_error
+    }
+
+    fn redirect_locations(&self) -> Vec<&str> {
+        self.redirect_locations.iter().map(|e| &**e).collect()
+    }
 }
 
 static HTTP_VERSION: Lazy<Regex> = Lazy::new(|| {
@@ -250,3 +338,41 @@ static HTTP_VERSION: Lazy<Regex> = Lazy::new(|| {
 // ```
 //
 // # The body
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spec_checks::{RunCheck, CheckResult};
+    use spec_checks::http::{HttpCheck, Pass, Fail};
+    use spec_checks::read::Rewrites;
+
+    /// Runs an HTTP check that expects a certificate error, with curl
+    /// replaced by a command that exits with the given status.
+    fn cert_error_check(status: i32) -> Vec<CheckResult<Pass, Fail>> {
+        let table = "url = 'https://localhost/'\nexpect_cert_error = true".parse().unwrap();
+        let check = HttpCheck::read(&table, &Rewrites::new()).unwrap();
+
+        let mut curl = CurlCommand::default();
+        check.load(&mut curl);
+        for exec in curl.results.values_mut() {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(format!("exit {}", status));
+            *exec = Exec::actual(cmd);
+        }
+
+        check.check(&mut Executor::new(), &curl)
+    }
+
+    #[test]
+    fn certificate_rejected() {
+        let results = cert_error_check(60);
+        assert!(matches!(results.as_slice(), [ CheckResult::Passed(_) ]));
+    }
+
+    #[test]
+    fn handshake_failure_is_not_certificate_error() {
+        let results = cert_error_check(35);
+        assert!(matches!(results.as_slice(), [ CheckResult::CommandError(_) ]));
+    }
+}
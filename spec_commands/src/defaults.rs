@@ -22,14 +22,24 @@
 //! ```
 //!
 //! The process returns 1 if the value is not present in the database.
+//!
+//! Values with a type other than string get read with a small shell script
+//! that prints the type of the value first, followed by the value itself,
+//! with arrays and dictionaries converted to JSON by `plutil`:
+//!
+//! ```text
+//! $ sh -c '...' sh com.apple.dock persistent-apps
+//! Type is array
+//! ["Downloads","Applications"]
+//! ```
 
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 
-use spec_checks::defaults::{RunDefaults, DefaultsLocation};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_checks::defaults::{RunDefaults, DefaultsLocation, DefaultsValue};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -38,6 +48,7 @@ use super::GlobalOptions;
 #[derive(Debug, Default)]
 pub struct DefaultsCommand {
     results: BTreeMap<DefaultsLocation, Exec<DefaultsOutput>>,
+    typed_results: BTreeMap<DefaultsLocation, Exec<TypedDefaultsOutput>>,
 }
 
 impl DefaultsCommand {
@@ -48,8 +59,10 @@ impl DefaultsCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.results.into_iter().map(|(_, e)| e.into_command().unwrap())
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let results = self.results.into_iter().map(|(_, e)| e.into_command().unwrap());
+        let typed_results = self.typed_results.into_iter().map(|(_, e)| e.into_command().unwrap());
+        results.chain(typed_results)
     }
 }
 
@@ -62,7 +75,15 @@ impl RunDefaults for DefaultsCommand {
         }
     }
 
-    fn get_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<Rc<str>>, Rc<ExecError>> {
+    fn prime_typed(&mut self, location: &DefaultsLocation) {
+        if ! self.typed_results.contains_key(location) {
+            debug!("Priming typed defaults command with {:?}", location);
+            let exec = Exec::actual(defaults_typed_lookup_cmd(location));
+            self.typed_results.insert(location.clone(), exec);
+        }
+    }
+
+    fn get_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<Arc<str>>, Arc<ExecError>> {
         debug!("Finding defaults value -> {:?}", location);
         let output = self.results[location].run(executor)?;
 
@@ -73,25 +94,44 @@ impl RunDefaults for DefaultsCommand {
             Ok(Some(output.get_value()))
         }
     }
+
+    fn get_typed_value(&self, executor: &mut Executor, location: &DefaultsLocation) -> Result<Option<DefaultsValue>, Arc<ExecError>> {
+        debug!("Finding typed defaults value -> {:?}", location);
+        let output = self.typed_results[location].run(executor)?;
+        Ok(output.value.clone())
+    }
 }
 
 fn defaults_lookup_cmd(location: &DefaultsLocation) -> Command {
     let mut cmd = Command::new("defaults");
+    if location.current_host {
+        cmd.arg("-currentHost");
+    }
+
     cmd.arg("read").arg(&location.place.to_string()).arg(&location.key);
     cmd
 }
 
+fn defaults_typed_lookup_cmd(location: &DefaultsLocation) -> Command {
+    let defaults = if location.current_host { "defaults -currentHost" } else { "defaults" };
+    let script = format!(r#"type=$({0} read-type "$1" "$2") || exit 1; echo "$type"; case "$type" in *array|*dictionary) {0} export "$1" - | plutil -extract "$2" json -o - - ;; *) {0} read "$1" "$2" ;; esac"#, defaults);
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script).arg("sh").arg(location.place.to_string()).arg(&location.key);
+    cmd
+}
+
 
 /// The **defaults output** encapsulates the output lines of an
 /// invoked `DefaultsCommand`.
 #[derive(Debug)]
 pub struct DefaultsOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
     missing: bool,
 }
 
 impl CommandOutput for DefaultsOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         if exit_reason.is(0) {
             let missing = false;
             Ok(Self { lines, missing })
@@ -110,7 +150,111 @@ impl DefaultsOutput {
 
     /// Returns a clone of the value read from the defaults, which should be
     /// on the first and only line.
-    fn get_value(&self) -> Rc<str> {
-        Rc::clone(&self.lines.first().unwrap())
+    fn get_value(&self) -> Arc<str> {
+        Arc::clone(&self.lines.first().unwrap())
+    }
+}
+
+
+/// The **typed defaults output** holds the value of an invoked typed
+/// `DefaultsCommand`, converted to the type it has in the database.
+#[derive(Debug)]
+pub struct TypedDefaultsOutput {
+    value: Option<DefaultsValue>,
+}
+
+impl CommandOutput for TypedDefaultsOutput {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        if exit_reason.is(1) {
+            return Ok(Self { value: None });
+        }
+
+        exit_reason.should_be(0)?;
+
+        let (type_line, value_lines) = match lines.split_first() {
+            Some(split)  => split,
+            None         => return Err(ExecError::InvalidOutput("no type was printed".into())),
+        };
+
+        let value = parse_typed_value(type_line.trim_start_matches("Type is "), &value_lines.join("\n"))?;
+        Ok(Self { value: Some(value) })
+    }
+}
+
+/// Converts the output of `defaults` into a value of the type it was
+/// printed with. Booleans are printed as `1` or `0`, and arrays and
+/// dictionaries are printed as JSON. Types with no equivalent, such as
+/// dates and data, are kept as strings.
+fn parse_typed_value(type_name: &str, text: &str) -> Result<DefaultsValue, ExecError> {
+    let invalid = || ExecError::InvalidOutput(format!("Invalid {} value {:?}", type_name, text));
+
+    match type_name {
+        "boolean"  => Ok(DefaultsValue::Boolean(text.trim() == "1")),
+        "integer"  => text.trim().parse().map(DefaultsValue::Integer).map_err(|_| invalid()),
+        "float"    => text.trim().parse().map(DefaultsValue::Float).map_err(|_| invalid()),
+        "array" | "dictionary" => {
+            let json = serde_json::from_str(text).map_err(|_| invalid())?;
+            Ok(json_to_value(json))
+        }
+        _ => Ok(DefaultsValue::String(text.into())),
+    }
+}
+
+fn json_to_value(json: serde_json::Value) -> DefaultsValue {
+    use serde_json::Value as J;
+
+    match json {
+        J::Null         => DefaultsValue::String(String::new()),
+        J::Bool(b)      => DefaultsValue::Boolean(b),
+        J::String(s)    => DefaultsValue::String(s),
+        J::Array(vs)    => DefaultsValue::Array(vs.into_iter().map(json_to_value).collect()),
+        J::Object(map)  => DefaultsValue::Dictionary(map.into_iter().map(|(k, v)| (k, json_to_value(v))).collect()),
+        J::Number(n)    => {
+            if let Some(i) = n.as_i64() {
+                DefaultsValue::Integer(i)
+            }
+            else {
+                DefaultsValue::Float(n.as_f64().unwrap_or_default())
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn boolean() {
+        assert_eq!(parse_typed_value("boolean", "1").unwrap(), DefaultsValue::Boolean(true));
+        assert_eq!(parse_typed_value("boolean", "0").unwrap(), DefaultsValue::Boolean(false));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_typed_value("integer", "6").unwrap(), DefaultsValue::Integer(6));
+        assert_eq!(parse_typed_value("float", "0.5").unwrap(), DefaultsValue::Float(0.5));
+        assert!(parse_typed_value("integer", "six").is_err());
+    }
+
+    #[test]
+    fn array() {
+        assert_eq!(parse_typed_value("array", r#"["Downloads", 3, true]"#).unwrap(),
+                   DefaultsValue::Array(vec![ DefaultsValue::String("Downloads".into()), DefaultsValue::Integer(3), DefaultsValue::Boolean(true) ]));
+    }
+
+    #[test]
+    fn dictionary() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("size".into(), DefaultsValue::Float(1.5));
+        assert_eq!(parse_typed_value("dictionary", r#"{ "size": 1.5 }"#).unwrap(),
+                   DefaultsValue::Dictionary(map));
+    }
+
+    #[test]
+    fn missing() {
+        let output = TypedDefaultsOutput::interpret_command_output(vec![], ExitReason::Status(1)).unwrap();
+        assert_eq!(output.value, None);
     }
 }
@@ -31,13 +31,13 @@
 
 
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 
 use log::*;
 
-use spec_checks::dns::{RunDns, Request, Nameserver};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_checks::dns::{RunDns, Request, Response, Nameserver};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -61,7 +61,7 @@ impl DigCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.results.into_iter().flat_map(|e| e.1.into_command())
     }
 }
@@ -75,16 +75,35 @@ impl RunDns for DigCommand {
         }
     }
 
-    fn get_values(&self, executor: &mut Executor, request: &Request) -> Result<Vec<Rc<str>>, Rc<ExecError>> {
+    fn get_response(&self, executor: &mut Executor, request: &Request) -> Result<Response, Arc<ExecError>> {
         debug!("Finding dns records -> {:?}", request);
         let output = self.results[request].run(executor)?;
-        Ok(output.clone_lines())
+
+        if request.with_ttl || request.dnssec {
+            Ok(output.parse_answers(request))
+        }
+        else {
+            Ok(Response { values: output.clone_lines(), .. Response::default() })
+        }
     }
 }
 
 fn dig_cmd(request: &Request) -> Command {
     let mut cmd = Command::new("dig");
-    cmd.arg("+short");
+
+    // The short output only has the values, so the answer section and the
+    // header comments (which include the flags) are needed for the TTLs
+    // and the Authenticated Data flag.
+    if request.with_ttl || request.dnssec {
+        cmd.arg("+noall").arg("+answer").arg("+comments");
+    }
+    else {
+        cmd.arg("+short");
+    }
+
+    if request.dnssec {
+        cmd.arg("+dnssec");
+    }
 
     if let Nameserver::ByIP(ref ip) = request.nameserver {
         cmd.arg(format!("@{}", ip));
@@ -100,18 +119,105 @@ fn dig_cmd(request: &Request) -> Command {
 /// invoked `DigCommand`.
 #[derive(Debug)]
 pub struct DigOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for DigOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
 }
 
 impl DigOutput {
-    fn clone_lines(&self) -> Vec<Rc<str>> {
+    fn clone_lines(&self) -> Vec<Arc<str>> {
         self.lines.clone()
     }
+
+    /// Parses the answer section and the header flags out of the longer
+    /// form of output, keeping only the records of the requested type.
+    fn parse_answers(&self, request: &Request) -> Response {
+        let rtype = format!("{:?}", request.rtype);
+        let mut response = Response::default();
+
+        for line in &self.lines {
+            if let Some(flags) = line.strip_prefix(";; flags:") {
+                let flags = flags.split(';').next().unwrap_or_default();
+                response.authenticated = flags.split_whitespace().any(|f| f == "ad");
+                continue;
+            }
+            else if line.starts_with(';') {
+                continue;
+            }
+
+            // Each record is the name, TTL, class, type, and then the
+            // value, which can contain spaces of its own.
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 5 || fields[3] != rtype {
+                continue;
+            }
+
+            if let Ok(ttl) = fields[1].parse() {
+                response.ttls.push(ttl);
+            }
+            else {
+                warn!("Invalid TTL in dig output: {:?}", line);
+            }
+
+            response.values.push(fields[4..].join(" ").into());
+        }
+
+        response
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spec_checks::dns::RecordType;
+
+    fn request() -> Request {
+        Request {
+            nameserver: Nameserver::DefaultResolver,
+            domain: "example.com".into(),
+            rtype: RecordType::A,
+            with_ttl: true,
+            dnssec: true,
+        }
+    }
+
+    #[test]
+    fn answers_with_flags() {
+        let lines = vec![
+            String::from(";; Got answer:").into(),
+            String::from(";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 1337").into(),
+            String::from(";; flags: qr rd ra ad; QUERY: 1, ANSWER: 3, AUTHORITY: 0, ADDITIONAL: 1").into(),
+            String::new().into(),
+            String::from("example.com.\t\t3600\tIN\tA\t192.0.2.1").into(),
+            String::from("example.com.\t\t300\tIN\tA\t192.0.2.2").into(),
+            String::from("example.com.\t\t300\tIN\tRRSIG\tA 13 2 300 20201231000000 20201201000000 1234 example.com. abcd").into(),
+        ];
+
+        let output = DigOutput { lines };
+        let response = output.parse_answers(&request());
+
+        assert_eq!(response.values, vec![ Arc::from("192.0.2.1"), Arc::from("192.0.2.2") ]);
+        assert_eq!(response.ttls, vec![ 3600, 300 ]);
+        assert!(response.authenticated);
+    }
+
+    #[test]
+    fn answers_without_ad_flag() {
+        let lines = vec![
+            String::from(";; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1").into(),
+            String::from("example.com.\t\t60\tIN\tA\t192.0.2.1").into(),
+        ];
+
+        let output = DigOutput { lines };
+        let response = output.parse_answers(&request());
+
+        assert_eq!(response.ttls, vec![ 60 ]);
+        assert!(! response.authenticated);
+    }
 }
@@ -12,7 +12,7 @@ use std::sync::Mutex;
 use log::*;
 
 use spec_checks::fs::LookupFile;
-use spec_exec::Command;
+use spec_exec::{Command, RunOptions};
 
 use super::GlobalOptions;
 
@@ -21,6 +21,7 @@ use super::GlobalOptions;
 /// the results.
 #[derive(Debug)]
 pub struct FilesystemNonCommand {
+    base_directory: Option<PathBuf>,
     exists:   BTreeMap<PathBuf,         Mutex<Option<bool>>>,
     files:    BTreeMap<(PathBuf, bool), Mutex<Option<Metadata>>>,
     contents: BTreeMap<PathBuf,         Mutex<Option<Vec<u8>>>>,
@@ -32,6 +33,7 @@ impl FilesystemNonCommand {
     /// Creates a new non-command.
     pub fn create(_global_options: &impl GlobalOptions) -> Self {
         Self {
+            base_directory: None,
             exists: BTreeMap::new(),
             files: BTreeMap::new(),
             contents: BTreeMap::new(),
@@ -39,14 +41,31 @@ impl FilesystemNonCommand {
         }
     }
 
+    /// Sets the directory that relative paths are looked up from. When this
+    /// is `None`, they are looked up from the current directory of the
+    /// process.
+    pub fn set_base_directory(&mut self, directory: Option<PathBuf>) {
+        self.base_directory = directory;
+    }
+
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         std::iter::empty()
     }
+
+    /// Resolves the given path against the base directory, if there is one.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.base_directory {
+            Some(base)  => base.join(path),
+            None        => path.to_path_buf(),
+        }
+    }
 }
 
 impl LookupFile for FilesystemNonCommand {
     fn prime(&mut self, path: &Path, follow: bool) {
+        let resolved = self.resolve(path);
+        let path = resolved.as_path();
         if ! self.files.contains_key(&(path.into(), follow)) {
             debug!("Priming filesystem with path {:?}", path);
             self.exists.insert(path.to_path_buf(), Mutex::new(None));
@@ -57,12 +76,16 @@ impl LookupFile for FilesystemNonCommand {
     }
 
     fn does_file_exist(&self, path: &Path) -> bool {
+        let resolved = self.resolve(path);
+        let path = resolved.as_path();
         let mut slot = self.exists.get(path).unwrap().lock().unwrap();
         let target = slot.get_or_insert_with(|| path.exists());
         *target
     }
 
     fn lookup_file(&self, path: &Path, follow: bool) -> Metadata {
+        let resolved = self.resolve(path);
+        let path = resolved.as_path();
         let mut slot = self.files.get(&(path.to_path_buf(), follow)).unwrap().lock().unwrap();
         let metadata = slot.get_or_insert_with(|| {
             if follow { path.metadata() }
@@ -72,14 +95,22 @@ impl LookupFile for FilesystemNonCommand {
     }
 
     fn read_file_contents(&self, path: &Path) -> Vec<u8> {
+        let resolved = self.resolve(path);
+        let path = resolved.as_path();
         let mut slot = self.contents.get(path).unwrap().lock().unwrap();
         let target = slot.get_or_insert_with(|| read_file(path).unwrap());
         target.clone()
     }
 
     fn lookup_link_target(&self, path: &Path) -> Result<PathBuf, IoError> {
+        let resolved = self.resolve(path);
+        let path = resolved.as_path();
         let mut slot = self.targets.get(path).unwrap().lock().unwrap();
         let target = slot.get_or_insert_with(|| path.read_link().unwrap());
         Ok(target.clone())
     }
+
+    fn base_directory(&self) -> Option<&Path> {
+        self.base_directory.as_deref()
+    }
 }
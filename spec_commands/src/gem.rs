@@ -14,14 +14,28 @@
 //! ansi (1.5.0)
 //! ast (2.4.0)
 //! aws-eventstream (1.0.3)
+//! bundler (default: 2.1.4, 1.17.2)
+//! ```
+//!
+//! Projects get the gems in their bundle listed by running `bundle` with
+//! the project’s Gemfile instead:
+//!
+//! ```text
+//! $ BUNDLE_GEMFILE=./webapp/Gemfile bundle list
+//! Gems included by the bundle:
+//!   * actioncable (6.0.3.4)
+//!   * rails (6.0.3.4)
+//! Use `bundle info` to print more detailed information about a gem
 //! ```
 
-use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::*;
 
 use spec_checks::gem::RunGem;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -30,6 +44,7 @@ use super::GlobalOptions;
 #[derive(Debug)]
 pub struct GemCommand {
     exec: Option<Exec<GemListOutput>>,
+    projects: BTreeMap<PathBuf, Exec<GemListOutput>>,
 }
 
 impl GemCommand {
@@ -37,26 +52,41 @@ impl GemCommand {
     /// Creates a new command to run `gem`.
     pub fn create(global_options: &impl GlobalOptions) -> Self {
         let exec = global_options.command("gem.output");
-        Self { exec }
+        Self { exec, projects: BTreeMap::new() }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.exec.into_iter().flat_map(Exec::into_command)
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let global = self.exec.into_iter().flat_map(Exec::into_command);
+        let projects = self.projects.into_iter().flat_map(|e| e.1.into_command());
+        global.chain(projects)
     }
 }
 
 impl RunGem for GemCommand {
-    fn prime(&mut self) {
-        if self.exec.is_none() {
+    fn prime(&mut self, project_path: Option<&Path>) {
+        if let Some(path) = project_path {
+            if ! self.projects.contains_key(path) {
+                debug!("Priming bundle command for project {:?}", path);
+                let exec = Exec::actual(bundle_list_cmd(path));
+                self.projects.insert(path.to_owned(), exec);
+            }
+        }
+        else if self.exec.is_none() {
             debug!("Priming gem command");
             self.exec = Some(Exec::actual(gem_list_cmd()));
         }
     }
 
-    fn find_gem(&self, executor: &mut Executor, gem_name: &str) -> Result<bool, Rc<ExecError>> {
-        debug!("Finding gem -> {:?}", gem_name);
-        let output = self.exec.as_ref().unwrap().run(executor)?;
+    fn find_gem(&self, executor: &mut Executor, project_path: Option<&Path>, gem_name: &str) -> Result<Vec<String>, Arc<ExecError>> {
+        debug!("Finding gem -> {:?} {:?}", project_path, gem_name);
+
+        let exec = match project_path {
+            Some(path)  => &self.projects[path],
+            None        => self.exec.as_ref().unwrap(),
+        };
+
+        let output = exec.run(executor)?;
         Ok(output.find_gem(gem_name))
     }
 }
@@ -67,16 +97,23 @@ fn gem_list_cmd() -> Command {
     cmd
 }
 
+fn bundle_list_cmd(project_path: &Path) -> Command {
+    let mut cmd = Command::new("bundle");
+    cmd.env("BUNDLE_GEMFILE", project_path.join("Gemfile"));
+    cmd.arg("list");
+    cmd
+}
+
 
 /// The **gem output** encapsulates the output lines of an
 /// invoked `GemCommand`.
 #[derive(Debug)]
 pub struct GemListOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for GemListOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
@@ -84,8 +121,60 @@ impl CommandOutput for GemListOutput {
 
 impl GemListOutput {
 
-    /// Searches through the output lines for a gem with the given name.
-    fn find_gem(&self, gem_name: &str) -> bool {
-        self.lines.iter().any(|line| line.starts_with(gem_name))
+    /// Searches through the output lines for a gem with the given name,
+    /// returning every version of it that is installed.
+    fn find_gem(&self, gem_name: &str) -> Vec<String> {
+        for line in &self.lines {
+            let line = line.trim_start().trim_start_matches("* ");
+
+            if let Some((name, versions)) = line.split_once(" (") {
+                if name == gem_name {
+                    return versions.trim_end_matches(')')
+                                   .split(", ")
+                                   .map(|v| v.trim_start_matches("default: "))
+                                   .map(String::from)
+                                   .collect();
+                }
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn global_gems() {
+        let lines = vec![
+            Arc::from(""),
+            Arc::from("*** LOCAL GEMS ***"),
+            Arc::from(""),
+            Arc::from("pry (0.13.1)"),
+            Arc::from("pry-byebug (3.9.0)"),
+            Arc::from("bundler (default: 2.1.4, 1.17.2)"),
+        ];
+
+        let output = GemListOutput::interpret_command_output(lines, ExitReason::Status(0)).unwrap();
+        assert_eq!(output.find_gem("pry"), vec![ String::from("0.13.1") ]);
+        assert_eq!(output.find_gem("bundler"), vec![ String::from("2.1.4"), String::from("1.17.2") ]);
+        assert_eq!(output.find_gem("byebug"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn bundled_gems() {
+        let lines = vec![
+            Arc::from("Gems included by the bundle:"),
+            Arc::from("  * actioncable (6.0.3.4)"),
+            Arc::from("  * rails (6.0.3.4)"),
+            Arc::from("Use `bundle info` to print more detailed information about a gem"),
+        ];
+
+        let output = GemListOutput::interpret_command_output(lines, ExitReason::Status(0)).unwrap();
+        assert_eq!(output.find_gem("rails"), vec![ String::from("6.0.3.4") ]);
+        assert_eq!(output.find_gem("sinatra"), Vec::<String>::new());
     }
 }
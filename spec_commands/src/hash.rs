@@ -6,18 +6,29 @@
 //! $ md5sum wibble
 //! d41d8cd98f00b204e9800998ecf8427e  wibble
 //! ```
+//!
+//! Directories get hashed by running a small shell pipeline that hashes
+//! every file in the tree in a consistent order, then hashes the result:
+//!
+//! ```text
+//! $ sh -c 'cd "$1" && find . -type f -print0 | LC_ALL=C sort -z | xargs -0 -r sha256sum | sha256sum' sh /opt/app/lib
+//! 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  -
+//! ```
+//!
+//! Checksum files are read directly, rather than by running a program.
 
 
 use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 use spec_checks::hashes::{RunHash, Algorithm};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -25,8 +36,12 @@ use super::GlobalOptions;
 /// The **hash command** that runs one of many hashing binaries.
 #[derive(Debug, Default)]
 pub struct HashCommand {
+    base_directory: Option<PathBuf>,
+
     results: BTreeMap<(PathBuf, Algorithm), Exec<HashOutput>>,
     // this is a BTreeMap but it’s also a hash map. get it?
+
+    directories: BTreeMap<(PathBuf, Algorithm), Exec<HashOutput>>,
 }
 
 
@@ -37,9 +52,18 @@ impl HashCommand {
         Self::default()
     }
 
+    /// Sets the directory that relative paths to checksum files are looked
+    /// up from. When this is `None`, they are looked up from the current
+    /// directory of the process.
+    pub fn set_base_directory(&mut self, directory: Option<PathBuf>) {
+        self.base_directory = directory;
+    }
+
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.results.into_iter().flat_map(|t| t.1.into_command())
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let results = self.results.into_iter().flat_map(|t| t.1.into_command());
+        let directories = self.directories.into_iter().flat_map(|t| t.1.into_command());
+        results.chain(directories)
     }
 }
 
@@ -52,38 +76,104 @@ impl RunHash for HashCommand {
         }
     }
 
-    fn hash_file(&self, executor: &mut Executor, path: PathBuf, algorithm: Algorithm) -> Result<String, Rc<ExecError>> {
+    fn prime_directory(&mut self, path: &Path, algorithm: Algorithm) {
+        if ! self.directories.contains_key(&(path.to_path_buf(), algorithm)) {
+            debug!("Priming {:?} hash command for directory {:?}", algorithm, path);
+            let exec = Exec::actual(hash_directory_cmd(path, algorithm));
+            self.directories.insert((path.into(), algorithm), exec);
+        }
+    }
+
+    fn hash_file(&self, executor: &mut Executor, path: PathBuf, algorithm: Algorithm) -> Result<String, Arc<ExecError>> {
         debug!("Calculating {:?} hash for file -> {:?}", algorithm, path);
         let output = self.results[&(path, algorithm)].run(executor)?;
         Ok(output.get_hash())
     }
+
+    fn hash_directory(&self, executor: &mut Executor, path: &Path, algorithm: Algorithm) -> Result<String, Arc<ExecError>> {
+        debug!("Calculating {:?} hash for directory -> {:?}", algorithm, path);
+        let output = self.directories[&(path.to_path_buf(), algorithm)].run(executor)?;
+        Ok(output.get_hash())
+    }
+
+    fn checksum_entry(&self, checksum_path: &Path, path: &Path) -> Option<String> {
+        debug!("Reading checksum file {:?} for file -> {:?}", checksum_path, path);
+
+        let resolved = match &self.base_directory {
+            Some(base)  => base.join(checksum_path),
+            None        => checksum_path.to_path_buf(),
+        };
+
+        let contents = match fs::read_to_string(resolved) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Could not read checksum file {:?}: {}", checksum_path, e);
+                return None;
+            }
+        };
+
+        let directory = checksum_path.parent().unwrap_or_else(|| Path::new(""));
+        find_checksum_entry(&contents, directory, path)
+    }
 }
 
-fn hash_file_cmd(path: &Path, algorithm: Algorithm) -> Command {
-    let command_name = match algorithm {
+fn program_name(algorithm: Algorithm) -> &'static str {
+    match algorithm {
         Algorithm::MD5     => "md5sum",
         Algorithm::SHA1    => "sha1sum",
         Algorithm::SHA224  => "sha224sum",
         Algorithm::SHA256  => "sha256sum",
         Algorithm::SHA384  => "sha384sum",
         Algorithm::SHA512  => "sha512sum",
-    };
+        Algorithm::BLAKE2  => "b2sum",
+        Algorithm::BLAKE3  => "b3sum",
+    }
+}
 
-    let mut cmd = Command::new(command_name);
+fn hash_file_cmd(path: &Path, algorithm: Algorithm) -> Command {
+    let mut cmd = Command::new(program_name(algorithm));
     cmd.arg(path);
     cmd
 }
 
+fn hash_directory_cmd(path: &Path, algorithm: Algorithm) -> Command {
+    let program = program_name(algorithm);
+    let script = format!(r#"cd "$1" && find . -type f -print0 | LC_ALL=C sort -z | xargs -0 -r {0} | {0}"#, program);
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script).arg("sh").arg(path);
+    cmd
+}
+
+/// Searches the contents of a checksum file in the `sha256sum` format for
+/// the entry for the given path, which is either written the same way or
+/// relative to the directory containing the checksum file.
+fn find_checksum_entry(contents: &str, directory: &Path, path: &Path) -> Option<String> {
+    for (hash, file_name) in contents.lines().filter_map(|line| line.split_once(' ')) {
+
+        // Files hashed in binary mode have an asterisk before their name,
+        // rather than a second space.
+        let file_name = file_name.strip_prefix(' ').or_else(|| file_name.strip_prefix('*')).unwrap_or(file_name);
+        let file_name = Path::new(file_name);
+
+        if file_name == path || directory.join(file_name) == path {
+            return Some(hash.to_ascii_lowercase());
+        }
+    }
+
+    None
+}
+
 
 /// The **hash output** encapsulates the output lines of an
 /// invoked `HashCommand`.
 #[derive(Debug)]
 pub struct HashOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for HashOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
@@ -108,3 +198,46 @@ static REGEX: Lazy<Regex> = Lazy::new(|| {
         \s
     "##).unwrap()
 });
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SUMS: &str = "\
+9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  app.tar.gz
+60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752 *docs/manual.pdf
+";
+
+    #[test]
+    fn checksum_relative_to_file() {
+        assert_eq!(find_checksum_entry(SUMS, Path::new("/opt/app"), Path::new("/opt/app/app.tar.gz")),
+                   Some(String::from("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")));
+    }
+
+    #[test]
+    fn checksum_binary_mode() {
+        assert_eq!(find_checksum_entry(SUMS, Path::new("/opt/app"), Path::new("/opt/app/docs/manual.pdf")),
+                   Some(String::from("60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752")));
+    }
+
+    #[test]
+    fn checksum_missing() {
+        assert_eq!(find_checksum_entry(SUMS, Path::new("/opt/app"), Path::new("/opt/other/app.tar.gz")),
+                   None);
+    }
+
+    #[test]
+    fn checksum_file_from_base_directory() {
+        let directory = std::env::temp_dir().join(format!("specsheet-hash-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("SHA256SUMS"), SUMS).unwrap();
+
+        let mut command = HashCommand::default();
+        command.set_base_directory(Some(directory.clone()));
+        let entry = command.checksum_entry(Path::new("SHA256SUMS"), Path::new("app.tar.gz"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(entry, Some(String::from("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")));
+    }
+}
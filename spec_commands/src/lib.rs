@@ -25,6 +25,7 @@
 #![deny(unsafe_code)]
 
 pub mod apt;
+pub mod apt_source;
 pub mod brew_cask;
 pub mod brew_tap;
 pub mod brew;
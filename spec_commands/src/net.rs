@@ -1,19 +1,22 @@
 //! Network requests and the sending thereof
 //!
-//! This does not actually run any external programs yet!
-//! It is just a placeholder.
+//! Most of these do not run any external programs, and deal with the network
+//! directly. The exception is TLS handshakes, which are done by running
+//! `openssl s_client`.
 
 use std::collections::HashMap;
-use std::io::Error as IoError;
+use std::io::{Read, Error as IoError};
 use std::net::{TcpStream, UdpSocket};
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use log::*;
 
-use spec_checks::tcp::{RunTcp, Request as TcpRequest};
-use spec_checks::udp::{RunUdp, Request as UdpRequest};
-use spec_exec::Command;
+use spec_checks::tcp::{RunTcp, Request as TcpRequest, TlsRequest, Connection};
+use spec_checks::udp::{RunUdp, Request as UdpRequest, Payload as UdpPayload};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -21,8 +24,10 @@ use super::GlobalOptions;
 /// The **net non-command** makes network requests and caches the results.
 #[derive(Debug)]
 pub struct NetNonCommand {
-    tcps: HashMap<TcpRequest, Mutex<Option<Option<bool>>>>,
-    udps: HashMap<UdpRequest, Mutex<Option<Option<bool>>>>,
+    base_directory: Option<PathBuf>,
+    tcps: HashMap<TcpRequest, Mutex<Option<Connection>>>,
+    udps: HashMap<UdpRequest, Mutex<Option<Option<Vec<u8>>>>>,
+    tls_handshakes: HashMap<TlsRequest, Exec<OpenSslOutput>>,
 }
 
 impl NetNonCommand {
@@ -30,14 +35,23 @@ impl NetNonCommand {
     /// Creates a new non-command.
     pub fn create(_global_options: &impl GlobalOptions) -> Self {
         Self {
+            base_directory: None,
             tcps: HashMap::new(),
             udps: HashMap::new(),
+            tls_handshakes: HashMap::new(),
         }
     }
 
+    /// Sets the directory that relative paths to files of expected replies
+    /// are looked up from. When this is `None`, they are looked up from the
+    /// current directory of the process.
+    pub fn set_base_directory(&mut self, directory: Option<PathBuf>) {
+        self.base_directory = directory;
+    }
+
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        std::iter::empty()
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        self.tls_handshakes.into_iter().flat_map(|e| e.1.into_command())
     }
 }
 
@@ -49,7 +63,15 @@ impl RunTcp for NetNonCommand {
         }
     }
 
-    fn send_tcp_request(&self, request: &TcpRequest) -> bool {
+    fn prime_tls(&mut self, request: &TlsRequest) {
+        if ! self.tls_handshakes.contains_key(request) {
+            debug!("Priming network with TLS request {:?}", request);
+            let exec = Exec::actual(openssl_cmd(request));
+            self.tls_handshakes.insert(request.clone(), exec);
+        }
+    }
+
+    fn send_tcp_request(&self, request: &TcpRequest) -> Connection {
         let mut slot = self.tcps.get(request).unwrap().lock().unwrap();
         let response = slot.get_or_insert_with(|| {
 
@@ -58,16 +80,87 @@ impl RunTcp for NetNonCommand {
             match TcpStream::connect(request.addr()) {
                 Ok(stream) => {
                     debug!("Received response -> {:?}", stream.peer_addr());
-                    Some(true)
+
+                    if request.read_banner {
+                        Connection::Accepted(Some(read_banner(stream, Duration::new(5, 0))))
+                    }
+                    else {
+                        Connection::Accepted(None)
+                    }
                 }
                 Err(e) => {
                     debug!("Network error -> {:?}", e);
-                    Some(false)
+                    Connection::Refused
                 }
             }
         });
 
-        response.clone().unwrap()
+        response.clone()
+    }
+
+    fn tls_handshake(&self, executor: &mut Executor, request: &TlsRequest) -> Result<bool, Arc<ExecError>> {
+        debug!("Performing TLS handshake -> {:?}", request);
+        let output = self.tls_handshakes[request].run(executor)?;
+        Ok(output.succeeded)
+    }
+}
+
+/// Reads whatever the other end sends as soon as the connection is made,
+/// stopping at the first line break. Servers that don’t send a banner will
+/// cause this to time out, and return nothing.
+fn read_banner(mut stream: TcpStream, timeout: Duration) -> Vec<u8> {
+    let mut banner = Vec::new();
+    let mut buf = [0; 512];
+
+    if let Err(e) = stream.set_read_timeout(Some(timeout)) {
+        warn!("Error setting banner timeout: {:?}", e);
+    }
+
+    while ! banner.contains(&b'\n') && banner.len() < 4096 {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => banner.extend_from_slice(&buf[.. n]),
+            Err(e) => {
+                debug!("Stopped reading banner -> {:?}", e);
+                break;
+            }
+        }
+    }
+
+    debug!("Received banner -> {:?}", String::from_utf8_lossy(&banner));
+    banner
+}
+
+fn openssl_cmd(request: &TlsRequest) -> Command {
+    let (host, port) = match &request.target {
+        Some(target)  => (&target[..], request.port.0),
+        None          => ("127.0.0.1", request.port.0),
+    };
+
+    let mut cmd = Command::new("openssl");
+    cmd.arg("s_client").arg("-brief");
+    cmd.arg("-connect").arg(format!("{}:{}", host, port));
+
+    if let Some(sni) = &request.sni {
+        cmd.arg("-servername").arg(sni);
+    }
+
+    // With nothing to read, the client disconnects as soon as the handshake
+    // is complete, instead of waiting for input.
+    cmd.stdin(Stdio::null());
+    cmd
+}
+
+
+/// The **openssl output** records whether a TLS handshake succeeded.
+#[derive(Debug, Copy, Clone)]
+pub struct OpenSslOutput {
+    succeeded: bool,
+}
+
+impl CommandOutput for OpenSslOutput {
+    fn interpret_command_output(_lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        Ok(Self { succeeded: exit_reason.is(0) })
     }
 }
 
@@ -79,31 +172,37 @@ impl RunUdp for NetNonCommand {
         }
     }
 
-    fn send_udp_request(&self, request: &UdpRequest) -> bool {
+    fn send_udp_request(&self, request: &UdpRequest) -> Option<Vec<u8>> {
         let mut slot = self.udps.get(request).unwrap().lock().unwrap();
         let response = slot.get_or_insert_with(|| {
-            let result = test_udp(request.addr(), Duration::new(2, 0));
+            let payload = request.payload.as_ref().map_or(&[0, 1, 2, 3, 4, 5][..], UdpPayload::bytes);
+            let result = test_udp(request.addr(), payload, Duration::new(2, 0));
 
             if let Err(e) = &result {
                 warn!("Error running network check: {:?}", e);
             }
 
-            Some(result.is_ok())
+            result.ok()
         });
-        response.clone().unwrap()
+        response.clone()
+
+    }
 
+    fn base_directory(&self) -> Option<&Path> {
+        self.base_directory.as_deref()
     }
 }
 
-fn test_udp(addr: (&str, u16), timeout: Duration) -> Result<(), IoError> {
+fn test_udp(addr: (&str, u16), payload: &[u8], timeout: Duration) -> Result<Vec<u8>, IoError> {
     let socket = UdpSocket::bind((addr.0, 49129))?;
     socket.set_read_timeout(Some(timeout))?;
     socket.set_write_timeout(Some(timeout))?;
     socket.connect(addr)?;
-    socket.send(&[0, 1, 2, 3, 4, 5])?;
+    socket.send(payload)?;
 
-    let mut buf = [0; 16];
+    let mut buf = vec![0; 65536];
     let received = socket.recv(&mut buf)?;
     debug!("Received {} bytes {:?}", received, &buf[..received]);
-    Ok(())
+    buf.truncate(received);
+    Ok(buf)
 }
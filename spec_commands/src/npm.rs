@@ -11,13 +11,30 @@
 //! ├── sass-lint@1.12.1
 //! └── typescript@3.4.3
 //! ```
+//!
+//! Projects get their local packages listed by passing their path as the
+//! prefix instead:
+//!
+//! ```text
+//! $ npm list --depth=0 --prefix ./webapp
+//! webapp@1.0.0 /home/ben/webapp
+//! ├── @types/node@14.14.2
+//! ├── UNMET DEPENDENCY express@^4.17.0
+//! └── lodash@4.17.20
+//! ```
+//!
+//! The program returns 1 when there is something wrong with the tree, such
+//! as a dependency that is missing, but it still lists the packages that
+//! _are_ installed.
 
-use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::*;
 
 use spec_checks::npm::RunNpm;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -26,6 +43,7 @@ use super::GlobalOptions;
 #[derive(Debug)]
 pub struct NpmCommand {
     exec: Option<Exec<NpmListOutput>>,
+    projects: BTreeMap<PathBuf, Exec<NpmListOutput>>,
 }
 
 impl NpmCommand {
@@ -33,33 +51,56 @@ impl NpmCommand {
     /// Creates a new command to run `npm`.
     pub fn create(global_options: &impl GlobalOptions) -> Self {
         let exec = global_options.command("npm.output");
-        Self { exec }
+        Self { exec, projects: BTreeMap::new() }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.exec.into_iter().flat_map(Exec::into_command)
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let global = self.exec.into_iter().flat_map(Exec::into_command);
+        let projects = self.projects.into_iter().flat_map(|e| e.1.into_command());
+        global.chain(projects)
     }
 }
 
 impl RunNpm for NpmCommand {
-    fn prime(&mut self) {
-        if self.exec.is_none() {
+    fn prime(&mut self, project_path: Option<&Path>) {
+        if let Some(path) = project_path {
+            if ! self.projects.contains_key(path) {
+                debug!("Priming npm command for project {:?}", path);
+                let exec = Exec::actual(npm_list_cmd(Some(path)));
+                self.projects.insert(path.to_owned(), exec);
+            }
+        }
+        else if self.exec.is_none() {
             debug!("Priming npm command");
-            self.exec = Some(Exec::actual(npm_list_cmd()));
+            self.exec = Some(Exec::actual(npm_list_cmd(None)));
         }
     }
 
-    fn find_package(&self, executor: &mut Executor, package_name: &str) -> Result<bool, Rc<ExecError>> {
-        debug!("Finding npm package -> {:?}", package_name);
-        let output = self.exec.as_ref().unwrap().run(executor)?;
+    fn find_package(&self, executor: &mut Executor, project_path: Option<&Path>, package_name: &str) -> Result<Option<String>, Arc<ExecError>> {
+        debug!("Finding npm package -> {:?} {:?}", project_path, package_name);
+
+        let exec = match project_path {
+            Some(path)  => &self.projects[path],
+            None        => self.exec.as_ref().unwrap(),
+        };
+
+        let output = exec.run(executor)?;
         Ok(output.find_package(package_name))
     }
 }
 
-fn npm_list_cmd() -> Command {
+fn npm_list_cmd(project_path: Option<&Path>) -> Command {
     let mut cmd = Command::new("npm");
-    cmd.arg("list").arg("-g").arg("--depth=0");
+    cmd.arg("list").arg("--depth=0");
+
+    if let Some(path) = project_path {
+        cmd.arg("--prefix").arg(path);
+    }
+    else {
+        cmd.arg("-g");
+    }
+
     cmd
 }
 
@@ -68,20 +109,84 @@ fn npm_list_cmd() -> Command {
 /// invoked `NpmCommand`.
 #[derive(Debug)]
 pub struct NpmListOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for NpmListOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
-        exit_reason.should_be(0)?;
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        if ! exit_reason.is(1) {
+            exit_reason.should_be(0)?;
+        }
+
         Ok(Self { lines })
     }
 }
 
 impl NpmListOutput {
 
-    /// Searches the output lines for a package with the given name.
-    fn find_package(&self, package_name: &str) -> bool {
-        self.lines.iter().any(|line| line.contains(package_name))
+    /// Searches the output lines for a package with the given name,
+    /// returning its version if it is installed. The first line is for the
+    /// project itself, so it gets skipped.
+    fn find_package(&self, package_name: &str) -> Option<String> {
+        self.lines.iter()
+            .skip(1)
+            .filter_map(|line| parse_package_line(line))
+            .find(|(name, _)| *name == package_name)
+            .map(|(_, version)| version.to_owned())
+    }
+}
+
+/// Parses the name and version out of a line of the package tree, skipping
+/// lines for dependencies that are not actually installed.
+fn parse_package_line(line: &str) -> Option<(&str, &str)> {
+    let entry = line.trim_start_matches(|c: char| ! c.is_ascii_alphanumeric() && c != '@');
+
+    if entry.starts_with("UNMET ") {
+        return None;
+    }
+
+    let entry = entry.split_whitespace().next()?;
+
+    // Scoped packages start with an ‘@’ of their own, so the version comes
+    // after the *last* one.
+    let (name, version) = entry.rsplit_once('@')?;
+    if name.is_empty() {
+        None
+    }
+    else {
+        Some((name, version))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn output() -> NpmListOutput {
+        let lines = vec![
+            Arc::from("webapp@1.0.0 /home/ben/webapp"),
+            Arc::from("├── @types/node@14.14.2"),
+            Arc::from("├── UNMET DEPENDENCY express@^4.17.0"),
+            Arc::from("├── left-pad@1.3.0 extraneous"),
+            Arc::from("└── lodash@4.17.20"),
+        ];
+
+        NpmListOutput::interpret_command_output(lines, ExitReason::Status(1)).unwrap()
+    }
+
+    #[test]
+    fn installed_packages() {
+        assert_eq!(output().find_package("lodash"), Some(String::from("4.17.20")));
+        assert_eq!(output().find_package("@types/node"), Some(String::from("14.14.2")));
+        assert_eq!(output().find_package("left-pad"), Some(String::from("1.3.0")));
+    }
+
+    #[test]
+    fn missing_packages() {
+        assert_eq!(output().find_package("express"), None);
+        assert_eq!(output().find_package("lodash.merge"), None);
+        assert_eq!(output().find_package("node"), None);
+        assert_eq!(output().find_package("webapp"), None);
     }
 }
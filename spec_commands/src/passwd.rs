@@ -2,16 +2,27 @@
 //!
 //! This does not actually run any external programs yet!
 //! It is just a placeholder.
+//!
+//! Password states are read from the shadow password file, which lists
+//! a locked password with a leading ‘!’ or ‘*’, and an empty password as
+//! an empty field:
+//!
+//! ```text
+//! root:!:19000:0:99999:7:::
+//! bethany:$6$salt$hash:19000:0:99999:7:::
+//! guest::19000:0:99999:7:::
+//! ```
 
 use std::collections::BTreeMap;
+use std::fs;
 use std::sync::Mutex;
 
 use log::*;
 use users::{User, Group};
 
-use spec_checks::user::LookupUser;
+use spec_checks::user::{LookupUser, PasswordState};
 use spec_checks::group::LookupGroup;
-use spec_exec::Command;
+use spec_exec::{Command, RunOptions};
 
 use super::GlobalOptions;
 
@@ -22,8 +33,12 @@ use super::GlobalOptions;
 pub struct PasswdNonCommand {
     users:  BTreeMap<String, Mutex<Option<Option<User>>>>,
     groups: BTreeMap<String, Mutex<Option<Option<Group>>>>,
+    shadow: Mutex<Option<Option<BTreeMap<String, PasswordState>>>>,
 }
 
+/// The path to the shadow password file.
+const SHADOW_PATH: &str = "/etc/shadow";
+
 impl PasswdNonCommand {
 
     /// Creates a new non-command.
@@ -31,11 +46,12 @@ impl PasswdNonCommand {
         Self {
             users:  BTreeMap::new(),
             groups: BTreeMap::new(),
+            shadow: Mutex::new(None),
         }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         std::iter::empty()
     }
 }
@@ -53,6 +69,46 @@ impl LookupUser for PasswdNonCommand {
         let user = slot.get_or_insert_with(|| users::get_user_by_name(username));
         user.clone()
     }
+
+    fn password_state(&self, username: &str) -> Option<PasswordState> {
+        let mut slot = self.shadow.lock().unwrap();
+        let shadow = slot.get_or_insert_with(read_shadow_file);
+        shadow.as_ref()?.get(username).copied()
+    }
+}
+
+fn read_shadow_file() -> Option<BTreeMap<String, PasswordState>> {
+    debug!("Reading shadow password file {:?}", SHADOW_PATH);
+
+    match fs::read_to_string(SHADOW_PATH) {
+        Ok(contents) => {
+            Some(parse_shadow(&contents))
+        }
+        Err(e) => {
+            warn!("Could not read shadow password file: {}", e);
+            None
+        }
+    }
+}
+
+/// Parses the contents of a shadow password file, returning the state of
+/// each user’s password.
+fn parse_shadow(contents: &str) -> BTreeMap<String, PasswordState> {
+    let mut states = BTreeMap::new();
+
+    for line in contents.lines() {
+        let mut fields = line.split(':');
+        if let (Some(username), Some(password)) = (fields.next(), fields.next()) {
+            let state = match password.chars().next() {
+                None               => PasswordState::Empty,
+                Some('!' | '*')    => PasswordState::Locked,
+                Some(_)            => PasswordState::Usable,
+            };
+            states.insert(username.to_owned(), state);
+        }
+    }
+
+    states
 }
 
 impl LookupGroup for PasswdNonCommand {
@@ -69,3 +125,20 @@ impl LookupGroup for PasswdNonCommand {
         group.clone()
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shadow_states() {
+        let states = parse_shadow("root:!:19000:0:99999:7:::\nbethany:$6$salt$hash:19000:0:99999:7:::\nguest::19000:0:99999:7:::\ndaemon:*:19000:0:99999:7:::\n");
+
+        assert_eq!(states.get("root"),    Some(&PasswordState::Locked));
+        assert_eq!(states.get("bethany"), Some(&PasswordState::Usable));
+        assert_eq!(states.get("guest"),   Some(&PasswordState::Empty));
+        assert_eq!(states.get("daemon"),  Some(&PasswordState::Locked));
+        assert_eq!(states.get("nobody"),  None);
+    }
+}
@@ -16,13 +16,13 @@
 //! ```
 
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 
 use log::*;
 
 use spec_checks::ping::RunPing;
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -44,7 +44,7 @@ impl PingCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.results.into_iter().flat_map(|e| e.1.into_command())
     }
 }
@@ -58,7 +58,7 @@ impl RunPing for PingCommand {
         }
     }
 
-    fn is_target_up(&self, executor: &mut Executor, target: &str) -> Result<bool, Rc<ExecError>> {
+    fn is_target_up(&self, executor: &mut Executor, target: &str) -> Result<bool, Arc<ExecError>> {
         debug!("Pinging target -> {:?}", target);
         let output = self.results[target].run(executor)?;
         Ok(output.received_response())
@@ -76,11 +76,11 @@ fn ping_target_cmd(target: &str) -> Command {
 /// invoked `PingCommand`.
 #[derive(Debug)]
 pub struct PingOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for PingOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         // Ping returns 0 on success, but returns 1 on Linux and 2 on macOS
         // and BSD upon failure
         if exit_reason.is(0) || exit_reason.is(1) || exit_reason.is(2) {
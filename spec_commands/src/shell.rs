@@ -5,13 +5,14 @@
 
 use std::collections::BTreeMap;
 use std::process::Command;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
 use log::*;
 use shell_words::quote as shellquote;
 
 use spec_checks::{Invocation, RunShell};
-use spec_exec::{Exec, Executor, RanCommand, ExecError};
+use spec_exec::{Exec, Executor, RanCommand, ExecError, RunOptions};
 
 use super::GlobalOptions;
 
@@ -22,6 +23,8 @@ use super::GlobalOptions;
 pub struct ShellCommand {
     shell_binary: String,
     aliases: BTreeMap<String, String>,
+    default_timeout: Option<Duration>,
+    default_pty: bool,
     results: BTreeMap<Invocation, Exec<RanCommand>>,
 }
 
@@ -35,14 +38,20 @@ impl ShellCommand {
         };
 
         let aliases = global_options.key_prefix_values("cmd.target.");
+        let default_timeout = global_options.duration("cmd.timeout");
+        let default_pty = match global_options.key_value("cmd.pty").as_deref() {
+            Some("true")          => true,
+            Some("false") | None  => false,
+            Some(other)           => { warn!("Invalid boolean for option \"cmd.pty\": {:?}", other); false }
+        };
 
         let results = BTreeMap::new();
 
-        Self { shell_binary, aliases, results }
+        Self { shell_binary, aliases, default_timeout, default_pty, results }
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.results.into_iter().flat_map(|e| e.1.into_command())
     }
 }
@@ -54,7 +63,6 @@ impl RunShell for ShellCommand {
 
             let mut cmd = Command::new(&self.shell_binary);
             cmd.arg("-c");
-            cmd.envs(&invocation.environment.0);
 
             let mut command = String::new();
             for (alias, path) in &self.aliases {
@@ -66,12 +74,28 @@ impl RunShell for ShellCommand {
             command.push_str(&invocation.shell.0);
             cmd.arg(&command);
 
-            let exec = Exec::actual(cmd);
+            // Commands without a timeout of their own use the default one,
+            // if there is one.
+            let options = RunOptions {
+                timeout: invocation.timeout.or(self.default_timeout),
+                stdin: invocation.stdin.clone(),
+                needs_root: false,
+                pty: invocation.pty.unwrap_or(self.default_pty),
+            };
+
+            let mut exec = Exec::actual_with_options(cmd, options);
+            for (key, value) in &invocation.environment.0 {
+                exec = exec.with_env(key, value);
+            }
+
+            if let Some(directory) = &invocation.directory {
+                exec = exec.with_directory(directory);
+            }
             self.results.insert(invocation.clone(), exec);
         }
     }
 
-    fn run_command(&self, executor: &mut Executor, invocation: &Invocation) -> Result<Rc<RanCommand>, Rc<ExecError>> {
+    fn run_command(&self, executor: &mut Executor, invocation: &Invocation) -> Result<Arc<RanCommand>, Arc<ExecError>> {
         debug!("Actually running command -> {:?}", invocation);
 
         self.results[invocation].run_raw(executor)
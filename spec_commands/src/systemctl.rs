@@ -28,15 +28,36 @@
 //!
 //! The program will return 4 in the case when the service being asked for
 //! doesn’t actually exist.
+//!
+//! The properties of a unit come from `systemctl show`, which outputs one
+//! property per line, even for units that don’t exist:
+//!
+//! ```text
+//! Restart=always
+//! User=postgres
+//! LoadState=loaded
+//! UnitFileState=enabled
+//! ```
+//!
+//! Timers get their properties queried with `--timestamp=unix`, so the time
+//! they next elapse is given as a number of seconds since the epoch:
+//!
+//! ```text
+//! NextElapseUSecRealtime=@1603065600
+//! LastTriggerUSec=@1602979200
+//! ```
+//!
+//! User units are queried by running `systemctl --user` instead.
 
 
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use log::*;
 
-use spec_checks::systemd::{RunSystemctl, ServiceState};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_checks::systemd::{RunSystemctl, ServiceState, Scope};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -44,7 +65,8 @@ use super::GlobalOptions;
 /// The **systemctl command** that runs the `systemctl` binary.
 #[derive(Debug, Default)]
 pub struct SystemctlCommand {
-    results: BTreeMap<String, Exec<SystemctlOutput>>,
+    results: BTreeMap<(Scope, String), Exec<SystemctlOutput>>,
+    properties: BTreeMap<(Scope, String), Exec<SystemctlShowOutput>>,
 }
 
 impl SystemctlCommand {
@@ -55,23 +77,35 @@ impl SystemctlCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
-        self.results.into_iter().flat_map(|e| e.1.into_command())
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
+        let results = self.results.into_iter().flat_map(|e| e.1.into_command());
+        let properties = self.properties.into_iter().flat_map(|e| e.1.into_command());
+        results.chain(properties)
     }
 }
 
 impl RunSystemctl for SystemctlCommand {
-    fn prime(&mut self, service_name: &str) {
-        if ! self.results.contains_key(service_name) {
-            debug!("Priming systemctl command with {:?}", service_name);
-            let exec = Exec::actual(systemctl_status_cmd(service_name));
-            self.results.insert(service_name.to_owned(), exec);
+    fn prime(&mut self, scope: Scope, unit_name: &str) {
+        let key = (scope, unit_name.to_owned());
+        if ! self.results.contains_key(&key) {
+            debug!("Priming systemctl command with {:?} {:?}", scope, unit_name);
+            let exec = Exec::actual(systemctl_status_cmd(scope, unit_name));
+            self.results.insert(key, exec);
+        }
+    }
+
+    fn prime_properties(&mut self, scope: Scope, unit_name: &str) {
+        let key = (scope, unit_name.to_owned());
+        if ! self.properties.contains_key(&key) {
+            debug!("Priming systemctl show command with {:?} {:?}", scope, unit_name);
+            let exec = Exec::actual(systemctl_show_cmd(scope, unit_name));
+            self.properties.insert(key, exec);
         }
     }
 
-    fn service_state(&self, executor: &mut Executor, service_name: &str) -> Result<ServiceState, Rc<ExecError>> {
-        debug!("Looking up service state -> {:?}", service_name);
-        let output = self.results[service_name].run(executor)?;
+    fn service_state(&self, executor: &mut Executor, scope: Scope, unit_name: &str) -> Result<ServiceState, Arc<ExecError>> {
+        debug!("Looking up service state -> {:?} {:?}", scope, unit_name);
+        let output = self.results[&(scope, unit_name.to_owned())].run(executor)?;
 
         if output.missing {
             Ok(ServiceState::Missing)
@@ -80,11 +114,44 @@ impl RunSystemctl for SystemctlCommand {
             Ok(output.service_state())
         }
     }
+
+    fn unit_property(&self, executor: &mut Executor, scope: Scope, unit_name: &str, property_name: &str) -> Result<Option<String>, Arc<ExecError>> {
+        debug!("Looking up unit property -> {:?} {:?} {:?}", scope, unit_name, property_name);
+        let output = self.properties[&(scope, unit_name.to_owned())].run(executor)?;
+        Ok(output.property(property_name))
+    }
+
+    fn next_elapse(&self, executor: &mut Executor, scope: Scope, timer_name: &str) -> Result<Option<Duration>, Arc<ExecError>> {
+        debug!("Looking up next elapse -> {:?} {:?}", scope, timer_name);
+        let output = self.properties[&(scope, timer_name.to_owned())].run(executor)?;
+        Ok(output.time_until("NextElapseUSecRealtime"))
+    }
+}
+
+fn systemctl_status_cmd(scope: Scope, unit_name: &str) -> Command {
+    let mut cmd = systemctl_cmd(scope);
+    cmd.arg("status").arg(unit_name).arg("--no-pager");
+    cmd
 }
 
-fn systemctl_status_cmd(service_name: &str) -> Command {
+fn systemctl_show_cmd(scope: Scope, unit_name: &str) -> Command {
+    let mut cmd = systemctl_cmd(scope);
+    cmd.arg("show").arg(unit_name).arg("--no-pager");
+
+    if matches!(unit_name.rsplit_once('.'), Some((_, "timer"))) {
+        cmd.arg("--timestamp=unix");
+    }
+
+    cmd
+}
+
+fn systemctl_cmd(scope: Scope) -> Command {
     let mut cmd = Command::new("systemctl");
-    cmd.arg("status").arg(service_name).arg("--no-pager");
+
+    if scope == Scope::User {
+        cmd.arg("--user");
+    }
+
     cmd
 }
 
@@ -93,12 +160,12 @@ fn systemctl_status_cmd(service_name: &str) -> Command {
 /// invoked `SystemctlCommand`.
 #[derive(Debug)]
 pub struct SystemctlOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
     missing: bool,
 }
 
 impl CommandOutput for SystemctlOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         if exit_reason.is(0) {
             let missing = false;
             Ok(Self { lines, missing })
@@ -128,3 +195,51 @@ impl SystemctlOutput {
         }
     }
 }
+
+
+/// The **systemctl show output** holds the properties of a unit, parsed
+/// from the output lines of `systemctl show`.
+#[derive(Debug)]
+pub struct SystemctlShowOutput {
+    properties: BTreeMap<String, String>,
+}
+
+impl CommandOutput for SystemctlShowOutput {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+        exit_reason.should_be(0)?;
+
+        let properties = lines.iter()
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+
+        Ok(Self { properties })
+    }
+}
+
+impl SystemctlShowOutput {
+
+    /// Returns the value of the property with the given name, treating
+    /// properties with empty values as not being set.
+    fn property(&self, property_name: &str) -> Option<String> {
+        self.properties.get(property_name)
+            .filter(|value| ! value.is_empty())
+            .cloned()
+    }
+
+    /// Returns the amount of time from now until the timestamp held in the
+    /// property with the given name, which is zero if it has already
+    /// passed, or `None` if the property is not set.
+    fn time_until(&self, property_name: &str) -> Option<Duration> {
+        let value = self.property(property_name)?;
+
+        if let Some(secs) = value.strip_prefix('@').and_then(|secs| secs.parse().ok()) {
+            let timestamp = UNIX_EPOCH + Duration::from_secs(secs);
+            Some(timestamp.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+        else {
+            warn!("Timestamp property {:?} has unexpected value {:?}", property_name, value);
+            None
+        }
+    }
+}
@@ -10,15 +10,24 @@
 //! 22/tcp                     ALLOW       Anywhere
 //! 22/tcp (v6)                ALLOW       Anywhere (v6)
 //! ```
+//!
+//! The verbose output also lists the default policies:
+//!
+//! ```text
+//! Status: active
+//! Logging: on (low)
+//! Default: deny (incoming), allow (outgoing), disabled (routed)
+//! New profiles: skip
+//! ```
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 use log::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-use spec_checks::ufw::{RunUfw, Portspec, Protocol, Rule};
-use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason};
+use spec_checks::ufw::{RunUfw, Portspec, Protocol, Rule, Action, DefaultPolicies};
+use spec_exec::{Command, Exec, Executor, ExecError, CommandOutput, ExitReason, RunOptions};
 
 use super::GlobalOptions;
 
@@ -39,7 +48,7 @@ impl UfwCommand {
     }
 
     /// Returns an iterator over the Commands contained within.
-    pub fn commands(self) -> impl Iterator<Item=Command> {
+    pub fn commands(self) -> impl Iterator<Item=(Command, RunOptions)> {
         self.exec.into_iter().flat_map(Exec::into_command)
     }
 }
@@ -48,14 +57,20 @@ impl RunUfw for UfwCommand {
     fn prime(&mut self) {
         if self.exec.is_none() {
             debug!("Priming ufw command");
-            self.exec = Some(Exec::actual(ufw_list_rules_cmd()));
+            self.exec = Some(Exec::actual_as_root(ufw_list_rules_cmd()));
         }
     }
 
-    fn find_rule(&self, executor: &mut Executor, portspec: Portspec, protocol: Protocol) -> Result<Option<Rule>, Rc<ExecError>> {
-        debug!("Finding ufw rule -> {:?}/{:?}", portspec, protocol);
+    fn find_rules(&self, executor: &mut Executor, portspec: Portspec, protocol: Protocol) -> Result<Vec<Rule>, Arc<ExecError>> {
+        debug!("Finding ufw rules -> {:?}/{:?}", portspec, protocol);
         let output = self.exec.as_ref().unwrap().run(executor)?;
-        Ok(output.find_rule(portspec, protocol))
+        Ok(output.find_rules(portspec, protocol))
+    }
+
+    fn default_policies(&self, executor: &mut Executor) -> Result<Option<DefaultPolicies>, Arc<ExecError>> {
+        debug!("Finding ufw default policies");
+        let output = self.exec.as_ref().unwrap().run(executor)?;
+        Ok(output.default_policies())
     }
 }
 
@@ -70,11 +85,11 @@ fn ufw_list_rules_cmd() -> Command {
 /// invoked `UfwCommand`.
 #[derive(Debug)]
 pub struct UfwOutput {
-    lines: Vec<Rc<str>>,
+    lines: Vec<Arc<str>>,
 }
 
 impl CommandOutput for UfwOutput {
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
         exit_reason.should_be(0)?;
         Ok(Self { lines })
     }
@@ -82,33 +97,70 @@ impl CommandOutput for UfwOutput {
 
 impl UfwOutput {
 
-    /// Searches the output lines for a rule with the given protocol and port
-    /// specification, returning the rest of the fields for that rule if one
-    /// is found, or nothing if none of the rules matches.
-    fn find_rule(&self, portspec: Portspec, protocol: Protocol) -> Option<Rule> {
-        let mut prefix = String::new();
-
-        match portspec {
-            Portspec::One(one)        => prefix.push_str(&one.to_string()),
-            Portspec::Range(from, to) => prefix.push_str(&format!("{}:{}", from, to)),
-        }
-
-        match protocol {
-            Protocol::TCP => prefix.push_str("/tcp"),
-            Protocol::UDP => prefix.push_str("/udp"),
+    /// Searches the output lines for rules with the given protocol and port
+    /// specification, returning the rest of the fields for each of them.
+    /// Rules without a protocol apply to both TCP and UDP.
+    fn find_rules(&self, portspec: Portspec, protocol: Protocol) -> Vec<Rule> {
+        let mut rules = Vec::new();
+
+        for line in &self.lines {
+            let caps = match REGEX.captures(line) {
+                Some(c) => c,
+                None    => continue,
+            };
+
+            let from = caps.name("from").unwrap().as_str().parse().ok();
+            let to = caps.name("to").map(|t| t.as_str().parse().ok());
+            let line_portspec = match (from, to) {
+                (Some(from), None)            => Portspec::One(from),
+                (Some(from), Some(Some(to)))  => Portspec::Range(from, to),
+                _                             => continue,
+            };
+
+            let line_protocol = match caps.name("protocol").map(|p| p.as_str()) {
+                Some("tcp")  => Some(Protocol::TCP),
+                Some("udp")  => Some(Protocol::UDP),
+                _            => None,
+            };
+
+            if line_portspec != portspec || matches!(line_protocol, Some(p) if p != protocol) {
+                continue;
+            }
+
+            let action = match caps.name("action").unwrap().as_str() {
+                "DENY"    => Action::Deny,
+                "REJECT"  => Action::Reject,
+                "LIMIT"   => Action::Limit,
+                _         => Action::Allow,
+            };
+
+            let iface = caps.name("iface").map(|s| s.as_str().to_owned());
+            let allow = caps.name("allow").unwrap().as_str().trim().to_owned();
+            let ipv6  = caps.name("ipv6").is_some();
+            rules.push(Rule { iface, action, allow, ipv6 });
         }
 
-        if let Some(line) = self.lines.iter().find(|line| line.starts_with(&prefix)) {
-            let caps  = REGEX.captures(line).expect(line);
+        rules
+    }
 
-            let iface = caps.get(4).map(|s| s.as_str().to_owned());
-            let allow = caps.get(6).unwrap().as_str().trim().to_owned();
-            let ipv6  = caps.get(7).is_some();
-            Some(Rule { iface, allow, ipv6 })
-        }
-        else {
-            None
+    /// Searches the output lines for the line that lists the default
+    /// policies, which is only present when the firewall is active.
+    fn default_policies(&self) -> Option<DefaultPolicies> {
+        let line = self.lines.iter().find_map(|line| line.strip_prefix("Default: "))?;
+        let mut policies = DefaultPolicies::default();
+
+        for field in line.split(", ") {
+            if let Some((policy, direction)) = field.split_once(" (") {
+                match direction.trim_end_matches(')') {
+                    "incoming"  => policies.incoming = policy.into(),
+                    "outgoing"  => policies.outgoing = policy.into(),
+                    "routed"    => policies.routed = policy.into(),
+                    _           => {/* ignore other directions */},
+                }
+            }
         }
+
+        Some(policies)
     }
 }
 
@@ -116,17 +168,20 @@ impl UfwOutput {
 /// Regular expression used to extract data from a line of ufw output.
 static REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r##"(?x) ^
-        (\d+) (: \d+)?      # from (and to) ports
-        / (tcp|udp) \s+     # the protocol
+        (?P<from> \d+ ) (?: : (?P<to> \d+ ) )?   # from (and to) ports
+        (?: / (?P<protocol> tcp|udp ) )?        # the protocol, if any
+        (?: \s+ \( v6 \) )?                      # repeated ipv6 marker
+        \s+
         (?:
             on \s+
-            ( .+? ) \s+     # interface
+            (?P<iface> .+? ) \s+                 # interface
         )?
-        (ALLOW \s IN | ALLOW) \s+  # directive
-        (.+?)                # allow from
+        (?P<action> ALLOW|DENY|REJECT|LIMIT )   # action
+        (?: \s (?: IN|OUT|FWD ) )? \s+          # direction
+        (?P<allow> .+? )                        # allow from
         (?:
             \s+
-            ( \( v6 \) )    # ipv6?
+            (?P<ipv6> \( v6 \) )                # ipv6?
         )?
     $ "##).unwrap()
 });
@@ -137,35 +192,82 @@ static REGEX: Lazy<Regex> = Lazy::new(|| {
 mod test {
     use super::*;
 
+    fn rule(iface: Option<&str>, action: Action, ipv6: bool) -> Rule {
+        Rule { iface: iface.map(String::from), action, allow: "Anywhere".into(), ipv6 }
+    }
+
     #[test]
     fn some_ufw_rules() {
         let lines = vec![
-            Rc::from("22/tcp                     ALLOW IN    Anywhere"),
-            Rc::from("60000:61000/udp            ALLOW IN    Anywhere"),
+            Arc::from("22/tcp                     ALLOW IN    Anywhere"),
+            Arc::from("60000:61000/udp            ALLOW IN    Anywhere"),
         ];
 
         let output = UfwOutput { lines };
 
-        assert_eq!(Some(Rule { iface: None, allow: "Anywhere".into(), ipv6: false }), output.find_rule(Portspec::One(22),             Protocol::TCP));
-        assert_eq!(Some(Rule { iface: None, allow: "Anywhere".into(), ipv6: false }), output.find_rule(Portspec::Range(60000, 61000), Protocol::UDP));
+        assert_eq!(vec![ rule(None, Action::Allow, false) ], output.find_rules(Portspec::One(22),             Protocol::TCP));
+        assert_eq!(vec![ rule(None, Action::Allow, false) ], output.find_rules(Portspec::Range(60000, 61000), Protocol::UDP));
 
-        assert_eq!(None, output.find_rule(Portspec::One(23), Protocol::TCP));
-        assert_eq!(None, output.find_rule(Portspec::One(22), Protocol::UDP));
+        assert_eq!(Vec::<Rule>::new(), output.find_rules(Portspec::One(23), Protocol::TCP));
+        assert_eq!(Vec::<Rule>::new(), output.find_rules(Portspec::One(22), Protocol::UDP));
+        assert_eq!(Vec::<Rule>::new(), output.find_rules(Portspec::One(60000), Protocol::UDP));
     }
 
 
     #[test]
     fn interface_and_ipv6_ufw_rules() {
         let lines = vec![
-            Rc::from("8500/tcp on eth0           ALLOW IN    Anywhere"),
-            Rc::from("8302/tcp on eth0           ALLOW IN    Anywhere (v6)"),
-            Rc::from("60000:61000/udp            ALLOW IN    Anywhere (v6)"),
+            Arc::from("8500/tcp on eth0           ALLOW IN    Anywhere"),
+            Arc::from("8302/tcp on eth0           ALLOW IN    Anywhere (v6)"),
+            Arc::from("60000:61000/udp            ALLOW IN    Anywhere (v6)"),
         ];
 
         let output = UfwOutput { lines };
 
-        assert_eq!(Some(Rule { iface: Some("eth0".into()), allow: "Anywhere".into(), ipv6: false }), output.find_rule(Portspec::One(8500),          Protocol::TCP));
-        assert_eq!(Some(Rule { iface: Some("eth0".into()), allow: "Anywhere".into(), ipv6: true }),  output.find_rule(Portspec::One(8302),           Protocol::TCP));
-        assert_eq!(Some(Rule { iface: None,                allow: "Anywhere".into(), ipv6: true }),  output.find_rule(Portspec::Range(60000, 61000), Protocol::UDP));
+        assert_eq!(vec![ rule(Some("eth0"), Action::Allow, false) ], output.find_rules(Portspec::One(8500),           Protocol::TCP));
+        assert_eq!(vec![ rule(Some("eth0"), Action::Allow, true) ],  output.find_rules(Portspec::One(8302),           Protocol::TCP));
+        assert_eq!(vec![ rule(None,         Action::Allow, true) ],  output.find_rules(Portspec::Range(60000, 61000), Protocol::UDP));
+    }
+
+    #[test]
+    fn actions_and_protocol_less_rules() {
+        let lines = vec![
+            Arc::from("22/tcp                     LIMIT IN    Anywhere"),
+            Arc::from("23                         DENY IN     Anywhere"),
+            Arc::from("25/tcp                     REJECT OUT  Anywhere"),
+            Arc::from("23 (v6)                    DENY IN     Anywhere (v6)"),
+        ];
+
+        let output = UfwOutput { lines };
+
+        assert_eq!(vec![ rule(None, Action::Limit, false) ],  output.find_rules(Portspec::One(22), Protocol::TCP));
+        assert_eq!(vec![ rule(None, Action::Deny, false), rule(None, Action::Deny, true) ],
+                   output.find_rules(Portspec::One(23), Protocol::UDP));
+        assert_eq!(vec![ rule(None, Action::Reject, false) ], output.find_rules(Portspec::One(25), Protocol::TCP));
+    }
+
+    #[test]
+    fn default_policies() {
+        let lines = vec![
+            Arc::from("Status: active"),
+            Arc::from("Logging: on (low)"),
+            Arc::from("Default: deny (incoming), allow (outgoing), disabled (routed)"),
+            Arc::from("New profiles: skip"),
+        ];
+
+        let output = UfwOutput { lines };
+
+        assert_eq!(Some(DefaultPolicies { incoming: "deny".into(), outgoing: "allow".into(), routed: "disabled".into() }),
+                   output.default_policies());
+    }
+
+    #[test]
+    fn inactive_firewall() {
+        let lines = vec![
+            Arc::from("Status: inactive"),
+        ];
+
+        let output = UfwOutput { lines };
+        assert_eq!(None, output.default_policies());
     }
 }
@@ -0,0 +1,278 @@
+//! The async executor, which runs processes on a tokio runtime.
+//!
+//! Most commands spend their time waiting for something else, such as a
+//! server on the other end of a network connection, rather than doing any
+//! work themselves. Reading the output of each process from threads of its
+//! own, as the synchronous executor does, means that running hundreds of
+//! them at once needs hundreds of threads. Here, every process gets run on
+//! the same small pool of threads instead, and a limit on the number that
+//! can be running at once stops them from overwhelming the machine.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::os::unix::process::CommandExt;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use log::*;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command as AsyncCommand};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::sync::Semaphore;
+use tokio::time;
+
+use crate::error::ExecError;
+use crate::executor::*;
+use crate::interrupt;
+
+
+/// The **async executor** runs processes concurrently on a tokio runtime,
+/// with no more than a certain number running at any one time.
+///
+/// Processes can be run on it directly, with `run`, from async code. The
+/// synchronous `Executor` can also be made to run its processes on it with
+/// `Executor::set_async`, in which case everything else the executor does,
+/// such as recording and caching results, stays the same, and can run many
+/// processes at once on it with `Executor::prefetch`.
+#[derive(Debug)]
+pub struct AsyncExecutor {
+    runtime: Runtime,
+    permits: Arc<Semaphore>,
+}
+
+impl AsyncExecutor {
+
+    /// Creates a new async executor, with a runtime of its own, that lets
+    /// up to the given number of processes run at once.
+    pub fn new(max_processes: usize) -> io::Result<Self> {
+        debug!("Creating async executor -> {} processes", max_processes);
+        let runtime = Builder::new_multi_thread()
+            .thread_name("async executor")
+            .enable_all()
+            .build()?;
+
+        Ok(Self { runtime, permits: Arc::new(Semaphore::new(max_processes)) })
+    }
+
+    /// Runs the given Command, waiting until there’s room for it to run if
+    /// the limit on processes has been reached, and returns its results.
+    ///
+    /// Unlike running it through an `Executor`, the command gets run
+    /// exactly as it is, and its results don’t get stored anywhere.
+    pub async fn run(&self, command: Command, options: &RunOptions) -> Result<RanCommand, ExecError> {
+        let mut command = command;
+        let invocation = format!("{:?}", command);
+        let environment = command_environment(&command);
+        let directory = command.get_current_dir().map(Path::to_path_buf);
+
+        set_up_io(&mut command, options)?;
+        command.process_group(0);
+
+        info!("Spawning command -> {:?}", command);
+        let finished = run_process(self.runtime.handle(), &self.permits, command, options, options.timeout, None).await?;
+        Ok(finished.into_ran_command(invocation, environment, directory))
+    }
+
+    /// Runs the process on the runtime, blocking the current thread until
+    /// it has finished. Any number of threads can be blocked on the same
+    /// runtime at once.
+    pub(crate) fn block_on_process(&self, command: Command, options: &RunOptions, timeout: Option<Duration>, events: Option<Sender<StreamEvent>>) -> Result<Finished, ExecError> {
+        self.runtime.block_on(run_process(self.runtime.handle(), &self.permits, command, options, timeout, events))
+    }
+
+    /// Runs every one of the processes as a task of its own on the runtime,
+    /// blocking the current thread until they have all finished, and
+    /// returns their results in the same order. No more of them than the
+    /// limit get run at once, however many there are.
+    pub(crate) fn block_on_processes(&self, processes: Vec<(Command, RunOptions, Option<Duration>)>) -> Vec<Result<Finished, ExecError>> {
+        debug!("Running {} processes as tasks", processes.len());
+
+        let tasks = processes.into_iter().map(|(command, options, timeout)| {
+            let (handle, permits) = (self.runtime.handle().clone(), Arc::clone(&self.permits));
+            self.runtime.spawn(async move {
+                run_process(&handle, &permits, command, &options, timeout, None).await
+            })
+        }).collect::<Vec<_>>();
+
+        self.runtime.block_on(async {
+            let mut results = Vec::with_capacity(tasks.len());
+            for task in tasks {
+                results.push(task.await.unwrap_or_else(|e| Err(ExecError::Wait(io::Error::other(e)))));
+            }
+
+            results
+        })
+    }
+}
+
+/// Waits for a permit, then spawns the process, reads its output, and waits
+/// for it to exit or for its timeout to elapse, whichever comes first.
+async fn run_process(handle: &Handle, permits: &Semaphore, command: Command, options: &RunOptions, timeout: Option<Duration>, events: Option<Sender<StreamEvent>>) -> Result<Finished, ExecError> {
+    let _permit = permits.acquire().await.expect("Semaphore closed");
+
+    // A lot can change while waiting for a permit.
+    if interrupt::is_interrupted() {
+        return Err(ExecError::Interrupted);
+    }
+
+    let timer = Instant::now();
+    let mut child = AsyncCommand::from(command).spawn().map_err(ExecError::Spawn)?;
+    let pid = child.id().expect("Process has no ID");
+    interrupt::register_group(pid);
+
+    if let Some(events) = &events {
+        let _ = events.send(StreamEvent::Started(pid));
+    }
+
+    // Both output streams get read from tasks of their own, which keep
+    // what they have read in the shared streams as they go, so there’s
+    // something to use if they never get to the end.
+    let output_tmp = SharedLines::default();
+    let stdout_tmp = SharedStream::default();
+    let stderr_tmp = SharedStream::default();
+    let stdout_task = handle.spawn(read_stream(child.stdout.take().unwrap(), OutputStream::Stdout, Arc::clone(&stdout_tmp), Arc::clone(&output_tmp), events.clone()));
+    let stderr_task = handle.spawn(read_stream(child.stderr.take().unwrap(), OutputStream::Stderr, Arc::clone(&stderr_tmp), Arc::clone(&output_tmp), events));
+
+    // The process is free to exit without reading all its input.
+    if let Some(Input::Bytes(bytes)) = &options.stdin {
+        let mut stdin = child.stdin.take().unwrap();
+        let bytes = bytes.clone();
+        handle.spawn(async move {
+            if let Err(e) = stdin.write_all(&bytes).await {
+                debug!("Error writing to process stdin: {}", e);
+            }
+        });
+    }
+
+    let exit_reason = wait(&mut child, pid, timeout).await;
+    let runtime = timer.elapsed();
+    debug!("Command complete in -> {:?}", runtime);
+
+    interrupt::unregister_group(pid);
+    let exit_reason = exit_reason?;
+
+    // A process that was killed may have left children of its own
+    // holding the pipes open, so in that case, only wait for a little
+    // while and use whatever output has been read by then.
+    let reading = async {
+        for task in [ stdout_task, stderr_task ] {
+            task.await.unwrap_or_else(|e| Err(io::Error::other(e))).map_err(ExecError::Stdout)?;
+        }
+
+        Ok(())
+    };
+
+    if exit_reason == ExitReason::TimedOut {
+        if let Ok(result) = time::timeout(READ_GRACE_PERIOD, reading).await {
+            result?;
+        }
+        else {
+            warn!("Output streams still open after timeout");
+        }
+    }
+    else {
+        reading.await?;
+    }
+
+    Ok(Finished::take(exit_reason, runtime, &stdout_tmp, &stderr_tmp, &output_tmp))
+}
+
+/// Reads lines from the given stream until it gets closed, adding each one
+/// to the shared streams as soon as it’s read.
+async fn read_stream(stream: impl AsyncRead + Unpin, which: OutputStream, read: SharedStream, both: SharedLines, events: Option<Sender<StreamEvent>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            return Ok(());
+        }
+
+        record_line(&buf, which, &read, &both, events.as_ref());
+    }
+}
+
+/// Waits for the child process to exit, killing it, along with every other
+/// process in its process group, if it is still running once the limit has
+/// elapsed.
+async fn wait(child: &mut Child, pid: u32, timeout: Option<Duration>) -> Result<ExitReason, ExecError> {
+    let Some(limit) = timeout else {
+        return child.wait().await.map(ExitReason::from).map_err(ExecError::Wait);
+    };
+
+    if let Ok(exit) = time::timeout(limit, child.wait()).await {
+        return exit.map(ExitReason::from).map_err(ExecError::Wait);
+    }
+
+    warn!("Process timed out after {:?}; killing it", limit);
+    if ! interrupt::kill_process_group(pid) {
+        if let Err(e) = child.start_kill() {
+            warn!("Error killing process: {}", e);
+        }
+    }
+
+    child.wait().await.map_err(ExecError::Wait)?;
+    Ok(ExitReason::TimedOut)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(command: Command, options: &RunOptions) -> Result<RanCommand, ExecError> {
+        let executor = AsyncExecutor::new(4).unwrap();
+        executor.runtime.block_on(executor.run(command, options))
+    }
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn exit_status() {
+        let ran_command = run(shell("echo hello; exit 3"), &RunOptions::default()).unwrap();
+        assert_eq!(ran_command.exit_reason, ExitReason::Status(3));
+        assert_eq!(ran_command.stdout_bytes(), b"hello\n");
+    }
+
+    #[test]
+    fn timeout() {
+        let options = RunOptions { timeout: Some(Duration::from_millis(100)), ..RunOptions::default() };
+        let ran_command = run(shell("sleep 5"), &options).unwrap();
+        assert_eq!(ran_command.exit_reason, ExitReason::TimedOut);
+        assert!(ran_command.runtime < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn stdin_bytes() {
+        let options = RunOptions { stdin: Some(Input::Bytes(b"one\ntwo\n".to_vec())), ..RunOptions::default() };
+        let ran_command = run(Command::new("cat"), &options).unwrap();
+        assert_eq!(ran_command.exit_reason, ExitReason::Status(0));
+        assert_eq!(ran_command.stdout_bytes(), b"one\ntwo\n");
+    }
+
+    #[test]
+    fn processes_limited() {
+        let executor = AsyncExecutor::new(2).unwrap();
+        let processes = (0 .. 4).map(|i| {
+            let mut command = shell(&format!("sleep 0.2; echo {}", i));
+            set_up_io(&mut command, &RunOptions::default()).unwrap();
+            (command, RunOptions::default(), None)
+        }).collect();
+
+        let timer = Instant::now();
+        let results = executor.block_on_processes(processes);
+        let elapsed = timer.elapsed();
+
+        let outputs = results.into_iter().map(|f| f.unwrap().stdout_raw).collect::<Vec<_>>();
+        assert_eq!(outputs, vec![ b"0\n".to_vec(), b"1\n".to_vec(), b"2\n".to_vec(), b"3\n".to_vec() ]);
+        assert!(elapsed >= Duration::from_millis(400), "Ran too many at once: {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(800), "Ran too few at once: {:?}", elapsed);
+    }
+}
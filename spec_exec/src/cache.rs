@@ -0,0 +1,74 @@
+//! Caching the results of commands, so identical ones only get run once.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::executor::RanCommand;
+
+
+/// A **command cache** holds the results of the commands that have been
+/// run, so that when more than one check needs the same command to be run —
+/// even through different types of command — it only gets run once.
+///
+/// Commands that fail to run at all have no results to keep, so they get
+/// run again the next time they’re needed.
+#[derive(Debug, Default)]
+pub struct CommandCache {
+    slots: Mutex<BTreeMap<String, Arc<Slot>>>,
+    store: Option<Box<dyn CacheStore>>,
+}
+
+/// The place in the cache for the result of one command. It gets locked
+/// while the command is running, so anything else that needs it waits for
+/// the result instead of running the command a second time.
+pub(crate) type Slot = Mutex<Option<Arc<RanCommand>>>;
+
+/// A **cache store** keeps the results of commands somewhere that lasts
+/// longer than a single run, such as on disk, so they can be used again by
+/// later runs instead of running the commands again.
+pub trait CacheStore: fmt::Debug + Send + Sync {
+
+    /// Returns the stored result of the command with the given key, if
+    /// there is one that is still fresh enough to use.
+    fn load(&self, key: &str) -> Option<RanCommand>;
+
+    /// Stores the result of the command with the given key.
+    fn save(&self, key: &str, ran_command: &RanCommand);
+}
+
+impl CommandCache {
+
+    /// Creates a new, empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty cache, which also looks for results in the
+    /// given store, and puts the results of commands it runs there.
+    pub fn with_store(store: Box<dyn CacheStore>) -> Self {
+        Self { store: Some(store), ..Self::default() }
+    }
+
+    /// Returns the slot for the command with the given key, creating it if
+    /// it doesn’t exist yet.
+    pub(crate) fn slot(&self, key: &str) -> Arc<Slot> {
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots.entry(key.into()).or_default();
+        Arc::clone(slot)
+    }
+
+    /// Returns the result of the command with the given key from the
+    /// store, if there is a store and it has one.
+    pub(crate) fn load(&self, key: &str) -> Option<RanCommand> {
+        self.store.as_ref()?.load(key)
+    }
+
+    /// Puts the result of the command with the given key in the store, if
+    /// there is one.
+    pub(crate) fn save(&self, key: &str, ran_command: &RanCommand) {
+        if let Some(store) = &self.store {
+            store.save(key, ran_command);
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use std::fmt;
 use std::io::Error as IoError;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::executor::ExitReason as ER;
 
 
 /// Something that can go wrong while running a program. To help with
-/// debugging, there are three different IO error variants, corresponding to
-/// the three places such an error could be raised.
+/// debugging, there are four different IO error variants, corresponding to
+/// the four places such an error could be raised.
 #[derive(Debug)]
 pub enum ExecError {
 
     /// There was an IO error while spawning the program.
     Spawn(IoError),
 
+    /// There was an IO error opening the file to use as the program’s input.
+    Stdin(IoError),
+
     /// There was an IO error getting the program’s output.
     Stdout(IoError),
 
@@ -22,18 +27,59 @@ pub enum ExecError {
     /// The process didn’t exit for the reason we expected. This may mean it
     /// exited with a status other than 0, or that it was killed by a signal.
     StatusMismatch(ER),
+
+    /// The process exited successfully, but its output was not in the
+    /// format we expected.
+    InvalidOutput(String),
+
+    /// The process was still running when its timeout elapsed, so it was
+    /// killed after running for this long. The lines it had written to its
+    /// standard output stream by then are kept, as they can help explain
+    /// what it was waiting for.
+    TimedOut(Duration, Vec<Arc<str>>),
+
+    /// Commands are being replayed from a recording, but this one, with
+    /// this invocation, was not in it.
+    NotRecorded(String),
+
+    /// The process needed to be run as root, but `sudo` could not run it
+    /// without asking for a password, and printed this message.
+    SudoUnavailable(String),
+
+    /// specsheet was interrupted, so the process either got killed or was
+    /// never run.
+    Interrupted,
+}
+
+impl ExecError {
+
+    /// Returns the output of the process that was captured before it got
+    /// killed, if it timed out.
+    pub fn partial_output(&self) -> Option<&[Arc<str>]> {
+        match self {
+            Self::TimedOut(_, lines)  => Some(lines),
+            _                         => None,
+        }
+    }
 }
 
 impl fmt::Display for ExecError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::Spawn(ref ioe)                  => write!(f, "Spawning failed: {}", ioe),
+            Self::Stdin(ref ioe)                  => write!(f, "Opening input failed: {}", ioe),
             Self::Stdout(ref ioe)                 => write!(f, "Read failed: {}", ioe),
             Self::Wait(ref ioe)                   => write!(f, "Wait failed: {}", ioe),
             Self::StatusMismatch(ER::Status(s))   => write!(f, "Process exited with status code ‘{}’", s),
             Self::StatusMismatch(ER::Signal(s))   => write!(f, "Process was killed with signal ‘{}’", s),
             Self::StatusMismatch(ER::Unknown)     => write!(f, "Process exited for an unknown reason"),
+            Self::StatusMismatch(ER::TimedOut)    => write!(f, "Process timed out and was killed"),
             Self::StatusMismatch(ER::Overridden)  => unreachable!(),
+            Self::InvalidOutput(ref message)      => write!(f, "Output could not be understood: {}", message),
+            Self::TimedOut(runtime, _)            => write!(f, "Process timed out after ‘{}ms’ and was killed", runtime.as_millis()),
+            Self::NotRecorded(ref invocation)     => write!(f, "Command was not in the recording: {}", invocation),
+            Self::SudoUnavailable(ref message)    => write!(f, "Passwordless sudo is not available ({})", message),
+            Self::Interrupted                     => write!(f, "Interrupted"),
         }
     }
 }
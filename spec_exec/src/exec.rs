@@ -1,15 +1,17 @@
 //! The Exec type and its methods.
 
 
+use std::ffi::OsStr;
 use std::fmt;
-use std::rc::Rc;
-use std::sync::Mutex;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
 pub use std::process::Command;
 
 use log::*;
 
 use crate::error::ExecError;
-use crate::executor::{Executor, RanCommand, ExitReason};
+use crate::executor::{Executor, RanCommand, ExitReason, RunOptions, StreamEvent};
 
 
 /// An **Exec** is the main thing that Specsheet deals with. It
@@ -34,12 +36,13 @@ enum Inner<T> {
         name: &'static str,
 
         /// A value created from the user’s input.
-        object: Rc<T>,
+        object: Arc<T>,
     },
 
     /// This exec runs a process and uses its exit status and output
-    /// streams. The state of the process is stored behind a Mutex.
-    Invocation(Mutex<State<T>>),
+    /// streams. The state of the process is stored behind a Mutex,
+    /// alongside the options to run it with.
+    Invocation(Mutex<State<T>>, RunOptions),
 }
 
 /// The internal state of a process-running Exec.
@@ -55,10 +58,10 @@ enum State<T> {
 
     /// This Exec has already run and succeeded, producing the output value
     /// created from its lines (as long as it’s not raw)
-    Completed(Rc<RanCommand>, Option<Rc<T>>),
+    Completed(Arc<RanCommand>, Option<Arc<T>>),
 
     /// This Exec has already run and failed.
-    Attempted(Rc<ExecError>),
+    Attempted(Arc<ExecError>),
 }
 
 /// Common trait for all the output types.
@@ -67,7 +70,7 @@ pub trait CommandOutput: Sized {
     /// Determine whether a process succeeded by examining its exit reason and
     /// standard output lines. Usually, an exit status of 0 signifies success,
     /// and the output format is up to the command.
-    fn interpret_command_output(lines: Vec<Rc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError>;
+    fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError>;
 }
 
 
@@ -75,13 +78,66 @@ impl<T> Exec<T> {
 
     /// Create a new Exec with a command to run.
     pub fn actual(command: Command) -> Self {
-        Self(Inner::Invocation(Mutex::new(State::Primed(command))))
+        Self::actual_with_options(command, RunOptions::default())
+    }
+
+    /// Create a new Exec with a command that needs to be run as root, which
+    /// gets run with `sudo` if the executor has been told to use it.
+    pub fn actual_as_root(command: Command) -> Self {
+        let options = RunOptions { needs_root: true, ..RunOptions::default() };
+        Self::actual_with_options(command, options)
+    }
+
+    /// Create a new Exec with a command to run, along with options that
+    /// affect how it gets run, such as a timeout.
+    pub fn actual_with_options(command: Command, options: RunOptions) -> Self {
+        Self(Inner::Invocation(Mutex::new(State::Primed(command)), options))
     }
 
     /// Create a new Exec that’s already been executed, with some pre-existing
     /// output and the command-line argument it came from.
     pub fn predetermined(name: &'static str, object: T) -> Self {
-        Self(Inner::Predetermined { name, object: Rc::new(object) })
+        Self(Inner::Predetermined { name, object: Arc::new(object) })
+    }
+
+    /// Sets an environment variable for the process this Exec runs. This
+    /// has no effect on an Exec that has been overridden.
+    #[must_use]
+    pub fn with_env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        if let Some(command) = self.primed_command() {
+            command.env(key, value);
+        }
+
+        self
+    }
+
+    /// Sets the directory that the process this Exec runs gets run from,
+    /// instead of the executor’s. A relative path is relative to the
+    /// executor’s directory. This has no effect on an Exec that has been
+    /// overridden.
+    #[must_use]
+    pub fn with_directory(mut self, directory: impl AsRef<Path>) -> Self {
+        if let Some(command) = self.primed_command() {
+            command.current_dir(directory);
+        }
+
+        self
+    }
+
+    /// Returns the command this Exec is going to run, if it hasn’t been
+    /// run yet.
+    fn primed_command(&mut self) -> Option<&mut Command> {
+        match self {
+            Self(Inner::Invocation(mutex, _)) => {
+                match mutex.get_mut().unwrap() {
+                    State::Primed(command)  => Some(command),
+                    _                       => None,
+                }
+            }
+            Self(Inner::Predetermined { .. }) => {
+                None
+            }
+        }
     }
 }
 
@@ -90,13 +146,13 @@ impl<T: CommandOutput> Exec<T> {
     /// Run the loaded command, producing an output value shared with the
     /// executor as well as its exit status, or an error if it fails. Non-zero
     /// exit statuses are not counted as failures.
-    pub fn run(&self, executor: &mut Executor) -> Result<Rc<T>, Rc<ExecError>> {
+    pub fn run(&self, executor: &mut Executor) -> Result<Arc<T>, Arc<ExecError>> {
         use std::mem;
 
         // An overridden Exec has been “run” with some output already.
-        let mutex = match self {
-            Self(Inner::Predetermined { object, .. })  => return Ok(Rc::clone(object)),
-            Self(Inner::Invocation(mutex))             => mutex,
+        let (mutex, options) = match self {
+            Self(Inner::Predetermined { object, .. })  => return Ok(Arc::clone(object)),
+            Self(Inner::Invocation(mutex, options))    => (mutex, options),
         };
 
         // Lock the mutex until the command has been run
@@ -104,9 +160,9 @@ impl<T: CommandOutput> Exec<T> {
         match &*state {
             State::Primed(_)                => {/* continue further */},
             State::Running                  => unreachable!("State still running"),
-            State::Completed(_rc, Some(t))  => return Ok(Rc::clone(t)),
+            State::Completed(_rc, Some(t))  => return Ok(Arc::clone(t)),
             State::Completed(_rc, None)     => unreachable!("No output value"),
-            State::Attempted(err)           => return Err(Rc::clone(err)),
+            State::Attempted(err)           => return Err(Arc::clone(err)),
         }
 
         // We need to temporarily set the state to Running in order to
@@ -120,26 +176,31 @@ impl<T: CommandOutput> Exec<T> {
         };
 
         // Then just set the state based on how running it goes
-        match executor.run_and_store(cmd) {
+        match executor.run_and_store_with_options(cmd, options) {
+            Ok(ran_command) if ran_command.exit_reason == ExitReason::TimedOut => {
+                let rc = Arc::new(ExecError::TimedOut(ran_command.runtime, ran_command.stdout_lines()));
+                *state = State::Attempted(Arc::clone(&rc));
+                Err(rc)
+            }
             Ok(ran_command) => {
                 let er = ran_command.exit_reason;
                 match T::interpret_command_output(ran_command.stdout_lines(), er) {
                     Ok(t) => {
-                        let rc_t = Rc::new(t);
-                        *state = State::Completed(ran_command, Some(Rc::clone(&rc_t)));
+                        let rc_t = Arc::new(t);
+                        *state = State::Completed(ran_command, Some(Arc::clone(&rc_t)));
                         Ok(rc_t)
                     }
                     Err(e) => {
-                        let rc = Rc::new(e);
-                        *state = State::Attempted(Rc::clone(&rc));
+                        let rc = Arc::new(e);
+                        *state = State::Attempted(Arc::clone(&rc));
                         // todo: put the failure reason in Attempted somewhere
                         Err(rc)
                     }
                 }
             }
             Err(e) => {
-                let rc = Rc::new(e);
-                *state = State::Attempted(Rc::clone(&rc));
+                let rc = Arc::new(e);
+                *state = State::Attempted(Arc::clone(&rc));
                 Err(rc)
             }
         }
@@ -150,13 +211,24 @@ impl Exec<RanCommand> {
 
     /// Runs a command, like `run`, but does not try to interpret the
     /// output, instead returning the raw `RanCommand`.
-    pub fn run_raw(&self, executor: &mut Executor) -> Result<Rc<RanCommand>, Rc<ExecError>> {
+    pub fn run_raw(&self, executor: &mut Executor) -> Result<Arc<RanCommand>, Arc<ExecError>> {
+        self.run_raw_inner(executor, None)
+    }
+
+    /// Runs a command, like `run_raw`, but also sends its lines of output
+    /// down the given channel as soon as they are read. Nothing gets sent
+    /// if the command has already been run.
+    pub fn run_streaming(&self, executor: &mut Executor, events: Sender<StreamEvent>) -> Result<Arc<RanCommand>, Arc<ExecError>> {
+        self.run_raw_inner(executor, Some(events))
+    }
+
+    fn run_raw_inner(&self, executor: &mut Executor, events: Option<Sender<StreamEvent>>) -> Result<Arc<RanCommand>, Arc<ExecError>> {
         use std::mem;
 
         // An overridden Exec has been “run” with some output already.
-        let mutex = match self {
-            Self(Inner::Predetermined { .. })  => unreachable!(),
-            Self(Inner::Invocation(mutex))     => mutex,
+        let (mutex, options) = match self {
+            Self(Inner::Predetermined { .. })        => unreachable!(),
+            Self(Inner::Invocation(mutex, options))  => (mutex, options),
         };
 
         // Lock the mutex until the command has been run
@@ -164,8 +236,8 @@ impl Exec<RanCommand> {
         match &*state {
             State::Primed(_)         => {/* continue further */},
             State::Running           => unreachable!("State still running"),
-            State::Completed(rc, _)  => return Ok(Rc::clone(rc)),
-            State::Attempted(err)    => return Err(Rc::clone(err)),
+            State::Completed(rc, _)  => return Ok(Arc::clone(rc)),
+            State::Attempted(err)    => return Err(Arc::clone(err)),
         }
 
         // We need to temporarily set the state to Running in order to
@@ -175,20 +247,25 @@ impl Exec<RanCommand> {
         // Extract the variables we skipped over earlier
         let cmd = match old_state {
             State::Primed(cmd)          => cmd,
-            State::Completed(rc, None)  => return Ok(Rc::clone(&rc)),
+            State::Completed(rc, None)  => return Ok(Arc::clone(&rc)),
             _                           => unreachable!(),
         };
 
         // Then just set the state based on how running it goes
-        match executor.run_and_store(cmd) {
+        let result = match events {
+            Some(events)  => executor.run_and_stream(cmd, options, events),
+            None          => executor.run_and_store_with_options(cmd, options),
+        };
+
+        match result {
             Ok(ran_command) => {
-                let rc_t = Rc::clone(&ran_command);
+                let rc_t = Arc::clone(&ran_command);
                 *state = State::Completed(ran_command, None);
                 Ok(rc_t)
             }
             Err(e) => {
-                let rc = Rc::new(e);
-                *state = State::Attempted(Rc::clone(&rc));
+                let rc = Arc::new(e);
+                *state = State::Attempted(Arc::clone(&rc));
                 Err(rc)
             }
         }
@@ -198,14 +275,15 @@ impl Exec<RanCommand> {
 impl<T: fmt::Debug> Exec<T> {
 
     /// Return the inner Command, if any, that has been loaded into
-    /// this Exec. This is used when listing commands to the user.
-    pub fn into_command(self) -> Option<Command> {
+    /// this Exec, along with the options it would be run with. This is
+    /// used when listing commands to the user.
+    pub fn into_command(self) -> Option<(Command, RunOptions)> {
         debug!("Extracting command -> {:?}", self);
 
-        if let Self(Inner::Invocation(mutex)) = self {
+        if let Self(Inner::Invocation(mutex, options)) = self {
             let state = mutex.into_inner().unwrap();
             if let State::Primed(command) = state {
-                Some(command)
+                Some((command, options))
             }
             else {
                 warn!("Command not primed -> {:?}", state);
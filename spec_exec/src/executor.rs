@@ -1,15 +1,30 @@
 //! The executor, which actually runs commands.
 
-use std::process::{Command, Stdio, ExitStatus};
-use std::rc::Rc;
-use std::os::unix::process::ExitStatusExt;
+use std::collections::BTreeMap;
+#[cfg(feature = "async")]
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write, BufReader, BufRead};
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Child, Stdio, ExitStatus};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender};
 use std::time::{SystemTime, Instant, Duration};
-use std::thread::spawn as spawn_thread;
+use std::thread::{sleep, spawn as spawn_thread};
 
 use log::*;
 
+#[cfg(feature = "async")]
+use crate::async_executor::AsyncExecutor;
+use crate::cache::CommandCache;
 use crate::error::ExecError;
+use crate::interrupt;
+use crate::recording::Recording;
+use crate::target::Target;
 
 
 /// All commands are run through an **executor**, which not only
@@ -18,10 +33,46 @@ use crate::error::ExecError;
 #[derive(Debug)]
 pub struct Executor {
     command_history: CommandHistory,
+
+    /// The commands whose results have been used since the last time they
+    /// were taken, whether they were run or came from the cache, so they
+    /// can be attributed to the check that used them.
+    used_commands: Vec<Arc<RanCommand>>,
+
+    working_directory: Option<PathBuf>,
+    default_timeout: Option<Duration>,
+    target: Target,
+    replay: Option<Arc<Recording>>,
+    cache: Option<Arc<CommandCache>>,
+    sudo: bool,
+
+    #[cfg(feature = "async")]
+    async_backend: Option<Arc<AsyncExecutor>>,
+}
+
+/// A command that has been wrapped up ready to be run, along with the
+/// details of it that get kept with its results.
+struct WrappedCommand {
+    command: Command,
+    invocation: String,
+    environment: BTreeMap<String, String>,
+    directory: Option<PathBuf>,
+    with_sudo: bool,
+}
+
+impl WrappedCommand {
+
+    /// Returns the key that identical commands share in the cache. As well
+    /// as the invocation, this has to include where the command gets run
+    /// from and the options it gets run with, as the same invocation with
+    /// different input can produce different output.
+    fn cache_key(&self, options: &RunOptions) -> String {
+        format!("{} {:?} {:?}", self.invocation, self.directory, options)
+    }
 }
 
 #[derive(Debug)]
-struct CommandHistory(Vec<Rc<RanCommand>>);
+struct CommandHistory(Vec<Arc<RanCommand>>);
 
 impl Executor {
 
@@ -29,93 +80,819 @@ impl Executor {
     pub fn new() -> Self {
         Executor {
             command_history: CommandHistory(Vec::new()),
+            used_commands: Vec::new(),
+            working_directory: None,
+            default_timeout: None,
+            target: Target::Local,
+            replay: None,
+            cache: None,
+            sudo: false,
+
+            #[cfg(feature = "async")]
+            async_backend: None,
         }
     }
 
+    /// Sets whether commands that need to be run as root get run with
+    /// `sudo`. It gets run non-interactively, so it has to be able to run
+    /// them without asking for a password.
+    pub fn set_sudo(&mut self, sudo: bool) {
+        debug!("Setting sudo -> {}", sudo);
+        self.sudo = sudo;
+    }
+
+    /// Sets the cache that makes sure identical commands only get run once.
+    /// Executors forked from this one share it. When this is `None`, every
+    /// command gets run, even if an identical one has been run already.
+    pub fn set_cache(&mut self, cache: Option<Arc<CommandCache>>) {
+        self.cache = cache;
+    }
+
+    /// Sets the async executor that processes get run on. This is still
+    /// an executor that runs one command at a time, but the processes of
+    /// every executor sharing the async one get run on the same handful of
+    /// threads, with a limit on how many can be running at once. When this
+    /// is `None`, each process gets threads of its own to read its output.
+    #[cfg(feature = "async")]
+    pub fn set_async(&mut self, async_executor: Option<Arc<AsyncExecutor>>) {
+        debug!("Setting async executor -> {:?}", async_executor);
+        self.async_backend = async_executor;
+    }
+
+    /// Sets the recording to replay commands from. When this is set, no
+    /// processes get run at all: commands get their results from the
+    /// recording instead, and ones that aren’t in it fail.
+    pub fn set_replay(&mut self, recording: Option<Arc<Recording>>) {
+        debug!("Setting replay -> {}", recording.is_some());
+        self.replay = recording;
+    }
+
+    /// Sets the target that commands get run on.
+    pub fn set_target(&mut self, target: Target) {
+        debug!("Setting target -> {}", target);
+        self.target = target;
+    }
+
+    /// Sets the timeout that applies to commands run without a timeout of
+    /// their own. When this is `None`, such commands can run forever.
+    pub fn set_default_timeout(&mut self, timeout: Option<Duration>) {
+        debug!("Setting default timeout -> {:?}", timeout);
+        self.default_timeout = timeout;
+    }
+
+    /// Sets the directory that commands get run from. Commands that have
+    /// had their own directory set are unaffected. When this is `None`,
+    /// commands are run from the current directory of the process.
+    ///
+    /// Changing the directory of the executor, rather than the directory of
+    /// the entire process, means that other threads are unaffected.
+    pub fn set_working_directory(&mut self, directory: Option<PathBuf>) {
+        debug!("Setting working directory -> {:?}", directory);
+        self.working_directory = directory;
+    }
+
+    /// Returns the directory that commands get run from, if one has been set.
+    pub fn working_directory(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+
+    /// Returns the target that commands get run on.
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
     /// Runs the given Command and stores its results in the command history.
-    pub fn run_and_store(&mut self, mut command: Command) -> Result<Rc<RanCommand>, ExecError> {
-        use std::io::{BufReader, BufRead};
+    pub fn run_and_store(&mut self, command: Command) -> Result<Arc<RanCommand>, ExecError> {
+        self.run_and_store_with_options(command, &RunOptions::default())
+    }
 
-        // Set up the command I/O so we can read its output.
-        command.stdout(Stdio::piped());
-        command.stderr(Stdio::piped());
+    /// Runs the given Command, like `run_and_store`, but with the given
+    /// options. A process that gets killed because it ran for too long has
+    /// an exit reason of `TimedOut`, and the output it printed before then.
+    ///
+    /// Processes are run in a process group of their own, so any processes
+    /// they start get killed along with them, whether that’s because they
+    /// timed out or because specsheet got interrupted.
+    pub fn run_and_store_with_options(&mut self, command: Command, options: &RunOptions) -> Result<Arc<RanCommand>, ExecError> {
+        self.run_and_store_inner(command, options, None)
+    }
 
-        // Spawn the command and get its output pipes
-        info!("Spawning command -> {:?}", command);
-        let timer = Instant::now();
-        let mut child = match command.spawn() {
-            Ok(c) => c,
-            Err(e) => return Err(ExecError::Spawn(e)),
+    /// Runs the given Command, like `run_and_store_with_options`, but also
+    /// sends its lines of output down the given channel as soon as they are
+    /// read, rather than only once the process has exited. The process ID
+    /// gets sent first, once it has been spawned. A replayed command has
+    /// its recorded lines sent all at once.
+    pub fn run_and_stream(&mut self, command: Command, options: &RunOptions, events: Sender<StreamEvent>) -> Result<Arc<RanCommand>, ExecError> {
+        self.run_and_store_inner(command, options, Some(events))
+    }
+
+    /// Returns the command that would actually get run for the given
+    /// command and options, after being wrapped to run with sudo, under a
+    /// pseudo-terminal, or on the target, and being given the executor’s
+    /// directory. Nothing gets run.
+    pub fn resolve(&self, command: Command, options: &RunOptions) -> Command {
+        let mut command = self.wrap(command, options).command;
+        self.apply_working_directory(&mut command);
+        command
+    }
+
+    fn run_and_store_inner(&mut self, command: Command, options: &RunOptions, events: Option<Sender<StreamEvent>>) -> Result<Arc<RanCommand>, ExecError> {
+        let rc = self.run_or_reuse(command, options, events)?;
+        self.used_commands.push(Arc::clone(&rc));
+        Ok(rc)
+    }
+
+    fn run_or_reuse(&mut self, command: Command, options: &RunOptions, events: Option<Sender<StreamEvent>>) -> Result<Arc<RanCommand>, ExecError> {
+        let wrapped = self.wrap(command, options);
+
+        // Identical commands only get run once per cache, no matter which
+        // type of command primed them. Other threads running the same
+        // command wait for this one to finish, rather than running it too.
+        let cache = match &self.cache {
+            Some(cache)  => Arc::clone(cache),
+            None         => return self.run_wrapped(wrapped, options, events),
         };
 
-        let stdout = BufReader::new(child.stdout.take().unwrap());
-        let stderr = BufReader::new(child.stderr.take().unwrap());
-
-        // I had loads of trouble reading from stdout and stderr at the
-        // same time. Then I had even more trouble reading into a Vec
-        // from another thread, so I just loaded up on wrapping types
-        // until it compiled. Rust™
-        let mut stdout_lines = Vec::new();
-        let stderr_lines_tmp = Arc::new(Mutex::new(Vec::new()));
-
-        let tmp2 = Arc::clone(&stderr_lines_tmp);
-        let thread = spawn_thread(move || {
-            let mut tmp = tmp2.lock().unwrap();
-            for line in stderr.lines() {
-                let line = line.unwrap();  // this is not the same as the one below!
-                tmp.push((SystemTime::now(), line));
+        let key = wrapped.cache_key(options);
+        let slot = cache.slot(&key);
+        let mut slot = slot.lock().unwrap();
+        if let Some(rc) = &*slot {
+            debug!("Using cached result -> {}", wrapped.invocation);
+            send_lines(events.as_ref(), rc);
+            return Ok(Arc::clone(rc));
+        }
+
+        // Results from an earlier run get stored in the history as though
+        // they had been run in this one. Commands being replayed have
+        // results already, so they don’t need any more.
+        let use_store = self.replay.is_none();
+        let stored = if use_store { cache.load(&key) } else { None };
+        if let Some(rc) = stored {
+            info!("Using stored result -> {}", wrapped.invocation);
+            let rc = self.command_history.store(rc);
+            send_lines(events.as_ref(), &rc);
+            *slot = Some(Arc::clone(&rc));
+            return Ok(rc);
+        }
+
+        let rc = self.run_wrapped(wrapped, options, events)?;
+        if use_store && rc.exit_reason != ExitReason::TimedOut {
+            cache.save(&key, &rc);
+        }
+
+        *slot = Some(Arc::clone(&rc));
+        Ok(rc)
+    }
+
+    /// Wraps the command up ready to be run, capturing the details of it
+    /// that get kept with its results along the way.
+    fn wrap(&self, command: Command, options: &RunOptions) -> WrappedCommand {
+
+        // The environment and directory get captured before the command
+        // gets wrapped, as that moves them onto the wrapper.
+        let mut command = command;
+        let directory = self.resolve_directory(&mut command);
+        let environment = command_environment(&command);
+
+        // Commands that need root get run with sudo, on the target.
+        let with_sudo = self.sudo && options.needs_root;
+        let command = if with_sudo { wrap_in_sudo(&command) } else { command };
+        let command = if options.pty { wrap_in_pty(&command) } else { command };
+
+        // Wrap the command so it runs on the target, which has to happen
+        // before the executor’s own directory gets applied, as that’s a
+        // directory on this machine.
+        let command = self.target.wrap(command, options.stdin.is_some());
+
+        // The invocation gets captured before the executor’s directory is
+        // applied, so it stays the same wherever specsheet is run from, and
+        // commands can be matched up with their recorded results.
+        let invocation = format!("{:?}", command);
+        WrappedCommand { command, invocation, environment, directory, with_sudo }
+    }
+
+    /// Makes the command run from the executor’s directory, unless it
+    /// already has one of its own.
+    fn apply_working_directory(&self, command: &mut Command) {
+        if let Some(directory) = &self.working_directory {
+            if command.get_current_dir().is_none() {
+                command.current_dir(directory);
             }
-        });
+        }
+    }
+
+    /// Runs a command that has been wrapped up ready to be run, either by
+    /// spawning a process or by replaying its recorded result.
+    fn run_wrapped(&mut self, wrapped: WrappedCommand, options: &RunOptions, events: Option<Sender<StreamEvent>>) -> Result<Arc<RanCommand>, ExecError> {
+        let WrappedCommand { mut command, invocation, environment, directory, with_sudo } = wrapped;
 
-        for line in stdout.lines() {
-            let rc = Rc::from(line.map_err(ExecError::Stdout)?);
-            stdout_lines.push(OutputLine { timestamp: SystemTime::now(), line: rc });
+        if self.replay.is_some() {
+            return self.replay(invocation, events.as_ref());
         }
 
-        thread.join().unwrap();
+        // Don’t start anything new once specsheet has been interrupted.
+        if interrupt::is_interrupted() {
+            return Err(ExecError::Interrupted);
+        }
 
-        let stderr_lines = Arc::try_unwrap(stderr_lines_tmp).unwrap().into_inner().unwrap().into_iter().map(|(timestamp, line)| {   // ugh
-            let rc = Rc::from(line);
-            OutputLine { timestamp, line: rc }
-        }).collect::<Vec<_>>();
+        self.apply_working_directory(&mut command);
+        set_up_io(&mut command, options)?;
 
-        // Wait until the process has finished executing, and measure how long
-        // it took to run
-        let exit = child.wait().map_err(ExecError::Wait)?;
-        let runtime = timer.elapsed();
-        debug!("Command complete in -> {:?}", runtime);
+        let timeout = options.timeout.or(self.default_timeout);
+        command.process_group(0);
+
+        info!("Spawning command -> {:?}", command);
+        let finished = self.run_process(command, options, timeout, events)?;
+
+        // A process that got killed because specsheet was interrupted
+        // doesn’t count as having exited for a reason of its own.
+        if interrupt::is_interrupted() {
+            return Err(ExecError::Interrupted);
+        }
 
         // Store the command results in the history
-        let rc = self.command_history.store(RanCommand {
-            invocation: format!("{:?}", command),
-            exit_reason: ExitReason::from(exit),
-            stdout_lines, stderr_lines, runtime,
-        });
+        let rc = self.command_history.store(finished.into_ran_command(invocation, environment, directory));
+
+        // sudo complains and exits with status 1 if it can’t run the
+        // command without a password, which would otherwise look like the
+        // command itself failing.
+        if with_sudo && rc.exit_reason == ExitReason::Status(1) {
+            if let Some(line) = rc.stderr_lines.iter().find(|l| l.line.starts_with("sudo: ")) {
+                return Err(ExecError::SudoUnavailable(line.line.to_string()));
+            }
+        }
 
         // Finally, return the shared reference to the result
         Ok(rc)
     }
 
+    /// Runs the process, on the async executor if there is one.
+    #[cfg(feature = "async")]
+    fn run_process(&self, command: Command, options: &RunOptions, timeout: Option<Duration>, events: Option<Sender<StreamEvent>>) -> Result<Finished, ExecError> {
+        match &self.async_backend {
+            Some(async_executor)  => async_executor.block_on_process(command, options, timeout, events),
+            None                  => run_process(command, options, timeout, events),
+        }
+    }
+
+    /// Runs the process.
+    #[cfg(not(feature = "async"))]
+    #[allow(clippy::unused_self)]
+    fn run_process(&self, command: Command, options: &RunOptions, timeout: Option<Duration>, events: Option<Sender<StreamEvent>>) -> Result<Finished, ExecError> {
+        run_process(command, options, timeout, events)
+    }
+
+    /// Runs all the given commands at once, as tasks on the async executor,
+    /// and puts their results in the cache, where the checks that run them
+    /// later will find them. This is how hundreds of checks can have their
+    /// commands run at the same time without a thread for each one.
+    ///
+    /// Commands that have results already, from the cache or a recording,
+    /// are left alone, as are commands run with sudo, which has to be able
+    /// to complain when it can’t run them. Any that fail to run get left for
+    /// the checks to run again and report the error themselves.
+    #[cfg(feature = "async")]
+    pub fn prefetch(&mut self, commands: impl IntoIterator<Item=(Command, RunOptions)>) {
+        let (Some(async_executor), Some(cache)) = (self.async_backend.clone(), self.cache.clone()) else {
+            return;
+        };
+
+        if self.replay.is_some() {
+            return;
+        }
+
+        let mut details = Vec::new();
+        let mut processes = Vec::new();
+        let mut keys = BTreeSet::new();
+
+        for (command, options) in commands {
+            let wrapped = self.wrap(command, &options);
+            let key = wrapped.cache_key(&options);
+            if wrapped.with_sudo || ! keys.insert(key.clone()) || cache.slot(&key).lock().unwrap().is_some() {
+                continue;
+            }
+
+            if let Some(rc) = cache.load(&key) {
+                info!("Using stored result -> {}", wrapped.invocation);
+                *cache.slot(&key).lock().unwrap() = Some(self.command_history.store(rc));
+                continue;
+            }
+
+            let WrappedCommand { mut command, invocation, environment, directory, .. } = wrapped;
+
+            self.apply_working_directory(&mut command);
+            if let Err(e) = set_up_io(&mut command, &options) {
+                debug!("Not prefetching command -> {}", e);
+                continue;
+            }
+
+            let timeout = options.timeout.or(self.default_timeout);
+            command.process_group(0);
+
+            details.push((key, invocation, environment, directory));
+            processes.push((command, options, timeout));
+        }
+
+        if processes.is_empty() {
+            return;
+        }
+
+        info!("Prefetching {} commands", processes.len());
+        let results = async_executor.block_on_processes(processes);
+
+        for ((key, invocation, environment, directory), finished) in details.into_iter().zip(results) {
+            if interrupt::is_interrupted() {
+                return;
+            }
+
+            let finished = match finished {
+                Ok(f)   => f,
+                Err(e)  => { debug!("Error prefetching command -> {}", e); continue; }
+            };
+
+            let rc = self.command_history.store(finished.into_ran_command(invocation, environment, directory));
+            if rc.exit_reason != ExitReason::TimedOut {
+                cache.save(&key, &rc);
+            }
+
+            *cache.slot(&key).lock().unwrap() = Some(rc);
+        }
+    }
+
+    /// Takes the result of the command with the given invocation from the
+    /// recording being replayed, and stores it as though it had been run.
+    fn replay(&mut self, invocation: String, events: Option<&Sender<StreamEvent>>) -> Result<Arc<RanCommand>, ExecError> {
+        let recording = self.replay.as_ref().expect("No recording");
+        let Some(rc) = recording.take(&invocation) else {
+            return Err(ExecError::NotRecorded(invocation));
+        };
+
+        info!("Replaying command -> {}", invocation);
+        send_lines(events, &rc);
+
+        self.command_history.0.push(Arc::clone(&rc));
+        Ok(rc)
+    }
+
+    /// Works out which directory the given command is going to be run from.
+    /// A command with a relative directory of its own gets run from there
+    /// relative to the executor’s directory, unless it gets run somewhere
+    /// else, where the executor’s directory means nothing.
+    fn resolve_directory(&self, command: &mut Command) -> Option<PathBuf> {
+        if let (Target::Local, Some(working_directory)) = (&self.target, &self.working_directory) {
+            if let Some(directory) = command.get_current_dir().filter(|d| d.is_relative()) {
+                let directory = working_directory.join(directory);
+                command.current_dir(directory);
+            }
+        }
+
+        command.get_current_dir().map(Path::to_path_buf)
+               .or_else(|| self.working_directory.clone())
+    }
+
+    /// Creates a new executor with an empty command history, which runs
+    /// commands from the same directory as this one. Each thread that runs
+    /// checks in parallel gets an executor of its own.
+    #[must_use]
+    pub fn fork(&self) -> Self {
+        Executor {
+            command_history: CommandHistory(Vec::new()),
+            used_commands: Vec::new(),
+            working_directory: self.working_directory.clone(),
+            default_timeout: self.default_timeout,
+            target: self.target.clone(),
+            replay: self.replay.clone(),
+            cache: self.cache.clone(),
+            sudo: self.sudo,
+
+            #[cfg(feature = "async")]
+            async_backend: self.async_backend.clone(),
+        }
+    }
+
+    /// Moves the command history of another executor, such as one created
+    /// with `fork`, onto the end of this one’s.
+    pub fn absorb(&mut self, other: Self) {
+        self.command_history.0.extend(other.command_history.0);
+    }
+
+    /// Returns the commands whose results have been used since the last
+    /// time this was called, in the order they were used. Commands that
+    /// came from the cache are included, as well as ones that got run.
+    pub fn take_used_commands(&mut self) -> Vec<Arc<RanCommand>> {
+        mem::take(&mut self.used_commands)
+    }
+
     /// Returns a list of references to the commands that have been run.
     /// This data is used to populate the result documents.
     pub fn to_commands(&self) -> impl Iterator<Item=&RanCommand> {
         self.command_history.0.iter()
-            .map(|rc| Rc::as_ref(rc))
+            .map(|rc| Arc::as_ref(rc))
     }
 }
 
+/// Options that affect how a command gets run.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct RunOptions {
+
+    /// How long the process is allowed to run for before it gets killed.
+    pub timeout: Option<Duration>,
+
+    /// What to feed into the process’s standard input stream. When this is
+    /// `None`, the process inherits ours.
+    pub stdin: Option<Input>,
+
+    /// Whether the process needs to be run as root to work, such as `ufw`.
+    /// These get run with `sudo` if the executor has been told to use it.
+    pub needs_root: bool,
+
+    /// Whether the process gets run under a pseudo-terminal, for programs
+    /// that behave differently when their output isn’t a terminal. Both
+    /// its output streams get merged into its standard output stream.
+    pub pty: bool,
+}
+
+/// Something that gets fed into a process’s standard input stream.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Input {
+
+    /// These bytes get written to the process’s input, which then gets
+    /// closed.
+    Bytes(Vec<u8>),
+
+    /// The process reads its input from the file at this path.
+    File(PathBuf),
+}
+
 impl CommandHistory {
 
     /// Stores the command we’ve just run in the history, and returns a
     /// reference to it.
-    fn store(&mut self, ran_command: RanCommand) -> Rc<RanCommand> {
-        let rc = Rc::new(ran_command);
-        self.0.push(Rc::clone(&rc));
+    fn store(&mut self, ran_command: RanCommand) -> Arc<RanCommand> {
+        let rc = Arc::new(ran_command);
+        self.0.push(Arc::clone(&rc));
         rc
     }
 }
 
 
+/// Sends the lines of output of a command that has already been run down
+/// the channel of events, if there is one, all at once.
+fn send_lines(events: Option<&Sender<StreamEvent>>, ran_command: &RanCommand) {
+    if let Some(events) = events {
+        for (stream, line) in &ran_command.output_lines {
+            let _ = events.send(StreamEvent::Line(*stream, line.clone()));
+        }
+    }
+}
+
+/// Spawns the process, reads its output from a thread for each stream,
+/// and waits for it to exit or for its timeout to elapse, whichever comes
+/// first.
+fn run_process(mut command: Command, options: &RunOptions, timeout: Option<Duration>, events: Option<Sender<StreamEvent>>) -> Result<Finished, ExecError> {
+    let timer = Instant::now();
+    let mut child = command.spawn().map_err(ExecError::Spawn)?;
+    interrupt::register_group(child.id());
+
+    if let Some(events) = &events {
+        let _ = events.send(StreamEvent::Started(child.id()));
+    }
+
+    // Both output streams get read from their own threads, so this thread
+    // is free to keep an eye on the clock. Each thread reports back over
+    // the channel once its stream has been closed.
+    let (done_tx, done_rx) = mpsc::channel();
+    let output_tmp = SharedLines::default();
+    let stdout_tmp = read_stream(child.stdout.take().unwrap(), OutputStream::Stdout, &output_tmp, events.clone(), done_tx.clone());
+    let stderr_tmp = read_stream(child.stderr.take().unwrap(), OutputStream::Stderr, &output_tmp, events, done_tx);
+
+    // The input gets written from its own thread too, so a process that
+    // produces a lot of output before reading all its input can’t get
+    // stuck. The process is free to exit without reading everything.
+    if let Some(Input::Bytes(bytes)) = &options.stdin {
+        let mut stdin = child.stdin.take().unwrap();
+        let bytes = bytes.clone();
+        spawn_thread(move || {
+            if let Err(e) = stdin.write_all(&bytes) {
+                debug!("Error writing to process stdin: {}", e);
+            }
+        });
+    }
+
+    // Wait until the process has finished executing, and measure how long
+    // it took to run
+    let exit_reason = match timeout {
+        Some(limit)  => wait_with_timeout(&mut child, timer, limit),
+        None         => child.wait().map(ExitReason::from).map_err(ExecError::Wait),
+    };
+    let runtime = timer.elapsed();
+    debug!("Command complete in -> {:?}", runtime);
+
+    interrupt::unregister_group(child.id());
+    let exit_reason = exit_reason?;
+
+    // Wait for the reading threads to reach the end of their streams. A
+    // process that was killed may have left children of its own holding
+    // the pipes open, so in that case, only wait for a little while and
+    // use whatever output has been read by then.
+    for _ in 0 .. 2 {
+        let result = if exit_reason == ExitReason::TimedOut {
+            if let Ok(result) = done_rx.recv_timeout(READ_GRACE_PERIOD) {
+                result
+            }
+            else {
+                warn!("Output streams still open after timeout");
+                break;
+            }
+        }
+        else {
+            done_rx.recv().unwrap()
+        };
+
+        result.map_err(ExecError::Stdout)?;
+    }
+
+    Ok(Finished::take(exit_reason, runtime, &stdout_tmp, &stderr_tmp, &output_tmp))
+}
+
+/// Sets up the command’s I/O so we can read its output, and so we can write
+/// its input if there is any.
+pub(crate) fn set_up_io(command: &mut Command, options: &RunOptions) -> Result<(), ExecError> {
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+
+    match &options.stdin {
+        Some(Input::Bytes(_)) => {
+            command.stdin(Stdio::piped());
+        }
+        Some(Input::File(path)) => {
+            // Relative paths are looked up from the directory the command
+            // runs from, rather than the one specsheet was run from.
+            let path = match command.get_current_dir() {
+                Some(directory)  => directory.join(path),
+                None             => path.clone(),
+            };
+
+            let file = File::open(path).map_err(ExecError::Stdin)?;
+            command.stdin(file);
+        }
+        None if options.pty => {
+            // The program that allocates the terminal puts its own input
+            // into raw mode if it’s a terminal, which would be ours.
+            command.stdin(Stdio::null());
+        }
+        None => {/* inherit it */}
+    }
+
+    Ok(())
+}
+
+/// Returns the environment variables that have been set on the given
+/// command. Variables that have been removed from it are left out, as
+/// are ones that aren’t valid UTF-8.
+pub(crate) fn command_environment(command: &Command) -> BTreeMap<String, String> {
+    command.get_envs()
+           .filter_map(|(key, value)| Some((key.to_str()?.into(), value?.to_str()?.into())))
+           .collect()
+}
+
+/// Turns the given command into one that runs it with `sudo`, without
+/// letting it prompt for a password. As `sudo` clears the environment,
+/// any variables set on the command get passed through `env`.
+fn wrap_in_sudo(command: &Command) -> Command {
+    let mut sudo = Command::new("sudo");
+    sudo.arg("-n").arg("--");
+
+    if let Some(directory) = command.get_current_dir() {
+        sudo.current_dir(directory);
+    }
+
+    let envs = command.get_envs().filter_map(|(k, v)| Some((k, v?))).collect::<Vec<_>>();
+    if ! envs.is_empty() {
+        sudo.arg("env");
+        for (key, value) in envs {
+            let mut pair = key.to_os_string();
+            pair.push("=");
+            pair.push(value);
+            sudo.arg(pair);
+        }
+    }
+
+    sudo.arg(command.get_program());
+    sudo.args(command.get_args());
+    sudo
+}
+
+
+/// Turns the given command into one that runs it under a pseudo-terminal,
+/// using `script`, which passes its input and output through. The terminal
+/// echoes back any input the process gets fed, and ends its lines of
+/// output with `\r\n`, which gets dealt with when they are read.
+fn wrap_in_pty(command: &Command) -> Command {
+    let mut script = Command::new("script");
+
+    if cfg!(target_os = "macos") {
+        script.arg("-q").arg("/dev/null");
+        script.arg(command.get_program());
+        script.args(command.get_args());
+    }
+    else {
+        let words = iter::once(command.get_program()).chain(command.get_args())
+                        .map(shell_quote)
+                        .collect::<Vec<_>>();
+
+        script.arg("--quiet").arg("--return");
+        script.arg("--command").arg(words.join(" "));
+        script.arg("/dev/null");
+    }
+
+    if let Some(directory) = command.get_current_dir() {
+        script.current_dir(directory);
+    }
+
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value)  => { script.env(key, value); }
+            None         => { script.env_remove(key); }
+        }
+    }
+
+    script
+}
+
+/// Quotes an argument so that `sh` treats it as a single word, however many
+/// special characters it contains.
+fn shell_quote(argument: &OsStr) -> String {
+    format!("'{}'", argument.to_string_lossy().replace('\'', "'\\''"))
+}
+
+
+/// How often to check whether a process with a timeout has exited yet.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to wait for the output streams of a process that timed out to
+/// be closed before giving up on them.
+pub(crate) const READ_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// What has been read from one of a process’s output streams, shared with
+/// the thread doing the reading.
+pub(crate) type SharedStream = Arc<Mutex<ReadStream>>;
+
+/// The lines read from both of a process’s output streams, in the order
+/// they were read, shared with both threads doing the reading.
+pub(crate) type SharedLines = Arc<Mutex<Vec<(OutputStream, OutputLine)>>>;
+
+/// The output of a stream, both as timestamped lines of text and as the
+/// exact bytes that were read.
+#[derive(Default)]
+pub(crate) struct ReadStream {
+    lines: Vec<OutputLine>,
+    bytes: Vec<u8>,
+}
+
+/// Spawns a thread that reads lines from the given stream until it gets
+/// closed, timestamping each one, then sends the result down the channel.
+/// Lines that are not valid UTF-8 have their invalid parts replaced, but
+/// the bytes are kept exactly as they were read.
+///
+/// Each line also gets added to the lines shared with the thread reading
+/// the other stream as soon as it’s read, so they end up in the order the
+/// process wrote them in (as near as we can tell), and gets sent down the
+/// channel of events, if there is one. Nobody listening any more is fine.
+///
+/// I had loads of trouble reading from stdout and stderr at the same time.
+/// Then I had even more trouble reading into a Vec from another thread, so
+/// I just loaded up on wrapping types until it compiled. Rust™
+fn read_stream(stream: impl Read + Send + 'static, which: OutputStream, both: &SharedLines, events: Option<Sender<StreamEvent>>, done: Sender<io::Result<()>>) -> SharedStream {
+    let read = Arc::new(Mutex::new(ReadStream::default()));
+
+    let tmp = Arc::clone(&read);
+    let both = Arc::clone(both);
+    spawn_thread(move || {
+        let mut reader = BufReader::new(stream);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => {
+                    break;
+                }
+                Ok(_) => {
+                    record_line(&buf, which, &tmp, &both, events.as_ref());
+                }
+                Err(e) => {
+                    let _ = done.send(Err(e));
+                    return;
+                }
+            }
+        }
+
+        let _ = done.send(Ok(()));
+    });
+
+    read
+}
+
+/// Adds a line that has just been read from one of a process’s output
+/// streams, including its line ending, to what has been read from that
+/// stream and from both streams, and sends it down the channel of events,
+/// if there is one. Nobody listening any more is fine.
+pub(crate) fn record_line(buf: &[u8], which: OutputStream, read: &SharedStream, both: &SharedLines, events: Option<&Sender<StreamEvent>>) {
+    let mut read = read.lock().unwrap();
+    read.bytes.extend_from_slice(buf);
+
+    let line = buf.strip_suffix(b"\n").unwrap_or(buf);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = OutputLine { timestamp: SystemTime::now(), line: Arc::from(String::from_utf8_lossy(line)) };
+
+    both.lock().unwrap().push((which, line.clone()));
+    if let Some(events) = events {
+        let _ = events.send(StreamEvent::Line(which, line.clone()));
+    }
+    read.lines.push(line);
+}
+
+/// Takes the lines and bytes that have been read so far out of the shared
+/// stream.
+fn take_stream(stream: &SharedStream) -> (Vec<OutputLine>, Vec<u8>) {
+    let ReadStream { lines, bytes } = mem::take(&mut *stream.lock().unwrap());
+    (lines, bytes)
+}
+
+/// Everything about a process that has finished running, apart from the
+/// details of how it was run.
+pub(crate) struct Finished {
+    pub(crate) exit_reason: ExitReason,
+    pub(crate) stdout_lines: Vec<OutputLine>,
+    pub(crate) stderr_lines: Vec<OutputLine>,
+    pub(crate) stdout_raw: Vec<u8>,
+    pub(crate) stderr_raw: Vec<u8>,
+    pub(crate) output_lines: Vec<(OutputStream, OutputLine)>,
+    pub(crate) runtime: Duration,
+}
+
+impl Finished {
+
+    /// Takes whatever has been read from the process’s output streams so
+    /// far, now that it has exited.
+    pub(crate) fn take(exit_reason: ExitReason, runtime: Duration, stdout: &SharedStream, stderr: &SharedStream, both: &SharedLines) -> Self {
+        let (stdout_lines, stdout_raw) = take_stream(stdout);
+        let (stderr_lines, stderr_raw) = take_stream(stderr);
+        let output_lines = mem::take(&mut *both.lock().unwrap());
+        Self { exit_reason, stdout_lines, stderr_lines, stdout_raw, stderr_raw, output_lines, runtime }
+    }
+
+    /// Turns the results into those of the command with the given details.
+    pub(crate) fn into_ran_command(self, invocation: String, environment: BTreeMap<String, String>, directory: Option<PathBuf>) -> RanCommand {
+        let Self { exit_reason, stdout_lines, stderr_lines, stdout_raw, stderr_raw, output_lines, runtime } = self;
+        RanCommand {
+            invocation,
+            environment,
+            directory,
+            exit_reason,
+            stdout_lines, stderr_lines,
+            stdout_raw, stderr_raw,
+            output_lines,
+            runtime,
+        }
+    }
+}
+
+/// Waits for the child process to exit, killing it if it is still running
+/// once the limit has elapsed since the timer was started.
+fn wait_with_timeout(child: &mut Child, timer: Instant, limit: Duration) -> Result<ExitReason, ExecError> {
+    loop {
+        if let Some(exit) = child.try_wait().map_err(ExecError::Wait)? {
+            return Ok(ExitReason::from(exit));
+        }
+
+        if timer.elapsed() >= limit {
+            warn!("Process timed out after {:?}; killing it", limit);
+            kill_process_group(child);
+
+            child.wait().map_err(ExecError::Wait)?;
+            return Ok(ExitReason::TimedOut);
+        }
+
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// Kills every process in the child’s process group, which includes the
+/// child itself and any processes it has started, falling back to killing
+/// just the child if that fails.
+fn kill_process_group(child: &mut Child) {
+    if interrupt::kill_process_group(child.id()) {
+        return;
+    }
+
+    if let Err(e) = child.kill() {
+        warn!("Error killing process: {}", e);
+    }
+}
+
+
 /// The results of a command that has been executed.
 #[derive(Debug)]
 pub struct RanCommand {
@@ -123,7 +900,13 @@ pub struct RanCommand {
     /// The shell that it was executed with.
     pub invocation: String,
 
-    // todo: also store the environment and directory
+    /// The environment variables that were set for the process, on top of
+    /// the ones it inherited from specsheet.
+    pub environment: BTreeMap<String, String>,
+
+    /// The directory the process was run from, or `None` if it was run
+    /// from specsheet’s own current directory.
+    pub directory: Option<PathBuf>,
 
     /// The reason the process exited.
     pub exit_reason: ExitReason,
@@ -134,6 +917,16 @@ pub struct RanCommand {
     /// The process’s lines of standard error, timestamped.
     pub stderr_lines: Vec<OutputLine>,
 
+    /// The exact bytes the process wrote to its standard output stream.
+    pub stdout_raw: Vec<u8>,
+
+    /// The exact bytes the process wrote to its standard error stream.
+    pub stderr_raw: Vec<u8>,
+
+    /// The lines of both the process’s output streams, merged together in
+    /// the order they were read.
+    pub output_lines: Vec<(OutputStream, OutputLine)>,
+
     /// The amount of time the process took to run.
     pub runtime: Duration,
 }
@@ -142,33 +935,49 @@ impl RanCommand {
 
     /// Returns the list of output lines, as untimestamped strings, from
     /// the completed process.
-    pub fn stdout_lines(&self) -> Vec<Rc<str>> {
+    pub fn stdout_lines(&self) -> Vec<Arc<str>> {
         self.stdout_lines.iter()
-            .map(|e| Rc::clone(&e.line))
+            .map(|e| Arc::clone(&e.line))
             .collect()
     }
 
     /// Returns the bytes of the completed process’s standard output stream,
-    /// albeit after UTF-8 encoding and decoding.
+    /// exactly as they were written.
     pub fn stdout_bytes(&self) -> Vec<u8> {
-        let mut v = Vec::new();
-        for line in &self.stdout_lines {
-            v.extend(line.line.as_bytes());
-            v.extend(b"\n");
-        }
-        v
+        self.stdout_raw.clone()
     }
 
     /// Returns the bytes of the completed process’s standard error stream,
-    /// albeit after UTF-8 encoding and decoding.
+    /// exactly as they were written.
     pub fn stderr_bytes(&self) -> Vec<u8> {
+        self.stderr_raw.clone()
+    }
+
+    /// Returns the bytes of both the completed process’s output streams,
+    /// interleaved in the order their lines were read.
+    ///
+    /// As the two streams have to be split into lines to be interleaved,
+    /// these get rebuilt from the lines, rather than being the exact bytes.
+    pub fn output_bytes(&self) -> Vec<u8> {
         let mut v = Vec::new();
-        for line in &self.stderr_lines {
+        for (_, line) in &self.output_lines {
             v.extend(line.line.as_bytes());
             v.extend(b"\n");
         }
         v
     }
+
+    /// Returns the text of both the completed process’s output streams,
+    /// interleaved in the order their lines were read, for showing to the
+    /// user when a check fails.
+    pub fn output_text(&self) -> String {
+        let mut text = String::new();
+        for (_, line) in &self.output_lines {
+            text.push_str(&line.line);
+            text.push('\n');
+        }
+        text
+    }
 }
 
 
@@ -180,7 +989,30 @@ pub struct OutputLine {
     pub timestamp: SystemTime,
 
     /// The text that was read.
-    pub line: Rc<str>,
+    pub line: Arc<str>,
+}
+
+/// Something that happens while a process is running, sent as it happens
+/// to anything listening to the process’s output.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+
+    /// The process was spawned, and has this process ID.
+    Started(u32),
+
+    /// The process wrote this line to one of its output streams.
+    Line(OutputStream, OutputLine),
+}
+
+/// One of the streams that a process writes its output to.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum OutputStream {
+
+    /// The standard output stream.
+    Stdout,
+
+    /// The standard error stream.
+    Stderr,
 }
 
 /// The reason a process exited.
@@ -199,6 +1031,10 @@ pub enum ExitReason {
     /// The process did not run at all; its output was overridden, and we can
     /// assume that an overridden command ‘succeeded’.
     Overridden,
+
+    /// The process was still running when its timeout elapsed, so it was
+    /// killed.
+    TimedOut,
 }
 
 impl From<ExitStatus> for ExitReason {
@@ -246,3 +1082,126 @@ impl ExitReason {
         }
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::exec::{CommandOutput, Exec};
+
+    #[test]
+    fn relative_stdin_file_from_working_directory() {
+        let directory = std::env::temp_dir().join(format!("specsheet-stdin-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("input.txt"), "hello\n").unwrap();
+
+        let mut executor = Executor::new();
+        executor.set_working_directory(Some(directory.clone()));
+
+        let options = RunOptions { stdin: Some(Input::File(PathBuf::from("input.txt"))), ..RunOptions::default() };
+        let result = executor.run_and_store_with_options(Command::new("cat"), &options);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let ran_command = result.unwrap();
+        assert_eq!(ran_command.exit_reason, ExitReason::Status(0));
+        assert_eq!(ran_command.stdout_bytes(), b"hello\n");
+    }
+
+    fn shell(script: &str) -> Arc<RanCommand> {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        Executor::new().run_and_store(command).unwrap()
+    }
+
+    #[test]
+    fn raw_bytes_kept_exactly() {
+        let ran_command = shell("printf 'a\\0b'; printf 'c\\r\\n' >&2");
+        assert_eq!(ran_command.stdout_bytes(), b"a\0b");
+        assert_eq!(ran_command.stderr_bytes(), b"c\r\n");
+    }
+
+    #[test]
+    fn merged_output_keeps_order() {
+        let ran_command = shell("echo one; sleep 0.1; echo two >&2; sleep 0.1; echo three; sleep 0.1; echo four >&2");
+
+        let streams = ran_command.output_lines.iter().map(|(stream, line)| (*stream, &*line.line)).collect::<Vec<_>>();
+        assert_eq!(streams, vec![
+            (OutputStream::Stdout, "one"),
+            (OutputStream::Stderr, "two"),
+            (OutputStream::Stdout, "three"),
+            (OutputStream::Stderr, "four"),
+        ]);
+
+        assert_eq!(ran_command.output_bytes(), b"one\ntwo\nthree\nfour\n");
+    }
+
+    /// The lines a command printed.
+    struct Lines(Vec<Arc<str>>);
+
+    impl CommandOutput for Lines {
+        fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+            exit_reason.should_be(0)?;
+            Ok(Self(lines))
+        }
+    }
+
+    /// How many lines a command printed.
+    struct LineCount(usize);
+
+    impl CommandOutput for LineCount {
+        fn interpret_command_output(lines: Vec<Arc<str>>, exit_reason: ExitReason) -> Result<Self, ExecError> {
+            exit_reason.should_be(0)?;
+            Ok(Self(lines.len()))
+        }
+    }
+
+    #[test]
+    fn identical_commands_run_once() {
+        let counter = std::env::temp_dir().join(format!("specsheet-cache-test-{}", std::process::id()));
+        let command = || {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(format!("echo >> {}; echo one; echo two", counter.display()));
+            command
+        };
+
+        let mut executor = Executor::new();
+        executor.set_cache(Some(Arc::new(CommandCache::new())));
+
+        let lines = Exec::<Lines>::actual(command()).run(&mut executor).unwrap();
+        let line_count = Exec::<LineCount>::actual(command()).run(&mut executor).unwrap();
+        let run_count = std::fs::read_to_string(&counter).unwrap().lines().count();
+        std::fs::remove_file(&counter).unwrap();
+
+        assert_eq!(run_count, 1);
+        assert_eq!(lines.0, vec![ Arc::from("one"), Arc::from("two") ]);
+        assert_eq!(line_count.0, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn prefetched_commands_not_run_again() {
+        let counter = std::env::temp_dir().join(format!("specsheet-prefetch-test-{}", std::process::id()));
+        let command = |i: usize| {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(format!("sleep 0.2; echo >> {}; echo {}", counter.display(), i));
+            command
+        };
+
+        let mut executor = Executor::new();
+        executor.set_cache(Some(Arc::new(CommandCache::new())));
+        executor.set_async(Some(Arc::new(AsyncExecutor::new(4).unwrap())));
+
+        let timer = Instant::now();
+        executor.prefetch((0 .. 4).map(|i| (command(i), RunOptions::default())));
+        let elapsed = timer.elapsed();
+
+        let lines = (0 .. 4).map(|i| Exec::<Lines>::actual(command(i)).run(&mut executor).unwrap().0.clone()).collect::<Vec<_>>();
+        let run_count = std::fs::read_to_string(&counter).unwrap().lines().count();
+        std::fs::remove_file(&counter).unwrap();
+
+        assert!(elapsed < Duration::from_millis(600), "Commands were not run at once: {:?}", elapsed);
+        assert_eq!(run_count, 4);
+        assert_eq!(lines, (0 .. 4).map(|i| vec![ Arc::from(i.to_string()) ]).collect::<Vec<_>>());
+        assert_eq!(executor.to_commands().count(), 4);
+    }
+}
@@ -0,0 +1,89 @@
+//! Keeping track of running processes, so they can be cleaned up when
+//! specsheet gets interrupted.
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::io;
+use std::sync::{Mutex, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::*;
+
+
+/// Whether specsheet has been interrupted.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The process groups of every process that is currently running. Each
+/// process gets run in a group of its own, which means that it won’t get
+/// sent the terminal’s signals, so we have to pass them on ourselves.
+static RUNNING_GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+
+/// Marks specsheet as having been interrupted, and kills every process that
+/// is running, along with any processes they have started. No more
+/// processes get run after this has been called.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    // The groups get copied out so the lock isn’t held while they get
+    // killed, which would stop other threads from registering theirs.
+    let groups = RUNNING_GROUPS.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    info!("Interrupted; killing {} process groups", groups.len());
+    for group in groups {
+        kill_process_group(group);
+    }
+}
+
+/// Returns whether specsheet has been interrupted, in which case it should
+/// stop running checks as soon as it can.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Stores the process group of a process that has just been started, so
+/// it gets killed if specsheet gets interrupted. A process that gets started
+/// just as it gets interrupted is killed straight away.
+pub(crate) fn register_group(group: u32) {
+    RUNNING_GROUPS.lock().unwrap().insert(group);
+
+    if is_interrupted() {
+        kill_process_group(group);
+    }
+}
+
+/// Forgets about the process group of a process that has finished.
+pub(crate) fn unregister_group(group: u32) {
+    RUNNING_GROUPS.lock().unwrap().remove(&group);
+}
+
+/// Kills every process in the process group with the given ID, returning
+/// whether it worked. A group with nothing left in it to kill counts as
+/// having worked.
+#[allow(unsafe_code)]   // needed for libc::kill
+pub(crate) fn kill_process_group(group: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(group) else {
+        warn!("Error killing process group {}: invalid ID", group);
+        return false;
+    };
+
+    // This needs unsafe because it’s a libc function. Killing processes
+    // does exist in std, but only for a single process rather than a
+    // process group.
+    let ret_val = unsafe {
+        libc::kill(-pid, libc::SIGKILL)
+    };
+
+    if ret_val == 0 {
+        return true;
+    }
+
+    let error = io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::ESRCH) {
+        debug!("Process group {} already dead", group);
+        true
+    }
+    else {
+        warn!("Error killing process group {}: {}", group, error);
+        false
+    }
+}
@@ -13,6 +13,7 @@
 #![warn(unused)]
 
 #![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::large_enum_variant)]
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::must_use_candidate)]
@@ -25,6 +26,14 @@
 #![deny(unsafe_code)]
 
 
+#[cfg(feature = "async")]
+mod async_executor;
+#[cfg(feature = "async")]
+pub use self::async_executor::*;
+
+mod cache;
+pub use self::cache::*;
+
 mod exec;
 pub use self::exec::*;
 
@@ -33,3 +42,12 @@ pub use self::executor::*;
 
 mod error;
 pub use self::error::*;
+
+mod interrupt;
+pub use self::interrupt::{interrupt, is_interrupted};
+
+mod recording;
+pub use self::recording::*;
+
+mod target;
+pub use self::target::*;
@@ -0,0 +1,48 @@
+//! Recordings of commands that have been run before.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::executor::RanCommand;
+
+
+/// A **recording** holds the results of commands that were run during an
+/// earlier run. An executor that replays a recording uses these results
+/// instead of running anything, matching them up by their invocation.
+///
+/// A command that was run more than once has each of its results used in
+/// turn, with the last one being used for any further runs.
+#[derive(Debug, Default)]
+pub struct Recording {
+    commands: Mutex<BTreeMap<String, VecDeque<Arc<RanCommand>>>>,
+}
+
+impl Recording {
+
+    /// Creates a new recording from a list of commands that have been run,
+    /// in the order they were run in.
+    pub fn new(ran_commands: impl IntoIterator<Item=RanCommand>) -> Self {
+        let mut commands = BTreeMap::<_, VecDeque<_>>::new();
+        for ran_command in ran_commands {
+            commands.entry(ran_command.invocation.clone())
+                    .or_default()
+                    .push_back(Arc::new(ran_command));
+        }
+
+        Self { commands: Mutex::new(commands) }
+    }
+
+    /// Returns the next recorded result of the command with the given
+    /// invocation, or `None` if it was never recorded.
+    pub(crate) fn take(&self, invocation: &str) -> Option<Arc<RanCommand>> {
+        let mut commands = self.commands.lock().unwrap();
+        let results = commands.get_mut(invocation)?;
+
+        if results.len() > 1 {
+            results.pop_front()
+        }
+        else {
+            results.front().map(Arc::clone)
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Execution targets, which are where commands get run.
+
+use std::fmt;
+use std::process::Command;
+
+use log::*;
+
+
+/// An **execution target** is the place that commands get run. By default,
+/// this is the machine that specsheet is running on, but commands can be
+/// run somewhere else instead by wrapping them in another command that
+/// runs them there.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum Target {
+
+    /// Commands get run as they are, on this machine.
+    #[default]
+    Local,
+
+    /// Commands get run inside the running Docker container with this
+    /// name or ID, using `docker exec`.
+    Docker(String),
+}
+
+impl Target {
+
+    /// Parses a target from a string given by the user, such as
+    /// `docker://container_name`. Returns `None` if the string is not
+    /// a valid target.
+    pub fn parse(input: &str) -> Option<Self> {
+        if input == "local" {
+            Some(Self::Local)
+        }
+        else if let Some(container) = input.strip_prefix("docker://") {
+            if container.is_empty() || container.contains('/') {
+                None
+            }
+            else {
+                Some(Self::Docker(container.into()))
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    /// Turns the given command into one that runs it on this target.
+    /// Whether the process needs to be fed standard input matters, because
+    /// some wrappers only pass it on when asked to.
+    ///
+    /// The command’s environment variables and directory get passed on to
+    /// the target, rather than applying to the wrapper.
+    pub fn wrap(&self, command: Command, has_stdin: bool) -> Command {
+        let container = match self {
+            Self::Local              => return command,
+            Self::Docker(container)  => container,
+        };
+
+        let mut docker = Command::new("docker");
+        docker.arg("exec");
+
+        if has_stdin {
+            docker.arg("--interactive");
+        }
+
+        if let Some(directory) = command.get_current_dir() {
+            docker.arg("--workdir").arg(directory);
+        }
+
+        for (key, value) in command.get_envs() {
+            if let Some(value) = value {
+                let mut pair = key.to_os_string();
+                pair.push("=");
+                pair.push(value);
+                docker.arg("--env").arg(pair);
+            }
+            else {
+                warn!("Cannot remove environment variable {:?} inside a container", key);
+            }
+        }
+
+        docker.arg(container);
+        docker.arg(command.get_program());
+        docker.args(command.get_args());
+        docker
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local              => write!(f, "local"),
+            Self::Docker(container)  => write!(f, "docker://{}", container),
+        }
+    }
+}
+
@@ -0,0 +1,101 @@
+//! Storing the results of commands on disk, so later runs can use them
+//! instead of running the commands again.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::*;
+use serde::{Serialize, Deserialize};
+
+use spec_exec::{CacheStore, RanCommand};
+
+use crate::fixtures::Fixture;
+use crate::meta::hostname;
+
+
+/// The **disk cache** keeps the results of commands in files in a
+/// directory, one file per command. Results are only used if they were
+/// stored on the same host, and are no older than the time-to-live.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DiskCache {
+
+    /// The directory the files get written to.
+    pub directory: PathBuf,
+
+    /// How long results can be used for after they were stored.
+    pub ttl: Duration,
+}
+
+/// The contents of one file in the cache.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+
+    /// The full key of the command, including the host, which gets checked
+    /// when loading in case two keys end up with the same file.
+    key: String,
+
+    /// The command that was run, and what happened when it was.
+    command: Fixture,
+}
+
+impl DiskCache {
+
+    /// The time-to-live used when one isn’t given.
+    pub const DEFAULT_TTL: Duration = Duration::from_hours(1);
+
+    /// Returns the full key for a command, which includes the host, and
+    /// the path of the file its result gets stored in.
+    fn entry_path(&self, key: &str) -> (String, PathBuf) {
+        let full_key = format!("{}\n{}", hostname().unwrap_or_default(), key);
+
+        let mut hasher = DefaultHasher::new();
+        full_key.hash(&mut hasher);
+        let path = self.directory.join(format!("{:016x}.toml", hasher.finish()));
+
+        (full_key, path)
+    }
+}
+
+impl CacheStore for DiskCache {
+    fn load(&self, key: &str) -> Option<RanCommand> {
+        let (full_key, path) = self.entry_path(key);
+
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if modified.elapsed().unwrap_or_default() > self.ttl {
+            debug!("Cached result has expired -> {}", path.display());
+            return None;
+        }
+
+        let contents = fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = match toml::from_str(&contents) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Invalid cached result in {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        if entry.key == full_key {
+            Some(entry.command.into_ran_command())
+        }
+        else {
+            None
+        }
+    }
+
+    fn save(&self, key: &str, ran_command: &RanCommand) {
+        let (full_key, path) = self.entry_path(key);
+        let entry = CacheEntry { key: full_key, command: Fixture::from_ran_command(ran_command) };
+
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|()| toml::to_string(&entry).map_err(std::io::Error::other))
+            .and_then(|toml| fs::write(&path, toml));
+
+        if let Err(e) = result {
+            warn!("Error caching result to {}: {}", path.display(), e);
+        }
+    }
+}
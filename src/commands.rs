@@ -1,14 +1,23 @@
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
+use log::*;
+
+use spec_checks::common::parse_duration;
+use spec_checks::fs::LookupFile;
 use spec_commands::*;
-use spec_exec::{Exec, Command, CommandOutput, ExitReason};
+use spec_exec::{Exec, Command, CommandOutput, ExitReason, CommandCache, CacheStore, RunOptions};
+
+use crate::side::SideProcesses;
 
 
 /// The **command set** contain the commands that hold the Execs.
 #[derive(Debug)]
 pub struct Commands {
     pub apt:        apt::AptCommand,
+    pub apt_source: apt_source::AptSourcesCommand,
     pub brew:       brew::BrewCommand,
     pub brew_cask:  brew_cask::BrewCaskCommand,
     pub brew_tap:   brew_tap::BrewTapCommand,
@@ -25,6 +34,34 @@ pub struct Commands {
     pub shell:      shell::ShellCommand,
     pub systemctl:  systemctl::SystemctlCommand,
     pub ufw:        ufw::UfwCommand,
+
+    /// How checks get retried if they fail, unless they say otherwise.
+    pub retry:      Retry,
+
+    /// The results of every command run through this set, shared between
+    /// all the command types, so identical commands only get run once.
+    pub cache:      Arc<CommandCache>,
+
+    /// Whether checks should have the programs they run looked for before
+    /// their commands get primed, so checks whose programs are missing can
+    /// be skipped instead of run.
+    pub check_programs: bool,
+
+    /// The side processes running alongside the checks, if there are any,
+    /// which get checked on before each check gets run.
+    pub side_processes: Option<Arc<SideProcesses>>,
+
+    /// The options these commands were created from, kept around so a
+    /// fresh set can be created when a check gets retried.
+    global_options: GlobalOptions,
+}
+
+/// How many more times a failing check gets run before its failure gets
+/// recorded, and how long to wait between each attempt.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Retry {
+    pub retries: u32,
+    pub delay: Duration,
 }
 
 impl Commands {
@@ -34,6 +71,7 @@ impl Commands {
     pub fn from_global_options(global_options: &GlobalOptions) -> Option<Self> {
         Some(Self {
             apt:        apt::AptCommand::create(global_options),
+            apt_source: apt_source::AptSourcesCommand::create(global_options),
             brew:       brew::BrewCommand::create(global_options),
             brew_cask:  brew_cask::BrewCaskCommand::create(global_options),
             brew_tap:   brew_tap::BrewTapCommand::create(global_options),
@@ -50,15 +88,48 @@ impl Commands {
             shell:      shell::ShellCommand::create(global_options),
             systemctl:  systemctl::SystemctlCommand::create(global_options),
             ufw:        ufw::UfwCommand::create(global_options),
+            retry:      Retry::from_global_options(global_options),
+            cache:      Arc::new(CommandCache::new()),
+            check_programs: false,
+            side_processes: None,
+            global_options: global_options.clone(),
         })
     }
 
+    /// Makes the commands in this set use results from the given store, and
+    /// put the results of the commands they run there.
+    pub fn set_cache_store(&mut self, store: Box<dyn CacheStore>) {
+        self.cache = Arc::new(CommandCache::with_store(store));
+    }
+
+    /// Creates a new set of commands from the same overrides as this one,
+    /// without any of the Execs that have been primed or run. Checks get
+    /// run against a fresh set when they are retried, because Execs only
+    /// run their processes once.
+    pub fn fresh(&self) -> Self {
+        let mut fresh = Self::from_global_options(&self.global_options).expect("Invalid overrides");
+        fresh.set_base_directory(self.files.base_directory().map(Into::into));
+        fresh
+    }
+
+    /// Sets the directory that the commands in this set look up relative
+    /// paths from, such as the paths of files to check or of files with
+    /// expected contents in them. When this is `None`, they are looked up
+    /// from the current directory of the process.
+    pub fn set_base_directory(&mut self, directory: Option<PathBuf>) {
+        self.files.set_base_directory(directory.clone());
+        self.hash.set_base_directory(directory.clone());
+        self.net.set_base_directory(directory);
+    }
+
     /// Iterates through all the command types, returning a vector of
-    /// the Command values that have been loaded. This is presented to
-    /// the user as the list of commands that would have been run.
-    pub fn list_commands(self) -> Vec<Command> {
+    /// the Command values that have been loaded, along with the options
+    /// they would be run with. This is presented to the user as the list
+    /// of commands that would have been run.
+    pub fn list_commands(self) -> Vec<(Command, RunOptions)> {
         let mut commands = Vec::new();
         commands.extend(self.apt.commands());
+        commands.extend(self.apt_source.commands());
         commands.extend(self.brew.commands());
         commands.extend(self.brew_cask.commands());
         commands.extend(self.brew_tap.commands());
@@ -81,7 +152,7 @@ impl Commands {
 
 
 /// The set of global options are created from user input.
-#[derive(PartialEq, Debug, Default)]
+#[derive(PartialEq, Debug, Default, Clone)]
 pub struct GlobalOptions {
     pub map: BTreeMap<String, String>,
 }
@@ -99,16 +170,19 @@ impl spec_commands::GlobalOptions for GlobalOptions {
     }
 
     fn duration(&self, key_name: &'static str) -> Option<std::time::Duration> {
-        if self.map.contains_key(key_name) {
-            todo!("Duration not done yet");
+        let value = self.map.get(key_name)?;
+        let duration = parse_duration(value);
+
+        if duration.is_none() {
+            warn!("Invalid duration for option {:?}: {:?}", key_name, value);
         }
 
-        None
+        duration
     }
 
     fn command<T: CommandOutput>(&self, key_name: &'static str) -> Option<Exec<T>> {
         if let Some(data) = self.map.get(key_name) {
-            let lines = data.lines().map(Rc::from).collect::<Vec<_>>();
+            let lines = data.lines().map(Arc::from).collect::<Vec<_>>();
             let object = T::interpret_command_output(lines, ExitReason::Status(0)).ok()?;  // todo: complain to the user here
             Some(Exec::predetermined(key_name, object))
         }
@@ -118,3 +192,50 @@ impl spec_commands::GlobalOptions for GlobalOptions {
     }
 }
 
+
+impl Retry {
+
+    /// Reads the default retry settings from the `retries` and
+    /// `retry_delay` global options, complaining about invalid ones.
+    fn from_global_options(global_options: &GlobalOptions) -> Self {
+        use spec_commands::GlobalOptions;
+
+        let retries = match global_options.key_value("retries").map(|r| r.parse()) {
+            Some(Ok(retries))  => retries,
+            Some(Err(e))       => { warn!("Invalid number for option \"retries\": {}", e); 0 }
+            None               => 0,
+        };
+
+        let delay = global_options.duration("retry_delay").unwrap_or_default();
+        Self { retries, delay }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(pairs: &[(&str, &str)]) -> GlobalOptions {
+        let map = pairs.iter().map(|(k, v)| ((*k).into(), (*v).into())).collect();
+        GlobalOptions { map }
+    }
+
+    #[test]
+    fn no_retries() {
+        assert_eq!(Retry::from_global_options(&options(&[])),
+                   Retry { retries: 0, delay: Duration::from_secs(0) });
+    }
+
+    #[test]
+    fn retries_and_delay() {
+        assert_eq!(Retry::from_global_options(&options(&[ ("retries", "3"), ("retry_delay", "2s") ])),
+                   Retry { retries: 3, delay: Duration::from_secs(2) });
+    }
+
+    #[test]
+    fn invalid_retries() {
+        assert_eq!(Retry::from_global_options(&options(&[ ("retries", "lots") ])),
+                   Retry { retries: 0, delay: Duration::from_secs(0) });
+    }
+}
@@ -0,0 +1,41 @@
+//! Shell completion scripts, which get printed with `--completions`.
+//!
+//! The scripts are the ones in the `completions` directory, built into the
+//! binary so they always match the version of specsheet that prints them.
+//! Values that depend on the check documents, such as tags, get completed
+//! by the scripts running specsheet on the files given so far.
+
+use crate::options::Shell;
+
+
+/// Returns the completion script for the given shell.
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash  => include_str!("../completions/specsheet.bash"),
+        Shell::Fish  => include_str!("../completions/specsheet.fish"),
+        Shell::Zsh   => include_str!("../completions/specsheet.zsh"),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::set::CHECK_TYPES;
+
+    #[test]
+    fn scripts_complete_every_check_type() {
+        let types = CHECK_TYPES.join(" ");
+
+        for shell in [ Shell::Bash, Shell::Fish, Shell::Zsh ] {
+            assert!(script(shell).contains(&types), "{:?} completions have out-of-date check types", shell);
+        }
+    }
+
+    #[test]
+    fn scripts_complete_tags_from_documents() {
+        for shell in [ Shell::Bash, Shell::Fish, Shell::Zsh ] {
+            assert!(script(shell).contains("--list-tags"), "{:?} completions don’t complete tags", shell);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! The config file, which gives default values for command-line options.
+//!
+//! It gets read from `specsheet.toml` in the current directory, or from
+//! `config.toml` in the user’s specsheet config directory if there isn’t
+//! one. The values in it get turned into command-line arguments for the
+//! options that weren’t given on the command-line, so they get checked the
+//! same way, and options given on the command-line override them.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use log::*;
+use serde::Deserialize;
+
+use spec_checks::load::TomlValue;
+
+
+/// A **config file** holds defaults for some of the command-line options.
+#[derive(PartialEq, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+
+    /// How to print the output, like `--print`.
+    pub print: Option<String>,
+
+    /// When to use terminal colours, like `--colour`.
+    #[serde(alias = "color")]
+    pub colour: Option<String>,
+
+    /// How many checks to run at once, like `--threads`.
+    pub threads: Option<usize>,
+
+    /// Global options, like `--option`, keyed by option name.
+    #[serde(default)]
+    pub options: BTreeMap<String, TomlValue>,
+
+    /// Rules to rewrite values in the input documents, like `--rewrite`.
+    #[serde(default)]
+    pub rewrites: Vec<String>,
+
+    /// Whether failing checks don’t make the exit status an error, like
+    /// `--exit-zero`.
+    #[serde(default, rename = "exit-zero")]
+    pub exit_zero: bool,
+
+    /// Whether failing warnings make the exit status an error, like
+    /// `--fail-on-warn`.
+    #[serde(default, rename = "fail-on-warn")]
+    pub fail_on_warn: bool,
+
+    /// Whether skipped checks make the exit status an error, like
+    /// `--fail-on-skip`.
+    #[serde(default, rename = "fail-on-skip")]
+    pub fail_on_skip: bool,
+}
+
+impl ConfigFile {
+
+    /// Reads the config file from the first of the places it can be that
+    /// has one, returning the default config if there isn’t one anywhere.
+    pub fn find() -> Result<Self, ConfigError> {
+        for path in config_paths() {
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    info!("Reading config from file {:?}", path);
+                    return toml::from_str(&contents).map_err(|e| ConfigError::Toml(path, e));
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    debug!("No config file at {:?}", path);
+                }
+                Err(e) => {
+                    return Err(ConfigError::Io(path, e));
+                }
+            }
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Returns the command-line arguments for the options in this config
+    /// file, leaving out the ones that were given on the command-line,
+    /// which have been parsed into the given matches.
+    pub fn default_args(&self, matches: &getopts::Matches) -> Vec<OsString> {
+        let mut args = Vec::new();
+
+        if let Some(print) = &self.print {
+            if ! matches.opt_present("print") {
+                args.push(format!("--print={}", print));
+            }
+        }
+
+        if let Some(colour) = &self.colour {
+            if ! matches.opt_present("colour") && ! matches.opt_present("color") {
+                args.push(format!("--colour={}", colour));
+            }
+        }
+
+        if let Some(threads) = self.threads {
+            if ! matches.opt_present("threads") {
+                args.push(format!("--threads={}", threads));
+            }
+        }
+
+        let given_options = matches.opt_strs("option");
+        for (key, value) in &self.options {
+            let prefix = format!("{}=", key);
+            if ! given_options.iter().any(|o| o.starts_with(&prefix)) {
+                let value = match value {
+                    TomlValue::String(s)  => s.clone(),
+                    other                 => other.to_string(),
+                };
+
+                args.push(format!("--option={}={}", key, value));
+            }
+        }
+
+        let flags = [
+            ("exit-zero",     self.exit_zero),
+            ("fail-on-warn",  self.fail_on_warn),
+            ("fail-on-skip",  self.fail_on_skip),
+        ];
+
+        for (name, set) in flags {
+            if set && ! matches.opt_present(name) {
+                args.push(format!("--{}", name));
+            }
+        }
+
+        // Rewrite rules get tried in order, so these come after any given
+        // on the command-line.
+        for rewrite in &self.rewrites {
+            args.push(format!("--rewrite={}", rewrite));
+        }
+
+        args.into_iter().map(OsString::from).collect()
+    }
+}
+
+
+/// The paths the config file can be at, in the order they get tried.
+fn config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![ PathBuf::from("specsheet.toml") ];
+
+    let config_directory = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+
+    if let Some(directory) = config_directory {
+        paths.push(directory.join("specsheet").join("config.toml"));
+    }
+
+    paths
+}
+
+
+/// Something that can go wrong while reading the config file.
+#[derive(Debug)]
+pub enum ConfigError {
+
+    /// The file exists, but could not be read.
+    Io(PathBuf, io::Error),
+
+    /// The file was not valid TOML, or had fields that aren’t options.
+    Toml(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e)    => write!(f, "Error reading config file {}: {}", path.display(), e),
+            Self::Toml(path, e)  => write!(f, "Invalid config file {}: {}", path.display(), e),
+        }
+    }
+}
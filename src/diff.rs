@@ -0,0 +1,160 @@
+//! Comparing two result documents, to find out whether the checks got
+//! better or worse between two runs, such as before and after a deploy.
+//!
+//! Checks get matched up by the input they were read from and their
+//! description, so a check whose description changed shows up as one
+//! check being removed and another being added.
+
+use std::collections::BTreeMap;
+
+use crate::doc::PreviousOutcome;
+use crate::terminal_ui::Colours;
+
+
+/// A check in a result document: the input it was read from, and its
+/// description.
+pub type CheckKey = (String, String);
+
+
+/// The differences between the checks in two result documents.
+#[derive(PartialEq, Debug, Default)]
+pub struct Comparison {
+
+    /// The checks that fail now, but passed or were skipped before.
+    pub newly_failing: Vec<CheckKey>,
+
+    /// The checks that pass now, but failed or were skipped before.
+    pub newly_passing: Vec<CheckKey>,
+
+    /// The checks that got skipped now, but were run before.
+    pub newly_skipped: Vec<CheckKey>,
+
+    /// The checks that weren’t there before, along with how they turned out.
+    pub added: Vec<(CheckKey, PreviousOutcome)>,
+
+    /// The checks that were there before, but aren’t any more.
+    pub removed: Vec<CheckKey>,
+}
+
+impl Comparison {
+
+    /// Compares how each check turned out in an old run with how it turned
+    /// out in a new one.
+    pub fn new(old: &BTreeMap<CheckKey, PreviousOutcome>, new: &BTreeMap<CheckKey, PreviousOutcome>) -> Self {
+        let mut comparison = Self::default();
+
+        for (key, &outcome) in new {
+            match (old.get(key), outcome) {
+                (None, _) => {
+                    comparison.added.push((key.clone(), outcome));
+                }
+                (Some(before), now) if *before == now => {}
+                (Some(_), PreviousOutcome::Failed) => {
+                    comparison.newly_failing.push(key.clone());
+                }
+                (Some(_), PreviousOutcome::Passed) => {
+                    comparison.newly_passing.push(key.clone());
+                }
+                (Some(_), PreviousOutcome::Skipped) => {
+                    comparison.newly_skipped.push(key.clone());
+                }
+            }
+        }
+
+        comparison.removed = old.keys().filter(|key| ! new.contains_key(*key)).cloned().collect();
+        comparison
+    }
+
+    /// The number of regressions: checks that fail now but didn’t before,
+    /// including new checks that fail.
+    pub fn regression_count(&self) -> usize {
+        let added_failures = self.added.iter().filter(|(_, outcome)| *outcome == PreviousOutcome::Failed).count();
+        self.newly_failing.len() + added_failures
+    }
+}
+
+
+/// Prints the differences between two runs, followed by how many
+/// regressions there were.
+pub fn print_comparison(comparison: &Comparison, colours: &Colours) {
+    let lists = [
+        ("Newly failing", &comparison.newly_failing, colours.cross.paint("✘")),
+        ("Newly passing", &comparison.newly_passing, colours.tick.paint("✔")),
+        ("Newly skipped", &comparison.newly_skipped, colours.skipped.paint("-")),
+    ];
+
+    for (title, keys, symbol) in lists {
+        if ! keys.is_empty() {
+            println!("\n   {}", colours.file_heading.paint(format!("{} ({})", title, keys.len())));
+            for (input, check) in keys {
+                println!(" {} {}: {}", symbol, input, check);
+            }
+        }
+    }
+
+    if ! comparison.added.is_empty() {
+        println!("\n   {}", colours.file_heading.paint(format!("Added ({})", comparison.added.len())));
+        for ((input, check), outcome) in &comparison.added {
+            let symbol = match outcome {
+                PreviousOutcome::Passed   => colours.tick.paint("✔"),
+                PreviousOutcome::Failed   => colours.cross.paint("✘"),
+                PreviousOutcome::Skipped  => colours.skipped.paint("-"),
+            };
+
+            println!(" {} {}: {}", symbol, input, check);
+        }
+    }
+
+    if ! comparison.removed.is_empty() {
+        println!("\n   {}", colours.file_heading.paint(format!("Removed ({})", comparison.removed.len())));
+        for (input, check) in &comparison.removed {
+            println!(" {} {}: {}", colours.skipped.paint("-"), input, check);
+        }
+    }
+
+    match comparison.regression_count() {
+        0 => println!("\n   No regressions"),
+        1 => println!("\n   {}", colours.cross.paint("1 regression")),
+        n => println!("\n   {}", colours.cross.paint(format!("{} regressions", n))),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use PreviousOutcome::*;
+
+    fn outcomes(checks: &[(&str, PreviousOutcome)]) -> BTreeMap<CheckKey, PreviousOutcome> {
+        checks.iter().map(|(check, outcome)| (("checks.toml".into(), (*check).into()), *outcome)).collect()
+    }
+
+    fn key(check: &str) -> CheckKey {
+        ("checks.toml".into(), check.into())
+    }
+
+    #[test]
+    fn nothing_changed() {
+        let run = outcomes(&[ ("a", Passed), ("b", Failed), ("c", Skipped) ]);
+        let comparison = Comparison::new(&run, &run);
+        assert_eq!(comparison, Comparison::default());
+        assert_eq!(comparison.regression_count(), 0);
+    }
+
+    #[test]
+    fn changes() {
+        let old = outcomes(&[ ("a", Passed), ("b", Failed), ("c", Passed), ("d", Skipped), ("e", Passed) ]);
+        let new = outcomes(&[ ("a", Failed), ("b", Passed), ("c", Skipped), ("d", Failed), ("f", Failed), ("g", Passed) ]);
+        let comparison = Comparison::new(&old, &new);
+
+        assert_eq!(comparison, Comparison {
+            newly_failing: vec![ key("a"), key("d") ],
+            newly_passing: vec![ key("b") ],
+            newly_skipped: vec![ key("c") ],
+            added: vec![ (key("f"), Failed), (key("g"), Passed) ],
+            removed: vec![ key("e") ],
+        });
+
+        assert_eq!(comparison.regression_count(), 3);
+    }
+}
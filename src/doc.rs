@@ -1,49 +1,225 @@
-use std::fs::File;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use derive_more::From;
 use horrorshow::html;
-use serde::Serialize;
-use spec_exec::RanCommand;
+use log::*;
+use serde::{Serialize, Serializer, Deserialize};
+use spec_exec::{RanCommand, ExitReason, OutputLine, OutputStream};
 
+use crate::history::CheckTrend;
 use crate::input::InputSource;
-use crate::results::{ResultsSection, Stats};
+use crate::meta::{RunMetadata, Timestamp};
+use crate::push;
+use crate::results::{ResultsSection, Stats, Expectation, CheckOutput, CheckStatus, ResultMessage, HookFailure};
 
 
+/// The paths to write result documents to. These can be templates
+/// containing parts of a timestamp, such as `results-%Y%m%dT%H%M%S.json`,
+/// which get filled in with the time the run started.
 #[derive(PartialEq, Debug)]
 pub struct DocumentPaths {
     pub html_path: Option<PathBuf>,
     pub json_path: Option<PathBuf>,
     pub toml_path: Option<PathBuf>,
+    pub junit_path: Option<PathBuf>,
+    pub markdown_path: Option<PathBuf>,
+    pub prom_path: Option<PathBuf>,
+    pub csv_path: Option<PathBuf>,
+
+    /// The Pushgateway to push the Prometheus metrics to, as well as or
+    /// instead of writing them to a file.
+    pub prom_push: Option<String>,
+    pub retention: Retention,
+}
+
+/// How many old result documents to keep around. This only applies to
+/// documents with templated paths, as otherwise, there’s only ever one.
+#[derive(PartialEq, Debug, Default, Copy, Clone)]
+pub struct Retention {
+
+    /// Only keep this many of the most recent documents.
+    pub keep_last: Option<usize>,
+
+    /// Only keep documents that were written in this many days.
+    pub keep_days: Option<u64>,
 }
 
 impl DocumentPaths {
-    pub fn write(&self, run: CompletedRun<'_>) -> io::Result<()> {
+    pub fn write(&self, run: &CompletedRun<'_>) -> io::Result<()> {
+        let start_time = run.metadata.start_time;
+
+        if let Some(template) = &self.html_path {
+            HtmlPage.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(template) = &self.json_path {
+            JsonDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(template) = &self.toml_path {
+            TomlDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(template) = &self.junit_path {
+            JunitDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(template) = &self.markdown_path {
+            MarkdownDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(template) = &self.prom_path {
+            PromDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        if let Some(gateway) = &self.prom_push {
+            PromDoc.push(gateway, run)?;
+        }
+
+        if let Some(template) = &self.csv_path {
+            CsvDoc.write(&fill_template(template, start_time), run)?;
+            self.retention.prune(template)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fills in any timestamp placeholders in a document path.
+fn fill_template(template: &Path, time: Timestamp) -> PathBuf {
+    match template.to_str() {
+        Some(string)  => PathBuf::from(time.format(string)),
+        None          => template.to_path_buf(),
+    }
+}
 
-        if let Some(path) = &self.html_path {
-            HtmlPage.write(&path, &run)?;
+
+impl Retention {
+
+    /// Deletes the documents in the template’s directory that were written
+    /// using the template, but are too old or too many to keep.
+    fn prune(self, template: &Path) -> io::Result<()> {
+        if self.keep_last.is_none() && self.keep_days.is_none() {
+            return Ok(());
         }
 
-        if let Some(path) = &self.json_path {
-            JsonDoc.write(&path, &run)?;
+        let file_template = match template.file_name().and_then(|e| e.to_str()) {
+            Some(ft) if ft.contains('%') => ft,
+            _ => return Ok(()),
+        };
+
+        let directory = match template.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _                                 => Path::new("."),
+        };
+
+        let mut documents = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+
+            if entry.file_name().to_str().is_some_and(|name| matches_template(file_template, name)) {
+                documents.push((entry.metadata()?.modified()?, entry.path()));
+            }
         }
 
-        if let Some(path) = &self.toml_path {
-            TomlDoc.write(&path, &run)?;
+        // Newest first, so the first few get kept.
+        documents.sort_by_key(|e| Reverse(e.0));
+
+        let cutoff = self.keep_days.map(|days| SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60));
+
+        for (index, (modified, path)) in documents.into_iter().enumerate() {
+            let too_many = self.keep_last.is_some_and(|n| index >= n);
+            let too_old = cutoff.is_some_and(|c| modified < c);
+
+            if too_many || too_old {
+                info!("Removing old result document {:?}", path);
+                fs::remove_file(path)?;
+            }
         }
 
         Ok(())
     }
 }
 
+/// Whether a file name could have been produced by filling in the given
+/// template, with each timestamp placeholder matching a run of digits.
+fn matches_template(template: &str, name: &str) -> bool {
+    let mut rest = name;
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        let digits = match (c, chars.clone().next()) {
+            ('%', Some('Y'))                            => 4,
+            ('%', Some('m' | 'd' | 'H' | 'M' | 'S'))    => 2,
+            _                                           => 0,
+        };
+
+        if digits > 0 {
+            let _ = chars.next();
+
+            match rest.get(.. digits) {
+                Some(ds) if ds.bytes().all(|b| b.is_ascii_digit()) => rest = &rest[digits ..],
+                _ => return false,
+            }
+        }
+        else {
+            if c == '%' && chars.clone().next() == Some('%') {
+                let _ = chars.next();
+            }
+
+            match rest.strip_prefix(c) {
+                Some(r)  => rest = r,
+                None     => return false,
+            }
+        }
+    }
+
+    rest.is_empty()
+}
+
+/// The version of the layout of the JSON and TOML result documents, which
+/// gets written at the top of them. It goes up whenever a field gets
+/// removed or changes meaning, but not when one gets added.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The JSON Schema of the JSON result documents, which the TOML ones
+/// follow too.
+pub const SCHEMA: &str = include_str!("schema.json");
+
 #[derive(Debug, Serialize)]
 pub struct CompletedRun<'a> {
     pub sections: Vec<CompletedSection>,
 
-    #[serde(skip)]
+    #[serde(serialize_with = "serialize_commands")]
     pub commands: Vec<&'a RanCommand>,
 
     pub totals: Stats,
+
+    pub metadata: RunMetadata,
+
+    /// How the checks in the run have been changing over the runs in the
+    /// history directory, if there is one. These only get shown in the
+    /// HTML report, as the history itself has the rest.
+    #[serde(skip)]
+    pub trends: Vec<CheckTrend>,
+
+    // This has to come last, as it gets written as an array of tables in
+    // TOML.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub side_processes: Vec<SideProcessRecord>,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,6 +228,101 @@ pub struct CompletedSection {
     pub results: ResultsSection,
 }
 
+/// A command that was run, as it gets written to result documents, with
+/// enough detail to run it again the same way.
+#[derive(Debug, Serialize)]
+struct CommandRecord<'a> {
+    invocation: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directory: Option<&'a Path>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<i32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal: Option<i32>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    runtime_ms: u64,
+
+    // This has to come last, as it gets written as a table in TOML.
+    environment: &'a BTreeMap<String, String>,
+}
+
+/// The output of a side process that ran alongside the checks, as it gets
+/// written to result documents.
+#[derive(Debug, Serialize)]
+pub struct SideProcessRecord {
+    pub shell: String,
+    pub output: Vec<SideLineRecord>,
+}
+
+/// A line of output from a side process, along with when it was read.
+#[derive(Debug, Serialize)]
+pub struct SideLineRecord {
+    pub stream: &'static str,
+    pub timestamp: Timestamp,
+    pub line: String,
+}
+
+impl SideProcessRecord {
+    pub fn new(shell: &str, lines: &[(OutputStream, OutputLine)]) -> Self {
+        let output = lines.iter().map(|(stream, line)| {
+            SideLineRecord {
+                stream: match stream {
+                    OutputStream::Stdout  => "stdout",
+                    OutputStream::Stderr  => "stderr",
+                },
+                timestamp: Timestamp::at(line.timestamp),
+                line: line.line.to_string(),
+            }
+        }).collect();
+
+        Self { shell: shell.into(), output }
+    }
+}
+
+/// A run, along with the version of the layout it gets written in.
+#[derive(Debug, Serialize)]
+struct VersionedRun<'r, 'a> {
+    format_version: u32,
+
+    #[serde(flatten)]
+    run: &'r CompletedRun<'a>,
+}
+
+impl<'r, 'a> VersionedRun<'r, 'a> {
+    fn new(run: &'r CompletedRun<'a>) -> Self {
+        Self { format_version: FORMAT_VERSION, run }
+    }
+}
+
+fn serialize_commands<S: Serializer>(commands: &[&RanCommand], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(commands.iter().map(|rc| CommandRecord::from(*rc)))
+}
+
+impl<'a> From<&'a RanCommand> for CommandRecord<'a> {
+    fn from(ran_command: &'a RanCommand) -> Self {
+        let (status, signal, timed_out) = match ran_command.exit_reason {
+            ExitReason::Status(s)  => (Some(s), None, false),
+            ExitReason::Signal(s)  => (None, Some(s), false),
+            ExitReason::TimedOut   => (None, None, true),
+            _                      => (None, None, false),
+        };
+
+        Self {
+            invocation: &ran_command.invocation,
+            directory: ran_command.directory.as_deref(),
+            status, signal, timed_out,
+            runtime_ms: ran_command.runtime.as_millis().try_into().unwrap_or(u64::MAX),
+            environment: &ran_command.environment,
+        }
+    }
+}
+
 
 #[derive(Debug, PartialEq)]
 pub struct JsonDoc;
@@ -60,12 +331,17 @@ impl JsonDoc {
     pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
         let mut file = File::create(path)?;
 
-        write!(file, "{}", serde_json::json!(run))?;
+        write!(file, "{}", json_document(run))?;
 
         Ok(())
     }
 }
 
+/// Renders a run as a JSON result document.
+pub fn json_document(run: &CompletedRun<'_>) -> String {
+    serde_json::json!(VersionedRun::new(run)).to_string()
+}
+
 
 #[derive(Debug, PartialEq)]
 pub struct TomlDoc;
@@ -74,54 +350,1414 @@ impl TomlDoc {
     pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
         let mut file = File::create(path)?;
 
-        write!(file, "{}", toml::to_string(&run).unwrap())?;
+        let document = toml_document(run).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write!(file, "{}", document)?;
 
         Ok(())
     }
 }
 
 
+/// Renders a run as TOML. Converting the run to a value first puts each
+/// table’s plain values before its tables, which TOML needs, even when an
+/// array that would otherwise be an array of tables is empty.
+fn toml_document(run: &CompletedRun<'_>) -> Result<String, toml::ser::Error> {
+    let value = toml::Value::try_from(VersionedRun::new(run))?;
+    toml::to_string(&value)
+}
+
+
 #[derive(Debug, PartialEq)]
-pub struct HtmlPage;
+pub struct JunitDoc;
 
-impl HtmlPage {
+impl JunitDoc {
     pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
         let mut file = File::create(path)?;
 
-        let html = html! {
-            html {
-                head {
-                    title : "Specsheet results";
-                    meta(charset="utf-8");
-                }
-                body {
-                    h1 {
-                        : "Specsheet results"
-                    }
+        write!(file, "{}", junit_xml(run))?;
 
-                    @ for section in &run.sections {
-                        section {
-                            h2 {
-                                : "Section"
-                            }
+        Ok(())
+    }
+}
 
-                            ul {
-                                @for output in &section.results.check_outputs {
-                                    li {
-                                        span {
-                                            : &output.message
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+/// Renders a run as the XML test report format that CI systems know how to
+/// display, with a test suite for each input document and a test case for
+/// each check.
+fn junit_xml(run: &CompletedRun<'_>) -> String {
+    let mut all_counts = JunitCounts::default();
+
+    let suites = run.sections.iter()
+        .map(|section| junit_test_suite(run, section, &mut all_counts))
+        .collect::<String>();
+
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"specsheet\" {} timestamp=\"{}\">\n{}</testsuites>\n",
+            all_counts, run.metadata.start_time, suites)
+}
+
+/// Renders one input document’s section of results as a test suite, adding
+/// its counts to the run’s. The failed setup and teardown commands of the
+/// file itself count as test cases with errors.
+fn junit_test_suite(run: &CompletedRun<'_>, section: &CompletedSection, all_counts: &mut JunitCounts) -> String {
+    let suite_name = section.input.to_string();
+    let mut counts = JunitCounts::default();
+
+    let mut cases = section.results.check_outputs.iter()
+        .map(|output| junit_test_case(&suite_name, output, &mut counts))
+        .collect::<String>();
+
+    for failure in &section.results.hook_failures {
+        counts.tests += 1;
+        counts.errors += 1;
+        cases.push_str(&junit_hook_case(&suite_name, failure));
+    }
+
+    *all_counts += counts;
+
+    format!("  <testsuite name=\"{}\" {} timestamp=\"{}\" hostname=\"{}\">\n{}  </testsuite>\n",
+            xml_escape(&suite_name), counts, run.metadata.start_time,
+            xml_escape(run.metadata.hostname.as_deref().unwrap_or("localhost")), cases)
+}
+
+fn junit_hook_case(suite_name: &str, failure: &HookFailure) -> String {
+    format!("    <testcase name=\"{}: {}\" classname=\"{}\" time=\"0.000\">\n      <error message=\"{}\" type=\"{}\"/>\n    </testcase>\n",
+            failure.stage, xml_escape(&failure.shell), xml_escape(suite_name), xml_escape(&failure.error), failure.stage)
+}
+
+/// Renders one check as a test case, adding it to the counts. The format
+/// has nothing like warnings or expected failures, so a warning counts as a
+/// failure of its own type, and a check that was expected to fail passes
+/// if it does fail, and fails if it passes.
+fn junit_test_case(suite_name: &str, output: &CheckOutput, counts: &mut JunitCounts) -> String {
+    counts.tests += 1;
+    counts.time += output.runtime;
+
+    let mut all_messages = String::new();
+    for result in &output.results {
+        writeln!(all_messages, "{}", result).unwrap();
+    }
+
+    let failure = output.results.iter().find_map(|result| {
+        if let ResultMessage::Failed(message) = result { Some(message.clone()) } else { None }
+    });
+
+    let error = output.results.iter().find_map(|result| {
+        if let ResultMessage::Error(message) = result { Some(message.clone()) } else { None }
+    }).or_else(|| {
+        output.hook_failures.first().map(|f| format!("{} command ‘{}’ failed: {}", f.stage, f.shell, f.error))
+    });
+
+    let known_issue = output.known_issue.as_ref().map(|issue| format!(" (known issue {})", issue)).unwrap_or_default();
+
+    let body = match output.status() {
+        CheckStatus::Passed | CheckStatus::ExpectedFailure => {
+            String::new()
+        }
+        CheckStatus::Skipped | CheckStatus::Blocked => {
+            counts.skipped += 1;
+            format!("      <skipped message=\"{}\"/>\n", xml_escape(output.skipped.as_deref().unwrap_or_default()))
+        }
+        CheckStatus::UnexpectedPass => {
+            counts.failures += 1;
+            "      <failure message=\"passed, but was expected to fail\" type=\"unexpected-pass\"/>\n".into()
+        }
+        CheckStatus::Warning => {
+            counts.failures += 1;
+            let message = failure.or(error).unwrap_or_default();
+            format!("      <failure message=\"{}\" type=\"warning\">{}</failure>\n", xml_escape(&message), xml_escape(&all_messages))
+        }
+        CheckStatus::Errored => {
+            counts.errors += 1;
+            let message = error.unwrap_or_default();
+            format!("      <error message=\"{}\" type=\"error\">{}</error>\n", xml_escape(&message), xml_escape(&all_messages))
+        }
+        CheckStatus::Failed => {
+            counts.failures += 1;
+            let message = failure.unwrap_or_default();
+            format!("      <failure message=\"{}{}\" type=\"failure\">{}</failure>\n", xml_escape(&message), xml_escape(&known_issue), xml_escape(&all_messages))
+        }
+    };
+
+    let mut system_out = String::new();
+    for command in &output.ran_commands {
+        system_out.push_str("$ ");
+        system_out.push_str(&command.invocation);
+        system_out.push('\n');
+        system_out.push_str(&command.output_text());
+    }
+
+    let system_out = if system_out.is_empty() { String::new() }
+                                          else { format!("      <system-out>{}</system-out>\n", xml_escape(&system_out)) };
+
+    format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">\n{}{}    </testcase>\n",
+            xml_escape(&output.message), xml_escape(suite_name), output.runtime.as_secs_f64(), body, system_out)
+}
+
+/// The totals that test suites have as attributes.
+#[derive(Debug, Default, Copy, Clone)]
+struct JunitCounts {
+    tests: u32,
+    failures: u32,
+    errors: u32,
+    skipped: u32,
+    time: Duration,
+}
+
+impl std::ops::AddAssign for JunitCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.tests += other.tests;
+        self.failures += other.failures;
+        self.errors += other.errors;
+        self.skipped += other.skipped;
+        self.time += other.time;
+    }
+}
+
+impl fmt::Display for JunitCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+               self.tests, self.failures, self.errors, self.skipped, self.time.as_secs_f64())
+    }
+}
+
+/// Escapes text to go in an XML attribute or element. Control characters
+/// aren’t allowed in XML at all, so the ones that commands print, such as
+/// the escape codes for terminal colours, get left out.
+fn xml_escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '&'                  => output.push_str("&amp;"),
+            '<'                  => output.push_str("&lt;"),
+            '>'                  => output.push_str("&gt;"),
+            '"'                  => output.push_str("&quot;"),
+            '\''                 => output.push_str("&apos;"),
+            '\t' | '\n' | '\r'   => output.push(c),
+            c if c.is_control()  => {}
+            c                    => output.push(c),
+        }
+    }
+
+    output
+}
+
+
+#[derive(Debug, PartialEq)]
+pub struct PromDoc;
+
+impl PromDoc {
+
+    /// Writes the metrics to a temporary file next to the path first, then
+    /// moves it into place, so a collector reading the file never sees it
+    /// half-written.
+    pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
+        let mut temporary_path = path.as_os_str().to_os_string();
+        temporary_path.push(".tmp");
+
+        fs::write(&temporary_path, prometheus_metrics(run))?;
+        fs::rename(&temporary_path, path)
+    }
+
+    /// Pushes the metrics to a Pushgateway, replacing any metrics that
+    /// were pushed by the previous run.
+    pub fn push(&self, gateway: &str, run: &CompletedRun<'_>) -> io::Result<()> {
+        let url = format!("{}/metrics/job/specsheet", gateway.trim_end_matches('/'));
+        push::send("PUT", &url, "text/plain; version=0.0.4", &prometheus_metrics(run))
+    }
+}
+
+/// Renders a run as metrics in the Prometheus text format: whether each
+/// check passed and how long it took, and how many checks had each result
+/// overall. Checks that were skipped or blocked neither passed nor failed,
+/// so they only count towards the totals.
+pub fn prometheus_metrics(run: &CompletedRun<'_>) -> String {
+    let mut passed = String::new();
+    let mut durations = String::new();
+
+    for section in &run.sections {
+        let file = section.input.to_string();
+
+        for output in &section.results.check_outputs {
+            let mut labels = format!("file=\"{}\",type=\"{}\",check=\"{}\"", prometheus_escape(&file), output.check_type, prometheus_escape(&output.message));
+            if let Some(name) = &output.name {
+                write!(labels, ",name=\"{}\"", prometheus_escape(name)).unwrap();
             }
-        };
 
-        write!(file, "{}", html)?;
+            let value = match output.status() {
+                CheckStatus::Passed | CheckStatus::ExpectedFailure                                               => 1,
+                CheckStatus::Failed | CheckStatus::Errored | CheckStatus::Warning | CheckStatus::UnexpectedPass  => 0,
+                CheckStatus::Skipped | CheckStatus::Blocked                                                      => continue,
+            };
+
+            writeln!(passed, "specsheet_check_passed{{{}}} {}", labels, value).unwrap();
+            writeln!(durations, "specsheet_check_duration_seconds{{{}}} {:.6}", labels, output.runtime.as_secs_f64()).unwrap();
+        }
+    }
+
+    let totals = run.totals;
+    let mut metrics = String::new();
+
+    metrics.push_str("# HELP specsheet_check_passed Whether the check passed, or failed as expected.\n");
+    metrics.push_str("# TYPE specsheet_check_passed gauge\n");
+    metrics.push_str(&passed);
+
+    metrics.push_str("# HELP specsheet_check_duration_seconds How long the check took to run, including retries.\n");
+    metrics.push_str("# TYPE specsheet_check_duration_seconds gauge\n");
+    metrics.push_str(&durations);
+
+    metrics.push_str("# HELP specsheet_checks The number of checks with each result in the run.\n");
+    metrics.push_str("# TYPE specsheet_checks gauge\n");
+
+    let results = [
+        ("passed",               totals.pass_count),
+        ("failed",               totals.fail_count),
+        ("errored",              totals.err_count),
+        ("warning",              totals.warning_count),
+        ("expected-failure",     totals.expected_failure_count),
+        ("unexpected-pass",      totals.unexpected_pass_count),
+        ("skipped",              totals.skip_count),
+        ("blocked",              totals.blocked_count),
+    ];
+
+    for (result, count) in results {
+        writeln!(metrics, "specsheet_checks{{result=\"{}\"}} {}", result, count).unwrap();
+    }
+
+    metrics.push_str("# HELP specsheet_hook_failures The number of setup and teardown commands that failed in the run.\n");
+    metrics.push_str("# TYPE specsheet_hook_failures gauge\n");
+    writeln!(metrics, "specsheet_hook_failures {}", totals.hook_failure_count).unwrap();
+
+    metrics.push_str("# HELP specsheet_run_duration_seconds How long the run took.\n");
+    metrics.push_str("# TYPE specsheet_run_duration_seconds gauge\n");
+    writeln!(metrics, "specsheet_run_duration_seconds {:.6}", run.metadata.duration().as_secs_f64()).unwrap();
+
+    metrics.push_str("# HELP specsheet_run_timestamp_seconds When the run finished, as a Unix timestamp.\n");
+    metrics.push_str("# TYPE specsheet_run_timestamp_seconds gauge\n");
+    writeln!(metrics, "specsheet_run_timestamp_seconds {}", run.metadata.end_time.unix_seconds()).unwrap();
+
+    metrics
+}
+
+/// Escapes text to go in a label value, which is surrounded by double
+/// quotes, and can’t contain a literal newline.
+fn prometheus_escape(input: &str) -> String {
+    input.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+#[derive(Debug, PartialEq)]
+pub struct CsvDoc;
+
+impl CsvDoc {
+    pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        write!(file, "{}", csv_rows(run))?;
+
+        Ok(())
+    }
+}
+
+/// Renders a run as a table with a row for each check, for spreadsheets.
+/// The message column has why the check didn’t pass, if it didn’t, which is
+/// its first failure or error, or why it was skipped.
+fn csv_rows(run: &CompletedRun<'_>) -> String {
+    let mut csv = String::from("file,type,name,description,tags,result,duration,message\r\n");
+
+    for section in &run.sections {
+        let file = section.input.to_string();
+
+        for output in &section.results.check_outputs {
+            let message = output.results.iter()
+                .find_map(|result| match result {
+                    ResultMessage::Passed(_)   => None,
+                    ResultMessage::Failed(m)   |
+                    ResultMessage::Error(m)    => Some(m.clone()),
+                })
+                .or_else(|| output.hook_failures.first().map(|f| format!("{} command ‘{}’ failed: {}", f.stage, f.shell, f.error)))
+                .or_else(|| output.skipped.clone())
+                .unwrap_or_default();
+
+            let fields = [
+                csv_field(&file),
+                csv_field(output.check_type),
+                csv_field(output.name.as_deref().unwrap_or_default()),
+                csv_field(&output.message),
+                csv_field(&output.tags.join(", ")),
+                csv_field(status_class(output.status())),
+                format!("{:.3}", output.runtime.as_secs_f64()),
+                csv_field(&message),
+            ];
+
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+    }
+
+    csv
+}
+
+/// Quotes a field if it contains anything that would otherwise be read as
+/// the end of it, doubling any quotes inside it.
+fn csv_field(input: &str) -> String {
+    if input.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", input.replace('"', "\"\""))
+    }
+    else {
+        input.into()
+    }
+}
+
+
+#[derive(Debug, PartialEq)]
+pub struct MarkdownDoc;
+
+impl MarkdownDoc {
+    pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        write!(file, "{}", markdown_report(run))?;
 
         Ok(())
     }
 }
+
+/// Renders a run as a Markdown report, for pasting into pull requests or
+/// chat: a summary of the totals, then a section for each input document
+/// with a table of its checks, and the details of each check that didn’t
+/// pass in a collapsible block underneath.
+fn markdown_report(run: &CompletedRun<'_>) -> String {
+    let metadata = &run.metadata;
+    let mut report = String::from("# Specsheet results\n\n");
+
+    writeln!(report, "**{}**\n", totals_line(run.totals)).unwrap();
+    writeln!(report, "Run on `{}` as `{}` at {}, taking {:.3}s.",
+             metadata.hostname.as_deref().unwrap_or("unknown"), metadata.user.as_deref().unwrap_or("unknown"),
+             metadata.start_time, metadata.duration().as_secs_f64()).unwrap();
+
+    for section in &run.sections {
+        report.push_str(&markdown_section(section));
+    }
+
+    report
+}
+
+fn markdown_section(section: &CompletedSection) -> String {
+    let results = &section.results;
+    let mut text = format!("\n## {}\n\n{}\n\n", markdown_escape(&section.input.to_string()), totals_line(results.totals));
+
+    if ! results.check_outputs.is_empty() {
+        text.push_str("| Result | Check | Time |\n| --- | --- | --: |\n");
+
+        for output in &results.check_outputs {
+            writeln!(text, "| {} | {}{} | {:.3}s |",
+                     status_label(output.status()), markdown_escape(&output.message),
+                     markdown_escape(&check_notes(output)), output.runtime.as_secs_f64()).unwrap();
+        }
+    }
+
+    for failure in &results.hook_failures {
+        writeln!(text, "\n✘ {} command `{}` failed: {}", failure.stage, failure.shell.replace('`', "'"), markdown_escape(&failure.error)).unwrap();
+    }
+
+    for output in &results.check_outputs {
+        if matches!(output.status(), CheckStatus::Failed | CheckStatus::Errored | CheckStatus::Warning | CheckStatus::UnexpectedPass) {
+            text.push_str(&markdown_details(output));
+        }
+    }
+
+    text
+}
+
+/// Summarises a set of totals as one line, leaving out the kinds of result
+/// that didn’t happen, apart from passes and failures.
+pub fn totals_line(stats: Stats) -> String {
+    let mut parts = vec![ format!("{} passed", stats.pass_count), format!("{} failed", stats.fail_count) ];
+
+    let others = [
+        (stats.err_count,               "errored",                       "errored"),
+        (stats.warning_count,           "warning",                       "warnings"),
+        (stats.expected_failure_count,  "failed as expected",            "failed as expected"),
+        (stats.unexpected_pass_count,   "passed unexpectedly",           "passed unexpectedly"),
+        (stats.skip_count,              "skipped",                       "skipped"),
+        (stats.blocked_count,           "blocked",                       "blocked"),
+        (stats.hook_failure_count,      "setup or teardown failure",     "setup or teardown failures"),
+        (stats.over_budget_count,       "over budget",                   "over budget"),
+    ];
+
+    for (count, singular, plural) in others {
+        if count == 1 {
+            parts.push(format!("1 {}", singular));
+        }
+        else if count > 1 {
+            parts.push(format!("{} {}", count, plural));
+        }
+    }
+
+    let symbol = if stats.fail_count + stats.err_count + stats.hook_failure_count == 0 { "✔" } else { "✘" };
+    format!("{} {}", symbol, parts.join(", "))
+}
+
+fn status_label(status: CheckStatus) -> &'static str {
+    match status {
+        CheckStatus::Passed           => "✔ passed",
+        CheckStatus::Failed           => "✘ failed",
+        CheckStatus::Errored          => "? errored",
+        CheckStatus::Warning          => "⚠ warning",
+        CheckStatus::ExpectedFailure  => "✔ failed as expected",
+        CheckStatus::UnexpectedPass   => "✘ passed unexpectedly",
+        CheckStatus::Skipped          => "– skipped",
+        CheckStatus::Blocked          => "– blocked",
+    }
+}
+
+/// The notes that go after a check’s description in a report, such as
+/// why it was skipped.
+fn check_notes(output: &CheckOutput) -> String {
+    let mut notes = Vec::new();
+
+    if let Some(reason) = &output.skipped {
+        notes.push(reason.clone());
+    }
+
+    if ! output.blocked_by.is_empty() {
+        notes.push(format!("needs {}", output.blocked_by.join(", ")));
+    }
+
+    if let Some(issue) = &output.known_issue {
+        notes.push(format!("known issue {}", issue));
+    }
+
+    if output.budget.is_some_and(|b| b.over_budget) {
+        notes.push("over budget".into());
+    }
+
+    if notes.is_empty() { String::new() }
+                   else { format!(" ({})", notes.join("; ")) }
+}
+
+/// Renders the results of a check that didn’t pass, along with the output
+/// of the commands it ran, as a block that starts off collapsed.
+fn markdown_details(output: &CheckOutput) -> String {
+    let mut text = format!("\n<details>\n<summary>{} {}</summary>\n\n",
+                           status_label(output.status()), markdown_escape(&output.message));
+
+    for result in &output.results {
+        let line = match result {
+            ResultMessage::Passed(message)  => format!("- ✔ {}\n", markdown_escape(message)),
+            ResultMessage::Failed(message)  => format!("- ✘ {}\n", markdown_escape(message)),
+            ResultMessage::Error(message)   => format!("- ✘ error: {}\n", markdown_escape(message)),
+        };
+        text.push_str(&line);
+    }
+
+    for failure in &output.hook_failures {
+        writeln!(text, "- ✘ {} command `{}` failed: {}", failure.stage, failure.shell.replace('`', "'"), markdown_escape(&failure.error)).unwrap();
+    }
+
+    for command in &output.ran_commands {
+        let contents = format!("$ {}\n{}", command.invocation, command.output_text());
+        let fence = code_fence(&contents);
+        writeln!(text, "\n{}\n{}{}", fence, contents, fence).unwrap();
+    }
+
+    text.push_str("\n</details>\n");
+    text
+}
+
+/// Returns a code fence that’s longer than any run of backticks in the
+/// given text, so that the text can’t end the code block early.
+fn code_fence(contents: &str) -> String {
+    let longest_run = contents.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+/// Escapes the characters that mean something in Markdown or HTML, so that
+/// text gets displayed as it is. Newlines become spaces, as they would
+/// otherwise break up the table.
+fn markdown_escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|'  => { output.push('\\'); output.push(c); }
+            '\n' | '\r'                                            => output.push(' '),
+            c if c.is_control()                                    => {}
+            c                                                      => output.push(c),
+        }
+    }
+
+    output
+}
+
+
+#[derive(Debug, PartialEq)]
+pub struct HtmlPage;
+
+impl HtmlPage {
+    pub fn write(&self, path: &Path, run: &CompletedRun<'_>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        write!(file, "{}", html_report(run))?;
+
+        Ok(())
+    }
+}
+
+/// The statuses a check can have, in the order they get listed in the
+/// HTML report.
+const STATUSES: [CheckStatus; 8] = [
+    CheckStatus::Passed, CheckStatus::Failed, CheckStatus::Errored, CheckStatus::Warning, CheckStatus::ExpectedFailure,
+    CheckStatus::UnexpectedPass, CheckStatus::Skipped, CheckStatus::Blocked,
+];
+
+/// Renders a run as a single HTML page, with its styles and scripts
+/// inlined so it can be passed around as one file. The page has the run’s
+/// details and a chart of its results at the top, then the checks in each
+/// input document, which can be filtered by status, type, and tag, and
+/// which can be expanded to show their results and command output.
+fn html_report(run: &CompletedRun<'_>) -> String {
+    let outputs = run.sections.iter().flat_map(|s| &s.results.check_outputs).collect::<Vec<_>>();
+    let status_counts = STATUSES.iter()
+        .map(|status| (*status, outputs.iter().filter(|o| o.status() == *status).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect::<Vec<_>>();
+
+    let check_types = outputs.iter().map(|o| o.check_type).filter(|t| ! t.is_empty()).collect::<BTreeSet<_>>();
+    let tags = outputs.iter().flat_map(|o| &o.tags).collect::<BTreeSet<_>>();
+    let metadata = &run.metadata;
+
+    let html = html! {
+        : horrorshow::helper::doctype::HTML;
+        html(lang="en") {
+            head {
+                meta(charset="utf-8");
+                meta(name="viewport", content="width=device-width, initial-scale=1");
+                title : "Specsheet results";
+                style : horrorshow::Raw(include_str!("report.css"));
+            }
+            body {
+                header {
+                    h1 : "Specsheet results";
+
+                    div(class="overview") {
+                        : horrorshow::Raw(donut_chart(&status_counts));
+
+                        ul(class="legend") {
+                            @ for (status, count) in &status_counts {
+                                li(class=status_class(*status)) : format!("{}: {}", status_label(*status), count);
+                            }
+                        }
+
+                        dl(class="metadata") {
+                            dt : "Host";
+                            dd : metadata.hostname.as_deref().unwrap_or("unknown");
+                            dt : "User";
+                            dd : metadata.user.as_deref().unwrap_or("unknown");
+                            dt : "System";
+                            dd : format!("{} ({})", metadata.os, metadata.arch);
+                            dt : "Version";
+                            dd : metadata.version;
+                            dt : "Arguments";
+                            dd : metadata.arguments.join(" ");
+                            dt : "Started";
+                            dd : metadata.start_time.to_string();
+                            dt : "Finished";
+                            dd : metadata.end_time.to_string();
+                            dt : "Duration";
+                            dd : format!("{:.3}s", metadata.duration().as_secs_f64());
+                        }
+                    }
+
+                    p(class="totals") : totals_line(run.totals);
+                }
+
+                form(id="filters") {
+                    label {
+                        : "Status ";
+                        select(id="status-filter") {
+                            option(value="") : "all";
+                            @ for (status, _) in &status_counts {
+                                option(value=status_class(*status)) : status_label(*status);
+                            }
+                        }
+                    }
+
+                    label {
+                        : "Type ";
+                        select(id="type-filter") {
+                            option(value="") : "all";
+                            @ for check_type in &check_types {
+                                option(value=*check_type) : *check_type;
+                            }
+                        }
+                    }
+
+                    label {
+                        : "Tag ";
+                        select(id="tag-filter") {
+                            option(value="") : "all";
+                            @ for tag in &tags {
+                                option(value=tag.as_str()) : tag.as_str();
+                            }
+                        }
+                    }
+
+                    label {
+                        : "Search ";
+                        input(id="text-filter", type="search");
+                    }
+                }
+
+                @ if ! run.trends.is_empty() {
+                    section(class="trends") {
+                        h2 : "Trends";
+
+                        ul {
+                            @ for trend in &run.trends {
+                                li : format!("{} ({}): {}", trend.check, trend.input, trend.notes().join("; "));
+                            }
+                        }
+                    }
+                }
+
+                @ for section in &run.sections {
+                    section(class="document") {
+                        h2 : section.input.to_string();
+                        p(class="totals") : totals_line(section.results.totals);
+
+                        @ for failure in &section.results.hook_failures {
+                            p(class="hook-failure") : format!("✘ {} command ‘{}’ failed: {}", failure.stage, failure.shell, failure.error);
+                        }
+
+                        @ if let Some(correlations) = &section.results.correlations {
+                            @ if ! correlations.is_empty() {
+                                ul(class="correlations") {
+                                    @ for correlation in correlations {
+                                        li : format!("Analysis: {}", correlation);
+                                    }
+                                }
+                            }
+                        }
+
+                        ul(class="checks") {
+                            @ for output in &section.results.check_outputs {
+                                li(class=format!("check {}", status_class(output.status())), data-status=status_class(output.status()),
+                                   data-type=output.check_type, data-tags=output.tags.join(" ")) {
+                                    details(open?=matches!(output.status(), CheckStatus::Failed | CheckStatus::Errored | CheckStatus::Warning | CheckStatus::UnexpectedPass)) {
+                                        summary {
+                                            span(class="status") : status_label(output.status());
+                                            span(class="message") : &output.message;
+                                            span(class="notes") : check_notes(output);
+                                            span(class="time") : format!("{:.3}s", output.runtime.as_secs_f64());
+                                        }
+
+                                        @ if ! output.tags.is_empty() {
+                                            p(class="tags") : format!("Tags: {}", output.tags.join(", "));
+                                        }
+
+                                        ul(class="results") {
+                                            @ for result in &output.results {
+                                                |tmpl| html_result(tmpl, result);
+                                            }
+
+                                            @ for failure in &output.hook_failures {
+                                                li(class="error") : format!("✘ {} command ‘{}’ failed: {}", failure.stage, failure.shell, failure.error);
+                                            }
+                                        }
+
+                                        @ for command in &output.ran_commands {
+                                            details(class="command") {
+                                                summary : format!("$ {}", command.invocation);
+                                                pre : command.output_text();
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                @ for side_process in &run.side_processes {
+                    section(class="side-process") {
+                        h2 : format!("Side process {}", side_process.shell);
+
+                        pre {
+                            @ for line in &side_process.output {
+                                : format!("{} [{}] {}\n", line.timestamp, line.stream, line.line)
+                            }
+                        }
+                    }
+                }
+
+                script : horrorshow::Raw(include_str!("report.js"));
+            }
+        }
+    };
+
+    html.to_string()
+}
+
+/// Renders one of a check’s results. Messages that span more than one
+/// line, such as diffs, get kept as they are.
+fn html_result(tmpl: &mut horrorshow::TemplateBuffer<'_>, result: &ResultMessage) {
+    let (class, symbol, message) = match result {
+        ResultMessage::Passed(message)  => ("pass", "✔", message),
+        ResultMessage::Failed(message)  => ("fail", "✘", message),
+        ResultMessage::Error(message)   => ("error", "✘ error:", message),
+    };
+
+    if message.contains('\n') {
+        tmpl << html! {
+            li(class=class) {
+                : symbol;
+                pre(class="diff") : message;
+            }
+        };
+    }
+    else {
+        tmpl << html! {
+            li(class=class) : format!("{} {}", symbol, message);
+        };
+    }
+}
+
+/// Draws the number of checks with each status as a donut chart, as an SVG
+/// element. Each ring segment is a circle with a dashed stroke, with the
+/// circle’s circumference being 100 so the dashes can be percentages.
+fn donut_chart(status_counts: &[(CheckStatus, usize)]) -> String {
+    let total = status_counts.iter().map(|(_, count)| count).sum::<usize>();
+    let mut svg = String::from("<svg class=\"donut\" viewBox=\"0 0 42 42\" role=\"img\">");
+    svg.push_str("<circle cx=\"21\" cy=\"21\" r=\"15.915\" fill=\"none\" stroke-width=\"6\" class=\"ring\"/>");
+
+    let mut offset = 0.0;
+    for (status, count) in status_counts {
+        #[allow(clippy::cast_precision_loss)]
+        let percent = *count as f64 * 100.0 / total as f64;
+
+        write!(svg, "<circle cx=\"21\" cy=\"21\" r=\"15.915\" fill=\"none\" stroke-width=\"6\" class=\"{}\" stroke-dasharray=\"{:.3} {:.3}\" stroke-dashoffset=\"{:.3}\"/>",
+               status_class(*status), percent, 100.0 - percent, 25.0 - offset).unwrap();
+        offset += percent;
+    }
+
+    write!(svg, "<text x=\"21\" y=\"23\" text-anchor=\"middle\">{}</text></svg>", total).unwrap();
+    svg
+}
+
+fn status_class(status: CheckStatus) -> &'static str {
+    status.name()
+}
+
+
+/// The parts of a JSON or TOML result document that get read back in, to
+/// find out which checks did not pass in a previous run, or to compare it
+/// with another run.
+#[derive(Debug, Deserialize)]
+struct PreviousRun {
+
+    /// The version of the document’s layout, which is missing from
+    /// documents written before it was added.
+    #[serde(default)]
+    format_version: Option<u32>,
+
+    sections: Vec<PreviousSection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviousSection {
+    input: InputSource,
+    results: PreviousResults,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviousResults {
+    check_outputs: Vec<PreviousOutput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviousOutput {
+    passed: bool,
+    message: String,
+
+    #[serde(default)]
+    skipped: Option<String>,
+
+    #[serde(default)]
+    expected: Expectation,
+}
+
+/// How a check turned out in a previous run.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum PreviousOutcome {
+
+    /// The check passed, or failed when it was expected to.
+    Passed,
+
+    /// The check failed or errored, or passed when it was expected to fail.
+    Failed,
+
+    /// The check was skipped or blocked rather than run.
+    Skipped,
+}
+
+/// Reads the result document at the given path, returning the descriptions
+/// of the checks in it that failed or errored. Checks that were skipped
+/// are left out, as are checks that failed when they were expected to, but
+/// checks that were expected to fail and passed are included.
+pub fn failed_checks(path: &Path) -> Result<BTreeSet<String>, PreviousRunError> {
+    Ok(read_previous_run(path)?.failed_checks())
+}
+
+/// Reads the result document at the given path, returning how each check
+/// in it turned out, keyed by the input it was read from and its
+/// description.
+pub fn check_outcomes(path: &Path) -> Result<BTreeMap<(String, String), PreviousOutcome>, PreviousRunError> {
+    Ok(read_previous_run(path)?.check_outcomes())
+}
+
+fn read_previous_run(path: &Path) -> Result<PreviousRun, PreviousRunError> {
+    let contents = fs::read_to_string(path)?;
+
+    let run: PreviousRun = if path.extension().is_some_and(|e| e == "toml") {
+        toml::from_str(&contents)?
+    }
+    else {
+        serde_json::from_str(&contents)?
+    };
+
+    run.check_version()?;
+    Ok(run)
+}
+
+impl PreviousRun {
+
+    /// Makes sure the document isn’t in a layout from a later version.
+    fn check_version(&self) -> Result<(), PreviousRunError> {
+        match self.format_version {
+            Some(version) if version > FORMAT_VERSION  => Err(PreviousRunError::NewerVersion(version)),
+            _                                           => Ok(()),
+        }
+    }
+
+    fn failed_checks(self) -> BTreeSet<String> {
+        self.sections.into_iter()
+            .flat_map(|section| section.results.check_outputs)
+            .filter(|output| output.outcome() == PreviousOutcome::Failed)
+            .map(|output| output.message)
+            .collect()
+    }
+
+    fn check_outcomes(self) -> BTreeMap<(String, String), PreviousOutcome> {
+        let mut outcomes = BTreeMap::new();
+
+        for section in self.sections {
+            let input = section.input.to_string();
+            for output in section.results.check_outputs {
+                let outcome = output.outcome();
+                outcomes.insert((input.clone(), output.message), outcome);
+            }
+        }
+
+        outcomes
+    }
+}
+
+impl PreviousOutput {
+    fn outcome(&self) -> PreviousOutcome {
+        if self.skipped.is_some() {
+            PreviousOutcome::Skipped
+        }
+        else if self.passed == (self.expected == Expectation::Fail) {
+            PreviousOutcome::Failed
+        }
+        else {
+            PreviousOutcome::Passed
+        }
+    }
+}
+
+/// Something that can go wrong while reading a previous result document.
+#[derive(Debug, From)]
+pub enum PreviousRunError {
+
+    /// The document could not be read.
+    Io(io::Error),
+
+    /// The document was not a valid JSON result document.
+    Json(serde_json::Error),
+
+    /// The document was not a valid TOML result document.
+    Toml(toml::de::Error),
+
+    /// The document was written by a later version of specsheet, in a
+    /// layout this one doesn’t know how to read.
+    #[from(ignore)]
+    NewerVersion(u32),
+}
+
+impl fmt::Display for PreviousRunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e)                  => write!(f, "{}", e),
+            Self::Json(e)                => write!(f, "Invalid JSON result document: {}", e),
+            Self::Toml(e)                => write!(f, "Invalid TOML result document: {}", e),
+            Self::NewerVersion(version)  => write!(f, "Result document has format version {}, but only versions up to {} can be read", version, FORMAT_VERSION),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::results::{BudgetUsage, CorrelationRecord, HookStage, Severity};
+
+    #[test]
+    fn template_matches() {
+        assert!(matches_template("results-%Y%m%dT%H%M%S.json", "results-20201231T235959.json"));
+    }
+
+    #[test]
+    fn template_wrong_extension() {
+        assert!(! matches_template("results-%Y%m%dT%H%M%S.json", "results-20201231T235959.toml"));
+    }
+
+    #[test]
+    fn template_not_digits() {
+        assert!(! matches_template("results-%Y.json", "results-last.json"));
+    }
+
+    #[test]
+    fn template_escaped_percent() {
+        assert!(matches_template("100%%-%d.html", "100%-31.html"));
+    }
+
+    #[test]
+    fn previous_failures() {
+        let run: PreviousRun = serde_json::from_str(r#"{
+            "sections": [
+                { "input": { "source": "file", "path": "a.toml" }, "results": { "check_outputs": [
+                    { "passed": true,  "message": "passed" },
+                    { "passed": false, "message": "failed" },
+                    { "passed": false, "message": "skipped", "skipped": "‘brew’ is not installed" }
+                ] } },
+                { "input": { "source": "stdin" }, "results": { "check_outputs": [
+                    { "passed": false, "message": "failed as expected", "expected": "fail" },
+                    { "passed": true,  "message": "passed unexpectedly", "expected": "fail" }
+                ] } }
+            ]
+        }"#).unwrap();
+
+        let failed = run.failed_checks().into_iter().collect::<Vec<_>>();
+        assert_eq!(failed, vec![ "failed", "passed unexpectedly" ]);
+    }
+
+    #[test]
+    fn previous_newer_version() {
+        let run: PreviousRun = serde_json::from_str(r#"{ "format_version": 99, "sections": [] }"#).unwrap();
+        assert!(matches!(run.check_version(), Err(PreviousRunError::NewerVersion(99))));
+
+        let run: PreviousRun = serde_json::from_str(r#"{ "sections": [] }"#).unwrap();
+        assert!(run.check_version().is_ok());
+    }
+
+    /// A run with one of everything in it, so every field gets written.
+    fn everything_run(commands: Vec<&RanCommand>) -> CompletedRun<'_> {
+        let passed = CheckOutput {
+            passed: true,
+            skipped: None,
+            check_type: "cmd",
+            name: Some("greeting".into()),
+            tags: vec![ "smoke".into() ],
+            attempts: 2,
+            expected: Expectation::Fail,
+            severity: Severity::Warn,
+            results: vec![ ResultMessage::Passed("it ran".into()), ResultMessage::Failed("no".into()), ResultMessage::Error("oops".into()) ],
+            budget: Some(BudgetUsage::new(Duration::from_secs(1), Duration::from_secs(2))),
+            hook_failures: vec![ HookFailure { stage: HookStage::Teardown, check: Some("greeting".into()), shell: "false".into(), error: "status 1".into() } ],
+            ..CheckOutput::skipped("Command ‘echo hi’ succeeds".into(), String::new(), Some("#1".into()))
+        };
+        let blocked = CheckOutput::blocked("File ‘x’ exists".into(), "needs ‘greeting’".into(), vec![ "greeting".into() ], None);
+
+        let mut results = ResultsSection::from_outputs(vec![ passed, blocked ]).with_hook_failures(vec![
+            HookFailure { stage: HookStage::Setup, check: None, shell: "true".into(), error: "status 1".into() },
+        ]);
+        results.correlations = Some(vec![ CorrelationRecord { property: "host", value: "example.com".into(), failures: 2 } ]);
+
+        CompletedRun {
+            totals: results.totals,
+            sections: vec![
+                CompletedSection { input: InputSource::File("checks.toml".into()), results },
+                CompletedSection { input: InputSource::Stdin, results: ResultsSection::from_outputs(Vec::new()) },
+            ],
+            commands,
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: vec![ SideProcessRecord::new("./server", &[]) ],
+        }
+    }
+
+    #[test]
+    fn schema_matches_documents() {
+        let ran_command = |exit_reason| RanCommand {
+            invocation: "\"sh\" \"-c\" \"true\"".into(),
+            environment: std::iter::once(("LANG".into(), "C".into())).collect(),
+            directory: Some("/tmp".into()),
+            exit_reason,
+            stdout_lines: Vec::new(),
+            stderr_lines: Vec::new(),
+            stdout_raw: Vec::new(),
+            stderr_raw: Vec::new(),
+            output_lines: Vec::new(),
+            runtime: Duration::from_millis(5),
+        };
+
+        let (exited, timed_out) = (ran_command(ExitReason::Status(1)), ran_command(ExitReason::TimedOut));
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        let run = everything_run(vec![ &exited, &timed_out ]);
+        let document = serde_json::json!(VersionedRun::new(&run));
+
+        let mut mismatches = Vec::new();
+        validate(&schema, &schema, &document, "$", &mut mismatches);
+        assert_eq!(mismatches, Vec::<String>::new());
+    }
+
+    /// Checks a value against the parts of JSON Schema that the schema
+    /// uses, collecting the paths of the values that don’t match.
+    fn validate(root: &serde_json::Value, schema: &serde_json::Value, value: &serde_json::Value, path: &str, mismatches: &mut Vec<String>) {
+        use serde_json::Value;
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            let name = reference.trim_start_matches("#/$defs/");
+            return validate(root, &root["$defs"][name], value, path, mismatches);
+        }
+
+        let type_name = match value {
+            Value::Null                      => "null",
+            Value::Bool(_)                   => "boolean",
+            Value::Number(n) if n.is_f64()   => "number",
+            Value::Number(_)                 => "integer",
+            Value::String(_)                 => "string",
+            Value::Array(_)                  => "array",
+            Value::Object(_)                 => "object",
+        };
+
+        let types_match = match &schema["type"] {
+            Value::String(t)  => t == type_name,
+            Value::Array(ts)  => ts.iter().any(|t| t == type_name),
+            _                 => true,
+        };
+
+        if ! types_match {
+            mismatches.push(format!("{} is {}", path, type_name));
+        }
+
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if ! options.contains(value) {
+                mismatches.push(format!("{} is not one of {:?}", path, options));
+            }
+        }
+
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                mismatches.push(format!("{} is not {}", path, constant));
+            }
+        }
+
+        match value {
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    validate(root, &schema["items"], item, &format!("{}[{}]", path, index), mismatches);
+                }
+            }
+            Value::Object(fields) => {
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for field in required {
+                        if ! fields.contains_key(field.as_str().unwrap()) {
+                            mismatches.push(format!("{}.{} is missing", path, field));
+                        }
+                    }
+                }
+
+                for (key, field) in fields {
+                    let field_path = format!("{}.{}", path, key);
+                    match schema.get("properties").and_then(|p| p.get(key)).or_else(|| schema.get("additionalProperties")) {
+                        Some(Value::Bool(false)) | None  => mismatches.push(format!("{} is not in the schema", field_path)),
+                        Some(field_schema)               => validate(root, field_schema, field, &field_path, mismatches),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn toml_document_without_commands() {
+        let mut run = everything_run(Vec::new());
+        run.side_processes.clear();
+
+        let document = toml_document(&run).unwrap();
+        assert!(document.starts_with("commands = []\nformat_version = 1\n"));
+    }
+
+    #[test]
+    fn check_runtimes() {
+        let output = CheckOutput {
+            runtime: Duration::from_millis(1234),
+            ..CheckOutput::skipped("file exists".into(), String::new(), None)
+        };
+
+        let document = serde_json::json!(output);
+        assert_eq!(document["runtime_ms"], 1234);
+    }
+
+    #[test]
+    fn junit_document() {
+        let skipped = CheckOutput::skipped("brew is <installed>".into(), "‘brew’ is not installed".into(), None);
+        let passed = CheckOutput {
+            passed: true,
+            skipped: None,
+            results: vec![ ResultMessage::Passed("it exists".into()) ],
+            runtime: Duration::from_millis(1500),
+            ..CheckOutput::skipped("file exists".into(), String::new(), None)
+        };
+        let failed = CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Passed("it ran".into()), ResultMessage::Failed("status was 1".into()) ],
+            ..CheckOutput::skipped("command succeeds".into(), String::new(), Some("#12".into()))
+        };
+
+        let run = CompletedRun {
+            sections: vec![ CompletedSection {
+                input: InputSource::File("checks.toml".into()),
+                results: ResultsSection::from_outputs(vec![ skipped, passed, failed ]),
+            } ],
+            commands: Vec::new(),
+            totals: Stats::default(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        };
+
+        let xml = junit_xml(&run);
+        assert!(xml.contains(r#"<testsuites name="specsheet" tests="3" failures="1" errors="0" skipped="1" time="1.500""#));
+        assert!(xml.contains(r#"<testcase name="brew is &lt;installed&gt;" classname="checks.toml" time="0.000">
+      <skipped message="‘brew’ is not installed"/>
+    </testcase>"#));
+        assert!(xml.contains(r#"<testcase name="file exists" classname="checks.toml" time="1.500">
+    </testcase>"#));
+        assert!(xml.contains(r#"<failure message="status was 1 (known issue #12)" type="failure">pass: it ran
+fail: status was 1
+</failure>"#));
+    }
+
+    #[test]
+    fn known_issue_failures() {
+        let known = CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Failed("status was 1".into()) ],
+            ..CheckOutput::skipped("command succeeds".into(), String::new(), Some("#12".into()))
+        };
+
+        let results = ResultsSection::from_outputs(vec![ known ]);
+        assert_eq!(results.totals.known_failure_count, 1);
+        assert!(results.fails_run(false));
+        assert!(! results.fails_run(true));
+    }
+
+    #[test]
+    fn errored_checks() {
+        let errored = CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Passed("it exists".into()), ResultMessage::Error("permission denied".into()) ],
+            ..CheckOutput::skipped("file is readable".into(), String::new(), Some("#12".into()))
+        };
+        let failed = CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Failed("status was 1".into()), ResultMessage::Error("no such file".into()) ],
+            ..CheckOutput::skipped("command succeeds".into(), String::new(), None)
+        };
+        let skipped = CheckOutput::skipped("brew is installed".into(), "‘brew’ is not installed".into(), None);
+
+        let results = ResultsSection::from_outputs(vec![ errored, failed, skipped ]);
+        assert_eq!(results.check_outputs[0].status(), CheckStatus::Errored);
+        assert_eq!(results.check_outputs[1].status(), CheckStatus::Failed);
+        assert_eq!((results.totals.check_count, results.totals.err_count, results.totals.fail_count), (3, 1, 1));
+        assert_eq!(results.totals.known_failure_count, 0);
+        assert!(results.failed_unexpectedly());
+        assert_eq!(totals_line(results.totals), "✘ 0 passed, 1 failed, 1 errored, 1 skipped");
+
+        let run = CompletedRun {
+            totals: results.totals,
+            sections: vec![ CompletedSection { input: InputSource::File("checks.toml".into()), results } ],
+            commands: Vec::new(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        };
+
+        let xml = junit_xml(&run);
+        assert!(xml.contains(r#"tests="3" failures="1" errors="1" skipped="1""#));
+        assert!(xml.contains(r#"<error message="permission denied" type="error">"#));
+    }
+
+    #[test]
+    fn markdown_document() {
+        let passed = CheckOutput {
+            passed: true,
+            skipped: None,
+            results: vec![ ResultMessage::Passed("it exists".into()) ],
+            runtime: Duration::from_millis(250),
+            ..CheckOutput::skipped("file exists".into(), String::new(), None)
+        };
+        let failed = CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Failed("status was 1".into()) ],
+            ..CheckOutput::skipped("command | succeeds".into(), String::new(), Some("#12".into()))
+        };
+
+        let section = CompletedSection {
+            input: InputSource::File("checks.toml".into()),
+            results: ResultsSection::from_outputs(vec![ passed, failed ]),
+        };
+
+        let markdown = markdown_section(&section);
+        assert!(markdown.starts_with("\n## checks.toml\n\n✘ 1 passed, 1 failed\n\n"));
+        assert!(markdown.contains("| ✔ passed | file exists | 0.250s |\n"));
+        assert!(markdown.contains("| ✘ failed | command \\| succeeds (known issue #12) | 0.000s |\n"));
+        assert!(markdown.contains("<summary>✘ failed command \\| succeeds</summary>\n\n- ✘ status was 1\n"));
+    }
+
+    #[test]
+    fn html_report_checks() {
+        let passed = CheckOutput {
+            passed: true,
+            skipped: None,
+            check_type: "fs",
+            tags: vec![ "smoke".into(), "files".into() ],
+            ..CheckOutput::skipped("file <exists>".into(), String::new(), None)
+        };
+
+        let run = CompletedRun {
+            sections: vec![ CompletedSection {
+                input: InputSource::File("checks.toml".into()),
+                results: ResultsSection::from_outputs(vec![ passed ]),
+            } ],
+            commands: Vec::new(),
+            totals: Stats::default(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        };
+
+        let html = html_report(&run);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(r#"<li class="check passed" data-status="passed" data-type="fs" data-tags="smoke files">"#));
+        assert!(html.contains(r#"<span class="message">file &lt;exists&gt;</span>"#));
+        assert!(html.contains(r#"<option value="fs">fs</option>"#));
+        assert!(html.contains(r#"<option value="files">files</option><option value="smoke">smoke</option>"#));
+    }
+
+    #[test]
+    fn html_report_trends() {
+        let trend = CheckTrend {
+            input: "checks.toml".into(),
+            check: "Command ‘true’ succeeds".into(),
+            failing_since: None,
+            recent_runs: 4,
+            recent_passes: 3,
+            average_runtime: None,
+            runtime: Duration::from_millis(5),
+        };
+
+        let run = CompletedRun {
+            sections: Vec::new(),
+            commands: Vec::new(),
+            totals: Stats::default(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: vec![ trend ],
+            side_processes: Vec::new(),
+        };
+
+        let html = html_report(&run);
+        assert!(html.contains(r#"<section class="trends"><h2>Trends</h2><ul><li>Command ‘true’ succeeds (checks.toml): passed 3 of the last 4 runs</li></ul></section>"#));
+    }
+
+    #[test]
+    fn donut_segments() {
+        let svg = donut_chart(&[ (CheckStatus::Passed, 3), (CheckStatus::Failed, 1) ]);
+        assert!(svg.contains(r#"class="passed" stroke-dasharray="75.000 25.000" stroke-dashoffset="25.000""#));
+        assert!(svg.contains(r#"class="failed" stroke-dasharray="25.000 75.000" stroke-dashoffset="-50.000""#));
+    }
+
+    #[test]
+    fn prometheus_document() {
+        let passed = CheckOutput {
+            passed: true,
+            skipped: None,
+            check_type: "fs",
+            name: Some("passwd".into()),
+            runtime: Duration::from_millis(1500),
+            ..CheckOutput::skipped("File ‘/etc/\"passwd\"’ exists".into(), String::new(), None)
+        };
+        let skipped = CheckOutput::skipped("Package ‘git’ is installed".into(), "‘brew’ is not installed".into(), None);
+
+        let results = ResultsSection::from_outputs(vec![ passed, skipped ]);
+        let run = CompletedRun {
+            totals: results.totals,
+            sections: vec![ CompletedSection { input: InputSource::File("checks.toml".into()), results } ],
+            commands: Vec::new(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        };
+
+        let metrics = prometheus_metrics(&run);
+        assert!(metrics.contains("\nspecsheet_check_passed{file=\"checks.toml\",type=\"fs\",check=\"File ‘/etc/\\\"passwd\\\"’ exists\",name=\"passwd\"} 1\n"));
+        assert!(metrics.contains("\nspecsheet_check_duration_seconds{file=\"checks.toml\",type=\"fs\",check=\"File ‘/etc/\\\"passwd\\\"’ exists\",name=\"passwd\"} 1.500000\n"));
+        assert!(! metrics.contains("git"));
+        assert!(metrics.contains("\nspecsheet_checks{result=\"passed\"} 1\n"));
+        assert!(metrics.contains("\nspecsheet_checks{result=\"skipped\"} 1\n"));
+    }
+
+    #[test]
+    fn csv_document() {
+        let failed = CheckOutput {
+            skipped: None,
+            check_type: "cmd",
+            tags: vec![ "smoke".into(), "shell".into() ],
+            results: vec![ ResultMessage::Passed("it ran".into()), ResultMessage::Failed("stdout was \"no\"".into()) ],
+            runtime: Duration::from_millis(20),
+            ..CheckOutput::skipped("Command ‘echo no’ succeeds".into(), String::new(), None)
+        };
+        let skipped = CheckOutput::skipped("Package ‘git’ is installed".into(), "‘brew’ is not installed".into(), None);
+
+        let run = CompletedRun {
+            sections: vec![ CompletedSection {
+                input: InputSource::File("checks.toml".into()),
+                results: ResultsSection::from_outputs(vec![ failed, skipped ]),
+            } ],
+            commands: Vec::new(),
+            totals: Stats::default(),
+            metadata: RunMetadata::capture(Timestamp::now()),
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        };
+
+        assert_eq!(csv_rows(&run), "file,type,name,description,tags,result,duration,message\r\n\
+                                    checks.toml,cmd,,Command ‘echo no’ succeeds,\"smoke, shell\",failed,0.020,\"stdout was \"\"no\"\"\"\r\n\
+                                    checks.toml,,,Package ‘git’ is installed,,skipped,0.000,‘brew’ is not installed\r\n");
+    }
+
+    #[test]
+    fn code_fences() {
+        assert_eq!(code_fence("echo hello"), "```");
+        assert_eq!(code_fence("echo ````"), "`````");
+    }
+
+    #[test]
+    fn xml_escaping() {
+        assert_eq!(xml_escape("<a href=\"x\">&\x1B[31m'</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;[31m&apos;&lt;/a&gt;");
+    }
+}
@@ -0,0 +1,73 @@
+//! The dry run, which shows the commands that would be run without running
+//! any of them.
+//!
+//! Unlike listing the commands, each one is shown exactly as it would be
+//! run — wrapped to run on the target or with sudo, with its environment
+//! variables and its directory — in a form that can be pasted into a shell.
+//! The programs they run get looked for on the `$PATH`, so missing tools
+//! can be spotted before running anything for real.
+
+use std::collections::BTreeSet;
+
+use log::*;
+use shell_words::quote;
+
+use spec_exec::{Command, Executor, Target};
+
+use crate::commands::Commands;
+use crate::preflight::is_installed;
+
+
+/// Prints every command that has been primed, resolved the way the given
+/// executor would run it, then returns the names of the programs they run
+/// that could not be found.
+pub fn print_commands(executor: &Executor, commands: Commands) -> BTreeSet<String> {
+    let mut missing = BTreeSet::new();
+
+    for (command, options) in commands.list_commands() {
+        let program = command.get_program().to_os_string();
+        let resolved = executor.resolve(command, &options);
+        println!("{}", shell_form(&resolved));
+
+        // Commands run somewhere else only need the wrapper installed
+        // here; the program itself has to be installed on the target.
+        let mut programs = vec![ resolved.get_program() ];
+        if executor.target() == &Target::Local {
+            programs.push(&program);
+        }
+
+        for program in programs {
+            if ! is_installed(program, resolved.get_current_dir()) {
+                debug!("Program not found -> {:?}", program);
+                missing.insert(program.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    missing
+}
+
+/// Writes out the command as a line of shell script that runs it, with
+/// every word quoted where it needs to be. A command with a directory
+/// changes to it in a subshell, so pasting the line leaves the shell’s own
+/// directory alone.
+pub fn shell_form(command: &Command) -> String {
+    let mut words = Vec::new();
+
+    for (key, value) in command.get_envs() {
+        if let Some(value) = value {
+            words.push(format!("{}={}", key.to_string_lossy(), quote(&value.to_string_lossy())));
+        }
+    }
+
+    words.push(quote(&command.get_program().to_string_lossy()).into_owned());
+    for arg in command.get_args() {
+        words.push(quote(&arg.to_string_lossy()).into_owned());
+    }
+
+    let line = words.join(" ");
+    match command.get_current_dir() {
+        Some(directory)  => format!("(cd {} && {})", quote(&directory.to_string_lossy()), line),
+        None             => line,
+    }
+}
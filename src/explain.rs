@@ -0,0 +1,268 @@
+//! Explaining checks, which shows everything about one check without
+//! running it, for people getting to know a check document someone else
+//! wrote.
+//!
+//! An explanation has what the check checks, its parameters as TOML once
+//! rewrite rules have been applied, the commands it would run exactly as
+//! they would be run, and the results it can have and when it has each.
+
+use std::time::Duration;
+
+use spec_checks::load::TomlValue;
+use spec_exec::{Command, Executor, RunOptions};
+
+use crate::commands::Retry;
+use crate::dry_run::shell_form;
+use crate::results::{CheckStatus, Expectation, Severity};
+use crate::set::ListedCheck;
+use crate::terminal_ui::format_runtime;
+
+
+/// Everything there is to know about a check before running it.
+#[derive(Debug)]
+pub struct Explanation {
+
+    /// The check’s type, name, tags, description, and location.
+    pub listed: ListedCheck,
+
+    /// The check’s parameters, after rewrite rules have been applied.
+    pub parameters: TomlValue,
+
+    /// The commands the check would run, and the options they would be
+    /// run with.
+    pub commands: Vec<(Command, RunOptions)>,
+
+    /// The descriptions of the checks that get run along with this one.
+    pub secondaries: Vec<String>,
+
+    /// The programs the check runs that aren’t installed.
+    pub missing_programs: Vec<String>,
+
+    /// The commands to run before and after the check.
+    pub setup: Vec<String>,
+    pub teardown: Vec<String>,
+
+    pub expected: Expectation,
+    pub severity: Severity,
+    pub known_issue: Option<String>,
+    pub retry: Retry,
+    pub budget: Option<Duration>,
+
+    /// The names of the checks that have to pass for this one to be run.
+    pub needs: Vec<String>,
+
+    /// Why the check wouldn’t be run on this machine, if it wouldn’t.
+    pub excluded: Option<String>,
+}
+
+
+/// Prints an explanation of a check, with its commands resolved the way
+/// the given executor would run them.
+pub fn print_explanation(explanation: Explanation, executor: &Executor) {
+    let outcomes = outcomes(&explanation);
+    let listed = &explanation.listed;
+    println!("[{}] {}", listed.check_type, listed.description);
+
+    if let Some(name) = &listed.name {
+        println!("   name: {}", name);
+    }
+
+    if ! listed.tags.is_empty() {
+        println!("   tags: {}", listed.tags.join(", "));
+    }
+
+    match (&listed.location.file, listed.location.start) {
+        (Some(file), Some(start))  => println!("   written at: {}:{}", file.display(), start),
+        (Some(file), None)         => println!("   written in: {}", file.display()),
+        (None, Some(start))        => println!("   written at: line {}, column {}", start.line, start.column),
+        (None, None)               => {}
+    }
+
+    println!("\nParameters:");
+    for line in parameter_lines(&explanation.parameters) {
+        println!("   {}", line);
+    }
+
+    println!("\nCommands:");
+    if explanation.commands.is_empty() {
+        println!("   (none; this check examines the machine specsheet runs on directly)");
+    }
+
+    for (command, options) in explanation.commands {
+        let resolved = executor.resolve(command, &options);
+        println!("   {}", shell_form(&resolved));
+    }
+
+    for (title, hooks) in [ ("Setup", &explanation.setup), ("Teardown", &explanation.teardown) ] {
+        if ! hooks.is_empty() {
+            println!("\n{}:", title);
+            for shell in hooks {
+                println!("   {}", shell);
+            }
+        }
+    }
+
+    println!("\nOutcomes:");
+    for outcome in outcomes {
+        println!("   - {}", outcome);
+    }
+
+    println!();
+}
+
+/// Lists the results the check can have, and when it has each of them,
+/// followed by anything else that happens when it gets run.
+fn outcomes(explanation: &Explanation) -> Vec<String> {
+    let mut outcomes = Vec::new();
+    let description = lowercase_first(&explanation.listed.description);
+    let mut outcome = |status: CheckStatus, when: String| outcomes.push(format!("{}: {}", status.name(), when));
+
+    if let Some(reason) = &explanation.excluded {
+        outcome(CheckStatus::Skipped, format!("always on this machine, because {}.", reason));
+    }
+
+    if ! explanation.missing_programs.is_empty() {
+        outcome(CheckStatus::Skipped, format!("always, because these programs aren’t installed: {}.", explanation.missing_programs.join(", ")));
+    }
+
+    if ! explanation.needs.is_empty() {
+        outcome(CheckStatus::Blocked, format!("if any of these checks don’t pass: {}.", explanation.needs.join(", ")));
+    }
+
+    match (explanation.expected, explanation.severity) {
+        (Expectation::Fail, _) => {
+            outcome(CheckStatus::ExpectedFailure, format!("if it finds that it’s not the case that {}, which doesn’t fail the run.", description));
+            outcome(CheckStatus::UnexpectedPass, format!("if {}.", description));
+        }
+        (Expectation::Pass, severity) => {
+            outcome(CheckStatus::Passed, format!("if {}.", description));
+
+            match (severity, &explanation.known_issue) {
+                (Severity::Warn, _) => {
+                    outcome(CheckStatus::Warning, "if not, which doesn’t fail the run unless --fail-on-warn is given.".into());
+                }
+                (Severity::Error, Some(known_issue)) => {
+                    outcome(CheckStatus::Failed, format!("if not, which is known issue ‘{}’, so it doesn’t fail the run with --allow-known-issues.", known_issue));
+                }
+                (Severity::Error, None) => {
+                    outcome(CheckStatus::Failed, "if not, which fails the run.".into());
+                }
+            }
+        }
+    }
+
+    match (explanation.commands.is_empty(), explanation.setup.is_empty()) {
+        (false, false)  => outcome(CheckStatus::Errored, "if one of its setup commands fails, or one of its commands can’t be run or exits unexpectedly.".into()),
+        (false, true)   => outcome(CheckStatus::Errored, "if one of its commands can’t be run or exits unexpectedly.".into()),
+        (true, false)   => outcome(CheckStatus::Errored, "if one of its setup commands fails.".into()),
+        (true, true)    => {}
+    }
+
+    if explanation.expected == Expectation::Fail && explanation.known_issue.is_some() {
+        outcomes.push("If it passes, its known issue annotation can be removed.".into());
+    }
+
+    if explanation.retry.retries > 0 && explanation.retry.delay.is_zero() {
+        outcomes.push(format!("If it fails, it gets run up to {} more times before its failure counts.", explanation.retry.retries));
+    }
+    else if explanation.retry.retries > 0 {
+        outcomes.push(format!("If it fails, it gets run up to {} more times, {} apart, before its failure counts.", explanation.retry.retries, format_runtime(explanation.retry.delay)));
+    }
+
+    if let Some(budget) = explanation.budget {
+        outcomes.push(format!("It should take less than {}; going over gets reported, but doesn’t make it fail.", format_runtime(budget)));
+    }
+
+    for secondary in &explanation.secondaries {
+        outcomes.push(format!("Its parameters add another check, which gets run straight after it: {}", secondary));
+    }
+
+    outcomes
+}
+
+/// Writes out a check’s parameters as TOML, one key to a line, the way
+/// they could be written in its document.
+fn parameter_lines(parameters: &TomlValue) -> Vec<String> {
+    match parameters {
+        TomlValue::Table(table) if table.is_empty() => {
+            vec![ "(none)".into() ]
+        }
+        TomlValue::Table(table) => {
+            table.iter().map(|(key, value)| format!("{} = {}", toml_key(key), inline_toml(value))).collect()
+        }
+        other => {
+            vec![ inline_toml(other) ]
+        }
+    }
+}
+
+/// Writes out a TOML value on one line, with tables as inline tables.
+fn inline_toml(value: &TomlValue) -> String {
+    match value {
+        TomlValue::Table(table) if table.is_empty() => {
+            "{}".into()
+        }
+        TomlValue::Table(table) => {
+            let pairs = table.iter().map(|(key, value)| format!("{} = {}", toml_key(key), inline_toml(value))).collect::<Vec<_>>();
+            format!("{{ {} }}", pairs.join(", "))
+        }
+        TomlValue::Array(array) => {
+            let values = array.iter().map(inline_toml).collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        scalar => {
+            scalar.to_string()
+        }
+    }
+}
+
+/// Writes out a TOML key, which only needs quotes if it isn’t made of
+/// letters, numbers, dashes, and underscores.
+fn toml_key(key: &str) -> String {
+    if ! key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        key.into()
+    }
+    else {
+        TomlValue::String(key.into()).to_string()
+    }
+}
+
+/// Lowercases the first letter of a check description, so it can go in
+/// the middle of a sentence, unless it starts an acronym such as ‘TCP’.
+fn lowercase_first(description: &str) -> String {
+    let mut chars = description.chars();
+    match (chars.next(), chars.next()) {
+        (Some(first), Some(second)) if ! second.is_uppercase() => {
+            first.to_lowercase().chain(std::iter::once(second)).chain(chars).collect()
+        }
+        _ => {
+            description.into()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parameters_as_toml() {
+        let parameters = toml::toml! {
+            url = "http://localhost:8080/health"
+            status = [ 200, 204 ]
+            headers = { Accept = "text/plain", "X Custom" = "yes" }
+        };
+
+        assert_eq!(parameter_lines(&parameters), vec![
+            "headers = { Accept = \"text/plain\", \"X Custom\" = \"yes\" }",
+            "status = [200, 204]",
+            "url = \"http://localhost:8080/health\"",
+        ]);
+    }
+
+    #[test]
+    fn no_parameters() {
+        assert_eq!(parameter_lines(&TomlValue::Table(toml::map::Map::new())), vec![ "(none)" ]);
+    }
+}
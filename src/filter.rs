@@ -1,11 +1,20 @@
-//! Filtering checks by their tags and types, and reordering the list after
-//! they have all been loaded.
+//! Filtering checks by their tags, types, and names, and reordering the
+//! list after they have all been loaded.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::path::PathBuf;
+
+use regex::Regex;
 
 
 #[derive(PartialEq, Debug, Default)]
 pub struct Filter {
     pub tags: TagsFilter,
     pub types: TypesFilter,
+    pub names: NamesFilter,
+    pub rerun: RerunFilter,
+    pub shard: Option<Shard>,
     pub order: RunningOrder,
 }
 
@@ -21,6 +30,43 @@ pub struct TypesFilter {
     pub skip_types: Vec<String>,
 }
 
+/// Regexes to match against the names of checks, or against their
+/// descriptions for checks that don’t have names.
+#[derive(Debug, Default)]
+pub struct NamesFilter {
+    pub names: Vec<Regex>,
+    pub skip_names: Vec<Regex>,
+}
+
+/// Which checks to run again, based on the results of a previous run.
+#[derive(PartialEq, Debug, Default)]
+pub enum RerunFilter {
+
+    /// Run every check, whether or not there was a previous run.
+    #[default]
+    Everything,
+
+    /// Only run the checks that failed in the result document at this
+    /// path, which has not been read yet.
+    FailedIn(PathBuf),
+
+    /// Only run the checks with these descriptions, which failed in the
+    /// previous run.
+    Only(BTreeSet<String>),
+}
+
+/// One of several parts that the checks get split into, so they can be
+/// run by separate jobs. Each check always ends up in the same shard.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Shard {
+
+    /// Which shard this is, counting from one.
+    pub index: u64,
+
+    /// How many shards there are in total.
+    pub count: u64,
+}
+
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum RunningOrder {
     ByType,
@@ -69,6 +115,67 @@ impl TypesFilter {
     }
 }
 
+impl NamesFilter {
+
+    /// Whether this filter should load a check with the given name, if it
+    /// has one, and description. A regex only has to match one of them.
+    pub fn should_include_check(&self, name: Option<&str>, description: &str) -> bool {
+        let matches = |regex: &Regex| name.is_some_and(|n| regex.is_match(n)) || regex.is_match(description);
+
+        if self.skip_names.iter().any(matches) {
+            false
+        }
+        else if self.names.is_empty() {
+            true
+        }
+        else {
+            self.names.iter().any(matches)
+        }
+    }
+}
+
+impl RerunFilter {
+
+    /// Whether this filter should load a check with the given description.
+    pub fn should_include_check(&self, description: &str) -> bool {
+        match self {
+            Self::Only(descriptions)                => descriptions.contains(description),
+            Self::Everything | Self::FailedIn(_)    => true,
+        }
+    }
+}
+
+impl Shard {
+
+    /// Whether a check of the given type and description is in this shard.
+    ///
+    /// This uses FNV-1a rather than the standard library’s hasher, as the
+    /// hash has to stay the same between builds and versions of specsheet
+    /// for every job to agree on which shard each check is in.
+    pub fn should_include_check(self, check_type: &str, description: &str) -> bool {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+        for byte in check_type.bytes().chain(iter::once(0)).chain(description.bytes()) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+
+        hash % self.count == self.index - 1
+    }
+}
+
+/// Regexes can’t be compared, so the filters get compared by their source.
+impl PartialEq for NamesFilter {
+    fn eq(&self, other: &Self) -> bool {
+        fn sources(regexes: &[Regex]) -> Vec<&str> {
+            regexes.iter().map(Regex::as_str).collect()
+        }
+
+        sources(&self.names) == sources(&other.names)
+            && sources(&self.skip_names) == sources(&other.skip_names)
+    }
+}
+
 
 #[cfg(test)]
 mod test {
@@ -153,4 +260,57 @@ mod test {
         assert_eq!(true,  filter.tags.should_include_tags(&[ "green" ]));
         assert_eq!(false, filter.tags.should_include_tags(EMPTY));
     }
+
+    #[test]
+    fn only_certain_names() {
+        let mut filter = Filter::default();
+        filter.names.names.push(Regex::new("^web").unwrap());
+
+        assert_eq!(true,  filter.names.should_include_check(Some("web is up"), "HTTP request to ‘https://example.com/’"));
+        assert_eq!(true,  filter.names.should_include_check(None, "web server is installed"));
+        assert_eq!(false, filter.names.should_include_check(Some("db is up"), "TCP port ‘5432’ is open"));
+    }
+
+    #[test]
+    fn skip_certain_names() {
+        let mut filter = Filter::default();
+        filter.names.skip_names.push(Regex::new("5432").unwrap());
+
+        assert_eq!(true,  filter.names.should_include_check(Some("web is up"), "HTTP request to ‘https://example.com/’"));
+        assert_eq!(false, filter.names.should_include_check(Some("db is up"), "TCP port ‘5432’ is open"));
+        assert_eq!(false, filter.names.should_include_check(None, "TCP port ‘5432’ is open"));
+    }
+
+    #[test]
+    fn names_and_skip_names() {
+        let mut filter = Filter::default();
+        filter.names.names.push(Regex::new("port").unwrap());
+        filter.names.skip_names.push(Regex::new("5432").unwrap());
+
+        assert_eq!(true,  filter.names.should_include_check(None, "TCP port ‘22’ is open"));
+        assert_eq!(false, filter.names.should_include_check(None, "TCP port ‘5432’ is open"));
+        assert_eq!(false, filter.names.should_include_check(None, "Command ‘true’ executes"));
+    }
+
+    #[test]
+    fn shards_split_checks() {
+        let descriptions = (0 .. 100).map(|n| format!("TCP port ‘{}’ is open", n)).collect::<Vec<_>>();
+
+        let shards = (1 ..= 3).map(|index| Shard { index, count: 3 }).collect::<Vec<_>>();
+        for description in &descriptions {
+            let in_shards = shards.iter().filter(|s| s.should_include_check("tcp", description)).count();
+            assert_eq!(in_shards, 1);
+        }
+
+        for shard in &shards {
+            assert!(descriptions.iter().any(|d| shard.should_include_check("tcp", d)));
+        }
+    }
+
+    #[test]
+    fn shards_are_stable() {
+        let shard = Shard { index: 1, count: 2 };
+        assert_eq!(false, shard.should_include_check("tcp", "TCP port ‘22’ is open"));
+        assert_eq!(true,  shard.should_include_check("cmd", "Command ‘false’ executes"));
+    }
 }
@@ -0,0 +1,205 @@
+//! Reading and writing command fixtures, for recording and replaying runs.
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use spec_exec::{RanCommand, OutputLine, OutputStream, ExitReason, Recording};
+
+
+/// A **fixtures file** holds every command that was run during a run, so
+/// they can be replayed later without running anything.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
+struct FixturesFile {
+
+    #[serde(default, rename = "command")]
+    commands: Vec<Fixture>,
+}
+
+/// One command that was run, and what happened when it was.
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+pub struct Fixture {
+
+    /// How the command was invoked, which is how commands get matched up
+    /// with their fixtures when replaying.
+    invocation: String,
+
+    /// The directory the command was run from, if it wasn’t specsheet’s
+    /// own current directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    directory: Option<PathBuf>,
+
+    /// The exit status of the process, if it exited normally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<i32>,
+
+    /// The signal that killed the process, if it was killed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signal: Option<i32>,
+
+    /// Whether the process was killed because it ran for too long.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    #[serde(default)]
+    stdout: Vec<String>,
+
+    #[serde(default)]
+    stderr: Vec<String>,
+
+    #[serde(default)]
+    runtime_ms: u64,
+
+    /// The environment variables that were set for the command. This has
+    /// to come last, as it gets written as a table.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    environment: BTreeMap<String, String>,
+}
+
+/// Writes every command that has been run to a fixtures file at the
+/// given path.
+pub fn record(path: &Path, ran_commands: &[&RanCommand]) -> io::Result<()> {
+    let file = FixturesFile {
+        commands: ran_commands.iter().copied().map(Fixture::from_ran_command).collect(),
+    };
+
+    let toml = toml::to_string(&file).map_err(io::Error::other)?;
+    fs::write(path, toml)
+}
+
+/// Reads the fixtures file at the given path into a recording that an
+/// executor can replay.
+pub fn load(path: &Path) -> Result<Recording, FixturesError> {
+    let contents = fs::read_to_string(path).map_err(FixturesError::Io)?;
+    let file: FixturesFile = toml::from_str(&contents).map_err(FixturesError::Toml)?;
+    Ok(Recording::new(file.commands.into_iter().map(Fixture::into_ran_command)))
+}
+
+
+impl Fixture {
+    pub fn from_ran_command(ran_command: &RanCommand) -> Self {
+        let (status, signal, timed_out) = match ran_command.exit_reason {
+            ExitReason::Status(s)  => (Some(s), None, false),
+            ExitReason::Signal(s)  => (None, Some(s), false),
+            ExitReason::TimedOut   => (None, None, true),
+            _                      => (None, None, false),
+        };
+
+        let lines = |lines: &[OutputLine]| lines.iter().map(|l| l.line.to_string()).collect();
+
+        Self {
+            invocation: ran_command.invocation.clone(),
+            directory: ran_command.directory.clone(),
+            status, signal, timed_out,
+            stdout: lines(&ran_command.stdout_lines),
+            stderr: lines(&ran_command.stderr_lines),
+            runtime_ms: ran_command.runtime.as_millis().try_into().unwrap_or(u64::MAX),
+            environment: ran_command.environment.clone(),
+        }
+    }
+
+    /// Turns this fixture back into a command that has been run. The lines
+    /// of output all get given the current time, as their original
+    /// timestamps aren’t kept, and the raw output is rebuilt from them. As
+    /// the order of the two streams isn’t kept either, the merged output
+    /// has all of standard output before standard error.
+    pub fn into_ran_command(self) -> RanCommand {
+        let exit_reason = match (self.status, self.signal, self.timed_out) {
+            (_, _, true)        => ExitReason::TimedOut,
+            (Some(s), _, _)     => ExitReason::Status(s),
+            (None, Some(s), _)  => ExitReason::Signal(s),
+            (None, None, _)     => ExitReason::Unknown,
+        };
+
+        let now = SystemTime::now();
+        let lines = |lines: &[String]| lines.iter().map(|line| OutputLine { timestamp: now, line: Arc::from(&**line) }).collect();
+        let raw = |lines: &[String]| lines.iter().flat_map(|line| line.bytes().chain(Some(b'\n'))).collect();
+
+        let stdout_lines: Vec<OutputLine> = lines(&self.stdout);
+        let stderr_lines: Vec<OutputLine> = lines(&self.stderr);
+        let output_lines = stdout_lines.iter().map(|l| (OutputStream::Stdout, l.clone()))
+                                 .chain(stderr_lines.iter().map(|l| (OutputStream::Stderr, l.clone())))
+                                 .collect();
+
+        RanCommand {
+            invocation: self.invocation,
+            environment: self.environment,
+            directory: self.directory,
+            exit_reason,
+            stdout_lines,
+            stderr_lines,
+            output_lines,
+            stdout_raw: raw(&self.stdout),
+            stderr_raw: raw(&self.stderr),
+            runtime: Duration::from_millis(self.runtime_ms),
+        }
+    }
+}
+
+
+/// Something that can go wrong while loading a fixtures file.
+#[derive(Debug)]
+pub enum FixturesError {
+
+    /// The file could not be read.
+    Io(io::Error),
+
+    /// The file was not valid TOML, or was missing fields.
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for FixturesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e)    => write!(f, "{}", e),
+            Self::Toml(e)  => write!(f, "Invalid fixtures: {}", e),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dig_fixture() -> Fixture {
+        Fixture {
+            invocation: r#""dig" "example.com""#.into(),
+            directory: None,
+            status: Some(0),
+            signal: None,
+            timed_out: false,
+            stdout: vec![ "1.2.3.4".into() ],
+            stderr: vec![],
+            runtime_ms: 12,
+            environment: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let ran_command = dig_fixture().into_ran_command();
+        assert_eq!(ran_command.exit_reason, ExitReason::Status(0));
+        assert_eq!(ran_command.stdout_lines(), vec![ Arc::from("1.2.3.4") ]);
+        assert_eq!(Fixture::from_ran_command(&ran_command), dig_fixture());
+    }
+
+    #[test]
+    fn timed_out() {
+        let file: FixturesFile = toml::from_str("[[command]]\ninvocation = 'sleep 9'\ntimed_out = true\n").unwrap();
+        let ran_command = file.commands.into_iter().next().unwrap().into_ran_command();
+        assert_eq!(ran_command.exit_reason, ExitReason::TimedOut);
+    }
+
+    #[test]
+    fn missing_invocation() {
+        assert!(toml::from_str::<FixturesFile>("[[command]]\nstatus = 0\n").is_err());
+    }
+}
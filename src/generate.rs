@@ -0,0 +1,347 @@
+//! Generating starter checks from the machine specsheet gets run on, so a
+//! spec can be bootstrapped from a host that’s known to be good, rather
+//! than every check having to be written by hand.
+//!
+//! Each type of thing gets listed by running a command, such as
+//! `apt-mark showmanual` for the packages that were installed on purpose,
+//! and each thing it lists becomes a check that it’s still there. The
+//! checks get written out as TOML, one section for each type, for a human
+//! to go through and trim down.
+
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use log::*;
+
+use spec_exec::{Command, Executor, ExecError};
+
+
+/// A **generator** lists one type of thing on the machine, and writes a
+/// check for each of them.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Generator {
+
+    /// Packages that were installed with apt on purpose, rather than as
+    /// another package’s dependency.
+    Apt,
+
+    /// Homebrew formulae that aren’t another formula’s dependency.
+    Homebrew,
+
+    /// Systemd services that are enabled.
+    Systemd,
+
+    /// TCP ports that something is listening on.
+    Tcp,
+
+    /// Every user account.
+    User,
+
+    /// Every group.
+    Group,
+}
+
+/// One check to write out, as its parameters and their values.
+#[derive(PartialEq, Debug)]
+struct StarterCheck {
+    parameters: Vec<(&'static str, Value)>,
+}
+
+/// The value of one of a starter check’s parameters.
+#[derive(PartialEq, Debug)]
+enum Value {
+    String(String),
+    Number(u16),
+}
+
+impl Generator {
+
+    /// Every generator, in the order they get run when all of them are
+    /// asked for.
+    pub const ALL: [Self; 6] = [ Self::Apt, Self::Homebrew, Self::Systemd, Self::Tcp, Self::User, Self::Group ];
+
+    /// Parses the name of a generator, which is also the name of the type
+    /// of check it writes.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "apt"                => Some(Self::Apt),
+            "homebrew" | "brew"  => Some(Self::Homebrew),
+            "systemd"            => Some(Self::Systemd),
+            "tcp"                => Some(Self::Tcp),
+            "user"               => Some(Self::User),
+            "group"              => Some(Self::Group),
+            _                    => None,
+        }
+    }
+
+    /// The type of check this generator writes.
+    fn check_type(self) -> &'static str {
+        match self {
+            Self::Apt       => "apt",
+            Self::Homebrew  => "homebrew",
+            Self::Systemd   => "systemd",
+            Self::Tcp       => "tcp",
+            Self::User      => "user",
+            Self::Group     => "group",
+        }
+    }
+
+    /// The things this generator lists, for the comment above its checks
+    /// and for errors.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Apt       => "packages installed with apt",
+            Self::Homebrew  => "formulae installed with Homebrew",
+            Self::Systemd   => "enabled systemd services",
+            Self::Tcp       => "listening TCP ports",
+            Self::User      => "users",
+            Self::Group     => "groups",
+        }
+    }
+
+    /// The command that lists the things on the machine.
+    fn command(self) -> Command {
+        let mut cmd;
+
+        match self {
+            Self::Apt => {
+                cmd = Command::new("apt-mark");
+                cmd.arg("showmanual");
+            }
+            Self::Homebrew => {
+                cmd = Command::new("brew");
+                cmd.env("HOMEBREW_NO_AUTO_UPDATE", "1");
+                cmd.arg("leaves");
+            }
+            Self::Systemd => {
+                cmd = Command::new("systemctl");
+                cmd.arg("list-unit-files").arg("--type=service").arg("--state=enabled");
+                cmd.arg("--no-legend").arg("--no-pager");
+            }
+            Self::Tcp => {
+                cmd = Command::new("ss");
+                cmd.arg("--listening").arg("--tcp").arg("--numeric").arg("--no-header");
+            }
+            Self::User => {
+                cmd = Command::new("getent");
+                cmd.arg("passwd");
+            }
+            Self::Group => {
+                cmd = Command::new("getent");
+                cmd.arg("group");
+            }
+        }
+
+        cmd
+    }
+
+    /// Runs the command that lists the things on the machine, and returns
+    /// the section of TOML with a check for each of them.
+    pub fn generate(self, executor: &mut Executor) -> Result<String, ExecError> {
+        let ran_command = executor.run_and_store(self.command())?;
+        ran_command.exit_reason.should_be(0)?;
+
+        let checks = self.starter_checks(&ran_command.stdout_lines());
+        debug!("Generated {} {} checks", checks.len(), self.check_type());
+        Ok(self.write_checks(&checks))
+    }
+
+    /// Turns the lines of output from the command into starter checks.
+    fn starter_checks(self, lines: &[Arc<str>]) -> Vec<StarterCheck> {
+        match self {
+            Self::Apt       => named_checks("package", lines.iter().map(|line| line.trim()).collect()),
+            Self::Homebrew  => named_checks("formula", lines.iter().map(|line| line.trim()).collect()),
+            Self::Systemd   => service_checks(lines),
+            Self::Tcp       => port_checks(lines),
+            Self::User      => named_checks("user", lines.iter().filter_map(|line| line.split(':').next()).collect()),
+            Self::Group     => named_checks("group", lines.iter().filter_map(|line| line.split(':').next()).collect()),
+        }
+    }
+
+    /// Writes out the starter checks as a section of TOML, with a comment
+    /// saying where they came from.
+    fn write_checks(self, checks: &[StarterCheck]) -> String {
+        use std::fmt::Write;
+
+        let mut toml = String::new();
+        let _ = writeln!(toml, "# {} {}", checks.len(), self.description());
+
+        for check in checks {
+            let _ = writeln!(toml, "\n[[{}]]", self.check_type());
+            for (key, value) in &check.parameters {
+                let _ = writeln!(toml, "{} = {}", key, value.to_toml());
+            }
+        }
+
+        toml
+    }
+}
+
+
+/// Makes a check with the given parameter for each non-empty name, in
+/// alphabetical order, without any duplicates.
+fn named_checks(parameter: &'static str, names: Vec<&str>) -> Vec<StarterCheck> {
+    let names = names.into_iter().filter(|name| ! name.is_empty()).collect::<BTreeSet<_>>();
+
+    names.into_iter()
+         .map(|name| StarterCheck { parameters: vec![ (parameter, Value::String(name.into())) ] })
+         .collect()
+}
+
+/// Makes a check for each enabled service listed by `systemctl`, such as
+/// `ssh.service  enabled  enabled`. Template units, such as
+/// `getty@.service`, can’t be checked without an instance, so they get
+/// skipped.
+fn service_checks(lines: &[Arc<str>]) -> Vec<StarterCheck> {
+    let names = lines.iter()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|unit| unit.strip_suffix(".service"))
+        .filter(|name| ! name.ends_with('@'))
+        .collect::<BTreeSet<_>>();
+
+    names.into_iter()
+         .map(|name| StarterCheck { parameters: vec![
+             ("service",     Value::String(name.into())),
+             ("enablement",  Value::String("enabled".into())),
+         ] })
+         .collect()
+}
+
+/// Makes a check for each port listed by `ss`, such as
+/// `LISTEN 0 128 0.0.0.0:22 0.0.0.0:*`. Ports that are listened on from
+/// every address, or from `127.0.0.1`, get checked on `127.0.0.1`, which is
+/// the default; the others get checked on the address they’re listened on.
+fn port_checks(lines: &[Arc<str>]) -> Vec<StarterCheck> {
+    let mut ports = BTreeSet::new();
+
+    for line in lines {
+        let Some(local) = line.split_whitespace().nth(3) else { continue };
+        let Some((address, port)) = local.rsplit_once(':') else { continue };
+
+        let Ok(port) = port.parse::<u16>() else {
+            warn!("Ignoring listening port {:?}", local);
+            continue;
+        };
+
+        // Addresses can have brackets around them, and an interface after
+        // them, such as `[fe80::1%eth0]` or `127.0.0.53%lo`.
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+        let address = address.split('%').next().unwrap_or(address);
+
+        let address = match address {
+            "*" | "0.0.0.0" | "::" | "127.0.0.1"  => None,
+            other => {
+                let Ok(ip) = other.parse::<IpAddr>() else {
+                    warn!("Ignoring listening address {:?}", local);
+                    continue;
+                };
+
+                Some(ip.to_string())
+            }
+        };
+
+        ports.insert((port, address));
+    }
+
+    ports.into_iter()
+         .map(|(port, address)| {
+             let mut parameters = vec![ ("port", Value::Number(port)) ];
+             if let Some(address) = address {
+                 parameters.push(("address", Value::String(address)));
+             }
+             StarterCheck { parameters }
+         })
+         .collect()
+}
+
+
+impl Value {
+
+    /// Writes out this value in TOML, as a literal string in single quotes
+    /// when it can be one, as they’re what the examples use.
+    fn to_toml(&self) -> String {
+        match self {
+            Self::Number(number)                                            => number.to_string(),
+            Self::String(string) if string.chars().all(is_literal_char)     => format!("'{}'", string),
+            Self::String(string)                                            => toml::Value::String(string.clone()).to_string(),
+        }
+    }
+}
+
+/// Whether the given character can appear in a TOML literal string.
+fn is_literal_char(c: char) -> bool {
+    c != '\'' && (c == '\t' || ! c.is_control())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(input: &str) -> Vec<Arc<str>> {
+        input.lines().map(Arc::from).collect()
+    }
+
+    #[test]
+    fn parse_names() {
+        assert_eq!(Generator::parse("brew"), Some(Generator::Homebrew));
+        assert_eq!(Generator::parse("systemd"), Some(Generator::Systemd));
+        assert_eq!(Generator::parse("cron"), None);
+    }
+
+    #[test]
+    fn apt_packages() {
+        let checks = Generator::Apt.starter_checks(&lines("vim\ncurl\n\ncurl\n"));
+        assert_eq!(Generator::Apt.write_checks(&checks),
+                   "# 2 packages installed with apt\n\n[[apt]]\npackage = 'curl'\n\n[[apt]]\npackage = 'vim'\n");
+    }
+
+    #[test]
+    fn enabled_services() {
+        let output = "cron.service                 enabled enabled\n\
+                      getty@.service               enabled enabled\n\
+                      ssh.service                  enabled enabled\n";
+
+        let checks = Generator::Systemd.starter_checks(&lines(output));
+        assert_eq!(Generator::Systemd.write_checks(&checks),
+                   "# 2 enabled systemd services\n\n\
+                    [[systemd]]\nservice = 'cron'\nenablement = 'enabled'\n\n\
+                    [[systemd]]\nservice = 'ssh'\nenablement = 'enabled'\n");
+    }
+
+    #[test]
+    fn listening_ports() {
+        let output = "LISTEN 0      4096   127.0.0.53%lo:53        0.0.0.0:*\n\
+                      LISTEN 0      128          0.0.0.0:22        0.0.0.0:*\n\
+                      LISTEN 0      128             [::]:22           [::]:*\n\
+                      LISTEN 0      511        127.0.0.1:6379      0.0.0.0:*\n\
+                      LISTEN 0      511            [::1]:6379         [::]:*\n";
+
+        let checks = Generator::Tcp.starter_checks(&lines(output));
+        assert_eq!(checks, vec![
+            StarterCheck { parameters: vec![ ("port", Value::Number(22)) ] },
+            StarterCheck { parameters: vec![ ("port", Value::Number(53)), ("address", Value::String("127.0.0.53".into())) ] },
+            StarterCheck { parameters: vec![ ("port", Value::Number(6379)) ] },
+            StarterCheck { parameters: vec![ ("port", Value::Number(6379)), ("address", Value::String("::1".into())) ] },
+        ]);
+    }
+
+    #[test]
+    fn users_and_groups() {
+        let checks = Generator::User.starter_checks(&lines("root:x:0:0:root:/root:/bin/bash\nbethany:x:1000:1000::/home/bethany:/bin/zsh\n"));
+        assert_eq!(checks, vec![
+            StarterCheck { parameters: vec![ ("user", Value::String("bethany".into())) ] },
+            StarterCheck { parameters: vec![ ("user", Value::String("root".into())) ] },
+        ]);
+
+        let checks = Generator::Group.starter_checks(&lines("sudo:x:27:bethany\n"));
+        assert_eq!(checks, vec![ StarterCheck { parameters: vec![ ("group", Value::String("sudo".into())) ] } ]);
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(Value::String("it's".into()).to_toml(), "\"it's\"");
+        assert_eq!(Value::String("plain".into()).to_toml(), "'plain'");
+    }
+}
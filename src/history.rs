@@ -0,0 +1,274 @@
+//! Keeping the results of every run in a history directory, so how each
+//! check has been changing over time can be reported: when it started
+//! failing, how often it has passed recently, and whether it has got
+//! slower or faster.
+//!
+//! The history is kept in one JSON Lines file in the directory, with a line
+//! for each run that lists how each check that got run turned out. Lines
+//! that can’t be read get logged and ignored, rather than stopping the run.
+
+use std::convert::TryInto;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::*;
+use serde::{Serialize, Deserialize};
+
+use spec_analysis::TrendTable;
+
+use crate::doc::CompletedSection;
+use crate::meta::Timestamp;
+use crate::results::CheckStatus;
+
+
+/// The name of the file in the history directory that the runs get
+/// appended to.
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// The number of most recent runs to work out a check’s pass rate and
+/// average runtime from.
+const TREND_WINDOW: usize = 10;
+
+/// How much a check’s runtime has to differ from its recent average, as a
+/// fraction of that average, for the change to be worth reporting.
+const RUNTIME_CHANGE_RATIO: f64 = 0.5;
+
+/// Changes in runtime smaller than this never get reported, as checks that
+/// only take a few milliseconds vary a lot between runs.
+const RUNTIME_CHANGE_MINIMUM: Duration = Duration::from_millis(100);
+
+
+/// A **history directory** holds the results of every run that was given
+/// it, oldest first.
+#[derive(PartialEq, Debug)]
+pub struct HistoryDir {
+    pub path: PathBuf,
+}
+
+/// One run, as it gets written to the history file.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryRun {
+
+    /// When the run started, in seconds since the Unix epoch.
+    time: u64,
+
+    checks: Vec<HistoryCheck>,
+}
+
+/// How one check turned out in a run, as it gets written to the history
+/// file. Checks that weren’t run don’t get written at all.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryCheck {
+    input: String,
+    check: String,
+
+    /// Whether the check passed, or failed when it was expected to.
+    passed: bool,
+
+    runtime_ms: u64,
+}
+
+/// How a check in the current run has been changing over the runs in the
+/// history.
+#[derive(Debug, Clone)]
+pub struct CheckTrend {
+
+    /// The input the check was read from.
+    pub input: String,
+
+    /// The check’s description.
+    pub check: String,
+
+    /// When the check started failing, if it’s failing now but passed in
+    /// an earlier run.
+    pub failing_since: Option<Timestamp>,
+
+    /// The number of recent runs the check was in, including this one.
+    pub recent_runs: usize,
+
+    /// The number of those runs where the check passed.
+    pub recent_passes: usize,
+
+    /// How long the check took on average in the recent runs before this
+    /// one, if there were any.
+    pub average_runtime: Option<Duration>,
+
+    /// How long the check took in this run.
+    pub runtime: Duration,
+}
+
+impl HistoryDir {
+
+    /// Adds a run to the end of the history, then works out the trend of
+    /// each check that was run in it. Only the trends with something worth
+    /// reporting get returned.
+    pub fn record(&self, start_time: Timestamp, sections: &[CompletedSection]) -> io::Result<Vec<CheckTrend>> {
+        let run = HistoryRun::new(start_time, sections);
+        let mut runs = self.read()?;
+
+        fs::create_dir_all(&self.path)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.path.join(HISTORY_FILE))?;
+        writeln!(file, "{}", serde_json::to_string(&run)?)?;
+
+        let mut table = TrendTable::new();
+        runs.push(run);
+        for run in &runs {
+            for check in &run.checks {
+                let key = (check.input.as_str(), check.check.as_str());
+                table.add(key, run.time, check.passed, Duration::from_millis(check.runtime_ms));
+            }
+        }
+
+        let latest = runs.last().expect("No latest run");
+        let trends = latest.checks.iter().filter_map(|check| {
+            let trend = table.trend(&(check.input.as_str(), check.check.as_str()), TREND_WINDOW)?;
+
+            Some(CheckTrend {
+                input: check.input.clone(),
+                check: check.check.clone(),
+                failing_since: trend.failing_since.map(|secs| Timestamp::at(UNIX_EPOCH + Duration::from_secs(*secs))),
+                recent_runs: trend.recent_runs,
+                recent_passes: trend.recent_passes,
+                average_runtime: trend.average_duration,
+                runtime: trend.latest_duration,
+            })
+        });
+
+        Ok(trends.filter(CheckTrend::is_notable).collect())
+    }
+
+    /// Reads every run in the history so far, skipping any lines that
+    /// can’t be read. A history that doesn’t exist yet has no runs.
+    fn read(&self) -> io::Result<Vec<HistoryRun>> {
+        let contents = match fs::read_to_string(self.path.join(HISTORY_FILE)) {
+            Ok(contents)                                   => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound  => return Ok(Vec::new()),
+            Err(e)                                         => return Err(e),
+        };
+
+        let mut runs = Vec::new();
+        for (index, line) in contents.lines().enumerate().filter(|(_, line)| ! line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(run)  => runs.push(run),
+                Err(e)   => warn!("Ignoring line {} of history file: {}", index + 1, e),
+            }
+        }
+
+        Ok(runs)
+    }
+}
+
+impl HistoryRun {
+    fn new(start_time: Timestamp, sections: &[CompletedSection]) -> Self {
+        let mut checks = Vec::new();
+
+        for section in sections {
+            let input = section.input.to_string();
+
+            for output in &section.results.check_outputs {
+                let passed = match output.status() {
+                    CheckStatus::Skipped | CheckStatus::Blocked         => continue,
+                    CheckStatus::Passed | CheckStatus::ExpectedFailure  => true,
+                    CheckStatus::Failed | CheckStatus::Errored | CheckStatus::Warning | CheckStatus::UnexpectedPass => false,
+                };
+
+                let runtime_ms = output.runtime.as_millis().try_into().unwrap_or(u64::MAX);
+                checks.push(HistoryCheck { input: input.clone(), check: output.message.clone(), passed, runtime_ms });
+            }
+        }
+
+        Self { time: start_time.unix_seconds(), checks }
+    }
+}
+
+impl CheckTrend {
+
+    /// Whether anything about this trend is worth reporting: the check
+    /// started failing, it hasn’t passed every recent run, or its runtime
+    /// changed a lot.
+    fn is_notable(&self) -> bool {
+        self.failing_since.is_some() || self.recent_passes < self.recent_runs || self.runtime_change().is_some()
+    }
+
+    /// The average runtime the check’s runtime changed from, if it changed
+    /// by enough to be worth reporting.
+    pub fn runtime_change(&self) -> Option<Duration> {
+        let average = self.average_runtime?;
+        let difference = self.runtime.abs_diff(average);
+
+        if difference >= RUNTIME_CHANGE_MINIMUM && difference.as_secs_f64() >= average.as_secs_f64() * RUNTIME_CHANGE_RATIO {
+            Some(average)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Describes each notable part of the trend, such as when the check
+    /// started failing, in a way that can be printed after its
+    /// description.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+
+        if let Some(since) = self.failing_since {
+            notes.push(format!("failing since {}", since));
+        }
+
+        if self.recent_passes < self.recent_runs {
+            notes.push(format!("passed {} of the last {} runs", self.recent_passes, self.recent_runs));
+        }
+
+        if let Some(average) = self.runtime_change() {
+            let direction = if self.runtime > average { "up" } else { "down" };
+            notes.push(format!("took {:.3}s, {} from an average of {:.3}s", self.runtime.as_secs_f64(), direction, average.as_secs_f64()));
+        }
+
+        notes
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trend(failing_since: Option<u64>, recent_passes: usize, average_ms: Option<u64>, runtime_ms: u64) -> CheckTrend {
+        CheckTrend {
+            input: "checks.toml".into(),
+            check: "Command ‘true’ succeeds".into(),
+            failing_since: failing_since.map(|secs| Timestamp::at(UNIX_EPOCH + Duration::from_secs(secs))),
+            recent_runs: 10,
+            recent_passes,
+            average_runtime: average_ms.map(Duration::from_millis),
+            runtime: Duration::from_millis(runtime_ms),
+        }
+    }
+
+    #[test]
+    fn steady() {
+        let trend = trend(None, 10, Some(400), 450);
+        assert!(! trend.is_notable());
+        assert_eq!(trend.notes(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn started_failing() {
+        let trend = trend(Some(1_609_459_199), 7, Some(400), 400);
+        assert!(trend.is_notable());
+        assert_eq!(trend.notes(), vec![ "failing since 2020-12-31T23:59:59Z", "passed 7 of the last 10 runs" ]);
+    }
+
+    #[test]
+    fn slower() {
+        let trend = trend(None, 10, Some(400), 1200);
+        assert_eq!(trend.notes(), vec![ "took 1.200s, up from an average of 0.400s" ]);
+    }
+
+    #[test]
+    fn small_changes_ignored() {
+        let trend = trend(None, 10, Some(10), 90);
+        assert!(! trend.is_notable());
+    }
+}
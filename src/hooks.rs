@@ -0,0 +1,91 @@
+//! Setup and teardown hooks, which are shell commands that get run before
+//! and after the checks in a file, or before and after a single check.
+//!
+//! They’re for fixtures the checks depend on, such as a user that has to
+//! exist or a database that has to have been seeded. A setup command that
+//! fails stops any more of them from running, as well as the checks they
+//! were for. Teardown commands always all get run, even if the setup
+//! commands or the checks failed, or specsheet got interrupted, so they
+//! can clean up whatever the setup commands managed to do.
+
+use std::process::Command;
+
+use log::*;
+
+use spec_checks::load::Hook;
+use spec_exec::{Executor, ExecError, ExitReason, RunOptions};
+
+use crate::results::{HookFailure, HookStage};
+
+
+/// The **hooks** of a file or check.
+#[derive(Debug, Default)]
+pub struct Hooks {
+
+    /// The commands to run before, in order.
+    pub setup: Vec<String>,
+
+    /// The commands to run after, in order.
+    pub teardown: Vec<String>,
+}
+
+impl Hooks {
+
+    /// Creates the hooks from the `setup` and `teardown` fields of a file
+    /// or check, either of which could be missing.
+    pub fn new(setup: Option<Hook>, teardown: Option<Hook>) -> Self {
+        Self {
+            setup: setup.map(Hook::into_commands).unwrap_or_default(),
+            teardown: teardown.map(Hook::into_commands).unwrap_or_default(),
+        }
+    }
+
+    /// Runs the setup commands in order, stopping at the first one that
+    /// fails, and returning how it failed.
+    pub fn run_setup(&self, executor: &mut Executor, check: Option<&str>) -> Option<HookFailure> {
+        self.setup.iter().find_map(|shell| {
+            run_hook(executor, shell).err().map(|error| {
+                HookFailure { stage: HookStage::Setup, check: check.map(String::from), shell: shell.clone(), error }
+            })
+        })
+    }
+
+    /// Runs every teardown command in order, whether or not the ones before
+    /// it failed, and returns how each one that did fail failed.
+    pub fn run_teardown(&self, executor: &mut Executor, check: Option<&str>) -> Vec<HookFailure> {
+        self.teardown.iter().filter_map(|shell| {
+            run_hook(executor, shell).err().map(|error| {
+                HookFailure { stage: HookStage::Teardown, check: check.map(String::from), shell: shell.clone(), error }
+            })
+        }).collect()
+    }
+}
+
+
+/// Runs a hook’s shell command, which has to exit with status 0.
+///
+/// Hooks don’t go through the command cache, as they have side effects
+/// that have to happen every time. Once specsheet has been interrupted, the
+/// executor won’t run anything new, so hooks get run directly instead, on
+/// the same target and with the same wrapping they would otherwise have.
+fn run_hook(executor: &mut Executor, shell: &str) -> Result<(), String> {
+    debug!("Running hook -> {:?}", shell);
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(shell);
+
+    executor.set_cache(None);
+
+    if spec_exec::is_interrupted() {
+        let mut command = executor.resolve(command, &RunOptions::default());
+        return match command.output() {
+            Ok(output)  => ExitReason::from(output.status).should_be(0).map_err(|e| e.to_string()),
+            Err(e)      => Err(ExecError::Spawn(e).to_string()),
+        };
+    }
+
+    match executor.run_and_store(command) {
+        Ok(rc)  => rc.exit_reason.should_be(0).map_err(|e| e.to_string()),
+        Err(e)  => Err(e.to_string()),
+    }
+}
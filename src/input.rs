@@ -1,14 +1,15 @@
-use std::fs::File;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
 use std::fmt;
 use std::io::{self, Read};
 use std::iter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use derive_more::{From, Display};
 use log::*;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 
-use spec_checks::load::{parse_toml, CheckDocument, TomlError};
+use spec_checks::load::{parse_toml, parse_yaml, parse_json, CheckDocument, TomlError, YamlError, JsonError};
 
 
 /// Where the input TOML comes from. This produces an iterator that yields
@@ -20,8 +21,13 @@ pub enum Inputs {
     Stdin,
 
     /// The command-line options say to read from the files at the given
-    /// paths. These must be files, not directories.
+    /// paths. Directories and glob patterns among them get expanded into
+    /// the check documents they contain.
     Files(Vec<PathBuf>),
+
+    /// The command-line options contain the checks themselves, which have
+    /// been put together into this TOML document.
+    Inline(String),
 }
 
 impl IntoIterator for Inputs {
@@ -32,13 +38,14 @@ impl IntoIterator for Inputs {
     fn into_iter(self) -> Self::IntoIter {
         match self {
             Self::Stdin        => Box::new(iter::once(InputSource::Stdin)),
-            Self::Files(files) => Box::new(files.into_iter().map(InputSource::File)),
+            Self::Files(files) => Box::new(files.into_iter().flat_map(expand_input_path).map(InputSource::File)),
+            Self::Inline(toml) => Box::new(iter::once(InputSource::Inline(toml))),
         }
     }
 }
 
 /// The type iterated by an [`Inputs`] iterator.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(tag = "source", content = "path")]
 pub enum InputSource {
@@ -48,6 +55,71 @@ pub enum InputSource {
 
     /// Read from the file at this path.
     File(PathBuf),
+
+    /// Read this TOML document, which was given on the command-line.
+    Inline(String),
+}
+
+/// The format a check document is written in. Each format gets read into
+/// the same structure.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum InputFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl InputFormat {
+
+    /// Picks the format of the check document file at the given path from
+    /// its extension. Files with an extension that isn’t known are TOML.
+    pub fn for_path(path: &Path) -> Self {
+        Self::for_extension(path).unwrap_or(Self::Toml)
+    }
+
+    /// Picks the format of the check document file at the given path from
+    /// its extension, returning nothing if it isn’t one of theirs.
+    fn for_extension(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml")          => Some(Self::Toml),
+            Some("yaml" | "yml")  => Some(Self::Yaml),
+            Some("json")          => Some(Self::Json),
+            _                     => None,
+        }
+    }
+
+    /// Guesses the format of a check document from its contents, for
+    /// standard input, which has no extension to go by. A JSON document is
+    /// an object, so it starts with a brace. Otherwise, the first line that
+    /// isn’t blank or a comment decides: a TOML line is a table header, or
+    /// has an equals sign before any colon, and a YAML line is the other
+    /// way around.
+    pub fn detect(contents: &str) -> Self {
+        let first_line = contents.lines().map(str::trim).find(|line| ! line.is_empty() && ! line.starts_with('#'));
+        let Some(line) = first_line else { return Self::Toml };
+
+        if line.starts_with('{') {
+            Self::Json
+        }
+        else if line.starts_with('[') {
+            Self::Toml
+        }
+        else if line == "---" || line.find(&[ '=', ':' ][..]).is_some_and(|index| line[index ..].starts_with(':')) {
+            Self::Yaml
+        }
+        else {
+            Self::Toml
+        }
+    }
+
+    /// Parses a check document in this format.
+    fn parse(self, contents: &str) -> Result<CheckDocument, LoadError> {
+        match self {
+            Self::Toml  => Ok(parse_toml(contents)?),
+            Self::Yaml  => Ok(parse_yaml(contents)?),
+            Self::Json  => Ok(parse_json(contents)?),
+        }
+    }
 }
 
 impl fmt::Display for InputSource {
@@ -55,20 +127,83 @@ impl fmt::Display for InputSource {
         match self {
             Self::Stdin       => write!(f, "<stdin>"),
             Self::File(path)  => write!(f, "{}", path.display()),
+            Self::Inline(_)   => write!(f, "<inline>"),
         }
     }
 }
 
 impl InputSource {
 
-    pub fn is_stdin(&self) -> bool {
-        matches!(self, Self::Stdin)
+    pub fn is_file(&self) -> bool {
+        matches!(self, Self::File(_))
+    }
+
+    /// Returns the directory containing the input file, which is where its
+    /// commands get run from unless another directory has been given.
+    /// Inputs that aren’t files don’t have one.
+    pub fn directory(&self) -> Option<PathBuf> {
+        match self {
+            Self::File(path) => {
+                let path = path.canonicalize().expect("canonicalize");
+                Some(path.parent().expect("parent").to_path_buf())
+            }
+            Self::Stdin | Self::Inline(_) => {
+                None
+            }
+        }
+    }
+
+    /// Works out the format of the check document with the given contents,
+    /// which is the format given on the command-line if there was one.
+    /// Otherwise, files have their format picked from their extension, and
+    /// standard input has it guessed from its contents. Checks given on the
+    /// command-line are always TOML.
+    fn format(&self, given: Option<InputFormat>, contents: &str) -> InputFormat {
+        match (self, given) {
+            (Self::Inline(_), _)       => InputFormat::Toml,
+            (_, Some(format))          => format,
+            (Self::File(path), None)   => InputFormat::for_path(path),
+            (Self::Stdin, None)        => InputFormat::detect(contents),
+        }
+    }
+
+    /// Reads and parses the check document, along with every document it
+    /// includes, which get merged into it.
+    pub fn load(&self, format: Option<InputFormat>) -> Result<CheckDocument, LoadError> {
+        self.load_with_includes(format).map(|(document, _)| document)
     }
 
-    pub fn load(&self) -> Result<CheckDocument, LoadError> {
+    /// Reads and parses the check document like `load`, also returning the
+    /// paths of every file that got read, including this one.
+    ///
+    /// The format given only applies to this document. The documents it
+    /// includes always have their format picked from their extension.
+    pub fn load_with_includes(&self, format: Option<InputFormat>) -> Result<(CheckDocument, Vec<PathBuf>), LoadError> {
+        if let Self::File(path) = self {
+            if path.is_dir() {
+                return Err(LoadError::NoDocumentsInDirectory);
+            }
+        }
+
         let contents = self.read_to_string()?;
-        let document = parse_toml(&contents)?;
-        Ok(document)
+        let mut document = self.format(format, &contents).parse(&contents)?;
+
+        let mut includes = Includes::default();
+        let directory = match self {
+            Self::File(path) => {
+                document.set_file(path);
+                let path = path.canonicalize()?;
+                includes.loaded.insert(path.clone());
+                includes.stack.push(path.clone());
+                path.parent().map(Path::to_path_buf).unwrap_or_default()
+            }
+            Self::Stdin | Self::Inline(_) => {
+                PathBuf::from(".")
+            }
+        };
+
+        let document = includes.resolve(document, &directory)?;
+        Ok((document, includes.loaded.into_iter().collect()))
     }
 
     /// Reads the entirety of the relevant input stream, returing an IO error
@@ -95,11 +230,171 @@ impl InputSource {
                 trace!("Successfully read file contents");
                 Ok(contents)
             }
+
+            Self::Inline(contents) => {
+                info!("Reading checks from the command-line");
+                Ok(contents.clone())
+            }
+        }
+    }
+}
+
+
+/// Expands an input path into the paths of the check documents it means.
+///
+/// A directory means every check document in it, or in any directory
+/// inside it — the files with a TOML, YAML, or JSON extension — in
+/// alphabetical order, skipping hidden ones. A glob pattern that
+/// isn’t the path of a file means the files it matches, with any
+/// directories it matches expanded in turn. Other paths get left as they
+/// are, as do directories and patterns with nothing in them, so they fail
+/// to load and the problem gets reported.
+fn expand_input_path(path: PathBuf) -> Vec<PathBuf> {
+    let paths = if path.is_dir() {
+        let mut documents = Vec::new();
+        find_documents(&path, &mut documents);
+        documents.sort();
+        documents
+    }
+    else if ! path.exists() && path.to_string_lossy().contains(&[ '*', '?', '[' ][..]) {
+        match glob::glob(&path.to_string_lossy()) {
+            Ok(entries) => {
+                entries.filter_map(|entry| entry.map_err(|e| warn!("Error matching {:?}: {}", path, e)).ok())
+                       .flat_map(expand_input_path)
+                       .collect()
+            }
+            Err(e) => {
+                warn!("Invalid input pattern {:?}: {}", path, e);
+                Vec::new()
+            }
+        }
+    }
+    else {
+        Vec::new()
+    };
+
+    if paths.is_empty() {
+        vec![ path ]
+    }
+    else {
+        debug!("Expanded input {:?} -> {:?}", path, paths);
+        paths
+    }
+}
+
+/// Adds the paths of the check documents in a directory, and in the
+/// directories inside it, to the list. Anything that can’t be read gets
+/// logged and skipped.
+fn find_documents(directory: &Path, documents: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Error reading directory {:?}: {}", directory, e);
+            return;
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Error reading directory {:?}: {}", directory, e);
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            find_documents(&path, documents);
+        }
+        else if InputFormat::for_extension(&path).is_some() && path.is_file() {
+            documents.push(path);
         }
     }
 }
 
 
+/// The state kept while loading the documents that a document includes.
+#[derive(Debug, Default)]
+struct Includes {
+
+    /// The paths of the documents that have been loaded so far, so that a
+    /// document included twice only gets its checks run once.
+    loaded: BTreeSet<PathBuf>,
+
+    /// The paths of the documents that are being loaded, each one included
+    /// by the one before it, so that cycles can be found.
+    stack: Vec<PathBuf>,
+}
+
+impl Includes {
+
+    /// Loads the documents included by the given document, whose include
+    /// patterns are relative to the given directory, along with the ones
+    /// they include, and merges it into them.
+    fn resolve(&mut self, mut document: CheckDocument, directory: &Path) -> Result<CheckDocument, LoadError> {
+        let mut merged = CheckDocument::default();
+
+        for pattern in std::mem::take(&mut document.include) {
+            for path in find_includes(&pattern, directory)? {
+                if let Some(start) = self.stack.iter().position(|p| *p == path) {
+                    let mut cycle = self.stack[start ..].to_vec();
+                    cycle.push(path);
+                    return Err(LoadError::IncludeCycle(cycle));
+                }
+
+                if ! self.loaded.insert(path.clone()) {
+                    debug!("Already included {:?}", path);
+                    continue;
+                }
+
+                info!("Including checks from file {:?}", path);
+                let mut included = fs::read_to_string(&path)
+                    .map_err(LoadError::from)
+                    .and_then(|contents| InputFormat::for_path(&path).parse(&contents))
+                    .map_err(|e| LoadError::Include(path.clone(), Box::new(e)))?;
+
+                included.set_file(&path);
+
+                self.stack.push(path.clone());
+                let included = self.resolve(included, path.parent().unwrap_or(directory))?;
+                self.stack.pop();
+
+                merged.append(included);
+            }
+        }
+
+        merged.append(document);
+        Ok(merged)
+    }
+}
+
+/// Returns the canonical paths of the files matched by an include pattern,
+/// in alphabetical order. A pattern that matches nothing is an error, as it
+/// probably has a typo in it.
+fn find_includes(pattern: &str, directory: &Path) -> Result<Vec<PathBuf>, LoadError> {
+    let full_pattern = directory.join(pattern);
+    let invalid = |message: String| LoadError::InvalidInclude(pattern.into(), message);
+
+    let paths = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|e| invalid(e.to_string()))?
+        .map(|entry| entry.map_err(|e| invalid(e.to_string())))
+        .filter(|entry| entry.as_ref().map_or(true, |path| path.is_file()))
+        .map(|entry| entry.and_then(|path| path.canonicalize().map_err(|e| invalid(e.to_string()))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if paths.is_empty() {
+        return Err(invalid("no files match it".into()));
+    }
+
+    Ok(paths)
+}
+
+
 /// Something that can go wrong while reading a file into a list of checks.
 #[derive(From, Display)]
 pub enum LoadError {
@@ -110,4 +405,96 @@ pub enum LoadError {
     /// A check document file was able to be read, but the TOML it contains
     /// has invalid syntax.
     Toml(TomlError),
+
+    /// A check document file was able to be read, but the YAML it contains
+    /// has invalid syntax, or isn’t shaped like a check document.
+    Yaml(YamlError),
+
+    /// A check document file was able to be read, but the JSON it contains
+    /// has invalid syntax, or isn’t shaped like a check document.
+    Json(JsonError),
+
+    /// A document that a check document includes could not be loaded.
+    #[from(ignore)]
+    #[display(fmt = "{}: {}", "_0.display()", _1)]
+    Include(PathBuf, Box<LoadError>),
+
+    /// An include pattern was invalid, or did not match any files.
+    #[from(ignore)]
+    #[display(fmt = "Invalid include {:?} ({})", _0, _1)]
+    InvalidInclude(String, String),
+
+    /// A directory was given as an input, but no check documents were
+    /// found in it.
+    #[from(ignore)]
+    #[display(fmt = "No check documents found in directory")]
+    NoDocumentsInDirectory,
+
+    /// Check documents include each other, which would never end.
+    #[from(ignore)]
+    #[display(fmt = "Documents include each other: {}", "display_cycle(_0)")]
+    IncludeCycle(Vec<PathBuf>),
+}
+
+fn display_cycle(paths: &[PathBuf]) -> String {
+    paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(" → ")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(InputFormat::for_path(Path::new("checks.toml")), InputFormat::Toml);
+        assert_eq!(InputFormat::for_path(Path::new("checks.yml")), InputFormat::Yaml);
+        assert_eq!(InputFormat::for_path(Path::new("checks.json")), InputFormat::Json);
+        assert_eq!(InputFormat::for_path(Path::new("checks")), InputFormat::Toml);
+    }
+
+    #[test]
+    fn missing_paths_left_alone() {
+        assert_eq!(expand_input_path(PathBuf::from("/nonexistent/checks.toml")), vec![ PathBuf::from("/nonexistent/checks.toml") ]);
+        assert_eq!(expand_input_path(PathBuf::from("/nonexistent/*.toml")), vec![ PathBuf::from("/nonexistent/*.toml") ]);
+    }
+
+    #[test]
+    fn mixed_format_directory() {
+        let directory = std::env::temp_dir().join(format!("specsheet-input-test-{}", std::process::id()));
+        fs::create_dir_all(directory.join("web")).unwrap();
+        for file in [ "dns.toml", "web/http.yaml", "web/ping.yml", "users.json", "notes.txt", ".hidden.toml" ] {
+            fs::write(directory.join(file), "").unwrap();
+        }
+
+        let documents = expand_input_path(directory.clone());
+        fs::remove_dir_all(&directory).unwrap();
+
+        let names = documents.iter().map(|path| path.strip_prefix(&directory).unwrap().to_path_buf()).collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            PathBuf::from("dns.toml"),
+            PathBuf::from("users.json"),
+            PathBuf::from("web/http.yaml"),
+            PathBuf::from("web/ping.yml"),
+        ]);
+    }
+
+    #[test]
+    fn detect_toml() {
+        assert_eq!(InputFormat::detect("# checks\n\n[[http]]\nurl = 'https://example.com/'\n"), InputFormat::Toml);
+        assert_eq!(InputFormat::detect("include = [ 'common.toml' ]\n"), InputFormat::Toml);
+        assert_eq!(InputFormat::detect(""), InputFormat::Toml);
+    }
+
+    #[test]
+    fn detect_yaml() {
+        assert_eq!(InputFormat::detect("# checks\nhttp:\n  - url: https://example.com/\n"), InputFormat::Yaml);
+        assert_eq!(InputFormat::detect("---\ncmd: []\n"), InputFormat::Yaml);
+        assert_eq!(InputFormat::detect("vars: { query: 'a=b' }\n"), InputFormat::Yaml);
+    }
+
+    #[test]
+    fn detect_json() {
+        assert_eq!(InputFormat::detect("  {\n  \"cmd\": [ { \"shell\": \"true\" } ]\n}\n"), InputFormat::Json);
+    }
 }
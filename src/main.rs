@@ -29,47 +29,107 @@
 
 #![allow(unsafe_code)]   // needed for libc::kill
 
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
 use std::env;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use log::*;
 
 use spec_analysis::AnalysisTable;
+use spec_checks::load::Variables;
+use spec_checks::read::Rewrites;
+
+mod cache;
 
 mod commands;
 use self::commands::Commands;
 
+mod completions;
+
+mod config;
+use self::config::ConfigFile;
+
+mod diff;
+
 mod doc;
+
+mod dry_run;
 use self::doc::{CompletedRun, CompletedSection};
 
+mod explain;
+
 mod filter;
+use self::filter::{Filter, RerunFilter};
+
+mod fixtures;
+
+mod generate;
+
+mod history;
+
+mod hooks;
 
 mod input;
-use self::input::InputSource;
+use self::input::{InputFormat, InputSource};
 
 mod logger;
 
+mod meta;
+use self::meta::{RunMetadata, Timestamp};
+
 mod options;
-use self::options::{Options, RunningMode, RunningDirectory, OptionsResult, HelpReason};
+use self::options::{Options, RunningMode, RunningDirectory, OptionsResult, HelpReason, StopCondition};
 
 mod output;
+use self::output::{Outputs, UseColours};
+
+mod notify;
+
+mod preflight;
+
+mod push;
 
 mod results;
-use self::results::Stats;
+use self::results::{Stats, CheckHistory, FlakyRecord};
+
+mod serve;
+use self::serve::StatusServer;
 
 mod set;
 use self::set::CheckSet;
 
 mod side;
+use self::side::SideProcesses;
+
+mod signals;
 
 mod terminal_ui;
 
+mod theme;
+
+mod watch;
+use self::watch::WatchedFiles;
+
 
 fn main() {
     use std::process::exit;
 
     logger::configure(env::var_os("SPECSHEET_DEBUG"));
 
-    match Options::getopts(env::args_os().skip(1)) {
+    let config = match ConfigFile::find() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(exits::OPTIONS_ERROR);
+        }
+    };
+
+    match Options::getopts(env::args_os().skip(1), &config) {
         OptionsResult::Ok(opts) => {
             exit(run(opts));
         }
@@ -101,6 +161,20 @@ fn main() {
             exit(exits::SUCCESS);
         }
 
+        OptionsResult::Completions(shell) => {
+            print!("{}", completions::script(shell));
+            exit(exits::SUCCESS);
+        }
+
+        OptionsResult::PrintSchema => {
+            print!("{}", doc::SCHEMA);
+            exit(exits::SUCCESS);
+        }
+
+        OptionsResult::Diff(old, new, use_colours) => {
+            exit(compare(&old, &new, use_colours));
+        }
+
         OptionsResult::InvalidOptionsFormat(why) => {
             eprintln!("{}", why);
             exit(exits::OPTIONS_ERROR);
@@ -117,35 +191,95 @@ fn main() {
 fn run(options: Options) -> i32 {
     use spec_exec::Executor;
 
-    let Options { mode, inputs, filter, rewrites, output } = options;
+    let Options { mode, inputs, input_format, mut filter, rewrites, variables, output, extra_outputs, exit_policy } = options;
     debug!("Mode -> {:#?}", mode);
     debug!("Input files -> {:#?}", inputs);
     debug!("Filter -> {:#?}", filter);
     debug!("Rewrites -> {:#?}", rewrites);
+    debug!("Variables -> {:#?}", variables);
     debug!("Output -> {:#?}", output);
+    debug!("Extra outputs -> {:#?}", extra_outputs);
+    debug!("Exit policy -> {:#?}", exit_policy);
+
+    if let RerunFilter::FailedIn(path) = &filter.rerun {
+        match doc::failed_checks(path) {
+            Ok(descriptions) => {
+                info!("Re-running {} failed checks", descriptions.len());
+                filter.rerun = RerunFilter::Only(descriptions);
+            }
+            Err(e) => {
+                eprintln!("Error reading previous results from {}: {}", path.display(), e);
+                return exits::FILE_ERROR;
+            }
+        }
+    }
 
-	let mut ui = output.ui();
+	let mut ui = match Outputs::open(output, &extra_outputs) {
+        Ok(ui) => ui,
+        Err(e) => {
+            eprintln!("Error opening output file: {}", e);
+            return exits::FILE_ERROR;
+        }
+    };
     let mut file_errored = false;
     let mut checks_have_failed = false;
 
+    // The totals of the checks that decide the exit status, which are every
+    // check in normal mode, and the last batch’s checks in continual mode.
+    let mut deciding_totals = Stats::default();
+
     match mode {
         RunningMode::Run(check_opts, end_opts) => {
+            ui.print_start();
+
+            if let Err(e) = signals::install_handler() {
+                warn!("Error installing signal handler: {}", e);
+            }
+
+            let start_time = Timestamp::now();
             let mut executor = Executor::new();
+            executor.set_default_timeout(check_opts.timeout);
+            executor.set_target(check_opts.target.clone());
+            executor.set_sudo(check_opts.sudo);
+            #[cfg(feature = "async")]
+            executor.set_async(start_async_executor(check_opts.async_processes));
             let mut commands = Commands::from_global_options(&check_opts.global_options).expect("Invalid overrides");
+            commands.check_programs = check_opts.checks_programs();
+            if let Some(disk_cache) = &check_opts.cache {
+                commands.set_cache_store(Box::new(disk_cache.clone()));
+            }
 
-            let here = env::current_dir().expect("current_dir");
-            let here = here.canonicalize().expect("canonicalize");
+            if let Some(path) = &check_opts.replay {
+                match fixtures::load(path) {
+                    Ok(recording) => {
+                        executor.set_replay(Some(Arc::new(recording)));
+                    }
+                    Err(e) => {
+                        eprintln!("Error loading fixtures from {}: {}", path.display(), e);
+                        return exits::FILE_ERROR;
+                    }
+                }
+            }
 
             if let RunningDirectory::OtherDirectory(other_dir) = &check_opts.directory {
-                debug!("Changing directory to specified directory -> {:?}", other_dir);
-                env::set_current_dir(other_dir).expect("set_current_dir to other_dir");
+                debug!("Running commands from specified directory -> {:?}", other_dir);
+                executor.set_working_directory(Some(other_dir.clone()));
+                commands.set_base_directory(Some(other_dir.clone()));
+            }
+
+            // Each side process has started up before the next one gets
+            // started, so later ones can depend on earlier ones.
+            let side_processes = Arc::new(SideProcesses::new(executor.working_directory().map(Path::to_path_buf)));
+            for side_process in &check_opts.processes {
+                if ! side_processes.start(side_process) {
+                    eprintln!("Side process ‘{}’ did not start up in time", side_process.shell);
+                    side_processes.stop_all();
+                    return exits::SIDE_PROCESS_ERROR;
+                }
             }
 
-            let mut side_child = None;
-            if let Some(side_process) = &check_opts.process {
-                let pid = side_process.start();
-                debug!("Process started -> {}", pid);
-                side_child = Some(pid);
+            if ! check_opts.processes.is_empty() {
+                commands.side_processes = Some(Arc::clone(&side_processes));
             }
 
             let mut sections = Vec::new();
@@ -160,22 +294,22 @@ fn run(options: Options) -> i32 {
                 }
 
                 // Printing the file section when the only input is
-                // stdin just takes up space
-                if ! input_source.is_stdin() {
+                // stdin or the command-line just takes up space
+                if input_source.is_file() {
                     ui.print_file_section(&input_source);
                 }
 
-                let check_document = match input_source.load() {
+                let check_document = match input_source.load(input_format) {
                     Ok(cd) => cd,
                     Err(e) => {
-                        ui.print_load_error(&input_source, e);
+                        ui.print_load_error(&input_source, &e);
                         file_errored = true;
                         continue;
                     }
                 };
 
                 let mut checks = CheckSet::new();
-                match checks.read_toml(&filter, &rewrites, check_document) {
+                match checks.read_toml(&filter, &rewrites, &variables, check_document) {
                     Ok(()) => {},
                     Err(es) => {
                         ui.print_read_errors(&es);
@@ -184,50 +318,83 @@ fn run(options: Options) -> i32 {
                 }
 
                 if let RunningDirectory::CheckDirectory = &check_opts.directory {
-                    if let InputSource::File(path) = &input_source {
-                        let base_directory = path.canonicalize().expect("canonicalize");
-                        let base_directory = base_directory.parent().expect("parent");
-                        debug!("Changing directory to check directory -> {:?}", base_directory);
-                        env::set_current_dir(base_directory).expect("set_current_dir");
-                    }
+                    let base_directory = input_source.directory();
+                    debug!("Running commands from check directory -> {:?}", base_directory);
+                    executor.set_working_directory(base_directory.clone());
+                    commands.set_base_directory(base_directory);
                 }
 
                 checks.prime_commands(&mut commands);
-                let section = checks.run_all(&mut executor, &mut commands, &mut ui, check_opts.delay, analysis_table.as_mut());
+                let mut section = checks.run_all(&mut executor, &mut commands, &mut ui, check_opts.delay, check_opts.threads, analysis_table.as_mut());
+                if let Some(table) = &analysis_table {
+                    section = section.with_correlations(table);
+                }
 
                 ui.print_stats(section.totals);
+                if let Some(correlations) = &section.correlations {
+                    ui.print_correlations(correlations);
+                }
 
-                if section.failed() {
+                if section.fails_run(end_opts.allow_known_issues) {
                     checks_have_failed = true;
                 }
 
 				let completed_section = CompletedSection { input: input_source, results: section };
                 sections.push(completed_section);
 
-                debug!("Changing to original directory -> {:?}", here);
-                env::set_current_dir(&here).expect("set_current_dir to here");
+                // Stop after the section that was interrupted, so the
+                // results so far still get written.
+                if spec_exec::is_interrupted() {
+                    break;
+                }
+            }
 
-                if let Some(table) = analysis_table {
-                    let corals = table.resolve_correlations();
+            side_processes.stop_all();
+            ui.print_held_checks();
 
-                    if corals.is_empty() {
-                        println!("No correlations detected.");
-                    }
-                    else {
-                        println!("\nAnalysis:");
-                        for correlation in corals {
-                            println!("- Failures {} (×{}, with 0 successes)", correlation.property, correlation.count);
-                        }
-                    }
+            // The output of the side processes is only worth showing if it
+            // could explain why something failed, but it always gets kept
+            // in the result documents.
+            let side_processes = side_processes.records();
+
+            if checks_have_failed {
+                for side_process in &side_processes {
+                    ui.print_side_output(side_process);
                 }
             }
 
-            if let (Some(side_child), Some(side_handle)) = (check_opts.process, side_child) {
-                side_child.stop(side_handle).expect("stop");
+            // Checks that weren’t run took no time, so they can’t be slow.
+            let mut slowest_checks = sections.iter()
+                .flat_map(|section| &section.results.check_outputs)
+                .filter(|check_output| check_output.attempts > 0)
+                .collect::<Vec<_>>();
+            slowest_checks.sort_by_key(|check_output| Reverse(check_output.runtime));
+            slowest_checks.truncate(10);
+            ui.print_slowest_checks(&slowest_checks);
+            ui.print_failure_recap(&sections);
+
+            let mut trends = Vec::new();
+            if let Some(history_dir) = &end_opts.history_dir {
+                match history_dir.record(start_time, &sections) {
+                    Ok(ts) => {
+                        ui.print_trends(&ts);
+                        trends = ts;
+                    }
+                    Err(e) => {
+                        eprintln!("Error recording history to {}: {}", history_dir.path.display(), e);
+                        file_errored = true;
+                    }
+                }
             }
 
             ui.print_end();
 
+            if let Some(path) = &check_opts.record {
+                if let Err(e) = fixtures::record(path, &executor.to_commands().collect::<Vec<_>>()) {
+                    eprintln!("Error recording fixtures to {}: {}", path.display(), e);
+                    file_errored = true;
+                }
+            }
 
             let commands = executor.to_commands();
 
@@ -236,8 +403,11 @@ fn run(options: Options) -> i32 {
                 totals += section.results.totals;
             }
 
-            let run = CompletedRun { sections, commands: commands.collect(), totals };
-            match end_opts.result_documents.write(run) {
+            deciding_totals = totals;
+
+            let metadata = RunMetadata::capture(start_time);
+            let run = CompletedRun { sections, commands: commands.collect(), totals, metadata, trends, side_processes };
+            match end_opts.result_documents.write(&run) {
                 Ok(()) => {
                     debug!("Output documents written OK.");
                 }
@@ -246,28 +416,26 @@ fn run(options: Options) -> i32 {
                     file_errored = true;
                 }
             }
+
+            if let Err(e) = end_opts.notifications.send(&run, checks_have_failed) {
+                eprintln!("Error sending notification: {}", e);
+                file_errored = true;
+            }
         }
 
-        RunningMode::Continual(check_opts) => {
-            // One check set for all input files.
-            let mut checks = CheckSet::new();
+        RunningMode::Continual(check_opts, result_documents, continual_opts) => {
+            ui.print_start();
 
-            for input_source in inputs {
-                let check_document = match input_source.load() {
-                    Ok(cd) => cd,
-                    Err(e) => {
-                        ui.print_load_error(&input_source, e);
-                        file_errored = true;
-                        continue;
-                    }
-                };
+            let reader = CheckReader { input_format, filter: &filter, rewrites: &rewrites, variables: &variables };
 
-                match checks.read_toml(&filter, &rewrites, check_document) {
-                    Ok(()) => {},
-                    Err(es) => {
-                        ui.print_read_errors(&es);
-                        file_errored = true;
-                    }
+            // One check set for each input file, so each batch’s result
+            // documents can have a section for each file.
+            let mut check_sets = Vec::new();
+
+            for input_source in inputs {
+                match reader.read(&input_source, &mut ui) {
+                    Some((checks, watched_files)) => check_sets.push((input_source, checks, watched_files)),
+                    None                          => file_errored = true,
                 }
             }
 
@@ -275,28 +443,238 @@ fn run(options: Options) -> i32 {
                 return exits::FILE_ERROR;
             }
 
+            if let Err(e) = signals::install_handler() {
+                warn!("Error installing signal handler: {}", e);
+            }
+
+            let recording = match &check_opts.replay {
+                Some(path) => match fixtures::load(path) {
+                    Ok(recording) => Some(Arc::new(recording)),
+                    Err(e) => {
+                        eprintln!("Error loading fixtures from {}: {}", path.display(), e);
+                        return exits::FILE_ERROR;
+                    }
+                },
+                None => None,
+            };
+
+            #[cfg(feature = "async")]
+            let async_executor = start_async_executor(check_opts.async_processes);
+
+            let status_server = match &continual_opts.serve {
+                Some(address) => match StatusServer::start(address) {
+                    Ok(server) => Some(server),
+                    Err(e) => {
+                        eprintln!("Error serving status on {}: {}", address, e);
+                        return exits::OPTIONS_ERROR;
+                    }
+                },
+                None => None,
+            };
+
+            let mut batch_count = 0;
+            let mut grand_totals = Stats::default();
+            let mut previously_failed = false;
+            let mut history = if continual_opts.detect_flaky { Some(CheckHistory::new()) } else { None };
+
             loop {
+
+                for (input_source, checks, watched_files) in &mut check_sets {
+                    reader.reload(input_source, checks, watched_files, &mut ui);
+                }
+
+                let start_time = Timestamp::now();
                 let mut executor = Executor::new();
+                executor.set_default_timeout(check_opts.timeout);
+                executor.set_target(check_opts.target.clone());
+                executor.set_sudo(check_opts.sudo);
+                #[cfg(feature = "async")]
+                executor.set_async(async_executor.clone());
+                executor.set_replay(recording.clone());
                 let mut commands = Commands::from_global_options(&check_opts.global_options).expect("Invalid overrides");
+                commands.check_programs = check_opts.checks_programs();
+                if let Some(disk_cache) = &check_opts.cache {
+                    commands.set_cache_store(Box::new(disk_cache.clone()));
+                }
 
-                checks.prime_commands(&mut commands);
-                checks.run_continual_batch(&mut executor, &mut commands, &mut ui, filter.order, check_opts.delay);
+                if let RunningDirectory::OtherDirectory(other_dir) = &check_opts.directory {
+                    executor.set_working_directory(Some(other_dir.clone()));
+                    commands.set_base_directory(Some(other_dir.clone()));
+                }
+
+                let mut sections = Vec::new();
+                let mut totals = Stats::default();
+                for (input_source, checks, _) in &mut check_sets {
+                    checks.prime_commands(&mut commands);
+                    let section = checks.run_continual_batch(&mut executor, &mut commands, &mut ui, filter.order, check_opts.delay, check_opts.threads);
+                    if let Some(history) = &mut history {
+                        section.record_history(&input_source.to_string(), history);
+                    }
+
+                    totals += section.totals;
+                    sections.push(CompletedSection { input: input_source.clone(), results: section });
+                }
+
+                ui.print_held_checks();
+
+                batch_count += 1;
+                grand_totals += totals;
+
+                // The exit status reflects the most recent batch, as that’s
+                // the state of things when specsheet stopped.
+                checks_have_failed = sections.iter().any(|s| s.results.fails_run(continual_opts.allow_known_issues));
+                deciding_totals = totals;
+
+                if let Some(path) = &check_opts.record {
+                    if let Err(e) = fixtures::record(path, &executor.to_commands().collect::<Vec<_>>()) {
+                        eprintln!("Error recording fixtures to {}: {}", path.display(), e);
+                    }
+                }
+
+                let metadata = RunMetadata::capture(start_time);
+                let run = CompletedRun { sections, commands: executor.to_commands().collect(), totals, metadata, trends: Vec::new(), side_processes: Vec::new() };
+                if let Err(e) = result_documents.write(&run) {
+                    eprintln!("Error writing output document: {}", e);
+                }
+
+                if let Some(server) = &status_server {
+                    server.update(&run, ! checks_have_failed);
+                }
+
+                // Notifications only get sent when the checks start or stop
+                // failing, rather than after every batch that fails.
+                if checks_have_failed != previously_failed {
+                    if let Err(e) = continual_opts.notifications.send(&run, checks_have_failed) {
+                        eprintln!("Error sending notification: {}", e);
+                    }
+                }
+
+                previously_failed = checks_have_failed;
+
+                if spec_exec::is_interrupted() {
+                    break;
+                }
+
+                if continual_opts.iterations.is_some_and(|i| batch_count >= i) {
+                    debug!("Stopping after {} batches", batch_count);
+                    break;
+                }
+
+                match continual_opts.until {
+                    Some(StopCondition::Failure) if checks_have_failed => {
+                        debug!("Stopping because a check failed");
+                        break;
+                    }
+                    Some(StopCondition::Success) if ! checks_have_failed => {
+                        debug!("Stopping because every check passed");
+                        break;
+                    }
+                    _ => {}
+                }
+
+                if let Some(interval) = continual_opts.interval {
+                    wait_for_interval(interval);
+
+                    if spec_exec::is_interrupted() {
+                        break;
+                    }
+                }
+            }
+
+            ui.print_batch_totals(batch_count, grand_totals);
+            if let Some(history) = &history {
+                ui.print_flaky_checks(&FlakyRecord::from_history(history));
+            }
+
+            ui.print_end();
+        }
+
+        RunningMode::Watch(check_opts) => {
+            if let Err(e) = signals::install_handler() {
+                warn!("Error installing signal handler: {}", e);
+            }
+
+            let inputs = inputs.into_iter().collect::<Vec<_>>();
+            let mut watched = inputs.iter().map(|_| WatchedFiles::default()).collect::<Vec<_>>();
+            let mut to_run = vec![ true; inputs.len() ];
+
+            while ! spec_exec::is_interrupted() {
+                watch::clear_screen();
+
+                for ((input_source, watched_files), _) in inputs.iter().zip(&mut watched).zip(&to_run).filter(|e| *e.1) {
+
+                    // Each run gets a new executor and commands, so nothing
+                    // gets cached between runs.
+                    let mut executor = Executor::new();
+                    executor.set_default_timeout(check_opts.timeout);
+                    executor.set_target(check_opts.target.clone());
+                    executor.set_sudo(check_opts.sudo);
+                    let mut commands = Commands::from_global_options(&check_opts.global_options).expect("Invalid overrides");
+                    commands.check_programs = check_opts.checks_programs();
+
+                    let base_directory = match &check_opts.directory {
+                        RunningDirectory::CheckDirectory         => input_source.directory(),
+                        RunningDirectory::OtherDirectory(other)  => Some(other.clone()),
+                    };
+
+                    executor.set_working_directory(base_directory.clone());
+                    commands.set_base_directory(base_directory.clone());
+
+                    ui.print_file_section(input_source);
+
+                    let (check_document, mut paths) = match input_source.load_with_includes(input_format) {
+                        Ok(loaded) => loaded,
+                        Err(e) => {
+                            ui.print_load_error(input_source, &e);
+                            if let InputSource::File(path) = input_source {
+                                *watched_files = WatchedFiles::new(vec![ path.clone() ]);
+                            }
+                            continue;
+                        }
+                    };
+
+                    paths.extend(watch::referenced_files(&check_document, &base_directory.unwrap_or_default()));
+                    *watched_files = WatchedFiles::new(paths);
+
+                    let mut checks = CheckSet::new();
+                    if let Err(es) = checks.read_toml(&filter, &rewrites, &variables, check_document) {
+                        ui.print_read_errors(&es);
+                    }
+
+                    checks.prime_commands(&mut commands);
+                    let section = checks.run_all(&mut executor, &mut commands, &mut ui, check_opts.delay, check_opts.threads, None);
+                    ui.print_stats(section.totals);
+                }
+
+                ui.print_held_checks();
+                ui.print_waiting_for_changes();
+
+                // Only the files whose checks depend on a file that has
+                // changed get run again.
+                while ! spec_exec::is_interrupted() {
+                    thread::sleep(Duration::from_millis(500));
+
+                    to_run = watched.iter_mut().map(WatchedFiles::have_changed).collect();
+                    if to_run.contains(&true) {
+                        break;
+                    }
+                }
             }
         }
 
         RunningMode::SyntaxCheckOnly => {
             for input_source in inputs {
-                let check_document = match input_source.load() {
+                let check_document = match input_source.load(input_format) {
                     Ok(cd) => cd,
                     Err(e) => {
-                        ui.print_load_error(&input_source, e);
+                        ui.print_load_error(&input_source, &e);
                         file_errored = true;
                         continue;
                     }
                 };
 
                 let mut checks = CheckSet::new();
-                match checks.read_toml(&filter, &rewrites, check_document) {
+                match checks.read_toml(&filter, &rewrites, &variables, check_document) {
                     Ok(()) => {
                         if checks.is_empty() {
                             println!("{} contains no checks", input_source);
@@ -317,16 +695,16 @@ fn run(options: Options) -> i32 {
             let mut checks = CheckSet::new();
 
             for input_source in inputs {
-                let check_document = match input_source.load() {
+                let check_document = match input_source.load(input_format) {
                     Ok(cd) => cd,
                     Err(e) => {
-                        ui.print_load_error(&input_source, e);
+                        ui.print_load_error(&input_source, &e);
                         file_errored = true;
                         continue;
                     }
                 };
 
-                match checks.read_toml(&filter, &rewrites, check_document) {
+                match checks.read_toml(&filter, &rewrites, &variables, check_document) {
                     Ok(()) => {},
                     Err(es) => {
                         ui.print_read_errors(&es);
@@ -338,32 +716,144 @@ fn run(options: Options) -> i32 {
             let mut commands = Commands::from_global_options(&global_options).expect("Invalid overrides");
 
             checks.prime_commands(&mut commands);
-            for command in commands.list_commands() {
+            for (command, _) in commands.list_commands() {
                 println!("{:?}", command);
             }
         }
 
+        RunningMode::DryRun(check_opts) => {
+            let mut executor = Executor::new();
+            executor.set_target(check_opts.target.clone());
+            executor.set_sudo(check_opts.sudo);
+
+            let mut missing_programs = BTreeSet::new();
+            for input_source in inputs {
+                let check_document = match input_source.load(input_format) {
+                    Ok(cd) => cd,
+                    Err(e) => {
+                        ui.print_load_error(&input_source, &e);
+                        file_errored = true;
+                        continue;
+                    }
+                };
+
+                let mut checks = CheckSet::new();
+                match checks.read_toml(&filter, &rewrites, &variables, check_document) {
+                    Ok(()) => {},
+                    Err(es) => {
+                        ui.print_read_errors(&es);
+                        file_errored = true;
+                    }
+                }
+
+                let base_directory = match &check_opts.directory {
+                    RunningDirectory::OtherDirectory(other_dir)  => Some(other_dir.clone()),
+                    RunningDirectory::CheckDirectory             => input_source.directory(),
+                };
+
+                let mut commands = Commands::from_global_options(&check_opts.global_options).expect("Invalid overrides");
+                executor.set_working_directory(base_directory.clone());
+                commands.set_base_directory(base_directory);
+
+                checks.prime_commands(&mut commands);
+                missing_programs.extend(dry_run::print_commands(&executor, commands));
+            }
+
+            // Missing programs get flagged like failed checks, as running
+            // the checks for real would fail.
+            for program in &missing_programs {
+                eprintln!("Program ‘{}’ is not installed", program);
+                checks_have_failed = true;
+            }
+        }
+
+        RunningMode::Explain(check_opts, query) => {
+            let mut executor = Executor::new();
+            executor.set_target(check_opts.target.clone());
+            executor.set_sudo(check_opts.sudo);
+
+            let mut explained_any = false;
+            for input_source in inputs {
+                let check_document = match input_source.load(input_format) {
+                    Ok(cd) => cd,
+                    Err(e) => {
+                        ui.print_load_error(&input_source, &e);
+                        file_errored = true;
+                        continue;
+                    }
+                };
+
+                let mut checks = CheckSet::new();
+                if let Err(es) = checks.read_toml(&filter, &rewrites, &variables, check_document) {
+                    ui.print_read_errors(&es);
+                    file_errored = true;
+                }
+
+                let base_directory = match &check_opts.directory {
+                    RunningDirectory::OtherDirectory(other_dir)  => Some(other_dir.clone()),
+                    RunningDirectory::CheckDirectory             => input_source.directory(),
+                };
+
+                let mut commands = Commands::from_global_options(&check_opts.global_options).expect("Invalid overrides");
+                commands.check_programs = check_opts.checks_programs();
+                executor.set_working_directory(base_directory.clone());
+                commands.set_base_directory(base_directory);
+
+                for explanation in checks.explain(&query, &commands) {
+                    explain::print_explanation(explanation, &executor);
+                    explained_any = true;
+                }
+            }
+
+            if ! explained_any && ! file_errored {
+                eprintln!("No check has a name or description like ‘{}’", query);
+                return exits::OPTIONS_ERROR;
+            }
+        }
+
+        RunningMode::Generate(check_opts, generators) => {
+            let mut executor = Executor::new();
+            executor.set_target(check_opts.target.clone());
+            executor.set_sudo(check_opts.sudo);
+            executor.set_default_timeout(check_opts.timeout);
+
+            println!("# Starter checks generated by specsheet");
+
+            for generator in generators {
+                match generator.generate(&mut executor) {
+                    Ok(toml) => {
+                        println!();
+                        print!("{}", toml);
+                    }
+                    Err(e) => {
+                        eprintln!("Error listing {}: {}", generator.description(), e);
+                        file_errored = true;
+                    }
+                }
+            }
+        }
+
         RunningMode::ListChecksOnly => {
             for input_source in inputs {
                 ui.print_file_section(&input_source);
 
-                let check_document = match input_source.load() {
+                let check_document = match input_source.load(input_format) {
                     Ok(cd) => cd,
                     Err(e) => {
-                        ui.print_load_error(&input_source, e);
+                        ui.print_load_error(&input_source, &e);
                         file_errored = true;
                         continue;
                     }
                 };
 
                 let mut checks = CheckSet::new();
-                match checks.read_toml(&filter, &rewrites, check_document) {
+                match checks.read_toml(&filter, &rewrites, &variables, check_document) {
                     Ok(()) => {},
                     Err(es) => ui.print_read_errors(&es),
                 }
 
                 for check in checks.list_checks() {
-                    println!("{}", check);
+                    ui.print_listed_check(&check);
                 }
             }
         }
@@ -374,16 +864,16 @@ fn run(options: Options) -> i32 {
 
             let mut all_tags = BTreeSet::new();
             for input_source in inputs {
-                let check_document = match input_source.load() {
+                let check_document = match input_source.load(input_format) {
                     Ok(cd) => cd,
                     Err(e) => {
-                        ui.print_load_error(&input_source, e);
+                        ui.print_load_error(&input_source, &e);
                         file_errored = true;
                         continue;
                     }
                 };
 
-                for check in check_document.values().flatten() {
+                for check in check_document.checks.values().flatten() {
                     if let Some(tags) = &check.tags {
                         match tags {
                             Tags::One(one)   => { all_tags.insert(one.clone()); },
@@ -403,10 +893,121 @@ fn run(options: Options) -> i32 {
         }
     }
 
-    if file_errored {
+    if spec_exec::is_interrupted() {
+        exits::INTERRUPTED
+    }
+    else if file_errored {
         exits::FILE_ERROR
     }
-    else if checks_have_failed {
+    else if exit_policy.checks_have_failed(checks_have_failed, deciding_totals) {
+        exits::CHECKS_HAVE_FAILED
+    }
+    else {
+        exits::SUCCESS
+    }
+}
+
+
+/// Creates the async executor that processes get run on, if one has been
+/// asked for. Failing to create one is not fatal, as processes can still
+/// be run without it.
+#[cfg(feature = "async")]
+fn start_async_executor(processes: Option<usize>) -> Option<Arc<spec_exec::AsyncExecutor>> {
+    match spec_exec::AsyncExecutor::new(processes?) {
+        Ok(async_executor) => {
+            Some(Arc::new(async_executor))
+        }
+        Err(e) => {
+            warn!("Error starting async executor: {}", e);
+            None
+        }
+    }
+}
+
+/// Reads the checks in input files in continual mode, where they get read
+/// again whenever they change.
+struct CheckReader<'opts> {
+    input_format: Option<InputFormat>,
+    filter: &'opts Filter,
+    rewrites: &'opts Rewrites,
+    variables: &'opts Variables,
+}
+
+impl CheckReader<'_> {
+
+    /// Reads the checks in an input file, along with the paths of the
+    /// files that got read for it, printing any errors.
+    fn read(&self, input_source: &InputSource, ui: &mut Outputs) -> Option<(CheckSet, WatchedFiles)> {
+        let (check_document, paths) = match input_source.load_with_includes(self.input_format) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                ui.print_load_error(input_source, &e);
+                return None;
+            }
+        };
+
+        let mut checks = CheckSet::new();
+        if let Err(es) = checks.read_toml(self.filter, self.rewrites, self.variables, check_document) {
+            ui.print_read_errors(&es);
+            return None;
+        }
+
+        Some((checks, WatchedFiles::new(paths)))
+    }
+
+    /// Reads the checks in an input file again if it has changed since the
+    /// last batch, so its checks can be edited without restarting. If it
+    /// can’t be read, the errors get printed, and the checks from before
+    /// keep getting run until it’s fixed.
+    fn reload(&self, input_source: &InputSource, checks: &mut CheckSet, watched_files: &mut WatchedFiles, ui: &mut Outputs) {
+        if ! watched_files.have_changed() {
+            return;
+        }
+
+        info!("Reloading checks from {}", input_source);
+
+        if let Some((mut new_checks, new_watched_files)) = self.read(input_source, ui) {
+            new_checks.carry_over_runs(mem::take(checks));
+            *checks = new_checks;
+            *watched_files = new_watched_files;
+        }
+    }
+}
+
+/// Waits for the interval between two batches of checks in continual mode,
+/// waking up often enough to stop waiting as soon as specsheet gets
+/// interrupted.
+fn wait_for_interval(interval: Duration) {
+    let end = Instant::now() + interval;
+
+    while ! spec_exec::is_interrupted() {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+
+        thread::sleep((end - now).min(Duration::from_millis(100)));
+    }
+}
+
+/// Compares two result documents, returning an error exit status if any
+/// checks regressed.
+fn compare(old: &Path, new: &Path, use_colours: UseColours) -> i32 {
+    let mut outcomes = Vec::new();
+    for path in [ old, new ] {
+        match doc::check_outcomes(path) {
+            Ok(o) => outcomes.push(o),
+            Err(e) => {
+                eprintln!("Error reading results from {}: {}", path.display(), e);
+                return exits::FILE_ERROR;
+            }
+        }
+    }
+
+    let comparison = diff::Comparison::new(&outcomes[0], &outcomes[1]);
+    diff::print_comparison(&comparison, &use_colours.palette());
+
+    if comparison.regression_count() > 0 {
         exits::CHECKS_HAVE_FAILED
     }
     else {
@@ -429,4 +1030,89 @@ mod exits {
 
     /// Exit code for when the command-line options were invalid.
     pub const OPTIONS_ERROR: i32 = 3;
+
+    /// Exit code for when a side process did not start up in time.
+    pub const SIDE_PROCESS_ERROR: i32 = 4;
+
+    /// Exit code for when the run was interrupted, such as with Ctrl-C,
+    /// following the convention of 128 plus the number of SIGINT.
+    pub const INTERRUPTED: i32 = 130;
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::sync::Mutex;
+    use spec_exec::Executor;
+    use crate::commands::GlobalOptions;
+    use crate::filter::RunningOrder;
+    use crate::options::{Delay, Threads};
+    use crate::output::OutputFormat;
+
+    /// A writer whose output can still be read after it’s been given to a
+    /// set of outputs.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run_batch(checks: &mut CheckSet) -> results::ResultsSection {
+        let mut commands = Commands::from_global_options(&GlobalOptions::default()).unwrap();
+        checks.prime_commands(&mut commands);
+        checks.run_continual_batch(&mut Executor::new(), &mut commands, &mut Outputs::discard(), RunningOrder::ByType, Delay::RunInstantly, Threads::One)
+    }
+
+    /// Changes a watched file, waiting first so its modification time is
+    /// sure to be different.
+    fn change(path: &Path, contents: &str) {
+        thread::sleep(Duration::from_millis(50));
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn reload_changed_checks() {
+        let dir = env::temp_dir().join(format!("specsheet-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checks.toml");
+        let counter = dir.join("counter");
+        let interval_check = format!("[[cmd]]\nshell = 'echo >> {}'\ninterval = '1h'\n", counter.display());
+        fs::write(&path, &interval_check).unwrap();
+
+        let (filter, rewrites, variables) = (Filter::default(), Rewrites::new(), Variables::new());
+        let reader = CheckReader { input_format: None, filter: &filter, rewrites: &rewrites, variables: &variables };
+        let input_source = InputSource::File(path.clone());
+        let captured = Captured::default();
+        let mut ui = Outputs::writing_to(OutputFormat::JsonLines, captured.clone());
+        let run_count = || fs::read_to_string(&counter).unwrap().lines().count();
+
+        let (mut checks, mut watched_files) = reader.read(&input_source, &mut ui).unwrap();
+        run_batch(&mut checks);
+        assert_eq!(run_count(), 1);
+
+        // The check that didn’t change keeps waiting for its interval.
+        change(&path, &format!("{}\n[[cmd]]\nshell = 'true'\n", interval_check));
+        reader.reload(&input_source, &mut checks, &mut watched_files, &mut ui);
+        assert_eq!(run_batch(&mut checks).check_outputs.len(), 2);
+        assert_eq!(run_count(), 1);
+
+        // A file that can’t be read gets its errors printed, and the checks
+        // from before keep getting run.
+        change(&path, "[[nope]]\n");
+        reader.reload(&input_source, &mut checks, &mut watched_files, &mut ui);
+        assert!(String::from_utf8_lossy(&captured.0.lock().unwrap()).contains("read-error"));
+        assert_eq!(run_batch(&mut checks).check_outputs.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,221 @@
+//! Information about the run itself, rather than the checks that were run,
+//! which gets recorded in the result documents so they make sense on their
+//! own after being archived.
+
+use std::env;
+use std::fmt::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::*;
+use serde::{Serialize, Serializer};
+
+
+/// The **run metadata** describes the environment a run took place in:
+/// where and when it happened, and how specsheet was invoked.
+#[derive(Debug, Serialize)]
+pub struct RunMetadata {
+
+    /// The host name of the machine specsheet ran on.
+    pub hostname: Option<String>,
+
+    /// The name of the user specsheet ran as.
+    pub user: Option<String>,
+
+    /// The operating system specsheet was compiled for.
+    pub os: &'static str,
+
+    /// The CPU architecture specsheet was compiled for.
+    pub arch: &'static str,
+
+    /// The version of specsheet that performed the run.
+    pub version: &'static str,
+
+    /// The command-line arguments specsheet was invoked with.
+    pub arguments: Vec<String>,
+
+    /// The time just before the first check was run.
+    pub start_time: Timestamp,
+
+    /// The time just after the last check was run.
+    pub end_time: Timestamp,
+}
+
+impl RunMetadata {
+
+    /// Gathers information about the current environment, using the given
+    /// time as the start of the run, and the current time as the end.
+    pub fn capture(start_time: Timestamp) -> Self {
+        let end_time = Timestamp::now();
+
+        Self {
+            hostname: hostname(),
+            user: users::get_current_username().map(|e| e.to_string_lossy().into()),
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+            version: env!("CARGO_PKG_VERSION"),
+            arguments: env::args_os().skip(1).map(|e| e.to_string_lossy().into()).collect(),
+            start_time,
+            end_time,
+        }
+    }
+
+    /// Returns how long the run took, from its start time to its end time.
+    pub fn duration(&self) -> Duration {
+        self.end_time.0.duration_since(self.start_time.0).unwrap_or_default()
+    }
+}
+
+
+/// Looks up the host name of this machine, returning nothing if it cannot
+/// be determined.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0_u8; 256];
+
+    // This needs unsafe because it’s a libc function. The buffer is one byte
+    // longer than we tell libc about, so it’s always null-terminated.
+    let ret_val = unsafe {
+        libc::gethostname(buf.as_mut_ptr().cast(), buf.len() - 1)
+    };
+
+    if ret_val != 0 {
+        warn!("Failed to get hostname: {}", std::io::Error::last_os_error());
+        return None;
+    }
+
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[.. len]).into())
+}
+
+
+/// A **timestamp** is a point in time, which gets written out in ISO 8601
+/// format, in UTC.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Timestamp(SystemTime);
+
+impl Timestamp {
+
+    /// Returns a timestamp representing the current time.
+    pub fn now() -> Self {
+        Self(SystemTime::now())
+    }
+
+    /// Returns a timestamp representing the given time.
+    pub fn at(time: SystemTime) -> Self {
+        Self(time)
+    }
+
+    /// Fills in a filename template with the parts of this timestamp. The
+    /// template can contain `%Y`, `%m`, `%d`, `%H`, `%M`, and `%S` for the
+    /// year, month, day, hour, minute, and second, and `%%` for a literal
+    /// percent sign. Anything else is left as it is.
+    pub fn format(&self, template: &str) -> String {
+        let (year, month, day, hour, minute, second) = self.parts();
+        let mut output = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('Y')  => write!(output, "{:04}", year).unwrap(),
+                Some('m')  => write!(output, "{:02}", month).unwrap(),
+                Some('d')  => write!(output, "{:02}", day).unwrap(),
+                Some('H')  => write!(output, "{:02}", hour).unwrap(),
+                Some('M')  => write!(output, "{:02}", minute).unwrap(),
+                Some('S')  => write!(output, "{:02}", second).unwrap(),
+                Some('%')  => output.push('%'),
+                Some(other) => { output.push('%'); output.push(other); }
+                None        => output.push('%'),
+            }
+        }
+
+        output
+    }
+
+    /// Returns the number of seconds between the Unix epoch and this
+    /// timestamp.
+    pub fn unix_seconds(self) -> u64 {
+        self.0.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+    }
+
+    /// Splits this timestamp into its year, month, day, hour, minute, and
+    /// second, in UTC.
+    fn parts(self) -> (u64, u64, u64, u64, u64, u64) {
+        let secs = self.unix_seconds();
+
+        let secs_of_day = secs % 86400;
+        let (year, month, day) = civil_from_days(secs / 86400);
+
+        (year, month, day, secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format("%Y-%m-%dT%H:%M:%SZ"))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Converts a number of days since the Unix epoch into a year, month, and
+/// day, using the algorithm from <http://howardhinnant.github.io/date_algorithms.html>.
+/// Times before the epoch are not supported.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn timestamp(secs: u64) -> String {
+        Timestamp(UNIX_EPOCH + Duration::from_secs(secs)).to_string()
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn leap_day() {
+        assert_eq!(timestamp(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn end_of_year() {
+        assert_eq!(timestamp(1_609_459_199), "2020-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn filename_template() {
+        let time = Timestamp(UNIX_EPOCH + Duration::from_secs(1_609_459_199));
+        assert_eq!(time.format("results-%Y%m%dT%H%M%S.json"), "results-20201231T235959.json");
+    }
+
+    #[test]
+    fn filename_template_escapes() {
+        let time = Timestamp(UNIX_EPOCH);
+        assert_eq!(time.format("100%%-%q-%"), "100%-%q-%");
+    }
+}
@@ -0,0 +1,182 @@
+//! Notifications, which send a summary of a run somewhere once it has
+//! finished, such as to a chat channel.
+//!
+//! There are two kinds: a generic webhook, which gets sent the summary as
+//! JSON, and a Slack incoming webhook, which gets sent a message written
+//! for people to read. In continual mode, they only get sent when the
+//! checks start failing or stop failing, rather than after every batch.
+
+use std::fmt::Write as _;
+use std::io;
+
+use serde_json::json;
+
+use crate::doc::{self, CompletedRun};
+use crate::push;
+use crate::results::CheckStatus;
+
+
+/// The number of failed checks to list in a Slack message before the rest
+/// get summed up, so a run where everything fails doesn’t flood the channel.
+const SLACK_FAILURE_LIMIT: usize = 10;
+
+
+/// Where to send notifications about a run.
+#[derive(PartialEq, Debug, Default)]
+pub struct Notifications {
+
+    /// The URL to post a JSON summary of the run to.
+    pub webhook: Option<String>,
+
+    /// The Slack incoming webhook URL to post a message about the run to.
+    pub slack_webhook: Option<String>,
+
+    /// Where the HTML report can be seen, to link to from the
+    /// notifications.
+    pub report_url: Option<String>,
+}
+
+impl Notifications {
+
+    /// Sends a summary of a finished run to each of the places
+    /// notifications go, given whether the run failed.
+    pub fn send(&self, run: &CompletedRun<'_>, failed: bool) -> io::Result<()> {
+        if let Some(url) = &self.webhook {
+            let body = webhook_payload(run, failed, self.report_url.as_deref());
+            push::send("POST", url, "application/json", &body.to_string())?;
+        }
+
+        if let Some(url) = &self.slack_webhook {
+            let body = json!({ "text": slack_message(run, failed, self.report_url.as_deref()) });
+            push::send("POST", url, "application/json", &body.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// The checks in a run that failed, along with the input each one was in.
+fn failed_checks<'run>(run: &'run CompletedRun<'_>) -> Vec<(String, &'run str)> {
+    run.sections.iter()
+        .flat_map(|section| {
+            let input = section.input.to_string();
+            section.results.check_outputs.iter()
+                .filter(|output| matches!(output.status(), CheckStatus::Failed | CheckStatus::Errored | CheckStatus::UnexpectedPass))
+                .map(move |output| (input.clone(), output.name.as_deref().unwrap_or(&output.message)))
+        })
+        .collect()
+}
+
+/// The JSON that gets sent to a generic webhook.
+fn webhook_payload(run: &CompletedRun<'_>, failed: bool, report_url: Option<&str>) -> serde_json::Value {
+    let failed_checks = failed_checks(run).into_iter().map(|(input, check)| json!({
+        "input": input,
+        "check": check,
+    })).collect::<Vec<_>>();
+
+    json!({
+        "passed": ! failed,
+        "totals": run.totals,
+        "failed_checks": failed_checks,
+        "report_url": report_url,
+        "metadata": run.metadata,
+    })
+}
+
+/// The message that gets posted to Slack, in its own flavour of Markdown.
+fn slack_message(run: &CompletedRun<'_>, failed: bool, report_url: Option<&str>) -> String {
+    let host = run.metadata.hostname.as_deref().unwrap_or("an unknown host");
+
+    let mut text = if failed {
+        format!(":x: *specsheet checks failed on {}*\n", slack_escape(host))
+    }
+    else {
+        format!(":white_check_mark: *specsheet checks passed on {}*\n", slack_escape(host))
+    };
+
+    text.push_str(&slack_escape(&doc::totals_line(run.totals)));
+
+    let failed_checks = failed_checks(run);
+    for (input, check) in failed_checks.iter().take(SLACK_FAILURE_LIMIT) {
+        write!(text, "\n• {} ({})", slack_escape(check), slack_escape(input)).unwrap();
+    }
+
+    if failed_checks.len() > SLACK_FAILURE_LIMIT {
+        write!(text, "\n…and {} more", failed_checks.len() - SLACK_FAILURE_LIMIT).unwrap();
+    }
+
+    if let Some(url) = report_url {
+        write!(text, "\n<{}|View the report>", url).unwrap();
+    }
+
+    text
+}
+
+/// Escapes the characters that Slack uses for links and mentions.
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::doc::CompletedSection;
+    use crate::input::InputSource;
+    use crate::meta::{RunMetadata, Timestamp};
+    use crate::results::{CheckOutput, ResultMessage, ResultsSection, Stats};
+
+    fn run_with(outputs: Vec<CheckOutput>) -> CompletedRun<'static> {
+        let results = ResultsSection::from_outputs(outputs);
+        let mut metadata = RunMetadata::capture(Timestamp::now());
+        metadata.hostname = Some("web<1>".into());
+
+        CompletedRun {
+            totals: results.totals,
+            sections: vec![ CompletedSection { input: InputSource::File("checks.toml".into()), results } ],
+            commands: Vec::new(),
+            metadata,
+            trends: Vec::new(),
+            side_processes: Vec::new(),
+        }
+    }
+
+    fn failed(description: &str) -> CheckOutput {
+        CheckOutput {
+            skipped: None,
+            results: vec![ ResultMessage::Failed("no".into()) ],
+            ..CheckOutput::skipped(description.into(), String::new(), None)
+        }
+    }
+
+    #[test]
+    fn webhook() {
+        let run = run_with(vec![ failed("Command ‘false’ succeeds") ]);
+        let payload = webhook_payload(&run, true, Some("https://example.com/report.html"));
+
+        assert_eq!(payload["passed"], false);
+        assert_eq!(payload["failed_checks"], json!([ { "input": "checks.toml", "check": "Command ‘false’ succeeds" } ]));
+        assert_eq!(payload["report_url"], "https://example.com/report.html");
+        assert_eq!(payload["totals"]["check_count"], run.totals.check_count);
+    }
+
+    #[test]
+    fn slack_failures() {
+        let run = run_with((0 .. 12).map(|n| failed(&format!("check {}", n))).collect());
+        let message = slack_message(&run, true, Some("https://example.com/report.html"));
+
+        assert!(message.starts_with(":x: *specsheet checks failed on web&lt;1&gt;*\n"));
+        assert!(message.contains("\n• check 9 (checks.toml)\n…and 2 more\n"));
+        assert!(! message.contains("check 10"));
+        assert!(message.ends_with("\n<https://example.com/report.html|View the report>"));
+    }
+
+    #[test]
+    fn slack_passes() {
+        let run = run_with(Vec::new());
+        let message = slack_message(&run, false, None);
+
+        assert_eq!(message, format!(":white_check_mark: *specsheet checks passed on web&lt;1&gt;*\n{}", doc::totals_line(Stats::default())));
+    }
+}
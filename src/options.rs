@@ -2,20 +2,30 @@
 
 use std::ffi::OsStr;
 use std::fmt;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::time::Duration;
 
 use log::*;
 
-use spec_checks::read::{Rewrites, Rewrite};
+use spec_checks::common::parse_duration;
+use spec_checks::load::Variables;
+use spec_checks::read::{TomlValue, Rewrites, Rewrite};
+use spec_exec::Target;
 
+use crate::cache::DiskCache;
 use crate::commands::GlobalOptions;
-use crate::doc::DocumentPaths;
-use crate::filter::{Filter, TagsFilter, TypesFilter, RunningOrder};
-use crate::input::Inputs;
-use crate::output::{OutputFormat, UseColours};
+use crate::config::ConfigFile;
+use crate::doc::{DocumentPaths, Retention};
+use crate::filter::{Filter, TagsFilter, TypesFilter, NamesFilter, RerunFilter, Shard, RunningOrder};
+use crate::history::HistoryDir;
+use crate::generate::Generator;
+use crate::input::{Inputs, InputFormat};
+use crate::notify::Notifications;
+use crate::output::{OutputFormat, ExtraOutput, UseColours};
+use crate::results::Stats;
 use crate::side::{SideProcess, StartupWait, KillSignal};
-use crate::terminal_ui::{ShownLines, ExpandLevel};
+use crate::terminal_ui::{ShownLines, ExpandLevel, GroupBy, SortBy};
 
 
 /// The **options** contains the entirety of the parsed user input from the
@@ -24,9 +34,22 @@ use crate::terminal_ui::{ShownLines, ExpandLevel};
 pub struct Options {
     pub mode: RunningMode,
     pub output: OutputFormat,
+    pub extra_outputs: Vec<ExtraOutput>,
     pub inputs: Inputs,
+
+    /// The format the input documents are written in, if it was given,
+    /// rather than being picked for each one.
+    pub input_format: Option<InputFormat>,
+
     pub filter: Filter,
     pub rewrites: Rewrites,
+
+    /// Values for variables given with `-O var.NAME=VALUE`, which override
+    /// the ones in the input documents.
+    pub variables: Variables,
+
+    /// What makes the exit status an error.
+    pub exit_policy: ExitPolicy,
 }
 
 /// Specsheet runs in a **mode**, which determines how much it does.
@@ -36,8 +59,11 @@ pub enum RunningMode {
     /// Run each file of tests completely.
     Run(CheckingOptions, EndingOptions),
 
-    /// Run in continual mode.
-    Continual(CheckingOptions),
+    /// Run in continual mode, writing result documents after each batch.
+    Continual(CheckingOptions, DocumentPaths, ContinualOptions),
+
+    /// Run each file of checks, then run it again whenever it changes.
+    Watch(CheckingOptions),
 
     /// Don’t run any checks, just validate each input file’s syntax.
     SyntaxCheckOnly,
@@ -46,6 +72,20 @@ pub enum RunningMode {
     /// executed.
     ListCommandsOnly(GlobalOptions),
 
+    /// Don’t run any checks, just print the commands that would have been
+    /// executed exactly as they would be run, and check that the programs
+    /// they run are installed.
+    DryRun(CheckingOptions),
+
+    /// Don’t run any checks, just explain the ones with the given name or
+    /// description: what they check, the commands they would run, and what
+    /// happens when they pass or fail.
+    Explain(CheckingOptions, String),
+
+    /// Don’t run any checks, just write starter checks for the things of
+    /// the given types on the machine.
+    Generate(CheckingOptions, Vec<Generator>),
+
     /// Don’t run any checks, just list the ones that would have been ran.
     ListChecksOnly,
 
@@ -57,9 +97,38 @@ pub enum RunningMode {
 #[derive(PartialEq, Debug)]
 pub struct CheckingOptions {
     pub delay: Delay,
+    pub threads: Threads,
+
+    /// How long any process may run for before it gets killed, unless it
+    /// has a timeout of its own.
+    pub timeout: Option<Duration>,
+
+    /// Where the commands get run, such as inside a Docker container.
+    pub target: Target,
+
+    /// Whether commands that need to be run as root get run with `sudo`.
+    pub sudo: bool,
+
+    /// The file to record every command that gets run to, as fixtures.
+    pub record: Option<PathBuf>,
+
+    /// The file of fixtures to replay commands from, instead of running
+    /// them.
+    pub replay: Option<PathBuf>,
+
+    /// Where to store the results of commands between runs, if anywhere.
+    pub cache: Option<DiskCache>,
+
+    /// How many processes can be running at once on the async executor,
+    /// if processes should be run on one.
+    pub async_processes: Option<usize>,
+
     pub global_options: GlobalOptions,
     pub directory: RunningDirectory,
-    pub process: Option<SideProcess>,
+
+    /// The side processes to run in the background while the checks get
+    /// run, in the order they get started.
+    pub processes: Vec<SideProcess>,
 }
 
 /// Options for what to do after all the checks have been run, which is only
@@ -67,7 +136,81 @@ pub struct CheckingOptions {
 #[derive(PartialEq, Debug)]
 pub struct EndingOptions {
     pub perform_analysis: bool,
+
+    /// Whether checks that fail because of a known issue should not cause
+    /// the exit status to be an error.
+    pub allow_known_issues: bool,
+
     pub result_documents: DocumentPaths,
+
+    /// Where to send a summary of the run once it has finished.
+    pub notifications: Notifications,
+
+    /// The directory to keep the results of every run in, to report how
+    /// the checks have been changing over time.
+    pub history_dir: Option<HistoryDir>,
+}
+
+/// Options for how long to keep running batches of checks for, which are
+/// only used in continual mode.
+#[derive(PartialEq, Debug, Default)]
+pub struct ContinualOptions {
+
+    /// How long to wait after one batch of checks before running the next.
+    pub interval: Option<Duration>,
+
+    /// How many batches to run before stopping, if there is a limit.
+    pub iterations: Option<u64>,
+
+    /// Whether to stop once a batch has a certain outcome.
+    pub until: Option<StopCondition>,
+
+    /// Whether checks annotated as known issues are allowed to fail,
+    /// without making a batch count as having failed.
+    pub allow_known_issues: bool,
+
+    /// Whether to keep track of how each check turns out in every batch,
+    /// and report the ones whose results flipped when stopping.
+    pub detect_flaky: bool,
+
+    /// The address to serve the results of the latest batch on over HTTP,
+    /// such as `127.0.0.1:9090`.
+    pub serve: Option<String>,
+
+    /// Where to send a summary of a batch when the checks start or stop
+    /// failing.
+    pub notifications: Notifications,
+}
+
+/// The outcome of a batch that makes continual mode stop.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum StopCondition {
+
+    /// Stop after the first batch where a check fails.
+    Failure,
+
+    /// Stop after the first batch where every check passes.
+    Success,
+}
+
+/// The **exit policy** determines which outcomes of running the checks
+/// make the exit status an error, so CI pipelines can choose how strict
+/// specsheet should be.
+#[derive(PartialEq, Debug, Default, Copy, Clone)]
+pub struct ExitPolicy {
+
+    /// Whether checks that fail should not cause the exit status to be an
+    /// error. Errors loading files or running specsheet itself still do.
+    /// This overrides the other two.
+    pub exit_zero: bool,
+
+    /// Whether checks that fail with a severity of ‘warn’ should cause the
+    /// exit status to be an error.
+    pub fail_on_warn: bool,
+
+    /// Whether checks that get skipped, or not run because of the checks
+    /// they need, should cause the exit status to be an error.
+    pub fail_on_skip: bool,
 }
 
 /// The **delay** determines how long to wait between running two checks.
@@ -81,16 +224,27 @@ pub enum Delay {
     RunInstantly,
 }
 
+/// The **threads** determine how many checks can be run at the same time.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Threads {
+
+    /// Run each check once the previous one has finished.
+    One,
+
+    /// Run up to this many checks at once, each on a thread of its own.
+    Many(usize),
+}
+
 /// Which directory checks should be run from.
 #[derive(PartialEq, Debug)]
 pub enum RunningDirectory {
 
-    /// Run commands from the directory the input check file is in. This
-    /// changes directory on every input file, and resets it at the end.
+    /// Run commands from the directory the input check file is in. Each
+    /// input file’s commands get run from a different directory.
     CheckDirectory,
 
-    /// Run commands from a specific directory. This changes directory once,
-    /// for the duration of the program.
+    /// Run commands from a specific directory, for the duration of the
+    /// program.
     OtherDirectory(PathBuf),
 }
 
@@ -98,14 +252,15 @@ pub enum RunningDirectory {
 impl Options {
 
     /// Parses and interprets a set of options from the user’s command-line
-    /// arguments.
+    /// arguments, using the values in the config file for the options that
+    /// weren’t given.
     ///
     /// This returns an `Ok` set of options if successful and running
     /// normally, a `Help` or `Version` variant if one of those options is
     /// specified, or an error variant if there’s an invalid option or
     /// inconsistency within the options after they were parsed.
     #[allow(unused_results)]
-    pub fn getopts<C>(args: C) -> OptionsResult
+    pub fn getopts<C>(args: C, config: &ConfigFile) -> OptionsResult
     where C: IntoIterator,
           C::Item: AsRef<OsStr>,
     {
@@ -114,40 +269,82 @@ impl Options {
         // Meta options
         opts.optflag ("v", "version",          "show version of specsheet");
         opts.optflag ("?", "help",             "show list of command-line options");
+        opts.optopt  ("",  "completions",      "print the shell completions for bash, fish, or zsh", "SHELL");
+        opts.optflag ("",  "print-schema",     "print the JSON Schema of the result documents");
 
         // Running modes
         opts.optflag ("c", "syntax-check",     "don't run, just check the syntax of the input files");
         opts.optflag ("C", "list-commands",    "don't run, just list the commands that would be executed");
+        opts.optflag ("",  "dry-run",          "don't run, just print the commands that would be run and check they're installed");
+        opts.optopt  ("",  "explain",          "don't run, just explain the checks with a name or description", "CHECK");
+        opts.optopt  ("",  "generate",         "don't run, just write starter checks for the machine (apt, homebrew, systemd, tcp, user, group, or all)", "TYPES");
+        opts.optopt  ("",  "diff",             "don't run, just compare a result document with a newer one", "OLD");
         opts.optflag ("l", "list-checks",      "don't run, just list the checks that would be run");
         opts.optflag (" ", "list-tags",        "don't run, just list the tags defined in the documents");
         opts.optflag ("",  "random-order",     "run the checks in a random order");
         opts.optflag ("",  "continual",        "run the checks in continual mode");
+        opts.optflag ("",  "watch",            "run the checks again whenever the input files change");
+        opts.optopt  ("",  "interval",         "amount of time to wait between batches in continual mode", "DURATION");
+        opts.optopt  ("",  "iterations",       "number of batches to run in continual mode", "COUNT");
+        opts.optflag ("",  "until-failure",    "stop continual mode after a batch where a check fails");
+        opts.optflag ("",  "until-success",    "stop continual mode after a batch where every check passes");
+        opts.optflag ("",  "detect-flaky",     "report checks whose results flip between batches in continual mode");
+        opts.optopt  ("",  "serve",            "serve the latest results over HTTP in continual mode", "ADDRESS");
         opts.optopt  ("",  "delay",            "amount of time to delay between checks", "DURATION");
         opts.optopt  ("",  "directory",        "directory to run the tests from", "PATH");
         opts.optopt  ("j", "threads",          "number of threads to run in parallel", "COUNT");
+        opts.optopt  ("",  "command-timeout",  "default amount of time commands may run for", "DURATION");
+        opts.optopt  ("",  "timeout",          "amount of time any process may run for", "DURATION");
+        opts.optopt  ("",  "target",           "where to run commands, such as docker://NAME", "TARGET");
+        opts.optflag ("",  "sudo",             "run commands that need root with sudo");
+        opts.optopt  ("",  "record",           "record the commands that get run to a fixtures file", "PATH");
+        opts.optopt  ("",  "replay",           "replay commands from a fixtures file instead of running them", "PATH");
+        opts.optopt  ("",  "cache-dir",        "directory to cache command results in between runs", "PATH");
+        opts.optopt  ("",  "cache-ttl",        "amount of time cached command results can be used for", "DURATION");
+        opts.optopt  ("",  "async",            "run up to this many commands at once on the async executor", "COUNT");
         opts.optmulti("O", "option",           "set a global option or override the environment", "KEY=VALUE");
         opts.optmulti("R", "rewrite",          "add a rule to rewrite values in the input documents", "THIS->THAT");
         opts.optflag ("z", "analysis",         "switch on analysis");
+        opts.optflag ("",  "allow-known-issues", "don't fail because of checks with known issues");
+        opts.optflag ("",  "exit-zero",        "exit with status 0 even if checks fail");
+        opts.optflag ("",  "fail-on-warn",     "fail if checks with a severity of warn fail");
+        opts.optflag ("",  "fail-on-skip",     "fail if any checks get skipped");
+        opts.optmulti("",  "check",            "run a check given on the command-line", "CHECK");
+        opts.optmulti("",  "toml",             "run the checks in a TOML snippet given on the command-line", "TOML");
+        opts.optopt  ("",  "input-format",     "format of the input documents (toml, yaml, or json)", "FORMAT");
 
         // Background process options
         opts.optmulti("x", "exec",             "process to run in the background during execution", "CMD");
-        opts.optopt  ("",  "exec-delay",       "wait an amount of time before running checks", "DURATION");
-        opts.optopt  ("",  "exec-port",        "wait until a port becomes open before running checks", "PORT");
-        opts.optopt  ("",  "exec-file",        "wait until a file exists before running checks", "PATH");
-        opts.optopt  ("",  "exec-line",        "wait until the process outputs a line before running checks", "REGEX");
-        opts.optopt  ("",  "exec-kill-signal", "signal to send to the background process after finishing", "SIGNAL");
+        opts.optmulti("",  "exec-delay",       "wait an amount of time before running checks", "DURATION");
+        opts.optmulti("",  "exec-port",        "wait until a port becomes open before running checks", "PORT");
+        opts.optmulti("",  "exec-file",        "wait until a file exists before running checks", "PATH");
+        opts.optmulti("",  "exec-line",        "wait until the process outputs a line before running checks", "REGEX");
+        opts.optmulti("",  "exec-timeout",     "give up if the process has not started up after an amount of time", "DURATION");
+        opts.optflagmulti("", "exec-restart", "start the background process again if it exits during execution");
+        opts.optmulti("",  "exec-kill-signal", "signal to send to the background process after finishing", "SIGNAL");
 
         // Filtering options
         opts.optopt  ("t", "tags",             "comma-separated list of tags to run", "TAGS");
         opts.optopt  ("",  "skip-tags",        "comma-separated list of tags to skip", "TAGS");
         opts.optopt  ("T", "types",            "comma-separated list of check types to run", "TYPES");
         opts.optopt  ("",  "skip-types",       "comma-separated list of check types to skip", "TYPES");
+        opts.optmulti("",  "name",             "only run checks whose names match a regex", "REGEX");
+        opts.optmulti("",  "skip-name",        "skip checks whose names match a regex", "REGEX");
+        opts.optopt  ("",  "rerun-failed",     "only run the checks that failed in a JSON or TOML result document", "PATH");
+        opts.optopt  ("",  "shard",            "only run one part of the checks, such as 2/5", "INDEX/COUNT");
 
         // Output options
+        opts.optflag ("q", "quiet",            "only show failures and the summaries");
+        opts.optflag ("V", "verbose",          "show everything, including the commands each check ran");
         opts.optopt  ("s", "successes",        "how to show successful results", "SHOW");
         opts.optopt  ("f", "failures",         "how to show unsuccessful results", "SHOW");
         opts.optopt  ("",  "summaries",        "how to show summaries for each file", "SHOW");
+        opts.optflag ("",  "timings",          "show how long each check took, and the slowest checks");
+        opts.optflag ("",  "show-commands",    "show the commands each check ran under it");
+        opts.optopt  ("",  "group-by",         "print the checks in groups once they have all been run (type, tag, file)", "GROUP");
+        opts.optopt  ("",  "sort",             "sort the checks within each group (name, duration, status)", "FIELD");
         opts.optopt  ("P", "print",            "how to print the output", "FORMAT");
+        opts.optmulti("",  "also-print",       "also print the output to a file in another format", "FORMAT:PATH");
         opts.optopt  ("",  "color",            "when to use terminal colors",  "WHEN");
         opts.optopt  ("",  "colour",           "when to use terminal colours", "WHEN");
 
@@ -155,15 +352,57 @@ impl Options {
         opts.optopt  ("",  "html-doc",         "produce an output HTML document", "PATH");
         opts.optopt  ("",  "json-doc",         "produce an output JSON document", "PATH");
         opts.optopt  ("",  "toml-doc",         "produce an output TOML document", "PATH");
-
-        let matches = match opts.parse(args) {
+        opts.optopt  ("",  "junit-doc",        "produce an output JUnit XML document", "PATH");
+        opts.optopt  ("",  "markdown-doc",     "produce an output Markdown document", "PATH");
+        opts.optopt  ("",  "prom-doc",         "produce an output Prometheus metrics file", "PATH");
+        opts.optopt  ("",  "prom-push",        "push Prometheus metrics to a Pushgateway", "URL");
+        opts.optopt  ("",  "csv-doc",          "produce an output CSV document", "PATH");
+        opts.optopt  ("",  "keep-last",        "only keep this many timestamped result documents", "COUNT");
+        opts.optopt  ("",  "keep-days",        "only keep timestamped result documents for this many days", "DAYS");
+        opts.optopt  ("",  "history-dir",      "keep the results of every run in a directory and report trends", "PATH");
+
+        // Notification options
+        opts.optopt  ("",  "notify-url",       "post a JSON summary of the run to a webhook", "URL");
+        opts.optopt  ("",  "notify-slack",     "post a summary of the run to a Slack webhook", "URL");
+        opts.optopt  ("",  "report-url",       "link to the HTML report at this URL in notifications", "URL");
+
+        let mut args = args.into_iter().map(|a| a.as_ref().to_os_string()).collect::<Vec<_>>();
+        let mut matches = match opts.parse(&args) {
             Ok(m)  => m,
             Err(e) => return OptionsResult::InvalidOptionsFormat(e),
         };
 
+        // The options from the config file go after the ones given on the
+        // command-line, but before any `--` that ends them.
+        let config_args = config.default_args(&matches);
+        if ! config_args.is_empty() {
+            let end = args.iter().position(|a| a == "--").unwrap_or(args.len());
+            args.splice(end .. end, config_args);
+
+            matches = match opts.parse(&args) {
+                Ok(m)  => m,
+                Err(e) => return OptionsResult::InvalidOptionsFormat(e),
+            };
+        }
+
         if matches.opt_present("version") {
             OptionsResult::Version(UseColours::deduce(&matches))
         }
+        else if matches.opt_present("print-schema") {
+            OptionsResult::PrintSchema
+        }
+        else if let Some(old) = matches.opt_str("diff") {
+            match &matches.free[..] {
+                [ new ]  => OptionsResult::Diff(PathBuf::from(old), PathBuf::from(new), UseColours::deduce(&matches)),
+                _        => OptionsResult::InvalidOptions(OptionsError::DiffWithoutTwoDocuments),
+            }
+        }
+        else if let Some(shell) = matches.opt_str("completions") {
+            match Shell::deduce(shell) {
+                Ok(shell) => OptionsResult::Completions(shell),
+                Err(e)    => OptionsResult::InvalidOptions(e),
+            }
+        }
         else if let Some(reason) = Self::check_help(&matches) {
             OptionsResult::Help(reason, UseColours::deduce(&matches))
         }
@@ -178,11 +417,25 @@ impl Options {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         let mode = RunningMode::deduce(matches)?;
         let output = OutputFormat::deduce(matches)?;
+        let extra_outputs = ExtraOutput::deduce_all(matches)?;
         let inputs = Inputs::deduce(matches)?;
-        let filter = Filter::deduce(matches);
+        let input_format = InputFormat::deduce(matches)?;
+        let filter = Filter::deduce(matches)?;
         let rewrites = parse_rewrites(matches)?;
+        let variables = parse_variables(matches)?;
+        let exit_policy = ExitPolicy::deduce(matches);
+
+        // Starter checks get written from the machine, not from documents.
+        if matches!(mode, RunningMode::Generate(..)) && inputs != Inputs::Files(Vec::new()) {
+            return Err(OptionsError::GenerateWithInputs);
+        }
+
+        // Only files can change, so there would be nothing to watch.
+        if matches!(mode, RunningMode::Watch(_)) && ! matches!(inputs, Inputs::Files(_)) {
+            return Err(OptionsError::WatchWithoutFiles);
+        }
 
-        Ok(Self { mode, output, inputs, filter, rewrites })
+        Ok(Self { mode, output, extra_outputs, inputs, input_format, filter, rewrites, variables, exit_policy })
     }
 
     /// Check whether the given set of matches require the help text to be
@@ -191,7 +444,7 @@ impl Options {
         if matches.opt_present("help") {
             Some(HelpReason::Flag)
         }
-        else if matches.free.is_empty() {
+        else if matches.free.is_empty() && ! matches.opt_present("check") && ! matches.opt_present("toml") && ! matches.opt_present("generate") {
             Some(HelpReason::NoArguments)
         }
         else {
@@ -210,15 +463,34 @@ impl RunningMode {
             let global_options = GlobalOptions::deduce(matches)?;
             Ok(Self::ListCommandsOnly(global_options))
         }
+        else if matches.opt_present("dry-run") {
+            let check_opts = CheckingOptions::deduce(matches)?;
+            Ok(Self::DryRun(check_opts))
+        }
+        else if let Some(query) = matches.opt_str("explain") {
+            let check_opts = CheckingOptions::deduce(matches)?;
+            Ok(Self::Explain(check_opts, query))
+        }
+        else if let Some(types) = matches.opt_str("generate") {
+            let check_opts = CheckingOptions::deduce(matches)?;
+            let generators = deduce_generators(&types)?;
+            Ok(Self::Generate(check_opts, generators))
+        }
         else if matches.opt_present("list-checks") {
             Ok(Self::ListChecksOnly)
         }
         else if matches.opt_present("list-tags") {
             Ok(Self::ListTagsOnly)
         }
+        else if matches.opt_present("watch") {
+            let check_opts = CheckingOptions::deduce(matches)?;
+            Ok(Self::Watch(check_opts))
+        }
         else if matches.opt_present("continual") {
             let check_opts = CheckingOptions::deduce(matches)?;
-            Ok(Self::Continual(check_opts))
+            let result_documents = DocumentPaths::deduce(matches)?;
+            let continual_opts = ContinualOptions::deduce(matches)?;
+            Ok(Self::Continual(check_opts, result_documents, continual_opts))
         }
         else {
             let check_opts = CheckingOptions::deduce(matches)?;
@@ -232,10 +504,26 @@ impl RunningMode {
 impl CheckingOptions {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         let delay = Delay::deduce(matches)?;
+        let threads = Threads::deduce(matches)?;
+        let async_processes = deduce_async_processes(matches)?;
+        let timeout = deduce_timeout(matches)?;
+        let target = deduce_target(matches)?;
+        let sudo = matches.opt_present("sudo");
+        let record = matches.opt_str("record").map(PathBuf::from);
+        let replay = matches.opt_str("replay").map(PathBuf::from);
+        let cache = deduce_cache(matches)?;
         let global_options = GlobalOptions::deduce(matches)?;
         let directory = RunningDirectory::deduce(matches);
-        let process = SideProcess::deduce(matches);
-        Ok(Self { delay, global_options, directory, process })
+        let processes = SideProcess::deduce(matches)?;
+        Ok(Self { delay, threads, timeout, target, sudo, record, replay, cache, async_processes, global_options, directory, processes })
+    }
+
+    /// Whether the programs that checks run should be looked for before
+    /// the checks get run. They can only be looked for on this machine, so
+    /// they don’t get looked for when commands get run on another target,
+    /// or get replayed instead of being run at all.
+    pub fn checks_programs(&self) -> bool {
+        self.target == Target::Local && self.replay.is_none()
     }
 }
 
@@ -243,7 +531,7 @@ impl CheckingOptions {
 impl Delay {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         if let Some(duration) = matches.opt_str("delay") {
-            let d = parse_delay(&duration)?;
+            let d = parse_duration(&duration).ok_or(OptionsError::InvalidDelay(duration))?;
             Ok(Self::Wait(d))
         }
         else {
@@ -253,6 +541,99 @@ impl Delay {
 }
 
 
+fn deduce_cache(matches: &getopts::Matches) -> Result<Option<DiskCache>, OptionsError> {
+    let ttl = match matches.opt_str("cache-ttl") {
+        Some(ttl)  => parse_duration(&ttl).ok_or(OptionsError::InvalidCacheTtl(ttl))?,
+        None       => DiskCache::DEFAULT_TTL,
+    };
+
+    let cache = matches.opt_str("cache-dir").map(|directory| {
+        DiskCache { directory: PathBuf::from(directory), ttl }
+    });
+
+    Ok(cache)
+}
+
+fn deduce_async_processes(matches: &getopts::Matches) -> Result<Option<usize>, OptionsError> {
+    let Some(count) = matches.opt_str("async") else {
+        return Ok(None);
+    };
+
+    if cfg!(not(feature = "async")) {
+        return Err(OptionsError::AsyncUnavailable);
+    }
+
+    match count.parse() {
+        Ok(n) if n > 0  => Ok(Some(n)),
+        _               => Err(OptionsError::InvalidAsyncProcesses(count)),
+    }
+}
+
+/// Parses the comma-separated list of types to write starter checks for,
+/// where `all` means every type there is.
+fn deduce_generators(types: &str) -> Result<Vec<Generator>, OptionsError> {
+    let mut generators = Vec::new();
+
+    for name in types.split(',').map(str::trim) {
+        let named = if name == "all" {
+            Generator::ALL.to_vec()
+        }
+        else if let Some(generator) = Generator::parse(name) {
+            vec![ generator ]
+        }
+        else {
+            return Err(OptionsError::InvalidGenerateType(name.into()));
+        };
+
+        for generator in named {
+            if ! generators.contains(&generator) {
+                generators.push(generator);
+            }
+        }
+    }
+
+    Ok(generators)
+}
+
+fn deduce_target(matches: &getopts::Matches) -> Result<Target, OptionsError> {
+    if let Some(target) = matches.opt_str("target") {
+        Target::parse(&target).ok_or(OptionsError::InvalidTarget(target))
+    }
+    else {
+        Ok(Target::Local)
+    }
+}
+
+
+fn deduce_timeout(matches: &getopts::Matches) -> Result<Option<Duration>, OptionsError> {
+    if let Some(timeout) = matches.opt_str("timeout") {
+        match parse_duration(&timeout) {
+            Some(duration)  => Ok(Some(duration)),
+            None            => Err(OptionsError::InvalidTimeout(timeout)),
+        }
+    }
+    else {
+        Ok(None)
+    }
+}
+
+
+impl Threads {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        if let Some(count) = matches.opt_str("threads") {
+            match count.parse() {
+                Ok(1)           => Ok(Self::One),
+                Ok(n) if n > 1  => Ok(Self::Many(n)),
+                _               => Err(OptionsError::InvalidThreads(count)),
+            }
+        }
+        else {
+            Ok(Self::One)
+        }
+    }
+}
+
+
 impl OutputFormat {
     pub fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         if let Some(format) = matches.opt_str("print") {
@@ -261,6 +642,8 @@ impl OutputFormat {
                 "dots"       => Self::Dots,
                 "json-lines" => Self::JsonLines,
                 "tap"        => Self::TAP,
+                "teamcity"   => Self::TeamCity,
+                "azure"      => Self::Azure,
                 _            => return Err(OptionsError::InvalidOutputFormat(format.clone())),
             })
         }
@@ -271,12 +654,90 @@ impl OutputFormat {
 }
 
 
+impl ExtraOutput {
+    fn deduce_all(matches: &getopts::Matches) -> Result<Vec<Self>, OptionsError> {
+        let mut extras = Vec::new();
+
+        for arg in matches.opt_strs("also-print") {
+            let (format, path) = match arg.split_once(':') {
+                Some((format, path)) if ! path.is_empty() => (format, path),
+                _ => return Err(OptionsError::InvalidExtraOutput(arg.clone())),
+            };
+
+            // Files never get ANSI colours, even with `--colour=always`,
+            // because that’s meant for the main output.
+            let format = match format {
+                "ansi"       => OutputFormat::Text(UseColours::Never, ShownLines::deduce(matches)?),
+                "dots"       => OutputFormat::Dots,
+                "json-lines" => OutputFormat::JsonLines,
+                "tap"        => OutputFormat::TAP,
+                "teamcity"   => OutputFormat::TeamCity,
+                "azure"      => OutputFormat::Azure,
+                _            => return Err(OptionsError::InvalidOutputFormat(format.into())),
+            };
+
+            extras.push(Self { format, path: PathBuf::from(path) });
+        }
+
+        Ok(extras)
+    }
+}
+
+
 impl ShownLines {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
-        let successes = ExpandLevel::deduce(matches, "successes")?.unwrap_or(ExpandLevel::Show);
+        // Quiet and verbose only change the defaults, so the other options
+        // can still be used to show more or less than they do.
+        let (quiet, verbose) = match (matches.opt_present("quiet"), matches.opt_present("verbose")) {
+            (true, true)  => return Err(OptionsError::QuietAndVerbose),
+            otherwise     => otherwise,
+        };
+
+        let default_successes = if quiet { ExpandLevel::Hide } else if verbose { ExpandLevel::Expanded } else { ExpandLevel::Show };
+        let default_summaries = if verbose { ExpandLevel::Expanded } else { ExpandLevel::Show };
+
+        let successes = ExpandLevel::deduce(matches, "successes")?.unwrap_or(default_successes);
         let failures  = ExpandLevel::deduce(matches, "failures")?.unwrap_or(ExpandLevel::Expanded);
-        let summaries = ExpandLevel::deduce(matches, "summaries")?.unwrap_or(ExpandLevel::Show);
-        Ok(Self { successes, failures, summaries })
+        let summaries = ExpandLevel::deduce(matches, "summaries")?.unwrap_or(default_summaries);
+        let timings   = verbose || matches.opt_present("timings");
+        let commands  = verbose || matches.opt_present("show-commands");
+        let group_by  = GroupBy::deduce(matches)?;
+        let sort_by   = SortBy::deduce(matches)?;
+        Ok(Self { successes, failures, summaries, timings, commands, group_by, sort_by })
+    }
+}
+
+
+impl GroupBy {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        if let Some(group) = matches.opt_str("group-by") {
+            Ok(Some(match &*group {
+                "type"  => Self::Type,
+                "tag"   => Self::Tag,
+                "file"  => Self::File,
+                _       => return Err(OptionsError::InvalidGroupBy(group.clone())),
+            }))
+        }
+        else {
+            Ok(None)
+        }
+    }
+}
+
+
+impl SortBy {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        if let Some(field) = matches.opt_str("sort") {
+            Ok(Some(match &*field {
+                "name"      => Self::Name,
+                "duration"  => Self::Duration,
+                "status"    => Self::Status,
+                _           => return Err(OptionsError::InvalidSortField(field.clone())),
+            }))
+        }
+        else {
+            Ok(None)
+        }
     }
 }
 
@@ -315,8 +776,27 @@ impl UseColours {
 
 impl Inputs {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
-        if matches.free.is_empty() {
-            unreachable!()  // dealt with in check_help
+        if matches.opt_present("check") || matches.opt_present("toml") {
+            if ! matches.free.is_empty() {
+                return Err(OptionsError::InlineChecksWithFiles);
+            }
+
+            let mut document = String::new();
+            for check in matches.opt_strs("check") {
+                document.push_str(&inline_check_toml(&check)?);
+            }
+
+            for snippet in matches.opt_strs("toml") {
+                document.push_str(&snippet);
+                document.push('\n');
+            }
+
+            Ok(Self::Inline(document))
+        }
+        else if matches.free.is_empty() {
+            // Only generating starter checks needs no input files; the
+            // other modes are dealt with in check_help.
+            Ok(Self::Files(Vec::new()))
         }
         else if matches.free[0] == "-" {
             Ok(Self::Stdin)
@@ -329,13 +809,101 @@ impl Inputs {
 }
 
 
+impl InputFormat {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        let Some(format) = matches.opt_str("input-format") else { return Ok(None) };
+
+        match &*format {
+            "toml"           => Ok(Some(Self::Toml)),
+            "yaml" | "yml"   => Ok(Some(Self::Yaml)),
+            "json"           => Ok(Some(Self::Json)),
+            _                => Err(OptionsError::InvalidInputFormat(format)),
+        }
+    }
+}
+
+
+/// Turns a check given on the command-line, such as
+/// `http url=https://example.com status=200`, into the TOML document that
+/// would have been written if the check were in a file.
+///
+/// Each value gets used as-is if it’s valid TOML, such as a number, boolean,
+/// array, or quoted string; otherwise, it’s treated as a string. Values can
+/// contain spaces if they are quoted or inside brackets.
+fn inline_check_toml(input: &str) -> Result<String, OptionsError> {
+    let mut words = split_inline_words(input).into_iter();
+
+    let check_type = match words.next() {
+        Some(ct) if ct.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => ct,
+        _ => return Err(OptionsError::InvalidInlineCheck(input.into())),
+    };
+
+    let mut lines = vec![ format!("[[{}]]\n", check_type) ];
+    for word in words {
+        let equals_index = match word.find('=') {
+            Some(ei) if ei > 0  => ei,
+            _                   => return Err(OptionsError::InvalidInlineCheck(input.into())),
+        };
+
+        let key = &word[.. equals_index];
+        let value = &word[equals_index + 1 ..];
+
+        if format!("value = {}", value).parse::<TomlValue>().is_ok() {
+            lines.push(format!("{} = {}\n", key, value));
+        }
+        else {
+            lines.push(format!("{} = {}\n", key, TomlValue::String(value.into())));
+        }
+    }
+
+    Ok(lines.concat())
+}
+
+/// Splits an inline check into words at each space, except for spaces
+/// inside quotes or brackets.
+fn split_inline_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut depth = 0_usize;
+
+    for c in input.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q      => quote = None,
+            (Some(_), _)                => {},
+            (None, '\'' | '"')          => quote = Some(c),
+            (None, '[' | '{')           => depth += 1,
+            (None, ']' | '}')           => depth = depth.saturating_sub(1),
+            (None, _) if c.is_whitespace() && depth == 0 => {
+                if ! word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            (None, _)                   => {},
+        }
+
+        word.push(c);
+    }
+
+    if ! word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+
 impl Filter {
-    fn deduce(matches: &getopts::Matches) -> Self {
-        Self {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        Ok(Self {
             tags: TagsFilter::deduce(matches),
             types: TypesFilter::deduce(matches),
+            names: NamesFilter::deduce(matches)?,
+            rerun: RerunFilter::deduce(matches),
+            shard: Shard::deduce(matches)?,
             order: RunningOrder::deduce(matches),
-        }
+        })
     }
 }
 
@@ -374,6 +942,48 @@ impl TypesFilter {
 }
 
 
+impl NamesFilter {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        let regexes = |option_name| {
+            matches.opt_strs(option_name).into_iter().map(|regex| {
+                regex::Regex::new(&regex).map_err(|e| OptionsError::InvalidNameRegex(regex, e.to_string()))
+            }).collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            names: regexes("name")?,
+            skip_names: regexes("skip-name")?,
+        })
+    }
+}
+
+
+impl RerunFilter {
+    fn deduce(matches: &getopts::Matches) -> Self {
+        match matches.opt_str("rerun-failed") {
+            Some(path)  => Self::FailedIn(PathBuf::from(path)),
+            None        => Self::Everything,
+        }
+    }
+}
+
+
+impl Shard {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        let Some(input) = matches.opt_str("shard") else { return Ok(None) };
+
+        let shard = input.split_once('/').and_then(|(index, count)| {
+            Some(Self { index: index.parse().ok()?, count: count.parse().ok()? })
+        });
+
+        match shard {
+            Some(shard) if shard.index >= 1 && shard.index <= shard.count => Ok(Some(shard)),
+            _ => Err(OptionsError::InvalidShard(input)),
+        }
+    }
+}
+
+
 impl RunningOrder {
     fn deduce(matches: &getopts::Matches) -> Self {
         if matches.opt_present("random-order") {
@@ -409,6 +1019,20 @@ impl GlobalOptions {
             }
         }
 
+        // The default command timeout is stored alongside the other
+        // options, as that’s where the shell command looks for it.
+        if let Some(timeout) = matches.opt_str("command-timeout") {
+            if parse_duration(&timeout).is_none() {
+                return Err(OptionsError::InvalidCommandTimeout(timeout));
+            }
+
+            if map.contains_key("cmd.timeout") {
+                return Err(OptionsError::DuplicateGlobal("cmd.timeout".into()));
+            }
+
+            map.insert("cmd.timeout".into(), timeout);
+        }
+
         Ok(Self { map })
     }
 }
@@ -427,61 +1051,116 @@ impl RunningDirectory {
 
 
 impl SideProcess {
-    fn deduce(matches: &getopts::Matches) -> Option<Self> {
-        if let Some(shell) = matches.opt_str("exec") {
-            let wait = StartupWait::deduce(matches).ok()?;
-            let signal = KillSignal::deduce(matches).ok()?;
-            Some(Self { shell, wait, signal })
+
+    /// Deduces every side process to run. Each `--exec` argument gets the
+    /// startup wait and kill signal arguments that come after it, up until
+    /// the next one; arguments that come before the first `--exec` apply to
+    /// the first process, too.
+    fn deduce(matches: &getopts::Matches) -> Result<Vec<Self>, OptionsError> {
+        let execs = matches.opt_strs_pos("exec");
+        let mut processes = Vec::new();
+
+        for (index, (_, shell)) in execs.iter().enumerate() {
+            let start = if index == 0 { 0 } else { execs[index].0 };
+            let end = execs.get(index + 1).map_or(usize::MAX, |next| next.0);
+            let arguments = ExecArguments { matches, positions: start .. end };
+
+            let waits = StartupWait::deduce_all(&arguments)?;
+            let wait_timeout = deduce_exec_timeout(&arguments)?;
+            let signal = KillSignal::deduce(&arguments)?;
+            let restart = arguments.opt_present("exec-restart");
+            processes.push(Self { shell: shell.clone(), waits, wait_timeout, signal, restart });
         }
-        else {
-            None
+
+        Ok(processes)
+    }
+}
+
+
+/// The arguments that apply to one `--exec` process: the ones between
+/// the given positions on the command-line.
+struct ExecArguments<'m> {
+    matches: &'m getopts::Matches,
+    positions: Range<usize>,
+}
+
+impl ExecArguments<'_> {
+
+    /// Returns the value of the argument with the given name, if it was
+    /// given for this process. Giving one more than once for the same
+    /// process is an error, as only one of them could be used.
+    fn opt_str(&self, name: &'static str) -> Result<Option<String>, OptionsError> {
+        let mut values = self.matches.opt_strs_pos(name).into_iter()
+                             .filter(|(position, _)| self.positions.contains(position))
+                             .map(|(_, value)| value);
+
+        let value = values.next();
+        if values.next().is_some() {
+            return Err(OptionsError::DuplicateExecOption(name));
         }
+
+        Ok(value)
+    }
+
+    /// Returns whether the flag with the given name was given for this
+    /// process.
+    fn opt_present(&self, name: &'static str) -> bool {
+        self.matches.opt_positions(name).iter().any(|position| self.positions.contains(position))
     }
 }
 
 
 impl StartupWait {
-    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
-        // TODO: some way to have more than one of these apply at once
-        if let Some(delay) = matches.opt_str("exec-delay") {
-            let duration = parse_delay(&delay)?;
-            Ok(Self::Delay(duration))
+
+    /// Deduces every condition to wait for before the process counts as
+    /// having started up. All of them have to be met, in any order.
+    fn deduce_all(arguments: &ExecArguments<'_>) -> Result<Vec<Self>, OptionsError> {
+        let mut waits = Vec::new();
+
+        if let Some(delay) = arguments.opt_str("exec-delay")? {
+            let duration = parse_duration(&delay).ok_or(OptionsError::InvalidDelay(delay))?;
+            waits.push(Self::Delay(duration));
         }
-        else if let Some(port) = matches.opt_str("exec-port") {
+
+        if let Some(port) = arguments.opt_str("exec-port")? {
             let port_number = port.parse().map_err(|e| OptionsError::InvalidPortNumber(e, port))?;
-            Ok(Self::Port(port_number))
+            waits.push(Self::Port(port_number));
         }
-        else if let Some(path) = matches.opt_str("exec-file") {
+
+        if let Some(path) = arguments.opt_str("exec-file")? {
             let path = PathBuf::from(path);
-            Ok(Self::File(path))
+            waits.push(Self::File(path));
         }
-        else if let Some(regex) = matches.opt_str("exec-line") {
-            // TODO: some way to check for invalid regexes early
-            Ok(Self::OutputLine(regex))
-        }
-        else {
-            Ok(Self::default())
+
+        if let Some(regex) = arguments.opt_str("exec-line")? {
+            if let Err(e) = regex::Regex::new(&regex) {
+                return Err(OptionsError::InvalidExecLine(regex, e.to_string()));
+            }
+
+            waits.push(Self::OutputLine(regex));
         }
+
+        Ok(waits)
     }
 }
 
-
-fn parse_delay(input: &str) -> Result<Duration, OptionsError> {
-    match input.parse() {
-        Ok(seconds) => {
-            Ok(Duration::new(seconds, 0))
-        }
-        Err(e) => {
-            warn!("Invalid delay duration: {}", e);
-            Err(OptionsError::InvalidDelay(input.into()))
+/// Deduces how long to wait for a side process to start up.
+fn deduce_exec_timeout(arguments: &ExecArguments<'_>) -> Result<Option<Duration>, OptionsError> {
+    if let Some(timeout) = arguments.opt_str("exec-timeout")? {
+        match parse_duration(&timeout) {
+            Some(duration)  => Ok(Some(duration)),
+            None            => Err(OptionsError::InvalidExecTimeout(timeout)),
         }
     }
+    else {
+        Ok(None)
+    }
 }
 
 
 impl KillSignal {
-    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
-        if let Some(signal_name) = matches.opt_str("exec-kill-signal") {
+    fn deduce(arguments: &ExecArguments<'_>) -> Result<Self, OptionsError> {
+        if let Some(signal_name) = arguments.opt_str("exec-kill-signal")? {
             match &*signal_name {
                 "int"  | "sigint"  | "2"  => Ok(Self::Int),
                 "kill" | "sigkill" | "9"  => Ok(Self::Kill),
@@ -499,33 +1178,142 @@ impl KillSignal {
 impl EndingOptions {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         let perform_analysis = matches.opt_present("analysis");
-        let result_documents = DocumentPaths::deduce(matches);
-        Ok(Self { perform_analysis, result_documents })
+        let allow_known_issues = matches.opt_present("allow-known-issues");
+        let result_documents = DocumentPaths::deduce(matches)?;
+        let notifications = Notifications::deduce(matches);
+        let history_dir = matches.opt_str("history-dir").map(|path| HistoryDir { path: path.into() });
+        Ok(Self { perform_analysis, allow_known_issues, result_documents, notifications, history_dir })
     }
 }
 
 
-impl DocumentPaths {
+impl ExitPolicy {
     fn deduce(matches: &getopts::Matches) -> Self {
         Self {
-            html_path: matches.opt_str("html-doc").map(PathBuf::from),
-            json_path: matches.opt_str("json-doc").map(PathBuf::from),
-            toml_path: matches.opt_str("toml-doc").map(PathBuf::from),
+            exit_zero: matches.opt_present("exit-zero"),
+            fail_on_warn: matches.opt_present("fail-on-warn"),
+            fail_on_skip: matches.opt_present("fail-on-skip"),
         }
     }
-}
 
+    /// Whether the checks having been run with the given totals should make
+    /// the exit status an error, given whether any of them failed.
+    pub fn checks_have_failed(self, any_failed: bool, totals: Stats) -> bool {
+        if self.exit_zero {
+            return false;
+        }
 
-fn parse_rewrites(matches: &getopts::Matches) -> Result<Rewrites, OptionsError> {
-    let mut rewrites = Rewrites::new();
+        any_failed
+            || (self.fail_on_warn && totals.warning_count > 0)
+            || (self.fail_on_skip && totals.skip_count + totals.blocked_count > 0)
+    }
+}
 
-    for rewrite_rule in matches.opt_strs("rewrite") {
-        let pos = match rewrite_rule.find("->") {
-            Some(p)  => p,
-            None     => return Err(OptionsError::InvalidRewriteRule(rewrite_rule)),
+
+impl ContinualOptions {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        let interval = match matches.opt_str("interval") {
+            Some(interval)  => Some(parse_duration(&interval).ok_or(OptionsError::InvalidInterval(interval))?),
+            None            => None,
         };
 
-        let this = &rewrite_rule[.. pos];
+        let iterations = match matches.opt_str("iterations") {
+            Some(count) => {
+                match count.parse() {
+                    Ok(c) if c > 0  => Some(c),
+                    _               => return Err(OptionsError::InvalidIterations(count)),
+                }
+            }
+            None => {
+                None
+            }
+        };
+
+        let until = match (matches.opt_present("until-failure"), matches.opt_present("until-success")) {
+            (true,  true)   => return Err(OptionsError::ConflictingStopConditions),
+            (true,  false)  => Some(StopCondition::Failure),
+            (false, true)   => Some(StopCondition::Success),
+            (false, false)  => None,
+        };
+
+        let allow_known_issues = matches.opt_present("allow-known-issues");
+        let detect_flaky = matches.opt_present("detect-flaky");
+        let serve = matches.opt_str("serve");
+        let notifications = Notifications::deduce(matches);
+        Ok(Self { interval, iterations, until, allow_known_issues, detect_flaky, serve, notifications })
+    }
+}
+
+
+impl Notifications {
+    fn deduce(matches: &getopts::Matches) -> Self {
+        Self {
+            webhook: matches.opt_str("notify-url"),
+            slack_webhook: matches.opt_str("notify-slack"),
+            report_url: matches.opt_str("report-url"),
+        }
+    }
+}
+
+
+impl DocumentPaths {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        Ok(Self {
+            html_path: matches.opt_str("html-doc").map(PathBuf::from),
+            json_path: matches.opt_str("json-doc").map(PathBuf::from),
+            toml_path: matches.opt_str("toml-doc").map(PathBuf::from),
+            junit_path: matches.opt_str("junit-doc").map(PathBuf::from),
+            markdown_path: matches.opt_str("markdown-doc").map(PathBuf::from),
+            prom_path: matches.opt_str("prom-doc").map(PathBuf::from),
+            prom_push: matches.opt_str("prom-push"),
+            csv_path: matches.opt_str("csv-doc").map(PathBuf::from),
+            retention: Retention::deduce(matches)?,
+        })
+    }
+}
+
+
+impl Retention {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        let keep_last = match matches.opt_str("keep-last") {
+            Some(count) => {
+                match count.parse() {
+                    Ok(c) if c > 0  => Some(c),
+                    _               => return Err(OptionsError::InvalidKeepLast(count)),
+                }
+            }
+            None => {
+                None
+            }
+        };
+
+        let keep_days = match matches.opt_str("keep-days") {
+            Some(days) => {
+                match days.parse() {
+                    Ok(d) if d > 0  => Some(d),
+                    _               => return Err(OptionsError::InvalidKeepDays(days)),
+                }
+            }
+            None => {
+                None
+            }
+        };
+
+        Ok(Self { keep_last, keep_days })
+    }
+}
+
+
+fn parse_rewrites(matches: &getopts::Matches) -> Result<Rewrites, OptionsError> {
+    let mut rewrites = Rewrites::new();
+
+    for rewrite_rule in matches.opt_strs("rewrite") {
+        let pos = match rewrite_rule.find("->") {
+            Some(p)  => p,
+            None     => return Err(OptionsError::InvalidRewriteRule(rewrite_rule)),
+        };
+
+        let this = &rewrite_rule[.. pos];
         let that = &rewrite_rule[pos + 2 ..];
 
         if this.starts_with("http://") || this.starts_with("https://") {
@@ -548,6 +1336,28 @@ fn parse_rewrites(matches: &getopts::Matches) -> Result<Rewrites, OptionsError>
 }
 
 
+/// Reads the global options that start with `var.` as values for
+/// variables. These get read in every mode, unlike the other global
+/// options, as variables can change which checks there are.
+fn parse_variables(matches: &getopts::Matches) -> Result<Variables, OptionsError> {
+    let mut variables = Variables::new();
+
+    for input in matches.opt_strs("option") {
+        let Some(name_value) = input.strip_prefix("var.") else { continue };
+
+        let Some((name, value)) = name_value.split_once('=') else {
+            return Err(OptionsError::InvalidGlobalSyntax(input));
+        };
+
+        if variables.insert(name.into(), TomlValue::String(value.into())).is_some() {
+            return Err(OptionsError::DuplicateGlobal(format!("var.{}", name)));
+        }
+    }
+
+    Ok(variables)
+}
+
+
 /// The result of the `Options::getopts` function.
 #[derive(PartialEq, Debug)]
 pub enum OptionsResult {
@@ -566,6 +1376,37 @@ pub enum OptionsResult {
 
     /// One of the arguments was `--version`, to display the version number.
     Version(UseColours),
+
+    /// One of the arguments was `--completions`, to print the completion
+    /// script for a shell.
+    Completions(Shell),
+
+    /// One of the arguments was `--print-schema`, to print the JSON Schema
+    /// of the result documents.
+    PrintSchema,
+
+    /// One of the arguments was `--diff`, to compare an old result document
+    /// with a new one instead of running any checks.
+    Diff(PathBuf, PathBuf, UseColours),
+}
+
+/// A shell that specsheet has a completion script for.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Shell {
+    Bash,
+    Fish,
+    Zsh,
+}
+
+impl Shell {
+    fn deduce(shell: String) -> Result<Self, OptionsError> {
+        match &*shell {
+            "bash"  => Ok(Self::Bash),
+            "fish"  => Ok(Self::Fish),
+            "zsh"   => Ok(Self::Zsh),
+            _       => Err(OptionsError::InvalidShell(shell)),
+        }
+    }
 }
 
 /// Something wrong with the combination of options the user has picked.
@@ -578,9 +1419,48 @@ pub enum OptionsError {
     /// The `--exec-port` argument was invalid.
     InvalidPortNumber(std::num::ParseIntError, String),
 
+    /// The `--exec-line` argument was not a valid regex.
+    InvalidExecLine(String, String),
+
+    /// A `--name` or `--skip-name` argument was not a valid regex.
+    InvalidNameRegex(String, String),
+
+    /// The `--shard` argument was not two numbers, or was not one of the
+    /// shards there are.
+    InvalidShard(String),
+
+    /// The `--exec-timeout` argument was an invalid duration.
+    InvalidExecTimeout(String),
+
+    /// One of the `--exec-*` arguments was given more than once for the
+    /// same `--exec` process.
+    DuplicateExecOption(&'static str),
+
     /// The `--delay` argument was an invalid duration.
     InvalidDelay(String),
 
+    /// The `--threads` argument was not a positive number.
+    InvalidThreads(String),
+
+    /// The `--timeout` argument was an invalid duration.
+    InvalidTimeout(String),
+
+    /// The `--command-timeout` argument was an invalid duration.
+    InvalidCommandTimeout(String),
+
+    /// The `--target` argument was not a valid target.
+    InvalidTarget(String),
+
+    /// The `--cache-ttl` argument was an invalid duration.
+    InvalidCacheTtl(String),
+
+    /// The `--async` argument was not a positive number.
+    InvalidAsyncProcesses(String),
+
+    /// The `--async` argument was given, but specsheet was built without
+    /// the async executor.
+    AsyncUnavailable,
+
     /// The syntax for a global option was invalid.
     InvalidGlobalSyntax(String),
 
@@ -590,11 +1470,65 @@ pub enum OptionsError {
     /// The `--print` argument was invalid.
     InvalidOutputFormat(String),
 
+    /// An `--also-print` argument was not of the form `FORMAT:PATH`.
+    InvalidExtraOutput(String),
+
     /// The `--successes` or `--failures` argument was invalid.
     InvalidExpandLevel(String),
 
+    /// The `--quiet` and `--verbose` arguments were both given.
+    QuietAndVerbose,
+
+    /// The `--group-by` argument was invalid.
+    InvalidGroupBy(String),
+
+    /// The `--sort` argument was invalid.
+    InvalidSortField(String),
+
     /// A `--rewrite` rule was invalid.
     InvalidRewriteRule(String),
+
+    /// A `--check` argument was invalid.
+    InvalidInlineCheck(String),
+
+    /// The `--input-format` argument was not a known format.
+    InvalidInputFormat(String),
+
+    /// Checks were given on the command-line as well as input files.
+    InlineChecksWithFiles,
+
+    /// Watch mode was used without any input files to watch.
+    WatchWithoutFiles,
+
+    /// One of the `--generate` types was not a type there are starter
+    /// checks for.
+    InvalidGenerateType(String),
+
+    /// Input documents were given as well as `--generate`.
+    GenerateWithInputs,
+
+    /// The `--keep-last` argument was not a positive number.
+    InvalidKeepLast(String),
+
+    /// The `--keep-days` argument was not a positive number.
+    InvalidKeepDays(String),
+
+    /// The `--interval` argument was not a duration.
+    InvalidInterval(String),
+
+    /// The `--iterations` argument was not a positive number.
+    InvalidIterations(String),
+
+    /// Both `--until-failure` and `--until-success` were given.
+    ConflictingStopConditions,
+
+    /// The `--completions` argument was not a shell there are completions
+    /// for.
+    InvalidShell(String),
+
+    /// The `--diff` option was given without exactly one other result
+    /// document to compare with.
+    DiffWithoutTwoDocuments,
 }
 
 /// The reason that help is being displayed. If it’s for the `--help` flag,
@@ -614,12 +1548,41 @@ impl fmt::Display for OptionsError {
         match self {
             Self::InvalidKillSignal(ks)        => write!(f, "Invalid kill signal {:?}", ks),
             Self::InvalidPortNumber(err, num)  => write!(f, "Invalid port number {:?}: {}", num, err),
+            Self::InvalidExecLine(regex, err)  => write!(f, "Invalid output line regex {:?}: {}", regex, err),
+            Self::InvalidNameRegex(regex, err) => write!(f, "Invalid check name regex {:?}: {}", regex, err),
+            Self::InvalidShard(shard)          => write!(f, "Invalid shard {:?} (it must be like ‘2/5’)", shard),
+            Self::InvalidExecTimeout(arg)      => write!(f, "Invalid exec timeout {:?}", arg),
+            Self::DuplicateExecOption(name)    => write!(f, "Option --{} given more than once for the same --exec process", name),
             Self::InvalidDelay(del)            => write!(f, "Invalid delay {:?}", del),
+            Self::InvalidThreads(arg)          => write!(f, "Invalid number of threads {:?}", arg),
+            Self::InvalidTimeout(arg)          => write!(f, "Invalid timeout {:?}", arg),
+            Self::InvalidCommandTimeout(arg)   => write!(f, "Invalid command timeout {:?}", arg),
+            Self::InvalidTarget(arg)           => write!(f, "Invalid target {:?} (expected local or docker://NAME)", arg),
+            Self::InvalidCacheTtl(arg)         => write!(f, "Invalid cache time-to-live {:?}", arg),
+            Self::InvalidAsyncProcesses(arg)   => write!(f, "Invalid number of async processes {:?}", arg),
+            Self::AsyncUnavailable             => write!(f, "Async executor is unavailable (specsheet was built without the ‘async’ feature)"),
             Self::InvalidGlobalSyntax(arg)     => write!(f, "Invalid global option syntax for {:?}", arg),
             Self::DuplicateGlobal(name)        => write!(f, "Global option {:?} was specified twice", name),
             Self::InvalidExpandLevel(arg)      => write!(f, "Invalid expand level {:?}", arg),
+            Self::QuietAndVerbose              => write!(f, "Options --quiet and --verbose cannot be combined"),
+            Self::InvalidGroupBy(arg)          => write!(f, "Invalid grouping {:?} (expected type, tag, or file)", arg),
+            Self::InvalidSortField(arg)        => write!(f, "Invalid sort field {:?} (expected name, duration, or status)", arg),
             Self::InvalidOutputFormat(arg)     => write!(f, "Invalid output format {:?}", arg),
+            Self::InvalidExtraOutput(arg)      => write!(f, "Invalid extra output {:?} (expected FORMAT:PATH)", arg),
             Self::InvalidRewriteRule(arg )     => write!(f, "Invalid rewrite rule {:?}", arg),
+            Self::InvalidInlineCheck(arg)      => write!(f, "Invalid inline check {:?}", arg),
+            Self::InvalidInputFormat(arg)      => write!(f, "Invalid input format {:?} (expected toml, yaml, or json)", arg),
+            Self::InlineChecksWithFiles        => write!(f, "Inline checks cannot be combined with input files"),
+            Self::WatchWithoutFiles            => write!(f, "Watch mode needs input files to watch"),
+            Self::InvalidGenerateType(arg)     => write!(f, "Invalid type to generate {:?} (expected apt, homebrew, systemd, tcp, user, group, or all)", arg),
+            Self::GenerateWithInputs           => write!(f, "Option --generate cannot be combined with input documents"),
+            Self::InvalidKeepLast(arg)         => write!(f, "Invalid number of documents to keep {:?}", arg),
+            Self::InvalidKeepDays(arg)         => write!(f, "Invalid number of days to keep documents {:?}", arg),
+            Self::InvalidInterval(arg)         => write!(f, "Invalid interval {:?}", arg),
+            Self::InvalidIterations(arg)       => write!(f, "Invalid number of iterations {:?}", arg),
+            Self::ConflictingStopConditions    => write!(f, "Options --until-failure and --until-success cannot be combined"),
+            Self::InvalidShell(arg)            => write!(f, "Invalid shell {:?} (expected bash, fish, or zsh)", arg),
+            Self::DiffWithoutTwoDocuments      => write!(f, "Option --diff needs two result documents, such as ‘--diff old.json new.json’"),
         }
     }
 }
@@ -630,7 +1593,11 @@ mod test {
     use super::*;
 
     fn getopts(checks: &[&str]) -> bool {
-        let opts = Options::getopts(checks);
+        getopts_with_config(checks, &ConfigFile::default())
+    }
+
+    fn getopts_with_config(checks: &[&str], config: &ConfigFile) -> bool {
+        let opts = Options::getopts(checks, config);
         println!("Options: {:?}", opts);
 
         if let OptionsResult::Ok(_opts) = opts {
@@ -651,6 +1618,39 @@ mod test {
         assert_eq!(false, getopts(&[ "--version" ]));
     }
 
+    #[test]
+    fn completions() {
+        let opts = Options::getopts(&[ "--completions", "zsh" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::Completions(Shell::Zsh)));
+    }
+
+    #[test]
+    fn print_schema() {
+        let opts = Options::getopts(&[ "--print-schema" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::PrintSchema));
+    }
+
+    #[test]
+    fn diff() {
+        let opts = Options::getopts(&[ "--diff", "old.json", "new.json" ], &ConfigFile::default());
+        match opts {
+            OptionsResult::Diff(old, new, _) => assert_eq!((old, new), (PathBuf::from("old.json"), PathBuf::from("new.json"))),
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn diff_one_document() {
+        let opts = Options::getopts(&[ "--diff", "old.json" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::InvalidOptions(OptionsError::DiffWithoutTwoDocuments)));
+    }
+
+    #[test]
+    fn completions_for_unknown_shell() {
+        let opts = Options::getopts(&[ "--completions", "powershell" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::InvalidOptions(OptionsError::InvalidShell(_))));
+    }
+
     #[test]
     fn check() {
         assert_eq!(true, getopts(&[ "check.toml" ]));
@@ -661,11 +1661,259 @@ mod test {
         assert_eq!(true, getopts(&[ "checks.toml", "--delay=10" ]));
     }
 
+    #[test]
+    fn delay_with_units() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--delay=1m30s" ]));
+    }
+
+    #[test]
+    fn exec_delay_with_units() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--exec", "./server", "--exec-delay", "500ms" ]));
+    }
+
     #[test]
     fn delay_not() {
         assert_eq!(false, getopts(&[ "checks.toml", "--delay=x" ]));
     }
 
+    #[test]
+    fn dry_run_with_target() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--dry-run", "--target", "docker://web" ]));
+    }
+
+    #[test]
+    fn threads_ok() {
+        assert_eq!(true, getopts(&[ "checks.toml", "-j", "4" ]));
+    }
+
+    #[test]
+    fn threads_zero() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--threads=0" ]));
+    }
+
+    #[test]
+    fn threads_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--threads=lots" ]));
+    }
+
+    #[test]
+    fn timeout_ok() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--timeout", "1m" ]));
+    }
+
+    #[test]
+    fn timeout_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--timeout", "soon" ]));
+    }
+
+    #[test]
+    fn exec_line() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--exec", "./server", "--exec-line", "^Listening on" ]));
+    }
+
+    #[test]
+    fn exec_line_invalid_regex() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--exec", "./server", "--exec-line", "(" ]));
+    }
+
+    #[test]
+    fn exec_twice() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--exec", "./backend", "--exec-port", "9000", "--exec", "./server", "--exec-line", "^Listening on" ]));
+    }
+
+    #[test]
+    fn exec_waits_combined() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--exec", "./server", "--exec-port", "9000", "--exec-line", "^Listening on", "--exec-delay", "1", "--exec-timeout", "30s" ]));
+    }
+
+    #[test]
+    fn exec_timeout_invalid() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--exec", "./server", "--exec-port", "9000", "--exec-timeout", "eventually" ]));
+    }
+
+    #[test]
+    fn exec_restart() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--exec", "./server", "--exec-restart", "--exec", "./backend", "--exec-restart" ]));
+    }
+
+    #[test]
+    fn exec_option_twice() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--exec", "./server", "--exec-port", "9000", "--exec-port", "9001" ]));
+    }
+
+    #[test]
+    fn cache_dir() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--cache-dir", ".specsheet-cache", "--cache-ttl", "10m" ]));
+    }
+
+    #[test]
+    fn name_regexes() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--name", "^web", "--name", "db", "--skip-name", "5432" ]));
+    }
+
+    #[test]
+    fn name_regex_invalid() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--name", "(" ]));
+    }
+
+    #[test]
+    fn watch() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--watch" ]));
+    }
+
+    #[test]
+    fn watch_stdin() {
+        assert_eq!(false, getopts(&[ "-", "--watch" ]));
+    }
+
+    #[test]
+    fn shard() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--shard", "2/5" ]));
+    }
+
+    #[test]
+    fn shard_zero() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--shard", "0/5" ]));
+    }
+
+    #[test]
+    fn shard_too_high() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--shard", "6/5" ]));
+    }
+
+    #[test]
+    fn shard_not_numbers() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--shard", "half" ]));
+    }
+
+    #[test]
+    fn config_threads() {
+        let config = ConfigFile { threads: Some(4), .. ConfigFile::default() };
+        assert_eq!(true, getopts_with_config(&[ "checks.toml" ], &config));
+    }
+
+    #[test]
+    fn config_threads_invalid() {
+        let config = ConfigFile { threads: Some(0), .. ConfigFile::default() };
+        assert_eq!(false, getopts_with_config(&[ "checks.toml" ], &config));
+    }
+
+    #[test]
+    fn config_threads_overridden() {
+        let config = ConfigFile { threads: Some(0), .. ConfigFile::default() };
+        assert_eq!(true, getopts_with_config(&[ "checks.toml", "-j", "2" ], &config));
+    }
+
+    #[test]
+    fn config_print_overridden() {
+        let config = ConfigFile { print: Some("nonsense".into()), .. ConfigFile::default() };
+        assert_eq!(true, getopts_with_config(&[ "checks.toml", "--print=tap" ], &config));
+    }
+
+    #[test]
+    fn config_option_overridden() {
+        let mut config = ConfigFile::default();
+        config.options.insert("retries".into(), TomlValue::Integer(2));
+        assert_eq!(true, getopts_with_config(&[ "checks.toml", "-O", "retries=3" ], &config));
+    }
+
+    #[test]
+    fn config_rewrite_invalid() {
+        let config = ConfigFile { rewrites: vec![ "nothing".into() ], .. ConfigFile::default() };
+        assert_eq!(false, getopts_with_config(&[ "checks.toml" ], &config));
+    }
+
+    #[test]
+    fn config_exit_policy() {
+        let config = ConfigFile { fail_on_skip: true, .. ConfigFile::default() };
+        match Options::getopts(&[ "checks.toml", "--exit-zero" ], &config) {
+            OptionsResult::Ok(opts) => assert_eq!(opts.exit_policy, ExitPolicy { exit_zero: true, fail_on_warn: false, fail_on_skip: true }),
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn config_before_double_dash() {
+        let config = ConfigFile { threads: Some(0), .. ConfigFile::default() };
+        assert_eq!(false, getopts_with_config(&[ "--", "checks.toml" ], &config));
+    }
+
+    #[test]
+    fn variable_in_syntax_check() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--syntax-check", "-O", "var.host=example.com" ]));
+    }
+
+    #[test]
+    fn variable_twice() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--list-checks", "-O", "var.host=a", "-O", "var.host=b" ]));
+    }
+
+    #[test]
+    fn variable_without_value() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--syntax-check", "-O", "var.host" ]));
+    }
+
+    #[test]
+    fn async_zero() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--async=0" ]));
+    }
+
+    #[test]
+    #[cfg(not(feature = "async"))]
+    fn async_unavailable() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--async=100" ]));
+    }
+
+    #[test]
+    fn cache_ttl_invalid() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--cache-dir", ".specsheet-cache", "--cache-ttl", "soon" ]));
+    }
+
+    #[test]
+    fn target_docker() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--target", "docker://web" ]));
+    }
+
+    #[test]
+    fn target_local() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--target=local" ]));
+    }
+
+    #[test]
+    fn target_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--target", "docker://" ]));
+    }
+
+    #[test]
+    fn target_unknown_scheme() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--target", "vm://web" ]));
+    }
+
+    #[test]
+    fn record_fixtures() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--record", "fixtures.toml" ]));
+    }
+
+    #[test]
+    fn replay_fixtures() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--replay=fixtures.toml" ]));
+    }
+
+    #[test]
+    fn command_timeout_ok() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--command-timeout", "30s" ]));
+    }
+
+    #[test]
+    fn command_timeout_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--command-timeout", "forever" ]));
+    }
+
+    #[test]
+    fn command_timeout_twice() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--command-timeout", "30s", "-O", "cmd.timeout=1m" ]));
+    }
+
     #[test]
     fn curl_option_ok() {
         assert_eq!(true, getopts(&[ "checks.toml", "-O", "http.localhost=8991" ]));
@@ -676,11 +1924,111 @@ mod test {
         assert_eq!(true, getopts(&[ "checks.toml", "-P", "json-lines" ]));
     }
 
+    #[test]
+    fn output_format_teamcity() {
+        assert_eq!(true, getopts(&[ "checks.toml", "-P", "teamcity" ]));
+    }
+
+    #[test]
+    fn also_print_azure() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--also-print", "azure:run.log" ]));
+    }
+
     #[test]
     fn output_format_not() {
         assert_eq!(false, getopts(&[ "checks.toml", "-P", "yaml-0bj3ctz" ]));
     }
 
+    #[test]
+    fn timings() {
+        match Options::getopts(&[ "checks.toml", "--timings" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { output: OutputFormat::Text(_, shown_lines), .. }) => assert!(shown_lines.timings),
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quiet() {
+        match Options::getopts(&[ "checks.toml", "--quiet" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { output: OutputFormat::Text(_, shown_lines), .. }) => {
+                assert_eq!(shown_lines.successes, ExpandLevel::Hide);
+                assert_eq!(shown_lines.failures, ExpandLevel::Expanded);
+                assert_eq!(shown_lines.summaries, ExpandLevel::Show);
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn verbose() {
+        match Options::getopts(&[ "checks.toml", "-V", "--successes", "show" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { output: OutputFormat::Text(_, shown_lines), .. }) => {
+                assert_eq!(shown_lines.successes, ExpandLevel::Show);
+                assert_eq!(shown_lines.summaries, ExpandLevel::Expanded);
+                assert!(shown_lines.timings);
+                assert!(shown_lines.commands);
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quiet_and_verbose() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--quiet", "--verbose" ]));
+    }
+
+    #[test]
+    fn show_commands() {
+        match Options::getopts(&[ "checks.toml", "--show-commands" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { output: OutputFormat::Text(_, shown_lines), .. }) => {
+                assert!(shown_lines.commands);
+                assert!(! shown_lines.timings);
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn group_and_sort() {
+        match Options::getopts(&[ "checks.toml", "--group-by", "tag", "--sort", "duration" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { output: OutputFormat::Text(_, shown_lines), .. }) => {
+                assert_eq!(shown_lines.group_by, Some(GroupBy::Tag));
+                assert_eq!(shown_lines.sort_by, Some(SortBy::Duration));
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn group_by_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--group-by", "colour" ]));
+    }
+
+    #[test]
+    fn sort_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--sort", "size" ]));
+    }
+
+    #[test]
+    fn also_print_ok() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--print", "ansi", "--also-print", "json-lines:run.jsonl" ]));
+    }
+
+    #[test]
+    fn also_print_twice() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--also-print", "tap:run.tap", "--also-print", "ansi:run.txt" ]));
+    }
+
+    #[test]
+    fn also_print_no_path() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--also-print", "json-lines" ]));
+    }
+
+    #[test]
+    fn also_print_bad_format() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--also-print", "yaml:run.yaml" ]));
+    }
+
     #[test]
     fn expand_level_ok() {
         assert_eq!(true, getopts(&[ "checks.toml", "-s", "expand" ]));
@@ -690,4 +2038,192 @@ mod test {
     fn expand_level_not() {
         assert_eq!(false, getopts(&[ "checks.toml", "-s", "random" ]));
     }
+
+    #[test]
+    fn keep_last_ok() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--json-doc", "results-%Y%m%d.json", "--keep-last", "10" ]));
+    }
+
+    #[test]
+    fn keep_last_zero() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--keep-last", "0" ]));
+    }
+
+    #[test]
+    fn keep_days_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--continual", "--keep-days", "a week" ]));
+    }
+
+    #[test]
+    fn continual_interval() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--continual", "--interval", "30s" ]));
+    }
+
+    #[test]
+    fn continual_interval_not() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--continual", "--interval", "often" ]));
+    }
+
+    #[test]
+    fn continual_iterations() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--continual", "--iterations", "10", "--until-failure" ]));
+    }
+
+    #[test]
+    fn continual_iterations_zero() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--continual", "--iterations", "0" ]));
+    }
+
+    #[test]
+    fn continual_until_both() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--continual", "--until-failure", "--until-success" ]));
+    }
+
+    #[test]
+    fn history_dir() {
+        match Options::getopts(&[ "checks.toml", "--history-dir", "history" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { mode: RunningMode::Run(_, end_opts), .. }) => {
+                assert_eq!(end_opts.history_dir, Some(HistoryDir { path: "history".into() }));
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn continual_serve() {
+        match Options::getopts(&[ "checks.toml", "--continual", "--serve", "127.0.0.1:9090" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { mode: RunningMode::Continual(_, _, continual_opts), .. }) => {
+                assert_eq!(continual_opts.serve, Some("127.0.0.1:9090".into()));
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn continual_detect_flaky() {
+        match Options::getopts(&[ "checks.toml", "--continual", "--iterations", "20", "--detect-flaky" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { mode: RunningMode::Continual(_, _, continual_opts), .. }) => {
+                assert_eq!(continual_opts.iterations, Some(20));
+                assert!(continual_opts.detect_flaky);
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn continual_allow_known_issues() {
+        match Options::getopts(&[ "checks.toml", "--continual", "--until-failure", "--allow-known-issues" ], &ConfigFile::default()) {
+            OptionsResult::Ok(Options { mode: RunningMode::Continual(_, _, continual_opts), .. }) => {
+                assert_eq!(continual_opts.until, Some(StopCondition::Failure));
+                assert!(continual_opts.allow_known_issues);
+            }
+            other => panic!("Unexpected options {:?}", other),
+        }
+    }
+
+    #[test]
+    fn exit_policy_flags() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--fail-on-warn", "--fail-on-skip" ]));
+    }
+
+    #[test]
+    fn exit_policy_default() {
+        let policy = ExitPolicy::default();
+        let totals = Stats { warning_count: 1, skip_count: 1, .. Stats::default() };
+        assert!(policy.checks_have_failed(true, totals));
+        assert!(! policy.checks_have_failed(false, totals));
+    }
+
+    #[test]
+    fn exit_policy_strict() {
+        let policy = ExitPolicy { fail_on_warn: true, fail_on_skip: true, .. ExitPolicy::default() };
+        assert!(policy.checks_have_failed(false, Stats { warning_count: 1, .. Stats::default() }));
+        assert!(policy.checks_have_failed(false, Stats { blocked_count: 1, .. Stats::default() }));
+        assert!(! policy.checks_have_failed(false, Stats::default()));
+    }
+
+    #[test]
+    fn exit_policy_zero() {
+        let policy = ExitPolicy { exit_zero: true, fail_on_warn: true, fail_on_skip: false };
+        assert!(! policy.checks_have_failed(true, Stats { warning_count: 1, .. Stats::default() }));
+    }
+
+    #[test]
+    fn explain() {
+        assert_eq!(true, getopts(&[ "checks.toml", "--explain", "nginx is running" ]));
+    }
+
+    #[test]
+    fn explain_needs_check() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--explain" ]));
+    }
+
+    #[test]
+    fn generate() {
+        let opts = Options::getopts(&[ "--generate", "apt,systemd,apt" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::Ok(Options { mode: RunningMode::Generate(_, generators), .. })
+                               if generators == vec![ Generator::Apt, Generator::Systemd ]));
+    }
+
+    #[test]
+    fn generate_all() {
+        let opts = Options::getopts(&[ "--generate=all", "--target", "docker://web" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::Ok(Options { mode: RunningMode::Generate(_, generators), .. })
+                               if generators.len() == Generator::ALL.len()));
+    }
+
+    #[test]
+    fn generate_invalid_type() {
+        let opts = Options::getopts(&[ "--generate", "apt,cron" ], &ConfigFile::default());
+        assert_eq!(opts, OptionsResult::InvalidOptions(OptionsError::InvalidGenerateType("cron".into())));
+    }
+
+    #[test]
+    fn generate_with_inputs() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--generate", "apt" ]));
+    }
+
+    #[test]
+    fn inline_check() {
+        assert_eq!(true, getopts(&[ "--check", "http url=https://example.com status=200" ]));
+    }
+
+    #[test]
+    fn inline_toml() {
+        assert_eq!(true, getopts(&[ "--toml", "[[ping]]\ntarget = 'localhost'" ]));
+    }
+
+    #[test]
+    fn inline_check_with_files() {
+        assert_eq!(false, getopts(&[ "checks.toml", "--check", "ping target=localhost" ]));
+    }
+
+    #[test]
+    fn inline_check_no_equals() {
+        assert_eq!(false, getopts(&[ "--check", "ping localhost" ]));
+    }
+
+    #[test]
+    fn input_format() {
+        let opts = Options::getopts(&[ "checks.txt", "--input-format", "yaml" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::Ok(Options { input_format: Some(InputFormat::Yaml), .. })));
+    }
+
+    #[test]
+    fn invalid_input_format() {
+        let opts = Options::getopts(&[ "checks.txt", "--input-format", "xml" ], &ConfigFile::default());
+        assert!(matches!(opts, OptionsResult::InvalidOptions(OptionsError::InvalidInputFormat(_))));
+    }
+
+    #[test]
+    fn inline_check_document() {
+        assert_eq!(inline_check_toml("http url=https://example.com/ status=200 headers={ Accept = 'text/html' }"),
+                   Ok(String::from("[[http]]\nurl = \"https://example.com/\"\nstatus = 200\nheaders = { Accept = 'text/html' }\n")));
+    }
+
+    #[test]
+    fn inline_check_quoted_spaces() {
+        assert_eq!(inline_check_toml("cmd shell='echo hi' stdout={string=\"hi\"}"),
+                   Ok(String::from("[[cmd]]\nshell = 'echo hi'\nstdout = {string=\"hi\"}\n")));
+    }
 }
@@ -1,11 +1,24 @@
+use std::convert::TryInto;
+use std::env;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, Write, BufWriter};
+use std::path::PathBuf;
+
+use log::*;
 use serde_json::json;
 
 use spec_checks::{Check, CheckResult, PassResult, FailResult};
+use spec_exec::ExitReason;
 
+use crate::doc::{SideProcessRecord, CompletedSection};
+use crate::history::CheckTrend;
 use crate::input::{InputSource, LoadError};
-use crate::results::Stats;
-use crate::set::ReadError;
-use crate::terminal_ui::{TerminalUI, Colours, ShownLines};
+use crate::meta::Timestamp;
+use crate::results::{Stats, RunDetails, HookFailure, HookStage, Expectation, Severity, CheckOutput, ResultMessage, CorrelationRecord, FlakyRecord};
+use crate::set::{ReadError, ListedCheck};
+use crate::terminal_ui::{TerminalUI, Colours, ShownLines, HeldChecks};
+use crate::theme;
 
 
 /// How to format the output data.
@@ -23,6 +36,24 @@ pub enum OutputFormat {
 
     /// Format the output as TAP (Test Anything Protocol).
     TAP,
+
+    /// Format the output as `##teamcity` service messages.
+    TeamCity,
+
+    /// Format the output as Azure Pipelines logging commands.
+    Azure,
+}
+
+/// An **extra output** is printed to a file at the same time as the main
+/// output is printed to the screen, in a format of its own.
+#[derive(PartialEq, Debug)]
+pub struct ExtraOutput {
+
+    /// The format to print the output in.
+    pub format: OutputFormat,
+
+    /// The path to the file that the output gets printed to.
+    pub path: PathBuf,
 }
 
 
@@ -31,19 +62,231 @@ impl OutputFormat {
         match self {
             Self::Text(uc, sl) => {
                 let tui = TerminalUI { colours: uc.palette(), shown_lines: sl };
-                Output::Text(tui)
+                let held = if sl.holds_checks() { Some(HeldChecks::default()) } else { None };
+                Output::Text(tui, held)
             }
             Self::Dots => {
                 Output::Dots
             }
             Self::JsonLines => {
-                Output::JSON
+                Output::JSON { file: None, totals: Stats::default(), batch_count: None }
             }
             Self::TAP => {
                 Output::TAP { count: 0 }
             }
+            Self::TeamCity => {
+                Output::TeamCity { suite: None }
+            }
+            Self::Azure => {
+                Output::Azure { in_group: false }
+            }
+        }
+    }
+}
+
+
+/// The **outputs** are every place that lines, errors, and check results
+/// get printed to: the main output to standard output, followed by any
+/// extra outputs to files.
+pub struct Outputs {
+    sinks: Vec<(Output, Box<dyn Write + Send>)>,
+}
+
+impl Outputs {
+
+    /// Creates the set of outputs, opening a file for each of the extra
+    /// outputs. Returns an error if any of the files cannot be created.
+    pub fn open(main: OutputFormat, extras: &[ExtraOutput]) -> io::Result<Self> {
+        let mut sinks: Vec<(Output, Box<dyn Write + Send>)> = vec![ (main.ui(), Box::new(io::stdout())) ];
+
+        for extra in extras {
+            let file = File::create(&extra.path).map_err(|e| {
+                io::Error::new(e.kind(), format!("{}: {}", extra.path.display(), e))
+            })?;
+
+            sinks.push((extra.format.ui(), Box::new(BufWriter::new(file))));
+        }
+
+        Ok(Self { sinks })
+    }
+
+    /// Creates a set of outputs that doesn’t print anything anywhere.
+    #[cfg(test)]
+    pub fn discard() -> Self {
+        Self { sinks: Vec::new() }
+    }
+
+    /// Creates a set of outputs that prints to just the given writer, in
+    /// the given format.
+    #[cfg(test)]
+    pub fn writing_to(format: OutputFormat, w: impl Write + Send + 'static) -> Self {
+        Self { sinks: vec![ (format.ui(), Box::new(w)) ] }
+    }
+
+    // A failure to write to one output shouldn’t stop the others from
+    // being written to, so errors just get logged.
+
+    pub fn print_file_section(&mut self, input_source: &InputSource) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_file_section(w.as_mut(), input_source));
+        }
+    }
+
+    pub fn print_load_error(&mut self, input: &InputSource, e: &LoadError) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_load_error(w.as_mut(), input, e));
+        }
+    }
+
+    pub fn print_read_errors(&mut self, es: &[ReadError]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_read_errors(w.as_mut(), es));
+        }
+    }
+
+    pub fn print_check(&mut self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_check(w.as_mut(), check, name, results, details));
+        }
+    }
+
+    pub fn print_skipped(&mut self, check: &impl fmt::Display, name: Option<&String>, reason: &str) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_skipped(w.as_mut(), check, name, reason));
+        }
+    }
+
+    pub fn print_stats(&mut self, stats: Stats) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_stats(w.as_mut(), stats));
+        }
+    }
+
+    pub fn print_listed_check(&mut self, check: &ListedCheck) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_listed_check(w.as_mut(), check));
+        }
+    }
+
+    pub fn print_batch_totals(&mut self, batch_count: u64, stats: Stats) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_batch_totals(w.as_mut(), batch_count, stats));
+        }
+    }
+
+    pub fn print_environment_error(&mut self, check: &impl fmt::Display, name: Option<&String>, error: &str) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_environment_error(w.as_mut(), check, name, error));
+        }
+    }
+
+    pub fn print_hook_failure(&mut self, failure: &HookFailure) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_hook_failure(w.as_mut(), failure));
+        }
+    }
+
+    pub fn print_side_output(&mut self, side_process: &SideProcessRecord) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_side_output(w.as_mut(), side_process));
+        }
+    }
+
+    /// Prints the properties that only the failed checks in a file had in
+    /// common, once analysis has found them.
+    pub fn print_correlations(&mut self, correlations: &[CorrelationRecord]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_correlations(w.as_mut(), correlations));
+        }
+    }
+
+    /// Prints the checks whose results flipped between batches in continual
+    /// mode, once it has stopped.
+    pub fn print_flaky_checks(&mut self, flaky_checks: &[FlakyRecord]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_flaky_checks(w.as_mut(), flaky_checks));
+        }
+    }
+
+    /// Prints how the checks in the run have been changing over the runs in
+    /// the history directory.
+    pub fn print_trends(&mut self, trends: &[CheckTrend]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_trends(w.as_mut(), trends));
         }
     }
+
+    pub fn print_slowest_checks(&mut self, checks: &[&CheckOutput]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_slowest_checks(w.as_mut(), checks));
+        }
+    }
+
+    pub fn print_failure_recap(&mut self, sections: &[CompletedSection]) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_failure_recap(w.as_mut(), sections));
+        }
+    }
+
+    /// Tells the outputs that a check has finished, and everything printed
+    /// for it has been printed.
+    pub fn finish_check(&mut self, check_output: &CheckOutput) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.finish_check(w.as_mut(), check_output));
+        }
+    }
+
+    /// Prints the checks that have been held back to be printed in groups,
+    /// once they have all been run.
+    pub fn print_held_checks(&mut self) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_held_checks(w.as_mut()));
+        }
+    }
+
+    /// Prints anything that has to come before the first check, such as
+    /// the version line of TAP output.
+    pub fn print_start(&mut self) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_start(w.as_mut()));
+        }
+    }
+
+    /// Says that watch mode is waiting for the input files to change. Only
+    /// the outputs meant for humans say so, so it goes to standard error
+    /// instead when the main output is meant for another program to read.
+    pub fn print_waiting_for_changes(&mut self) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_waiting_for_changes(w.as_mut()));
+            log_error(w.flush());
+        }
+
+        if ! matches!(self.sinks[0].0, Output::Text(..) | Output::Dots) {
+            eprintln!("Watching for changes...");
+        }
+    }
+
+    pub fn print_end(&mut self) {
+        for (output, w) in &mut self.sinks {
+            log_error(output.print_end(w.as_mut()));
+            log_error(w.flush());
+        }
+    }
+}
+
+/// Where the terminal UI prints a check: straight to the output, or to
+/// the checks being held back, if they get printed in groups at the end.
+fn check_writer<'w>(w: &'w mut dyn Write, held: &'w mut Option<HeldChecks>) -> &'w mut dyn Write {
+    match held {
+        Some(held)  => &mut held.buffer,
+        None        => w,
+    }
+}
+
+fn log_error(result: io::Result<()>) {
+    if let Err(e) = result {
+        error!("Error printing output: {}", e);
+    }
 }
 
 
@@ -51,63 +294,235 @@ impl OutputFormat {
 /// It would be a trait, but I can’t make it a trait because of some weird
 /// Rust reason I don’t really understand (the language got in my way).
 pub enum Output {
-    Text(TerminalUI),
+    /// The terminal UI, along with the checks it’s holding back to print
+    /// in groups at the end, if it is.
+    Text(TerminalUI, Option<HeldChecks>),
+
     Dots,
-    JSON,
+
+    /// Each check’s event has the file it’s in, and the run’s last event
+    /// has the totals of every file, or of every batch in continual mode.
+    JSON { file: Option<String>, totals: Stats, batch_count: Option<u64> },
+
     TAP { count: u32 },
+
+    /// Service messages group tests into suites, which have to be finished
+    /// with the same name they were started with.
+    TeamCity { suite: Option<String> },
+
+    /// Azure Pipelines can’t nest its groups, so the last one has to be
+    /// ended before the next one starts.
+    Azure { in_group: bool },
 }
 
 impl Output {
     // ugh, this repetition
 
-    pub fn print_file_section(&self, input_source: &InputSource) {
+    pub fn print_file_section(&mut self, w: &mut dyn Write, input_source: &InputSource) -> io::Result<()> {
+        match self {
+            Self::Text(_, Some(held))   => { held.start_file(input_source); Ok(()) }
+            Self::Text(tui, None)       => tui.print_file_section(w, input_source),
+            Self::Dots                  => Ok(()),
+            Self::JSON { file, .. }     => json_print_file_section(w, input_source, file),
+            Self::TAP { .. }            => tap_print_file_section(w, input_source),
+            Self::TeamCity { suite }    => teamcity_print_file_section(w, input_source, suite),
+            Self::Azure { in_group }    => azure_print_file_section(w, input_source, in_group),
+        }
+    }
+
+    pub fn print_load_error(&self, w: &mut dyn Write, input: &InputSource, e: &LoadError) -> io::Result<()> {
         match self {
-            Self::Text(tui)   => tui.print_file_section(input_source),
-            Self::Dots        => {/* do nothing */},
-            Self::JSON        => json_print_file_section(input_source),
-            Self::TAP { .. }  => tap_print_file_section(input_source),
+            Self::Text(tui, _)     => tui.print_load_error(w, input, e),
+            Self::Dots             => dots_print_load_error(w),
+            Self::JSON { .. }      => json_print_load_error(w, input, e),
+            Self::TAP { .. }       => tap_print_load_error(w),
+            Self::TeamCity { .. }  => teamcity_print_error(w, &format!("{}: {}", input, e)),
+            Self::Azure { .. }     => azure_print_issue(w, "error", &format!("{}: {}", input, e)),
         }
     }
 
-    pub fn print_load_error(&self, input: &InputSource, e: LoadError) {
+    pub fn print_read_errors(&self, w: &mut dyn Write, es: &[ReadError]) -> io::Result<()> {
         match self {
-            Self::Text(tui)   => tui.print_load_error(input, e),
-            Self::Dots        => dots_print_load_error(),
-            Self::JSON        => json_print_load_error(input, e),
-            Self::TAP { .. }  => tap_print_load_error(),
+            Self::Text(tui, _)     => tui.print_read_errors(w, es),
+            Self::Dots             => dots_print_read_error(w),
+            Self::JSON { .. }      => json_print_read_error(w, es),
+            Self::TAP { .. }       => tap_print_read_error(w),
+            Self::TeamCity { .. }  => teamcity_print_read_errors(w, es),
+            Self::Azure { .. }     => azure_print_read_errors(w, es),
         }
     }
 
-    pub fn print_read_errors(&self, es: &[ReadError]) {
+    pub fn print_check(&mut self, w: &mut dyn Write, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) -> io::Result<()> {
         match self {
-            Self::Text(tui)   => tui.print_read_errors(es),
-            Self::Dots        => dots_print_read_error(),
-            Self::JSON        => json_print_read_error(es),
-            Self::TAP { .. }  => tap_print_read_error(),
+            Self::Text(tui, held)  => tui.print_check(check_writer(w, held), check, name, results, details),
+            Self::Dots             => dots_print_check(w, check, results, details),
+            Self::JSON { .. }      => Ok(()),  // printed once the check has finished
+            Self::TAP { count }    => tap_print_check(w, check, name, results, details, { *count += 1; *count }),
+            Self::TeamCity { .. }  => teamcity_print_check(w, check, name, results, details),
+            Self::Azure { .. }     => azure_print_check(w, check, name, results, details),
         }
     }
 
-    pub fn print_check(&mut self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>]) {
+    pub fn print_skipped(&mut self, w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, reason: &str) -> io::Result<()> {
         match self {
-            Self::Text(tui)      => tui.print_check(check, name, results),
-            Self::Dots           => dots_print_check(check, results),
-            Self::JSON           => json_print_check(check, name, results),
-            Self::TAP { count }  => tap_print_check(check, name, results, { *count += 1; *count }),
+            Self::Text(tui, held)  => tui.print_skipped(check_writer(w, held), check, name, reason),
+            Self::Dots             => write!(w, "-"),
+            Self::JSON { .. }      => Ok(()),
+            Self::TAP { count }    => tap_print_skipped(w, check, name, reason, { *count += 1; *count }),
+            Self::TeamCity { .. }  => teamcity_print_skipped(w, check, name, reason),
+            Self::Azure { .. }     => azure_print_skipped(w, check, name, reason),
         }
     }
 
-    pub fn print_stats(&self, stats: Stats) {
+    pub fn print_stats(&mut self, w: &mut dyn Write, stats: Stats) -> io::Result<()> {
         match self {
-            Self::Text(tui)   => tui.print_stats(stats),
-            Self::JSON        => json_print_stats(stats),
-            _                 => {/* do nothing */},
+            Self::Text(_, Some(held))        => { held.add_totals(stats); Ok(()) }
+            Self::Text(tui, None)            => tui.print_stats(w, stats),
+            Self::JSON { file, totals, .. }  => json_print_file_finished(w, file.as_deref(), stats, totals),
+            Self::Azure { .. }               => azure_print_stats(w, stats),
+            _                                => Ok(()),
         }
     }
 
-    pub fn print_end(&self) {
+    pub fn print_listed_check(&self, w: &mut dyn Write, check: &ListedCheck) -> io::Result<()> {
         match self {
-            Self::Dots => println!(),
-            _          => {/* do nothing */},
+            Self::JSON { .. }  => json_print_listed_check(w, check),
+            _                  => writeln!(w, "[{}] {}", check.check_type, check.description),
+        }
+    }
+
+    pub fn print_batch_totals(&mut self, w: &mut dyn Write, batch_count: u64, stats: Stats) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)  => tui.print_batch_totals(w, batch_count, stats),
+            Self::JSON { totals, batch_count: count, .. } => {
+                // These get printed with the end of the run.
+                *totals = stats;
+                *count = Some(batch_count);
+                Ok(())
+            }
+            _                   => Ok(()),
+        }
+    }
+
+    pub fn print_environment_error(&mut self, w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, error: &str) -> io::Result<()> {
+        match self {
+            Self::Text(tui, held)  => tui.print_environment_error(check_writer(w, held), check, name, error),
+            Self::Dots             => write!(w, "E"),
+            Self::JSON { .. }      => Ok(()),
+            Self::TAP { count }    => tap_print_environment_error(w, check, name, error, { *count += 1; *count }),
+            Self::TeamCity { .. }  => teamcity_print_environment_error(w, check, name, error),
+            Self::Azure { .. }     => azure_print_environment_error(w, check, name, error),
+        }
+    }
+
+    pub fn print_hook_failure(&mut self, w: &mut dyn Write, failure: &HookFailure) -> io::Result<()> {
+        match self {
+            Self::Text(tui, held)  => {
+                // A file’s own hooks don’t belong to any check, so they
+                // don’t get held back with one.
+                let w = if failure.check.is_some() { check_writer(w, held) } else { w };
+                tui.print_hook_failure(w, failure)
+            }
+            Self::Dots             => write!(w, "E"),
+            Self::JSON { .. }      => json_print_hook_failure(w, failure),
+            Self::TAP { count }    => tap_print_hook_failure(w, failure, count),
+            Self::TeamCity { .. }  => teamcity_print_hook_failure(w, failure),
+            Self::Azure { .. }     => azure_print_hook_failure(w, failure),
+        }
+    }
+
+    pub fn print_side_output(&mut self, w: &mut dyn Write, side_process: &SideProcessRecord) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)          => tui.print_side_output(w, side_process),
+            Self::Dots                  => Ok(()),
+            Self::JSON { .. }           => json_print_side_output(w, side_process),
+            Self::TAP { .. }            => tap_print_side_output(w, side_process),
+            Self::TeamCity { suite }    => teamcity_print_side_output(w, side_process, suite),
+            Self::Azure { in_group }    => azure_print_side_output(w, side_process, in_group),
+        }
+    }
+
+    pub fn print_correlations(&self, w: &mut dyn Write, correlations: &[CorrelationRecord]) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)       => tui.print_correlations(w, correlations),
+            Self::Dots               => Ok(()),
+            Self::JSON { file, .. }  => json_print_correlations(w, file.as_deref(), correlations),
+            Self::TAP { .. }         => tap_print_correlations(w, correlations),
+            Self::TeamCity { .. }    => teamcity_print_correlations(w, correlations),
+            Self::Azure { .. }       => azure_print_correlations(w, correlations),
+        }
+    }
+
+    pub fn print_flaky_checks(&self, w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)     => tui.print_flaky_checks(w, flaky_checks),
+            Self::Dots             => Ok(()),
+            Self::JSON { .. }      => json_print_flaky_checks(w, flaky_checks),
+            Self::TAP { .. }       => tap_print_flaky_checks(w, flaky_checks),
+            Self::TeamCity { .. }  => teamcity_print_flaky_checks(w, flaky_checks),
+            Self::Azure { .. }     => azure_print_flaky_checks(w, flaky_checks),
+        }
+    }
+
+    pub fn print_trends(&self, w: &mut dyn Write, trends: &[CheckTrend]) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)  => tui.print_trends(w, trends),
+            _                   => Ok(()),
+        }
+    }
+
+    pub fn print_slowest_checks(&self, w: &mut dyn Write, checks: &[&CheckOutput]) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)  => tui.print_slowest_checks(w, checks),
+            _                   => Ok(()),
+        }
+    }
+
+    pub fn print_failure_recap(&self, w: &mut dyn Write, sections: &[CompletedSection]) -> io::Result<()> {
+        match self {
+            Self::Text(tui, _)  => tui.print_failure_recap(w, sections),
+            _                   => Ok(()),
+        }
+    }
+
+    pub fn finish_check(&mut self, w: &mut dyn Write, check_output: &CheckOutput) -> io::Result<()> {
+        match self {
+            Self::Text(_, Some(held))  => { held.finish_check(check_output); Ok(()) }
+            Self::JSON { file, .. }    => json_print_check_finished(w, file.as_deref(), check_output),
+            _                          => Ok(()),
+        }
+    }
+
+    pub fn print_held_checks(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::Text(tui, Some(held))  => tui.print_held_checks(w, held),
+            _                            => Ok(()),
+        }
+    }
+
+    pub fn print_start(&self, w: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::JSON { .. }  => json_print_run_started(w),
+            Self::TAP { .. }   => tap_print_start(w),
+            _                  => Ok(()),
+        }
+    }
+
+    pub fn print_waiting_for_changes(&self, w: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::Text(..) | Self::Dots  => writeln!(w, "\nWatching for changes..."),
+            _                            => Ok(()),
+        }
+    }
+
+    pub fn print_end(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::Dots                              => writeln!(w),
+            Self::JSON { totals, batch_count, .. }  => json_print_run_finished(w, *totals, *batch_count),
+            Self::TAP { count }                     => writeln!(w, "1..{}", count),
+            Self::TeamCity { suite }                => teamcity_finish_suite(w, suite),
+            Self::Azure { in_group }                => azure_end_group(w, in_group),
+            Self::Text(..)                          => Ok(()),
         }
     }
 }
@@ -131,16 +546,22 @@ impl UseColours {
 
     /// Whether we should use colours or not. This checks whether the user has
     /// overridden the colour setting, and if not, whether output is to a
-    /// terminal.
+    /// terminal, and whether the `NO_COLOR` environment variable is set.
     pub fn should_use_colours(self) -> bool {
-        self == Self::Always || (atty::is(atty::Stream::Stdout) && self != Self::Never)
+        match self {
+            Self::Always     => true,
+            Self::Never      => false,
+            Self::Automatic  => atty::is(atty::Stream::Stdout) && ! no_colour_requested(),
+        }
     }
 
     /// Creates a palette of colours depending on the user’s wishes or whether
-    /// output is to a terminal.
+    /// output is to a terminal, with any theme from the environment applied.
     pub fn palette(self) -> Colours {
         if self.should_use_colours() {
-            Colours::pretty()
+            let mut colours = Colours::pretty();
+            theme::apply_from_environment(&mut colours);
+            colours
         }
         else {
             Colours::plain()
@@ -149,125 +570,873 @@ impl UseColours {
 }
 
 
+/// Whether the `NO_COLOR` environment variable asks for no colours, which
+/// it does when it’s set to anything other than an empty string.
+fn no_colour_requested() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| ! value.is_empty())
+}
+
+
 // dots
 
-fn dots_print_load_error() {
-    print!("?");
+fn dots_print_load_error(w: &mut dyn Write) -> io::Result<()> {
+    write!(w, "?")
 }
 
-fn dots_print_read_error() {
-    print!("?");
+fn dots_print_read_error(w: &mut dyn Write) -> io::Result<()> {
+    write!(w, "?")
 }
 
-fn dots_print_check(_check: &impl Check, results: &[CheckResult<impl PassResult, impl FailResult>]) {
+fn dots_print_check(w: &mut dyn Write, _check: &impl Check, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) -> io::Result<()> {
     let passed = results.iter().all(CheckResult::passed);
     if passed {
-        print!(".");
+        write!(w, ".")?;
+    }
+    else if details.known_issue.is_some() || details.expected == Expectation::Fail {
+        write!(w, "x")?;
+    }
+    else if details.severity == Severity::Warn {
+        write!(w, "w")?;
     }
     else {
-        print!("X");
+        write!(w, "X")?;
     }
+
+    Ok(())
 }
 
 
 // tap
 
-fn tap_print_file_section(input_source: &InputSource) {
-    println!("# {}", input_source);
+fn tap_print_start(w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, "TAP version 13")
 }
 
-fn tap_print_load_error() {
-    println!("# Load error");
+fn tap_print_file_section(w: &mut dyn Write, input_source: &InputSource) -> io::Result<()> {
+    writeln!(w, "# {}", input_source)
 }
 
-fn tap_print_read_error() {
-    println!("# Load error");
+fn tap_print_load_error(w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, "# Load error")
 }
 
-fn tap_print_check(check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], count: u32) {
-    let name = name.cloned().unwrap_or_else(|| check.to_string());
+fn tap_print_read_error(w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, "# Load error")
+}
 
+fn tap_print_check(w: &mut dyn Write, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>, count: u32) -> io::Result<()> {
+    let name = tap_name(check, name);
     let passed = results.iter().all(CheckResult::passed);
+
+    // Known issues are what the TODO directive is for: consumers report
+    // them as expected failures, or as unexpected successes.
+    // The same goes for checks that are expected to fail, and for failed
+    // checks that are only warnings, which don’t fail the run either.
+    let name = match (details.known_issue, details.expected, details.severity) {
+        (Some(issue), _, _)                                   => format!("{} # TODO known issue {}", name, issue),
+        (None, Expectation::Fail, _)                          => format!("{} # TODO expected to fail", name),
+        (None, Expectation::Pass, Severity::Warn) if ! passed => format!("{} # TODO warning", name),
+        (None, Expectation::Pass, _)                          => name,
+    };
+
     if passed {
-        println!("ok {} - {}", count, name);
+        writeln!(w, "ok {} - {}", count, name)?;
     }
     else {
-        println!("fail {} - {}", count, name);
+        writeln!(w, "not ok {} - {}", count, name)?;
+
+        let severity = if details.severity == Severity::Warn { "warn" } else { "fail" };
+        tap_print_diagnostics_start(w, &first_failure(results), severity)?;
+        writeln!(w, "  results:")?;
 
         for result in results {
             match result {
-                CheckResult::Passed(message) => println!("  {}", message),
-                CheckResult::Failed(message) => println!("  {}", message),
-                CheckResult::CommandError(message) => println!("  {}", message),
+                CheckResult::Passed(pass) => {
+                    writeln!(w, "    - state: passed")?;
+                    writeln!(w, "      message: {}", yaml_string(&pass.to_string()))?;
+
+                    if let Some((_, output)) = pass.command_output() {
+                        writeln!(w, "      output: {}", yaml_string(output))?;
+                    }
+                }
+                CheckResult::Failed(fail) => {
+                    writeln!(w, "    - state: failed")?;
+                    writeln!(w, "      message: {}", yaml_string(&fail.to_string()))?;
+
+                    if let Some((_, output)) = fail.command_output() {
+                        writeln!(w, "      output: {}", yaml_string(output))?;
+                    }
+                    else if let Some((_, expected, got)) = fail.diff_output() {
+                        writeln!(w, "      expected: {}", yaml_string(expected))?;
+                        writeln!(w, "      got: {}", yaml_string(got))?;
+                    }
+                }
+                CheckResult::CommandError(err) => {
+                    writeln!(w, "    - state: error")?;
+                    writeln!(w, "      message: {}", yaml_string(&err.to_string()))?;
+
+                    if let Some(lines) = err.partial_output().filter(|lines| ! lines.is_empty()) {
+                        writeln!(w, "      output: {}", yaml_string(&lines.join("\n")))?;
+                    }
+                }
             }
         }
+
+        writeln!(w, "  ...")?;
+    }
+
+    if let Some(budget) = details.budget {
+        if budget.over_budget {
+            writeln!(w, "# over budget: took {}ms, budget {}ms", budget.taken_ms, budget.budget_ms)?;
+        }
+    }
+
+    if details.attempts > 1 {
+        writeln!(w, "# attempts: {}", details.attempts)?;
     }
+
+    writeln!(w, "# took: {}ms", details.runtime.as_millis())?;
+
+    Ok(())
 }
 
+fn tap_print_skipped(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, reason: &str, count: u32) -> io::Result<()> {
+    let name = tap_name(check, name);
+    writeln!(w, "ok {} - {} # SKIP {}", count, name, reason)
+}
 
-// json
+fn tap_print_environment_error(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, error: &str, count: u32) -> io::Result<()> {
+    let name = tap_name(check, name);
+    writeln!(w, "not ok {} - {}", count, name)?;
+    tap_print_diagnostics_start(w, &format!("environment died: {}", error), "fail")?;
+    writeln!(w, "  ...")
+}
 
-fn json_print_file_section(input_source: &InputSource) {
-    println!("{}", json!({
-        "file": {
-            "path": input_source.to_string(),
+/// A check whose setup failed gets a test line of its own, as it doesn’t
+/// get one otherwise; every other failed hook gets a comment.
+fn tap_print_hook_failure(w: &mut dyn Write, failure: &HookFailure, count: &mut u32) -> io::Result<()> {
+    match (failure.stage, &failure.check) {
+        (HookStage::Setup, Some(check)) => {
+            *count += 1;
+            writeln!(w, "not ok {} - {}", count, tap_escape(check))?;
+            tap_print_diagnostics_start(w, &format!("setup ‘{}’ failed: {}", failure.shell, failure.error), "fail")?;
+            writeln!(w, "  ...")
+        }
+        _ => {
+            writeln!(w, "# {} ‘{}’ failed: {}", failure.stage, failure.shell, failure.error)
         }
-    }));
+    }
+}
+
+/// Returns the name of a check for its test line: its own name if it has
+/// one, or its description otherwise, escaped.
+fn tap_name(check: &impl fmt::Display, name: Option<&String>) -> String {
+    match name {
+        Some(name)  => tap_escape(name),
+        None        => tap_escape(&check.to_string()),
+    }
+}
+
+/// Escapes the characters in a test’s description that would otherwise
+/// start a directive, such as a `#` in a check’s name.
+fn tap_escape(description: &str) -> String {
+    description.replace('\\', "\\\\").replace('#', "\\#")
+}
+
+/// Starts the YAML block of diagnostics that follows a failed test, which
+/// has the failure message and its severity. It has to be ended with a
+/// line of three dots.
+fn tap_print_diagnostics_start(w: &mut dyn Write, message: &str, severity: &str) -> io::Result<()> {
+    writeln!(w, "  ---")?;
+    writeln!(w, "  message: {}", yaml_string(message))?;
+    writeln!(w, "  severity: {}", severity)
+}
+
+/// Quotes a string for a YAML diagnostic block. A JSON string is also a
+/// valid YAML one, and it keeps multi-line output on a single line.
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
 }
 
-fn json_print_load_error(input_source: &InputSource, e: LoadError) {
-    println!("{}", json!({
-        "load-error": {
-            "path": input_source.to_string(),
-            "error": e.to_string(),
+fn tap_print_side_output(w: &mut dyn Write, side_process: &SideProcessRecord) -> io::Result<()> {
+    writeln!(w, "# side process output: {}", side_process.shell)?;
+
+    for line in &side_process.output {
+        writeln!(w, "#   {}", line.line)?;
+    }
+
+    Ok(())
+}
+
+fn tap_print_correlations(w: &mut dyn Write, correlations: &[CorrelationRecord]) -> io::Result<()> {
+    if correlations.is_empty() {
+        writeln!(w, "# no correlations detected")?;
+    }
+
+    for correlation in correlations {
+        writeln!(w, "# analysis: {}", correlation)?;
+    }
+
+    Ok(())
+}
+
+fn tap_print_flaky_checks(w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+    if flaky_checks.is_empty() {
+        writeln!(w, "# no flaky checks detected")?;
+    }
+
+    for flaky in flaky_checks {
+        writeln!(w, "# flaky: {} ({})", flaky, flaky.input)?;
+
+        for output in &flaky.outputs {
+            let outcome = if output.passed { "passed" } else { "failed" };
+            writeln!(w, "#   {} with: {}", outcome, output.results.join("; "))?;
         }
-    }));
+    }
+
+    Ok(())
+}
+
+
+// teamcity
+
+fn teamcity_print_file_section(w: &mut dyn Write, input_source: &InputSource, suite: &mut Option<String>) -> io::Result<()> {
+    teamcity_finish_suite(w, suite)?;
+
+    let name = teamcity_escape(&input_source.to_string());
+    writeln!(w, "##teamcity[testSuiteStarted name='{}']", name)?;
+    *suite = Some(name);
+    Ok(())
 }
 
-fn json_print_read_error(es: &[ReadError]) {
-    println!("{}", json!({
-        "read-error": {
-            "errors": es.iter().map(|e| e.inner.to_string()).collect::<Vec<_>>(),
+fn teamcity_finish_suite(w: &mut dyn Write, suite: &mut Option<String>) -> io::Result<()> {
+    if let Some(name) = suite.take() {
+        writeln!(w, "##teamcity[testSuiteFinished name='{}']", name)?;
+    }
+
+    Ok(())
+}
+
+fn teamcity_print_error(w: &mut dyn Write, message: &str) -> io::Result<()> {
+    writeln!(w, "##teamcity[message text='{}' status='ERROR']", teamcity_escape(message))
+}
+
+fn teamcity_print_read_errors(w: &mut dyn Write, es: &[ReadError]) -> io::Result<()> {
+    for err in es {
+        let at = match (&err.file, err.position) {
+            (Some(file), Some(position))  => format!("{}:{}: ", file.display(), position),
+            (None, Some(position))        => format!("{}: ", position),
+            (_, None)                     => String::new(),
+        };
+
+        teamcity_print_error(w, &format!("{}[{}] {}", at, err.name, err.inner))?;
+    }
+
+    Ok(())
+}
+
+/// Checks that fail without failing the run, such as ones that were
+/// expected to, get reported as passing tests, as service messages have
+/// no other way to say that a test failing is fine.
+fn teamcity_print_check(w: &mut dyn Write, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) -> io::Result<()> {
+    let name = teamcity_escape(&name.cloned().unwrap_or_else(|| check.to_string()));
+    writeln!(w, "##teamcity[testStarted name='{}']", name)?;
+
+    let passed = results.iter().all(CheckResult::passed);
+    let details_text = result_lines(results).join("\n");
+
+    if passed && details.expected == Expectation::Fail {
+        writeln!(w, "##teamcity[testFailed name='{}' message='expected to fail, but passed']", name)?;
+    }
+    else if passed || details.expected == Expectation::Fail {
+        // nothing else to say
+    }
+    else if details.severity == Severity::Warn {
+        writeln!(w, "##teamcity[message text='{}' status='WARNING']", teamcity_escape(&format!("warning: {}", first_failure(results))))?;
+    }
+    else {
+        let message = match details.known_issue {
+            Some(issue)  => format!("{} (known issue ‘{}’)", first_failure(results), issue),
+            None         => first_failure(results),
+        };
+
+        writeln!(w, "##teamcity[testFailed name='{}' message='{}' details='{}']", name, teamcity_escape(&message), teamcity_escape(&details_text))?;
+    }
+
+    writeln!(w, "##teamcity[testFinished name='{}' duration='{}']", name, details.runtime.as_millis())
+}
+
+fn teamcity_print_skipped(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, reason: &str) -> io::Result<()> {
+    let name = name.cloned().unwrap_or_else(|| check.to_string());
+    writeln!(w, "##teamcity[testIgnored name='{}' message='{}']", teamcity_escape(&name), teamcity_escape(reason))
+}
+
+fn teamcity_print_environment_error(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, error: &str) -> io::Result<()> {
+    let name = teamcity_escape(&name.cloned().unwrap_or_else(|| check.to_string()));
+    writeln!(w, "##teamcity[testStarted name='{}']", name)?;
+    writeln!(w, "##teamcity[testFailed name='{}' message='{}']", name, teamcity_escape(&format!("environment died: {}", error)))?;
+    writeln!(w, "##teamcity[testFinished name='{}']", name)
+}
+
+/// A check whose setup failed gets a test of its own, as it doesn’t get one
+/// otherwise; every other failed hook gets logged as an error.
+fn teamcity_print_hook_failure(w: &mut dyn Write, failure: &HookFailure) -> io::Result<()> {
+    let message = format!("{} ‘{}’ failed: {}", failure.stage, failure.shell, failure.error);
+
+    match (failure.stage, &failure.check) {
+        (HookStage::Setup, Some(check)) => {
+            let name = teamcity_escape(check);
+            writeln!(w, "##teamcity[testStarted name='{}']", name)?;
+            writeln!(w, "##teamcity[testFailed name='{}' message='{}']", name, teamcity_escape(&message))?;
+            writeln!(w, "##teamcity[testFinished name='{}']", name)
         }
-    }));
+        _ => {
+            teamcity_print_error(w, &message)
+        }
+    }
 }
 
-fn json_print_check(check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>]) {
+fn teamcity_print_side_output(w: &mut dyn Write, side_process: &SideProcessRecord, suite: &mut Option<String>) -> io::Result<()> {
+    teamcity_finish_suite(w, suite)?;
+
+    let block = teamcity_escape(&format!("Side process output: {}", side_process.shell));
+    writeln!(w, "##teamcity[blockOpened name='{}']", block)?;
+
+    for line in &side_process.output {
+        let status = if line.stream == "stderr" { "WARNING" } else { "NORMAL" };
+        writeln!(w, "##teamcity[message text='{}' status='{}']", teamcity_escape(&line.line), status)?;
+    }
+
+    writeln!(w, "##teamcity[blockClosed name='{}']", block)
+}
+
+fn teamcity_print_correlations(w: &mut dyn Write, correlations: &[CorrelationRecord]) -> io::Result<()> {
+    for correlation in correlations {
+        writeln!(w, "##teamcity[message text='{}' status='WARNING']", teamcity_escape(&format!("Analysis: {}", correlation)))?;
+    }
+
+    Ok(())
+}
+
+fn teamcity_print_flaky_checks(w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+    for flaky in flaky_checks {
+        writeln!(w, "##teamcity[message text='{}' status='WARNING']", teamcity_escape(&format!("Flaky: {} ({})", flaky, flaky.input)))?;
+    }
+
+    Ok(())
+}
+
+/// Escapes a value in a `##teamcity` service message, where the quote and
+/// bracket characters have to be preceded by a `|`.
+fn teamcity_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\''        => escaped.push_str("|'"),
+            '\n'        => escaped.push_str("|n"),
+            '\r'        => escaped.push_str("|r"),
+            '|'         => escaped.push_str("||"),
+            '['         => escaped.push_str("|["),
+            ']'         => escaped.push_str("|]"),
+            '\u{0085}'  => escaped.push_str("|x"),
+            '\u{2028}'  => escaped.push_str("|l"),
+            '\u{2029}'  => escaped.push_str("|p"),
+            c           => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+
+// azure
+
+fn azure_print_file_section(w: &mut dyn Write, input_source: &InputSource, in_group: &mut bool) -> io::Result<()> {
+    azure_end_group(w, in_group)?;
+    writeln!(w, "##[group]{}", azure_escape(&input_source.to_string()))?;
+    *in_group = true;
+    Ok(())
+}
+
+fn azure_end_group(w: &mut dyn Write, in_group: &mut bool) -> io::Result<()> {
+    if *in_group {
+        writeln!(w, "##[endgroup]")?;
+        *in_group = false;
+    }
+
+    Ok(())
+}
+
+/// Logs an error or a warning, which shows up in the pipeline’s summary
+/// as well as in the log.
+fn azure_print_issue(w: &mut dyn Write, issue_type: &str, message: &str) -> io::Result<()> {
+    writeln!(w, "##vso[task.logissue type={}]{}", issue_type, azure_escape(message))
+}
+
+/// Read errors get logged with where they are, so the pipeline can link to
+/// the line in the check document.
+fn azure_print_read_errors(w: &mut dyn Write, es: &[ReadError]) -> io::Result<()> {
+    for err in es {
+        let mut properties = String::from("type=error");
+
+        if let Some(file) = &err.file {
+            write!(properties, ";sourcepath={}", azure_escape_property(&file.display().to_string())).unwrap();
+        }
+
+        if let Some(position) = err.position {
+            write!(properties, ";linenumber={};columnnumber={}", position.line, position.column).unwrap();
+        }
+
+        writeln!(w, "##vso[task.logissue {}]{}", properties, azure_escape(&format!("[{}] {}", err.name, err.inner)))?;
+    }
+
+    Ok(())
+}
+
+fn azure_print_check(w: &mut dyn Write, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) -> io::Result<()> {
+    let name = name.cloned().unwrap_or_else(|| check.to_string());
     let passed = results.iter().all(CheckResult::passed);
 
-    let mut stages = Vec::new();
-    for result in results {
+    if passed && details.expected == Expectation::Fail {
+        azure_print_issue(w, "error", &format!("{}: expected to fail, but passed", name))?;
+    }
+    else if passed {
+        writeln!(w, "✔ {}", azure_escape(&name))?;
+    }
+    else if details.expected == Expectation::Fail {
+        writeln!(w, "✘ {} (failed as expected)", azure_escape(&name))?;
+    }
+    else if details.severity == Severity::Warn {
+        azure_print_issue(w, "warning", &format!("{}: {}", name, first_failure(results)))?;
+    }
+    else if let Some(issue) = details.known_issue {
+        azure_print_issue(w, "error", &format!("{}: {} (known issue ‘{}’)", name, first_failure(results), issue))?;
+    }
+    else {
+        azure_print_issue(w, "error", &format!("{}: {}", name, first_failure(results)))?;
+    }
+
+    if ! passed {
+        for line in result_lines(results) {
+            writeln!(w, "  {}", azure_escape(&line))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn azure_print_skipped(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, reason: &str) -> io::Result<()> {
+    let name = name.cloned().unwrap_or_else(|| check.to_string());
+    writeln!(w, "- {} (skipped: {})", azure_escape(&name), azure_escape(reason))
+}
+
+fn azure_print_environment_error(w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, error: &str) -> io::Result<()> {
+    let name = name.cloned().unwrap_or_else(|| check.to_string());
+    azure_print_issue(w, "error", &format!("{}: environment died: {}", name, error))
+}
+
+fn azure_print_hook_failure(w: &mut dyn Write, failure: &HookFailure) -> io::Result<()> {
+    let hook = format!("{} ‘{}’ failed: {}", failure.stage, failure.shell, failure.error);
+
+    match &failure.check {
+        Some(check)  => azure_print_issue(w, "error", &format!("{}: {}", check, hook)),
+        None         => azure_print_issue(w, "error", &hook),
+    }
+}
+
+fn azure_print_side_output(w: &mut dyn Write, side_process: &SideProcessRecord, in_group: &mut bool) -> io::Result<()> {
+    azure_end_group(w, in_group)?;
+    writeln!(w, "##[group]Side process output: {}", azure_escape(&side_process.shell))?;
+
+    for line in &side_process.output {
+        writeln!(w, "{}", azure_escape(&line.line))?;
+    }
+
+    writeln!(w, "##[endgroup]")
+}
+
+fn azure_print_correlations(w: &mut dyn Write, correlations: &[CorrelationRecord]) -> io::Result<()> {
+    for correlation in correlations {
+        azure_print_issue(w, "warning", &format!("Analysis: {}", correlation))?;
+    }
+
+    Ok(())
+}
+
+fn azure_print_flaky_checks(w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+    for flaky in flaky_checks {
+        azure_print_issue(w, "warning", &format!("Flaky: {} ({})", flaky, flaky.input))?;
+    }
+
+    Ok(())
+}
+
+fn azure_print_stats(w: &mut dyn Write, stats: Stats) -> io::Result<()> {
+    writeln!(w, "{}/{} successful", stats.pass_count, stats.pass_count + stats.fail_count + stats.err_count)
+}
+
+/// Escapes the message of an Azure Pipelines logging command, which has to
+/// fit on one line.
+fn azure_escape(value: &str) -> String {
+    value.replace('%', "%AZP25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a property of an Azure Pipelines logging command, which also
+/// can’t contain the characters that separate and end the properties.
+fn azure_escape_property(value: &str) -> String {
+    azure_escape(value).replace(';', "%3B").replace(']', "%5D")
+}
+
+
+// shared between the CI formats
+
+/// Returns the message of the first result that didn’t pass.
+fn first_failure(results: &[CheckResult<impl PassResult, impl FailResult>]) -> String {
+    results.iter().find_map(|result| {
         match result {
-            CheckResult::Passed(pass) => {
-                stages.push(json!({ "status": "pass", "message": pass.to_string() }));
-            }
+            CheckResult::Passed(_)            => None,
+            CheckResult::Failed(fail)         => Some(fail.to_string()),
+            CheckResult::CommandError(err)    => Some(err.to_string()),
+        }
+    }).unwrap_or_default()
+}
 
-            CheckResult::Failed(fail) => {
-                stages.push(json!({ "status": "fail", "message": fail.to_string() }));
-            }
+/// Returns a line for each of a check’s results, saying whether it passed.
+fn result_lines(results: &[CheckResult<impl PassResult, impl FailResult>]) -> Vec<String> {
+    results.iter().map(|result| {
+        match result {
+            CheckResult::Passed(pass)         => format!("✔ {}", pass),
+            CheckResult::Failed(fail)         => format!("✘ {}", fail),
+            CheckResult::CommandError(err)    => format!("? {}", err),
+        }
+    }).collect()
+}
 
-            CheckResult::CommandError(err) => {
-                stages.push(json!({ "status": "error", "message": err.to_string() }));
-            }
+
+// json
+
+fn json_print_run_started(w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "run-started",
+        "version": env!("CARGO_PKG_VERSION"),
+        "time": Timestamp::now(),
+    }))
+}
+
+fn json_print_file_section(w: &mut dyn Write, input_source: &InputSource, file: &mut Option<String>) -> io::Result<()> {
+    *file = Some(input_source.to_string());
+
+    writeln!(w, "{}", json!({
+        "event": "file-started",
+        "file": file,
+    }))
+}
+
+fn json_print_load_error(w: &mut dyn Write, input_source: &InputSource, e: &LoadError) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "load-error",
+        "file": input_source.to_string(),
+        "error": e.to_string(),
+    }))
+}
+
+fn json_print_read_error(w: &mut dyn Write, es: &[ReadError]) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "read-error",
+        "errors": es.iter().map(|e| json!({
+            "message": e.inner.to_string(),
+            "table": e.name,
+            "file": e.file,
+            "line": e.position.map(|p| p.line),
+            "column": e.position.map(|p| p.column),
+        })).collect::<Vec<_>>(),
+    }))
+}
+
+/// Prints everything about a check once it has finished, whether it was
+/// run or not, along with the commands it ran and what they printed.
+fn json_print_check_finished(w: &mut dyn Write, file: Option<&str>, check_output: &CheckOutput) -> io::Result<()> {
+    let results = check_output.results.iter().map(|result| {
+        match result {
+            ResultMessage::Passed(message)  => json!({ "status": "pass",  "message": message }),
+            ResultMessage::Failed(message)  => json!({ "status": "fail",  "message": message }),
+            ResultMessage::Error(message)   => json!({ "status": "error", "message": message }),
         }
+    }).collect::<Vec<_>>();
+
+    let commands = check_output.ran_commands.iter().map(|command| {
+        let exit_status = match command.exit_reason {
+            ExitReason::Status(status)  => Some(status),
+            _                           => None,
+        };
+
+        json!({
+            "invocation": command.invocation,
+            "exit-status": exit_status,
+            "output": command.output_text(),
+        })
+    }).collect::<Vec<_>>();
+
+    writeln!(w, "{}", json!({
+        "event": "check-finished",
+        "file": file,
+        "type": check_output.check_type,
+        "name": check_output.name,
+        "description": check_output.message,
+        "tags": check_output.tags,
+        "status": check_output.status().name(),
+        "passed": check_output.passed,
+        "results": results,
+        "skip-reason": check_output.skipped,
+        "blocked-by": check_output.blocked_by,
+        "known-issue": check_output.known_issue,
+        "expected": check_output.expected,
+        "severity": check_output.severity,
+        "attempts": check_output.attempts,
+        "runtime-ms": check_output.runtime.as_millis().try_into().unwrap_or(u64::MAX),
+        "budget": check_output.budget.map(|b| json!({
+            "budget-ms":   b.budget_ms,
+            "taken-ms":    b.taken_ms,
+            "over-budget": b.over_budget,
+        })),
+        "hook-failures": check_output.hook_failures,
+        "commands": commands,
+    }))
+}
+
+/// Prints a failed setup or teardown command that belongs to a file. The
+/// ones that belong to checks are part of their check-finished events.
+fn json_print_hook_failure(w: &mut dyn Write, failure: &HookFailure) -> io::Result<()> {
+    if failure.check.is_some() {
+        return Ok(());
     }
 
-    println!("{}", json!({
-        "ran-check": {
-            "name": name.cloned().unwrap_or_else(|| check.to_string()),
-            "passed": passed,
-            "stages": stages,
-        }
-    }));
+    writeln!(w, "{}", json!({
+        "event": "hook-failed",
+        "stage": failure.stage,
+        "shell": failure.shell,
+        "error": failure.error,
+    }))
+}
+
+fn json_print_side_output(w: &mut dyn Write, side_process: &SideProcessRecord) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "side-process-output",
+        "shell": side_process.shell,
+        "output": side_process.output,
+    }))
 }
 
-fn json_print_stats(stats: Stats) {
-    println!("{}", json!({
-        "stats": {
-            "check-count": stats.check_count,
-            "pass-count":  stats.pass_count,
-            "fail-count":  stats.fail_count,
-            "err-count":   stats.err_count,
-        },
-    }));
+fn json_print_listed_check(w: &mut dyn Write, check: &ListedCheck) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "listed-check",
+        "type": check.check_type,
+        "name": check.name,
+        "tags": check.tags,
+        "description": check.description,
+        "file": check.location.file,
+        "line": check.location.start.map(|p| p.line),
+        "column": check.location.start.map(|p| p.column),
+    }))
+}
+
+fn json_print_file_finished(w: &mut dyn Write, file: Option<&str>, stats: Stats, totals: &mut Stats) -> io::Result<()> {
+    *totals += stats;
+
+    writeln!(w, "{}", json!({
+        "event": "file-finished",
+        "file": file,
+        "totals": json_stats(stats),
+    }))
+}
+
+fn json_print_correlations(w: &mut dyn Write, file: Option<&str>, correlations: &[CorrelationRecord]) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "analysis-finished",
+        "file": file,
+        "correlations": correlations,
+    }))
+}
+
+fn json_print_flaky_checks(w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "flaky-checks-found",
+        "flaky-checks": flaky_checks,
+    }))
+}
+
+fn json_print_run_finished(w: &mut dyn Write, totals: Stats, batch_count: Option<u64>) -> io::Result<()> {
+    writeln!(w, "{}", json!({
+        "event": "run-finished",
+        "time": Timestamp::now(),
+        "batch-count": batch_count,
+        "totals": json_stats(totals),
+    }))
+}
+
+fn json_stats(stats: Stats) -> serde_json::Value {
+    json!({
+        "check-count": stats.check_count,
+        "pass-count":  stats.pass_count,
+        "fail-count":  stats.fail_count,
+        "err-count":   stats.err_count,
+        "over-budget-count": stats.over_budget_count,
+        "known-failure-count": stats.known_failure_count,
+        "resolved-issue-count": stats.resolved_issue_count,
+        "skip-count": stats.skip_count,
+        "blocked-count": stats.blocked_count,
+        "hook-failure-count": stats.hook_failure_count,
+        "expected-failure-count": stats.expected_failure_count,
+        "unexpected-pass-count": stats.unexpected_pass_count,
+        "warning-count": stats.warning_count,
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    use crate::terminal_ui::ExpandLevel;
+
+    struct Thing;
+
+    impl fmt::Display for Thing {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "thing works")
+        }
+    }
+
+    impl Check for Thing {
+        const TYPE: &'static str = "thing";
+    }
+
+    struct Message(&'static str);
+
+    impl fmt::Display for Message {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl PassResult for Message {}
+    impl FailResult for Message {}
+
+    fn details() -> RunDetails<'static> {
+        RunDetails { budget: None, known_issue: None, attempts: 1, expected: Expectation::Pass, severity: Severity::Error, runtime: Duration::from_millis(5), commands: &[] }
+    }
+
+    fn tap(print: impl FnOnce(&mut Output, &mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut output = OutputFormat::TAP.ui();
+        let mut buffer = Vec::new();
+        output.print_start(&mut buffer).unwrap();
+        print(&mut output, &mut buffer).unwrap();
+        output.print_end(&mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn text(print: impl FnOnce(&mut Output, &mut Vec<u8>) -> io::Result<()>) -> String {
+        let shown_lines = ShownLines { successes: ExpandLevel::Show, failures: ExpandLevel::Show, summaries: ExpandLevel::Show, timings: false, commands: false, group_by: None, sort_by: None };
+        let mut output = OutputFormat::Text(UseColours::Never, shown_lines).ui();
+        let mut buffer = Vec::new();
+        print(&mut output, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn text_known_issue_fails() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Failed(Message("status was 1")) ];
+        let output = text(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { known_issue: Some("#12"), ..details() }));
+        assert_eq!(output, " ✘ thing works (known issue ‘#12’)\n");
+    }
+
+    #[test]
+    fn text_known_issue_passes() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Passed(Message("it ran")) ];
+        let output = text(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { known_issue: Some("#12"), ..details() }));
+        assert_eq!(output, " ✔ thing works (known issue ‘#12’ no longer fails)\n");
+    }
+
+    #[test]
+    fn text_known_issue_stats() {
+        let stats = Stats { check_count: 3, pass_count: 1, fail_count: 2, known_failure_count: 2, resolved_issue_count: 1, ..Stats::default() };
+        let output = text(|o, w| o.print_stats(w, stats));
+        assert!(output.contains("\n   2 failures are known issues\n"));
+        assert!(output.contains("\n   1 known issue no longer fails\n"));
+    }
+
+    #[test]
+    fn tap_passes() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Passed(Message("it ran")) ];
+        let output = tap(|o, w| {
+            o.print_check(w, &Thing, None, &results, details())?;
+            o.print_check(w, &Thing, None, &results, details())
+        });
+
+        assert_eq!(output, "TAP version 13\nok 1 - thing works\n# took: 5ms\nok 2 - thing works\n# took: 5ms\n1..2\n");
+    }
+
+    #[test]
+    fn tap_failure_diagnostics() {
+        let results: [CheckResult<Message, Message>; 2] = [ CheckResult::Passed(Message("it ran")), CheckResult::Failed(Message("status was 1")) ];
+        let output = tap(|o, w| o.print_check(w, &Thing, None, &results, details()));
+
+        assert_eq!(output, "TAP version 13\n\
+                            not ok 1 - thing works\n  \
+                            ---\n  \
+                            message: \"status was 1\"\n  \
+                            severity: fail\n  \
+                            results:\n    \
+                            - state: passed\n      \
+                            message: \"it ran\"\n    \
+                            - state: failed\n      \
+                            message: \"status was 1\"\n  \
+                            ...\n\
+                            # took: 5ms\n\
+                            1..1\n");
+    }
+
+    #[test]
+    fn tap_skip() {
+        let output = tap(|o, w| o.print_skipped(w, &Thing, None, "‘thing’ is not installed"));
+        assert_eq!(output, "TAP version 13\nok 1 - thing works # SKIP ‘thing’ is not installed\n1..1\n");
+    }
+
+    #[test]
+    fn tap_todo_known_issue() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Failed(Message("status was 1")) ];
+        let output = tap(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { known_issue: Some("#12"), ..details() }));
+        assert!(output.contains("\nnot ok 1 - thing works # TODO known issue #12\n"));
+    }
+
+    #[test]
+    fn tap_todo_expected_failure() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Failed(Message("status was 1")) ];
+        let output = tap(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { expected: Expectation::Fail, ..details() }));
+        assert!(output.contains("\nnot ok 1 - thing works # TODO expected to fail\n"));
+    }
+
+    #[test]
+    fn tap_todo_warning() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Failed(Message("status was 1")) ];
+        let output = tap(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { severity: Severity::Warn, ..details() }));
+        assert!(output.contains("\nnot ok 1 - thing works # TODO warning\n"));
+        assert!(output.contains("\n  severity: warn\n"));
+    }
+
+    #[test]
+    fn tap_passing_warning() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Passed(Message("it ran")) ];
+        let output = tap(|o, w| o.print_check(w, &Thing, None, &results, RunDetails { severity: Severity::Warn, ..details() }));
+        assert!(output.contains("\nok 1 - thing works\n"));
+    }
+
+    #[test]
+    fn tap_escapes_names() {
+        let results: [CheckResult<Message, Message>; 1] = [ CheckResult::Passed(Message("it ran")) ];
+        let name = String::from("issue #12 is fixed");
+        let output = tap(|o, w| o.print_check(w, &Thing, Some(&name), &results, details()));
+        assert!(output.contains("\nok 1 - issue \\#12 is fixed\n"));
+    }
 }
@@ -0,0 +1,132 @@
+//! The preflight, which looks for the programs that checks run before any
+//! of them get run.
+//!
+//! A check whose program isn’t installed, such as a `dns` check on a
+//! machine without `dig`, can’t say anything about whether the thing it
+//! checks is right or wrong. Rather than failing with an error when it gets
+//! run, it gets skipped, and counted separately from the checks that passed
+//! or failed.
+//!
+//! The same goes for a check that has been told to only run on certain
+//! kinds of machine, when it’s not on one.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use log::*;
+
+use spec_checks::load::OnlyIf;
+
+use crate::commands::Commands;
+
+
+/// The **preflight** remembers which programs have been looked for, so
+/// each one only gets searched for once however many checks run it.
+#[derive(Debug, Default)]
+pub struct Preflight {
+    installed: BTreeMap<OsString, bool>,
+}
+
+impl Preflight {
+
+    /// Returns the names of the programs that the commands primed in the
+    /// given set would run that could not be found, in order.
+    pub fn missing_programs(&mut self, commands: Commands) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        for (command, _) in commands.list_commands() {
+            let program = command.get_program();
+            let directory = command.get_current_dir();
+
+            let installed = *self.installed.entry(program.to_os_string())
+                                 .or_insert_with(|| is_installed(program, directory));
+
+            let name = program.to_string_lossy().into_owned();
+            if ! installed && ! missing.contains(&name) {
+                debug!("Program not found -> {:?}", program);
+                missing.push(name);
+            }
+        }
+
+        missing
+    }
+}
+
+/// Describes why a check that runs the given missing programs got skipped.
+pub fn skip_reason(missing_programs: &[String]) -> String {
+    let quoted = missing_programs.iter().map(|p| format!("‘{}’", p)).collect::<Vec<_>>();
+
+    match quoted.as_slice() {
+        [ one ]  => format!("{} is not installed", one),
+        _        => format!("{} are not installed", quoted.join(", ")),
+    }
+}
+
+/// Returns why a check with the given conditions shouldn’t be run on this
+/// machine, if any of them aren’t met.
+pub fn unmet_condition(only_if: &OnlyIf) -> Option<String> {
+    if let Some(os) = &only_if.os {
+        if os != env::consts::OS {
+            return Some(format!("only runs on ‘{}’", os));
+        }
+    }
+
+    if let Some(arch) = &only_if.arch {
+        if arch != env::consts::ARCH {
+            return Some(format!("only runs on ‘{}’", arch));
+        }
+    }
+
+    if let Some(program) = &only_if.command_exists {
+        if ! is_installed(OsStr::new(program), None) {
+            return Some(format!("‘{}’ is not installed", program));
+        }
+    }
+
+    for (name, value) in only_if.env.iter().flatten() {
+        if env::var_os(name).as_deref() != Some(OsStr::new(value)) {
+            return Some(format!("‘{}’ is not ‘{}’", name, value));
+        }
+    }
+
+    None
+}
+
+/// Whether the given program can be run. A program given as a path, rather
+/// than a name, has to be at that path, relative to the directory it gets
+/// run from; otherwise, it has to be in one of the directories in `$PATH`,
+/// or one of the `sbin` directories that programs run by root, such as
+/// `ufw`, live in, which are often missing from the `$PATH` of other users.
+pub fn is_installed(program: &OsStr, directory: Option<&Path>) -> bool {
+    let path = Path::new(program);
+
+    if path.components().count() > 1 {
+        return match directory {
+            Some(directory)  => is_executable(&directory.join(path)),
+            None             => is_executable(path),
+        };
+    }
+
+    let in_path = match env::var_os("PATH") {
+        Some(paths)  => env::split_paths(&paths).any(|dir| is_executable(&dir.join(path))),
+        None         => false,
+    };
+
+    in_path || SBIN_DIRECTORIES.iter().any(|dir| is_executable(&Path::new(dir).join(path)))
+}
+
+/// The directories that programs only meant to be run by root get
+/// installed to.
+const SBIN_DIRECTORIES: &[&str] = &[ "/usr/local/sbin", "/usr/sbin", "/sbin" ];
+
+/// Whether the file at the given path exists and can be executed by
+/// somebody.
+fn is_executable(path: &Path) -> bool {
+    match path.metadata() {
+        Ok(metadata)  => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_)        => false,
+    }
+}
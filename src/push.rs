@@ -0,0 +1,42 @@
+//! Pushing results to other services over HTTP, such as a Prometheus
+//! Pushgateway.
+//!
+//! Requests get made by running curl, the same program that HTTP checks
+//! use, so specsheet doesn’t need an HTTP client of its own. curl’s error
+//! message gets passed on if the request fails, or if the server responds
+//! with an error status.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use log::*;
+
+
+/// Sends the given body to a URL with the given HTTP method, returning an
+/// error if it could not be sent or the server rejected it.
+pub fn send(method: &str, url: &str, content_type: &str, body: &str) -> io::Result<()> {
+    debug!("Sending {} request to {:?}", method, url);
+
+    let mut child = Command::new("curl")
+        .args([ "--silent", "--show-error", "--fail", "--request", method ])
+        .arg("--header").arg(format!("Content-Type: {}", content_type))
+        .arg("--data-binary").arg("@-")
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    }
+    else {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(io::Error::other(format!("could not send to {}: {}", url, message)))
+    }
+}
@@ -0,0 +1,37 @@
+// Shows only the checks that match the filters at the top of the page,
+// and hides the sections that have none left.
+(function() {
+    var statusFilter = document.getElementById('status-filter');
+    var typeFilter = document.getElementById('type-filter');
+    var tagFilter = document.getElementById('tag-filter');
+    var textFilter = document.getElementById('text-filter');
+
+    function applyFilters() {
+        var text = textFilter.value.toLowerCase();
+        var filtering = statusFilter.value || typeFilter.value || tagFilter.value || text;
+
+        document.querySelectorAll('li.check').forEach(function(check) {
+            var tags = check.dataset.tags ? check.dataset.tags.split(' ') : [];
+
+            check.hidden = (statusFilter.value && check.dataset.status !== statusFilter.value)
+                        || (typeFilter.value && check.dataset.type !== typeFilter.value)
+                        || (tagFilter.value && tags.indexOf(tagFilter.value) < 0)
+                        || (text && check.textContent.toLowerCase().indexOf(text) < 0);
+        });
+
+        document.querySelectorAll('section.document').forEach(function(section) {
+            section.hidden = filtering && ! section.querySelector('li.check:not([hidden])');
+        });
+    }
+
+    [ statusFilter, typeFilter, tagFilter, textFilter ].forEach(function(filter) {
+        filter.addEventListener('input', applyFilters);
+    });
+
+    document.querySelectorAll('.legend li').forEach(function(item) {
+        item.addEventListener('click', function() {
+            statusFilter.value = statusFilter.value === item.className ? '' : item.className;
+            applyFilters();
+        });
+    });
+})();
@@ -1,21 +1,193 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 use derive_more::AddAssign;
-use serde::Serialize;
+use serde::{Serialize, Serializer, Deserialize};
+
+use spec_analysis::{AnalysisTable, RunHistory};
+use spec_exec::RanCommand;
 
 
 #[derive(Debug, Serialize)]
 pub struct ResultsSection {
     pub check_outputs: Vec<CheckOutput>,
+
+    /// The setup and teardown commands of the file itself that failed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hook_failures: Vec<HookFailure>,
+
     pub totals: Stats,
+
+    /// The properties that only the checks that failed had in common, if
+    /// analysis was switched on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlations: Option<Vec<CorrelationRecord>>,
 }
 
-#[derive(Debug, Serialize)]
+/// A property that every check involving it failed on, found by analysis,
+/// such as every check that connected to a certain host failing.
+#[derive(Debug, Clone, Serialize)]
+pub struct CorrelationRecord {
+
+    /// The kind of thing the checks had in common, such as ‘path’ or ‘host’.
+    pub property: &'static str,
+
+    /// The thing itself, such as the path or the hostname.
+    pub value: String,
+
+    /// The number of failed checks that involved it.
+    pub failures: u32,
+}
+
+/// The results of each check over several batches in continual mode, kept
+/// by the input the check was read from and its description, with its
+/// result messages as its output.
+pub type CheckHistory = RunHistory<(String, String), Vec<String>>;
+
+/// A check whose result flipped between passing and failing over the
+/// batches in continual mode, even though nothing about it changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlakyRecord {
+
+    /// The input the check was read from.
+    pub input: String,
+
+    /// The check’s description.
+    pub check: String,
+
+    /// The number of batches the check was run in.
+    pub runs: u32,
+
+    /// The number of those batches where the check passed.
+    pub passes: u32,
+
+    /// The number of times the check’s result flipped from one batch to
+    /// the next.
+    pub flips: u32,
+
+    /// Each different set of result messages the check had, and whether it
+    /// passed with them.
+    pub outputs: Vec<FlakyOutput>,
+}
+
+/// One of the different outputs a flaky check had.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlakyOutput {
+    pub passed: bool,
+    pub results: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CheckOutput {
     pub passed: bool,
     pub message: String,
+
+    /// The type of the check, such as `cmd` or `http`.
+    #[serde(rename = "type")]
+    pub check_type: &'static str,
+
+    /// The check’s name, if it was given one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    pub known_issue: Option<String>,
+
+    /// How many times the check was run before these results were
+    /// recorded, which is more than once if it was retried.
+    pub attempts: u32,
+
+    /// How long the check took to run, including any retries.
+    #[serde(rename = "runtime_ms", serialize_with = "serialize_millis")]
+    pub runtime: Duration,
+
+    /// Why the check was skipped rather than run, such as a program it
+    /// runs not being installed. A skipped check neither passes nor fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+
+    /// The names of the checks this check needed that didn’t pass, which
+    /// stopped it from being run. A check like this also counts as skipped.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<String>,
+
+    /// Whether the check was expected to fail.
+    #[serde(skip_serializing_if = "Expectation::is_pass")]
+    pub expected: Expectation,
+
+    /// Whether the check failing only counts as a warning.
+    #[serde(skip_serializing_if = "Severity::is_error")]
+    pub severity: Severity,
+
+    // These have to come last, as they get written as tables in TOML. The
+    // results come first, as they are a value rather than a table when a
+    // check got skipped or wasn’t run.
     pub results: Vec<ResultMessage>,
+
+    /// The check’s own setup and teardown commands that failed. A check
+    /// whose setup failed doesn’t get run.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hook_failures: Vec<HookFailure>,
+
+    pub budget: Option<BudgetUsage>,
+
+    /// The commands the check ran, or used the cached results of, for
+    /// result documents that show their output.
+    #[serde(skip)]
+    pub ran_commands: Vec<Arc<RanCommand>>,
 }
 
-#[derive(Debug, Serialize)]
+/// What happened to a check, once its results and how they were expected
+/// to turn out have been taken into account.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+
+    /// The check couldn’t be run properly, such as because a command it
+    /// runs couldn’t be run, or one of its setup commands failed, rather
+    /// than because it found something wrong.
+    Errored,
+
+    /// The check failed, but its failures only count as warnings.
+    Warning,
+
+    /// The check was expected to fail, and did.
+    ExpectedFailure,
+
+    /// The check was expected to fail, but passed.
+    UnexpectedPass,
+
+    /// The check was skipped rather than run.
+    Skipped,
+
+    /// The check was not run because checks it needed did not pass.
+    Blocked,
+}
+
+impl CheckStatus {
+
+    /// The status as it gets written in machine-readable output, such as
+    /// ‘expected-failure’.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Passed           => "passed",
+            Self::Failed           => "failed",
+            Self::Errored          => "errored",
+            Self::Warning          => "warning",
+            Self::ExpectedFailure  => "expected-failure",
+            Self::UnexpectedPass   => "unexpected-pass",
+            Self::Skipped          => "skipped",
+            Self::Blocked          => "blocked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "state", content = "message", rename_all = "lowercase")]
 pub enum ResultMessage {
     Passed(String),
@@ -29,12 +201,525 @@ pub struct Stats {
     pub pass_count: u32,
     pub fail_count: u32,
     pub err_count: u32,
+    pub over_budget_count: u32,
+
+    /// The number of failed checks that were annotated with a known issue.
+    pub known_failure_count: u32,
+
+    /// The number of passed checks that were annotated with a known issue,
+    /// and so no longer need the annotation.
+    pub resolved_issue_count: u32,
+
+    /// The number of checks that were skipped rather than run, which count
+    /// as neither passes nor failures.
+    pub skip_count: u32,
+
+    /// The number of checks that were not run because checks they needed
+    /// did not pass, which count separately from other skipped checks.
+    pub blocked_count: u32,
+
+    /// The number of setup and teardown commands that failed, whether they
+    /// belonged to a file or to a check.
+    pub hook_failure_count: u32,
+
+    /// The number of checks that were expected to fail, and did, which
+    /// count as neither passes nor failures.
+    pub expected_failure_count: u32,
+
+    /// The number of checks that were expected to fail, but passed, and so
+    /// are probably fixed.
+    pub unexpected_pass_count: u32,
+
+    /// The number of checks whose failures only count as warnings, which
+    /// failed. These don’t count as failures.
+    pub warning_count: u32,
+}
+
+/// Whether a check is expected to pass or to fail.
+#[derive(PartialEq, Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expectation {
+    #[default]
+    Pass,
+    Fail,
+}
+
+/// How much it matters when a check fails.
+#[derive(PartialEq, Debug, Copy, Clone, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+
+    /// The check failing fails the run.
+    #[default]
+    Error,
+
+    /// The check failing gets reported, but doesn’t fail the run.
+    Warn,
+}
+
+/// A setup or teardown command that failed, which gets reported separately
+/// from the results of the checks themselves.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookFailure {
+    pub stage: HookStage,
+
+    /// The check the command belonged to, or `None` if it belonged to the
+    /// file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check: Option<String>,
+
+    pub shell: String,
+    pub error: String,
+}
+
+/// Whether a hook gets run before or after the checks.
+#[derive(PartialEq, Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookStage {
+    Setup,
+    Teardown,
+}
+
+/// How long a check took to run, compared to how long it was expected to take.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct BudgetUsage {
+    pub budget_ms: u64,
+    pub taken_ms: u64,
+    pub over_budget: bool,
+}
+
+/// The details of how a check was run that get printed alongside its
+/// results, but which don’t come from the check itself.
+#[derive(Debug, Copy, Clone)]
+pub struct RunDetails<'a> {
+    pub budget: Option<BudgetUsage>,
+    pub known_issue: Option<&'a str>,
+    pub attempts: u32,
+    pub expected: Expectation,
+    pub severity: Severity,
+
+    /// How long the check took to run, including any retries.
+    pub runtime: Duration,
+
+    /// The commands the check ran, or used the cached results of.
+    pub commands: &'a [Arc<RanCommand>],
+}
+
+
+impl Stats {
+
+    /// Adds one check’s output to these totals.
+    pub fn count_check(&mut self, check_output: &CheckOutput) {
+        self.check_count += 1;
+        self.hook_failure_count += count(check_output.hook_failures.len());
+
+        let status = check_output.status();
+        match status {
+            CheckStatus::Passed           => self.pass_count += 1,
+            CheckStatus::Failed           => self.fail_count += 1,
+            CheckStatus::Errored          => self.err_count += 1,
+            CheckStatus::Warning          => self.warning_count += 1,
+            CheckStatus::ExpectedFailure  => self.expected_failure_count += 1,
+            CheckStatus::Skipped          => self.skip_count += 1,
+            CheckStatus::Blocked          => self.blocked_count += 1,
+            CheckStatus::UnexpectedPass   => {
+                self.pass_count += 1;
+                self.unexpected_pass_count += 1;
+            }
+        }
+
+        // Only checks that failed when they should have passed can be
+        // known issues. Errors have got nothing to do with them.
+        if check_output.known_issue.is_some() {
+            if check_output.passed {
+                self.resolved_issue_count += 1;
+            }
+            else if status == CheckStatus::Failed {
+                self.known_failure_count += 1;
+            }
+        }
+
+        if check_output.budget.is_some_and(|b| b.over_budget) {
+            self.over_budget_count += 1;
+        }
+    }
 }
 
 
 impl ResultsSection {
+
+    /// Creates a new section from the outputs of each check, totalling up
+    /// how many of them passed and failed.
+    pub fn from_outputs(check_outputs: Vec<CheckOutput>) -> Self {
+        let mut totals = Stats::default();
+        for check_output in &check_outputs {
+            totals.count_check(check_output);
+        }
+
+        Self { check_outputs, hook_failures: Vec::new(), totals, correlations: None }
+    }
+
+    /// Adds the failures of the file’s own setup and teardown commands to
+    /// this section.
+    pub fn with_hook_failures(mut self, hook_failures: Vec<HookFailure>) -> Self {
+        self.totals.hook_failure_count += count(hook_failures.len());
+        self.hook_failures.extend(hook_failures);
+        self
+    }
+
+    /// Adds the properties that only the failed checks in this section had
+    /// in common, found by analysing them, to this section.
+    pub fn with_correlations<C>(mut self, table: &AnalysisTable<'_, C>) -> Self {
+        let correlations = table.resolve_correlations().into_iter().map(|correlation| CorrelationRecord {
+            property: correlation.property.kind(),
+            value: correlation.property.value(),
+            failures: count(correlation.count),
+        }).collect();
+
+        self.correlations = Some(correlations);
+        self
+    }
+
+    /// Records how each check in this section that got run turned out, so
+    /// the flaky ones can be found after several batches.
+    pub fn record_history(&self, input: &str, history: &mut CheckHistory) {
+        for check_output in &self.check_outputs {
+            if matches!(check_output.status(), CheckStatus::Skipped | CheckStatus::Blocked) {
+                continue;
+            }
+
+            let key = (input.to_owned(), check_output.message.clone());
+            let results = check_output.results.iter().map(ResultMessage::to_string).collect();
+            history.record(key, check_output.passed, results);
+        }
+    }
+
     pub fn failed(&self) -> bool {
-        self.totals.fail_count > 0 || self.totals.err_count > 0
+        self.totals.fail_count > 0 || self.totals.err_count > 0 || self.totals.hook_failure_count > 0
+    }
+
+    /// Whether any checks failed that were not annotated as known issues.
+    /// A failed setup or teardown command is never a known issue.
+    pub fn failed_unexpectedly(&self) -> bool {
+        self.totals.fail_count > self.totals.known_failure_count || self.totals.err_count > 0 || self.totals.hook_failure_count > 0
+    }
+
+    /// Whether this section should make the run fail, which leaves out
+    /// checks annotated as known issues when they are allowed.
+    pub fn fails_run(&self, allow_known_issues: bool) -> bool {
+        if allow_known_issues { self.failed_unexpectedly() }
+                         else { self.failed() }
+    }
+}
+
+impl CheckOutput {
+
+    /// Creates the output of a check that got skipped for the given reason
+    /// without being run.
+    pub fn skipped(message: String, reason: String, known_issue: Option<String>) -> Self {
+        Self {
+            passed: false,
+            message,
+            check_type: "",
+            name: None,
+            tags: Vec::new(),
+            results: Vec::new(),
+            budget: None,
+            known_issue,
+            attempts: 0,
+            skipped: Some(reason),
+            blocked_by: Vec::new(),
+            expected: Expectation::Pass,
+            severity: Severity::Error,
+            hook_failures: Vec::new(),
+            runtime: Duration::ZERO,
+            ran_commands: Vec::new(),
+        }
+    }
+
+    /// Creates the output of a check that got skipped because the checks
+    /// with the given names, which it needs, did not pass.
+    pub fn blocked(message: String, reason: String, blocked_by: Vec<String>, known_issue: Option<String>) -> Self {
+        Self {
+            blocked_by,
+            ..Self::skipped(message, reason, known_issue)
+        }
+    }
+
+    /// Creates the output of a check that failed without being run because
+    /// one of its setup commands failed.
+    pub fn setup_failed(message: String, failure: HookFailure) -> Self {
+        Self {
+            passed: false,
+            message,
+            check_type: "",
+            name: None,
+            tags: Vec::new(),
+            known_issue: None,
+            attempts: 0,
+            skipped: None,
+            blocked_by: Vec::new(),
+            expected: Expectation::Pass,
+            severity: Severity::Error,
+            hook_failures: vec![ failure ],
+            budget: None,
+            results: Vec::new(),
+            runtime: Duration::ZERO,
+            ran_commands: Vec::new(),
+        }
+    }
+
+    /// Works out what happened to the check.
+    pub fn status(&self) -> CheckStatus {
+        if ! self.blocked_by.is_empty() {
+            CheckStatus::Blocked
+        }
+        else if self.skipped.is_some() {
+            CheckStatus::Skipped
+        }
+        else if self.errored() {
+            CheckStatus::Errored
+        }
+        else if self.passed && self.expected == Expectation::Fail {
+            CheckStatus::UnexpectedPass
+        }
+        else if self.passed {
+            CheckStatus::Passed
+        }
+        else if self.expected == Expectation::Fail {
+            CheckStatus::ExpectedFailure
+        }
+        else if self.severity == Severity::Warn {
+            CheckStatus::Warning
+        }
+        else {
+            CheckStatus::Failed
+        }
+    }
+
+    /// Whether the check didn’t pass because of errors alone, without any
+    /// of its results having failed.
+    fn errored(&self) -> bool {
+        if self.passed || self.results.iter().any(|result| matches!(result, ResultMessage::Failed(_))) {
+            return false;
+        }
+
+        self.results.iter().any(|result| matches!(result, ResultMessage::Error(_))) || ! self.hook_failures.is_empty()
+    }
+}
+
+impl CheckOutput {
+
+    /// Creates the output of a check that failed without being run because
+    /// the environment it gets run in has died, such as a side process
+    /// having exited. This counts as a failure even for checks with known
+    /// issues, as it’s got nothing to do with them.
+    pub fn environment_error(message: String, error: &str) -> Self {
+        Self {
+            passed: false,
+            message,
+            check_type: "",
+            name: None,
+            tags: Vec::new(),
+            known_issue: None,
+            attempts: 0,
+            skipped: None,
+            blocked_by: Vec::new(),
+            expected: Expectation::Pass,
+            severity: Severity::Error,
+            hook_failures: Vec::new(),
+            budget: None,
+            results: vec![ ResultMessage::Error(format!("environment died: {}", error)) ],
+            runtime: Duration::ZERO,
+            ran_commands: Vec::new(),
+        }
+    }
+}
+
+impl BudgetUsage {
+
+    /// Compares the time a check took against its budget.
+    pub fn new(budget: Duration, taken: Duration) -> Self {
+        Self {
+            budget_ms: millis(budget),
+            taken_ms: millis(taken),
+            over_budget: taken > budget,
+        }
+    }
+}
+
+// serde passes the values to skip by reference.
+
+impl Expectation {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_pass(&self) -> bool {
+        *self == Self::Pass
+    }
+}
+
+impl Severity {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_error(&self) -> bool {
+        *self == Self::Error
+    }
+}
+
+impl FlakyRecord {
+
+    /// Finds the checks whose results flipped in a history, the flakiest
+    /// first.
+    pub fn from_history(history: &CheckHistory) -> Vec<Self> {
+        history.flaky_checks().into_iter().map(|flaky| {
+            let (input, check) = flaky.key;
+            let outputs = flaky.outputs.iter().map(|(passed, results)| FlakyOutput { passed: *passed, results: results.clone() }).collect();
+            Self { input: input.clone(), check: check.clone(), runs: count(flaky.runs), passes: count(flaky.passes), flips: count(flaky.flips), outputs }
+        }).collect()
     }
 }
 
+impl fmt::Display for ResultMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passed(message)  => write!(f, "pass: {}", message),
+            Self::Failed(message)  => write!(f, "fail: {}", message),
+            Self::Error(message)   => write!(f, "error: {}", message),
+        }
+    }
+}
+
+impl fmt::Display for FlakyRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let times = if self.flips == 1 { "time" } else { "times" };
+        write!(f, "{} flipped {} {} in {} runs, passing {}", self.check, self.flips, times, self.runs, self.passes)
+    }
+}
+
+impl fmt::Display for CorrelationRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checks = if self.failures == 1 { "check" } else { "checks" };
+        write!(f, "{} failed {} involving {} ‘{}’, with none passing", self.failures, checks, self.property, self.value)
+    }
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Setup     => write!(f, "setup"),
+            Self::Teardown  => write!(f, "teardown"),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(millis(*duration))
+}
+
+fn count(length: usize) -> u32 {
+    length.try_into().unwrap_or(u32::MAX)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn output(results: Vec<ResultMessage>) -> CheckOutput {
+        CheckOutput {
+            passed: results.iter().all(|r| matches!(r, ResultMessage::Passed(_))),
+            skipped: None,
+            results,
+            ..CheckOutput::skipped("thing works".into(), String::new(), None)
+        }
+    }
+
+    fn passed() -> CheckOutput {
+        output(vec![ ResultMessage::Passed("it ran".into()) ])
+    }
+
+    fn failed() -> CheckOutput {
+        output(vec![ ResultMessage::Failed("status was 1".into()) ])
+    }
+
+    fn errored() -> CheckOutput {
+        output(vec![ ResultMessage::Error("command not found".into()) ])
+    }
+
+    fn known_issue(check_output: CheckOutput) -> CheckOutput {
+        CheckOutput { known_issue: Some("#12".into()), ..check_output }
+    }
+
+    #[test]
+    fn known_failures() {
+        let section = ResultsSection::from_outputs(vec![ known_issue(failed()), known_issue(failed()), passed() ]);
+        assert_eq!(section.totals.fail_count, 2);
+        assert_eq!(section.totals.known_failure_count, 2);
+        assert_eq!(section.totals.resolved_issue_count, 0);
+        assert!(section.failed());
+        assert!(! section.failed_unexpectedly());
+        assert!(section.fails_run(false));
+        assert!(! section.fails_run(true));
+    }
+
+    #[test]
+    fn known_and_unknown_failures() {
+        let section = ResultsSection::from_outputs(vec![ known_issue(failed()), failed() ]);
+        assert_eq!(section.totals.known_failure_count, 1);
+        assert!(section.failed_unexpectedly());
+        assert!(section.fails_run(true));
+    }
+
+    #[test]
+    fn resolved_issues() {
+        let section = ResultsSection::from_outputs(vec![ known_issue(passed()) ]);
+        assert_eq!(section.totals.pass_count, 1);
+        assert_eq!(section.totals.known_failure_count, 0);
+        assert_eq!(section.totals.resolved_issue_count, 1);
+        assert!(! section.failed());
+    }
+
+    #[test]
+    fn errors_are_not_known_issues() {
+        let section = ResultsSection::from_outputs(vec![ known_issue(errored()) ]);
+        assert_eq!(section.totals.err_count, 1);
+        assert_eq!(section.totals.known_failure_count, 0);
+        assert!(section.fails_run(true));
+    }
+
+    #[test]
+    fn expected_failures() {
+        let expect_fail = |check_output| CheckOutput { expected: Expectation::Fail, ..check_output };
+        let section = ResultsSection::from_outputs(vec![ expect_fail(failed()), expect_fail(passed()) ]);
+        assert_eq!(section.check_outputs[0].status(), CheckStatus::ExpectedFailure);
+        assert_eq!(section.check_outputs[1].status(), CheckStatus::UnexpectedPass);
+        assert_eq!(section.totals.expected_failure_count, 1);
+        assert_eq!(section.totals.unexpected_pass_count, 1);
+        assert_eq!(section.totals.pass_count, 1);
+        assert_eq!(section.totals.fail_count, 0);
+        assert!(! section.failed());
+    }
+
+    #[test]
+    fn warnings() {
+        let warn = |check_output| CheckOutput { severity: Severity::Warn, ..check_output };
+        let section = ResultsSection::from_outputs(vec![ warn(failed()), warn(passed()) ]);
+        assert_eq!(section.check_outputs[0].status(), CheckStatus::Warning);
+        assert_eq!(section.check_outputs[1].status(), CheckStatus::Passed);
+        assert_eq!(section.totals.warning_count, 1);
+        assert_eq!(section.totals.pass_count, 1);
+        assert_eq!(section.totals.fail_count, 0);
+        assert!(! section.failed());
+    }
+
+    #[test]
+    fn warnings_still_error() {
+        let section = ResultsSection::from_outputs(vec![ CheckOutput { severity: Severity::Warn, ..errored() } ]);
+        assert_eq!(section.totals.err_count, 1);
+        assert_eq!(section.totals.warning_count, 0);
+        assert!(section.failed());
+    }
+}
@@ -0,0 +1,206 @@
+//! Serving the results of continual mode over HTTP, so specsheet can be
+//! used as a lightweight blackbox monitor that a load balancer or
+//! Prometheus can poll.
+//!
+//! The server is tiny: it only answers `GET` and `HEAD` requests for a few
+//! paths, each on a thread of its own, and always serves the results of
+//! the most recent batch.
+//!
+//! The results only get locked for long enough to take a reference to
+//! them, so a client that’s slow to read a response can’t hold up the
+//! batch that’s about to replace them.
+//!
+//! - `/healthz` responds with 200 if every check passed, and 503 if not;
+//! - `/results.json` responds with the JSON result document;
+//! - `/metrics` responds with the Prometheus metrics.
+//!
+//! Until the first batch has finished, every path responds with 503.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use log::*;
+
+use crate::doc::{self, CompletedRun};
+
+
+/// How long to wait for a client to send its request, or to read its
+/// response, before giving up on it, so slow clients don’t pile up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// The **status server** serves the results of the latest batch of checks.
+#[derive(Debug)]
+pub struct StatusServer {
+    latest: Arc<Mutex<Option<Arc<Snapshot>>>>,
+}
+
+/// The responses for a finished batch, rendered once when the batch
+/// finishes rather than for every request.
+#[derive(Debug)]
+struct Snapshot {
+    passing: bool,
+    results_json: String,
+    metrics: String,
+}
+
+/// A response to send back to a client.
+struct Response<'a> {
+    status: &'static str,
+    content_type: &'static str,
+    body: &'a str,
+}
+
+impl StatusServer {
+
+    /// Starts listening on the given address, such as `127.0.0.1:9090`,
+    /// and answers requests on a background thread from then on.
+    pub fn start(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        info!("Serving status on {}", listener.local_addr()?);
+
+        let latest = Arc::new(Mutex::new(None));
+        let server_latest = Arc::clone(&latest);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Error accepting status request: {}", e);
+                        continue;
+                    }
+                };
+
+                let latest = Arc::clone(&server_latest);
+                thread::spawn(move || {
+                    if let Err(e) = respond(stream, &latest) {
+                        warn!("Error answering status request: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { latest })
+    }
+
+    /// Replaces the results being served with the ones from a batch that
+    /// has just finished, given whether it passed.
+    pub fn update(&self, run: &CompletedRun<'_>, passing: bool) {
+        let snapshot = Snapshot {
+            passing,
+            results_json: doc::json_document(run),
+            metrics: doc::prometheus_metrics(run),
+        };
+
+        *self.latest.lock().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(snapshot));
+    }
+}
+
+
+/// Reads a request from the stream, and writes the response to it.
+fn respond(mut stream: TcpStream, latest: &Mutex<Option<Arc<Snapshot>>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // The headers don’t matter, but they have to be read before responding.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    debug!("Status request -> {} {}", method, path);
+
+    // The lock gets let go of straight away, rather than being held while
+    // the response gets written.
+    let latest = latest.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    let response = route(method, path, latest.as_deref());
+    let head_only = method == "HEAD";
+
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+           response.status, response.content_type, response.body.len())?;
+
+    if ! head_only {
+        stream.write_all(response.body.as_bytes())?;
+    }
+
+    stream.flush()
+}
+
+/// Picks the response to a request for a path, given the results of the
+/// latest batch, if one has finished.
+fn route<'a>(method: &str, path: &str, latest: Option<&'a Snapshot>) -> Response<'a> {
+    if method != "GET" && method != "HEAD" {
+        return Response::text("405 Method Not Allowed", "method not allowed\n");
+    }
+
+    // Query strings get ignored, so cache-busting parameters still work.
+    let path = path.split('?').next().unwrap_or(path);
+
+    match (path, latest) {
+        ("/healthz" | "/results.json" | "/metrics", None)  => Response::text("503 Service Unavailable", "no results yet\n"),
+        ("/healthz", Some(snapshot)) if snapshot.passing   => Response::text("200 OK", "ok\n"),
+        ("/healthz", Some(_))                              => Response::text("503 Service Unavailable", "failing\n"),
+        ("/results.json", Some(snapshot))                  => Response { status: "200 OK", content_type: "application/json", body: &snapshot.results_json },
+        ("/metrics", Some(snapshot))                       => Response { status: "200 OK", content_type: "text/plain; version=0.0.4", body: &snapshot.metrics },
+        _                                                  => Response::text("404 Not Found", "not found\n"),
+    }
+}
+
+impl Response<'_> {
+    fn text(status: &'static str, body: &'static str) -> Self {
+        Self { status, content_type: "text/plain", body }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot(passing: bool) -> Snapshot {
+        Snapshot { passing, results_json: "{}".into(), metrics: "specsheet_checks 1\n".into() }
+    }
+
+    #[test]
+    fn healthy() {
+        assert_eq!(route("GET", "/healthz", Some(&snapshot(true))).status, "200 OK");
+        assert_eq!(route("HEAD", "/healthz?probe=1", Some(&snapshot(true))).status, "200 OK");
+    }
+
+    #[test]
+    fn unhealthy() {
+        assert_eq!(route("GET", "/healthz", Some(&snapshot(false))).status, "503 Service Unavailable");
+    }
+
+    #[test]
+    fn no_results_yet() {
+        assert_eq!(route("GET", "/metrics", None).status, "503 Service Unavailable");
+        assert_eq!(route("GET", "/healthz", None).status, "503 Service Unavailable");
+    }
+
+    #[test]
+    fn results() {
+        let snapshot = snapshot(false);
+        let response = route("GET", "/results.json", Some(&snapshot));
+        assert_eq!((response.status, response.content_type, response.body), ("200 OK", "application/json", "{}"));
+
+        let response = route("GET", "/metrics", Some(&snapshot));
+        assert_eq!(response.body, "specsheet_checks 1\n");
+    }
+
+    #[test]
+    fn not_found() {
+        assert_eq!(route("GET", "/", Some(&snapshot(true))).status, "404 Not Found");
+        assert_eq!(route("POST", "/healthz", Some(&snapshot(true))).status, "405 Method Not Allowed");
+    }
+}
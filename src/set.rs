@@ -1,33 +1,138 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use std::thread::sleep;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
 
 use derive_more::{From, Display};
 use log::*;
 
 use spec_analysis::AnalysisTable;
 use spec_checks::*;
-use spec_checks::load::{CheckDocument, CheckEntry, Tags};
-use spec_checks::read::Rewrites;
+use spec_checks::common::parse_duration;
+use spec_checks::load::{CheckDocument, CheckEntry, Hook, Location, Position, Tags, TomlValue, Variables};
+use spec_checks::read::{closest_name, Rewrites};
 use spec_exec::Executor;
 
-use crate::commands::Commands;
-use crate::filter::{Filter, RunningOrder};
-use crate::options::Delay;
-use crate::output::Output;
-use crate::results::{ResultsSection, ResultMessage, CheckOutput, Stats};
+use crate::commands::{Commands, Retry};
+use crate::explain::Explanation;
+use crate::filter::{Filter, RunningOrder, Shard};
+use crate::hooks::Hooks;
+use crate::options::{Delay, Threads};
+use crate::output::Outputs;
+use crate::preflight::{Preflight, skip_reason, unmet_condition};
+use crate::results::{ResultsSection, ResultMessage, CheckOutput, BudgetUsage, RunDetails, HookFailure, Expectation, Severity};
+
+
+/// The types of check that can be read from an input document, in
+/// alphabetical order. Any type added to `read_toml` has to be added here
+/// too, for the shell completions to know about it.
+pub const CHECK_TYPES: &[&str] = &[
+    <apt::AptCheck as Check>::TYPE,
+    <apt_source::AptSourceCheck as Check>::TYPE,
+    <cmd::CommandCheck as Check>::TYPE,
+    <defaults::DefaultsCheck as Check>::TYPE,
+    <dns::DnsCheck as Check>::TYPE,
+    <fs::FilesystemCheck as Check>::TYPE,
+    <gem::GemCheck as Check>::TYPE,
+    <group::GroupCheck as Check>::TYPE,
+    <hashes::HashCheck as Check>::TYPE,
+    <homebrew::HomebrewCheck as Check>::TYPE,
+    <homebrew_cask::HomebrewCaskCheck as Check>::TYPE,
+    <homebrew_tap::HomebrewTapCheck as Check>::TYPE,
+    <http::HttpCheck as Check>::TYPE,
+    <npm::NpmCheck as Check>::TYPE,
+    <ping::PingCheck as Check>::TYPE,
+    <systemd::SystemdCheck as Check>::TYPE,
+    <tap::TapCheck as Check>::TYPE,
+    <tcp::TcpCheck as Check>::TYPE,
+    <udp::UdpCheck as Check>::TYPE,
+    <ufw::UfwCheck as Check>::TYPE,
+    <user::UserCheck as Check>::TYPE,
+];
 
 
 /// A **check set** is read from each input file.
 #[derive(Debug, Default)]
 pub struct CheckSet {
     checks: Vec<ReadyCheck>,
+
+    /// The commands to run before and after all the checks in the file.
+    hooks: Hooks,
 }
 
 #[derive(Debug)]
 struct ReadyCheck {
     class: LoadedCheck,
     name: Option<String>,
+    budget: Option<Duration>,
+    known_issue: Option<String>,
+    retries: Option<u32>,
+    retry_delay: Option<Duration>,
+    expected: Expectation,
+    severity: Severity,
+
+    /// How long to wait after running this check before running it again
+    /// in continual mode. A check without one gets run in every batch.
+    interval: Option<Duration>,
+
+    /// When this check and its secondary checks last got run in continual
+    /// mode, and their outputs, which get reported again in the batches
+    /// before it’s due to be run again.
+    last_run: Mutex<Option<(Instant, Vec<CheckOutput>)>>,
+
+    /// Checks implied by this check’s parameters, such as the ufw rule for
+    /// a `tcp` check with a `ufw` sub-table, which get run straight after
+    /// it and have their results reported separately.
+    secondaries: Vec<ReadyCheck>,
+
+    /// The programs this check runs that could not be found when its
+    /// commands were primed. A check with missing programs gets skipped.
+    missing_programs: Vec<String>,
+
+    /// Why this check shouldn’t be run on this machine, if its `only_if`
+    /// conditions aren’t met. A check like this gets skipped.
+    excluded: Option<String>,
+
+    /// The commands to run before and after this check.
+    hooks: Hooks,
+
+    /// The names of the checks that have to pass for this check to be run.
+    /// Every check with one of these names gets run before this one.
+    needs: Vec<String>,
+
+    /// The check’s tags, which are only kept around to be listed.
+    tags: Vec<String>,
+
+    /// Where the check was written, which is also only kept to be listed.
+    location: Location,
+
+    /// The check’s parameters from its document, with any rewrite rules
+    /// applied, which are only kept to be explained.
+    parameters: TomlValue,
+}
+
+/// A check that would be run, as listed in list-checks mode.
+#[derive(Debug)]
+pub struct ListedCheck {
+
+    /// The name of the check’s type, such as `cmd`.
+    pub check_type: &'static str,
+
+    /// The name given to the check in its document, if it has one.
+    pub name: Option<String>,
+
+    /// The check’s tags.
+    pub tags: Vec<String>,
+
+    /// The description of what the check checks.
+    pub description: String,
+
+    /// Where the check was written.
+    pub location: Location,
 }
 
 #[derive(Debug, Display, From)]
@@ -46,6 +151,7 @@ pub enum LoadedCheck {
 
     // remote
     Apt(apt::AptCheck),
+    AptSource(apt_source::AptSourceCheck),
     Defaults(defaults::DefaultsCheck),
     Fs(fs::FilesystemCheck),
     Gem(gem::GemCheck),
@@ -70,21 +176,72 @@ impl CheckSet {
 
     /// Read a file full of checks into this check set, using the filter to
     /// determine which checks to include.
-    pub fn read_toml(&mut self, filter: &Filter, rewrites: &Rewrites, check_document: CheckDocument) -> Result<(), Vec<ReadError>> {
+    pub fn read_toml(&mut self, filter: &Filter, rewrites: &Rewrites, overrides: &Variables, check_document: CheckDocument) -> Result<(), Vec<ReadError>> {
 
         // Work out the parent directory, because certain checks need to
         // access files relative to the file the check was in.
         //let base_directory = path.canonicalize().expect("canonicalize");
         //let base_directory = base_directory.parent().expect("parent");
 
+        let CheckDocument { include: _, mut vars, setup, teardown, checks } = check_document;
+        for (name, value) in overrides {
+            let value = override_value(vars.get(name), value);
+            vars.insert(name.clone(), value);
+        }
+
+        // Variables get replaced before anything else happens, so that
+        // every check type can use them, and so can the names that checks
+        // need each other by.
         let mut errors = Vec::new();
+        let mut interpolate_hook = |table: &str, hook: Option<Hook>| {
+            let hook = hook?;
+            hook.interpolated(&vars).map_err(|variable| {
+                let error = ReadError {
+                    name: table.to_owned().into(),
+                    inner: Box::new(UnresolvedVariable(variable)),
+                    file: None,
+                    position: None,
+                };
+
+                errors.push(error);
+            }).ok()
+        };
+
+        let setup = interpolate_hook("setup", setup.map(|t| t.shell));
+        let teardown = interpolate_hook("teardown", teardown.map(|t| t.shell));
+        self.hooks = Hooks::new(setup, teardown);
+
+        let check_document = checks.into_iter().map(|(check_key, entries)| {
+            let entries = entries.iter().filter_map(|entry| {
+                entry.interpolated(&vars).map_err(|variable| {
+                    let error = ReadError::in_entry(check_key.clone(), Box::new(UnresolvedVariable(variable)), &entry.location, None);
+
+                    errors.push(error);
+                }).ok()
+            }).collect::<Vec<_>>();
+
+            (check_key, entries)
+        }).collect::<BTreeMap<_, _>>();
+
+        // Checks can need checks that get filtered out, which just don’t
+        // pass, but not ones that don’t exist at all.
+        let all_names = check_document.values().flatten()
+                                      .filter_map(|e| e.name.clone())
+                                      .collect::<BTreeSet<_>>();
+
+        // Whether each check read from this document was picked by the
+        // name filters, which only get applied once every check has been
+        // read, so the checks they need can be picked too.
+        let first_check = self.checks.len();
+        let mut picked = Vec::new();
+
         for (check_key, checks) in check_document {
             if ! filter.types.should_include_type(&check_key) {
                 debug!("Skipping check type {}", check_key);
                 continue;
             }
 
-            for CheckEntry { inner, name, tags } in checks {
+            for CheckEntry { inner, name, tags, budget, known_issue, retries, retry_delay, interval, setup, teardown, needs, only_if, expected, severity, matrix: _, location } in checks {
                 let nothing: &[String] = &[];
                 let tag_ok = match &tags {
                     Some(Tags::One(tag))    => filter.tags.should_include_tags(&[ tag ]),
@@ -97,6 +254,72 @@ impl CheckSet {
                     continue;
                 }
 
+                let tags = tags.map(Tags::into_vec).unwrap_or_default();
+
+                let budget = match budget.as_deref().map(parse_duration) {
+                    None            => None,
+                    Some(Some(d))   => Some(d),
+                    Some(None)      => {
+                        let error = ReadError::in_entry(check_key.clone(), Box::new(InvalidBudget(budget.unwrap())), &location, Some("budget"));
+
+                        errors.push(error);
+                        continue;
+                    }
+                };
+
+                let expected = match expected.as_deref() {
+                    None | Some("pass")  => Expectation::Pass,
+                    Some("fail")         => Expectation::Fail,
+                    Some(_)              => {
+                        let error = ReadError::in_entry(check_key.clone(), Box::new(InvalidExpectation(expected.unwrap())), &location, Some("expected"));
+
+                        errors.push(error);
+                        continue;
+                    }
+                };
+
+                let severity = match severity.as_deref() {
+                    None | Some("error")  => Severity::Error,
+                    Some("warn")          => Severity::Warn,
+                    Some(_)               => {
+                        let error = ReadError::in_entry(check_key.clone(), Box::new(InvalidSeverity(severity.unwrap())), &location, Some("severity"));
+
+                        errors.push(error);
+                        continue;
+                    }
+                };
+
+                let excluded = only_if.as_ref().and_then(unmet_condition);
+                let needs = needs.unwrap_or_default();
+                if let Some(unknown) = needs.iter().find(|n| ! all_names.contains(*n)) {
+                    let error = ReadError::in_entry(check_key.clone(), Box::new(UnknownDependency(unknown.clone())), &location, Some("needs"));
+
+                    errors.push(error);
+                    continue;
+                }
+
+                let retry_delay = match retry_delay.as_deref().map(parse_duration) {
+                    None            => None,
+                    Some(Some(d))   => Some(d),
+                    Some(None)      => {
+                        let error = ReadError::in_entry(check_key.clone(), Box::new(InvalidRetryDelay(retry_delay.unwrap())), &location, Some("retry_delay"));
+
+                        errors.push(error);
+                        continue;
+                    }
+                };
+
+                let interval = match interval.as_deref().map(parse_duration) {
+                    None            => None,
+                    Some(Some(d))   => Some(d),
+                    Some(None)      => {
+                        let error = ReadError::in_entry(check_key.clone(), Box::new(InvalidInterval(interval.unwrap())), &location, Some("interval"));
+
+                        errors.push(error);
+                        continue;
+                    }
+                };
+
                 macro_rules! read_check_type {
                     ($type:path $(, $read_args:tt )*) => {
                         let type_str = <$type as Check>::TYPE;
@@ -105,17 +328,40 @@ impl CheckSet {
 
                             match <$type>::read(&inner, $( $read_args )*) {
                                 Ok(check) => {
+                                    let class = LoadedCheck::from(check);
+                                    let description = class.to_string();
+                                    picked.push(filter.names.should_include_check(name.as_deref(), &description)
+                                                && filter.rerun.should_include_check(&description));
+
+                                    let secondaries = class.secondary_checks().into_iter().map(|secondary| {
+                                        ReadyCheck { class: secondary, name: name.clone(), budget: None, known_issue: known_issue.clone(), retries, retry_delay, interval, last_run: Mutex::default(), expected, severity, secondaries: Vec::new(), missing_programs: Vec::new(), excluded: excluded.clone(), hooks: Hooks::default(), needs: needs.clone(), tags: tags.clone(), location: location.clone(), parameters: TomlValue::Table(toml::map::Map::new()) }
+                                    }).collect();
+
                                     self.checks.push(ReadyCheck {
-                                        class: LoadedCheck::from(check),
+                                        class,
                                         name,
+                                        budget,
+                                        known_issue,
+                                        retries,
+                                        retry_delay,
+                                        interval,
+                                        last_run: Mutex::default(),
+                                        expected,
+                                        severity,
+                                        secondaries,
+                                        missing_programs: Vec::new(),
+                                        excluded,
+                                        hooks: Hooks::new(setup, teardown),
+                                        needs,
+                                        tags,
+                                        location,
+                                        parameters: rewrites.parameters(type_str, &inner),
                                     });
                                 }
                                 Err(e) => {
                                     warn!("Failed to read: {:?}", e);
-                                    let error = ReadError {
-                                        name: type_str.into(),
-                                        inner: Box::new(e),
-                                    };
+                                    let parameter_name = e.parameter_name().to_owned();
+                                    let error = ReadError::in_entry(type_str, Box::new(e), &location, Some(&parameter_name));
 
                                     errors.push(error);
                                 }
@@ -139,31 +385,47 @@ impl CheckSet {
 
                 // local
                 read_check_type!(apt::AptCheck);
+                read_check_type!(apt_source::AptSourceCheck);
                 read_check_type!(defaults::DefaultsCheck, rewrites);
                 read_check_type!(fs::FilesystemCheck, rewrites);
-                read_check_type!(gem::GemCheck);
+                read_check_type!(gem::GemCheck, rewrites);
                 read_check_type!(group::GroupCheck);
                 read_check_type!(hashes::HashCheck, rewrites);
                 read_check_type!(homebrew_cask::HomebrewCaskCheck);
                 read_check_type!(homebrew::HomebrewCheck);
                 read_check_type!(homebrew_tap::HomebrewTapCheck);
-                read_check_type!(npm::NpmCheck);
+                read_check_type!(npm::NpmCheck, rewrites);
                 read_check_type!(systemd::SystemdCheck);
                 read_check_type!(ufw::UfwCheck);
                 read_check_type!(user::UserCheck, rewrites);
 
-                let error = ReadError {
-                    name: check_key.clone().into(),
-                    inner: Box::new(UnknownCheckType(check_key.clone())),
-                };
+                let error = ReadError::in_entry(check_key.clone(), Box::new(UnknownCheckType(check_key.clone(), closest_name(&check_key, CHECK_TYPES))), &location, None);
 
                 errors.push(error);
             }
+        }
 
-            if filter.order == RunningOrder::Random {
-                trace!("Shuffling order of checks");
-                rand::seq::SliceRandom::shuffle(self.checks.as_mut_slice(), &mut rand::thread_rng());
-            }
+        self.keep_picked_checks(first_check, picked, filter.shard);
+
+        if filter.order == RunningOrder::Random {
+            trace!("Shuffling order of checks");
+            rand::seq::SliceRandom::shuffle(self.checks.as_mut_slice(), &mut rand::thread_rng());
+        }
+
+        // Checks that need each other can never be run, so they get left
+        // out, like checks that could not be read.
+        while let Some(cycle) = self.find_cycle() {
+            warn!("Checks need each other -> {:?}", cycle);
+            self.checks.retain(|c| ! c.name.as_ref().is_some_and(|n| cycle.contains(n)));
+
+            let error = ReadError {
+                name: "needs".into(),
+                inner: Box::new(DependencyCycle(cycle)),
+                file: None,
+                position: None,
+            };
+
+            errors.push(error);
         }
 
         if errors.is_empty() {
@@ -181,31 +443,18 @@ impl CheckSet {
     /// the command prime the Exec. For those with multiple invocations (such
     /// as `dns`), this will have the command prime all the necessary Execs.
     /// Checks with no commands (such as `fs`) have nothing done to them.
-    pub fn prime_commands(&self, commands: &mut Commands) {
-        for c in &self.checks {
-            match &c.class {
-                LoadedCheck::Cmd(c)           => c.load(&mut commands.shell),
-                LoadedCheck::Tap(c)           => c.load(&mut commands.shell),
-
-                LoadedCheck::Dns(c)           => c.load(&mut commands.dig),
-                LoadedCheck::Http(c)          => c.load(&mut commands.curl),
-                LoadedCheck::Ping(c)          => c.load(&mut commands.ping),
-                LoadedCheck::Tcp(c)           => c.load(&mut commands.net),
-                LoadedCheck::Udp(c)           => c.load(&mut commands.net),
-
-                LoadedCheck::Apt(c)           => c.load(&mut commands.apt),
-                LoadedCheck::Defaults(c)      => c.load(&mut commands.defaults),
-                LoadedCheck::Fs(c)            => c.load(&mut commands.files),
-                LoadedCheck::Gem(c)           => c.load(&mut commands.gem),
-                LoadedCheck::Group(c)         => c.load(&mut commands.passwd),
-                LoadedCheck::Hash(c)          => c.load(&mut commands.hash),
-                LoadedCheck::Homebrew(c)      => c.load(&mut commands.brew),
-                LoadedCheck::HomebrewCask(c)  => c.load(&mut commands.brew_cask),
-                LoadedCheck::HomebrewTap(c)   => c.load(&mut commands.brew_tap),
-                LoadedCheck::Npm(c)           => c.load(&mut commands.npm),
-                LoadedCheck::Systemd(c)       => c.load(&mut commands.systemctl),
-                LoadedCheck::Ufw(c)           => c.load(&mut commands.ufw),
-                LoadedCheck::User(c)          => c.load(&mut commands.passwd),
+    ///
+    /// If the commands are set to check programs, each check first has the
+    /// programs it would run looked for, and a check with any missing does
+    /// not get primed, so it can be skipped when the checks get run.
+    pub fn prime_commands(&mut self, commands: &mut Commands) {
+        let mut preflight = Preflight::default();
+
+        for check in &mut self.checks {
+            check.prime(commands, &mut preflight);
+
+            for secondary in &mut check.secondaries {
+                secondary.prime(commands, &mut preflight);
             }
         }
     }
@@ -213,11 +462,46 @@ impl CheckSet {
     /// Runs all the checks in this set in type order, running external
     /// programs using the `Executor` from commands in the `Commands` set, and
     /// printing results out to the `TerminalUI`.
-    pub fn run_all<'set>(&'set self, executor: &mut Executor, commands: &mut Commands, ui: &mut Output, delay: Delay, table: Option<&mut AnalysisTable<'set, LoadedCheck>>) -> ResultsSection {
+    ///
+    /// The file’s setup commands get run first, and if one of them fails,
+    /// none of the checks get run. Its teardown commands get run last,
+    /// whatever happened.
+    pub fn run_all<'set>(&'set self, executor: &mut Executor, commands: &mut Commands, ui: &mut Outputs, delay: Delay, threads: Threads, table: Option<&mut AnalysisTable<'set, LoadedCheck>>) -> ResultsSection {
+        let setup_failure = self.run_setup(executor, ui);
+
+        let section = match setup_failure {
+            None     => self.run_checks(executor, commands, ui, delay, threads, table),
+            Some(_)  => ResultsSection::from_outputs(Vec::new()),
+        };
+
+        section.with_hook_failures(self.run_teardown(executor, ui, setup_failure))
+    }
+
+    /// Runs the checks themselves, without the file’s hooks.
+    ///
+    /// Checks get run on several threads at once if more than one thread is
+    /// requested, unless there’s a delay between them. When there’s no
+    /// delay, the commands of checks that are ready get run ahead of them
+    /// on the async executor, if there is one.
+    fn run_checks<'set>(&'set self, executor: &mut Executor, commands: &mut Commands, ui: &mut Outputs, delay: Delay, threads: Threads, table: Option<&mut AnalysisTable<'set, LoadedCheck>>) -> ResultsSection {
+        let running_order = self.running_order();
+
+        #[cfg(feature = "async")]
+        if delay == Delay::RunInstantly {
+            prefetch_commands(&running_order, executor, commands);
+        }
+
+        if let (Threads::Many(thread_count), Delay::RunInstantly) = (threads, delay) {
+            let ready_checks = running_order.into_iter().flat_map(ReadyCheck::with_secondaries).collect::<Vec<_>>();
+            let outcomes = Outcomes::new(&self.checks);
+            return ResultsSection::from_outputs(run_all_parallel(&ready_checks, &outcomes, executor, commands, ui, thread_count, table));
+        }
+
+        let outcomes = Outcomes::new(&self.checks);
         let mut check_outputs = Vec::new();
         let mut first = true;
 
-        for ready_check in &self.checks {
+        for ready_check in running_order {
             if let Delay::Wait(duration) = delay {
                 if first {
                     sleep(duration);
@@ -227,46 +511,260 @@ impl CheckSet {
                 }
             }
 
-            let check_output = run_base_check(&ready_check, executor, commands, ui);
+            if spec_exec::is_interrupted() {
+                break;
+            }
 
-            if let Some(&mut ref mut table) = table {
-                let properties = match ready_check.class {
-                    LoadedCheck::Fs(ref c)     => c.properties(),
-                    LoadedCheck::User(ref c)   => c.properties(),
-                    LoadedCheck::Group(ref c)  => c.properties(),
-                    _                          => Vec::new(),
-                };
+            for ready_check in ready_check.with_secondaries() {
+                let check_output = run_base_check(ready_check, executor, commands, &outcomes, ui);
+
+                if let Some(&mut ref mut table) = table {
+                    ready_check.add_to_table(table, &check_output);
+                }
 
-                table.add(&ready_check.class, properties.into_iter(), check_output.passed);
+                check_outputs.push(check_output);
             }
+        }
+
+        ResultsSection::from_outputs(check_outputs)
+    }
 
-            check_outputs.push(check_output);
+    /// Runs a batch of checks in continual mode, like `run_all`, but
+    /// without adding them to an analysis table.
+    ///
+    /// Checks with an interval only get run once it has passed since they
+    /// were last run. In the batches before then, the outputs from when they
+    /// last got run get reported again, without being printed, and count as
+    /// their outcomes for the checks that need them.
+    pub fn run_continual_batch(&mut self, executor: &mut Executor, commands: &mut Commands, ui: &mut Outputs, order: RunningOrder, delay: Delay, threads: Threads) -> ResultsSection {
+        if order == RunningOrder::Random {
+            trace!("Shuffling order of all checks");
+            rand::seq::SliceRandom::shuffle(self.checks.as_mut_slice(), &mut rand::thread_rng());
         }
 
-        let mut totals = Stats::default();
-        for check_output in &check_outputs {
-            if check_output.passed {
-                totals.pass_count += 1;
+        let batch_start = Instant::now();
+        let setup_failure = self.run_setup(executor, ui);
+
+        let section = match setup_failure {
+            None     => self.run_continual_checks(executor, commands, ui, delay, threads, batch_start),
+            Some(_)  => ResultsSection::from_outputs(Vec::new()),
+        };
+
+        section.with_hook_failures(self.run_teardown(executor, ui, setup_failure))
+    }
+
+    fn run_continual_checks(&self, executor: &mut Executor, commands: &mut Commands, ui: &mut Outputs, delay: Delay, threads: Threads, batch_start: Instant) -> ResultsSection {
+        let running_order = self.running_order();
+        let outcomes = Outcomes::new(&self.checks);
+
+        let earlier_outputs = running_order.iter().map(|c| c.earlier_outputs(batch_start)).collect::<Vec<_>>();
+        for (ready_check, earlier) in running_order.iter().zip(&earlier_outputs) {
+            if let Some(earlier) = earlier {
+                debug!("Not running check {} until its interval has passed", ready_check.class);
+
+                for (ready_check, check_output) in ready_check.with_secondaries().zip(earlier) {
+                    outcomes.record(ready_check, check_output.passed);
+                }
             }
-            else {
-                totals.fail_count += 1;
+        }
+
+        let due_checks = running_order.iter().zip(&earlier_outputs)
+                                      .filter(|(_, earlier)| earlier.is_none())
+                                      .map(|(ready_check, _)| *ready_check)
+                                      .collect::<Vec<_>>();
+
+        #[cfg(feature = "async")]
+        if delay == Delay::RunInstantly {
+            prefetch_commands(&due_checks, executor, commands);
+        }
+
+        let new_outputs = if let (Threads::Many(thread_count), Delay::RunInstantly) = (threads, delay) {
+            let ready_checks = due_checks.into_iter().flat_map(ReadyCheck::with_secondaries).collect::<Vec<_>>();
+            run_all_parallel(&ready_checks, &outcomes, executor, commands, ui, thread_count, None)
+        }
+        else {
+            let mut check_outputs = Vec::new();
+
+            for ready_check in due_checks {
+                if spec_exec::is_interrupted() {
+                    break;
+                }
+
+                for ready_check in ready_check.with_secondaries() {
+                    check_outputs.push(run_base_check(ready_check, executor, commands, &outcomes, ui));
+                }
+
+                if let Delay::Wait(duration) = delay {
+                    sleep(duration);
+                }
+            }
+
+            check_outputs
+        };
+
+        // The new outputs are in the same order as the checks that were due,
+        // so they get put back in between the earlier ones in that order.
+        let mut new_outputs = new_outputs.into_iter();
+        let mut check_outputs = Vec::new();
+
+        for (ready_check, earlier) in running_order.into_iter().zip(earlier_outputs) {
+            if let Some(earlier) = earlier {
+                check_outputs.extend(earlier);
+                continue;
             }
+
+            let outputs = new_outputs.by_ref().take(ready_check.secondaries.len() + 1).collect::<Vec<_>>();
+            if ready_check.interval.is_some() && outputs.len() == ready_check.secondaries.len() + 1 {
+                *ready_check.last_run.lock().unwrap_or_else(PoisonError::into_inner) = Some((batch_start, outputs.clone()));
+            }
+
+            check_outputs.extend(outputs);
         }
 
-        ResultsSection { check_outputs, totals }
+        ResultsSection::from_outputs(check_outputs)
     }
 
-    pub fn run_continual_batch(&mut self, executor: &mut Executor, commands: &mut Commands, ui: &mut Output, order: RunningOrder, delay: Delay) {
-        if order == RunningOrder::Random {
-            trace!("Shuffling order of all checks");
-            rand::seq::SliceRandom::shuffle(self.checks.as_mut_slice(), &mut rand::thread_rng());
+    /// Returns the checks in the order they get run in, which is the order
+    /// they are in, except that each check comes after every check it needs.
+    fn running_order(&self) -> Vec<&ReadyCheck> {
+        let mut remaining = self.checks.iter().collect::<Vec<_>>();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        while ! remaining.is_empty() {
+            let is_ready = |check: &&ReadyCheck| check.needs.iter().all(|need| {
+                ! remaining.iter().any(|other| other.name.as_ref() == Some(need))
+            });
+
+            // There’s always a check that’s ready, as checks that need each
+            // other get left out when they are read.
+            let position = remaining.iter().position(is_ready).unwrap_or(0);
+            order.push(remaining.remove(position));
         }
 
-        for ready_check in &self.checks {
-            run_base_check(ready_check, executor, commands, ui);
+        order
+    }
 
-            if let Delay::Wait(duration) = delay {
-                sleep(duration);
+    /// Removes the checks read from a document, starting at the given
+    /// index, that weren’t picked by the name filters or by the previous
+    /// run, or that are in another shard. Checks needed by a picked check
+    /// get picked too, even if the filters left them out, so a check can
+    /// be run on its own without being blocked by the checks it needs not
+    /// being run.
+    ///
+    /// Checks that need each other, directly or through other checks, are
+    /// always in the same shard, so a sharded run never blocks a check
+    /// that an unsharded run would have run.
+    fn keep_picked_checks(&mut self, first_check: usize, mut picked: Vec<bool>, shard: Option<Shard>) {
+        let checks = &self.checks[first_check ..];
+
+        loop {
+            let needed = checks.iter().zip(&picked)
+                               .filter(|(_, picked)| **picked)
+                               .flat_map(|(check, _)| &check.needs)
+                               .collect::<BTreeSet<_>>();
+
+            let mut picked_more = false;
+            for (check, picked) in checks.iter().zip(&mut picked) {
+                if ! *picked && check.name.as_ref().is_some_and(|name| needed.contains(name)) {
+                    debug!("Picking check {:?} because another check needs it", check.class.to_string());
+                    *picked = true;
+                    picked_more = true;
+                }
+            }
+
+            if ! picked_more {
+                break;
+            }
+        }
+
+        for (check, picked) in checks.iter().zip(&picked) {
+            if ! picked {
+                debug!("Skipping check {:?} that wasn’t picked", check.class.to_string());
+            }
+        }
+
+        if let Some(shard) = shard {
+            for (index, (check_type, description)) in needs_groups(checks).into_iter().enumerate() {
+                if picked[index] && ! shard.should_include_check(check_type, &description) {
+                    debug!("Skipping check {:?} in another shard", checks[index].class.to_string());
+                    picked[index] = false;
+                }
+            }
+        }
+
+        let mut index = 0;
+        self.checks.retain(|_| {
+            let keep = index < first_check || picked[index - first_check];
+            index += 1;
+            keep
+        });
+    }
+
+    /// Returns the names of a cycle of checks that need each other, starting
+    /// and ending with the same one, if there is one.
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut graph = BTreeMap::<&str, BTreeSet<&str>>::new();
+        for check in &self.checks {
+            if let Some(name) = &check.name {
+                graph.entry(name).or_default().extend(check.needs.iter().map(String::as_str));
+            }
+        }
+
+        let mut visited = BTreeSet::new();
+        graph.keys().find_map(|name| visit_needs(name, &graph, &mut Vec::new(), &mut visited))
+    }
+
+    /// Runs the file’s setup commands, printing and returning the failure of
+    /// the one that failed, if any did. A file with no checks to run doesn’t
+    /// have its hooks run either.
+    fn run_setup(&self, executor: &mut Executor, ui: &mut Outputs) -> Option<HookFailure> {
+        if self.checks.is_empty() {
+            return None;
+        }
+
+        let failure = self.hooks.run_setup(executor, None)?;
+        ui.print_hook_failure(&failure);
+        Some(failure)
+    }
+
+    /// Runs the file’s teardown commands, printing the failure of each one
+    /// that failed, and returns them after the failure of the setup
+    /// command, if there was one.
+    fn run_teardown(&self, executor: &mut Executor, ui: &mut Outputs, setup_failure: Option<HookFailure>) -> Vec<HookFailure> {
+        if self.checks.is_empty() {
+            return Vec::new();
+        }
+
+        let mut failures = setup_failure.into_iter().collect::<Vec<_>>();
+        for failure in self.hooks.run_teardown(executor, None) {
+            ui.print_hook_failure(&failure);
+            failures.push(failure);
+        }
+
+        failures
+    }
+
+    /// Carries over when each check was last run in continual mode, and
+    /// its outputs, from the set of checks this one replaces after their
+    /// input file got read again. Checks are matched by their type, name,
+    /// and description, so a check with an interval that didn’t change
+    /// keeps waiting for it, rather than being run again because something
+    /// else in the file changed.
+    pub fn carry_over_runs(&mut self, previous: Self) {
+        let mut previous_runs = previous.checks.into_iter()
+            .filter_map(|check| {
+                let identity = check.identity();
+                let last_run = check.last_run.into_inner().unwrap_or_else(PoisonError::into_inner)?;
+                Some((identity, last_run))
+            })
+            .collect::<Vec<_>>();
+
+        for check in &mut self.checks {
+            let identity = check.identity();
+            if let Some(index) = previous_runs.iter().position(|(previous, _)| *previous == identity) {
+                let (_, last_run) = previous_runs.remove(index);
+                debug!("Carrying over last run of {:?}", identity.2);
+                *check.last_run.get_mut().unwrap_or_else(PoisonError::into_inner) = Some(last_run);
             }
         }
     }
@@ -278,21 +776,639 @@ impl CheckSet {
         self.checks.is_empty()
     }
 
-    /// Formats each check in the set as a string containing their check type
-    /// name and description, and returns them as a vector.
-    pub fn list_checks(self) -> Vec<String> {
+    /// Returns explanations of the checks in the set whose name or
+    /// description is the given text. If there aren’t any, the ones whose
+    /// name or description contains it, ignoring case, get explained.
+    pub fn explain(self, query: &str, commands: &Commands) -> Vec<Explanation> {
+        let exact = |c: &ReadyCheck| c.name.as_deref() == Some(query) || c.class.to_string() == query;
+
+        let lowercase_query = query.to_lowercase();
+        let contains = |c: &ReadyCheck| {
+            c.name.as_ref().is_some_and(|n| n.to_lowercase().contains(&lowercase_query))
+                || c.class.to_string().to_lowercase().contains(&lowercase_query)
+        };
+
+        let matcher: &dyn Fn(&ReadyCheck) -> bool = if self.checks.iter().any(exact) { &exact } else { &contains };
+        let mut preflight = Preflight::default();
+
+        self.checks.into_iter().filter(|c| matcher(c)).map(|check| {
+            let mut scratch = commands.fresh();
+            check.class.load_into(&mut scratch);
+
+            let mut missing_programs = Vec::new();
+            if commands.check_programs {
+                let mut scratch = commands.fresh();
+                check.class.load_into(&mut scratch);
+                missing_programs = preflight.missing_programs(scratch);
+            }
+
+            Explanation {
+                commands: scratch.list_commands(),
+                secondaries: check.secondaries.iter().map(|s| format!("[{}] {}", s.class.name(), s.class)).collect(),
+                retry: check.retry(commands.retry),
+                parameters: check.parameters,
+                missing_programs,
+                setup: check.hooks.setup,
+                teardown: check.hooks.teardown,
+                expected: check.expected,
+                severity: check.severity,
+                known_issue: check.known_issue,
+                budget: check.budget,
+                needs: check.needs,
+                excluded: check.excluded,
+                listed: ListedCheck {
+                    check_type: check.class.name(),
+                    name: check.name,
+                    tags: check.tags,
+                    description: check.class.to_string(),
+                    location: check.location,
+                },
+            }
+        }).collect()
+    }
+
+    /// Returns the type, name, tags, description, and location of each
+    /// check in the set, in order.
+    pub fn list_checks(self) -> Vec<ListedCheck> {
         self.checks.into_iter()
-            .map(|e| format!("[{}] {}", e.class.name(), e.class))
+            .map(|e| ListedCheck {
+                check_type: e.class.name(),
+                name: e.name,
+                tags: e.tags,
+                description: e.class.to_string(),
+                location: e.location,
+            })
             .collect()
     }
 }
 
 
-fn run_base_check(ready_check: &ReadyCheck, executor: &mut Executor, commands: &mut Commands, ui: &mut Output) -> CheckOutput {
+impl ReadyCheck {
+
+    /// Primes the commands this check uses, unless it’s not going to be run
+    /// on this machine, or the programs it runs are being checked for and
+    /// any of them are missing.
+    fn prime(&mut self, commands: &mut Commands, preflight: &mut Preflight) {
+        if self.excluded.is_some() {
+            return;
+        }
+
+        if commands.check_programs {
+            let mut scratch = commands.fresh();
+            self.class.load_into(&mut scratch);
+            self.missing_programs = preflight.missing_programs(scratch);
+
+            if ! self.missing_programs.is_empty() {
+                info!("Skipping check {} -> missing {:?}", self.class, self.missing_programs);
+                return;
+            }
+        }
+
+        self.class.load_into(commands);
+    }
+
+    /// Returns an iterator over this check, followed by its secondary
+    /// checks.
+    fn with_secondaries(&self) -> impl Iterator<Item=&Self> {
+        std::iter::once(self).chain(&self.secondaries)
+    }
+
+    /// Returns what identifies this check between two readings of its
+    /// input file: its type, its name, and its description, which changes
+    /// whenever its parameters do.
+    fn identity(&self) -> (&'static str, Option<String>, String) {
+        (self.class.name(), self.name.clone(), self.class.to_string())
+    }
+
+    /// Returns the outputs from when this check and its secondary checks
+    /// were last run, if it has an interval that hasn’t passed since then
+    /// by the start of the batch, so it shouldn’t be run again yet.
+    fn earlier_outputs(&self, batch_start: Instant) -> Option<Vec<CheckOutput>> {
+        let interval = self.interval?;
+        let last_run = self.last_run.lock().unwrap_or_else(PoisonError::into_inner);
+        let (ran_at, check_outputs) = last_run.as_ref()?;
+
+        if batch_start.duration_since(*ran_at) < interval {
+            Some(check_outputs.clone())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Works out how this check should be retried, with its own settings
+    /// taking precedence over the global ones.
+    fn retry(&self, defaults: Retry) -> Retry {
+        Retry {
+            retries: self.retries.unwrap_or(defaults.retries),
+            delay: self.retry_delay.unwrap_or(defaults.delay),
+        }
+    }
+
+    /// Adds the properties of this check, and whether it passed, to the
+    /// analysis table.
+    fn add_to_table<'set>(&'set self, table: &mut AnalysisTable<'set, LoadedCheck>, check_output: &CheckOutput) {
+
+        // A skipped check says nothing about the properties it would have
+        // checked, one way or the other.
+        if check_output.skipped.is_some() {
+            return;
+        }
+
+        let properties = match self.class {
+            LoadedCheck::Cmd(ref c)           => c.properties(),
+            LoadedCheck::Tap(ref c)           => c.properties(),
+            LoadedCheck::Dns(ref c)           => c.properties(),
+            LoadedCheck::Http(ref c)          => c.properties(),
+            LoadedCheck::Ping(ref c)          => c.properties(),
+            LoadedCheck::Tcp(ref c)           => c.properties(),
+            LoadedCheck::Udp(ref c)           => c.properties(),
+            LoadedCheck::Apt(ref c)           => c.properties(),
+            LoadedCheck::Fs(ref c)            => c.properties(),
+            LoadedCheck::Gem(ref c)           => c.properties(),
+            LoadedCheck::Group(ref c)         => c.properties(),
+            LoadedCheck::Homebrew(ref c)      => c.properties(),
+            LoadedCheck::HomebrewCask(ref c)  => c.properties(),
+            LoadedCheck::Npm(ref c)           => c.properties(),
+            LoadedCheck::Systemd(ref c)       => c.properties(),
+            LoadedCheck::Ufw(ref c)           => c.properties(),
+            LoadedCheck::User(ref c)          => c.properties(),
+            _                                 => Vec::new(),
+        };
+
+        table.add(&self.class, properties.into_iter(), check_output.passed);
+    }
+}
+
+
+/// Works out which group each check is in, where a group is the checks
+/// that need each other, directly or through other checks, and returns the
+/// type and description that each check’s group gets sharded by: those of
+/// the check that comes first in its group, as every check in the group
+/// agrees on which that is.
+fn needs_groups(checks: &[ReadyCheck]) -> Vec<(&'static str, String)> {
+    fn root(parents: &[usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            index = parents[index];
+        }
+        index
+    }
+
+    let identities = checks.iter().map(|c| (c.class.name(), c.class.to_string())).collect::<Vec<_>>();
+    let mut parents = (0 .. checks.len()).collect::<Vec<_>>();
+
+    for (index, check) in checks.iter().enumerate() {
+        for (other, needed) in checks.iter().enumerate() {
+            if needed.name.as_ref().is_some_and(|name| check.needs.contains(name)) {
+                let (a, b) = (root(&parents, index), root(&parents, other));
+
+                // The root of a group is always the check that comes first.
+                if identities[a] < identities[b] {
+                    parents[b] = a;
+                }
+                else {
+                    parents[a] = b;
+                }
+            }
+        }
+    }
+
+    (0 .. checks.len()).map(|index| identities[root(&parents, index)].clone()).collect()
+}
+
+/// Visits the checks with the given name, then the checks they need, and so
+/// on, returning the path back to a check that’s already on it.
+fn visit_needs<'a>(name: &'a str, graph: &BTreeMap<&'a str, BTreeSet<&'a str>>, path: &mut Vec<&'a str>, visited: &mut BTreeSet<&'a str>) -> Option<Vec<String>> {
+    if let Some(position) = path.iter().position(|n| *n == name) {
+        let mut cycle = path[position ..].iter().map(|n| (*n).to_string()).collect::<Vec<_>>();
+        cycle.push(name.to_string());
+        return Some(cycle);
+    }
+
+    if ! visited.insert(name) {
+        return None;
+    }
+
+    path.push(name);
+    let cycle = graph.get(name).into_iter().flatten().find_map(|need| visit_needs(need, graph, path, visited));
+    path.pop();
+    cycle
+}
+
+
+/// The **outcomes** of the checks that have been run, by name, which the
+/// checks that need them wait for before being run themselves.
+struct Outcomes {
+    progress: Mutex<BTreeMap<String, Outcome>>,
+    recorded: Condvar,
+}
+
+/// Whether all the checks with a name have passed so far, and how many of
+/// them there are left to run.
+struct Outcome {
+    left_to_run: usize,
+    passed: bool,
+}
+
+impl Outcomes {
+
+    /// Creates the outcomes for the given checks and their secondary checks,
+    /// none of which have been run yet.
+    fn new(ready_checks: &[ReadyCheck]) -> Self {
+        let mut progress = BTreeMap::new();
+        for name in ready_checks.iter().flat_map(ReadyCheck::with_secondaries).filter_map(|c| c.name.as_ref()) {
+            progress.entry(name.clone()).or_insert(Outcome { left_to_run: 0, passed: true }).left_to_run += 1;
+        }
+
+        Self { progress: Mutex::new(progress), recorded: Condvar::new() }
+    }
+
+    /// Records whether a check passed, waking up any checks waiting on it.
+    fn record(&self, ready_check: &ReadyCheck, passed: bool) {
+        let Some(name) = &ready_check.name else { return };
+
+        let mut progress = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(outcome) = progress.get_mut(name) {
+            outcome.left_to_run = outcome.left_to_run.saturating_sub(1);
+            outcome.passed &= passed;
+        }
+
+        self.recorded.notify_all();
+    }
+
+    /// Waits for every check the given check needs to be run, then returns
+    /// the names of the ones that didn’t pass. Needed checks that aren’t
+    /// going to be run at all, because they were filtered out, don’t pass.
+    fn unmet_needs(&self, ready_check: &ReadyCheck) -> Vec<String> {
+        let mut progress = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut unmet = Vec::new();
+
+        for need in &ready_check.needs {
+            while progress.get(need).is_some_and(|o| o.left_to_run > 0) {
+                progress = self.recorded.wait(progress).unwrap_or_else(PoisonError::into_inner);
+            }
+
+            if ! progress.get(need).is_some_and(|o| o.passed) {
+                unmet.push(need.clone());
+            }
+        }
+
+        unmet
+    }
+}
+
+/// Describes why a check whose needed checks didn’t pass got skipped.
+fn unmet_reason(unmet_needs: &[String]) -> String {
+    let quoted = unmet_needs.iter().map(|n| format!("‘{}’", n)).collect::<Vec<_>>();
+
+    format!("needs {}, which did not pass", quoted.join(", "))
+}
+
+
+/// Something that check results can be printed to: either the outputs
+/// themselves, or a turn to print to them when running in parallel.
+trait PrintCheck {
+    fn print_check(&mut self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>);
+    fn print_skipped(&mut self, check: &LoadedCheck, name: Option<&String>, reason: &str);
+    fn print_environment_error(&mut self, check: &LoadedCheck, name: Option<&String>, error: &str);
+    fn print_hook_failure(&mut self, failure: &HookFailure);
+    fn finish_check(&mut self, check_output: &CheckOutput);
+}
+
+impl PrintCheck for Outputs {
+    fn print_check(&mut self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) {
+        Outputs::print_check(self, check, name, results, details);
+    }
+
+    fn print_skipped(&mut self, check: &LoadedCheck, name: Option<&String>, reason: &str) {
+        Outputs::print_skipped(self, check, name, reason);
+    }
+
+    fn print_environment_error(&mut self, check: &LoadedCheck, name: Option<&String>, error: &str) {
+        Outputs::print_environment_error(self, check, name, error);
+    }
+
+    fn print_hook_failure(&mut self, failure: &HookFailure) {
+        Outputs::print_hook_failure(self, failure);
+    }
+
+    fn finish_check(&mut self, check_output: &CheckOutput) {
+        Outputs::finish_check(self, check_output);
+    }
+}
+
+
+/// The **turnstile** lets checks that are being run on several threads
+/// print their results one at a time, in the order the checks are in.
+struct Turnstile<'ui, 'set> {
+    progress: Mutex<Progress<'ui, 'set>>,
+    turn_over: Condvar,
+}
+
+/// The state behind the turnstile’s lock.
+struct Progress<'ui, 'set> {
+
+    /// The index of the check whose turn it is to print its results.
+    current_index: usize,
+
+    ui: &'ui mut Outputs,
+    table: Option<&'ui mut AnalysisTable<'set, LoadedCheck>>,
+    check_outputs: Vec<CheckOutput>,
+}
+
+/// A check’s **turn** at the turnstile. Printing to it waits until every
+/// check before it has been printed.
+struct Turn<'t, 'ui, 'set> {
+    turnstile: &'t Turnstile<'ui, 'set>,
+    ready_check: &'set ReadyCheck,
+    index: usize,
+    finished: bool,
+}
+
+impl<'ui, 'set> Turnstile<'ui, 'set> {
+    fn new(ui: &'ui mut Outputs, table: Option<&'ui mut AnalysisTable<'set, LoadedCheck>>) -> Self {
+        let progress = Progress { current_index: 0, ui, table, check_outputs: Vec::new() };
+        Self { progress: Mutex::new(progress), turn_over: Condvar::new() }
+    }
+
+    /// Hands out the turn for the check with the given index.
+    fn turn(&self, index: usize, ready_check: &'set ReadyCheck) -> Turn<'_, 'ui, 'set> {
+        Turn { turnstile: self, ready_check, index, finished: false }
+    }
+
+    /// Blocks until it’s the turn of the check with the given index.
+    fn wait_for(&self, index: usize) -> MutexGuard<'_, Progress<'ui, 'set>> {
+        let mut progress = self.lock();
+        while progress.current_index != index {
+            progress = self.turn_over.wait(progress).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        progress
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Progress<'ui, 'set>> {
+        self.progress.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the outputs of every check, in order.
+    fn into_outputs(self) -> Vec<CheckOutput> {
+        self.progress.into_inner().unwrap_or_else(PoisonError::into_inner).check_outputs
+    }
+}
+
+impl Turn<'_, '_, '_> {
+
+    /// Records the output of the check once it’s this check’s turn, then
+    /// passes the turn on to the next one.
+    fn finish(mut self, check_output: CheckOutput) {
+        let mut progress = self.turnstile.wait_for(self.index);
+
+        if let Some(&mut ref mut table) = progress.table {
+            self.ready_check.add_to_table(table, &check_output);
+        }
+
+        progress.check_outputs.push(check_output);
+        progress.current_index += 1;
+        self.finished = true;
+        self.turnstile.turn_over.notify_all();
+    }
+}
+
+impl PrintCheck for Turn<'_, '_, '_> {
+    fn print_check(&mut self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) {
+        let progress = &mut *self.turnstile.wait_for(self.index);
+        progress.ui.print_check(check, name, results, details);
+    }
+
+    fn print_skipped(&mut self, check: &LoadedCheck, name: Option<&String>, reason: &str) {
+        let progress = &mut *self.turnstile.wait_for(self.index);
+        progress.ui.print_skipped(check, name, reason);
+    }
+
+    fn print_environment_error(&mut self, check: &LoadedCheck, name: Option<&String>, error: &str) {
+        let progress = &mut *self.turnstile.wait_for(self.index);
+        progress.ui.print_environment_error(check, name, error);
+    }
+
+    fn print_hook_failure(&mut self, failure: &HookFailure) {
+        let progress = &mut *self.turnstile.wait_for(self.index);
+        progress.ui.print_hook_failure(failure);
+    }
+
+    fn finish_check(&mut self, check_output: &CheckOutput) {
+        let progress = &mut *self.turnstile.wait_for(self.index);
+        progress.ui.finish_check(check_output);
+    }
+}
+
+impl Drop for Turn<'_, '_, '_> {
+
+    /// Passes the turn on even if the check never finished, such as when
+    /// its thread panics, so the other threads don’t wait forever.
+    fn drop(&mut self) {
+        if ! self.finished {
+            let mut progress = self.turnstile.wait_for(self.index);
+            progress.current_index += 1;
+            self.turnstile.turn_over.notify_all();
+        }
+    }
+}
+
+
+/// Runs the given checks, like `run_base_check`, but spread over the given
+/// number of threads, returning their outputs in order. Each thread has an
+/// executor of its own, whose command history gets added to the main one’s
+/// afterwards.
+///
+/// A check that needs other checks waits for them to finish first. They
+/// always come before it, so they have already been started.
+///
+/// The results get printed in the same order they would have been if
+/// the checks had been run one after another, so a check that finishes
+/// early waits for the checks before it to be printed first.
+fn run_all_parallel<'set>(ready_checks: &[&'set ReadyCheck], outcomes: &Outcomes, executor: &mut Executor, commands: &Commands, ui: &mut Outputs, thread_count: usize, table: Option<&mut AnalysisTable<'set, LoadedCheck>>) -> Vec<CheckOutput> {
+    let thread_count = thread_count.min(ready_checks.len());
+    debug!("Running {} checks on {} threads", ready_checks.len(), thread_count);
+
+    let next_index = AtomicUsize::new(0);
+    let turnstile = Turnstile::new(ui, table);
+
+    let worker_executors = thread::scope(|scope| {
+        let (next_index, turnstile) = (&next_index, &turnstile);
+
+        let handles = (0 .. thread_count).map(|_| {
+            let mut worker_executor = executor.fork();
+
+            scope.spawn(move || {
+                let take_next = || {
+                    if spec_exec::is_interrupted() {
+                        return None;
+                    }
+
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    ready_checks.get(index).map(|ready_check| (index, *ready_check))
+                };
+
+                while let Some((index, ready_check)) = take_next() {
+                    let mut turn = turnstile.turn(index, ready_check);
+                    let check_output = run_base_check(ready_check, &mut worker_executor, commands, outcomes, &mut turn);
+                    turn.finish(check_output);
+                }
+
+                worker_executor
+            })
+        }).collect::<Vec<_>>();
+
+        handles.into_iter()
+               .map(|handle| handle.join().expect("Check thread panicked"))
+               .collect::<Vec<_>>()
+    });
+
+    for worker_executor in worker_executors {
+        executor.absorb(worker_executor);
+    }
+
+    turnstile.into_outputs()
+}
+
+
+/// Runs the commands of every one of the given checks that can be run
+/// straight away, all at once, as tasks on the executor’s async executor,
+/// if it has one. The checks then find their results in the cache of the
+/// set of commands when they get run, so hundreds of checks that spend
+/// their time waiting on the network can share a few threads.
+///
+/// Checks that need other checks, or that have setup commands of their
+/// own, get left out, as their commands depend on something else happening
+/// first. So do checks that won’t be run at all.
+#[cfg(feature = "async")]
+fn prefetch_commands(ready_checks: &[&ReadyCheck], executor: &mut Executor, commands: &Commands) {
+    let prefetched = ready_checks.iter()
+        .filter(|c| c.needs.is_empty() && c.hooks.setup.is_empty())
+        .flat_map(|c| c.with_secondaries())
+        .filter(|c| c.excluded.is_none() && c.missing_programs.is_empty())
+        .flat_map(|c| {
+            let mut scratch = commands.fresh();
+            c.class.load_into(&mut scratch);
+            scratch.list_commands()
+        });
+
+    executor.set_cache(Some(Arc::clone(&commands.cache)));
+    executor.prefetch(prefetched);
+}
+
+
+/// Runs a single check, printing and returning its results.
+///
+/// The check’s commands get run through the cache of the set of commands,
+/// so any identical commands that other checks have run get used instead.
+/// A check that fails gets run again, up to its number of retries, against
+/// a fresh set of commands each time so its processes get run again too.
+/// Only the results of the last attempt get printed.
+///
+/// A check that needs other checks waits for them to be run first, and gets
+/// skipped if any of them didn’t pass. Either way, whether it passed gets
+/// recorded for the checks that need it in turn.
+fn run_base_check(ready_check: &ReadyCheck, executor: &mut Executor, commands: &Commands, outcomes: &Outcomes, ui: &mut impl PrintCheck) -> CheckOutput {
+    let unmet_needs = outcomes.unmet_needs(ready_check);
+
+    let check_output = if unmet_needs.is_empty() {
+        run_ready_check(ready_check, executor, commands, ui)
+    }
+    else {
+        let reason = unmet_reason(&unmet_needs);
+        ui.print_skipped(&ready_check.class, ready_check.name.as_ref(), &reason);
+        CheckOutput::blocked(ready_check.class.to_string(), reason, unmet_needs, ready_check.known_issue.clone())
+    };
+
+    outcomes.record(ready_check, check_output.passed);
+
+    let check_output = CheckOutput {
+        check_type: ready_check.class.name(),
+        name: ready_check.name.clone(),
+        tags: ready_check.tags.clone(),
+        ..check_output
+    };
+
+    ui.finish_check(&check_output);
+    check_output
+}
+
+/// Runs a check whose needed checks have passed.
+///
+/// A check that isn’t meant for this machine doesn’t get run at all, nor
+/// does one whose programs are missing, and neither
+/// does one that gets run after a side process has died, as its results
+/// wouldn’t mean anything. Otherwise, the check’s setup commands get run
+/// once before all its attempts, and its teardown commands once after
+/// them, even if the setup failed and the check never got run.
+fn run_ready_check(ready_check: &ReadyCheck, executor: &mut Executor, commands: &Commands, ui: &mut impl PrintCheck) -> CheckOutput {
+    if let Some(reason) = &ready_check.excluded {
+        ui.print_skipped(&ready_check.class, ready_check.name.as_ref(), reason);
+        return CheckOutput::skipped(ready_check.class.to_string(), reason.clone(), ready_check.known_issue.clone());
+    }
+
+    if ! ready_check.missing_programs.is_empty() {
+        let reason = skip_reason(&ready_check.missing_programs);
+        ui.print_skipped(&ready_check.class, ready_check.name.as_ref(), &reason);
+        return CheckOutput::skipped(ready_check.class.to_string(), reason, ready_check.known_issue.clone());
+    }
+
+    if let Some(error) = commands.side_processes.as_ref().and_then(|s| s.check_health()) {
+        ui.print_environment_error(&ready_check.class, ready_check.name.as_ref(), &error);
+        return CheckOutput::environment_error(ready_check.class.to_string(), &error);
+    }
+
+    let check_name = ready_check.name.clone().unwrap_or_else(|| ready_check.class.to_string());
+
+    let mut check_output = match ready_check.hooks.run_setup(executor, Some(&check_name)) {
+        None => {
+            run_attempts(ready_check, executor, commands, ui)
+        }
+        Some(failure) => {
+            ui.print_hook_failure(&failure);
+            CheckOutput::setup_failed(ready_check.class.to_string(), failure)
+        }
+    };
+
+    for failure in ready_check.hooks.run_teardown(executor, Some(&check_name)) {
+        ui.print_hook_failure(&failure);
+        check_output.hook_failures.push(failure);
+    }
+
+    check_output
+}
+
+/// Runs a check as many times as it takes to pass, up to its number of
+/// retries, printing and returning the results of the last attempt.
+fn run_attempts(ready_check: &ReadyCheck, executor: &mut Executor, commands: &Commands, ui: &mut impl PrintCheck) -> CheckOutput {
+    let retry = ready_check.retry(commands.retry);
+
     macro_rules! results_to_output {
-        ($c:expr, $name:expr, $results:expr) => {{
-            let results = $results;
-            ui.print_check($c, $name, &results);
+        ($c:expr, $name:expr, |$commands:ident| $results:expr) => {{
+            let timer = Instant::now();
+            let _ = executor.take_used_commands();
+            executor.set_cache(Some(Arc::clone(&commands.cache)));
+            let mut results = { let $commands = commands; $results };
+            let mut attempts = 1;
+
+            while attempts <= retry.retries && ! results.iter().all(CheckResult::passed) {
+                debug!("Retrying check after attempt {}", attempts);
+                sleep(retry.delay);
+
+                let mut fresh = commands.fresh();
+                ready_check.class.load_into(&mut fresh);
+                executor.set_cache(Some(Arc::clone(&fresh.cache)));
+                results = { let $commands = &fresh; $results };
+                attempts += 1;
+            }
+
+            let runtime = timer.elapsed();
+            let ran_commands = executor.take_used_commands();
+            let budget = ready_check.budget.map(|b| BudgetUsage::new(b, runtime));
+            let known_issue = ready_check.known_issue.as_deref();
+            let (expected, severity) = (ready_check.expected, ready_check.severity);
+            ui.print_check($c, $name, &results, RunDetails { budget, known_issue, attempts, expected, severity, runtime, commands: &ran_commands });
 
             let passed = results.iter().all(CheckResult::passed);
             let message = $c.to_string();
@@ -305,36 +1421,56 @@ fn run_base_check(ready_check: &ReadyCheck, executor: &mut Executor, commands: &
                 }
             }).collect();
 
-            CheckOutput { passed, results, message }
+            let known_issue = ready_check.known_issue.clone();
+
+            CheckOutput { passed, results, message, check_type: "", name: None, tags: Vec::new(), budget, known_issue, attempts, skipped: None, blocked_by: Vec::new(), expected, severity, hook_failures: Vec::new(), runtime, ran_commands }
         }}
     }
 
     let name = ready_check.name.as_ref();
 
     match &ready_check.class {
-        LoadedCheck::Cmd(c)           => results_to_output!(c, name, c.check(executor, &commands.shell)),
-        LoadedCheck::Tap(c)           => results_to_output!(c, name, c.check(executor, &commands.shell)),
+        LoadedCheck::Cmd(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.shell)),
+        LoadedCheck::Tap(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.shell)),
+
+        LoadedCheck::Dns(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.dig)),
+        LoadedCheck::Http(c)          => results_to_output!(c, name, |cs| c.check(executor, &cs.curl)),
+        LoadedCheck::Ping(c)          => results_to_output!(c, name, |cs| c.check(executor, &cs.ping)),
+        LoadedCheck::Tcp(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.net)),
+        LoadedCheck::Udp(c)           => results_to_output!(c, name, |cs| c.check(&cs.net)),
+
+        LoadedCheck::Apt(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.apt)),
+        LoadedCheck::AptSource(c)     => results_to_output!(c, name, |cs| c.check(executor, &cs.apt_source)),
+        LoadedCheck::Defaults(c)      => results_to_output!(c, name, |cs| c.check(executor, &cs.defaults)),
+        LoadedCheck::Fs(c)            => results_to_output!(c, name, |cs| c.check(&cs.files)),
+        LoadedCheck::Gem(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.gem)),
+        LoadedCheck::Group(c)         => results_to_output!(c, name, |cs| c.check(&cs.passwd)),
+        LoadedCheck::Hash(c)          => results_to_output!(c, name, |cs| c.check(executor, &cs.hash)),
+        LoadedCheck::Homebrew(c)      => results_to_output!(c, name, |cs| c.check(executor, &cs.brew)),
+        LoadedCheck::HomebrewCask(c)  => results_to_output!(c, name, |cs| c.check(executor, &cs.brew_cask)),
+        LoadedCheck::HomebrewTap(c)   => results_to_output!(c, name, |cs| c.check(executor, &cs.brew_tap)),
+        LoadedCheck::Npm(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.npm)),
+        LoadedCheck::Systemd(c)       => results_to_output!(c, name, |cs| c.check(executor, &cs.systemctl)),
+        LoadedCheck::Ufw(c)           => results_to_output!(c, name, |cs| c.check(executor, &cs.ufw)),
+        LoadedCheck::User(c)          => results_to_output!(c, name, |cs| c.check(&cs.passwd)),
+    }
+}
 
-        LoadedCheck::Dns(c)           => results_to_output!(c, name, c.check(executor, &commands.dig)),
-        LoadedCheck::Http(c)          => results_to_output!(c, name, c.check(executor, &commands.curl)),
-        LoadedCheck::Ping(c)          => results_to_output!(c, name, c.check(executor, &commands.ping)),
-        LoadedCheck::Tcp(c)           => results_to_output!(c, name, c.check(&commands.net)),
-        LoadedCheck::Udp(c)           => results_to_output!(c, name, c.check(&commands.net)),
 
-        LoadedCheck::Apt(c)           => results_to_output!(c, name, c.check(executor, &commands.apt)),
-        LoadedCheck::Defaults(c)      => results_to_output!(c, name, c.check(executor, &commands.defaults)),
-        LoadedCheck::Fs(c)            => results_to_output!(c, name, c.check(&commands.files)),
-        LoadedCheck::Gem(c)           => results_to_output!(c, name, c.check(executor, &commands.gem)),
-        LoadedCheck::Group(c)         => results_to_output!(c, name, c.check(&commands.passwd)),
-        LoadedCheck::Hash(c)          => results_to_output!(c, name, c.check(executor, &commands.hash)),
-        LoadedCheck::Homebrew(c)      => results_to_output!(c, name, c.check(executor, &commands.brew)),
-        LoadedCheck::HomebrewCask(c)  => results_to_output!(c, name, c.check(executor, &commands.brew_cask)),
-        LoadedCheck::HomebrewTap(c)   => results_to_output!(c, name, c.check(executor, &commands.brew_tap)),
-        LoadedCheck::Npm(c)           => results_to_output!(c, name, c.check(executor, &commands.npm)),
-        LoadedCheck::Systemd(c)       => results_to_output!(c, name, c.check(executor, &commands.systemctl)),
-        LoadedCheck::Ufw(c)           => results_to_output!(c, name, c.check(executor, &commands.ufw)),
-        LoadedCheck::User(c)          => results_to_output!(c, name, c.check(&commands.passwd)),
-    }
+/// Converts the value of a variable given on the command-line, which is
+/// always a string, to the type of the value in the document it overrides,
+/// so numbers can be overridden with numbers.
+fn override_value(original: Option<&TomlValue>, value: &TomlValue) -> TomlValue {
+    let TomlValue::String(text) = value else { return value.clone() };
+
+    let converted = match original {
+        Some(TomlValue::Integer(_))  => text.parse().ok().map(TomlValue::Integer),
+        Some(TomlValue::Float(_))    => text.parse().ok().map(TomlValue::Float),
+        Some(TomlValue::Boolean(_))  => text.parse().ok().map(TomlValue::Boolean),
+        _                            => None,
+    };
+
+    converted.unwrap_or_else(|| value.clone())
 }
 
 
@@ -347,19 +1483,161 @@ pub struct ReadError {
 
     /// The error that caused reading to fail.
     pub inner: Box<dyn fmt::Display>,
+
+    /// The file the table was in, if it was read from one.
+    pub file: Option<PathBuf>,
+
+    /// Where in the file the error is, if it’s known.
+    pub position: Option<Position>,
+}
+
+impl ReadError {
+
+    /// Creates an error for a check entry, which points at where the given
+    /// parameter was written, or at the entry itself if it wasn’t.
+    fn in_entry(name: impl Into<Cow<'static, str>>, inner: Box<dyn fmt::Display>, location: &Location, parameter_name: Option<&str>) -> Self {
+        let position = parameter_name.and_then(|p| location.parameter(p)).or(location.start);
+        Self { name: name.into(), inner, file: location.file.clone(), position }
+    }
 }
 
 
 #[derive(Debug)]
-pub struct UnknownCheckType(String);
+pub struct UnknownCheckType(String, Option<&'static str>);
 
 impl fmt::Display for UnknownCheckType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Unknown check type {:?}", self.0)
+        match self.1 {
+            Some(suggestion)  => write!(f, "Unknown check type {:?} (did you mean ‘{}’?)", self.0, suggestion),
+            None              => write!(f, "Unknown check type {:?}", self.0),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct UnknownDependency(String);
+
+impl fmt::Display for UnknownDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Needs unknown check {:?}", self.0)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct DependencyCycle(Vec<String>);
+
+impl fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checks need each other: {}", self.0.join(" → "))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct UnresolvedVariable(String);
+
+impl fmt::Display for UnresolvedVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.strip_prefix("env:") {
+            Some(name)  => write!(f, "Environment variable {:?} is not set", name),
+            None        => write!(f, "Variable {:?} has no value", self.0),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidExpectation(String);
+
+impl fmt::Display for InvalidExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid expected result {:?} (it must be ‘pass’ or ‘fail’)", self.0)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidSeverity(String);
+
+impl fmt::Display for InvalidSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid severity {:?} (it must be ‘error’ or ‘warn’)", self.0)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidBudget(String);
+
+impl fmt::Display for InvalidBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid budget duration {:?}", self.0)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidRetryDelay(String);
+
+impl fmt::Display for InvalidRetryDelay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid retry delay duration {:?}", self.0)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidInterval(String);
+
+impl fmt::Display for InvalidInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid interval duration {:?}", self.0)
     }
 }
 
 impl LoadedCheck {
+
+    /// Returns the checks that this check’s parameters imply, which get
+    /// run after it.
+    fn secondary_checks(&self) -> Vec<Self> {
+        match self {
+            Self::Tcp(c)  => c.ufw_check().into_iter().map(Self::from).collect(),
+            Self::Udp(c)  => c.ufw_check().into_iter().map(Self::from).collect(),
+            _             => Vec::new(),
+        }
+    }
+
+    /// Tells the command that this check uses to prepare itself.
+    fn load_into(&self, commands: &mut Commands) {
+        match self {
+            Self::Cmd(c)           => c.load(&mut commands.shell),
+            Self::Tap(c)           => c.load(&mut commands.shell),
+
+            Self::Dns(c)           => c.load(&mut commands.dig),
+            Self::Http(c)          => c.load(&mut commands.curl),
+            Self::Ping(c)          => c.load(&mut commands.ping),
+            Self::Tcp(c)           => c.load(&mut commands.net),
+            Self::Udp(c)           => c.load(&mut commands.net),
+
+            Self::Apt(c)           => c.load(&mut commands.apt),
+            Self::AptSource(c)     => c.load(&mut commands.apt_source),
+            Self::Defaults(c)      => c.load(&mut commands.defaults),
+            Self::Fs(c)            => c.load(&mut commands.files),
+            Self::Gem(c)           => c.load(&mut commands.gem),
+            Self::Group(c)         => c.load(&mut commands.passwd),
+            Self::Hash(c)          => c.load(&mut commands.hash),
+            Self::Homebrew(c)      => c.load(&mut commands.brew),
+            Self::HomebrewCask(c)  => c.load(&mut commands.brew_cask),
+            Self::HomebrewTap(c)   => c.load(&mut commands.brew_tap),
+            Self::Npm(c)           => c.load(&mut commands.npm),
+            Self::Systemd(c)       => c.load(&mut commands.systemctl),
+            Self::Ufw(c)           => c.load(&mut commands.ufw),
+            Self::User(c)          => c.load(&mut commands.passwd),
+        }
+    }
+
     fn name(&self) -> &'static str {
         match self {
 
@@ -376,6 +1654,7 @@ impl LoadedCheck {
 
             // local
             Self::Apt(_)           => apt::AptCheck::TYPE,
+            Self::AptSource(_)     => apt_source::AptSourceCheck::TYPE,
             Self::Defaults(_)      => defaults::DefaultsCheck::TYPE,
             Self::Fs(_)            => fs::FilesystemCheck::TYPE,
             Self::Gem(_)           => gem::GemCheck::TYPE,
@@ -391,3 +1670,241 @@ impl LoadedCheck {
         }
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spec_checks::load::parse_toml;
+    use crate::commands::GlobalOptions;
+    use crate::filter::{NamesFilter, TagsFilter};
+    use crate::results::CheckStatus;
+    use regex::Regex;
+
+    fn read(document: &str) -> CheckSet {
+        read_filtered(document, &Filter::default())
+    }
+
+    fn read_filtered(document: &str, filter: &Filter) -> CheckSet {
+        let mut checks = CheckSet::new();
+        if let Err(es) = checks.read_toml(filter, &Rewrites::new(), &Variables::new(), parse_toml(document).unwrap()) {
+            panic!("Error reading [{}]: {}", es[0].name, es[0].inner);
+        }
+
+        checks
+    }
+
+    #[test]
+    fn secondary_known_issue() {
+        let checks = read("[[tcp]]\nport = 22\nufw = { allow = 'Anywhere' }\nknown_issue = '#12'\n");
+        assert_eq!(checks.checks[0].secondaries.len(), 1);
+        assert_eq!(checks.checks[0].secondaries[0].known_issue, Some("#12".into()));
+    }
+
+    fn run(checks: CheckSet, threads: Threads) -> ResultsSection {
+        let commands = Commands::from_global_options(&GlobalOptions::default()).unwrap();
+        run_with(checks, commands, threads)
+    }
+
+    fn run_with(mut checks: CheckSet, mut commands: Commands, threads: Threads) -> ResultsSection {
+        checks.prime_commands(&mut commands);
+        checks.run_all(&mut Executor::new(), &mut commands, &mut Outputs::discard(), Delay::RunInstantly, threads, None)
+    }
+
+    fn names(section: &ResultsSection) -> Vec<Option<&str>> {
+        section.check_outputs.iter().map(|o| o.name.as_deref()).collect()
+    }
+
+    #[test]
+    fn needs_run_first() {
+        let checks = read("[[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\n\n\
+                           [[cmd]]\nname = 'a'\nshell = 'true'\n");
+
+        let section = run(checks, Threads::One);
+        assert_eq!(names(&section), vec![ Some("a"), Some("b") ]);
+        assert!(section.check_outputs.iter().all(|o| o.passed));
+    }
+
+    #[test]
+    fn failing_need_blocks() {
+        let checks = read("[[cmd]]\nname = 'a'\nshell = 'false'\nstatus = 0\n\n\
+                           [[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\n");
+
+        let section = run(checks, Threads::One);
+        let blocked = &section.check_outputs[1];
+        assert!(! blocked.passed);
+        assert_eq!(blocked.blocked_by, vec![ String::from("a") ]);
+        assert_eq!(blocked.skipped.as_deref(), Some("needs ‘a’, which did not pass"));
+        assert_eq!(section.totals.blocked_count, 1);
+    }
+
+    #[test]
+    fn filtered_out_need_blocks() {
+        let filter = Filter { tags: TagsFilter { tags: vec![ "picked".into() ], skip_tags: Vec::new() }, ..Filter::default() };
+        let checks = read_filtered("[[cmd]]\nname = 'a'\nshell = 'true'\n\n\
+                                    [[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\ntags = 'picked'\n", &filter);
+
+        let section = run(checks, Threads::One);
+        assert_eq!(names(&section), vec![ Some("b") ]);
+        assert_eq!(section.check_outputs[0].blocked_by, vec![ String::from("a") ]);
+    }
+
+    #[test]
+    fn name_filter_picks_needs() {
+        let filter = Filter { names: NamesFilter { names: vec![ Regex::new("^b$").unwrap() ], skip_names: Vec::new() }, ..Filter::default() };
+        let checks = read_filtered("[[cmd]]\nname = 'a'\nshell = 'true'\n\n\
+                                    [[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\n\n\
+                                    [[cmd]]\nname = 'c'\nshell = 'true'\n", &filter);
+
+        let section = run(checks, Threads::One);
+        assert_eq!(names(&section), vec![ Some("a"), Some("b") ]);
+        assert!(section.check_outputs.iter().all(|o| o.passed));
+    }
+
+    #[test]
+    fn parallel_need_waits() {
+        let marker = std::env::temp_dir().join(format!("specsheet-needs-test-{}", std::process::id()));
+        let checks = read(&format!("[[cmd]]\nname = 'a'\nshell = 'sleep 0.2 && touch {0}'\n\n\
+                                    [[cmd]]\nname = 'b'\nshell = 'test -e {0}'\nneeds = ['a']\n", marker.display()));
+
+        let section = run(checks, Threads::Many(2));
+        std::fs::remove_file(&marker).unwrap();
+        assert_eq!(names(&section), vec![ Some("a"), Some("b") ]);
+        assert!(section.check_outputs.iter().all(|o| o.passed));
+    }
+
+    #[test]
+    fn parallel_failing_need_blocks() {
+        let checks = read("[[cmd]]\nname = 'a'\nshell = 'sleep 0.2; false'\nstatus = 0\n\n\
+                           [[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\n");
+
+        let section = run(checks, Threads::Many(2));
+        assert!(! section.check_outputs[0].passed);
+        assert_eq!(section.check_outputs[1].blocked_by, vec![ String::from("a") ]);
+    }
+
+    #[test]
+    fn interval_reuses_earlier_outputs() {
+        let counter = std::env::temp_dir().join(format!("specsheet-interval-test-{}", std::process::id()));
+        let mut checks = read(&format!("[[cmd]]\nname = 'a'\nshell = 'echo >> {0}'\ninterval = '300ms'\n\n\
+                                        [[cmd]]\nname = 'b'\nshell = 'true'\nneeds = ['a']\n", counter.display()));
+
+        let mut executor = Executor::new();
+        let mut run_batch = |checks: &mut CheckSet| {
+            let mut commands = Commands::from_global_options(&GlobalOptions::default()).unwrap();
+            checks.prime_commands(&mut commands);
+            checks.run_continual_batch(&mut executor, &mut commands, &mut Outputs::discard(), RunningOrder::ByType, Delay::RunInstantly, Threads::One)
+        };
+        let run_count = || std::fs::read_to_string(&counter).unwrap().lines().count();
+
+        let first = run_batch(&mut checks);
+        assert_eq!(run_count(), 1);
+
+        let second = run_batch(&mut checks);
+        assert_eq!(run_count(), 1);
+        assert_eq!(names(&second), vec![ Some("a"), Some("b") ]);
+        assert_eq!(second.check_outputs[0].runtime, first.check_outputs[0].runtime);
+        assert!(second.check_outputs[1].passed);
+        assert!(second.check_outputs[1].blocked_by.is_empty());
+
+        sleep(Duration::from_millis(300));
+        run_batch(&mut checks);
+        assert_eq!(run_count(), 2);
+
+        std::fs::remove_file(&counter).unwrap();
+    }
+
+    fn run_only_if(condition: &str) -> CheckOutput {
+        let checks = read(&format!("[[cmd]]\nshell = 'true'\nonly_if = {{ {} }}\n", condition));
+        run(checks, Threads::One).check_outputs.remove(0)
+    }
+
+    #[test]
+    fn only_if_os() {
+        assert_eq!(run_only_if(&format!("os = '{}'", std::env::consts::OS)).skipped, None);
+        assert_eq!(run_only_if("os = 'plan9'").skipped.as_deref(), Some("only runs on ‘plan9’"));
+    }
+
+    #[test]
+    fn only_if_arch() {
+        assert_eq!(run_only_if(&format!("arch = '{}'", std::env::consts::ARCH)).skipped, None);
+        assert_eq!(run_only_if("arch = 'pdp11'").skipped.as_deref(), Some("only runs on ‘pdp11’"));
+    }
+
+    #[test]
+    fn only_if_command_exists() {
+        assert_eq!(run_only_if("command_exists = 'sh'").skipped, None);
+        assert_eq!(run_only_if("command_exists = 'specsheet-missing-program'").skipped.as_deref(), Some("‘specsheet-missing-program’ is not installed"));
+    }
+
+    #[test]
+    fn only_if_env() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(run_only_if(&format!("env = {{ PATH = '{}' }}", path)).skipped, None);
+
+        let check_output = run_only_if("env = { SPECSHEET_UNSET_VARIABLE = 'yes' }");
+        assert_eq!(check_output.skipped.as_deref(), Some("‘SPECSHEET_UNSET_VARIABLE’ is not ‘yes’"));
+        assert_eq!(check_output.status(), CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn expected_and_severity() {
+        let checks = read("[[cmd]]\nshell = 'false'\nstatus = 0\nexpected = 'fail'\n\n\
+                           [[cmd]]\nshell = 'false'\nstatus = 0\nseverity = 'warn'\n");
+
+        let section = run(checks, Threads::One);
+        assert_eq!(section.check_outputs[0].status(), CheckStatus::ExpectedFailure);
+        assert_eq!(section.check_outputs[1].status(), CheckStatus::Warning);
+        assert!(! section.failed());
+    }
+
+    fn missing_shell(check_programs: bool) -> ResultsSection {
+        let global_options = GlobalOptions { map: vec![ ("cmd.shell".into(), "specsheet-missing-shell".into()) ].into_iter().collect() };
+        let mut commands = Commands::from_global_options(&global_options).unwrap();
+        commands.check_programs = check_programs;
+
+        run_with(read("[[cmd]]\nshell = 'true'\n"), commands, Threads::One)
+    }
+
+    #[test]
+    fn missing_program_skipped() {
+        let section = missing_shell(true);
+        assert_eq!(section.check_outputs[0].status(), CheckStatus::Skipped);
+        assert_eq!(section.check_outputs[0].skipped.as_deref(), Some("‘specsheet-missing-shell’ is not installed"));
+        assert_eq!(section.totals.skip_count, 1);
+        assert!(! section.failed());
+    }
+
+    #[test]
+    fn missing_program_errors_unless_checked() {
+        let section = missing_shell(false);
+        assert_eq!(section.check_outputs[0].status(), CheckStatus::Errored);
+        assert!(section.failed());
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn async_commands_run_at_once() {
+        let marker = std::env::temp_dir().join(format!("specsheet-async-test-{}", std::process::id()));
+        let mut checks = read(&format!("[[cmd]]\nname = 'a'\nshell = 'sleep 0.3 && touch {0}'\n\n\
+                                    [[cmd]]\nname = 'b'\nshell = 'sleep 0.3 && echo b'\n\n\
+                                    [[cmd]]\nname = 'c'\nshell = 'sleep 0.3 && echo c'\n\n\
+                                    [[cmd]]\nname = 'd'\nshell = 'test -e {0}'\nneeds = ['a']\n", marker.display()));
+
+        let mut commands = Commands::from_global_options(&GlobalOptions::default()).unwrap();
+        checks.prime_commands(&mut commands);
+
+        let mut executor = Executor::new();
+        executor.set_async(Some(Arc::new(spec_exec::AsyncExecutor::new(8).unwrap())));
+
+        let timer = Instant::now();
+        let section = checks.run_all(&mut executor, &mut commands, &mut Outputs::discard(), Delay::RunInstantly, Threads::One, None);
+        let elapsed = timer.elapsed();
+        std::fs::remove_file(&marker).unwrap();
+
+        assert_eq!(names(&section), vec![ Some("a"), Some("b"), Some("c"), Some("d") ]);
+        assert!(section.check_outputs.iter().all(|o| o.passed));
+        assert!(elapsed < Duration::from_millis(800), "Commands were not run at once: {:?}", elapsed);
+        assert_eq!(executor.to_commands().count(), 4);
+    }
+}
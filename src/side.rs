@@ -1,31 +1,47 @@
 use std::io;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use log::*;
 use regex::Regex;
 
+use spec_exec::{Executor, ExitReason, OutputLine, OutputStream, RunOptions, StreamEvent};
+
+use crate::doc::SideProcessRecord;
+
 
 /// The **side process** gets run in the background as the checks are run. It
 /// contains a string of shell that gets executed.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct SideProcess {
     pub shell: String,
-    pub wait: StartupWait,
+
+    /// The conditions that all have to be met before the process counts as
+    /// having started up. With none, checks start running immediately.
+    pub waits: Vec<StartupWait>,
+
+    /// How long to wait for all the conditions to be met before giving up,
+    /// if there’s a limit.
+    pub wait_timeout: Option<Duration>,
+
     pub signal: KillSignal,
+
+    /// Whether the process should be started again if it exits while the
+    /// checks are still being run.
+    pub restart: bool,
 }
 
-/// What we should do to wait for the external process to start up.
+/// Something we should wait for to know the external process has started
+/// up.
 #[derive(PartialEq, Debug, Clone)]
 pub enum StartupWait {
 
-    /// Start running checks immediately after the process starts.
-    Immediate,
-
-    /// Wait the given amount of time before starting to run checks.
+    /// Wait until the given amount of time has passed since the process
+    /// was started before starting to run checks.
     Delay(Duration),
 
     /// Continuously attempt to connect to the given TCP network port, and
@@ -41,6 +57,41 @@ pub enum StartupWait {
     OutputLine(String),
 }
 
+/// A side process that has been started, and the output it has written so
+/// far.
+#[derive(Debug)]
+pub struct RunningSideProcess {
+
+    /// The process ID, which is also the ID of its process group.
+    pub pid: u32,
+
+    /// Whether every startup condition was met before the timeout.
+    pub started_up: bool,
+
+    /// The lines the process has written, which keep getting added to as
+    /// it runs.
+    pub output: SideOutput,
+
+    /// A description of how the process exited, once it has.
+    exit: Arc<Mutex<Option<String>>>,
+}
+
+/// The **side processes** that are running while the checks get run. They
+/// get watched, so that checks run after one of them has died can say so
+/// instead of failing confusingly, and restarted if they should be.
+#[derive(Debug, Default)]
+pub struct SideProcesses {
+    directory: Option<PathBuf>,
+    running: Mutex<Vec<(SideProcess, RunningSideProcess)>>,
+}
+
+/// The lines of output of a side process, collected as it runs, from both
+/// its output streams in the order they were read.
+#[derive(Debug, Clone, Default)]
+pub struct SideOutput {
+    lines: Arc<Mutex<Vec<(OutputStream, OutputLine)>>>,
+}
+
 /// What signal we should send to the external process to get it to stop.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum KillSignal {
@@ -56,12 +107,6 @@ pub enum KillSignal {
 }
 
 
-impl Default for StartupWait {
-    fn default() -> Self {
-        Self::Immediate
-    }
-}
-
 impl Default for KillSignal {
     fn default() -> Self {
         // TODO: Some sort of ability to send TERM, then wait 10 seconds, then
@@ -73,15 +118,24 @@ impl Default for KillSignal {
 
 impl StartupWait {
 
-    /// Do the actual waiting.
-    fn wait(&self) {
+    /// Do the actual waiting, with the events of the side process in case
+    /// we need to look at its output. Returns whether the condition was met
+    /// before the deadline, if there is one.
+    fn wait(&self, events: &Receiver<StreamEvent>, started: Instant, deadline: Option<Instant>) -> bool {
+        let timed_out = || deadline.is_some_and(|d| Instant::now() >= d);
+
         match self {
-            Self::Immediate => {
-                info!("Running immediately");
-            }
             Self::Delay(duration) => {
                 info!("Delay -> {:?}", duration);
-                thread::sleep(*duration);
+                let until = started + *duration;
+
+                // A delay longer than the timeout can never be waited out.
+                if deadline.is_some_and(|d| d < until) {
+                    return false;
+                }
+
+                thread::sleep(until.saturating_duration_since(Instant::now()));
+                true
             }
             Self::Port(port) => {
                 use std::net::{SocketAddr, TcpStream, Ipv4Addr};
@@ -91,10 +145,17 @@ impl StartupWait {
 
                 loop {
                     thread::sleep(Duration::from_millis(100));
+                    if spec_exec::is_interrupted() {
+                        return true;
+                    }
+                    else if timed_out() {
+                        return false;
+                    }
+
                     match TcpStream::connect_timeout(&addr, Duration::from_millis(100)) {
                         Ok(stream) => {
                             debug!("Received response -> {:?}", stream.peer_addr());
-                            break;
+                            return true;
                         }
                         Err(e) => {
                             debug!("Connection failed -> {:?}", e);
@@ -108,9 +169,16 @@ impl StartupWait {
 
                 loop {
                     thread::sleep(Duration::from_millis(100));
+                    if spec_exec::is_interrupted() {
+                        return true;
+                    }
+                    else if timed_out() {
+                        return false;
+                    }
+
                     if path.exists() {
                         debug!("File exists");
-                        break;
+                        return true;
                     }
                     else {
                         debug!("File does not exist yet");
@@ -119,8 +187,33 @@ impl StartupWait {
                 }
             }
             Self::OutputLine(regex) => {
-                let _ = Regex::new(regex);
-                unimplemented!("Just not done yet")
+                info!("Waiting for output line -> {:?}", regex);
+                let regex = Regex::new(regex).expect("Invalid regex");
+
+                loop {
+                    let event = match deadline {
+                        Some(d) => events.recv_timeout(d.saturating_duration_since(Instant::now())),
+                        None    => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+
+                    match event {
+                        Ok(StreamEvent::Line(_, line)) => {
+                            debug!("Side process line -> {:?}", line.line);
+                            if regex.is_match(&line.line) {
+                                debug!("Line matches");
+                                return true;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(RecvTimeoutError::Timeout) => {
+                            return false;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            warn!("Side process finished without printing a matching line");
+                            return true;
+                        }
+                    }
+                }
             }
         }
     }
@@ -129,62 +222,228 @@ impl StartupWait {
 
 impl SideProcess {
 
-    /// Execute the process and return its handle.
-    pub fn start(&self) -> u32 {
-        use std::io::{BufRead, BufReader};
+    /// Execute the process and return its handle, once it has started up,
+    /// along with whether it started up in time and its output: every
+    /// startup condition has to be met before the timeout elapses, if there
+    /// is one. The process is run from the given directory, if there is one,
+    /// or the current directory if not.
+    ///
+    /// It gets run by an executor of its own, in a process group of its own
+    /// with the same ID as the process, so it and everything it starts can
+    /// be stopped together. Its output gets streamed back from there as it
+    /// runs, which is how we know when it has printed a certain line, and
+    /// gets kept so it can be shown if any checks fail.
+    pub fn start(&self, directory: Option<&Path>) -> RunningSideProcess {
+        self.start_with_output(directory, SideOutput::default())
+    }
 
+    /// Execute the process, like `start`, adding the lines it writes to
+    /// the given output.
+    fn start_with_output(&self, directory: Option<&Path>, output: SideOutput) -> RunningSideProcess {
         debug!("Spawning side process -> {:?}", self.shell);
 
-        let (tx, rx) = mpsc::channel();
+        let (tx, events) = mpsc::channel();
+        let (forward_tx, rx) = mpsc::channel();
+        let exit = Arc::new(Mutex::new(None));
 
+        let mut command = Command::new("bash");
+        command.arg("-c").arg(&self.shell);
+
+        let mut executor = Executor::new();
+        executor.set_working_directory(directory.map(Path::to_path_buf));
+
+        let exited = Arc::clone(&exit);
         let builder = thread::Builder::new().name("side process thread".into());
-        let shell = self.shell.clone();
-        let wait = self.wait.clone();
         builder.spawn(move || {
-            let cmd = Command::new("bash")
-                .arg("-c")
-                .arg(&shell)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .expect("Failed to execute child");
-
-
-            wait.wait();
-            tx.send(cmd.id()).expect("Sending tx");
-
-            let reader = BufReader::new(cmd.stdout.expect("Process stdout"));
-            for line in reader.lines() {
-                let line = line.expect("Line IO error");
-                debug!("Child line -> {:?}", line);
+            let description = match executor.run_and_stream(command, &RunOptions::default(), tx) {
+                Ok(rc) => {
+                    debug!("Side process exited -> {:?}", rc.exit_reason);
+                    match rc.exit_reason {
+                        ExitReason::Status(status)  => format!("exited with status {}", status),
+                        ExitReason::Signal(signal)  => format!("was killed by signal {}", signal),
+                        _                           => String::from("exited"),
+                    }
+                }
+                Err(e) => {
+                    warn!("Side process failed -> {}", e);
+                    format!("failed: {}", e)
+                }
+            };
+
+            *exited.lock().unwrap_or_else(PoisonError::into_inner) = Some(description);
+        }).expect("spawn");
+
+        // Every line gets collected for as long as the process runs, and
+        // passed on for the startup conditions to look at, until they have
+        // all been met.
+        let collected = output.clone();
+        let builder = thread::Builder::new().name("side process output thread".into());
+        builder.spawn(move || {
+            for event in events {
+                if let StreamEvent::Line(stream, line) = &event {
+                    collected.push(*stream, line.clone());
+                }
+
+                let _ = forward_tx.send(event);
             }
         }).expect("spawn");
 
-        rx.recv().expect("Receiving rx")
+        let Ok(StreamEvent::Started(pid)) = rx.recv() else {
+            panic!("Failed to execute side process");
+        };
+
+        let started = Instant::now();
+        let deadline = self.wait_timeout.map(|timeout| started + timeout);
+
+        if self.waits.is_empty() {
+            info!("Running immediately");
+        }
+
+        for wait in &self.waits {
+            if ! wait.wait(&rx, started, deadline) {
+                warn!("Side process did not start up in time -> {:?}", wait);
+                return RunningSideProcess { pid, started_up: false, output, exit };
+            }
+        }
+
+        RunningSideProcess { pid, started_up: true, output, exit }
     }
 
-    /// Given a handle that was started earlier, kill it.
+    /// Given a handle that was started earlier, kill it, along with any
+    /// processes it has started.
     pub fn stop(&self, child_pid: u32) -> io::Result<()> {
         debug!("Stopping side process with ID -> {}", child_pid);
 
         // This needs unsafe because it’s a libc function. Killing processes
-        // does exist in std, but only for SIGKILL.
+        // does exist in std, but only for SIGKILL, and only for a single
+        // process rather than a process group.
         let ret_val = unsafe {
-            libc::kill(child_pid as i32, self.signal.number())
+            libc::kill(-(child_pid as i32), self.signal.number())
         };
 
-        // According to the man page, `kill` returns 0 on success.
+        // According to the man page, `kill` returns 0 on success. If there
+        // was nothing left to kill, such as when specsheet was interrupted,
+        // then there’s nothing left to do.
         if ret_val == 0 {
             debug!("Process dead.");
             Ok(())
         }
         else {
-            warn!("Stopping side process failed");
-            Err(io::Error::last_os_error())
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() == Some(libc::ESRCH) {
+                debug!("Process already dead.");
+                Ok(())
+            }
+            else {
+                warn!("Stopping side process failed");
+                Err(error)
+            }
         }
     }
 }
 
+impl RunningSideProcess {
+
+    /// Returns a description of how the process exited, if it has.
+    fn exit(&self) -> Option<String> {
+        self.exit.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+
+impl SideProcesses {
+
+    /// Creates a new, empty set of side processes, which get run from the
+    /// given directory, if there is one, or the current directory if not.
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self { directory, running: Mutex::default() }
+    }
+
+    /// Starts the given process, returning whether it started up in time.
+    pub fn start(&self, process: &SideProcess) -> bool {
+        let running = process.start(self.directory.as_deref());
+        debug!("Process started -> {}", running.pid);
+        let started_up = running.started_up;
+
+        let mut processes = self.lock();
+        processes.push((process.clone(), running));
+        track(&processes);
+        started_up
+    }
+
+    /// Checks that every side process is still running, restarting the ones
+    /// that have died and should be restarted. If one has died and can’t be
+    /// restarted, returns a description of what happened to it.
+    pub fn check_health(&self) -> Option<String> {
+        let mut processes = self.lock();
+        let mut problem = None;
+
+        for (process, running) in processes.iter_mut() {
+            let Some(exit) = running.exit() else {
+                continue;
+            };
+
+            if ! process.restart {
+                problem = Some(format!("side process ‘{}’ {}", process.shell, exit));
+                break;
+            }
+
+            warn!("Side process {} -> restarting it", exit);
+            *running = process.start_with_output(self.directory.as_deref(), running.output.clone());
+
+            if ! running.started_up {
+                problem = Some(format!("side process ‘{}’ {}, and did not start up again in time", process.shell, exit));
+                break;
+            }
+        }
+
+        track(&processes);
+        problem
+    }
+
+    /// Stops every side process, most recently started first.
+    pub fn stop_all(&self) {
+        let processes = self.lock();
+
+        for (process, running) in processes.iter().rev() {
+            process.stop(running.pid).expect("stop");
+        }
+
+        crate::signals::track_side_processes(&[]);
+    }
+
+    /// Returns everything each side process has written so far, ready to
+    /// be printed or written to result documents.
+    pub fn records(&self) -> Vec<SideProcessRecord> {
+        self.lock().iter()
+            .map(|(process, running)| SideProcessRecord::new(&process.shell, &running.output.lines()))
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(SideProcess, RunningSideProcess)>> {
+        self.running.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Records the process groups of the running side processes, so they get
+/// killed if specsheet has to exit straight away.
+fn track(processes: &[(SideProcess, RunningSideProcess)]) {
+    let pids = processes.iter().map(|(_, running)| running.pid).collect::<Vec<_>>();
+    crate::signals::track_side_processes(&pids);
+}
+
+
+impl SideOutput {
+    fn push(&self, stream: OutputStream, line: OutputLine) {
+        self.lines.lock().unwrap_or_else(PoisonError::into_inner).push((stream, line));
+    }
+
+    /// Returns every line the process has written so far.
+    pub fn lines(&self) -> Vec<(OutputStream, OutputLine)> {
+        self.lines.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
 impl KillSignal {
     fn number(self) -> libc::c_int {
         match self {
@@ -0,0 +1,155 @@
+//! Handling the signals that interrupt specsheet, such as Ctrl-C.
+//!
+//! Commands get run in process groups of their own, so the signals sent
+//! by the terminal only reach specsheet. The first signal stops the run:
+//! every running command gets killed, no more checks get run, and the
+//! results so far get written out as usual. A second signal gives up on
+//! that, kills the side processes, and exits straight away.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::FromRawFd;
+use std::process::exit;
+use std::ptr;
+use std::sync::{Mutex, PoisonError};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+use log::*;
+
+use crate::exits;
+
+
+/// The process groups of the side processes that are running, in the order
+/// they were started.
+static SIDE_PROCESS_GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// The file descriptor of the end of the pipe that the signal handler
+/// writes the number of each signal it receives to.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// The signals that interrupt specsheet.
+const SIGNALS: [libc::c_int; 3] = [ libc::SIGINT, libc::SIGTERM, libc::SIGHUP ];
+
+
+/// Starts a thread that waits for specsheet to be interrupted.
+///
+/// The signal handler itself only writes the signal to a pipe, which the
+/// thread reads from, so the thread can do whatever it likes when one
+/// arrives. Signals don’t get blocked anywhere, as processes inherit the
+/// signal mask of the thread that starts them, and the commands and side
+/// processes that get run need to be able to receive them.
+pub fn install_handler() -> io::Result<()> {
+    let mut fds = [0; 2];
+
+    // This needs unsafe because it’s a libc function. Neither end of the
+    // pipe should be inherited by the processes that get run.
+    let ret_val = unsafe {
+        libc::pipe(fds.as_mut_ptr())
+    };
+
+    if ret_val != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    for fd in &fds {
+        // This needs unsafe because it’s a libc function.
+        let ret_val = unsafe {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC)
+        };
+
+        if ret_val != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    SIGNAL_PIPE.store(fds[1], Ordering::SeqCst);
+
+    // This needs unsafe because it takes ownership of a file descriptor,
+    // which nothing else uses.
+    let mut reader = unsafe { File::from_raw_fd(fds[0]) };
+
+    for signal in &SIGNALS {
+        // This needs unsafe because these are libc functions. The handler
+        // only does things that are safe to do in a signal handler.
+        let ret_val = unsafe {
+            let handler: extern "C" fn(libc::c_int) = handle_signal;
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(ptr::addr_of_mut!(action.sa_mask));
+            libc::sigaction(*signal, ptr::addr_of!(action), ptr::null_mut())
+        };
+
+        if ret_val != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    thread::Builder::new().name("signal thread".into()).spawn(move || {
+        let mut interrupted = false;
+
+        loop {
+            let mut signal = [0_u8];
+            if let Err(e) = reader.read_exact(&mut signal) {
+                warn!("Error waiting for signals: {}", e);
+                return;
+            }
+
+            if interrupted {
+                eprintln!("Interrupted again; exiting.");
+                kill_side_processes();
+                exit(exits::INTERRUPTED);
+            }
+
+            info!("Received signal -> {}", signal[0]);
+            eprintln!("Interrupted; stopping checks. Interrupt again to exit immediately.");
+            interrupted = true;
+            spec_exec::interrupt();
+        }
+    })?;
+
+    Ok(())
+}
+
+/// The signal handler, which passes the signal on to the signal thread.
+extern "C" fn handle_signal(signal: libc::c_int) {
+    let byte = u8::try_from(signal).unwrap_or(u8::MAX);
+
+    // This needs unsafe because it’s a libc function. Writing to a pipe is
+    // one of the few things that are safe to do in a signal handler.
+    unsafe {
+        libc::write(SIGNAL_PIPE.load(Ordering::SeqCst), ptr::addr_of!(byte).cast(), 1);
+    }
+}
+
+/// Records the process groups of the side processes that are running, so
+/// they get killed if specsheet has to exit straight away.
+pub fn track_side_processes(groups: &[u32]) {
+    let mut tracked = SIDE_PROCESS_GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    tracked.clear();
+    tracked.extend_from_slice(groups);
+}
+
+/// Kills every process in the process group of each side process that is
+/// running, most recently started first.
+fn kill_side_processes() {
+    let tracked = SIDE_PROCESS_GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    for group in tracked.iter().rev() {
+        let Ok(group) = libc::pid_t::try_from(*group) else {
+            continue;
+        };
+
+        // This needs unsafe because it’s a libc function. A negative
+        // process ID means the whole process group.
+        let ret_val = unsafe {
+            libc::kill(-group, libc::SIGKILL)
+        };
+
+        if ret_val != 0 {
+            warn!("Error killing side process: {}", io::Error::last_os_error());
+        }
+    }
+}
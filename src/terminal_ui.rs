@@ -1,16 +1,25 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::time::Duration;
+
 use ansi_term::{Style, Colour::*};
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Regex, Captures};
 
 use spec_checks::{Check, CheckResult, PassResult, FailResult};
+use spec_exec::RanCommand;
 
+use crate::doc::{SideProcessRecord, CompletedSection};
+use crate::history::CheckTrend;
 use crate::input::{InputSource, LoadError};
-use crate::results::Stats;
+use crate::results::{Stats, RunDetails, HookFailure, Expectation, Severity, CheckOutput, CheckStatus, ResultMessage, CorrelationRecord, FlakyRecord};
 use crate::set::ReadError;
 
 
-/// The **terminal UI** handles printing stuff to the screen as
-/// specsheet executes.
+/// The **terminal UI** handles printing stuff to the screen (or to a file
+/// of output) as specsheet executes.
 #[derive(PartialEq, Debug)]
 pub struct TerminalUI {
     pub colours: Colours,
@@ -24,6 +33,58 @@ pub struct ShownLines {
     pub successes: ExpandLevel,
     pub failures:  ExpandLevel,
     pub summaries: ExpandLevel,
+
+    /// Whether to show how long each check took, and which were slowest.
+    pub timings: bool,
+
+    /// Whether to show the commands each check ran, and how long each
+    /// one took, underneath it.
+    pub commands: bool,
+
+    /// How to group the checks, if they should be printed in groups once
+    /// they have all been run, rather than as they get run.
+    pub group_by: Option<GroupBy>,
+
+    /// How to sort the checks within each group, if they should be.
+    pub sort_by: Option<SortBy>,
+}
+
+/// What to put checks in the same group by.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum GroupBy {
+
+    /// Group checks of the same type, such as `cmd` or `http`.
+    Type,
+
+    /// Group checks with the same tag. A check with several tags appears
+    /// in the group of each one.
+    Tag,
+
+    /// Group checks from the same input file.
+    File,
+}
+
+/// What to sort checks by.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SortBy {
+
+    /// Sort checks by their names or descriptions, alphabetically.
+    Name,
+
+    /// Sort checks by how long they took, slowest first.
+    Duration,
+
+    /// Sort checks by what happened to them, failures first.
+    Status,
+}
+
+impl ShownLines {
+
+    /// Whether checks should be held back and printed once they have all
+    /// been run, because they get grouped or sorted.
+    pub fn holds_checks(self) -> bool {
+        self.group_by.is_some() || self.sort_by.is_some()
+    }
 }
 
 /// Whether to show individual Pass/Fail results in the output.
@@ -44,101 +105,543 @@ impl TerminalUI {
 
     /// Print a new section based on the path to the file of checks
     /// being run. This gets executed at the start of each file.
-    pub fn print_file_section(&self, input_stream: &InputSource) {
-        println!("\n   {}", self.colours.file_heading.paint(&input_stream.to_string()));
+    pub fn print_file_section(&self, w: &mut dyn Write, input_stream: &InputSource) -> io::Result<()> {
+        writeln!(w, "\n   {}", self.colours.file_heading.paint(&input_stream.to_string()))?;
+        Ok(())
     }
 
     /// Prints an errors that occurred while loading a file of checks.
-    pub fn print_load_error(&self, input: &InputSource, e: LoadError) {
+    pub fn print_load_error(&self, w: &mut dyn Write, input: &InputSource, e: &LoadError) -> io::Result<()> {
         match e {
             // For the first two, show the path so the terminal can
             // linkify it. Also it makes it seem more “official”.
             LoadError::Io(ioe) => {
-                println!(" {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("error:"), input, ioe);
+                writeln!(w, " {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("error:"), input, ioe)?;
             }
             LoadError::Toml(te) => {
                 if let Some((line, col)) = te.line_col() {
-                    println!(" {} {} {}:{}:{}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, line + 1, col, te);
+                    writeln!(w, " {} {} {}:{}:{}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, line + 1, col, te)?;
                 }
                 else {
-                    println!(" {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, te);
+                    writeln!(w, " {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, te)?;
                 }
             }
+            LoadError::Yaml(ye) => {
+                if let Some(location) = ye.location() {
+                    writeln!(w, " {} {} {}:{}:{}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, location.line(), location.column(), ye)?;
+                }
+                else {
+                    writeln!(w, " {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, ye)?;
+                }
+            }
+            LoadError::Json(je) => {
+                writeln!(w, " {} {} {}:{}:{}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("parse error:"), input, je.line(), je.column(), je)?;
+            }
+            LoadError::Include(path, inner) => {
+                self.print_load_error(w, &InputSource::File(path.clone()), inner)?;
+            }
+            LoadError::InvalidInclude(..) | LoadError::IncludeCycle(_) | LoadError::NoDocumentsInDirectory => {
+                writeln!(w, " {} {} {}: {}", self.colours.question_sub.paint("?"), self.colours.error.paint("error:"), input, e)?;
+            }
         }
+
+        Ok(())
     }
 
-    pub fn print_read_errors(&self, es: &[ReadError]) {
-        // We don’t need to show the path here. Read errors are the most
-        // common type of error a user will encounter, and they’re printed at
-        // the top of the section so the path is right there, and we don’t
-        // have a line/column number.
+    pub fn print_read_errors(&self, w: &mut dyn Write, es: &[ReadError]) -> io::Result<()> {
+        // Read errors are the most common type of error a user will
+        // encounter, so they get printed with the file, line, and column
+        // they’re at, the way compilers print them, for editors to jump to.
 
         for err in es {
-            println!(" {} {} {} {}", self.colours.question_sub.paint("?"), self.colours.error.paint("read error:"), self.colours.question_sub.paint(&format!("[{}]", err.name)), err.inner);
+            let at = match (&err.file, err.position) {
+                (Some(file), Some(position))  => format!(" {}:{}:", file.display(), position),
+                (None, Some(position))        => format!(" {}:", position),
+                (Some(file), None)            => format!(" {}:", file.display()),
+                (None, None)                  => String::new(),
+            };
+
+            writeln!(w, " {} {}{} {} {}", self.colours.question_sub.paint("?"), self.colours.error.paint("read error:"), at, self.colours.question_sub.paint(&format!("[{}]", err.name)), err.inner)?;
         }
+
+        Ok(())
     }
 
     /// Print an individual check and its results to the screen. This
     /// gets executed after a check has been run.
-    pub fn print_check(&self, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>]) {
+    pub fn print_check(&self, w: &mut dyn Write, check: &impl Check, name: Option<&String>, results: &[CheckResult<impl PassResult, impl FailResult>], details: RunDetails<'_>) -> io::Result<()> {
 
         // Make text in ‘single smart quotes’ bold for the terminal
         let check = name.cloned().unwrap_or_else(|| check.to_string());
-        let check = SMART_QUOTES.replace_all(&check, "\x1B[1m$1\x1b[0m");
+        let check = SMART_QUOTES.replace_all(&check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
 
         let passed = results.iter().all(CheckResult::passed);
 
+        let timing = if self.shown_lines.timings {
+            self.colours.timing.paint(format!(" ({})", format_runtime(details.runtime))).to_string()
+        }
+        else {
+            String::new()
+        };
+
+        // Going over budget doesn’t fail the check, but it should still be
+        // visible next to it.
+        let over_budget = match details.budget {
+            Some(b) if b.over_budget => {
+                let text = format!(" (over budget: took {}ms, budget {}ms)", b.taken_ms, b.budget_ms);
+                self.colours.over_budget.paint(text).to_string()
+            }
+            _ => String::new(),
+        };
+
+        // A check that only passed after being retried is worth knowing
+        // about, as is one that kept failing.
+        let retried = match details.attempts {
+            0 | 1        => String::new(),
+            n if passed  => self.colours.retried.paint(format!(" (passed on attempt {})", n)).to_string(),
+            n            => self.colours.retried.paint(format!(" (failed after {} attempts)", n)).to_string(),
+        };
+
         if passed {
             if self.shown_lines.successes == ExpandLevel::Hide {
-                return;
+                return Ok(());
             }
 
-            println!(" {} {}", self.colours.tick.paint("✔"), check);
+            // A check that passes despite a known issue should have its
+            // annotation removed, so nag about it. The same goes for a check
+            // that was expected to fail.
+            let resolved = match (details.known_issue, details.expected) {
+                (Some(issue), _)              => self.colours.known_issue.paint(format!(" (known issue ‘{}’ no longer fails)", issue)).to_string(),
+                (None, Expectation::Fail)     => self.colours.known_issue.paint(" (expected to fail, but passed)").to_string(),
+                (None, Expectation::Pass)     => String::new(),
+            };
+
+            writeln!(w, " {} {}{}{}{}{}", self.colours.tick.paint("✔"), check, timing, retried, over_budget, resolved)?;
         }
         else {
             if self.shown_lines.failures == ExpandLevel::Hide {
-                return;
+                return Ok(());
             }
 
-            println!(" {} {}", self.colours.cross.paint("✘"), check);
+            // A check that couldn’t be run properly didn’t find anything
+            // wrong, so it gets marked differently from a failure.
+            let errored = ! results.iter().any(|result| matches!(result, CheckResult::Failed(_)));
+
+            if errored {
+                writeln!(w, " {} {}{}{}{}", self.colours.question.paint("?"), check, timing, retried, over_budget)?;
+            }
+            else if details.expected == Expectation::Fail {
+                let note = self.colours.known_issue.paint(" (failed as expected)");
+                writeln!(w, " {} {}{}{}{}{}", self.colours.known_issue.paint("✘"), check, timing, retried, over_budget, note)?;
+            }
+            else if details.severity == Severity::Warn {
+                let note = self.colours.warning.paint(" (warning)");
+                writeln!(w, " {} {}{}{}{}{}", self.colours.warning.paint("✘"), check, timing, retried, over_budget, note)?;
+            }
+            else if let Some(issue) = details.known_issue {
+                let note = self.colours.known_issue.paint(format!(" (known issue ‘{}’)", issue));
+                writeln!(w, " {} {}{}{}{}{}", self.colours.known_issue.paint("✘"), check, timing, retried, over_budget, note)?;
+            }
+            else {
+                writeln!(w, " {} {}{}{}{}", self.colours.cross.paint("✘"), check, timing, retried, over_budget)?;
+            }
+        }
+
+        if self.shown_lines.commands {
+            for command in details.commands {
+                self.print_command(w, command)?;
+            }
         }
 
         for result in results {
             if passed {
                 if self.shown_lines.successes == ExpandLevel::Expanded {
-                    self.print_result(&result);
-                    self.print_output(&result);
+                    self.print_result(w, &result)?;
+                    self.print_output(w, &result)?;
                 }
             }
             else {
                 if self.shown_lines.failures == ExpandLevel::Expanded {
-                    self.print_result(&result);
-                    self.print_output(&result);
+                    self.print_result(w, &result)?;
+                    self.print_output(w, &result)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print a check that got skipped rather than run, along with the reason
+    /// why. Skipped checks get shown whenever failed ones do, as they are
+    /// something that probably needs looking at.
+    pub fn print_skipped(&self, w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, reason: &str) -> io::Result<()> {
+        if self.shown_lines.failures == ExpandLevel::Hide {
+            return Ok(());
+        }
+
+        let check = name.cloned().unwrap_or_else(|| check.to_string());
+        let check = SMART_QUOTES.replace_all(&check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+
+        let note = self.colours.skipped.paint(format!(" (skipped: {})", reason));
+        writeln!(w, " {} {}{}", self.colours.skipped.paint("-"), check, note)
+    }
+
+    /// Print a check that failed without being run, because the environment
+    /// it gets run in has died.
+    pub fn print_environment_error(&self, w: &mut dyn Write, check: &impl fmt::Display, name: Option<&String>, error: &str) -> io::Result<()> {
+        if self.shown_lines.failures == ExpandLevel::Hide {
+            return Ok(());
+        }
+
+        let check = name.cloned().unwrap_or_else(|| check.to_string());
+        let check = SMART_QUOTES.replace_all(&check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+
+        writeln!(w, " {} {} {}", self.colours.question.paint("?"), check, self.colours.error.paint(format!("(environment died: {})", error)))
+    }
+
+    /// Print a setup or teardown command that failed, either one of the
+    /// file’s own, or one that belongs to a check.
+    pub fn print_hook_failure(&self, w: &mut dyn Write, failure: &HookFailure) -> io::Result<()> {
+        if self.shown_lines.failures == ExpandLevel::Hide {
+            return Ok(());
+        }
+
+        let hook = format!("{} ‘{}’ failed", failure.stage, failure.shell);
+
+        match &failure.check {
+            Some(check) => {
+                let check = SMART_QUOTES.replace_all(check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+                writeln!(w, " {} {} {}", self.colours.cross.paint("✘"), check, self.colours.error.paint(format!("({}: {})", hook, failure.error)))
+            }
+            None => {
+                writeln!(w, " {} {} {}", self.colours.cross.paint("✘"), self.colours.error.paint(format!("{}:", hook)), failure.error)
+            }
+        }
+    }
+
+    /// Prints everything a side process wrote while the checks were being
+    /// run, which can explain why some of them failed.
+    pub fn print_side_output(&self, w: &mut dyn Write, side_process: &SideProcessRecord) -> io::Result<()> {
+        writeln!(w, "\n   {}", self.colours.output_heading.paint(format!("Side process output: {}", side_process.shell)))?;
+
+        for line in &side_process.output {
+            if line.stream == "stderr" {
+                writeln!(w, "     {}", self.colours.cross_sub.paint(line.line.escape_default().to_string()))?;
+            }
+            else {
+                writeln!(w, "     {}", line.line.escape_default())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the properties that only the failed checks in a file had in
+    /// common, such as every check involving one host failing, which could
+    /// point to what caused them to fail.
+    pub fn print_correlations(&self, w: &mut dyn Write, correlations: &[CorrelationRecord]) -> io::Result<()> {
+        if correlations.is_empty() {
+            writeln!(w, "   No correlations detected")?;
+            return Ok(());
+        }
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint("Analysis"))?;
+
+        for correlation in correlations {
+            let text = correlation.to_string();
+            let text = SMART_QUOTES.replace_all(&text, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+            writeln!(w, "   {} {}", self.colours.cross.paint("-"), text)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the checks whose results flipped between batches, along with
+    /// each different set of results they had, so it’s clear which checks
+    /// can’t be relied on.
+    pub fn print_flaky_checks(&self, w: &mut dyn Write, flaky_checks: &[FlakyRecord]) -> io::Result<()> {
+        if flaky_checks.is_empty() {
+            writeln!(w, "   No flaky checks detected")?;
+            return Ok(());
+        }
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint("Flaky checks"))?;
+
+        for flaky in flaky_checks {
+            let text = format!("{} ({})", flaky, flaky.input);
+            let text = SMART_QUOTES.replace_all(&text, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+            writeln!(w, "   {} {}", self.colours.warning.paint("~"), text)?;
+
+            for output in &flaky.outputs {
+                let symbol = if output.passed { self.colours.tick_sub.paint("✔") } else { self.colours.cross_sub.paint("✘") };
+                writeln!(w, "     {} {}", symbol, output.results.join("; "))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the checks that have been changing over the runs in the
+    /// history directory, such as ones that started failing or got slower.
+    pub fn print_trends(&self, w: &mut dyn Write, trends: &[CheckTrend]) -> io::Result<()> {
+        if trends.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint("Trends"))?;
+
+        for trend in trends {
+            let check = SMART_QUOTES.replace_all(&trend.check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+            let symbol = if trend.failing_since.is_some() { self.colours.cross.paint("✘") } else { self.colours.warning.paint("~") };
+            writeln!(w, " {} {} ({}): {}", symbol, check, trend.input, trend.notes().join("; "))?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the checks that took the longest to run, slowest first, so
+    /// it’s clear where the time in a slow run went.
+    pub fn print_slowest_checks(&self, w: &mut dyn Write, checks: &[&CheckOutput]) -> io::Result<()> {
+        if ! self.shown_lines.timings || checks.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint("Slowest checks"))?;
+
+        for check in checks {
+            let description = check.name.as_ref().unwrap_or(&check.message);
+            let description = SMART_QUOTES.replace_all(description, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+            let runtime = format!("{:>8}", format_runtime(check.runtime));
+            writeln!(w, "   {} {}", self.colours.timing.paint(runtime), description)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the checks that have been held back, in their groups, each
+    /// followed by its subtotals. Checks that only get sorted, without
+    /// being grouped, stay in the groups of the files they are in.
+    pub fn print_held_checks(&self, w: &mut dyn Write, held: &mut HeldChecks) -> io::Result<()> {
+        let HeldChecks { mut checks, totals, .. } = mem::take(held);
+
+        match self.shown_lines.sort_by {
+            Some(SortBy::Name)      => checks.sort_by_cached_key(|check| check.description.to_lowercase()),
+            Some(SortBy::Duration)  => checks.sort_by_key(|check| Reverse(check.runtime)),
+            Some(SortBy::Status)    => checks.sort_by_key(|check| status_rank(check.status)),
+            None                    => {}
+        }
+
+        let group_by = self.shown_lines.group_by.unwrap_or(GroupBy::File);
+        let mut groups: Vec<(Option<&str>, Vec<&HeldCheck>)> = Vec::new();
+
+        for check in &checks {
+            let keys = match group_by {
+                GroupBy::Type  => vec![ Some(check.check_type) ],
+                GroupBy::File  => vec![ check.file.as_deref() ],
+                GroupBy::Tag if check.tags.is_empty()  => vec![ None ],
+                GroupBy::Tag   => check.tags.iter().map(|tag| Some(tag.as_str())).collect(),
+            };
+
+            for key in keys {
+                match groups.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, members))  => members.push(check),
+                    None                => groups.push((key, vec![ check ])),
                 }
             }
         }
+
+        // Files stay in the order they were run in, but types and tags are
+        // easier to find in alphabetical order, with the untagged ones last.
+        if group_by != GroupBy::File {
+            groups.sort_by_key(|(key, _)| (key.is_none(), *key));
+        }
+
+        for (key, members) in &groups {
+            let heading = match (group_by, key) {
+                (GroupBy::Type, Some(check_type))  => Some(format!("{} checks", check_type)),
+                (GroupBy::Tag,  Some(tag))         => Some(format!("Tagged ‘{}’", tag)),
+                (GroupBy::Tag,  None)              => Some(String::from("Untagged")),
+                (GroupBy::File, Some(file))        => Some(String::from(*file)),
+                _                                  => None,
+            };
+
+            if let Some(heading) = heading {
+                writeln!(w, "\n   {}", self.colours.file_heading.paint(heading))?;
+            }
+
+            let mut subtotals = Stats::default();
+            for check in members {
+                w.write_all(&check.lines)?;
+                subtotals += check.stats;
+            }
+
+            self.print_stats(w, subtotals)?;
+        }
+
+        if let Some(totals) = totals.filter(|_| groups.len() > 1) {
+            writeln!(w, "\n   {}", self.colours.file_heading.paint("Totals"))?;
+            self.print_stats(w, totals)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints every check that failed once all the files of checks have
+    /// been run, so they can be found without scrolling back through a
+    /// long run. Each one gets shown with its first failing result, or
+    /// with all of them if the summaries are expanded.
+    pub fn print_failure_recap(&self, w: &mut dyn Write, sections: &[CompletedSection]) -> io::Result<()> {
+        if self.shown_lines.summaries == ExpandLevel::Hide {
+            return Ok(());
+        }
+
+        let failures = sections.iter()
+            .flat_map(|section| section.results.check_outputs.iter().map(move |check_output| (&section.input, check_output)))
+            .filter(|(_, check_output)| matches!(check_output.status(), CheckStatus::Failed | CheckStatus::Errored | CheckStatus::UnexpectedPass))
+            .collect::<Vec<_>>();
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint(format!("Failures ({})", failures.len())))?;
+
+        for (input, check_output) in failures {
+            let check = check_output.name.as_ref().unwrap_or(&check_output.message);
+            let check = SMART_QUOTES.replace_all(check, |caps: &Captures<'_>| self.colours.quoted.paint(&caps[1]).to_string());
+
+            let symbol = if check_output.status() == CheckStatus::Errored { self.colours.question.paint("?") }
+                                                                          else { self.colours.cross.paint("✘") };
+
+            if input.is_file() {
+                writeln!(w, " {} {}: {}", symbol, input, check)?;
+            }
+            else {
+                writeln!(w, " {} {}", symbol, check)?;
+            }
+
+            let failing_results = check_output.results.iter()
+                .filter(|result| ! matches!(result, ResultMessage::Passed(_)))
+                .take(if self.shown_lines.summaries == ExpandLevel::Expanded { usize::MAX } else { 1 });
+
+            for result in failing_results {
+                match result {
+                    ResultMessage::Failed(message)  => writeln!(w, "   {} {}", self.colours.cross_sub.paint("✘"), message)?,
+                    ResultMessage::Error(message)   => writeln!(w, "   {} {}", self.colours.question_sub.paint("?"), message)?,
+                    ResultMessage::Passed(_)        => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the totals across every batch of checks run in continual
+    /// mode, once it stops.
+    pub fn print_batch_totals(&self, w: &mut dyn Write, batch_count: u64, stats: Stats) -> io::Result<()> {
+        let heading = if batch_count == 1 {
+            String::from("Totals for 1 batch")
+        }
+        else {
+            format!("Totals for {} batches", batch_count)
+        };
+
+        writeln!(w, "\n   {}", self.colours.file_heading.paint(heading))?;
+        self.print_stats(w, stats)
     }
 
     /// Prints the number of successes and failures to the screen.
     /// This gets called after a file of checks has been run, and
     /// their totals tallied up.
-    pub fn print_stats(&self, stats: Stats) {
+    pub fn print_stats(&self, w: &mut dyn Write, stats: Stats) -> io::Result<()> {
         let successes = stats.pass_count;
-        let failed = stats.fail_count;
+        let failed = stats.fail_count + stats.err_count;
 
         let total = successes + failed;
 
         if self.shown_lines.summaries != ExpandLevel::Hide {
             if total == 0 {
-                println!("   {}", self.colours.zero.paint(format!("{}/{} successful", successes, total)))
+                writeln!(w, "   {}", self.colours.zero.paint(format!("{}/{} successful", successes, total)))?;
             }
             else if failed == 0 {
-                println!("   {}/{} successful", successes, total)
+                writeln!(w, "   {}/{} successful", successes, total)?;
             }
             else {
-                println!("   {}", self.colours.cross.paint(format!("{}/{} successful", successes, total)))
+                writeln!(w, "   {}", self.colours.cross.paint(format!("{}/{} successful", successes, total)))?;
+            }
+
+            if stats.err_count == 1 {
+                writeln!(w, "   {}", self.colours.question.paint("1 check errored"))?;
+            }
+            else if stats.err_count > 1 {
+                writeln!(w, "   {}", self.colours.question.paint(format!("{} checks errored", stats.err_count)))?;
+            }
+
+            if stats.known_failure_count == 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint("1 failure is a known issue"))?;
+            }
+            else if stats.known_failure_count > 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint(format!("{} failures are known issues", stats.known_failure_count)))?;
+            }
+
+            if stats.resolved_issue_count == 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint("1 known issue no longer fails"))?;
+            }
+            else if stats.resolved_issue_count > 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint(format!("{} known issues no longer fail", stats.resolved_issue_count)))?;
+            }
+
+            if stats.over_budget_count == 1 {
+                writeln!(w, "   {}", self.colours.over_budget.paint("1 check over budget"))?;
+            }
+            else if stats.over_budget_count > 1 {
+                writeln!(w, "   {}", self.colours.over_budget.paint(format!("{} checks over budget", stats.over_budget_count)))?;
+            }
+
+            if stats.warning_count == 1 {
+                writeln!(w, "   {}", self.colours.warning.paint("1 warning"))?;
+            }
+            else if stats.warning_count > 1 {
+                writeln!(w, "   {}", self.colours.warning.paint(format!("{} warnings", stats.warning_count)))?;
+            }
+
+            if stats.expected_failure_count == 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint("1 check failed as expected"))?;
+            }
+            else if stats.expected_failure_count > 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint(format!("{} checks failed as expected", stats.expected_failure_count)))?;
+            }
+
+            if stats.unexpected_pass_count == 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint("1 check expected to fail passed"))?;
+            }
+            else if stats.unexpected_pass_count > 1 {
+                writeln!(w, "   {}", self.colours.known_issue.paint(format!("{} checks expected to fail passed", stats.unexpected_pass_count)))?;
+            }
+
+            if stats.blocked_count == 1 {
+                writeln!(w, "   {}", self.colours.skipped.paint("1 check not run because of the checks it needs"))?;
+            }
+            else if stats.blocked_count > 1 {
+                writeln!(w, "   {}", self.colours.skipped.paint(format!("{} checks not run because of the checks they need", stats.blocked_count)))?;
+            }
+
+            if stats.hook_failure_count == 1 {
+                writeln!(w, "   {}", self.colours.cross.paint("1 setup or teardown command failed"))?;
+            }
+            else if stats.hook_failure_count > 1 {
+                writeln!(w, "   {}", self.colours.cross.paint(format!("{} setup or teardown commands failed", stats.hook_failure_count)))?;
+            }
+
+            if stats.skip_count == 1 {
+                writeln!(w, "   {}", self.colours.skipped.paint("1 check skipped"))?;
+            }
+            else if stats.skip_count > 1 {
+                writeln!(w, "   {}", self.colours.skipped.paint(format!("{} checks skipped", stats.skip_count)))?;
             }
         }
+
+        Ok(())
     }
 }
 
@@ -146,60 +649,170 @@ impl TerminalUI {
 
     /// Prints an individual result to the screen. This gets executed
     /// when the type of result has the `Extended` level.
-    fn print_result(&self, result: &CheckResult<impl PassResult, impl FailResult>) {
+    fn print_result(&self, w: &mut dyn Write, result: &CheckResult<impl PassResult, impl FailResult>) -> io::Result<()> {
         match result {
             CheckResult::Passed(pass) => {
-                println!("   {} {}", self.colours.tick_sub.paint("✔"), pass);
+                writeln!(w, "   {} {}", self.colours.tick_sub.paint("✔"), pass)?;
             }
 
             CheckResult::Failed(fail) => {
-                println!("   {} {}", self.colours.cross_sub.paint("✘"), fail);
+                writeln!(w, "   {} {}", self.colours.cross_sub.paint("✘"), fail)?;
             }
 
             CheckResult::CommandError(err) => {
-                println!("   {} {}", self.colours.question_sub.paint("?"), err);
+                writeln!(w, "   {} {}", self.colours.question_sub.paint("?"), err)?;
             }
         }
+
+        Ok(())
+    }
+
+    /// Prints one of the commands a check ran, along with how long it took.
+    fn print_command(&self, w: &mut dyn Write, command: &RanCommand) -> io::Result<()> {
+        let timing = self.colours.timing.paint(format!("({})", format_runtime(command.runtime)));
+        writeln!(w, "   {} {} {}", self.colours.output_heading.paint("$"), command.invocation, timing)?;
+        Ok(())
     }
 
-    fn print_output(&self, result: &CheckResult<impl PassResult, impl FailResult>) {
+    fn print_output(&self, w: &mut dyn Write, result: &CheckResult<impl PassResult, impl FailResult>) -> io::Result<()> {
         match result {
             CheckResult::Passed(pass) => {
                 if let Some((title, string)) = pass.command_output() {
-                    println!("     {}", self.colours.output_heading.paint(title));
+                    writeln!(w, "     {}", self.colours.output_heading.paint(title))?;
 
                     for line in string.lines() {
-                        println!("     {}", line.escape_default());
+                        writeln!(w, "     {}", line.escape_default())?;
                     }
                 }
             }
 
             CheckResult::Failed(fail) => {
                 if let Some((title, string)) = fail.command_output() {
-                    println!("     {}", self.colours.output_heading.paint(title));
+                    writeln!(w, "     {}", self.colours.output_heading.paint(title))?;
 
                     for line in string.lines() {
-                        println!("     {}", line.escape_default());
+                        writeln!(w, "     {}", line.escape_default())?;
                     }
                 }
                 else if let Some((title, expected, got)) = fail.diff_output() {
                     use diff::Result;
 
-                    println!("     {}", self.colours.output_heading.paint(title));
+                    writeln!(w, "     {}", self.colours.output_heading.paint(title))?;
                     for line in diff::lines(got, expected) {
                         match line {
-                            Result::Left(left)   => println!("    +{}", self.colours.diff_addition.paint(&left.escape_default().collect::<String>())),
-                            Result::Right(right) => println!("    -{}", self.colours.diff_removal.paint(&right.escape_default().collect::<String>())),
-                            Result::Both(a, _)   => println!("     {}", a.escape_default()),
+                            Result::Left(left)   => writeln!(w, "    +{}", self.colours.diff_addition.paint(&left.escape_default().collect::<String>()))?,
+                            Result::Right(right) => writeln!(w, "    -{}", self.colours.diff_removal.paint(&right.escape_default().collect::<String>()))?,
+                            Result::Both(a, _)   => writeln!(w, "     {}", a.escape_default())?,
                         }
                     }
                 }
             }
 
-            CheckResult::CommandError(_err) => {
-                // No diff for command errors
+            CheckResult::CommandError(err) => {
+                // No diff for command errors, but a command that timed out
+                // may have printed something useful before it got killed
+                if let Some(lines) = err.partial_output().filter(|lines| ! lines.is_empty()) {
+                    writeln!(w, "     {}", self.colours.output_heading.paint("Output before timing out:"))?;
+
+                    for line in lines {
+                        writeln!(w, "     {}", line.escape_default())?;
+                    }
+                }
             }
         }
+
+        Ok(())
+    }
+}
+
+
+/// The output of checks that gets held back while they are being run, so
+/// it can be printed in groups once they have all finished.
+#[derive(Debug, Default)]
+pub struct HeldChecks {
+
+    /// The file whose checks are being run, if they came from a file.
+    file: Option<String>,
+
+    /// What has been printed for the check being run so far.
+    pub buffer: Vec<u8>,
+
+    /// The checks that have finished, in the order they were run.
+    checks: Vec<HeldCheck>,
+
+    /// The totals of every file of checks, if any have been printed.
+    totals: Option<Stats>,
+}
+
+/// A check that has finished, along with everything that got printed for
+/// it, and what it gets grouped and sorted by.
+#[derive(Debug)]
+struct HeldCheck {
+    lines: Vec<u8>,
+    file: Option<String>,
+    check_type: &'static str,
+    tags: Vec<String>,
+    description: String,
+    runtime: Duration,
+    status: CheckStatus,
+    stats: Stats,
+}
+
+impl HeldChecks {
+
+    /// Notes that the checks being run from now on are from a new file.
+    pub fn start_file(&mut self, input_source: &InputSource) {
+        self.file = Some(input_source.to_string());
+    }
+
+    /// Holds on to everything printed since the last check finished, as
+    /// the output of the check that just did.
+    pub fn finish_check(&mut self, check_output: &CheckOutput) {
+        let mut stats = Stats::default();
+        stats.count_check(check_output);
+
+        self.checks.push(HeldCheck {
+            lines:       mem::take(&mut self.buffer),
+            file:        self.file.clone(),
+            check_type:  check_output.check_type,
+            tags:        check_output.tags.clone(),
+            description: check_output.name.clone().unwrap_or_else(|| check_output.message.clone()),
+            runtime:     check_output.runtime,
+            status:      check_output.status(),
+            stats,
+        });
+    }
+
+    /// Adds the totals of a file of checks to the overall totals.
+    pub fn add_totals(&mut self, stats: Stats) {
+        *self.totals.get_or_insert_with(Stats::default) += stats;
+    }
+}
+
+/// Where a check goes when sorting by status: failures first, then
+/// everything else that needs looking at, with passes last.
+fn status_rank(status: CheckStatus) -> u8 {
+    match status {
+        CheckStatus::Failed           => 0,
+        CheckStatus::Errored          => 1,
+        CheckStatus::Warning          => 2,
+        CheckStatus::UnexpectedPass   => 3,
+        CheckStatus::Blocked          => 4,
+        CheckStatus::Skipped          => 5,
+        CheckStatus::ExpectedFailure  => 6,
+        CheckStatus::Passed           => 7,
+    }
+}
+
+
+/// Formats how long a check took, in milliseconds if it was under a
+/// second, and in seconds otherwise.
+pub fn format_runtime(runtime: Duration) -> String {
+    if runtime < Duration::from_secs(1) {
+        format!("{}ms", runtime.as_millis())
+    }
+    else {
+        format!("{:.2}s", runtime.as_secs_f64())
     }
 }
 
@@ -240,6 +853,27 @@ pub struct Colours {
 
     pub zero: Style,
 
+    /// The style used for text in ‘single smart quotes’ in check names
+    pub quoted: Style,
+
+    /// The style used for the note that a check took longer than its budget
+    pub over_budget: Style,
+
+    /// The style used for checks annotated with a known issue
+    pub known_issue: Style,
+
+    /// The style used for the note that a check had to be retried
+    pub retried: Style,
+
+    /// The style used for checks that were skipped rather than run
+    pub skipped: Style,
+
+    /// The style used for checks whose failures only count as warnings
+    pub warning: Style,
+
+    /// The style used for how long each check took
+    pub timing: Style,
+
     pub output_heading: Style,
     pub diff_addition: Style,
     pub diff_removal: Style,
@@ -260,6 +894,13 @@ impl Colours {
             file_heading:    Fixed(248).underline(),
             error:           Red.bold(),
             zero:            Yellow.bold(),
+            quoted:          Style::new().bold(),
+            over_budget:     Yellow.normal(),
+            known_issue:     Yellow.normal(),
+            retried:         Yellow.normal(),
+            skipped:         Fixed(244).normal(),
+            warning:         Yellow.bold(),
+            timing:          Fixed(244).normal(),
             output_heading:  Fixed(187).underline(),
             diff_addition:   Green.normal(),
             diff_removal:    Red.normal(),
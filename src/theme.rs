@@ -0,0 +1,168 @@
+//! Themes, which change the colours of the terminal output.
+//!
+//! A theme gets read from the `SPECSHEET_COLOURS` environment variable (or
+//! `SPECSHEET_COLORS`), which works like `LS_COLORS`: it’s a list of
+//! `key=codes` entries separated by colons, where the key is the name of a
+//! style and the codes are ANSI style codes separated by semicolons, such
+//! as `cross=31;1:quoted=0`. The `reset` entry, with no codes, clears
+//! every style before the entries after it get applied.
+//!
+//! Entries that can’t be understood get logged and ignored, rather than
+//! stopping specsheet from running.
+
+use std::env;
+
+use ansi_term::{Colour, Style};
+use log::*;
+
+use crate::terminal_ui::Colours;
+
+
+/// Changes the styles in a palette to the ones in the theme given in the
+/// environment, if there is one.
+pub fn apply_from_environment(colours: &mut Colours) {
+    let theme = env::var("SPECSHEET_COLOURS").or_else(|_| env::var("SPECSHEET_COLORS"));
+
+    if let Ok(theme) = theme {
+        apply(colours, &theme);
+    }
+}
+
+/// Changes the styles in a palette to the ones in the given theme.
+pub fn apply(colours: &mut Colours, theme: &str) {
+    for entry in theme.split(':').filter(|entry| ! entry.is_empty()) {
+        if entry == "reset" {
+            *colours = Colours::plain();
+        }
+        else if let Some((key, codes)) = entry.split_once('=') {
+            match (style_mut(colours, key), parse_style(codes)) {
+                (Some(slot), Some(style))  => *slot = style,
+                (None, _)                  => warn!("Unknown style {:?} in theme", key),
+                (_, None)                  => warn!("Invalid style codes {:?} in theme entry {:?}", codes, entry),
+            }
+        }
+        else {
+            warn!("Invalid theme entry {:?} (expected KEY=CODES)", entry);
+        }
+    }
+}
+
+/// Finds the style in a palette with the given name.
+fn style_mut<'c>(colours: &'c mut Colours, key: &str) -> Option<&'c mut Style> {
+    Some(match key {
+        "tick"            => &mut colours.tick,
+        "tick_sub"        => &mut colours.tick_sub,
+        "cross"           => &mut colours.cross,
+        "cross_sub"       => &mut colours.cross_sub,
+        "question"        => &mut colours.question,
+        "question_sub"    => &mut colours.question_sub,
+        "file_heading"    => &mut colours.file_heading,
+        "error"           => &mut colours.error,
+        "zero"            => &mut colours.zero,
+        "quoted"          => &mut colours.quoted,
+        "over_budget"     => &mut colours.over_budget,
+        "known_issue"     => &mut colours.known_issue,
+        "retried"         => &mut colours.retried,
+        "skipped"         => &mut colours.skipped,
+        "warning"         => &mut colours.warning,
+        "timing"          => &mut colours.timing,
+        "output_heading"  => &mut colours.output_heading,
+        "diff_addition"   => &mut colours.diff_addition,
+        "diff_removal"    => &mut colours.diff_removal,
+        _                 => return None,
+    })
+}
+
+/// Parses a list of ANSI style codes, such as `1;38;5;208`, into a style.
+/// An empty list, or `0`, is no style at all.
+fn parse_style(codes: &str) -> Option<Style> {
+    let mut style = Style::new();
+    let mut codes = codes.split(';').filter(|code| ! code.is_empty()).map(str::parse::<u8>);
+
+    while let Some(code) = codes.next() {
+        match code.ok()? {
+            0                => style = Style::new(),
+            1                => style = style.bold(),
+            2                => style = style.dimmed(),
+            3                => style = style.italic(),
+            4                => style = style.underline(),
+            5                => style = style.blink(),
+            7                => style = style.reverse(),
+            8                => style = style.hidden(),
+            9                => style = style.strikethrough(),
+            n @ 30 ..= 37    => style = style.fg(basic_colour(n - 30)),
+            n @ 40 ..= 47    => style = style.on(basic_colour(n - 40)),
+            n @ 90 ..= 97    => style = style.fg(Colour::Fixed(n - 90 + 8)),
+            n @ 100 ..= 107  => style = style.on(Colour::Fixed(n - 100 + 8)),
+            38               => style = style.fg(extended_colour(&mut codes)?),
+            48               => style = style.on(extended_colour(&mut codes)?),
+            _                => return None,
+        }
+    }
+
+    Some(style)
+}
+
+/// The colour with one of the first eight ANSI colour numbers.
+fn basic_colour(number: u8) -> Colour {
+    match number {
+        0 => Colour::Black,
+        1 => Colour::Red,
+        2 => Colour::Green,
+        3 => Colour::Yellow,
+        4 => Colour::Blue,
+        5 => Colour::Purple,
+        6 => Colour::Cyan,
+        _ => Colour::White,
+    }
+}
+
+/// Reads the rest of an extended colour code, which is `5;N` for one of
+/// the 256 colours, or `2;R;G;B` for a true colour.
+fn extended_colour(codes: &mut impl Iterator<Item = Result<u8, std::num::ParseIntError>>) -> Option<Colour> {
+    match codes.next()?.ok()? {
+        5 => Some(Colour::Fixed(codes.next()?.ok()?)),
+        2 => Some(Colour::RGB(codes.next()?.ok()?, codes.next()?.ok()?, codes.next()?.ok()?)),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn styles() {
+        assert_eq!(parse_style("31;1"), Some(Colour::Red.bold()));
+        assert_eq!(parse_style("4;38;5;208"), Some(Colour::Fixed(208).underline()));
+        assert_eq!(parse_style("92;48;2;0;0;64"), Some(Colour::Fixed(10).on(Colour::RGB(0, 0, 64))));
+        assert_eq!(parse_style("0"), Some(Style::new()));
+        assert_eq!(parse_style(""), Some(Style::new()));
+    }
+
+    #[test]
+    fn bad_styles() {
+        assert_eq!(parse_style("red"), None);
+        assert_eq!(parse_style("38;5"), None);
+        assert_eq!(parse_style("60"), None);
+    }
+
+    #[test]
+    fn theme() {
+        let mut colours = Colours::pretty();
+        apply(&mut colours, "cross=35:quoted=0:nonsense=1:tick=green");
+
+        assert_eq!(colours.cross, Colour::Purple.normal());
+        assert_eq!(colours.quoted, Style::new());
+        assert_eq!(colours.tick, Colours::pretty().tick);
+    }
+
+    #[test]
+    fn reset() {
+        let mut colours = Colours::pretty();
+        apply(&mut colours, "reset:error=1");
+
+        assert_eq!(colours, Colours { error: Style::new().bold(), ..Colours::plain() });
+    }
+}
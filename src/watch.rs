@@ -0,0 +1,113 @@
+//! Watch mode, which runs the checks in each input file again whenever it
+//! changes, for while the checks are being written.
+//!
+//! Along with the input files themselves, the files they include and the
+//! files their checks compare output against, such as with
+//! `contents = { file = ... }`, get watched. Files get watched by polling
+//! their modification times, which is simple and works everywhere.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::*;
+
+use spec_checks::load::{CheckDocument, TomlValue};
+
+
+/// The files that one input file’s checks depend on.
+#[derive(Debug, Default)]
+pub struct WatchedFiles {
+
+    /// Each file, along with when it was last modified, or `None` if it
+    /// does not exist.
+    files: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl WatchedFiles {
+
+    /// Starts watching the given files as they are now.
+    pub fn new(paths: impl IntoIterator<Item=PathBuf>) -> Self {
+        let files = paths.into_iter().map(|path| {
+            let modified = modified_time(&path);
+            (path, modified)
+        }).collect();
+
+        Self { files }
+    }
+
+    /// Returns whether any of the files has been changed, created, or
+    /// deleted since the last time this was called.
+    pub fn have_changed(&mut self) -> bool {
+        let mut changed = false;
+
+        for (path, modified) in &mut self.files {
+            let now = modified_time(path);
+            if now != *modified {
+                debug!("Watched file {:?} changed", path);
+                *modified = now;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+
+/// Returns the paths of the files that the checks in a document compare
+/// against, which are the `file` fields of the tables in their parameters.
+/// Relative paths get resolved against the given directory.
+pub fn referenced_files(document: &CheckDocument, directory: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for entry in document.checks.values().flatten() {
+        if let TomlValue::Table(table) = &entry.inner {
+            for value in table.values() {
+                find_file_fields(value, &mut paths);
+            }
+        }
+    }
+
+    paths.into_iter().map(|path| directory.join(path)).collect()
+}
+
+fn find_file_fields(value: &TomlValue, paths: &mut Vec<PathBuf>) {
+    match value {
+        TomlValue::Table(table) => {
+            if let Some(TomlValue::String(path)) = table.get("file") {
+                paths.push(PathBuf::from(path));
+            }
+
+            for value in table.values() {
+                find_file_fields(value, paths);
+            }
+        }
+        TomlValue::Array(array) => {
+            for value in array {
+                find_file_fields(value, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+
+/// Clears the terminal, so each run’s results start at the top of it. Output
+/// that isn’t going to a terminal gets left alone.
+pub fn clear_screen() {
+    if ! atty::is(atty::Stream::Stdout) {
+        return;
+    }
+
+    print!("\x1B[2J\x1B[H");
+    if let Err(e) = io::stdout().flush() {
+        warn!("Error clearing the screen: {}", e);
+    }
+}